@@ -127,7 +127,22 @@ def make_parser():
     parser.add_argument(
         '--unix-file-copy-paste',
         action='store_true',
-        help='Build with unix file copy paste feature'
+        help='Build with unix file copy paste feature, enabled by default for the flutter builds on linux and macos'
+    )
+    parser.add_argument(
+        '--no-unix-file-copy-paste',
+        action='store_true',
+        help='Build the flutter version on linux and macos without unix file copy paste feature'
+    )
+    parser.add_argument(
+        '--unity-bindings',
+        action='store_true',
+        help='Generate the C header and C# bindings of the Unity FFI into target/unity'
+    )
+    parser.add_argument(
+        '--unity-static',
+        action='store_true',
+        help='Build the Unity FFI as a static library into target/unity, without the Sciter UI'
     )
     parser.add_argument(
         '--skip-cargo',
@@ -279,8 +294,12 @@ def get_features(args):
         features.append('vram')
     if args.flutter:
         features.append('flutter')
-    if args.unix_file_copy_paste:
+    if args.unix_file_copy_paste or (args.flutter and not windows and not args.no_unix_file_copy_paste):
         features.append('unix-file-copy-paste')
+    if args.unity_bindings:
+        features.append('unity-bindings')
+    if args.unity_static:
+        features.append('unity-static')
     if osx:
         if args.screencapturekit:
             features.append('screencapturekit')
@@ -462,6 +481,15 @@ def build_flutter_windows(version, features, skip_portable_pack):
         f'output location: {os.path.abspath(os.curdir)}/rustdesk-{version}-install.exe')
 
 
+def build_unity_static(features):
+    system2(
+        f'cargo rustc --lib --crate-type staticlib --release --features {features}')
+    lib = 'librustdesk.lib' if windows else 'liblibrustdesk.a'
+    os.makedirs('target/unity', exist_ok=True)
+    shutil.copy2(f'target/release/{lib}', 'target/unity')
+    print(f'output location: {os.path.abspath("target/unity")}/{lib}')
+
+
 def main():
     global skip_cargo
     parser = make_parser()
@@ -474,6 +502,9 @@ def main():
     version = get_version()
     features = ','.join(get_features(args))
     flutter = args.flutter
+    if args.unity_static:
+        build_unity_static(features)
+        return
     if not flutter:
         system2('python3 res/inline-sciter.py')
     print(args.skip_cargo)
@@ -20,6 +20,8 @@ fn build_mac() {
     }
     b.file(file).compile("macos");
     println!("cargo:rerun-if-changed={}", file);
+    // The link args of scrap are not passed on, for the ScreenCaptureKit capture on macOS 13+.
+    println!("cargo:rustc-link-arg=-Wl,-weak_framework,ScreenCaptureKit");
 }
 
 #[cfg(all(windows, feature = "inline"))]
@@ -77,9 +79,303 @@ fn install_android_deps() {
     println!("cargo:rustc-link-lib=OpenSLES");
 }
 
+/// Generates `rustdesk_unity.h` and `RustDeskNative.cs` from the `#[no_mangle]` surface of the Unity bridge,
+/// so the managed bindings are always in sync with the native ABI.
+#[cfg(feature = "unity-bindings")]
+mod unity_bindings {
+    use std::{
+        fmt::Write,
+        path::{Path, PathBuf},
+    };
+
+    const HEADER_NAME: &str = "rustdesk_unity.h";
+    const CSHARP_NAME: &str = "RustDeskNative.cs";
+    const CSHARP_NAMESPACE: &str = "RustDesk.Unity";
+
+    fn sources(root: &Path) -> Vec<PathBuf> {
+        let mut files = vec![
+            root.join("src/unity.rs"),
+            root.join("src/unreal.rs"),
+            root.join("src/plugin/mod.rs"),
+            root.join("src/plugin/unity.rs"),
+        ];
+        if let Ok(rd) = std::fs::read_dir(root.join("src/unity")) {
+            let mut sub: Vec<PathBuf> = rd
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| p.extension().map(|e| e == "rs").unwrap_or(false))
+                .collect();
+            sub.sort();
+            files.extend(sub);
+        }
+        files
+    }
+
+    pub fn generate() {
+        let root = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap());
+        let out_dir = std::env::var("RUSTDESK_UNITY_BINDINGS_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| root.join("target").join("unity"));
+        std::fs::create_dir_all(&out_dir).unwrap();
+
+        let files = sources(&root);
+        let mut builder = cbindgen::Builder::new()
+            .with_language(cbindgen::Language::C)
+            .with_include_guard("RUSTDESK_UNITY_H")
+            .with_documentation(false)
+            .with_autogen_warning(
+                "/* Generated by build.rs with feature unity-bindings, do not edit. */",
+            );
+        for f in files.iter() {
+            println!("cargo:rerun-if-changed={}", f.display());
+            builder = builder.with_src(f);
+        }
+        let header_path = out_dir.join(HEADER_NAME);
+        match builder.generate() {
+            Ok(bindings) => {
+                bindings.write_to_file(&header_path);
+            }
+            Err(e) => {
+                println!("cargo:warning=Failed to generate Unity bindings: {}", e);
+                return;
+            }
+        }
+        let header = std::fs::read_to_string(&header_path).unwrap();
+        std::fs::write(out_dir.join(CSHARP_NAME), to_csharp(&header)).unwrap();
+    }
+
+    // Split the header into top-level statements, ignoring preprocessor lines except `#define`.
+    fn statements(header: &str) -> (Vec<(String, String)>, Vec<String>) {
+        let mut defines = vec![];
+        let mut body = String::new();
+        let mut in_comment = false;
+        for line in header.lines() {
+            let line = line.trim();
+            if in_comment {
+                in_comment = !line.contains("*/");
+                continue;
+            }
+            if line.starts_with("/*") {
+                in_comment = !line.contains("*/");
+                continue;
+            }
+            if let Some(define) = line.strip_prefix("#define ") {
+                let mut it = define.splitn(2, ' ');
+                if let (Some(name), Some(value)) = (it.next(), it.next()) {
+                    defines.push((name.to_owned(), value.trim().to_owned()));
+                }
+                continue;
+            }
+            if line.starts_with('#') || line.starts_with("//") {
+                continue;
+            }
+            body.push_str(line);
+            body.push(' ');
+        }
+        let mut stmts = vec![];
+        let mut depth = 0;
+        let mut cur = String::new();
+        for c in body.chars() {
+            match c {
+                '{' => depth += 1,
+                '}' => depth -= 1,
+                ';' if depth == 0 => {
+                    let stmt = cur.split_whitespace().collect::<Vec<_>>().join(" ");
+                    if !stmt.is_empty() {
+                        stmts.push(stmt);
+                    }
+                    cur.clear();
+                    continue;
+                }
+                _ => {}
+            }
+            cur.push(c);
+        }
+        (defines, stmts)
+    }
+
+    // "const char *name" -> ("const char *", "name")
+    fn split_decl(decl: &str) -> (String, String) {
+        let decl = decl.trim();
+        let pos = decl
+            .rfind(|c: char| c == ' ' || c == '*')
+            .map(|p| p + 1)
+            .unwrap_or(0);
+        (decl[..pos].trim().to_owned(), decl[pos..].trim().to_owned())
+    }
+
+    fn cs_type(c_type: &str, is_return: bool) -> String {
+        let is_ptr = c_type.contains('*');
+        let base = c_type
+            .replace("const", "")
+            .replace('*', "")
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        if is_ptr {
+            if base == "char" && !is_return && c_type.starts_with("const") {
+                return "[MarshalAs(UnmanagedType.LPUTF8Str)] string".to_owned();
+            }
+            return "IntPtr".to_owned();
+        }
+        match base.as_str() {
+            "void" => "void",
+            "bool" => {
+                if is_return {
+                    "bool"
+                } else {
+                    "[MarshalAs(UnmanagedType.I1)] bool"
+                }
+            }
+            "int" | "int32_t" => "int",
+            "uint32_t" => "uint",
+            "int8_t" => "sbyte",
+            "uint8_t" => "byte",
+            "int16_t" => "short",
+            "uint16_t" => "ushort",
+            "int64_t" => "long",
+            "uint64_t" => "ulong",
+            "uintptr_t" | "size_t" => "UIntPtr",
+            "intptr_t" => "IntPtr",
+            "float" => "float",
+            "double" => "double",
+            other => other,
+        }
+        .to_owned()
+    }
+
+    fn cs_params(params: &str) -> String {
+        let params = params.trim();
+        if params.is_empty() || params == "void" {
+            return String::new();
+        }
+        params
+            .split(',')
+            .map(|p| {
+                let (t, name) = split_decl(p);
+                format!("{} {}", cs_type(&t, false), name)
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn return_attr(c_type: &str) -> &'static str {
+        if c_type.trim() == "bool" {
+            "[return: MarshalAs(UnmanagedType.I1)]\n        "
+        } else {
+            ""
+        }
+    }
+
+    fn to_csharp(header: &str) -> String {
+        let (defines, stmts) = statements(header);
+        let mut types = String::new();
+        let mut funcs = String::new();
+        for stmt in stmts.iter() {
+            if let Some(rest) = stmt.strip_prefix("typedef ") {
+                if let (Some(l), Some(r)) = (rest.find("(*"), rest.find(")(")) {
+                    // typedef RET (*Name)(ARGS)
+                    let ret = rest[..l].trim();
+                    let name = &rest[l + 2..r];
+                    let params = &rest[r + 2..rest.len() - 1];
+                    writeln!(
+                        types,
+                        "    [UnmanagedFunctionPointer(CallingConvention.Cdecl)]\n    {}public delegate {} {}({});\n",
+                        return_attr(ret),
+                        cs_type(ret, true),
+                        name,
+                        cs_params(params)
+                    )
+                    .ok();
+                } else if rest.starts_with("struct ") {
+                    // typedef struct Name { FIELDS } Name
+                    let (Some(l), Some(r)) = (rest.find('{'), rest.rfind('}')) else {
+                        continue;
+                    };
+                    let name = rest[r + 1..].trim();
+                    writeln!(
+                        types,
+                        "    [StructLayout(LayoutKind.Sequential)]\n    public struct {}\n    {{",
+                        name
+                    )
+                    .ok();
+                    for field in rest[l + 1..r].split(';').filter(|f| !f.trim().is_empty()) {
+                        let (t, field) = split_decl(field);
+                        writeln!(types, "        public {} {};", cs_type(&t, true), field).ok();
+                    }
+                    writeln!(types, "    }}\n").ok();
+                }
+            } else if let (Some(l), true) = (stmt.find('('), stmt.ends_with(')')) {
+                let (ret, name) = split_decl(&stmt[..l]);
+                if !name.starts_with("rustdesk_unity_") && !name.starts_with("rustdesk_unreal_") {
+                    continue;
+                }
+                writeln!(
+                    funcs,
+                    "        [DllImport(Library, CallingConvention = CallingConvention.Cdecl)]\n        {}public static extern {} {}({});\n",
+                    return_attr(&ret),
+                    cs_type(&ret, true),
+                    name,
+                    cs_params(&stmt[l + 1..stmt.len() - 1])
+                )
+                .ok();
+            }
+        }
+        let mut consts = String::new();
+        for (name, value) in defines.iter() {
+            if name == "RUSTDESK_UNITY_H" {
+                continue;
+            }
+            let cs_value = value.trim_start_matches('(').trim_end_matches(')');
+            let t = if cs_value.starts_with('"') {
+                "string"
+            } else if cs_value.trim_start_matches('-').parse::<i32>().is_ok() {
+                "int"
+            } else if cs_value.parse::<u32>().is_ok() {
+                "uint"
+            } else {
+                continue;
+            };
+            writeln!(
+                consts,
+                "        public const {} {} = {};",
+                t, name, cs_value
+            )
+            .ok();
+        }
+        format!(
+            r#"// <auto-generated>
+// Generated by build.rs with feature unity-bindings, do not edit.
+// </auto-generated>
+using System;
+using System.Runtime.InteropServices;
+
+namespace {ns}
+{{
+{types}    public static class Native
+    {{
+#if (UNITY_IOS || UNITY_WEBGL) && !UNITY_EDITOR
+        public const string Library = "__Internal";
+#else
+        public const string Library = "librustdesk";
+#endif
+
+{consts}
+{funcs}    }}
+}}
+"#,
+            ns = CSHARP_NAMESPACE,
+            types = types,
+            consts = consts,
+            funcs = funcs
+        )
+    }
+}
+
 fn main() {
     hbb_common::gen_version();
     install_android_deps();
+    #[cfg(feature = "unity-bindings")]
+    unity_bindings::generate();
     #[cfg(all(windows, feature = "inline"))]
     build_manifest();
     #[cfg(windows)]
@@ -0,0 +1,4 @@
+# Called from rust by name.
+-keep class ffi.FFI { *; }
+-keep class com.rustdesk.unity.HostService { *; }
+-keep class com.rustdesk.unity.RustDeskUnity { *; }
@@ -0,0 +1,419 @@
+package com.rustdesk.unity
+
+import android.Manifest
+import android.annotation.SuppressLint
+import android.app.Activity
+import android.app.Notification
+import android.app.NotificationChannel
+import android.app.NotificationManager
+import android.app.Service
+import android.content.ComponentName
+import android.content.Context
+import android.content.Intent
+import android.content.pm.PackageManager
+import android.content.pm.ServiceInfo
+import android.graphics.PixelFormat
+import android.hardware.display.DisplayManager
+import android.hardware.display.VirtualDisplay
+import android.media.AudioAttributes
+import android.media.AudioFormat
+import android.media.AudioPlaybackCaptureConfiguration
+import android.media.AudioRecord
+import android.media.ImageReader
+import android.media.projection.MediaProjection
+import android.media.projection.MediaProjectionManager
+import android.os.Build
+import android.os.Handler
+import android.os.HandlerThread
+import android.os.IBinder
+import android.os.PowerManager
+import android.os.Process
+import android.service.quicksettings.TileService
+import android.util.DisplayMetrics
+import android.util.Log
+import android.view.WindowManager
+import androidx.annotation.Keep
+import androidx.annotation.RequiresApi
+import androidx.core.app.ActivityCompat
+import androidx.core.app.NotificationCompat
+import ffi.FFI
+import org.json.JSONException
+import org.json.JSONObject
+import java.nio.ByteBuffer
+import java.nio.ByteOrder
+import kotlin.concurrent.thread
+
+/**
+ * Foreground service hosting the screen of this device, the Unity counterpart of `MainService` of the app.
+ *
+ * Rust calls the `rust*` methods by name on the context passed to `FFI.init`, keep their signatures.
+ *
+ * It stays in the foreground while available, even without the capture permission, and holds a partial
+ * wake lock so the connection to the rendezvous server survives Doze. It's restarted by the system with
+ * the saved config directory if killed, until [RustDeskUnity.stopHost].
+ */
+class HostService : Service() {
+
+    companion object {
+        const val ACT_INIT_MEDIA_PROJECTION = "INIT_MEDIA_PROJECTION"
+        const val EXT_MEDIA_PROJECTION_RES_INTENT = "MEDIA_PROJECTION_RES_INTENT"
+        const val EXT_CONFIG_DIR = "CONFIG_DIR"
+
+        private const val NOTIFY_ID = 11
+        private const val CHANNEL_ID = "RustDeskUnity"
+        private const val PREFS = "rustdesk_unity_host"
+        private const val PREF_AVAILABLE = "available"
+        private const val PREF_CONFIG_DIR = "config_dir"
+        private const val AUDIO_SAMPLE_RATE = 48000
+
+        // mouse masks, see InputService of the app
+        internal const val LEFT_DOWN = 9
+
+        @Volatile
+        private var _isReady = false
+        @Volatile
+        private var _isStart = false
+        @Volatile
+        private var _isRunning = false
+        val isReady: Boolean
+            get() = _isReady
+        val isStart: Boolean
+            get() = _isStart
+        val isRunning: Boolean
+            get() = _isRunning
+
+        // width, height, scale, read by rust with `rustGetByName("screen_size")`
+        internal var screenWidth = 0
+        internal var screenHeight = 0
+        internal var screenScale = 1
+
+        /** Whether the host was left available, for the restarts and the quick settings tile. */
+        internal fun isAvailable(context: Context): Boolean =
+            context.getSharedPreferences(PREFS, Context.MODE_PRIVATE).getBoolean(PREF_AVAILABLE, false)
+
+        internal fun savedConfigDir(context: Context): String =
+            context.getSharedPreferences(PREFS, Context.MODE_PRIVATE).getString(PREF_CONFIG_DIR, "") ?: ""
+
+        internal fun saveAvailable(context: Context, available: Boolean, configDir: String? = null) {
+            context.getSharedPreferences(PREFS, Context.MODE_PRIVATE).edit().apply {
+                putBoolean(PREF_AVAILABLE, available)
+                configDir?.let { putString(PREF_CONFIG_DIR, it) }
+            }.apply()
+        }
+
+        /** Report the state to the bridge as the `host_state` event, and refresh the tile. */
+        internal fun reportState(context: Context) {
+            val state = JSONObject().apply {
+                put("available", isRunning)
+                put("capture", isReady)
+                put("capturing", isStart)
+                put("input", RustDeskUnity.isInputEnabled(context))
+                put("ignoring_battery_optimizations", RustDeskUnity.isIgnoringBatteryOptimizations(context))
+            }
+            try {
+                FFI.onHostStateChanged(state.toString())
+            } catch (e: UnsatisfiedLinkError) {
+                Log.e("LOG_UNITY_SERVICE", "report host state failed: $e")
+            }
+            if (Build.VERSION.SDK_INT >= Build.VERSION_CODES.N) {
+                TileService.requestListeningState(context, ComponentName(context, HostTileService::class.java))
+            }
+        }
+    }
+
+    private val logTag = "LOG_UNITY_SERVICE"
+    private var serverStarted = false
+    private var handler: Handler? = null
+
+    private var mediaProjection: MediaProjection? = null
+    private var imageReader: ImageReader? = null
+    private var virtualDisplay: VirtualDisplay? = null
+    private var audioRecord: AudioRecord? = null
+    @Volatile
+    private var audioRunning = false
+
+    private val powerManager: PowerManager by lazy { getSystemService(Context.POWER_SERVICE) as PowerManager }
+    @Suppress("DEPRECATION")
+    private val wakeLock: PowerManager.WakeLock by lazy {
+        powerManager.newWakeLock(
+            PowerManager.ACQUIRE_CAUSES_WAKEUP or PowerManager.SCREEN_BRIGHT_WAKE_LOCK,
+            "rustdesk:unity_wakelock"
+        )
+    }
+    // Keep the CPU for the network while the screen is off, the foreground service alone is not enough in Doze.
+    private val cpuWakeLock: PowerManager.WakeLock by lazy {
+        powerManager.newWakeLock(PowerManager.PARTIAL_WAKE_LOCK, "rustdesk:unity_host").apply {
+            setReferenceCounted(false)
+        }
+    }
+
+    @Keep
+    @RequiresApi(Build.VERSION_CODES.N)
+    fun rustPointerInput(kind: Int, mask: Int, x: Int, y: Int) {
+        if (!powerManager.isInteractive && (kind == 0 || mask == LEFT_DOWN)) {
+            if (wakeLock.isHeld) {
+                wakeLock.release()
+            }
+            wakeLock.acquire(5000)
+            return
+        }
+        when (kind) {
+            0 -> InputAccessibilityService.ctx?.onTouchInput(mask, x, y)
+            1 -> InputAccessibilityService.ctx?.onMouseInput(mask, x, y)
+        }
+    }
+
+    @Keep
+    fun rustKeyEventInput(input: ByteArray) {
+        // Key events are protobuf encoded, the bridge doesn't embed the protobuf runtime.
+    }
+
+    @Keep
+    fun rustGetByName(name: String): String {
+        return when (name) {
+            "screen_size" -> JSONObject().apply {
+                put("width", screenWidth)
+                put("height", screenHeight)
+                put("scale", screenScale)
+            }.toString()
+            "is_start" -> isStart.toString()
+            else -> ""
+        }
+    }
+
+    @Keep
+    fun rustSetByName(name: String, arg1: String, arg2: String) {
+        when (name) {
+            "add_connection" -> {
+                try {
+                    val json = JSONObject(arg1)
+                    val authorized = json["authorized"] as Boolean
+                    val isFileTransfer = json["is_file_transfer"] as Boolean
+                    if (authorized && !isFileTransfer && !isStart) {
+                        handler?.post { startCapture() }
+                    }
+                } catch (e: JSONException) {
+                    e.printStackTrace()
+                }
+            }
+            "stop_capture" -> handler?.post { stopCapture() }
+            else -> {}
+        }
+    }
+
+    @SuppressLint("WakelockTimeout")
+    override fun onCreate() {
+        super.onCreate()
+        FFI.init(this)
+        HandlerThread("UnityHostService", Process.THREAD_PRIORITY_BACKGROUND).apply {
+            start()
+            handler = Handler(looper)
+        }
+        updateScreenInfo()
+        _isRunning = true
+        cpuWakeLock.acquire()
+        reportState(this)
+    }
+
+    override fun onStartCommand(intent: Intent?, flags: Int, startId: Int): Int {
+        super.onStartCommand(intent, flags, startId)
+        startForegroundCompat(intent?.action == ACT_INIT_MEDIA_PROJECTION)
+        if (!serverStarted) {
+            serverStarted = true
+            // The intent is null if restarted by the system.
+            val configDir = intent?.getStringExtra(EXT_CONFIG_DIR) ?: savedConfigDir(this)
+            FFI.startServer(configDir, "")
+        }
+        if (intent?.action == ACT_INIT_MEDIA_PROJECTION) {
+            val mediaProjectionManager =
+                getSystemService(MEDIA_PROJECTION_SERVICE) as MediaProjectionManager
+            @Suppress("DEPRECATION")
+            intent.getParcelableExtra<Intent>(EXT_MEDIA_PROJECTION_RES_INTENT)?.let {
+                stopCapture()
+                mediaProjection = mediaProjectionManager.getMediaProjection(Activity.RESULT_OK, it)
+                _isReady = mediaProjection != null
+                FFI.startService()
+                reportState(this)
+            }
+        }
+        return START_STICKY
+    }
+
+    override fun onBind(intent: Intent): IBinder? = null
+
+    override fun onDestroy() {
+        _isRunning = false
+        stopCapture()
+        mediaProjection?.stop()
+        mediaProjection = null
+        _isReady = false
+        if (cpuWakeLock.isHeld) {
+            cpuWakeLock.release()
+        }
+        reportState(this)
+        handler?.looper?.quitSafely()
+        super.onDestroy()
+    }
+
+    private fun updateScreenInfo() {
+        val windowManager = getSystemService(Context.WINDOW_SERVICE) as WindowManager
+        @Suppress("DEPRECATION")
+        if (Build.VERSION.SDK_INT >= Build.VERSION_CODES.R) {
+            val bounds = windowManager.maximumWindowMetrics.bounds
+            screenWidth = bounds.width()
+            screenHeight = bounds.height()
+        } else {
+            val dm = DisplayMetrics()
+            windowManager.defaultDisplay.getRealMetrics(dm)
+            screenWidth = dm.widthPixels
+            screenHeight = dm.heightPixels
+        }
+        FFI.refreshScreen()
+    }
+
+    @SuppressLint("WrongConstant")
+    private fun startCapture() {
+        val projection = mediaProjection
+        if (isStart || projection == null) {
+            return
+        }
+        updateScreenInfo()
+        val reader = ImageReader.newInstance(screenWidth, screenHeight, PixelFormat.RGBA_8888, 4)
+        reader.setOnImageAvailableListener({ r ->
+            try {
+                r.acquireLatestImage().use { image ->
+                    if (image == null || !isStart) return@setOnImageAvailableListener
+                    val buffer = image.planes[0].buffer
+                    buffer.rewind()
+                    FFI.onVideoFrameUpdate(buffer)
+                }
+            } catch (ignored: Exception) {
+            }
+        }, handler)
+        imageReader = reader
+        virtualDisplay = projection.createVirtualDisplay(
+            "RustDeskUnityVD",
+            screenWidth, screenHeight, resources.configuration.densityDpi,
+            DisplayManager.VIRTUAL_DISPLAY_FLAG_PUBLIC,
+            reader.surface, null, null
+        )
+        if (Build.VERSION.SDK_INT >= Build.VERSION_CODES.Q) {
+            startAudio(projection)
+        }
+        _isStart = true
+        FFI.setFrameRawEnable("video", true)
+        updateNotification()
+        reportState(this)
+    }
+
+    @Synchronized
+    private fun stopCapture() {
+        FFI.setFrameRawEnable("video", false)
+        _isStart = false
+        audioRunning = false
+        virtualDisplay?.release()
+        virtualDisplay = null
+        // the surface is released with the reader
+        imageReader?.close()
+        imageReader = null
+        if (_isRunning) {
+            updateNotification()
+            reportState(this)
+        }
+    }
+
+    @RequiresApi(Build.VERSION_CODES.Q)
+    private fun startAudio(projection: MediaProjection) {
+        if (ActivityCompat.checkSelfPermission(this, Manifest.permission.RECORD_AUDIO)
+            != PackageManager.PERMISSION_GRANTED
+        ) {
+            Log.d(logTag, "no RECORD_AUDIO permission, audio is not shared")
+            return
+        }
+        val config = AudioPlaybackCaptureConfiguration.Builder(projection)
+            .addMatchingUsage(AudioAttributes.USAGE_MEDIA)
+            .addMatchingUsage(AudioAttributes.USAGE_GAME)
+            .addMatchingUsage(AudioAttributes.USAGE_UNKNOWN)
+            .build()
+        val format = AudioFormat.Builder()
+            .setEncoding(AudioFormat.ENCODING_PCM_FLOAT)
+            .setSampleRate(AUDIO_SAMPLE_RATE)
+            .setChannelMask(AudioFormat.CHANNEL_IN_STEREO)
+            .build()
+        val record = try {
+            AudioRecord.Builder()
+                .setAudioFormat(format)
+                .setAudioPlaybackCaptureConfig(config)
+                .build()
+        } catch (e: Exception) {
+            Log.e(logTag, "create audio recorder failed: $e")
+            return
+        }
+        // read f32 to byte, length * 4
+        val size = 2 * 4 * AudioRecord.getMinBufferSize(
+            AUDIO_SAMPLE_RATE,
+            AudioFormat.CHANNEL_IN_STEREO,
+            AudioFormat.ENCODING_PCM_FLOAT
+        )
+        if (size <= 0) {
+            record.release()
+            return
+        }
+        audioRecord = record
+        audioRunning = true
+        thread {
+            val buffer = ByteBuffer.allocateDirect(size).order(ByteOrder.nativeOrder())
+            FFI.setFrameRawEnable("audio", true)
+            record.startRecording()
+            while (audioRunning) {
+                buffer.clear()
+                val n = record.read(buffer, size, AudioRecord.READ_BLOCKING)
+                if (n > 0) {
+                    buffer.limit(n)
+                    FFI.onAudioFrameUpdate(buffer)
+                }
+            }
+            record.stop()
+            record.release()
+            audioRecord = null
+            FFI.setFrameRawEnable("audio", false)
+        }
+    }
+
+    private fun buildNotification(): Notification {
+        val notificationManager = getSystemService(NOTIFICATION_SERVICE) as NotificationManager
+        if (Build.VERSION.SDK_INT >= Build.VERSION_CODES.O) {
+            val channel = NotificationChannel(
+                CHANNEL_ID,
+                getString(R.string.unity_notification_channel),
+                NotificationManager.IMPORTANCE_LOW
+            )
+            notificationManager.createNotificationChannel(channel)
+        }
+        val text = if (isStart) R.string.unity_notification_text else R.string.unity_notification_ready
+        return NotificationCompat.Builder(this, CHANNEL_ID)
+            .setSmallIcon(applicationInfo.icon)
+            .setContentTitle(applicationInfo.loadLabel(packageManager))
+            .setContentText(getString(text))
+            .setOngoing(true)
+            .build()
+    }
+
+    private fun updateNotification() {
+        val notificationManager = getSystemService(NOTIFICATION_SERVICE) as NotificationManager
+        notificationManager.notify(NOTIFY_ID, buildNotification())
+    }
+
+    // Since Android 14, a mediaProjection service can only be in foreground with a projection token,
+    // the service is in foreground as specialUse until the permission is granted.
+    private fun startForegroundCompat(withProjection: Boolean) {
+        val notification = buildNotification()
+        if (Build.VERSION.SDK_INT >= Build.VERSION_CODES.Q && (withProjection || mediaProjection != null)) {
+            startForeground(NOTIFY_ID, notification, ServiceInfo.FOREGROUND_SERVICE_TYPE_MEDIA_PROJECTION)
+        } else if (Build.VERSION.SDK_INT >= Build.VERSION_CODES.UPSIDE_DOWN_CAKE) {
+            startForeground(NOTIFY_ID, notification, ServiceInfo.FOREGROUND_SERVICE_TYPE_SPECIAL_USE)
+        } else {
+            startForeground(NOTIFY_ID, notification)
+        }
+    }
+}
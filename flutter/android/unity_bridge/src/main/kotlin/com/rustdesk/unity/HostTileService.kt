@@ -0,0 +1,42 @@
+package com.rustdesk.unity
+
+import android.os.Build
+import android.service.quicksettings.Tile
+import android.service.quicksettings.TileService
+import androidx.annotation.RequiresApi
+
+/** Quick settings tile toggling the availability of the host, with the config directory of the last [RustDeskUnity.startHost]. */
+@RequiresApi(Build.VERSION_CODES.N)
+class HostTileService : TileService() {
+
+    override fun onStartListening() {
+        super.onStartListening()
+        updateTile()
+    }
+
+    override fun onClick() {
+        super.onClick()
+        if (HostService.isRunning) {
+            RustDeskUnity.stopHost(this)
+        } else {
+            RustDeskUnity.startHost(this, HostService.savedConfigDir(this))
+        }
+        updateTile()
+    }
+
+    private fun updateTile() {
+        val tile = qsTile ?: return
+        // The service may be still starting or stopping after a click.
+        tile.state = if (HostService.isAvailable(this)) Tile.STATE_ACTIVE else Tile.STATE_INACTIVE
+        if (Build.VERSION.SDK_INT >= Build.VERSION_CODES.Q) {
+            tile.subtitle = getString(
+                when {
+                    HostService.isStart -> R.string.unity_tile_capturing
+                    HostService.isRunning -> R.string.unity_tile_available
+                    else -> R.string.unity_tile_unavailable
+                }
+            )
+        }
+        tile.updateTile()
+    }
+}
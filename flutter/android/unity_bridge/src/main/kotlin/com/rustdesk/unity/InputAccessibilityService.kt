@@ -0,0 +1,127 @@
+package com.rustdesk.unity
+
+import android.accessibilityservice.AccessibilityService
+import android.accessibilityservice.GestureDescription
+import android.graphics.Path
+import android.os.Build
+import android.view.ViewConfiguration
+import android.view.accessibility.AccessibilityEvent
+import androidx.annotation.RequiresApi
+import kotlin.math.max
+
+/** Injects the remote mouse and touch input as gestures, a reduced `InputService` of the app. */
+class InputAccessibilityService : AccessibilityService() {
+
+    companion object {
+        var ctx: InputAccessibilityService? = null
+
+        private const val LEFT_MOVE = 8
+        private const val LEFT_DOWN = 9
+        private const val LEFT_UP = 10
+        private const val RIGHT_UP = 18
+        private const val BACK_UP = 66
+        private const val WHEEL_BUTTON_UP = 34
+        private const val WHEEL_DOWN = 523331
+        private const val WHEEL_UP = 963
+        private const val TOUCH_PAN_START = 4
+        private const val TOUCH_PAN_UPDATE = 5
+        private const val TOUCH_PAN_END = 6
+        private const val WHEEL_STEP = 120
+        private const val WHEEL_DURATION = 50L
+    }
+
+    private var leftIsDown = false
+    private var mouseX = 0
+    private var mouseY = 0
+    private var touchPath = Path()
+    private var gestureStartTime = 0L
+    private val longPressDuration =
+        ViewConfiguration.getTapTimeout().toLong() + ViewConfiguration.getLongPressTimeout().toLong()
+
+    @RequiresApi(Build.VERSION_CODES.N)
+    fun onMouseInput(mask: Int, x: Int, y: Int) {
+        if (mask == 0 || mask == LEFT_MOVE || mask == LEFT_DOWN || mask == LEFT_UP) {
+            mouseX = max(0, x) * HostService.screenScale
+            mouseY = max(0, y) * HostService.screenScale
+        }
+        when (mask) {
+            LEFT_DOWN -> {
+                leftIsDown = true
+                startGesture(mouseX, mouseY)
+            }
+            LEFT_MOVE -> if (leftIsDown) touchPath.lineTo(mouseX.toFloat(), mouseY.toFloat())
+            LEFT_UP -> if (leftIsDown) {
+                leftIsDown = false
+                endGesture(mouseX, mouseY)
+            }
+            RIGHT_UP -> dispatchStroke(Path().apply { moveTo(mouseX.toFloat(), mouseY.toFloat()) }, longPressDuration)
+            BACK_UP -> performGlobalAction(GLOBAL_ACTION_BACK)
+            WHEEL_BUTTON_UP -> performGlobalAction(GLOBAL_ACTION_HOME)
+            WHEEL_DOWN, WHEEL_UP -> {
+                if (mouseY < WHEEL_STEP) {
+                    return
+                }
+                val dy = if (mask == WHEEL_DOWN) -WHEEL_STEP else WHEEL_STEP
+                val path = Path()
+                path.moveTo(mouseX.toFloat(), mouseY.toFloat())
+                path.lineTo(mouseX.toFloat(), (mouseY + dy).toFloat())
+                dispatchStroke(path, WHEEL_DURATION)
+            }
+        }
+    }
+
+    @RequiresApi(Build.VERSION_CODES.N)
+    fun onTouchInput(mask: Int, x: Int, y: Int) {
+        when (mask) {
+            TOUCH_PAN_START -> {
+                mouseX = max(0, x) * HostService.screenScale
+                mouseY = max(0, y) * HostService.screenScale
+                startGesture(mouseX, mouseY)
+            }
+            TOUCH_PAN_UPDATE -> {
+                mouseX = max(0, mouseX - x * HostService.screenScale)
+                mouseY = max(0, mouseY - y * HostService.screenScale)
+                touchPath.lineTo(mouseX.toFloat(), mouseY.toFloat())
+            }
+            TOUCH_PAN_END -> endGesture(mouseX, mouseY)
+        }
+    }
+
+    private fun startGesture(x: Int, y: Int) {
+        touchPath = Path()
+        touchPath.moveTo(x.toFloat(), y.toFloat())
+        gestureStartTime = System.currentTimeMillis()
+    }
+
+    @RequiresApi(Build.VERSION_CODES.N)
+    private fun endGesture(x: Int, y: Int) {
+        touchPath.lineTo(x.toFloat(), y.toFloat())
+        dispatchStroke(touchPath, max(1L, System.currentTimeMillis() - gestureStartTime))
+    }
+
+    @RequiresApi(Build.VERSION_CODES.N)
+    private fun dispatchStroke(path: Path, duration: Long) {
+        try {
+            val stroke = GestureDescription.StrokeDescription(path, 0, duration)
+            dispatchGesture(GestureDescription.Builder().addStroke(stroke).build(), null, null)
+        } catch (e: Exception) {
+            e.printStackTrace()
+        }
+    }
+
+    override fun onServiceConnected() {
+        super.onServiceConnected()
+        ctx = this
+        HostService.reportState(this)
+    }
+
+    override fun onDestroy() {
+        ctx = null
+        HostService.reportState(this)
+        super.onDestroy()
+    }
+
+    override fun onAccessibilityEvent(event: AccessibilityEvent?) {}
+
+    override fun onInterrupt() {}
+}
@@ -0,0 +1,41 @@
+package com.rustdesk.unity
+
+import android.app.Activity
+import android.content.Intent
+import android.media.projection.MediaProjectionManager
+import android.os.Bundle
+import android.util.Log
+
+/** Transparent activity requesting the MediaProjection permission and handing the result to [HostService]. */
+class ProjectionRequestActivity : Activity() {
+    private val logTag = "ProjectionRequest"
+
+    override fun onCreate(savedInstanceState: Bundle?) {
+        super.onCreate(savedInstanceState)
+        val mediaProjectionManager =
+            getSystemService(MEDIA_PROJECTION_SERVICE) as MediaProjectionManager
+        startActivityForResult(
+            mediaProjectionManager.createScreenCaptureIntent(),
+            REQ_REQUEST_MEDIA_PROJECTION
+        )
+    }
+
+    override fun onActivityResult(requestCode: Int, resultCode: Int, data: Intent?) {
+        super.onActivityResult(requestCode, resultCode, data)
+        if (requestCode == REQ_REQUEST_MEDIA_PROJECTION) {
+            if (resultCode == RESULT_OK && data != null) {
+                val intent = Intent(this, HostService::class.java)
+                    .setAction(HostService.ACT_INIT_MEDIA_PROJECTION)
+                    .putExtra(HostService.EXT_MEDIA_PROJECTION_RES_INTENT, data)
+                RustDeskUnity.startForegroundService(this, intent)
+            } else {
+                Log.w(logTag, "media projection is denied")
+            }
+        }
+        finish()
+    }
+
+    companion object {
+        private const val REQ_REQUEST_MEDIA_PROJECTION = 201
+    }
+}
@@ -0,0 +1,129 @@
+package com.rustdesk.unity
+
+import android.annotation.SuppressLint
+import android.app.Activity
+import android.content.ComponentName
+import android.content.Context
+import android.content.Intent
+import android.net.Uri
+import android.os.Build
+import android.os.PowerManager
+import android.provider.Settings
+import android.text.TextUtils
+import android.util.Log
+import androidx.annotation.Keep
+import ffi.FFI
+
+/**
+ * Entry of the Unity bridge on Android, called from C# with `AndroidJavaClass("com.rustdesk.unity.RustDeskUnity")`.
+ *
+ * The viewer side only needs [initialize], the `rustdesk_unity_*` functions work as on the other platforms.
+ * To act as a host, call [requestScreenCapture] and ask the user to enable the accessibility service for input.
+ * The host state is reported as the `host_state` session event, see `src/unity/host_state.rs`.
+ */
+@Keep
+object RustDeskUnity {
+    private const val logTag = "RustDeskUnity"
+    private var initialized = false
+
+    @JvmStatic
+    fun initialize(context: Context) {
+        if (initialized) {
+            return
+        }
+        initialized = true
+        FFI.onAppStart(context.applicationContext)
+        Log.d(logTag, "initialized")
+    }
+
+    /**
+     * Start the host service, [configDir] is the config directory, empty means the default one.
+     * It stays available, restarted by the system if killed, until [stopHost].
+     */
+    @JvmStatic
+    fun startHost(context: Context, configDir: String) {
+        initialize(context)
+        HostService.saveAvailable(context, true, configDir)
+        val intent = Intent(context, HostService::class.java)
+            .putExtra(HostService.EXT_CONFIG_DIR, configDir)
+        startForegroundService(context, intent)
+    }
+
+    /** Ask the user for the screen capture permission, the capture starts on the first authorized connection. */
+    @JvmStatic
+    fun requestScreenCapture(activity: Activity) {
+        initialize(activity)
+        activity.startActivity(Intent(activity, ProjectionRequestActivity::class.java))
+    }
+
+    @JvmStatic
+    fun stopHost(context: Context) {
+        HostService.saveAvailable(context, false)
+        context.stopService(Intent(context, HostService::class.java))
+    }
+
+    /** Report the host state again, e.g. after the user is back from the system settings. */
+    @JvmStatic
+    fun refreshHostState(context: Context) {
+        initialize(context)
+        HostService.reportState(context.applicationContext)
+    }
+
+    @JvmStatic
+    fun isHostReady(): Boolean = HostService.isReady
+
+    @JvmStatic
+    fun isCapturing(): Boolean = HostService.isStart
+
+    @JvmStatic
+    fun isInputEnabled(context: Context): Boolean {
+        if (InputAccessibilityService.ctx != null) {
+            return true
+        }
+        val enabled = Settings.Secure.getString(
+            context.contentResolver,
+            Settings.Secure.ENABLED_ACCESSIBILITY_SERVICES
+        ) ?: return false
+        val name = ComponentName(context, InputAccessibilityService::class.java).flattenToString()
+        val splitter = TextUtils.SimpleStringSplitter(':')
+        splitter.setString(enabled)
+        return splitter.any { it.equals(name, ignoreCase = true) }
+    }
+
+    @JvmStatic
+    fun isIgnoringBatteryOptimizations(context: Context): Boolean {
+        if (Build.VERSION.SDK_INT < Build.VERSION_CODES.M) {
+            return true
+        }
+        val powerManager = context.getSystemService(Context.POWER_SERVICE) as PowerManager
+        return powerManager.isIgnoringBatteryOptimizations(context.packageName)
+    }
+
+    /** Ask the user to exempt the app from the battery optimizations, or the host may be unreachable in Doze. */
+    @JvmStatic
+    fun requestIgnoreBatteryOptimizations(activity: Activity) {
+        if (isIgnoringBatteryOptimizations(activity)) {
+            return
+        }
+        @SuppressLint("BatteryLife")
+        val intent = Intent(Settings.ACTION_REQUEST_IGNORE_BATTERY_OPTIMIZATIONS)
+            .setData(Uri.parse("package:${activity.packageName}"))
+        activity.startActivity(intent)
+    }
+
+    /** The accessibility service can only be enabled by the user in the system settings. */
+    @JvmStatic
+    fun openInputSettings(context: Context) {
+        val intent = Intent(Settings.ACTION_ACCESSIBILITY_SETTINGS)
+            .addFlags(Intent.FLAG_ACTIVITY_NEW_TASK)
+        context.startActivity(intent)
+    }
+
+    internal fun startForegroundService(context: Context, intent: Intent) {
+        if (android.os.Build.VERSION.SDK_INT >= android.os.Build.VERSION_CODES.O) {
+            context.startForegroundService(intent)
+        } else {
+            context.startService(intent)
+        }
+    }
+}
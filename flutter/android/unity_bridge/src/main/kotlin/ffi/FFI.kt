@@ -0,0 +1,24 @@
+// ffi.kt, the subset of `flutter/android/app/src/main/kotlin/ffi.kt` used by the Unity bridge.
+// The package and names must match the `Java_ffi_FFI_*` symbols.
+
+package ffi
+
+import android.content.Context
+import java.nio.ByteBuffer
+
+object FFI {
+    init {
+        System.loadLibrary("rustdesk")
+    }
+
+    external fun init(ctx: Context)
+    external fun onAppStart(ctx: Context)
+    external fun startServer(app_dir: String, custom_client_config: String)
+    external fun startService()
+    external fun onVideoFrameUpdate(buf: ByteBuffer)
+    external fun onAudioFrameUpdate(buf: ByteBuffer)
+    external fun refreshScreen()
+    external fun setFrameRawEnable(name: String, value: Boolean)
+    external fun getLocalOption(key: String): String
+    external fun onHostStateChanged(state: String)
+}
@@ -0,0 +1,29 @@
+#!/usr/bin/env bash
+# Build the Android library of the Unity bridge: librustdesk.so of each abi plus the
+# MediaProjection/Accessibility services, output to android/unity_bridge/build/outputs/aar.
+set -e
+
+MODE=${MODE:=release}
+ABIS=${ABIS:="arm64-v8a armeabi-v7a"}
+
+cd "$(dirname "$0")"
+for abi in $ABIS; do
+  case $abi in
+    arm64-v8a) target=aarch64-linux-android ;;
+    armeabi-v7a) target=armv7-linux-androideabi ;;
+    x86_64) target=x86_64-linux-android ;;
+    x86) target=i686-linux-android ;;
+    *) echo "unknown abi $abi"; exit 1 ;;
+  esac
+  (cd .. && cargo ndk --platform 21 --target $target build --release --features flutter)
+  mkdir -p android/unity_bridge/jniLibs/$abi
+  cp ../target/$target/release/liblibrustdesk.so android/unity_bridge/jniLibs/$abi/librustdesk.so
+  $ANDROID_NDK_HOME/toolchains/llvm/prebuilt/linux-x86_64/bin/llvm-strip android/unity_bridge/jniLibs/$abi/librustdesk.so
+done
+
+cd android
+if [ "$MODE" = "release" ]; then
+  ./gradlew :unity_bridge:assembleRelease
+else
+  ./gradlew :unity_bridge:assembleDebug
+fi
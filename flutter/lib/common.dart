@@ -2404,6 +2404,12 @@ List<String>? urlLinkToCmdArgs(Uri uri) {
   var queryParameters =
       uri.queryParameters.map((k, v) => MapEntry(k.toLowerCase(), v));
 
+  // The token to hand off a session from another device, redeemed on the login.
+  final handoff = queryParameters["handoff"];
+  if (id != null && handoff != null) {
+    bind.mainSetPeerOptionSync(id: id, key: 'handoff-token', value: handoff);
+  }
+
   var key = queryParameters["key"];
   if (id != null) {
     if (key != null) {
@@ -3466,12 +3472,15 @@ Future<bool> setServerConfig(
       return false;
     }
   }
-  // relay
+  // relay, several relay servers can be separated by commas
   if (config.relayServer.isNotEmpty && errMsgs != null) {
-    errMsgs[1].value = translate(await bind.mainTestIfValidServer(
-        server: config.relayServer, testWithProxy: true));
-    if (errMsgs[1].isNotEmpty) {
-      return false;
+    for (final relayServer in config.relayServer.split(',')) {
+      if (relayServer.trim().isEmpty) continue;
+      errMsgs[1].value = translate(await bind.mainTestIfValidServer(
+          server: relayServer.trim(), testWithProxy: true));
+      if (errMsgs[1].isNotEmpty) {
+        return false;
+      }
     }
   }
   // api
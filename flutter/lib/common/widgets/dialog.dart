@@ -2097,7 +2097,8 @@ void showWindowsSessionsDialog(
     OverlayDialogManager dialogManager,
     SessionID sessionId,
     String peerId,
-    String sessions) {
+    String sessions,
+    {bool cancelable = false}) {
   List<dynamic> sessionsList = [];
   try {
     sessionsList = json.decode(sessions);
@@ -2110,6 +2111,7 @@ void showWindowsSessionsDialog(
     sids.add(session['sid']);
     names.add(session['name']);
   }
+  if (sids.isEmpty) return;
   String selectedUserValue = sids.first;
   dialogManager.dismissAll();
   dialogManager.show((setState, close, context) {
@@ -2135,8 +2137,11 @@ void showWindowsSessionsDialog(
         ],
       ),
       actions: [
+        if (cancelable)
+          dialogButton('Cancel', onPressed: close, isOutline: true),
         dialogButton('Connect', onPressed: submit, isOutline: false),
       ],
+      onCancel: cancelable ? close : null,
     );
   });
 }
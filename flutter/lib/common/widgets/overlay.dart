@@ -606,6 +606,10 @@ class QualityMonitor extends StatelessWidget {
                       _row(
                           "Codec", qualityMonitorModel.data.codecFormat ?? '-'),
                       _row("Chroma", qualityMonitorModel.data.chroma ?? '-'),
+                      _row("Audio Buffer",
+                          "${qualityMonitorModel.data.audioBufferMs ?? '-'}ms"),
+                      _row("Frame Allocs",
+                          qualityMonitorModel.data.frameBufferAllocs ?? '-'),
                     ],
                   ),
                 )
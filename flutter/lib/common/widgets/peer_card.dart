@@ -673,8 +673,13 @@ abstract class BasePeerCard extends StatelessWidget {
         translate('WOL'),
         style: style,
       ),
-      proc: () {
+      proc: () async {
         bind.mainWol(id: id);
+        // The magic packet is only sent on the LAN, also ask the online peers on the LAN of the peer.
+        final n = await bind.mainWolViaPeers(id: id);
+        if (n > 0) {
+          showToast(translate('wol-via-peers-tip'));
+        }
       },
       padding: menuPadding,
       dismissOnClicked: true,
@@ -231,6 +231,17 @@ List<TTextMenu> toolbarControls(BuildContext context, String id, FFI ffi) {
               showRestartRemoteDevice(pi, id, sessionId, ffi.dialogManager)),
     );
   }
+  // windowsSessions
+  if (isDefaultConn &&
+      ffiModel.keyboard &&
+      pi.platform == kPeerPlatformWindows) {
+    v.add(
+      TTextMenu(
+          child: Text(translate('Windows sessions')),
+          onPressed: () =>
+              bind.sessionRequestWindowsSessions(sessionId: sessionId)),
+    );
+  }
   // insertLock
   if (isDefaultConn && !ffiModel.viewOnly && ffi.ffiModel.keyboard) {
     v.add(
@@ -298,6 +309,22 @@ List<TTextMenu> toolbarControls(BuildContext context, String id, FFI ffi) {
         onPressed: () => ffi.recordingModel.toggle()));
   }
 
+  // hand off the session to another device
+  if (isDefaultConn) {
+    v.add(TTextMenu(
+        child: Text(translate('Hand off session')),
+        onPressed: () => bind.sessionRequestHandoff(sessionId: sessionId)));
+  }
+  // pass the input control to another viewer
+  final inputControl = ffiModel.inputControl;
+  if (isDefaultConn &&
+      inputControl['policy'] == 'token' &&
+      ffiModel.inControl &&
+      (inputControl['viewers'] as List? ?? []).length > 1) {
+    v.add(TTextMenu(
+        child: Text(translate('Pass input control')),
+        onPressed: () => showPassInputControlDialog(ffi)));
+  }
   // to-do:
   // 1. Web desktop
   // 2. Mobile, copy the image to the clipboard
@@ -338,10 +365,102 @@ List<TTextMenu> toolbarControls(BuildContext context, String id, FFI ffi) {
       child: Text(translate('Copy Fingerprint')),
       onPressed: () => onCopyFingerprint(FingerprintState.find(id).value),
     ));
+    v.add(TTextMenu(
+      child: Text(translate('Copy Session Fingerprint')),
+      onPressed: () =>
+          onCopyFingerprint(SessionFingerprintState.find(id).value),
+    ));
   }
   return v;
 }
 
+void showPassInputControlDialog(FFI ffi) {
+  final inputControl = ffi.ffiModel.inputControl;
+  final viewers = (inputControl['viewers'] as List? ?? [])
+      .where((v) => v['conn_id'] != inputControl['you'])
+      .toList();
+  ffi.dialogManager.show((setState, close, context) {
+    return CustomAlertDialog(
+      title: Text(translate('Pass input control')),
+      content: Column(
+        mainAxisSize: MainAxisSize.min,
+        children: viewers
+            .map((v) => ListTile(
+                  title: Text(v['name'] ?? ''),
+                  subtitle: Text(v['peer_id'] ?? ''),
+                  onTap: () {
+                    bind.sessionPassInputControl(
+                        sessionId: ffi.sessionId, to: v['conn_id']);
+                    close();
+                  },
+                ))
+            .toList(),
+      ),
+      actions: [dialogButton('Cancel', onPressed: close, isOutline: true)],
+      onCancel: close,
+    );
+  });
+}
+
+void showAddVirtualMonitorDialog(FFI ffi) {
+  const presets = ['1920x1080', '2560x1440', '3840x2160', '1280x720'];
+  final widthController = TextEditingController(text: '2560');
+  final heightController = TextEditingController(text: '1440');
+  ffi.dialogManager.show((setState, close, context) {
+    submit() {
+      final width = int.tryParse(widthController.text) ?? 0;
+      final height = int.tryParse(heightController.text) ?? 0;
+      if (width <= 0 || height <= 0) return;
+      bind.sessionAddVirtualMonitor(
+          sessionId: ffi.sessionId, width: width, height: height);
+      close();
+    }
+
+    Widget sizeField(TextEditingController controller, String label) {
+      return Expanded(
+        child: TextField(
+          controller: controller,
+          inputFormatters: [FilteringTextInputFormatter.digitsOnly],
+          decoration: InputDecoration(labelText: translate(label)),
+        ),
+      );
+    }
+
+    return CustomAlertDialog(
+      title: Text(translate('Add virtual monitor')),
+      content: Column(
+        mainAxisSize: MainAxisSize.min,
+        children: [
+          Wrap(
+            spacing: 8,
+            children: presets
+                .map((p) => ActionChip(
+                      label: Text(p),
+                      onPressed: () {
+                        final size = p.split('x');
+                        widthController.text = size[0];
+                        heightController.text = size[1];
+                      },
+                    ))
+                .toList(),
+          ),
+          Row(children: [
+            sizeField(widthController, 'Width'),
+            const SizedBox(width: 8),
+            sizeField(heightController, 'Height'),
+          ]),
+        ],
+      ),
+      actions: [
+        dialogButton('Cancel', onPressed: close, isOutline: true),
+        dialogButton('OK', onPressed: submit),
+      ],
+      onSubmit: submit,
+      onCancel: close,
+    );
+  });
+}
+
 Future<List<TRadioMenu<String>>> toolbarViewStyle(
     BuildContext context, String id, FFI ffi) async {
   final groupValue =
@@ -397,6 +516,11 @@ Future<List<TRadioMenu<String>>> toolbarImageQuality(
         value: kRemoteImageQualityLow,
         groupValue: groupValue,
         onChanged: onChanged),
+    TRadioMenu<String>(
+        child: Text(translate('Lossless')),
+        value: kRemoteImageQualityLossless,
+        groupValue: groupValue,
+        onChanged: onChanged),
     TRadioMenu<String>(
       child: Text(translate('Custom')),
       value: kRemoteImageQualityCustom,
@@ -409,6 +533,30 @@ Future<List<TRadioMenu<String>>> toolbarImageQuality(
   ];
 }
 
+Future<List<TRadioMenu<String>>> toolbarEncoderProfile(
+    BuildContext context, String id, FFI ffi) async {
+  var groupValue =
+      await bind.sessionGetEncoderProfile(sessionId: ffi.sessionId) ?? '';
+  if (groupValue.isEmpty) groupValue = kEncoderProfileBalanced;
+  onChanged(String? value) async {
+    if (value == null) return;
+    await bind.sessionSetEncoderProfile(sessionId: ffi.sessionId, value: value);
+  }
+
+  TRadioMenu<String> radio(String text, String value) => TRadioMenu<String>(
+      child: Text(translate(text)),
+      value: value,
+      groupValue: groupValue,
+      onChanged: onChanged);
+
+  return [
+    radio('Balanced', kEncoderProfileBalanced),
+    radio('Latency first', kEncoderProfileLatency),
+    radio('Quality first', kEncoderProfileQuality),
+    radio('Bandwidth first', kEncoderProfileBandwidth),
+  ];
+}
+
 Future<List<TRadioMenu<String>>> toolbarCodec(
     BuildContext context, String id, FFI ffi) async {
   final sessionId = ffi.sessionId;
@@ -705,8 +853,14 @@ Future<List<TToggleMenu>> toolbarDisplayToggle(
 
   // 444
   final codec_format = ffi.qualityMonitorModel.data.codecFormat;
+  final codecPreference = await bind.sessionGetOption(
+          sessionId: sessionId, arg: kOptionCodecPreference) ??
+      '';
+  // Newer peers switch from the hardware codecs to vp9/av1 in auto mode.
+  final autoI444 = versionCmp(pi.version, "1.4.4") >= 0 &&
+      (codecPreference == '' || codecPreference == 'auto');
   if (versionCmp(pi.version, "1.2.4") >= 0 &&
-      (codec_format == "AV1" || codec_format == "VP9")) {
+      (codec_format == "AV1" || codec_format == "VP9" || autoI444)) {
     final option = 'i444';
     final value =
         bind.sessionGetToggleOptionSync(sessionId: sessionId, arg: option);
@@ -874,6 +1028,42 @@ List<TToggleMenu> toolbarKeyboardToggles(FFI ffi) {
         onChanged: enabled ? onChanged : null,
         child: Text(translate('swap-left-right-mouse'))));
   }
+
+  // relative mouse mode
+  if (isDesktop && ffiModel.keyboard && pi.platform != kPeerPlatformAndroid) {
+    final inputModel = ffi.inputModel;
+    onChanged(bool? value) {
+      if (value == null) return;
+      inputModel.setRelativeMouseMode(value);
+    }
+
+    final enabled = !ffi.ffiModel.viewOnly;
+    v.add(TToggleMenu(
+        value: inputModel.relativeMouseMode.value,
+        onChanged: enabled ? onChanged : null,
+        child: Text(translate('Relative mouse mode'))));
+  }
+
+  // full keyboard grab, by the rdev hook of input source 1
+  if (isDesktop && ffiModel.keyboard && !isInputSourceFlutter) {
+    final option = kOptionFullKeyboardGrab;
+    final value =
+        bind.sessionGetToggleOptionSync(sessionId: sessionId, arg: option);
+    onChanged(bool? value) async {
+      if (value == null) return;
+      await bind.sessionToggleOption(sessionId: sessionId, value: option);
+      if (value) {
+        final hotkey = bind.mainGetKeyboardGrabEscapeHotkey();
+        showToast(translate('full-keyboard-grab-{$hotkey}-tip'));
+      }
+    }
+
+    final enabled = !ffi.ffiModel.viewOnly;
+    v.add(TToggleMenu(
+        value: value,
+        onChanged: enabled ? onChanged : null,
+        child: Text(translate('Full keyboard grab'))));
+  }
   return v;
 }
 
@@ -916,6 +1106,17 @@ List<Widget> getVirtualDisplayMenuChildren(
             ffi: ffi,
           )));
     }
+    children.add(Obx(() => MenuButton(
+          onPressed: privacyModeState.isNotEmpty ||
+                  virtualDisplays.length >= kMaxVirtualDisplayCount
+              ? null
+              : () {
+                  clickCallBack?.call();
+                  showAddVirtualMonitorDialog(ffi);
+                },
+          ffi: ffi,
+          child: Text(translate('Add virtual monitor')),
+        )));
     children.add(Divider());
     children.add(Obx(() => MenuButton(
           onPressed: privacyModeState.isNotEmpty
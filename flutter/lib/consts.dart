@@ -28,6 +28,7 @@ const String kPlatformAdditionsAmyuniVirtualDisplays =
 const String kPlatformAdditionsHasFileClipboard = "has_file_clipboard";
 const String kPlatformAdditionsSupportedPrivacyModeImpl =
     "supported_privacy_mode_impl";
+const String kPlatformAdditionsSupportPen = "support_pen";
 
 const String kPeerPlatformWindows = "Windows";
 const String kPeerPlatformLinux = "Linux";
@@ -113,8 +114,12 @@ const String kOptionTerminalPersistent = "terminal-persistent";
 const String kOptionEnableTunnel = "enable-tunnel";
 const String kOptionEnableRemoteRestart = "enable-remote-restart";
 const String kOptionEnableBlockInput = "enable-block-input";
+const String kOptionEnableSystemInfo = "enable-system-info";
+const String kOptionEnableTaskManager = "enable-task-manager";
+const String kOptionEnableAnnotation = "enable-annotation";
 const String kOptionAllowRemoteConfigModification =
     "allow-remote-config-modification";
+const String kOptionAllowRemoteCommand = "allow-remote-command";
 const String kOptionVerificationMethod = "verification-method";
 const String kOptionApproveMode = "approve-mode";
 const String kOptionAllowNumericOneTimePassword =
@@ -153,6 +158,7 @@ const String kOptionAllowLinuxHeadless = "allow-linux-headless";
 const String kOptionAllowRemoveWallpaper = "allow-remove-wallpaper";
 const String kOptionStopService = "stop-service";
 const String kOptionDirectxCapture = "enable-directx-capture";
+const String kOptionScreenCaptureKit = "enable-screencapturekit";
 const String kOptionAllowRemoteCmModification = "allow-remote-cm-modification";
 const String kOptionEnableUdpPunch = "enable-udp-punch";
 const String kOptionEnableIpv6Punch = "enable-ipv6-punch";
@@ -181,6 +187,8 @@ const String kOptionHideHelpCards = "hide-help-cards";
 
 const String kOptionToggleViewOnly = "view-only";
 const String kOptionToggleShowMyCursor = "show-my-cursor";
+const String kOptionFullKeyboardGrab = "full-keyboard-grab";
+const String kOptionFileTransferRateLimit = "file-transfer-rate-limit";
 
 const String kOptionDisableFloatingWindow = "disable-floating-window";
 
@@ -206,6 +214,14 @@ const String kMouseEventTypePanEnd = "pan_end";
 const String kMouseEventTypeDown = "down";
 const String kMouseEventTypeUp = "up";
 
+// The same as `crate::pen::flags`.
+const int kPenFlagInRange = 0x01;
+const int kPenFlagContact = 0x02;
+const int kPenFlagBarrel = 0x04;
+const int kPenFlagEraser = 0x08;
+const int kPenFlagInverted = 0x10;
+const int kPenMaxPressure = 1024;
+
 const String kKeyFlutterKey = "flutter_key";
 
 const String kKeyShowDisplaysAsIndividualWindows =
@@ -352,6 +368,21 @@ const kRemoteImageQualityLow = 'low';
 /// [kRemoteImageQualityCustom] Custom image quality.
 const kRemoteImageQualityCustom = 'custom';
 
+/// [kRemoteImageQualityLossless] Lossless image quality, high bitrate.
+const kRemoteImageQualityLossless = 'lossless';
+
+/// [kEncoderProfileBalanced] The default tuning of the encoders.
+const kEncoderProfileBalanced = 'balanced';
+
+/// [kEncoderProfileLatency] Faster encoding, drops frames instead of delaying them.
+const kEncoderProfileLatency = 'latency';
+
+/// [kEncoderProfileQuality] Sharper text, no dropped frames.
+const kEncoderProfileQuality = 'quality';
+
+/// [kEncoderProfileBandwidth] More compression for slow links.
+const kEncoderProfileBandwidth = 'bandwidth';
+
 const kIgnoreDpi = true;
 
 const Set<PointerDeviceKind> kTouchBasedDeviceKinds = {
@@ -77,7 +77,7 @@ class DesktopSettingPage extends StatefulWidget {
     if (!isWeb && !bind.isIncomingOnly() && bind.pluginFeatureIsEnabled())
       SettingsTabKey.plugin,
     if (!bind.isDisableAccount()) SettingsTabKey.account,
-    if (isWindows &&
+    if ((isWindows || isLinux || isMacOS) &&
         bind.mainGetBuildinOption(key: kOptionHideRemotePrinterSetting) != 'Y')
       SettingsTabKey.printer,
     SettingsTabKey.about,
@@ -541,6 +541,12 @@ class _GeneralState extends State<_General> {
             'Capture screen using DirectX',
             kOptionDirectxCapture,
           ),
+        if (isMacOS && !bind.isOutgoingOnly())
+          _OptionCheckBox(
+            context,
+            'Capture screen using ScreenCaptureKit',
+            kOptionScreenCaptureKit,
+          ),
         if (!bind.isIncomingOnly()) ...[
           _OptionCheckBox(
             context,
@@ -669,6 +675,7 @@ class _GeneralState extends State<_General> {
         if (!bind.isOutgoingOnly())
           _OptionCheckBox(context, 'Automatically record incoming sessions',
               kOptionAllowAutoRecordIncoming),
+        if (!bind.isOutgoingOnly()) ...recordingPolicy(context),
         if (!bind.isIncomingOnly())
           _OptionCheckBox(context, 'Automatically record outgoing sessions',
               kOptionAllowAutoRecordOutgoing,
@@ -741,6 +748,66 @@ class _GeneralState extends State<_General> {
     });
   }
 
+  List<Widget> recordingPolicy(BuildContext context) {
+    // The options of the recording of the incoming sessions, see `recording_policy.rs`.
+    final fields = [
+      ('Directory', 'incoming-record-directory', '', false),
+      (
+        'File name template',
+        'incoming-record-filename',
+        'incoming_{id}_{date}{time}_{display}',
+        false
+      ),
+      ('Retention in days', 'incoming-record-retention-days', '0', true),
+      ('Maximum size in MB', 'incoming-record-max-size', '0', true),
+      ('Minimum free space in MB', 'incoming-record-min-free-space', '0', true),
+    ];
+    final controllers = fields
+        .map((f) =>
+            TextEditingController(text: bind.mainGetOptionSync(key: f.$2)))
+        .toList();
+    RxBool applyEnabled = false.obs;
+    final enabled = !locked && fields.every((f) => !isOptionFixed(f.$2));
+    return [
+      for (var i = 0; i < fields.length; i++)
+        _SubLabeledWidget(
+          context,
+          fields[i].$1,
+          Expanded(
+            child: TextField(
+              controller: controllers[i],
+              enabled: enabled,
+              onChanged: (_) => applyEnabled.value = true,
+              inputFormatters: fields[i].$4
+                  ? [FilteringTextInputFormatter.digitsOnly]
+                  : null,
+              decoration: InputDecoration(
+                hintText: fields[i].$3,
+                contentPadding:
+                    EdgeInsets.symmetric(vertical: 12, horizontal: 12),
+              ),
+            ).workaroundFreezeLinuxMint(),
+          ),
+          enabled: enabled,
+        ).marginOnly(bottom: 4),
+      Obx(() => ElevatedButton(
+            onPressed: applyEnabled.value && enabled
+                ? () async {
+                    applyEnabled.value = false;
+                    for (var i = 0; i < fields.length; i++) {
+                      await bind.mainSetOption(
+                          key: fields[i].$2,
+                          value: controllers[i].text.trim());
+                    }
+                  }
+                : null,
+            child: Text(
+              translate('Apply'),
+            ),
+          )).marginOnly(left: _kContentHSubMargin),
+    ];
+  }
+
   Widget language() {
     return futureBuilder(future: () async {
       String langs = await bind.mainGetLangs();
@@ -810,7 +877,9 @@ class _SafetyState extends State<_Safety> with AutomaticKeepAliveClientMixin {
                 permissions(context),
                 password(context),
                 _Card(title: '2FA', children: [tfa()]),
+                _Card(title: 'Security keys', children: [securityKeys()]),
                 _Card(title: 'ID', children: [changeId()]),
+                _Card(title: 'Key', children: [rotateKey()]),
                 more(context),
               ]),
             ),
@@ -937,6 +1006,33 @@ class _SafetyState extends State<_Safety> with AutomaticKeepAliveClientMixin {
     return tmpWrapper();
   }
 
+  Widget securityKeys() {
+    final names =
+        (jsonDecode(bind.mainGetFido2Keys()) as List<dynamic>).cast<String>();
+    return Column(
+      crossAxisAlignment: CrossAxisAlignment.start,
+      children: [
+        ...names.map((name) => Row(
+              children: [
+                Expanded(child: Text(name)),
+                IconButton(
+                  icon: const Icon(Icons.delete_outline),
+                  onPressed: locked
+                      ? null
+                      : () async {
+                          await bind.mainRemoveFido2Key(name: name);
+                          setState(() {});
+                        },
+                ),
+              ],
+            ).marginOnly(left: _kContentHMargin)),
+        _Button('Register security key',
+            () => registerSecurityKey(() => setState(() {})),
+            enabled: !locked),
+      ],
+    );
+  }
+
   Widget changeId() {
     return ChangeNotifierProvider.value(
         value: gFFI.serverModel,
@@ -946,6 +1042,16 @@ class _SafetyState extends State<_Safety> with AutomaticKeepAliveClientMixin {
         })));
   }
 
+  Widget rotateKey() {
+    return _Button('Rotate key', () {
+      CommonConfirmDialog(
+          gFFI.dialogManager, translate('rotate-key-confirm-tip'), () async {
+        final err = await bind.mainRotateKeyPair(force: true);
+        showToast(translate(err.isEmpty ? 'key-rotated-tip' : err));
+      });
+    }, enabled: !locked);
+  }
+
   Widget permissions(context) {
     bool enabled = !locked;
     // Simple temp wrapper for PR check
@@ -994,6 +1100,8 @@ class _SafetyState extends State<_Safety> with AutomaticKeepAliveClientMixin {
               await bind.mainSetOption(key: kOptionAccessMode, value: mode);
               setState(() {});
             }).marginOnly(left: _kContentHMargin),
+        permissionProfile(enabled),
+        inputControlPolicy(enabled),
         Column(
           children: [
             _OptionCheckBox(
@@ -1023,6 +1131,15 @@ class _SafetyState extends State<_Safety> with AutomaticKeepAliveClientMixin {
             _OptionCheckBox(
                 context, 'Enable recording session', kOptionEnableRecordSession,
                 enabled: enabled, fakeValue: fakeValue),
+            _OptionCheckBox(
+                context, 'Enable system info', kOptionEnableSystemInfo,
+                enabled: enabled, fakeValue: fakeValue),
+            _OptionCheckBox(
+                context, 'Enable task manager', kOptionEnableTaskManager,
+                enabled: enabled, fakeValue: fakeValue),
+            _OptionCheckBox(
+                context, 'Enable annotation', kOptionEnableAnnotation,
+                enabled: enabled, fakeValue: fakeValue),
             if (isWindows)
               _OptionCheckBox(context, 'Enable blocking user input',
                   kOptionEnableBlockInput,
@@ -1030,6 +1147,9 @@ class _SafetyState extends State<_Safety> with AutomaticKeepAliveClientMixin {
             _OptionCheckBox(context, 'Enable remote configuration modification',
                 kOptionAllowRemoteConfigModification,
                 enabled: enabled, fakeValue: fakeValue),
+            _OptionCheckBox(
+                context, 'Enable remote command', kOptionAllowRemoteCommand,
+                enabled: enabled, fakeValue: fakeValue),
           ],
         ),
       ]);
@@ -1038,6 +1158,59 @@ class _SafetyState extends State<_Safety> with AutomaticKeepAliveClientMixin {
     return tmpWrapper();
   }
 
+  // The default permission profile, which restricts the toggles below, they are used as is if none.
+  Widget permissionProfile(bool enabled) {
+    const key = 'permission-profile';
+    final profiles =
+        (jsonDecode(bind.mainGetPermissionProfiles()) as Map<String, dynamic>)
+            .keys
+            .toList();
+    final current = bind.mainGetOptionSync(key: key);
+    return Row(
+      children: [
+        Text(translate('Default permission profile')).marginOnly(right: 10),
+        Expanded(
+          child: ComboBox(
+              keys: ['', ...profiles],
+              values: [translate('None'), ...profiles],
+              enabled: enabled && !isOptionFixed(key),
+              initialKey: profiles.contains(current) ? current : '',
+              onChanged: (profile) async {
+                await bind.mainSetOption(key: key, value: profile);
+                setState(() {});
+              }),
+        ),
+      ],
+    ).marginOnly(left: _kContentHMargin, top: 8);
+  }
+
+  // Who controls the input of the simultaneous sessions.
+  Widget inputControlPolicy(bool enabled) {
+    const key = 'input-control-policy';
+    final current = bind.mainGetOptionSync(key: key);
+    return Row(
+      children: [
+        Text(translate('Input control')).marginOnly(right: 10),
+        Expanded(
+          child: ComboBox(
+              keys: ['', 'first-come', 'token', 'host'],
+              values: [
+                translate('All sessions'),
+                translate('First come'),
+                translate('Passed by the session in control'),
+                translate('Assigned by me'),
+              ],
+              enabled: enabled && !isOptionFixed(key),
+              initialKey: current,
+              onChanged: (policy) async {
+                await bind.mainSetOption(key: key, value: policy);
+                setState(() {});
+              }),
+        ),
+      ],
+    ).marginOnly(left: _kContentHMargin, top: 8);
+  }
+
   Widget password(BuildContext context) {
     return ChangeNotifierProvider.value(
         value: gFFI.serverModel,
@@ -1199,6 +1372,13 @@ class _SafetyState extends State<_Safety> with AutomaticKeepAliveClientMixin {
       ...directIp(context),
       whitelist(),
       ...autoDisconnect(context),
+      ...sessionTimeLimit(context),
+      accessWindows(context),
+      _OptionCheckBox(context, 'enable-audit-log-tip', 'enable-audit-log',
+          enabled: enabled),
+      _OptionCheckBox(
+          context, 'enable-session-handoff-tip', 'enable-session-handoff',
+          enabled: enabled),
       if (bind.mainIsInstalled())
         _OptionCheckBox(context, 'allow-only-conn-window-open-tip',
             'allow-only-conn-window-open',
@@ -1452,6 +1632,137 @@ class _SafetyState extends State<_Safety> with AutomaticKeepAliveClientMixin {
     ];
   }
 
+  List<Widget> sessionTimeLimit(BuildContext context) {
+    const kOptionTimeLimit = 'session-time-limit';
+    const kOptionExpiryAction = 'session-expiry-action';
+    TextEditingController controller = TextEditingController();
+    RxBool applyEnabled = false.obs;
+    controller.text = bind.mainGetOptionSync(key: kOptionTimeLimit);
+    final isOptFixed = isOptionFixed(kOptionTimeLimit);
+    final enabled = !locked && !isOptFixed;
+    return [
+      _SubLabeledWidget(
+        context,
+        'Session time limit in minutes',
+        Row(children: [
+          SizedBox(
+            width: 95,
+            child: TextField(
+              controller: controller,
+              enabled: enabled,
+              onChanged: (_) => applyEnabled.value = true,
+              inputFormatters: [FilteringTextInputFormatter.digitsOnly],
+              decoration: const InputDecoration(
+                hintText: '0',
+                contentPadding:
+                    EdgeInsets.symmetric(vertical: 12, horizontal: 12),
+              ),
+            ).workaroundFreezeLinuxMint().marginOnly(right: 15),
+          ),
+          Obx(() => ElevatedButton(
+                onPressed: applyEnabled.value && enabled
+                    ? () async {
+                        applyEnabled.value = false;
+                        await bind.mainSetOption(
+                            key: kOptionTimeLimit, value: controller.text);
+                      }
+                    : null,
+                child: Text(
+                  translate('Apply'),
+                ),
+              ))
+        ]),
+        enabled: enabled,
+      ),
+      _OptionCheckBox(
+        context,
+        'session-expiry-view-only-tip',
+        kOptionExpiryAction,
+        enabled: !locked,
+        optGetter: () =>
+            bind.mainGetOptionSync(key: kOptionExpiryAction) == 'view-only',
+        optSetter: (key, value) =>
+            bind.mainSetOption(key: key, value: value ? 'view-only' : ''),
+      ),
+    ];
+  }
+
+  Widget accessWindows(BuildContext context) {
+    const kOptionWindows = 'access-windows';
+    const kOptionTimezone = 'access-windows-timezone';
+    TextEditingController windowsController = TextEditingController();
+    TextEditingController timezoneController = TextEditingController();
+    RxBool applyEnabled = false.obs;
+    RxString errorText = ''.obs;
+    windowsController.text = bind.mainGetOptionSync(key: kOptionWindows);
+    timezoneController.text = bind.mainGetOptionSync(key: kOptionTimezone);
+    final isOptFixed =
+        isOptionFixed(kOptionWindows) || isOptionFixed(kOptionTimezone);
+    final enabled = !locked && !isOptFixed;
+    onChanged(_) {
+      applyEnabled.value = true;
+      errorText.value = '';
+    }
+
+    return _SubLabeledWidget(
+      context,
+      'Access windows',
+      Expanded(
+        child: Row(children: [
+          Expanded(
+            child: Obx(() => TextField(
+                  controller: windowsController,
+                  enabled: enabled,
+                  onChanged: onChanged,
+                  decoration: InputDecoration(
+                    hintText: 'Mon-Fri 09:00-18:00',
+                    errorText: errorText.isNotEmpty ? errorText.value : null,
+                    contentPadding:
+                        EdgeInsets.symmetric(vertical: 12, horizontal: 12),
+                  ),
+                ).workaroundFreezeLinuxMint()),
+          ).marginOnly(right: 10),
+          SizedBox(
+            width: 120,
+            child: TextField(
+              controller: timezoneController,
+              enabled: enabled,
+              onChanged: onChanged,
+              decoration: InputDecoration(
+                hintText: translate('Local time'),
+                contentPadding:
+                    EdgeInsets.symmetric(vertical: 12, horizontal: 12),
+              ),
+            ).workaroundFreezeLinuxMint().marginOnly(right: 15),
+          ),
+          Obx(() => ElevatedButton(
+                onPressed: applyEnabled.value && enabled
+                    ? () async {
+                        final windows = windowsController.text.trim();
+                        final timezone = timezoneController.text.trim();
+                        final err = bind.mainCheckAccessWindows(
+                            windows: windows, timezone: timezone);
+                        if (err.isNotEmpty) {
+                          errorText.value = err;
+                          return;
+                        }
+                        applyEnabled.value = false;
+                        await bind.mainSetOption(
+                            key: kOptionWindows, value: windows);
+                        await bind.mainSetOption(
+                            key: kOptionTimezone, value: timezone);
+                      }
+                    : null,
+                child: Text(
+                  translate('Apply'),
+                ),
+              ))
+        ]),
+      ),
+      enabled: enabled,
+    );
+  }
+
   Widget unlockPin() {
     bool enabled = !locked;
     RxString unlockPin = bind.mainGetUnlockPin().obs;
@@ -2212,6 +2523,11 @@ class __PrinterState extends State<_Printer> {
 
     PrinterOptions printerOptions = PrinterOptions.load();
     return _Card(title: 'Incoming Print Jobs', children: [
+      if (!isWindows)
+        Align(
+          alignment: Alignment.topLeft,
+          child: Text(translate('printer-cups-requirement-tip')),
+        ).marginOnly(left: _kCardLeftMargin),
       _Radio(context,
           value: kValuePrinterIncomingJobDismiss,
           groupValue: printerOptions.action,
@@ -2766,6 +3082,69 @@ class _CountDownButtonState extends State<_CountDownButton> {
 
 //#region dialogs
 
+void registerSecurityKey(Function() callback) {
+  final nameController = TextEditingController();
+  final pinController = TextEditingController();
+  String errorMsg = '';
+  var isInProgress = false;
+  gFFI.dialogManager.show((setState, close, context) {
+    submit() async {
+      final name = nameController.text.trim();
+      if (name.isEmpty) return;
+      setState(() {
+        errorMsg = '';
+        isInProgress = true;
+      });
+      final err = await bind.mainRegisterFido2Key(
+          name: name, pin: pinController.text.trim());
+      if (err.isNotEmpty) {
+        setState(() {
+          errorMsg = translate(err);
+          isInProgress = false;
+        });
+        return;
+      }
+      callback();
+      close();
+    }
+
+    return CustomAlertDialog(
+      title: Text(translate('Register security key')),
+      content: ConstrainedBox(
+        constraints: const BoxConstraints(minWidth: 400),
+        child: Column(
+          crossAxisAlignment: CrossAxisAlignment.start,
+          children: [
+            Text(translate('fido2-register-tip')).marginOnly(bottom: 8),
+            TextField(
+              controller: nameController,
+              decoration: InputDecoration(
+                labelText: translate('Name'),
+                errorText: errorMsg.isNotEmpty ? errorMsg : null,
+              ),
+              autofocus: true,
+            ).workaroundFreezeLinuxMint(),
+            TextField(
+              controller: pinController,
+              obscureText: true,
+              decoration: InputDecoration(labelText: 'PIN'),
+            ).workaroundFreezeLinuxMint(),
+            // NOT use Offstage to wrap LinearProgressIndicator
+            if (isInProgress)
+              const LinearProgressIndicator().marginOnly(top: 8),
+          ],
+        ),
+      ),
+      actions: [
+        dialogButton('Cancel', onPressed: close, isOutline: true),
+        dialogButton('OK', onPressed: isInProgress ? null : submit),
+      ],
+      onSubmit: submit,
+      onCancel: close,
+    );
+  });
+}
+
 void changeSocks5Proxy() async {
   var socks = await bind.mainGetSocks();
 
@@ -2818,6 +3197,13 @@ void changeSocks5Proxy() async {
           cancel();
           return;
         }
+        final err = await bind.mainTestProxy(
+            proxy: proxy, username: username, password: password);
+        if (err.isNotEmpty) {
+          proxyMsg = '${translate('proxy-test-failed-tip')}: $err';
+          cancel();
+          return;
+        }
       }
       await bind.mainSetSocks(
           proxy: proxy, username: username, password: password);
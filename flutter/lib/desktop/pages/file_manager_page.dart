@@ -23,6 +23,7 @@ import 'package:flutter_hbb/web/dummy.dart'
 import '../../consts.dart';
 import '../../desktop/widgets/material_mod_popup_menu.dart' as mod_menu;
 import '../../common.dart';
+import '../../common/widgets/dialog.dart';
 import '../../models/model.dart';
 import '../../models/platform_model.dart';
 import '../widgets/popup_menu.dart';
@@ -276,6 +277,25 @@ class _FileManagerPageState extends State<FileManagerPage>
                         Row(
                           mainAxisAlignment: MainAxisAlignment.end,
                           children: [
+                            Offstage(
+                              offstage: isWeb ||
+                                  item.type != JobType.transfer ||
+                                  item.state != JobState.inProgress,
+                              child: MenuButton(
+                                tooltip: item.rateLimit > 0
+                                    ? '${translate("Transfer rate limit")}: ${item.rateLimit} KB/s'
+                                    : translate("Transfer rate limit"),
+                                onPressed: () => showTransferLimitDialog(
+                                    _ffi.dialogManager,
+                                    item.rateLimit,
+                                    (kbps) => jobController.setJobTransferLimit(
+                                        item, kbps)),
+                                child: Icon(Icons.speed,
+                                    color: Colors.white, size: 20),
+                                color: MyTheme.accent,
+                                hoverColor: MyTheme.accent80,
+                              ),
+                            ),
                             Offstage(
                               offstage: item.state != JobState.paused,
                               child: MenuButton(
@@ -956,6 +976,62 @@ class _FileManagerViewState extends State<FileManagerView> {
     );
   }
 
+  void showFolderSyncDialog() {
+    final local = controller.directory.value.path;
+    final remote = _ffi.fileModel.remoteController.directory.value.path;
+    final interval = TextEditingController(text: '0');
+    var delete = false;
+    _ffi.dialogManager.show((setState, close, context) {
+      submit() {
+        _ffi.fileModel.startFolderSync(local, remote,
+            intervalMins: int.tryParse(interval.text) ?? 0, delete: delete);
+        close();
+      }
+
+      cancel() => close(false);
+      return CustomAlertDialog(
+        title: Text(translate('Sync to the remote folder')),
+        content: Column(
+          mainAxisSize: MainAxisSize.min,
+          crossAxisAlignment: CrossAxisAlignment.start,
+          children: [
+            Text('$local -> $remote'),
+            Text(translate('folder-sync-tip')).marginOnly(top: 8),
+            DialogTextField(
+              title: translate('Re-sync interval in minutes, 0 for once'),
+              controller: interval,
+              keyboardType: TextInputType.number,
+              inputFormatters: [FilteringTextInputFormatter.digitsOnly],
+            ),
+            CheckboxListTile(
+              contentPadding: const EdgeInsets.all(0),
+              dense: true,
+              controlAffinity: ListTileControlAffinity.leading,
+              title: Text(translate('folder-sync-delete-tip')),
+              value: delete,
+              onChanged: (v) => setState(() => delete = v ?? false),
+            ),
+          ],
+        ),
+        actions: [
+          dialogButton(
+            "Cancel",
+            icon: Icon(Icons.close_rounded),
+            onPressed: cancel,
+            isOutline: true,
+          ),
+          dialogButton(
+            "Ok",
+            icon: Icon(Icons.done_rounded),
+            onPressed: submit,
+          ),
+        ],
+        onSubmit: submit,
+        onCancel: cancel,
+      );
+    });
+  }
+
   Widget menu({bool isLocal = false}) {
     var menuPos = RelativeRect.fill;
 
@@ -983,7 +1059,34 @@ class _FileManagerViewState extends State<FileManagerView> {
               Text(translate("Unselect All"), style: style),
           proc: () => selectedItems.clear(),
           padding: kDesktopMenuPadding,
-          dismissOnClicked: true)
+          dismissOnClicked: true),
+      if (isLocal && !isWeb)
+        MenuEntryButton(
+            childBuilder: (style) =>
+                Text(translate("Transfer rate limit"), style: style),
+            proc: () => showTransferLimitDialog(
+                _ffi.dialogManager,
+                int.tryParse(
+                        bind.mainGetLocalOption(key: kOptionFileTransferRateLimit)) ??
+                    0,
+                (kbps) => _ffi.fileModel.jobController.setTransferLimit(kbps)),
+            padding: kDesktopMenuPadding,
+            dismissOnClicked: true),
+      if (isLocal && !isWeb)
+        MenuEntryButton(
+            childBuilder: (style) =>
+                Text(translate("Sync to the remote folder"), style: style),
+            proc: () => showFolderSyncDialog(),
+            padding: kDesktopMenuPadding,
+            dismissOnClicked: true),
+      if (isLocal)
+        ..._ffi.fileModel.folderSyncs.entries.map((e) => MenuEntryButton(
+            childBuilder: (style) => Text(
+                '${translate("Stop sync")} ${e.value.$1} -> ${e.value.$2}',
+                style: style),
+            proc: () => _ffi.fileModel.stopFolderSync(e.key),
+            padding: kDesktopMenuPadding,
+            dismissOnClicked: true)),
     ];
 
     return Listener(
@@ -1676,3 +1779,42 @@ Widget buildWindowsThisPC(BuildContext context, [TextStyle? textStyle]) {
     Text(translate('This PC'), style: textStyle)
   ]);
 }
+
+/// Ask for a transfer rate limit in KB/s, 0 for no limit.
+void showTransferLimitDialog(OverlayDialogManager dialogManager, int kbps,
+    void Function(int kbps) onSubmit) {
+  final controller = TextEditingController(text: kbps > 0 ? '$kbps' : '');
+  dialogManager.show((setState, close, context) {
+    submit() {
+      onSubmit(int.tryParse(controller.text) ?? 0);
+      close();
+    }
+
+    cancel() => close(false);
+    return CustomAlertDialog(
+      title: Text(translate('Transfer rate limit')),
+      content: DialogTextField(
+        title: 'KB/s',
+        hintText: translate('transfer-rate-limit-tip'),
+        controller: controller,
+        keyboardType: TextInputType.number,
+        inputFormatters: [FilteringTextInputFormatter.digitsOnly],
+      ),
+      actions: [
+        dialogButton(
+          "Cancel",
+          icon: Icon(Icons.close_rounded),
+          onPressed: cancel,
+          isOutline: true,
+        ),
+        dialogButton(
+          "Ok",
+          icon: Icon(Icons.done_rounded),
+          onPressed: submit,
+        ),
+      ],
+      onSubmit: submit,
+      onCancel: cancel,
+    );
+  });
+}
@@ -181,7 +181,11 @@ class _RemotePageState extends State<RemotePage>
       // unfocus the primary-focus when the whole window is lost focus,
       // and let OS to handle events instead.
       _rawKeyFocusNode.unfocus();
+    } else if (_ffi.inputModel.isFullKeyboardGrab) {
+      // The grab is kept after the cursor leaves the image, until the window is inactive.
+      _ffi.inputModel.enterOrLeave(false);
     }
+    _ffi.inputModel.setRelativeMouseMode(false);
     stateGlobal.isFocused.value = false;
   }
 
@@ -448,7 +452,8 @@ class _RemotePageState extends State<RemotePage>
       }
     }
     // See [onWindowBlur].
-    if (!isWindows) {
+    if (!isWindows &&
+        !(_ffi.inputModel.isFullKeyboardGrab && stateGlobal.isFocused.value)) {
       _ffi.inputModel.enterOrLeave(false);
     }
   }
@@ -611,7 +616,8 @@ class _ImagePaintState extends State<ImagePaint> {
 
           return MouseRegion(
               cursor: cursorOverImage.isTrue
-                  ? c.cursorEmbedded
+                  ? c.cursorEmbedded ||
+                          widget.ffi.inputModel.relativeMouseMode.isTrue
                       ? SystemMouseCursors.none
                       : keyboardEnabled.isTrue
                           ? (() {
@@ -160,6 +160,12 @@ class _ConnectionTabPageState extends State<ConnectionTabPage> {
             } else {
               msgFingerprint += fingerprint;
             }
+            final sessionFingerprint =
+                SessionFingerprintState.find(key).value;
+            if (sessionFingerprint.isNotEmpty) {
+              msgFingerprint +=
+                  '\n${translate('Session Fingerprint')}:\n$sessionFingerprint';
+            }
 
             final tab = Row(
               mainAxisAlignment: MainAxisAlignment.center,
@@ -311,6 +317,17 @@ class _ConnectionTabPageState extends State<ConnectionTabPage> {
         dismissOnClicked: true,
         dismissCallback: cancelFunc,
       ),
+      MenuEntryButton<String>(
+        childBuilder: (TextStyle? style) => Text(
+          translate('Copy Session Fingerprint'),
+          style: style,
+        ),
+        proc: () =>
+            onCopyFingerprint(SessionFingerprintState.find(key).value),
+        padding: padding,
+        dismissOnClicked: true,
+        dismissCallback: cancelFunc,
+      ),
       MenuEntryButton<String>(
         childBuilder: (TextStyle? style) => Text(
           translate('Close'),
@@ -529,6 +529,13 @@ class _CmHeaderState extends State<_CmHeader>
                       style: TextStyle(color: Colors.white70, fontSize: 12),
                     ),
                   ),
+                if (client.sessionFingerprint.isNotEmpty)
+                  FittedBox(
+                    child: Text(
+                      "${translate('Session Fingerprint')}: ${client.sessionFingerprint}",
+                      style: TextStyle(color: Colors.white70, fontSize: 12),
+                    ),
+                  ),
                 SizedBox(height: 10.0),
                 FittedBox(
                     child: Row(
@@ -551,7 +558,24 @@ class _CmHeaderState extends State<_CmHeader>
                         ),
                       )
                   ],
-                ))
+                )),
+                if (client.authorized &&
+                    !client.disconnected &&
+                    (client.expireTime > 0 || client.expired))
+                  Obx(() {
+                    // Refreshed with the connected time.
+                    _time.value;
+                    final remaining = client.expireTime -
+                        DateTime.now().millisecondsSinceEpoch ~/ 1000;
+                    return FittedBox(
+                      child: Text(
+                        client.expired || remaining <= 0
+                            ? translate('Session expired')
+                            : '${translate('Expires in')} ${formatDurationToTime(Duration(seconds: remaining))}',
+                        style: TextStyle(color: Colors.white70, fontSize: 12),
+                      ),
+                    );
+                  }),
               ],
             ),
           ),
@@ -706,6 +730,19 @@ class _PrivilegeBoardState extends State<_PrivilegeBoard> {
                         },
                         translate('Enable keyboard/mouse'),
                       ),
+                      if (bind
+                          .mainGetOptionSync(key: 'input-control-policy')
+                          .isNotEmpty)
+                        buildPermissionIcon(
+                          client.inControl,
+                          Icons.pan_tool_rounded,
+                          (enabled) {
+                            // The control is taken by giving it to another one.
+                            if (!enabled) return;
+                            bind.cmAssignInputControl(connId: client.id);
+                          },
+                          translate('Give input control'),
+                        ),
                       buildPermissionIcon(
                         client.clipboard,
                         Icons.assignment_rounded,
@@ -6,6 +6,7 @@ import 'package:flutter/services.dart';
 import 'package:flutter_hbb/common/widgets/audio_input.dart';
 import 'package:flutter_hbb/common/widgets/dialog.dart';
 import 'package:flutter_hbb/common/widgets/toolbar.dart';
+import 'package:flutter_hbb/models/annotation_model.dart';
 import 'package:flutter_hbb/models/chat_model.dart';
 import 'package:flutter_hbb/models/state_model.dart';
 import 'package:flutter_hbb/consts.dart';
@@ -941,6 +942,7 @@ class _DisplayMenuState extends State<_DisplayMenu> {
         viewStyle(customPercent: _customPercent),
         scrollStyle(state, colorScheme),
         imageQuality(),
+        encoderProfile(),
         codec(),
         if (ffi.connType == ConnType.defaultConn)
           _ResolutionsMenu(
@@ -1164,6 +1166,27 @@ class _DisplayMenuState extends State<_DisplayMenu> {
         });
   }
 
+  encoderProfile() {
+    return futureBuilder(
+        future: toolbarEncoderProfile(context, id, ffi),
+        hasData: (data) {
+          final v = data as List<TRadioMenu<String>>;
+          if (v.isEmpty) return Offstage();
+
+          return _SubmenuButton(
+              ffi: widget.ffi,
+              child: Text(translate('Encoder profile')),
+              menuChildren: v
+                  .map((e) => RdoMenuButton(
+                      value: e.value,
+                      groupValue: e.groupValue,
+                      onChanged: e.onChanged,
+                      child: e.child,
+                      ffi: ffi))
+                  .toList());
+        });
+  }
+
   codec() {
     return futureBuilder(
         future: toolbarCodec(context, id, ffi),
@@ -1745,6 +1768,9 @@ class _KeyboardMenu extends StatelessWidget {
               if ([kPeerPlatformWindows, kPeerPlatformMacOS, kPeerPlatformLinux]
                   .contains(pi.platform))
                 showMyCursor(),
+              if ([kPeerPlatformWindows, kPeerPlatformMacOS, kPeerPlatformLinux]
+                  .contains(pi.platform))
+                ...annotation(),
               Divider(),
               ...toolbarToggles(),
               ...mouseSpeed(),
@@ -1938,6 +1964,46 @@ class _KeyboardMenu extends StatelessWidget {
         .paddingOnly(left: 26.0);
   }
 
+  annotation() {
+    final model = ffi.annotationModel;
+    final tools = {
+      AnnotationTool.arrow: 'Arrow',
+      AnnotationTool.rect: 'Box',
+      AnnotationTool.pen: 'Pen',
+    };
+    return [
+      CkbMenuButton(
+              value: model.enabled,
+              onChanged: (value) {
+                if (value == null) return;
+                model.setEnabled(value);
+              },
+              ffi: ffi,
+              child: Text(translate('Annotate')))
+          .paddingOnly(left: 26.0),
+      if (model.enabled)
+        ...tools.entries.map((e) => RdoMenuButton<AnnotationTool>(
+              value: e.key,
+              groupValue: model.tool,
+              onChanged: (value) {
+                if (value != null) model.setTool(value);
+              },
+              ffi: ffi,
+              child: Text(translate(e.value)),
+            ).paddingOnly(left: 26.0)),
+      if (model.enabled)
+        MenuButton(
+            child: Text(translate('Undo')).paddingOnly(left: 26.0),
+            onPressed: () => model.undo(),
+            ffi: ffi),
+      if (model.enabled)
+        MenuButton(
+            child: Text(translate('Clear annotations')).paddingOnly(left: 26.0),
+            onPressed: () => model.clear(),
+            ffi: ffi),
+    ];
+  }
+
   mobileActions() {
     if (pi.platform != kPeerPlatformAndroid) return [];
     final enabled = versionCmp(pi.version, '1.2.7') >= 0;
@@ -1175,6 +1175,8 @@ void showOptions(
       await toolbarViewStyle(context, id, gFFI);
   List<TRadioMenu<String>> imageQualityRadios =
       await toolbarImageQuality(context, id, gFFI);
+  List<TRadioMenu<String>> encoderProfileRadios =
+      await toolbarEncoderProfile(context, id, gFFI);
   List<TRadioMenu<String>> codecRadios = await toolbarCodec(context, id, gFFI);
   List<TToggleMenu> cursorToggles = await toolbarCursor(context, id, gFFI);
   List<TToggleMenu> displayToggles =
@@ -1196,6 +1198,10 @@ void showOptions(
     var imageQuality =
         (imageQualityRadios.isNotEmpty ? imageQualityRadios[0].groupValue : '')
             .obs;
+    var encoderProfile = (encoderProfileRadios.isNotEmpty
+            ? encoderProfileRadios[0].groupValue
+            : '')
+        .obs;
     var codec = (codecRadios.isNotEmpty ? codecRadios[0].groupValue : '').obs;
     final radios = [
       for (var e in viewStyleRadios)
@@ -1226,6 +1232,18 @@ void showOptions(
                   }
                 : null)),
       const Divider(color: MyTheme.border),
+      for (var e in encoderProfileRadios)
+        Obx(() => getRadio<String>(
+            e.child,
+            e.value,
+            encoderProfile.value,
+            e.onChanged != null
+                ? (v) {
+                    e.onChanged?.call(v);
+                    if (v != null) encoderProfile.value = v;
+                  }
+                : null)),
+      const Divider(color: MyTheme.border),
       for (var e in codecRadios)
         Obx(() => getRadio<String>(
             e.child,
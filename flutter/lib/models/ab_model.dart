@@ -365,6 +365,12 @@ class AbModel {
 
   Future<bool> changeTagForPeers(List<String> ids, List<dynamic> tags) async {
     bool ret = await current.changeTagForPeers(ids, tags);
+    if (ret) {
+      // Also shown in the other peer lists.
+      for (final id in ids) {
+        await bind.mainSetPeerTags(id: id, tags: jsonEncode(tags));
+      }
+    }
     await pullNonLegacyAfterChange();
     currentAbPeers.refresh();
     _saveCache();
@@ -381,6 +387,9 @@ class AbModel {
 
   Future<bool> changeNote({required String id, required String note}) async {
     bool res = await current.changeNote(id: id, note: note);
+    if (res) {
+      await bind.mainSetPeerNote(id: id, note: note);
+    }
     await pullNonLegacyAfterChange();
     currentAbPeers.refresh();
     // no need to save cache
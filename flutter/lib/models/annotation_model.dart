@@ -0,0 +1,123 @@
+import 'dart:convert';
+
+import 'package:flutter/material.dart';
+import 'package:flutter_hbb/consts.dart';
+import 'package:flutter_hbb/models/model.dart';
+import 'package:flutter_hbb/models/platform_model.dart';
+
+enum AnnotationTool { arrow, rect, pen }
+
+/// Draws the arrows, boxes and strokes on the screen of the remote side.
+///
+/// The pointer events of the remote image are taken while it's enabled,
+/// the shape is sent on the pointer up and shown in the video stream.
+class AnnotationModel with ChangeNotifier {
+  WeakReference<FFI> parent;
+  AnnotationModel(this.parent);
+
+  bool _enabled = false;
+  AnnotationTool _tool = AnnotationTool.arrow;
+  // The points of the shape being drawn, in the remote coordinates.
+  final List<List<double>> _points = [];
+
+  bool get enabled => _enabled;
+  AnnotationTool get tool => _tool;
+  int argb = 0xFFFF3B30;
+  double width = 4;
+
+  setEnabled(bool enabled) {
+    final sessionId = parent.target?.sessionId;
+    if (sessionId == null || enabled == _enabled) return;
+    _enabled = enabled;
+    _points.clear();
+    bind.sessionSetAnnotating(sessionId: sessionId, on: enabled);
+    notifyListeners();
+  }
+
+  setTool(AnnotationTool tool) {
+    _tool = tool;
+    notifyListeners();
+  }
+
+  // The remote side replied with the error, it's not annotating.
+  onError() {
+    _enabled = false;
+    _points.clear();
+    notifyListeners();
+  }
+
+  undo() {
+    final sessionId = parent.target?.sessionId;
+    if (sessionId == null) return;
+    bind.sessionUndoAnnotation(sessionId: sessionId);
+  }
+
+  clear() {
+    final sessionId = parent.target?.sessionId;
+    if (sessionId == null) return;
+    bind.sessionClearAnnotations(sessionId: sessionId);
+  }
+
+  List<double>? _toRemote(Offset position) {
+    final pos = parent.target?.inputModel.handlePointerDevicePos(
+        kPointerEventKindMouse,
+        position.dx,
+        position.dy,
+        false,
+        kMouseEventTypeDefault);
+    if (pos == null) return null;
+    return [pos.x.toDouble(), pos.y.toDouble()];
+  }
+
+  onPointerDown(Offset position) {
+    _points.clear();
+    final p = _toRemote(position);
+    if (p != null) _points.add(p);
+  }
+
+  onPointerMove(Offset position) {
+    if (_points.isEmpty) return;
+    final p = _toRemote(position);
+    if (p == null) return;
+    if (_tool == AnnotationTool.pen) {
+      _points.add(p);
+    } else if (_points.length > 1) {
+      _points[1] = p;
+    } else {
+      _points.add(p);
+    }
+  }
+
+  onPointerUp(Offset position) {
+    onPointerMove(position);
+    final sessionId = parent.target?.sessionId;
+    if (sessionId != null && _points.length > 1) {
+      final Map<String, dynamic> shape;
+      switch (_tool) {
+        case AnnotationTool.arrow:
+          shape = {
+            't': 'Arrow',
+            'c': {'from': _points.first, 'to': _points.last}
+          };
+          break;
+        case AnnotationTool.rect:
+          shape = {
+            't': 'Rect',
+            'c': {'from': _points.first, 'to': _points.last}
+          };
+          break;
+        case AnnotationTool.pen:
+          shape = {
+            't': 'Stroke',
+            'c': {'points': _points}
+          };
+          break;
+      }
+      bind.sessionDrawAnnotation(
+          sessionId: sessionId,
+          annotation:
+              jsonEncode({'shape': shape, 'argb': argb, 'width': width}));
+    }
+    _points.clear();
+  }
+}
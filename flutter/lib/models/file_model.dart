@@ -61,6 +61,9 @@ class FileModel {
   SessionID get sessionId => getSessionID();
   late final FileDialogEventLoop evtLoop;
 
+  /// The running folder sync jobs, id to the local and the remote folders.
+  final folderSyncs = <int, (String, String)>{}.obs;
+
   FileModel(this.parent) {
     getSessionID = () => parent.target!.sessionId;
     getDialogManager = () => parent.target?.dialogManager;
@@ -96,6 +99,46 @@ class FileModel {
     await remoteController.close();
   }
 
+  /// Mirror [local] to [remote], again every [intervalMins] if not 0.
+  void startFolderSync(String local, String remote,
+      {int intervalMins = 0, bool delete = false}) {
+    final id = JobController.jobID.next();
+    folderSyncs[id] = (local, remote);
+    bind.sessionStartFolderSync(
+        sessionId: sessionId,
+        actId: id,
+        local: local,
+        remote: remote,
+        intervalMins: intervalMins,
+        delete: delete);
+  }
+
+  void stopFolderSync(int id) {
+    folderSyncs.remove(id);
+    bind.sessionStopFolderSync(sessionId: sessionId, actId: id);
+  }
+
+  void updateFolderSync(Map<String, dynamic> evt) {
+    final status = jsonDecode(evt['status']);
+    final int id = status['id'];
+    if (!folderSyncs.containsKey(id)) return;
+    final String state = status['state'];
+    if (state == 'syncing') return;
+    final int nextRun = status['next_run'];
+    if (nextRun == 0) {
+      folderSyncs.remove(id);
+    }
+    if (state == 'error') {
+      final List<dynamic> errors = status['errors'];
+      debugPrint('Folder sync $id failed: $errors');
+      showToast(
+          '${translate('folder-sync-failed-tip')} ${errors.isEmpty ? '' : errors.first}');
+    } else {
+      showToast(translate('folder-sync-{${status['files']}}-done-tip'));
+    }
+    remoteController.refresh();
+  }
+
   Future<void> refreshAll() async {
     if (!isWeb) await localController.refresh();
     await remoteController.refresh();
@@ -1033,6 +1076,24 @@ class JobController {
     await bind.sessionCancelJob(sessionId: sessionId, actId: id);
   }
 
+  /// Limit the rate of the job in KB/s, 0 for no limit.
+  Future<void> setJobTransferLimit(JobProgress job, int kbps) async {
+    await bind.sessionSetJobTransferLimit(
+        sessionId: sessionId,
+        actId: job.id,
+        isRemote: job.isRemoteToLocal,
+        kbps: kbps);
+    job.rateLimit = kbps;
+    jobTable.refresh();
+  }
+
+  /// Limit the rate of all the jobs together in KB/s, 0 for no limit.
+  Future<void> setTransferLimit(int kbps) async {
+    await bind.mainSetLocalOption(
+        key: kOptionFileTransferRateLimit, value: kbps > 0 ? '$kbps' : '');
+    await bind.sessionSetTransferLimit(sessionId: sessionId, kbps: kbps);
+  }
+
   Future<void> loadLastJob(Map<String, dynamic> evt) async {
     debugPrint("load last job: $evt");
     Map<String, dynamic> jobDetail = json.decode(evt['value']);
@@ -1436,6 +1497,8 @@ class JobProgress {
   var to = "";
   var showHidden = false;
   var err = "";
+  // KB/s, 0 for no limit
+  var rateLimit = 0;
   int lastTransferredSize = 0;
 
   clear() {
@@ -1452,6 +1515,7 @@ class JobProgress {
     remote = "";
     to = "";
     err = "";
+    rateLimit = 0;
   }
 
   String display() {
@@ -355,6 +355,10 @@ class InputModel {
 
   // mouse
   final isPhysicalMouse = false.obs;
+  // The local cursor is pinned and hidden, its movements are sent by Rust.
+  final relativeMouseMode = false.obs;
+  // The full keyboard grab is released by the escape hotkey, until the next click or focus.
+  bool keyboardGrabReleased = false;
   int _lastButtons = 0;
   Offset lastMousePos = Offset.zero;
 
@@ -371,6 +375,11 @@ class InputModel {
   bool get showMyCursor => parent.target!.ffiModel.showMyCursor;
   double get devicePixelRatio => parent.target!.canvasModel.devicePixelRatio;
   bool get isViewCamera => parent.target!.connType == ConnType.viewCamera;
+  bool get isFullKeyboardGrab =>
+      isDesktop &&
+      !isInputSourceFlutter &&
+      bind.sessionGetToggleOptionSync(
+          sessionId: sessionId, arg: kOptionFullKeyboardGrab);
   int get trackpadSpeed => _trackpadSpeed;
   bool get useEdgeScroll => parent.target!.canvasModel.scrollStyle == ScrollStyle.scrolledge;
 
@@ -849,6 +858,23 @@ class InputModel {
         msg: json.encode(modify({'type': type, 'buttons': button.value})));
   }
 
+  void setRelativeMouseMode(bool enabled) {
+    if (enabled == relativeMouseMode.value) return;
+    if (enabled && (isViewOnly || isViewCamera || !keyboardPerm)) return;
+    final ok = bind.sessionSetRelativeMouseMode(
+        sessionId: sessionId, enabled: enabled);
+    if (!enabled) {
+      relativeMouseMode.value = false;
+    } else if (ok) {
+      relativeMouseMode.value = true;
+      // Do not move the mouse to the pinned cursor on the next click.
+      _pointerMovedAfterEnter = true;
+      showToast(translate('relative-mouse-mode-tip'));
+    } else {
+      showToast(translate('relative-mouse-mode-not-supported-tip'));
+    }
+  }
+
   void enterOrLeave(bool enter) {
     toReleaseKeys.release(handleKeyEvent);
     toReleaseRawKeys.release(handleRawKeyEvent);
@@ -859,6 +885,9 @@ class InputModel {
       resetModifiers();
     }
     _flingTimer?.cancel();
+    if (enter) {
+      keyboardGrabReleased = false;
+    }
     if (!isInputSourceFlutter) {
       bind.sessionEnterOrLeave(sessionId: sessionId, enter: enter);
     }
@@ -881,7 +910,12 @@ class InputModel {
   void onPointHoverImage(PointerHoverEvent e) {
     _stopFling = true;
     if (isViewOnly && !showMyCursor) return;
+    if (_isPen(e)) {
+      _handlePen(e, contact: false, isMove: true);
+      return;
+    }
     if (e.kind != ui.PointerDeviceKind.mouse) return;
+    if (relativeMouseMode.value) return;
     if (!isPhysicalMouse.value) {
       isPhysicalMouse.value = true;
     }
@@ -1041,8 +1075,20 @@ class InputModel {
     if (isDesktop) _queryOtherWindowCoords = true;
     _remoteWindowCoords = [];
     _windowRect = null;
+    final annotationModel = parent.target!.annotationModel;
+    if (annotationModel.enabled) {
+      annotationModel.onPointerDown(e.position);
+      return;
+    }
     if (isViewOnly && !showMyCursor) return;
     if (isViewCamera) return;
+    if (keyboardGrabReleased) {
+      enterOrLeave(true);
+    }
+    if (_isPen(e)) {
+      _handlePen(e, contact: true);
+      return;
+    }
     if (e.kind != ui.PointerDeviceKind.mouse) {
       if (isPhysicalMouse.value) {
         isPhysicalMouse.value = false;
@@ -1055,8 +1101,17 @@ class InputModel {
 
   void onPointUpImage(PointerUpEvent e) {
     if (isDesktop) _queryOtherWindowCoords = false;
+    final annotationModel = parent.target!.annotationModel;
+    if (annotationModel.enabled) {
+      annotationModel.onPointerUp(e.position);
+      return;
+    }
     if (isViewOnly && !showMyCursor) return;
     if (isViewCamera) return;
+    if (_isPen(e)) {
+      _handlePen(e, contact: false);
+      return;
+    }
     if (e.kind != ui.PointerDeviceKind.mouse) return;
     if (isPhysicalMouse.value) {
       handleMouse(_getMouseEvent(e, _kMouseEventUp), e.position);
@@ -1064,9 +1119,19 @@ class InputModel {
   }
 
   void onPointMoveImage(PointerMoveEvent e) {
+    final annotationModel = parent.target!.annotationModel;
+    if (annotationModel.enabled) {
+      annotationModel.onPointerMove(e.position);
+      return;
+    }
     if (isViewOnly && !showMyCursor) return;
     if (isViewCamera) return;
+    if (_isPen(e)) {
+      _handlePen(e, contact: e.down, isMove: true);
+      return;
+    }
     if (e.kind != ui.PointerDeviceKind.mouse) return;
+    if (relativeMouseMode.value) return;
     if (_queryOtherWindowCoords) {
       Future.delayed(Duration.zero, () async {
         _windowRect = await fillRemoteCoordsAndGetCurFrame(_remoteWindowCoords);
@@ -1296,6 +1361,51 @@ class InputModel {
     return evt;
   }
 
+  bool _isPen(PointerEvent e) =>
+      (e.kind == ui.PointerDeviceKind.stylus ||
+          e.kind == ui.PointerDeviceKind.invertedStylus) &&
+      parent.target?.ffiModel.pi.isSupportPen == true;
+
+  /// Send the stylus with its pressure and tilt, instead of as the mouse.
+  void _handlePen(PointerEvent e,
+      {required bool contact, bool isMove = false}) {
+    if (isViewOnly || isViewCamera) return;
+    final pos = handlePointerDevicePos(
+      kPointerEventKindMouse,
+      e.position.dx,
+      max(0.0, e.position.dy),
+      isMove,
+      contact ? kMouseEventTypeDefault : kMouseEventTypeUp,
+    );
+    if (pos == null) return;
+    final pressureRange = e.pressureMax - e.pressureMin;
+    var pressure = 0.0;
+    if (contact) {
+      pressure = pressureRange > 0
+          ? (e.pressure - e.pressureMin) / pressureRange
+          : 1.0;
+    }
+    // `tilt` is the angle from the perpendicular,
+    // `orientation` is 0 when the pen points up.
+    final tiltRatio = tan(e.tilt);
+    final tiltX = atan(tiltRatio * sin(e.orientation)) * 180 / pi;
+    final tiltY = -atan(tiltRatio * cos(e.orientation)) * 180 / pi;
+    final inverted = e.kind == ui.PointerDeviceKind.invertedStylus;
+    var flags = kPenFlagInRange;
+    if (contact) flags |= kPenFlagContact;
+    if (e.buttons & kPrimaryStylusButton != 0) flags |= kPenFlagBarrel;
+    if (inverted) flags |= kPenFlagInverted;
+    if (inverted && contact) flags |= kPenFlagEraser;
+    bind.sessionSendPenEvent(
+        sessionId: sessionId,
+        x: pos.x.toInt(),
+        y: pos.y.toInt(),
+        pressure: (pressure.clamp(0.0, 1.0) * kPenMaxPressure).toInt(),
+        tiltX: tiltX.clamp(-90.0, 90.0).toInt(),
+        tiltY: tiltY.clamp(-90.0, 90.0).toInt(),
+        flags: flags);
+  }
+
   Map<String, dynamic>? handleMouse(
     Map<String, dynamic> evt,
     Offset offset, {
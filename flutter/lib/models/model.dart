@@ -13,6 +13,7 @@ import 'package:flutter/scheduler.dart';
 import 'package:flutter_hbb/common/widgets/peers_view.dart';
 import 'package:flutter_hbb/consts.dart';
 import 'package:flutter_hbb/models/ab_model.dart';
+import 'package:flutter_hbb/models/annotation_model.dart';
 import 'package:flutter_hbb/models/chat_model.dart';
 import 'package:flutter_hbb/models/cm_file_model.dart';
 import 'package:flutter_hbb/models/file_model.dart';
@@ -113,6 +114,14 @@ class FfiModel with ChangeNotifier {
 
   var _inputBlocked = false;
   final _permissions = <String, bool>{};
+  // The input control of the simultaneous sessions, empty if not arbitrated.
+  Map<String, dynamic> _inputControl = {};
+  // The system info sent by the host after the login, empty if not permitted.
+  Map<String, dynamic> _systemInfo = {};
+  // The processes of the host in the last reply of the task manager.
+  List<dynamic> _processes = [];
+  // The remote commands by the id, with the output, the exit code and the error.
+  final Map<int, Map<String, dynamic>> _remoteCommands = {};
   bool? _secure;
   bool? _direct;
   bool _touchMode = false;
@@ -145,6 +154,14 @@ class FfiModel with ChangeNotifier {
     _permissions.addAll(permissions);
   }
 
+  Map<String, dynamic> get inputControl => _inputControl;
+  Map<String, dynamic> get systemInfo => _systemInfo;
+  List<dynamic> get processes => _processes;
+  Map<int, Map<String, dynamic>> get remoteCommands => _remoteCommands;
+  bool get inControl =>
+      _inputControl.isEmpty ||
+      _inputControl['controller'] == _inputControl['you'];
+
   bool? get secure => _secure;
 
   bool? get direct => _direct;
@@ -310,10 +327,34 @@ class FfiModel with ChangeNotifier {
         handleToast(evt, sessionId, peerId);
       } else if (name == 'set_multiple_windows_session') {
         handleMultipleWindowsSession(evt, sessionId, peerId);
+      } else if (name == 'update_windows_sessions') {
+        handleUpdateWindowsSessions(evt, sessionId, peerId);
       } else if (name == 'peer_info') {
         handlePeerInfo(evt, peerId, false);
       } else if (name == 'sync_peer_info') {
         handleSyncPeerInfo(evt, sessionId, peerId);
+      } else if (name == 'update_relative_mouse_mode') {
+        parent.target?.inputModel.relativeMouseMode.value =
+            evt['enabled'] == 'true';
+      } else if (name == 'update_session_expiry') {
+        handleSessionExpiry(evt);
+      } else if (name == 'permissions_revoked') {
+        handlePermissionsRevoked(evt);
+      } else if (name == 'update_input_control') {
+        handleInputControl(evt);
+      } else if (name == 'update_system_info') {
+        handleSystemInfo(evt);
+      } else if (name == 'update_task_manager') {
+        handleTaskManager(evt);
+      } else if (name == 'update_remote_command') {
+        handleRemoteCommand(evt);
+      } else if (name == 'update_annotation') {
+        handleAnnotation(evt);
+      } else if (name == 'handoff_token') {
+        handleHandoffToken(evt);
+      } else if (name == 'keyboard_grab_released') {
+        parent.target?.inputModel.keyboardGrabReleased = true;
+        showToast(translate('keyboard-grab-released-{${evt['hotkey']}}-tip'));
       } else if (name == 'sync_platform_additions') {
         handlePlatformAdditions(evt, sessionId, peerId);
       } else if (name == 'connection_ready') {
@@ -360,6 +401,8 @@ class FfiModel with ChangeNotifier {
         parent.target?.fileModel.jobController.jobError(evt);
       } else if (name == 'override_file_confirm') {
         parent.target?.fileModel.postOverrideFileConfirm(evt);
+      } else if (name == 'update_folder_sync') {
+        parent.target?.fileModel.updateFolderSync(evt);
       } else if (name == 'load_last_job') {
         parent.target?.fileModel.jobController.loadLastJob(evt);
       } else if (name == 'update_folder_files') {
@@ -403,8 +446,16 @@ class FfiModel with ChangeNotifier {
         parent.target?.chatModel.onVoiceCallIncoming();
       } else if (name == 'update_voice_call_state') {
         parent.target?.serverModel.updateVoiceCallState(evt);
+      } else if (name == 'update_session_expiry') {
+        parent.target?.serverModel.updateSessionExpiry(evt);
+      } else if (name == 'update_permissions') {
+        parent.target?.serverModel.updatePermissions(evt);
+      } else if (name == 'update_command_requests') {
+        parent.target?.serverModel.updateCommandRequests(evt);
       } else if (name == 'fingerprint') {
         FingerprintState.find(peerId).value = evt['fingerprint'] ?? '';
+      } else if (name == 'session_fingerprint') {
+        SessionFingerprintState.find(peerId).value = evt['fingerprint'] ?? '';
       } else if (name == 'plugin_manager') {
         pluginManager.handleEvent(evt);
       } else if (name == 'plugin_event') {
@@ -849,6 +900,19 @@ class FfiModel with ChangeNotifier {
         type, title, text, dialogManager, sessionId, peerId, sessions);
   }
 
+  handleUpdateWindowsSessions(
+      Map<String, dynamic> evt, SessionID sessionId, String peerId) {
+    if (parent.target == null) return;
+    final dialogManager = parent.target!.dialogManager;
+    final sessions = evt['windows_sessions'];
+    final title = translate('Windows sessions');
+    final text = translate('Please select the session you want to connect to');
+
+    showWindowsSessionsDialog(
+        "", title, text, dialogManager, sessionId, peerId, sessions,
+        cancelable: true);
+  }
+
   /// Handle the message box event based on [evt] and [id].
   handleMsgBox(Map<String, dynamic> evt, SessionID sessionId, String peerId) {
     if (parent.target == null) return;
@@ -874,7 +938,7 @@ class FfiModel with ChangeNotifier {
     } else if (type == 'terminal-admin-login-password') {
       enterUserLoginAndPasswordDialog(
           sessionId, dialogManager, 'terminal-admin-login-tip', false);
-    } else if (type == 'restarting') {
+    } else if (type == 'restarting' || type == 'wait-fido2') {
       showMsgBox(sessionId, type, title, text, link, false, dialogManager,
           hasCancel: false);
     } else if (type == 'wait-remote-accept-nook') {
@@ -899,6 +963,154 @@ class FfiModel with ChangeNotifier {
     }
   }
 
+  handleSessionExpiry(Map<String, dynamic> evt) {
+    final remaining = int.tryParse(evt['remaining'] ?? '') ?? 0;
+    if (remaining > 0) {
+      final minutes = (remaining / 60).ceil();
+      showToast(translate('session-expires-in-{$minutes}-minutes-tip'));
+    } else if (evt['view_only'] == 'true') {
+      showToast(translate('session-expired-view-only-tip'));
+    }
+  }
+
+  handlePermissionsRevoked(Map<String, dynamic> evt) {
+    final permissions =
+        (evt['permissions'] as String? ?? '').replaceAll(',', ', ');
+    final reason = evt['reason'] as String? ?? '';
+    var text = '${translate('permissions-revoked-tip')}: $permissions';
+    if (reason.isNotEmpty) {
+      text += '\n$reason';
+    }
+    showToast(text, timeout: const Duration(seconds: 6));
+  }
+
+  handleHandoffToken(Map<String, dynamic> evt) {
+    final error = evt['error'] ?? '';
+    if (error.isNotEmpty) {
+      showToast(translate(error));
+      return;
+    }
+    final link = evt['link'] ?? '';
+    final expiresIn = evt['expires_in'] ?? '';
+    parent.target?.dialogManager.show((setState, close, context) {
+      return CustomAlertDialog(
+        title: Text(translate('Hand off session')),
+        content: Column(
+          mainAxisSize: MainAxisSize.min,
+          crossAxisAlignment: CrossAxisAlignment.start,
+          children: [
+            Text(translate('handoff-token-{$expiresIn}-tip')),
+            SelectableText(link).marginOnly(top: 12),
+          ],
+        ),
+        actions: [
+          dialogButton('Copy Link', onPressed: () {
+            Clipboard.setData(ClipboardData(text: link));
+            showToast(translate('Copied'));
+          }, isOutline: true),
+          dialogButton('Close', onPressed: close),
+        ],
+        onCancel: close,
+      );
+    });
+  }
+
+  handleSystemInfo(Map<String, dynamic> evt) {
+    try {
+      _systemInfo = jsonDecode(evt['info'] ?? '') as Map<String, dynamic>;
+      notifyListeners();
+    } catch (e) {
+      debugPrint('Failed to decode the system info: $e');
+    }
+  }
+
+  handleTaskManager(Map<String, dynamic> evt) {
+    try {
+      final reply = jsonDecode(evt['reply'] ?? '');
+      final c = reply['c'] as Map<String, dynamic>;
+      final error = c['error'] as String? ?? '';
+      if (error.isNotEmpty) {
+        showToast(translate(error));
+      }
+      if (reply['t'] == 'Processes') {
+        _processes = c['processes'] as List<dynamic>? ?? [];
+      } else if (reply['t'] == 'Killed' && error.isEmpty) {
+        _processes.removeWhere((p) => p['pid'] == c['pid']);
+      }
+      notifyListeners();
+    } catch (e) {
+      debugPrint('Failed to decode the task manager reply: $e');
+    }
+  }
+
+  handleRemoteCommand(Map<String, dynamic> evt) {
+    try {
+      final reply = jsonDecode(evt['reply'] ?? '');
+      final c = reply['c'] as Map<String, dynamic>;
+      final int id = c['id'];
+      final command = _remoteCommands.putIfAbsent(
+          id, () => {'output': '', 'pending': false, 'exited': false});
+      if (reply['t'] == 'Pending') {
+        command['pending'] = true;
+      } else if (reply['t'] == 'Output') {
+        command['pending'] = false;
+        command['output'] += c['data'] ?? '';
+      } else if (reply['t'] == 'Exited') {
+        final error = c['error'] as String? ?? '';
+        if (error.isNotEmpty) {
+          showToast(translate(error));
+        }
+        command['pending'] = false;
+        command['exited'] = true;
+        command['code'] = c['code'];
+        command['error'] = error;
+      }
+      notifyListeners();
+    } catch (e) {
+      debugPrint('Failed to decode the remote command reply: $e');
+    }
+  }
+
+  handleAnnotation(Map<String, dynamic> evt) {
+    try {
+      final reply = jsonDecode(evt['reply'] ?? '');
+      if (reply['t'] == 'Error') {
+        showToast(translate(reply['c']['error'] ?? ''));
+        parent.target?.annotationModel.onError();
+      }
+    } catch (e) {
+      debugPrint('Failed to decode the annotation reply: $e');
+    }
+  }
+
+  handleInputControl(Map<String, dynamic> evt) {
+    try {
+      final state = jsonDecode(evt['state'] ?? '');
+      if (state['t'] != 'State') return;
+      final c = state['c'] as Map<String, dynamic>;
+      _inputControl = c['policy'] == '' ? {} : c;
+      final viewer = c['viewer'] as Map<String, dynamic>? ?? {};
+      final name = viewer['name'] ?? viewer['peer_id'] ?? '';
+      final isMe = viewer['conn_id'] == c['you'];
+      switch (c['event']) {
+        case 'join':
+          if (!isMe) showToast(translate('viewer-joined-{$name}-tip'));
+          break;
+        case 'leave':
+          showToast(translate('viewer-left-{$name}-tip'));
+          break;
+        case 'control':
+          showToast(isMe
+              ? translate('input-control-given-tip')
+              : translate('input-control-of-{$name}-tip'));
+          break;
+      }
+      notifyListeners();
+    } catch (e) {
+      debugPrint('handleInputControl failed: $e');
+    }
+  }
+
   handleToast(Map<String, dynamic> evt, SessionID sessionId, String peerId) {
     final type = evt['type'] ?? 'info';
     final text = evt['text'] ?? '';
@@ -3186,6 +3398,8 @@ class QualityMonitorData {
   String? targetBitrate;
   String? codecFormat;
   String? chroma;
+  String? audioBufferMs;
+  String? frameBufferAllocs;
 }
 
 class QualityMonitorModel with ChangeNotifier {
@@ -3248,6 +3462,14 @@ class QualityMonitorModel with ChangeNotifier {
       if (evt.containsKey('chroma') && (evt['chroma'] as String).isNotEmpty) {
         _data.chroma = evt['chroma'];
       }
+      if (evt.containsKey('audio_buffer_ms') &&
+          (evt['audio_buffer_ms'] as String).isNotEmpty) {
+        _data.audioBufferMs = evt['audio_buffer_ms'];
+      }
+      if (evt.containsKey('frame_buffer_allocs') &&
+          (evt['frame_buffer_allocs'] as String).isNotEmpty) {
+        _data.frameBufferAllocs = evt['frame_buffer_allocs'];
+      }
       notifyListeners();
     } catch (e) {
       //
@@ -3331,6 +3553,7 @@ class FFI {
   late final RecordingModel recordingModel; // session
   late final InputModel inputModel; // session
   late final ElevationModel elevationModel; // session
+  late final AnnotationModel annotationModel; // session
   late final CmFileModel cmFileModel; // cm
   late final TextureModel textureModel; //session
   late final Peers recentPeersModel; // global
@@ -3360,6 +3583,7 @@ class FFI {
     recordingModel = RecordingModel(WeakReference(this));
     inputModel = InputModel(WeakReference(this));
     elevationModel = ElevationModel(WeakReference(this));
+    annotationModel = AnnotationModel(WeakReference(this));
     cmFileModel = CmFileModel(WeakReference(this));
     textureModel = TextureModel(WeakReference(this));
     recentPeersModel = Peers(
@@ -3761,6 +3985,8 @@ class PeerInfo with ChangeNotifier {
 
   bool get isWayland => platformAdditions[kPlatformAdditionsIsWayland] == true;
   bool get isHeadless => platformAdditions[kPlatformAdditionsHeadless] == true;
+  bool get isSupportPen =>
+      platformAdditions[kPlatformAdditionsSupportPen] == true;
   bool get isInstalled =>
       platform != kPeerPlatformWindows ||
       platformAdditions[kPlatformAdditionsIsInstalled] == true;
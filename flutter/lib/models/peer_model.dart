@@ -38,7 +38,10 @@ class Peer {
         hostname = json['hostname'] ?? '',
         platform = json['platform'] ?? '',
         alias = json['alias'] ?? '',
-        tags = json['tags'] ?? [],
+        // A JSON list in the local peers.
+        tags = json['tags'] is String
+            ? _decodeTags(json['tags'])
+            : json['tags'] ?? [],
         forceAlwaysRelay = json['forceAlwaysRelay'] == 'true',
         rdpPort = json['rdpPort'] ?? '',
         rdpUsername = json['rdpUsername'] ?? '',
@@ -47,6 +50,14 @@ class Peer {
         note = json['note'] is String ? json['note'] : '',
         sameServer = json['same_server'];
 
+  static List<dynamic> _decodeTags(String tags) {
+    try {
+      return jsonDecode(tags) as List<dynamic>;
+    } catch (_) {
+      return [];
+    }
+  }
+
   Map<String, dynamic> toJson() {
     return <String, dynamic>{
       "id": id,
@@ -643,6 +643,51 @@ class ServerModel with ChangeNotifier {
     );
   }
 
+  String getCommandDialogTag(int id, int commandId) =>
+      'remote-command-$id-$commandId';
+
+  handleRemoteCommand(Client client, int commandId, bool approved) {
+    bind.cmHandleRemoteCommand(
+        id: client.id, commandId: commandId, approved: approved);
+  }
+
+  showCommandDialog(Client client, Map<String, dynamic> request) {
+    final int commandId = request['id'];
+    final args = (request['args'] as List<dynamic>? ?? []).join(' ');
+    final String cwd = request['cwd'] ?? '';
+    parent.target?.dialogManager.show((setState, close, context) {
+      cancel() {
+        handleRemoteCommand(client, commandId, false);
+        close();
+      }
+
+      submit() {
+        handleRemoteCommand(client, commandId, true);
+        close();
+      }
+
+      return CustomAlertDialog(
+        title: Text(translate('Remote command')),
+        content: Column(
+          mainAxisSize: MainAxisSize.min,
+          crossAxisAlignment: CrossAxisAlignment.start,
+          children: [
+            ClientInfo(client),
+            SelectableText('${request['command']} $args'.trim(),
+                style: const TextStyle(fontFamily: 'monospace')),
+            if (cwd.isNotEmpty) Text('${translate('Directory')}: $cwd'),
+          ],
+        ),
+        actions: [
+          dialogButton("Dismiss", onPressed: cancel, isOutline: true),
+          dialogButton("Accept", onPressed: submit),
+        ],
+        onSubmit: submit,
+        onCancel: cancel,
+      );
+    }, tag: getCommandDialogTag(client.id, commandId));
+  }
+
   showClientDialog(Client client, String title, String contentTitle,
       String content, VoidCallback onCancel, VoidCallback onSubmit) {
     parent.target?.dialogManager.show((setState, close, context) {
@@ -785,6 +830,80 @@ class ServerModel with ChangeNotifier {
     }
   }
 
+  void updateSessionExpiry(Map<String, dynamic> evt) {
+    try {
+      final client = Client.fromJson(jsonDecode(evt["client"]));
+      final index = _clients.indexWhere((element) => element.id == client.id);
+      if (index != -1) {
+        _clients[index].expireTime = client.expireTime;
+        _clients[index].expired = client.expired;
+        if (client.expired) {
+          _clients[index].keyboard = client.keyboard;
+          _clients[index].clipboard = client.clipboard;
+          _clients[index].audio = client.audio;
+          _clients[index].file = client.file;
+          _clients[index].restart = client.restart;
+          _clients[index].recording = client.recording;
+          _clients[index].blockInput = client.blockInput;
+        }
+        notifyListeners();
+      }
+    } catch (e) {
+      debugPrint("updateSessionExpiry failed: $e");
+    }
+  }
+
+  void updatePermissions(Map<String, dynamic> evt) {
+    try {
+      final client = Client.fromJson(jsonDecode(evt["client"]));
+      final index = _clients.indexWhere((element) => element.id == client.id);
+      if (index != -1) {
+        _clients[index].keyboard = client.keyboard;
+        _clients[index].clipboard = client.clipboard;
+        _clients[index].audio = client.audio;
+        _clients[index].file = client.file;
+        _clients[index].restart = client.restart;
+        _clients[index].recording = client.recording;
+        _clients[index].blockInput = client.blockInput;
+        _clients[index].inControl = client.inControl;
+        notifyListeners();
+      }
+    } catch (e) {
+      debugPrint("updatePermissions failed: $e");
+    }
+  }
+
+  void updateCommandRequests(Map<String, dynamic> evt) {
+    try {
+      final client = Client.fromJson(jsonDecode(evt["client"]));
+      final index = _clients.indexWhere((element) => element.id == client.id);
+      if (index != -1) {
+        final shown =
+            _clients[index].commandRequests.map((r) => r['id']).toSet();
+        final pending = client.commandRequests.map((r) => r['id']).toSet();
+        _clients[index].commandRequests = client.commandRequests;
+        // The ones cancelled by the peer.
+        for (final commandId in shown.difference(pending)) {
+          parent.target?.dialogManager
+              .dismissByTag(getCommandDialogTag(client.id, commandId));
+        }
+        for (final request in client.commandRequests) {
+          if (!shown.contains(request['id'])) {
+            showCommandDialog(_clients[index], request);
+          }
+        }
+        if (pending.difference(shown).isNotEmpty && !isAndroid) {
+          Future.delayed(Duration.zero, () {
+            windowOnTop(null);
+          });
+        }
+        notifyListeners();
+      }
+    } catch (e) {
+      debugPrint("updateCommandRequests failed: $e");
+    }
+  }
+
   void androidUpdatekeepScreenOn() async {
     if (!isAndroid) return;
     var floatingWindowDisabled =
@@ -835,6 +954,15 @@ class Client {
   bool fromSwitch = false;
   bool inVoiceCall = false;
   bool incomingVoiceCall = false;
+  // The unix time in seconds when the session expires, 0 if no time limit.
+  int expireTime = 0;
+  bool expired = false;
+  // The fingerprint of the end-to-end key of the session, empty if not secured.
+  String sessionFingerprint = '';
+  // Whether it controls the input if only one of the sessions does.
+  bool inControl = false;
+  // The remote commands waiting for the consent.
+  List<dynamic> commandRequests = [];
 
   RxInt unreadChatMessageCount = 0.obs;
 
@@ -862,6 +990,11 @@ class Client {
     fromSwitch = json['from_switch'];
     inVoiceCall = json['in_voice_call'];
     incomingVoiceCall = json['incoming_voice_call'];
+    expireTime = json['expire_time'] ?? 0;
+    expired = json['expired'] ?? false;
+    sessionFingerprint = json['session_fingerprint'] ?? '';
+    inControl = json['in_control'] ?? false;
+    commandRequests = json['command_requests'] ?? [];
   }
 
   Map<String, dynamic> toJson() {
@@ -885,6 +1018,11 @@ class Client {
     data['from_switch'] = fromSwitch;
     data['in_voice_call'] = inVoiceCall;
     data['incoming_voice_call'] = incomingVoiceCall;
+    data['expire_time'] = expireTime;
+    data['expired'] = expired;
+    data['session_fingerprint'] = sessionFingerprint;
+    data['in_control'] = inControl;
+    data['command_requests'] = commandRequests;
     return data;
   }
 
@@ -305,6 +305,85 @@ class RustdeskImpl {
         () => js.context.callMethod('setByName', ['image_quality', value]));
   }
 
+  Future<String?> sessionGetEncoderProfile(
+      {required UuidValue sessionId, dynamic hint}) {
+    return Future(() => js.context
+        .callMethod('getByName', ['option:session', 'encoder-profile']));
+  }
+
+  Future<void> sessionSetEncoderProfile(
+      {required UuidValue sessionId, required String value, dynamic hint}) {
+    return Future(() => js.context.callMethod('setByName', [
+          'option:session',
+          jsonEncode({'name': 'encoder-profile', 'value': value})
+        ]));
+  }
+
+  Future<String?> sessionGetAudioJitterBuffer(
+      {required UuidValue sessionId, dynamic hint}) {
+    throw UnimplementedError("sessionGetAudioJitterBuffer");
+  }
+
+  Future<void> sessionSetAudioJitterBuffer(
+      {required UuidValue sessionId,
+      required int targetMs,
+      required int maxMs,
+      dynamic hint}) {
+    throw UnimplementedError("sessionSetAudioJitterBuffer");
+  }
+
+  Future<void> sessionListAudioSources(
+      {required UuidValue sessionId, dynamic hint}) {
+    throw UnimplementedError("sessionListAudioSources");
+  }
+
+  Future<void> sessionSelectAudioSource(
+      {required UuidValue sessionId, required int pid, dynamic hint}) {
+    throw UnimplementedError("sessionSelectAudioSource");
+  }
+
+  Future<String> mainGetLocalGamepads({dynamic hint}) {
+    return Future.value('[]');
+  }
+
+  Future<void> sessionSetGamepadForwarding(
+      {required UuidValue sessionId, required bool enabled, dynamic hint}) {
+    throw UnimplementedError("sessionSetGamepadForwarding");
+  }
+
+  bool sessionSetRelativeMouseMode(
+      {required UuidValue sessionId, required bool enabled, dynamic hint}) {
+    return false;
+  }
+
+  Future<void> sessionSendPenEvent(
+      {required UuidValue sessionId,
+      required int x,
+      required int y,
+      required int pressure,
+      required int tiltX,
+      required int tiltY,
+      required int flags,
+      dynamic hint}) {
+    throw UnimplementedError("sessionSendPenEvent");
+  }
+
+  Future<String?> sessionGetDisplaySettings(
+      {required UuidValue sessionId, dynamic hint}) {
+    return Future(() => js.context
+        .callMethod('getByName', ['option:session', 'display-settings']));
+  }
+
+  Future<void> sessionSetDisplaySettings(
+      {required UuidValue sessionId,
+      required int display,
+      required int quality,
+      required int fps,
+      required String codec,
+      dynamic hint}) {
+    throw UnimplementedError("sessionSetDisplaySettings");
+  }
+
   Future<String?> sessionGetKeyboardMode(
       {required UuidValue sessionId, dynamic hint}) {
     final mode =
@@ -487,6 +566,101 @@ class RustdeskImpl {
         () => js.context.callMethod('setByName', ['send_chat', text]));
   }
 
+  Future<void> sessionRequestHandoff(
+      {required UuidValue sessionId, dynamic hint}) {
+    throw UnimplementedError("sessionRequestHandoff");
+  }
+
+  Future<void> sessionPassInputControl(
+      {required UuidValue sessionId, required int to, dynamic hint}) {
+    throw UnimplementedError("sessionPassInputControl");
+  }
+
+  Future<void> sessionAddVirtualMonitor(
+      {required UuidValue sessionId,
+      required int width,
+      required int height,
+      dynamic hint}) {
+    throw UnimplementedError("sessionAddVirtualMonitor");
+  }
+
+  Future<void> sessionRequestWindowsSessions(
+      {required UuidValue sessionId, dynamic hint}) {
+    throw UnimplementedError("sessionRequestWindowsSessions");
+  }
+
+  Future<void> sessionRequestProcesses(
+      {required UuidValue sessionId, dynamic hint}) {
+    throw UnimplementedError("sessionRequestProcesses");
+  }
+
+  Future<void> sessionKillProcess(
+      {required UuidValue sessionId, required int pid, dynamic hint}) {
+    throw UnimplementedError("sessionKillProcess");
+  }
+
+  int sessionRunRemoteCommand(
+      {required UuidValue sessionId,
+      required String command,
+      required List<String> args,
+      required String cwd,
+      dynamic hint}) {
+    return 0;
+  }
+
+  Future<void> sessionCancelRemoteCommand(
+      {required UuidValue sessionId, required int id, dynamic hint}) {
+    throw UnimplementedError("sessionCancelRemoteCommand");
+  }
+
+  Future<void> sessionSetAnnotating(
+      {required UuidValue sessionId, required bool on, dynamic hint}) {
+    throw UnimplementedError("sessionSetAnnotating");
+  }
+
+  Future<void> sessionDrawAnnotation(
+      {required UuidValue sessionId,
+      required String annotation,
+      dynamic hint}) {
+    throw UnimplementedError("sessionDrawAnnotation");
+  }
+
+  Future<void> sessionUndoAnnotation(
+      {required UuidValue sessionId, dynamic hint}) {
+    throw UnimplementedError("sessionUndoAnnotation");
+  }
+
+  Future<void> sessionClearAnnotations(
+      {required UuidValue sessionId, dynamic hint}) {
+    throw UnimplementedError("sessionClearAnnotations");
+  }
+
+  Future<void> sessionSetPriorityRegion(
+      {required UuidValue sessionId,
+      required int display,
+      required int x,
+      required int y,
+      required int w,
+      required int h,
+      dynamic hint}) {
+    throw UnimplementedError("sessionSetPriorityRegion");
+  }
+
+  Future<void> sessionStartLatencyProbe(
+      {required UuidValue sessionId, dynamic hint}) {
+    throw UnimplementedError("sessionStartLatencyProbe");
+  }
+
+  Future<void> sessionStopLatencyProbe(
+      {required UuidValue sessionId, dynamic hint}) {
+    throw UnimplementedError("sessionStopLatencyProbe");
+  }
+
+  String sessionGetLatencyReport(
+      {required UuidValue sessionId, dynamic hint}) {
+    return '';
+  }
+
   Future<void> sessionPeerOption(
       {required UuidValue sessionId,
       required String name,
@@ -795,6 +969,14 @@ class RustdeskImpl {
     return Future.value('');
   }
 
+  Future<String> mainTestProxy(
+      {required String proxy,
+      required String username,
+      required String password,
+      dynamic hint}) {
+    throw UnimplementedError("mainTestProxy");
+  }
+
   Future<void> mainSetSocks(
       {required String proxy,
       required String username,
@@ -940,6 +1122,10 @@ class RustdeskImpl {
     return inputSource != '' ? inputSource : 'Input source 1';
   }
 
+  String mainGetKeyboardGrabEscapeHotkey({dynamic hint}) {
+    return '';
+  }
+
   Future<void> mainSetInputSource(
       {required UuidValue sessionId, required String value, dynamic hint}) {
     return Future(() => js.context.callMethod('setByName', [
@@ -1012,6 +1198,31 @@ class RustdeskImpl {
     return Future.value();
   }
 
+  Future<void> mainSetPeerNote(
+      {required String id, required String note, dynamic hint}) {
+    mainSetPeerOptionSync(id: id, key: 'note', value: note, hint: hint);
+    return Future.value();
+  }
+
+  Future<void> mainSetPeerTags(
+      {required String id, required String tags, dynamic hint}) {
+    mainSetPeerOptionSync(id: id, key: 'tags', value: tags, hint: hint);
+    return Future.value();
+  }
+
+  String mainGetPeerTagColors({dynamic hint}) {
+    return '{}';
+  }
+
+  Future<void> mainSetPeerTagColor(
+      {required String tag, required int color, dynamic hint}) {
+    throw UnimplementedError("mainSetPeerTagColor");
+  }
+
+  Future<void> mainRemovePeerTagColor({required String tag, dynamic hint}) {
+    throw UnimplementedError("mainRemovePeerTagColor");
+  }
+
   Future<String> mainGetNewStoredPeers({dynamic hint}) {
     throw UnimplementedError("mainGetNewStoredPeers");
   }
@@ -1135,6 +1346,14 @@ class RustdeskImpl {
     throw UnimplementedError("cmHandleIncomingVoiceCall");
   }
 
+  Future<void> cmHandleRemoteCommand(
+      {required int id,
+      required int commandId,
+      required bool approved,
+      dynamic hint}) {
+    throw UnimplementedError("cmHandleRemoteCommand");
+  }
+
   Future<void> cmCloseVoiceCall({required int id, dynamic hint}) {
     throw UnimplementedError("cmCloseVoiceCall");
   }
@@ -1367,6 +1586,14 @@ class RustdeskImpl {
     throw UnimplementedError("mainWol");
   }
 
+  Future<int> mainWolViaPeers({required String id, dynamic hint}) {
+    throw UnimplementedError("mainWolViaPeers");
+  }
+
+  Future<void> mainSetPrewarmPeers({required List<String> ids, dynamic hint}) {
+    throw UnimplementedError("mainSetPrewarmPeers");
+  }
+
   Future<void> mainCreateShortcut({required String id, dynamic hint}) {
     throw UnimplementedError("mainCreateShortcut");
   }
@@ -1406,6 +1633,10 @@ class RustdeskImpl {
     throw UnimplementedError("cmSwitchPermission");
   }
 
+  Future<void> cmAssignInputControl({required int connId, dynamic hint}) {
+    throw UnimplementedError("cmAssignInputControl");
+  }
+
   bool cmCanElevate({dynamic hint}) {
     throw UnimplementedError("cmCanElevate");
   }
@@ -1747,6 +1978,49 @@ class RustdeskImpl {
     throw UnimplementedError("mainHasValid2FaSync");
   }
 
+  String mainGetPermissionProfiles({dynamic hint}) {
+    throw UnimplementedError("mainGetPermissionProfiles");
+  }
+
+  String mainCheckAccessWindows(
+      {required String windows, required String timezone, dynamic hint}) {
+    throw UnimplementedError("mainCheckAccessWindows");
+  }
+
+  Future<void> mainSetPermissionProfileAssignment(
+      {required String kind,
+      required String key,
+      required String profile,
+      dynamic hint}) {
+    throw UnimplementedError("mainSetPermissionProfileAssignment");
+  }
+
+  Future<String> mainGetAuditLog({required String query, dynamic hint}) {
+    throw UnimplementedError("mainGetAuditLog");
+  }
+
+  Future<String> mainGetConnectionHistory(
+      {required String query, dynamic hint}) {
+    throw UnimplementedError("mainGetConnectionHistory");
+  }
+
+  String mainGetFido2Keys({dynamic hint}) {
+    throw UnimplementedError("mainGetFido2Keys");
+  }
+
+  Future<String> mainRegisterFido2Key(
+      {required String name, required String pin, dynamic hint}) {
+    throw UnimplementedError("mainRegisterFido2Key");
+  }
+
+  Future<void> mainRemoveFido2Key({required String name, dynamic hint}) {
+    throw UnimplementedError("mainRemoveFido2Key");
+  }
+
+  Future<String> mainRotateKeyPair({required bool force, dynamic hint}) {
+    throw UnimplementedError("mainRotateKeyPair");
+  }
+
   String mainGetHardOption({required String key, dynamic hint}) {
     throw UnimplementedError("mainGetHardOption");
   }
@@ -1870,6 +2144,36 @@ class RustdeskImpl {
         ]));
   }
 
+  Future<void> sessionStartFolderSync(
+      {required UuidValue sessionId,
+      required int actId,
+      required String local,
+      required String remote,
+      required int intervalMins,
+      required bool delete,
+      dynamic hint}) {
+    throw UnimplementedError("sessionStartFolderSync");
+  }
+
+  Future<void> sessionStopFolderSync(
+      {required UuidValue sessionId, required int actId, dynamic hint}) {
+    throw UnimplementedError("sessionStopFolderSync");
+  }
+
+  Future<void> sessionSetTransferLimit(
+      {required UuidValue sessionId, required int kbps, dynamic hint}) {
+    throw UnimplementedError("sessionSetTransferLimit");
+  }
+
+  Future<void> sessionSetJobTransferLimit(
+      {required UuidValue sessionId,
+      required int actId,
+      required bool isRemote,
+      required int kbps,
+      dynamic hint}) {
+    throw UnimplementedError("sessionSetJobTransferLimit");
+  }
+
   Future<void> sessionSelectFiles(
       {required UuidValue sessionId, dynamic hint}) {
     return Future(() => js.context.callMethod('setByName', ['select_files']));
@@ -1879,6 +2183,11 @@ class RustdeskImpl {
     throw UnimplementedError("sessionGetConnToken");
   }
 
+  String sessionGetSessionFingerprint(
+      {required UuidValue sessionId, dynamic hint}) {
+    return '';
+  }
+
   String mainGetPrinterNames({dynamic hint}) {
     return '';
   }
@@ -226,6 +226,22 @@ fn ffmpeg() {
 }
 */
 
+/// The ScreenCaptureKit shim, the framework is linked weakly for macOS before 13.
+fn build_screen_capture_kit() {
+    let src = "src/quartz/screencapturekit.m";
+    println!("cargo:rerun-if-changed={}", src);
+    cc::Build::new()
+        .file(src)
+        .flag("-fobjc-arc")
+        .flag("-fmodules")
+        .compile("screencapturekit");
+    for framework in ["CoreMedia", "CoreVideo", "IOSurface"] {
+        println!("cargo:rustc-link-lib=framework={}", framework);
+    }
+    // Only for the targets of scrap, the dependents link it weakly themselves.
+    println!("cargo:rustc-link-arg=-Wl,-weak_framework,ScreenCaptureKit");
+}
+
 fn main() {
     // in this crate, these are also valid configurations
     println!("cargo:rustc-check-cfg=cfg(dxgi,quartz,x11)");
@@ -260,6 +276,7 @@ fn main() {
     } else if cfg!(target_os = "macos") {
         // Quartz is second because macOS is the (annoying) exception.
         println!("cargo:rustc-cfg=quartz");
+        build_screen_capture_kit();
     } else if cfg!(unix) {
         // On UNIX we pray that X11 (with XCB) is available.
         println!("cargo:rustc-cfg=x11");
@@ -6,7 +6,7 @@
 
 include!(concat!(env!("OUT_DIR"), "/aom_ffi.rs"));
 
-use crate::codec::{base_bitrate, codec_thread_num};
+use crate::codec::{base_bitrate, codec_thread_num, EncoderProfile};
 use crate::{codec::EncoderApi, EncodeFrame, STRIDE_ALIGN};
 use crate::{common::GoogleImage, generate_call_macro, generate_call_ptr_macro, Error, Result};
 use crate::{EncodeInput, EncodeYuvFormat, Pixfmt};
@@ -47,6 +47,7 @@ pub struct AomEncoderConfig {
     pub height: u32,
     pub quality: f32,
     pub keyframe_interval: Option<usize>,
+    pub profile: EncoderProfile,
 }
 
 pub struct AomEncoder {
@@ -55,6 +56,7 @@ pub struct AomEncoder {
     height: usize,
     i444: bool,
     yuvfmt: EncodeYuvFormat,
+    force_keyframe: bool,
 }
 
 // https://webrtc.googlesource.com/src/+/refs/heads/main/modules/video_coding/codecs/av1/libaom_av1_encoder.cc
@@ -122,7 +124,11 @@ mod webrtc {
         c.g_usage = kUsageProfile;
         c.g_error_resilient = 0;
         // Low-latency settings.
-        c.rc_end_usage = aom_rc_mode::AOM_CBR; // Constant Bit Rate (CBR) mode
+        c.rc_end_usage = if cfg.profile.vbr() {
+            aom_rc_mode::AOM_VBR
+        } else {
+            aom_rc_mode::AOM_CBR // Constant Bit Rate (CBR) mode
+        };
         c.g_pass = aom_enc_pass::AOM_RC_ONE_PASS; // One-pass rate control
         c.g_lag_in_frames = kLagInFrames; // No look ahead when lag equals 0.
 
@@ -132,7 +138,11 @@ mod webrtc {
         Ok(c)
     }
 
-    pub fn set_controls(ctx: *mut aom_codec_ctx_t, cfg: &aom_codec_enc_cfg) -> ResultType<()> {
+    pub fn set_controls(
+        ctx: *mut aom_codec_ctx_t,
+        cfg: &aom_codec_enc_cfg,
+        profile: EncoderProfile,
+    ) -> ResultType<()> {
         use aom_tune_content::*;
         use aome_enc_control_id::*;
         macro_rules! call_ctl {
@@ -141,7 +151,8 @@ mod webrtc {
             }};
         }
 
-        call_ctl!(ctx, AOME_SET_CPUUSED, get_cpu_speed(cfg.g_w, cfg.g_h));
+        let speed = (get_cpu_speed(cfg.g_w, cfg.g_h) as i32 + profile.speed_offset()).clamp(6, 10);
+        call_ctl!(ctx, AOME_SET_CPUUSED, speed);
         call_ctl!(ctx, AV1E_SET_ENABLE_CDEF, 1);
         call_ctl!(ctx, AV1E_SET_ENABLE_TPL_MODEL, 0);
         call_ctl!(ctx, AV1E_SET_DELTAQ_MODE, 0);
@@ -219,13 +230,14 @@ impl EncoderApi for AomEncoder {
                     flags,
                     AOM_ENCODER_ABI_VERSION as _
                 ));
-                webrtc::set_controls(&mut ctx, &c)?;
+                webrtc::set_controls(&mut ctx, &c, config.profile)?;
                 Ok(Self {
                     ctx,
                     width: config.width as _,
                     height: config.height as _,
                     i444,
                     yuvfmt: Self::get_yuvfmt(config.width, config.height, i444),
+                    force_keyframe: false,
                 })
             }
             _ => Err(anyhow!("encoder type mismatch")),
@@ -284,6 +296,11 @@ impl EncoderApi for AomEncoder {
     }
 
     fn disable(&self) {}
+
+    fn request_keyframe(&mut self) -> bool {
+        self.force_keyframe = true;
+        true
+    }
 }
 
 impl AomEncoder {
@@ -309,12 +326,17 @@ impl AomEncoder {
         ));
         let pts = webrtc::kTimeBaseDen / 1000 * ms;
         let duration = webrtc::kTimeBaseDen / 1000;
+        let flags = if std::mem::take(&mut self.force_keyframe) {
+            AOM_EFLAG_FORCE_KF
+        } else {
+            0
+        };
         call_aom!(aom_codec_encode(
             &mut self.ctx,
             &image,
             pts as _,
             duration as _, // Duration
-            0,             // Flags
+            flags as _,
         ));
 
         Ok(EncodeFrames {
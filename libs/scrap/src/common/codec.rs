@@ -15,7 +15,7 @@ use crate::{
     aom::{self, AomDecoder, AomEncoder, AomEncoderConfig},
     common::GoogleImage,
     vpxcodec::{self, VpxDecoder, VpxDecoderConfig, VpxEncoder, VpxEncoderConfig, VpxVideoCodecId},
-    CodecFormat, EncodeInput, EncodeYuvFormat, ImageRgb, ImageTexture,
+    CodecFormat, DamageRect, EncodeInput, EncodeYuvFormat, ImageRgb, ImageTexture,
 };
 
 #[cfg(any(
@@ -32,8 +32,9 @@ use hbb_common::{
     lazy_static, log,
     message_proto::{
         supported_decoding::PreferCodec, video_frame, Chroma, CodecAbility, EncodedVideoFrames,
-        SupportedDecoding, SupportedEncoding, VideoFrame,
+        OptionMessage, SupportedDecoding, SupportedEncoding, VideoFrame,
     },
+    serde_derive::Serialize,
     sysinfo::System,
     ResultType,
 };
@@ -80,6 +81,20 @@ pub trait EncoderApi {
     fn is_hardware(&self) -> bool;
 
     fn disable(&self);
+
+    /// The changed regions of the next frame, None if unknown.
+    fn set_damage(&mut self, _damage: Option<&[DamageRect]>) {}
+
+    /// The region of the next frames to encode in a higher quality than the rest at the same
+    /// bitrate, None to encode the frame evenly. Returns false if it is not supported.
+    fn set_priority_region(&mut self, _region: Option<DamageRect>) -> bool {
+        false
+    }
+
+    /// Encode the next frame as a keyframe, false if it is not supported.
+    fn request_keyframe(&mut self) -> bool {
+        false
+    }
 }
 
 pub struct Encoder {
@@ -230,6 +245,12 @@ impl Encoder {
                     HwRamEncoder::try_get(CodecFormat::H265).map_or(None, |c| Some(c.name));
             }
         }
+        #[allow(unused_mut)]
+        let mut av1hw_encoding = false;
+        #[cfg(feature = "hwcodec")]
+        if enable_hwcodec_option() && av1_useable {
+            av1hw_encoding = HwRamEncoder::try_get(CodecFormat::AV1).is_some();
+        }
         let h264_useable =
             _all_support_h264_decoding && (h264vram_encoding || h264hw_encoding.is_some());
         let h265_useable =
@@ -267,10 +288,18 @@ impl Encoder {
             .find(|(_, count)| *count == max_count)
             .unwrap_or((PreferCodec::Auto.into(), 0));
         let preference = most_frequent.enum_value_or(PreferCodec::Auto);
+        // 4:4:4 is only supported by the software vp9 and av1 encoders.
+        let prefer_i444 = decodings.len() > 0
+            && decodings
+                .iter()
+                .all(|(_, s)| s.prefer_chroma == Chroma::I444.into());
+        let i444_vp9_useable = decodings.iter().all(|(_, s)| s.i444.vp9);
+        let i444_av1_useable = av1_useable && decodings.iter().all(|(_, s)| s.i444.av1);
 
         // auto: h265 > h264 > av1/vp9/vp8
+        // The speed test is for the software encoder, the hardware one is fast enough.
         let av1_test = Config::get_option(hbb_common::config::keys::OPTION_AV1_TEST) != "N";
-        let mut auto_codec = if av1_useable && av1_test {
+        let mut auto_codec = if av1_useable && (av1_test || av1hw_encoding) {
             CodecFormat::AV1
         } else {
             CodecFormat::VP9
@@ -281,7 +310,20 @@ impl Encoder {
         if h265_useable {
             auto_codec = CodecFormat::H265;
         }
-        if auto_codec == CodecFormat::VP9 || auto_codec == CodecFormat::AV1 {
+        let auto_i444 = prefer_i444 && (i444_vp9_useable || i444_av1_useable);
+        if auto_i444 {
+            auto_codec = if i444_av1_useable && av1_test {
+                CodecFormat::AV1
+            } else if i444_vp9_useable {
+                CodecFormat::VP9
+            } else {
+                CodecFormat::AV1
+            };
+        }
+        if !auto_i444
+            && (auto_codec == CodecFormat::VP9
+                || (auto_codec == CodecFormat::AV1 && !av1hw_encoding))
+        {
             let mut system = System::new();
             system.refresh_memory();
             if vp8_useable && system.total_memory() <= 4 * 1024 * 1024 * 1024 {
@@ -312,7 +354,7 @@ impl Encoder {
         };
         if decodings.len() > 0 {
             log::info!(
-                "usable: vp8={vp8_useable}, av1={av1_useable}, h264={h264_useable}, h265={h265_useable}",
+                "usable: vp8={vp8_useable}, av1={av1_useable}, h264={h264_useable}, h265={h265_useable}, av1hw={av1hw_encoding}, prefer_i444={prefer_i444}",
             );
             log::info!(
                 "connection count: {}, used preference: {:?}, encoder: {:?}",
@@ -358,6 +400,21 @@ impl Encoder {
         USABLE_ENCODING.lock().unwrap().clone()
     }
 
+    /// Whether all the peers can decode the codec and there is an encoder for it.
+    pub fn usable(format: CodecFormat) -> bool {
+        let Some(usable) = Self::usable_encoding() else {
+            return format == CodecFormat::VP9;
+        };
+        match format {
+            CodecFormat::VP8 => usable.vp8,
+            CodecFormat::VP9 => true,
+            CodecFormat::AV1 => usable.av1,
+            CodecFormat::H264 => usable.h264,
+            CodecFormat::H265 => usable.h265,
+            CodecFormat::Unknown => false,
+        }
+    }
+
     pub fn set_fallback(config: &EncoderCfg) {
         let format = match config {
             EncoderCfg::VPX(vpx) => match vpx.codec {
@@ -400,6 +457,15 @@ impl Encoder {
         }
     }
 
+    // All peers prefer 4:4:4.
+    pub fn prefer_i444() -> bool {
+        let decodings = PEER_DECODINGS.lock().unwrap();
+        !decodings.is_empty()
+            && decodings
+                .iter()
+                .all(|d| d.1.prefer_chroma == Chroma::I444.into())
+    }
+
     pub fn use_i444(config: &EncoderCfg) -> bool {
         let decodings = PEER_DECODINGS.lock().unwrap().clone();
         let prefer_i444 = decodings
@@ -408,7 +474,14 @@ impl Encoder {
         let i444_useable = match config {
             EncoderCfg::VPX(vpx) => match vpx.codec {
                 VpxVideoCodecId::VP8 => false,
-                VpxVideoCodecId::VP9 => decodings.iter().all(|d| d.1.i444.vp9),
+                VpxVideoCodecId::VP9 => {
+                    let useable = decodings.iter().all(|d| d.1.i444.vp9);
+                    // The chroma subsampling of i420 is lossy.
+                    if vpx.lossless && useable && !decodings.is_empty() {
+                        return true;
+                    }
+                    useable
+                }
             },
             EncoderCfg::AOM(_) => decodings.iter().all(|d| d.1.i444.av1),
             #[cfg(feature = "hwcodec")]
@@ -890,6 +963,265 @@ pub fn allow_d3d_render() -> bool {
     option2bool(OPTION, &hbb_common::config::LocalConfig::get_option(OPTION))
 }
 
+/// The ordered preference of the hardware encoders, e.g. "nvenc,qsv,amf,software".
+/// The encoders after "software" and the ones not listed are not used, empty means automatic.
+pub const OPTION_ENCODER_PREFERENCE: &str = "encoder-preference";
+pub const ENCODER_SOFTWARE: &str = "software";
+pub const ENCODER_KINDS: [&str; 7] = [
+    "nvenc",
+    "qsv",
+    "amf",
+    "vaapi",
+    "videotoolbox",
+    "mediacodec",
+    ENCODER_SOFTWARE,
+];
+
+pub fn encoder_preference() -> Vec<&'static str> {
+    let mut v = vec![];
+    for kind in Config::get_option(OPTION_ENCODER_PREFERENCE)
+        .split(',')
+        .map(|k| k.trim().to_lowercase())
+    {
+        if let Some(kind) = ENCODER_KINDS.iter().find(|k| **k == kind) {
+            if !v.contains(kind) {
+                v.push(*kind);
+            }
+        }
+    }
+    v
+}
+
+/// The kind of the hardware encoder by its name, e.g. "h264_nvenc" -> "nvenc".
+pub fn encoder_kind(name: &str) -> Option<&'static str> {
+    let name = name.to_lowercase();
+    ENCODER_KINDS
+        .iter()
+        .find(|k| **k != ENCODER_SOFTWARE && name.contains(*k))
+        .copied()
+}
+
+/// Pick the first candidate in the order of `preference`, `None` if software is preferred.
+/// The reasons of the skipped preferred kinds are appended to `skipped`.
+pub fn select_by_preference<T>(
+    preference: &[&'static str],
+    mut candidates: Vec<(&'static str, T)>,
+    skipped: &mut Vec<String>,
+) -> Option<T> {
+    for kind in preference {
+        if *kind == ENCODER_SOFTWARE {
+            return None;
+        }
+        match candidates.iter().position(|(k, _)| k == kind) {
+            Some(i) => return Some(candidates.swap_remove(i).1),
+            None => skipped.push(format!("{}: not available", kind)),
+        }
+    }
+    None
+}
+
+/// The encoder actually used for a display, and why the preferred ones are not used.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct EncoderSelection {
+    pub codec: String,
+    pub encoder: String,
+    pub hardware: bool,
+    pub skipped: Vec<String>,
+}
+
+impl EncoderCfg {
+    pub fn name(&self) -> String {
+        match self {
+            EncoderCfg::VPX(vpx) => match vpx.codec {
+                VpxVideoCodecId::VP8 => "vp8".to_owned(),
+                VpxVideoCodecId::VP9 => "vp9".to_owned(),
+            },
+            EncoderCfg::AOM(_) => "aom".to_owned(),
+            #[cfg(feature = "hwcodec")]
+            EncoderCfg::HWRAM(hw) => hw.name.clone(),
+            #[cfg(feature = "vram")]
+            EncoderCfg::VRAM(vram) => {
+                format!("{:?}_{:?}", vram.feature.data_format, vram.feature.driver).to_lowercase()
+            }
+        }
+    }
+}
+
+/// Set in the low bits of the custom image quality to request the lossless mode.
+/// The peers not supporting it ignore the bits and use the custom quality.
+pub const IMAGE_QUALITY_LOSSLESS_FLAG: i32 = 0x01;
+/// The custom image quality sent with the lossless flag, the max bitrate ratio.
+pub const IMAGE_QUALITY_LOSSLESS: i32 = 2000;
+const IMAGE_QUALITY_PROFILE_SHIFT: i32 = 1;
+const IMAGE_QUALITY_PROFILE_MASK: i32 = 0x07;
+
+/// The tuning of the encoders for the use of the session.
+///
+/// Sent in bits 1-3 of the custom image quality as the index plus one, with the preset image
+/// qualities too, the peers not supporting it ignore the bits.
+/// None of the profiles uses lookahead, each frame is flushed once it is encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EncoderProfile {
+    /// The tuning before the profiles.
+    #[default]
+    Balanced,
+    /// Faster encoding and more dropped frames instead of delayed ones, e.g. for gaming.
+    Latency,
+    /// Slower encoding, variable bitrate and no dropped frames, e.g. for reviewing documents.
+    Quality,
+    /// The most compression effort at the same bitrate, for slow links.
+    Bandwidth,
+}
+
+impl EncoderProfile {
+    const ALL: [EncoderProfile; 4] = [
+        EncoderProfile::Balanced,
+        EncoderProfile::Latency,
+        EncoderProfile::Quality,
+        EncoderProfile::Bandwidth,
+    ];
+
+    pub fn from_name(name: &str) -> Self {
+        Self::ALL
+            .iter()
+            .find(|p| p.name() == name)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EncoderProfile::Balanced => "balanced",
+            EncoderProfile::Latency => "latency",
+            EncoderProfile::Quality => "quality",
+            EncoderProfile::Bandwidth => "bandwidth",
+        }
+    }
+
+    /// The bits set in the custom image quality.
+    pub fn image_quality_flag(&self) -> i32 {
+        (*self as i32 + 1) << IMAGE_QUALITY_PROFILE_SHIFT
+    }
+
+    /// The profile in the custom image quality, `None` if it is not sent.
+    pub fn from_image_quality(image_quality: i32) -> Option<Self> {
+        let v = image_quality >> IMAGE_QUALITY_PROFILE_SHIFT & IMAGE_QUALITY_PROFILE_MASK;
+        Self::ALL.get((v as usize).checked_sub(1)?).cloned()
+    }
+
+    /// `None` disables the periodic keyframes, which are spikes of the bitrate.
+    pub fn keyframe_interval(&self) -> Option<usize> {
+        match self {
+            // Refresh the picture once a minute at 30 fps.
+            EncoderProfile::Quality => Some(1800),
+            _ => None,
+        }
+    }
+
+    /// Variable bitrate lets the complex frames, e.g. scrolled text, take more bits.
+    pub fn vbr(&self) -> bool {
+        *self == EncoderProfile::Quality
+    }
+
+    /// The buffer fullness in percent below which the rate control drops frames, 0 disables it.
+    pub fn drop_frame_threshold(&self) -> u32 {
+        match self {
+            EncoderProfile::Latency => 40,
+            EncoderProfile::Quality => 0,
+            EncoderProfile::Balanced | EncoderProfile::Bandwidth => 25,
+        }
+    }
+
+    /// Offset of the speed preset of each encoder, higher is faster with lower quality.
+    pub fn speed_offset(&self) -> i32 {
+        match self {
+            EncoderProfile::Latency => 1,
+            EncoderProfile::Balanced => 0,
+            EncoderProfile::Quality => -1,
+            EncoderProfile::Bandwidth => -2,
+        }
+    }
+}
+
+const IMAGE_QUALITY_CODEC_SHIFT: i32 = 4;
+const IMAGE_QUALITY_CODEC_MASK: i32 = 0x0F;
+const IMAGE_QUALITY_DISPLAY_SHIFT: i32 = 20;
+const IMAGE_QUALITY_DISPLAY_MASK: i32 = 0xFF;
+const DISPLAY_CODECS: [CodecFormat; 5] = [
+    CodecFormat::VP8,
+    CodecFormat::VP9,
+    CodecFormat::AV1,
+    CodecFormat::H264,
+    CodecFormat::H265,
+];
+
+/// The settings of a display overriding the ones of the session.
+///
+/// Sent as an option message with the display plus one in bits 20-27 and the codec in bits 4-7
+/// of the custom image quality, the fps in the custom fps. Only sent to the peers supporting it,
+/// the older ones take it for the session quality.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct DisplaySettings {
+    pub display: usize,
+    /// The custom image quality, 0 follows the session.
+    pub quality: i32,
+    /// 0 follows the session.
+    pub fps: u32,
+    /// `None` follows the session.
+    pub codec: Option<CodecFormat>,
+}
+
+impl DisplaySettings {
+    pub fn is_default(&self) -> bool {
+        self.quality == 0 && self.fps == 0 && self.codec.is_none()
+    }
+
+    pub fn write(&self, o: &mut OptionMessage) {
+        let codec = self
+            .codec
+            .and_then(|c| DISPLAY_CODECS.iter().position(|f| *f == c))
+            .map_or(0, |i| i as i32 + 1);
+        o.custom_image_quality = (self.display as i32 + 1) << IMAGE_QUALITY_DISPLAY_SHIFT
+            | (self.quality & 0xFFF) << 8
+            | codec << IMAGE_QUALITY_CODEC_SHIFT;
+        o.custom_fps = self.fps as _;
+    }
+
+    /// `None` if the option is for the session.
+    pub fn read(o: &OptionMessage) -> Option<Self> {
+        let q = o.custom_image_quality;
+        let display = (q >> IMAGE_QUALITY_DISPLAY_SHIFT & IMAGE_QUALITY_DISPLAY_MASK) as usize;
+        if q <= 0 || display == 0 {
+            return None;
+        }
+        let codec = (q >> IMAGE_QUALITY_CODEC_SHIFT & IMAGE_QUALITY_CODEC_MASK) as usize;
+        Some(Self {
+            display: display - 1,
+            quality: q >> 8 & 0xFFF,
+            fps: o.custom_fps.max(0) as _,
+            codec: codec
+                .checked_sub(1)
+                .and_then(|i| DISPLAY_CODECS.get(i))
+                .cloned(),
+        })
+    }
+
+    /// The name of the codec, e.g. "vp9", empty if it follows the session.
+    pub fn codec_name(&self) -> String {
+        self.codec
+            .map(|c| c.to_string().to_lowercase())
+            .unwrap_or_default()
+    }
+
+    /// The codec of the name returned by [`Self::codec_name`].
+    pub fn codec_from_name(name: &str) -> Option<CodecFormat> {
+        DISPLAY_CODECS
+            .iter()
+            .find(|f| f.to_string().to_lowercase() == name.to_lowercase())
+            .cloned()
+    }
+}
+
 pub const BR_BEST: f32 = 1.5;
 pub const BR_BALANCED: f32 = 0.67;
 pub const BR_SPEED: f32 = 0.5;
@@ -1107,6 +1439,7 @@ pub fn test_av1() {
                     height,
                     quality,
                     keyframe_interval,
+                    profile: Default::default(),
                 }),
                 i444,
             ) else {
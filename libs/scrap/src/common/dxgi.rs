@@ -1,6 +1,6 @@
 #[cfg(feature = "vram")]
 use crate::AdapterDevice;
-use crate::{common::TraitCapturer, dxgi, Frame, Pixfmt};
+use crate::{common::TraitCapturer, dxgi, DamageRect, Frame, Pixfmt};
 use std::{
     io::{
         self,
@@ -74,6 +74,7 @@ pub struct PixelBuffer<'a> {
     width: usize,
     height: usize,
     stride: Vec<usize>,
+    damage: Option<&'a [DamageRect]>,
 }
 
 impl<'a> PixelBuffer<'a> {
@@ -87,6 +88,7 @@ impl<'a> PixelBuffer<'a> {
             width,
             height,
             stride,
+            damage: None,
         }
     }
 
@@ -94,6 +96,11 @@ impl<'a> PixelBuffer<'a> {
     pub fn with_BGRA(data: &'a [u8], width: usize, height: usize) -> Self {
         Self::new(data, Pixfmt::BGRA, width, height)
     }
+
+    pub fn with_damage(mut self, damage: Option<&'a [DamageRect]>) -> Self {
+        self.damage = damage;
+        self
+    }
 }
 
 impl<'a> crate::TraitPixelBuffer for PixelBuffer<'a> {
@@ -116,6 +123,10 @@ impl<'a> crate::TraitPixelBuffer for PixelBuffer<'a> {
     fn pixfmt(&self) -> Pixfmt {
         self.pixfmt
     }
+
+    fn damage(&self) -> Option<&[DamageRect]> {
+        self.damage
+    }
 }
 
 pub struct Display(dxgi::Display);
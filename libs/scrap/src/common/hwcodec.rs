@@ -1,5 +1,8 @@
 use crate::{
-    codec::{base_bitrate, codec_thread_num, enable_hwcodec_option, EncoderApi, EncoderCfg},
+    codec::{
+        base_bitrate, codec_thread_num, enable_hwcodec_option, encoder_kind, encoder_preference,
+        select_by_preference, EncoderApi, EncoderCfg, EncoderProfile,
+    },
     convert::*,
     CodecFormat, EncodeInput, ImageFormat, ImageRgb, Pixfmt, HW_STRIDE_ALIGN,
 };
@@ -11,6 +14,7 @@ use hbb_common::{
     serde_derive::{Deserialize, Serialize},
     serde_json, ResultType,
 };
+pub use hwcodec::ffmpeg_ram::CodecInfo;
 use hwcodec::{
     common::{
         DataFormat, HwcodecErrno,
@@ -21,7 +25,7 @@ use hwcodec::{
     ffmpeg_ram::{
         decode::{DecodeContext, DecodeFrame, Decoder},
         encode::{EncodeContext, EncodeFrame, Encoder},
-        ffmpeg_linesize_offset_length, CodecInfo,
+        ffmpeg_linesize_offset_length,
     },
 };
 
@@ -47,6 +51,7 @@ pub struct HwRamEncoderConfig {
     pub height: usize,
     pub quality: f32,
     pub keyframe_interval: Option<usize>,
+    pub profile: EncoderProfile,
 }
 
 pub struct HwRamEncoder {
@@ -79,7 +84,7 @@ impl EncoderApi for HwRamEncoder {
                     kbs: bitrate as i32,
                     fps: DEFAULT_FPS,
                     gop,
-                    quality: DEFAULT_HW_QUALITY,
+                    quality: Self::quality_preset(config.profile),
                     rc,
                     q: -1,
                     thread_count: codec_thread_num(16) as _, // ffmpeg's thread_count is used for cpu
@@ -130,6 +135,7 @@ impl EncoderApi for HwRamEncoder {
             match self.format {
                 DataFormat::H264 => vf.set_h264s(frames),
                 DataFormat::H265 => vf.set_h265s(frames),
+                DataFormat::AV1 => vf.set_av1s(frames),
                 _ => bail!("unsupported format: {:?}", self.format),
             }
             Ok(vf)
@@ -211,6 +217,10 @@ impl EncoderApi for HwRamEncoder {
 
 impl HwRamEncoder {
     pub fn try_get(format: CodecFormat) -> Option<CodecInfo> {
+        let preference = encoder_preference();
+        if !preference.is_empty() {
+            return select_by_preference(&preference, Self::candidates(format), &mut vec![]);
+        }
         let mut info = None;
         let best = CodecInfo::prioritized(HwCodecConfig::get().ram_encode);
         match format {
@@ -224,11 +234,33 @@ impl HwRamEncoder {
                     info = Some(v);
                 }
             }
+            // The bitstream is decoded by aom on the peer, the same as the software encoder.
+            CodecFormat::AV1 => {
+                if let Some(v) = best.av1 {
+                    info = Some(v);
+                }
+            }
             _ => {}
         }
         info
     }
 
+    /// The available encoders of `format` by their kinds, see [`encoder_kind`].
+    pub fn candidates(format: CodecFormat) -> Vec<(&'static str, CodecInfo)> {
+        let data_format = match format {
+            CodecFormat::H264 => DataFormat::H264,
+            CodecFormat::H265 => DataFormat::H265,
+            CodecFormat::AV1 => DataFormat::AV1,
+            _ => return vec![],
+        };
+        HwCodecConfig::get()
+            .ram_encode
+            .drain(..)
+            .filter(|c| c.format == data_format)
+            .filter_map(|c| encoder_kind(&c.name).map(|kind| (kind, c)))
+            .collect()
+    }
+
     pub fn encode(&mut self, yuv: &[u8], ms: i64) -> ResultType<Vec<EncodeFrame>> {
         match self.encoder.encode(yuv, ms) {
             Ok(v) => {
@@ -240,14 +272,26 @@ impl HwRamEncoder {
         }
     }
 
-    fn rate_control(_config: &HwRamEncoderConfig) -> RateControl {
+    fn rate_control(config: &HwRamEncoderConfig) -> RateControl {
         #[cfg(target_os = "android")]
-        if _config.name.contains("mediacodec") {
+        if config.name.contains("mediacodec") {
+            return RC_VBR;
+        }
+        if config.profile.vbr() {
             return RC_VBR;
         }
         RC_CBR
     }
 
+    // The speed preset of the encoder.
+    fn quality_preset(profile: EncoderProfile) -> Quality {
+        match profile.speed_offset() {
+            0 => DEFAULT_HW_QUALITY,
+            offset if offset > 0 => Quality_Low,
+            _ => Quality_High,
+        }
+    }
+
     pub fn bitrate(name: &str, width: usize, height: usize, ratio: f32) -> u32 {
         Self::calc_bitrate(width, height, ratio, name.contains("h264"))
     }
@@ -551,6 +595,10 @@ impl HwCodecConfig {
                     name_prefix: "hevc",
                     data_format: DataFormat::H265,
                 },
+                T {
+                    name_prefix: "av1",
+                    data_format: DataFormat::AV1,
+                },
             ];
             let mut e = vec![];
             if let Some(info) = info {
@@ -677,6 +725,28 @@ impl HwCodecConfig {
     }
 }
 
+/// The DRM render nodes used by vaapi, and whether they can be opened for read and write.
+#[cfg(target_os = "linux")]
+pub fn vaapi_render_nodes() -> Vec<(String, bool)> {
+    let Ok(entries) = std::fs::read_dir("/dev/dri") else {
+        return vec![];
+    };
+    let mut nodes: Vec<_> = entries
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_name().to_string_lossy().starts_with("renderD"))
+        .map(|e| {
+            let accessible = std::fs::OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(e.path())
+                .is_ok();
+            (e.path().to_string_lossy().to_string(), accessible)
+        })
+        .collect();
+    nodes.sort();
+    nodes
+}
+
 pub fn check_available_hwcodec() -> String {
     #[cfg(any(target_os = "linux", target_os = "macos"))]
     hwcodec::common::setup_parent_death_signal();
@@ -701,9 +771,26 @@ pub fn check_available_hwcodec() -> String {
     let vram_string = vram.2;
     #[cfg(not(feature = "vram"))]
     let vram_string = "".to_owned();
+    let ram_encode = Encoder::available_encoders(ctx, Some(vram_string));
+    let ram_decode = Decoder::available_decoders();
+    #[cfg(target_os = "linux")]
+    {
+        // ffmpeg uses the first render node which can be opened, log them to diagnose the multi-gpu machines.
+        let vaapi: Vec<_> = ram_encode
+            .iter()
+            .chain(ram_decode.iter())
+            .filter(|c| c.name.contains("vaapi"))
+            .map(|c| c.name.clone())
+            .collect();
+        log::info!(
+            "vaapi render nodes: {:?}, codecs: {:?}",
+            vaapi_render_nodes(),
+            vaapi
+        );
+    }
     let c = HwCodecConfig {
-        ram_encode: Encoder::available_encoders(ctx, Some(vram_string)),
-        ram_decode: Decoder::available_decoders(),
+        ram_encode,
+        ram_decode,
         #[cfg(feature = "vram")]
         vram_encode: vram.0,
         #[cfg(feature = "vram")]
@@ -105,7 +105,7 @@ impl MediaCodecDecoder {
                                 h as _,
                             );
                         }
-                        ImageFormat::ARGB => {
+                        ImageFormat::ABGR => {
                             I420ToABGR(
                                 y_ptr,
                                 stride,
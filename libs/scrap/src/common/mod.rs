@@ -126,6 +126,70 @@ pub fn would_block_if_equal(old: &mut Vec<u8>, b: &[u8]) -> std::io::Result<()>
     Ok(())
 }
 
+/// A changed region of the captured frame in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DamageRect {
+    pub x: usize,
+    pub y: usize,
+    pub w: usize,
+    pub h: usize,
+}
+
+const DAMAGE_TILE: usize = 64;
+
+/// Like `would_block_if_equal`, but compares the frame in tiles and collects the changed ones
+/// into `damage`, for the capturers without the damage reported by the OS.
+pub fn would_block_or_damage(
+    old: &mut Vec<u8>,
+    b: &[u8],
+    width: usize,
+    height: usize,
+    stride: usize,
+    bytes_per_pixel: usize,
+    damage: &mut Vec<DamageRect>,
+) -> std::io::Result<()> {
+    damage.clear();
+    if old.len() != b.len() || b.len() < stride * height || stride < width * bytes_per_pixel {
+        damage.push(DamageRect {
+            x: 0,
+            y: 0,
+            w: width,
+            h: height,
+        });
+    } else {
+        for ty in (0..height).step_by(DAMAGE_TILE) {
+            let th = DAMAGE_TILE.min(height - ty);
+            for tx in (0..width).step_by(DAMAGE_TILE) {
+                let tw = DAMAGE_TILE.min(width - tx);
+                let changed = (ty..ty + th).any(|y| {
+                    let start = y * stride + tx * bytes_per_pixel;
+                    let end = start + tw * bytes_per_pixel;
+                    b[start..end] != old[start..end]
+                });
+                if !changed {
+                    continue;
+                }
+                // Merge the adjacent tiles of a row
+                match damage.last_mut() {
+                    Some(last) if last.y == ty && last.x + last.w == tx => last.w += tw,
+                    _ => damage.push(DamageRect {
+                        x: tx,
+                        y: ty,
+                        w: tw,
+                        h: th,
+                    }),
+                }
+            }
+        }
+        if damage.is_empty() {
+            return Err(std::io::ErrorKind::WouldBlock.into());
+        }
+    }
+    old.resize(b.len(), 0);
+    old.copy_from_slice(b);
+    Ok(())
+}
+
 pub trait TraitCapturer {
     // We doesn't support
     #[cfg(not(any(target_os = "ios")))]
@@ -170,6 +234,11 @@ pub trait TraitPixelBuffer {
     fn stride(&self) -> Vec<usize>;
 
     fn pixfmt(&self) -> Pixfmt;
+
+    /// The changed regions since the last frame, None if unknown.
+    fn damage(&self) -> Option<&[DamageRect]> {
+        None
+    }
 }
 
 #[cfg(not(any(target_os = "ios")))]
@@ -187,6 +256,13 @@ impl Frame<'_> {
         }
     }
 
+    pub fn damage(&self) -> Option<&[DamageRect]> {
+        match self {
+            Frame::PixelBuffer(pixelbuffer) => pixelbuffer.damage(),
+            Frame::Texture(_) => None,
+        }
+    }
+
     pub fn to<'a>(
         &'a self,
         yuvfmt: EncodeYuvFormat,
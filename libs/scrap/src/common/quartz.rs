@@ -1,46 +1,113 @@
-use crate::{quartz, Frame, Pixfmt};
+use crate::{
+    quartz::{self, screencapturekit},
+    Frame, Pixfmt,
+};
+use hbb_common::{
+    config::{option2bool, Config},
+    log,
+};
 use std::marker::PhantomData;
 use std::sync::{Arc, Mutex, TryLockError};
 use std::{io, mem};
 
+/// Capture with ScreenCaptureKit if available, or the legacy CGDisplayStream.
+pub const OPTION_SCREEN_CAPTURE_KIT: &str = "enable-screencapturekit";
+
+enum Inner {
+    ScreenCaptureKit(screencapturekit::Capturer),
+    DisplayStream(quartz::Capturer),
+}
+
 pub struct Capturer {
-    inner: quartz::Capturer,
+    inner: Inner,
     frame: Arc<Mutex<Option<quartz::Frame>>>,
     saved_raw_data: Vec<u8>, // for faster compare and copy
 }
 
+pub fn enable_screen_capture_kit() -> bool {
+    option2bool(
+        OPTION_SCREEN_CAPTURE_KIT,
+        &Config::get_option(OPTION_SCREEN_CAPTURE_KIT),
+    ) && screencapturekit::is_available()
+}
+
+fn frame_handler(f: Arc<Mutex<Option<quartz::Frame>>>) -> impl Fn(quartz::Frame) {
+    move |inner| {
+        if let Ok(mut f) = f.lock() {
+            *f = Some(inner);
+        }
+    }
+}
+
 impl Capturer {
     pub fn new(display: Display) -> io::Result<Capturer> {
         let frame = Arc::new(Mutex::new(None));
 
-        let f = frame.clone();
+        if enable_screen_capture_kit() {
+            match screencapturekit::Capturer::new(
+                screencapturekit::Source::Display(display.0.id()),
+                display.width(),
+                display.height(),
+                false,
+                frame_handler(frame.clone()),
+            ) {
+                Ok(inner) => {
+                    return Ok(Capturer {
+                        inner: Inner::ScreenCaptureKit(inner),
+                        frame,
+                        saved_raw_data: Vec::new(),
+                    });
+                }
+                Err(e) => log::warn!("{}, fall back to CGDisplayStream", e),
+            }
+        }
+
         let inner = quartz::Capturer::new(
             display.0,
             display.width(),
             display.height(),
             quartz::PixelFormat::Argb8888,
             Default::default(),
-            move |inner| {
-                if let Ok(mut f) = f.lock() {
-                    *f = Some(inner);
-                }
-            },
+            frame_handler(frame.clone()),
         )
         .map_err(|_| io::Error::from(io::ErrorKind::Other))?;
 
         Ok(Capturer {
-            inner,
+            inner: Inner::DisplayStream(inner),
+            frame,
+            saved_raw_data: Vec::new(),
+        })
+    }
+
+    /// Capture the window of the CGWindowID, with ScreenCaptureKit only.
+    pub fn new_window(window_id: u32, width: usize, height: usize) -> io::Result<Capturer> {
+        let frame = Arc::new(Mutex::new(None));
+        let inner = screencapturekit::Capturer::new(
+            screencapturekit::Source::Window(window_id),
+            width,
+            height,
+            false,
+            frame_handler(frame.clone()),
+        )?;
+        Ok(Capturer {
+            inner: Inner::ScreenCaptureKit(inner),
             frame,
             saved_raw_data: Vec::new(),
         })
     }
 
     pub fn width(&self) -> usize {
-        self.inner.width()
+        match &self.inner {
+            Inner::ScreenCaptureKit(inner) => inner.width(),
+            Inner::DisplayStream(inner) => inner.width(),
+        }
     }
 
     pub fn height(&self) -> usize {
-        self.inner.height()
+        match &self.inner {
+            Inner::ScreenCaptureKit(inner) => inner.height(),
+            Inner::DisplayStream(inner) => inner.height(),
+        }
     }
 }
 
@@ -54,7 +121,7 @@ impl crate::TraitCapturer for Capturer {
                 match frame {
                     Some(mut frame) => {
                         crate::would_block_if_equal(&mut self.saved_raw_data, frame.inner())?;
-                        frame.surface_to_bgra(self.height());
+                        frame.map_bgra(self.height());
                         Ok(Frame::PixelBuffer(PixelBuffer {
                             frame,
                             data: PhantomData,
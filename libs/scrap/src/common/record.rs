@@ -28,6 +28,9 @@ pub struct RecorderContext {
     pub display_idx: usize,
     pub camera: bool,
     pub tx: Option<Sender<RecordState>>,
+    /// The template of the file name without the extension, the default one if empty.
+    /// `{id}`, `{date}`, `{time}`, `{display}` and `{codec}` are replaced.
+    pub template: String,
 }
 
 #[derive(Debug, Clone)]
@@ -43,24 +46,43 @@ impl RecorderContext2 {
         if !PathBuf::from(&ctx.dir).exists() {
             std::fs::create_dir_all(&ctx.dir)?;
         }
-        let file = if ctx.server { "incoming" } else { "outgoing" }.to_string()
-            + "_"
-            + &ctx.id.clone()
-            + &chrono::Local::now().format("_%Y%m%d%H%M%S%3f_").to_string()
-            + &format!(
-                "{}{}_",
-                if ctx.camera { "camera" } else { "display" },
-                ctx.display_idx
-            )
-            + &self.format.to_string().to_lowercase()
-            + if self.format == CodecFormat::VP9
-                || self.format == CodecFormat::VP8
-                || self.format == CodecFormat::AV1
-            {
-                ".webm"
-            } else {
-                ".mp4"
-            };
+        let now = chrono::Local::now();
+        let display = format!(
+            "{}{}",
+            if ctx.camera { "camera" } else { "display" },
+            ctx.display_idx
+        );
+        let codec = self.format.to_string().to_lowercase();
+        let ext = if self.format == CodecFormat::VP9
+            || self.format == CodecFormat::VP8
+            || self.format == CodecFormat::AV1
+        {
+            ".webm"
+        } else {
+            ".mp4"
+        };
+        let mut file = if ctx.template.is_empty() {
+            if ctx.server { "incoming" } else { "outgoing" }.to_string()
+                + "_"
+                + &ctx.id.clone()
+                + &now.format("_%Y%m%d%H%M%S%3f_").to_string()
+                + &display
+                + "_"
+                + &codec
+        } else {
+            ctx.template
+                .replace("{id}", &ctx.id)
+                .replace("{date}", &now.format("%Y%m%d").to_string())
+                .replace("{time}", &now.format("%H%M%S").to_string())
+                .replace("{display}", &display)
+                .replace("{codec}", &codec)
+                .replace(['/', '\\', ':', '*', '?', '"', '<', '>', '|'], "_")
+        };
+        // The templates may produce the same name in a second.
+        if PathBuf::from(&ctx.dir).join(file.clone() + ext).exists() {
+            file += &now.format("_%3f").to_string();
+        }
+        file += ext;
         self.filename = PathBuf::from(&ctx.dir)
             .join(file)
             .to_string_lossy()
@@ -79,7 +101,7 @@ pub trait RecorderApi {
     fn write_video(&mut self, frame: &EncodedVideoFrame) -> bool;
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum RecordState {
     NewFile(String),
     NewFrame,
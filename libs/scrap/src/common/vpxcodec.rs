@@ -8,8 +8,8 @@ use hbb_common::log;
 use hbb_common::message_proto::{Chroma, EncodedVideoFrame, EncodedVideoFrames, VideoFrame};
 use hbb_common::ResultType;
 
-use crate::codec::{base_bitrate, codec_thread_num, EncoderApi};
-use crate::{EncodeInput, EncodeYuvFormat, GoogleImage, Pixfmt, STRIDE_ALIGN};
+use crate::codec::{base_bitrate, codec_thread_num, EncoderApi, EncoderProfile};
+use crate::{DamageRect, EncodeInput, EncodeYuvFormat, GoogleImage, Pixfmt, STRIDE_ALIGN};
 
 use super::vpx::{vp8e_enc_control_id::*, vpx_codec_err_t::*, *};
 use crate::{generate_call_macro, generate_call_ptr_macro, Error, Result};
@@ -20,6 +20,12 @@ use std::{ptr, slice};
 generate_call_macro!(call_vpx, false);
 generate_call_ptr_macro!(call_vpx_ptr);
 
+// The skipped macroblocks keep their quality, encode the whole frame once in a while to refine them.
+const ACTIVE_MAP_REFRESH_FRAMES: usize = 60;
+// The quantizer deltas of the segments of the region of interest map, in [-63, 63].
+const ROI_DELTA_Q_PERIPHERY: i32 = 12;
+const ROI_DELTA_Q_PRIORITY: i32 = -20;
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum VpxVideoCodecId {
     VP8,
@@ -39,6 +45,14 @@ pub struct VpxEncoder {
     id: VpxVideoCodecId,
     i444: bool,
     yuvfmt: EncodeYuvFormat,
+    lossless: bool,
+    // One byte per 16x16 macroblock, 1 if it is encoded.
+    active_map: Vec<u8>,
+    active_map_set: bool,
+    active_map_frames: usize,
+    force_keyframe: bool,
+    // One byte per block, 1 if it is in the priority region.
+    roi_map: Vec<u8>,
 }
 
 pub struct VpxDecoder {
@@ -70,12 +84,18 @@ impl EncoderApi for VpxEncoder {
                 c.rc_undershoot_pct = 95;
                 // When the data buffer falls below this percentage of fullness, a dropped frame is indicated. Set the threshold to zero (0) to disable this feature.
                 // In dynamic scenes, low bitrate gets low fps while high bitrate gets high fps.
-                c.rc_dropframe_thresh = 25;
+                c.rc_dropframe_thresh = config.profile.drop_frame_threshold();
                 c.g_threads = codec_thread_num(64) as _;
                 c.g_error_resilient = VPX_ERROR_RESILIENT_DEFAULT;
                 // https://developers.google.com/media/vp9/bitrate-modes/
                 // Constant Bitrate mode (CBR) is recommended for live streaming with VP9.
-                c.rc_end_usage = vpx_rc_mode::VPX_CBR;
+                c.rc_end_usage = if config.profile.vbr() {
+                    vpx_rc_mode::VPX_VBR
+                } else {
+                    vpx_rc_mode::VPX_CBR
+                };
+                // No lookahead in any profile, the frames are flushed once they are encoded.
+                c.g_lag_in_frames = 0;
                 if let Some(keyframe_interval) = config.keyframe_interval {
                     c.kf_min_dist = 0;
                     c.kf_max_dist = keyframe_interval as _;
@@ -88,6 +108,14 @@ impl EncoderApi for VpxEncoder {
                 c.rc_max_quantizer = q_max;
                 c.rc_target_bitrate =
                     Self::bitrate(config.width as _, config.height as _, config.quality);
+                let lossless = config.lossless && config.codec == VpxVideoCodecId::VP9;
+                if lossless {
+                    // Frames must not be dropped or quantized by the rate control.
+                    c.rc_end_usage = vpx_rc_mode::VPX_VBR;
+                    c.rc_dropframe_thresh = 0;
+                    c.rc_min_quantizer = 0;
+                    c.rc_max_quantizer = 0;
+                }
                 // https://chromium.googlesource.com/webm/libvpx/+/refs/heads/main/vp9/common/vp9_enums.h#29
                 // https://chromium.googlesource.com/webm/libvpx/+/refs/heads/main/vp8/vp8_cx_iface.c#282
                 c.g_profile = if i444 && config.codec == VpxVideoCodecId::VP9 {
@@ -129,7 +157,8 @@ impl EncoderApi for VpxEncoder {
                     Higher numbers (7 or 8) will be lower quality but more manageable for lower latency
                     use cases and also for lower CPU power devices such as mobile.
                     */
-                    call_vpx!(vpx_codec_control_(&mut ctx, VP8E_SET_CPUUSED as _, 7,));
+                    let speed = (7 + config.profile.speed_offset()).clamp(5, 9);
+                    call_vpx!(vpx_codec_control_(&mut ctx, VP8E_SET_CPUUSED as _, speed));
                     // set row level multi-threading
                     /*
                     as some people in comments and below have already commented,
@@ -152,10 +181,19 @@ impl EncoderApi for VpxEncoder {
                         VP9E_SET_TILE_COLUMNS as _,
                         4 as c_int
                     ));
+
+                    if lossless {
+                        call_vpx!(vpx_codec_control_(
+                            &mut ctx,
+                            VP9E_SET_LOSSLESS as _,
+                            1 as c_int
+                        ));
+                    }
                 } else if config.codec == VpxVideoCodecId::VP8 {
                     // https://github.com/webmproject/libvpx/blob/972149cafeb71d6f08df89e91a0130d6a38c4b15/vpx/vp8cx.h#L172
                     // https://groups.google.com/a/webmproject.org/g/webm-discuss/c/DJhSrmfQ61M
-                    call_vpx!(vpx_codec_control_(&mut ctx, VP8E_SET_CPUUSED as _, 12,));
+                    let speed = (12 + 2 * config.profile.speed_offset()).clamp(4, 16);
+                    call_vpx!(vpx_codec_control_(&mut ctx, VP8E_SET_CPUUSED as _, speed));
                 }
 
                 Ok(Self {
@@ -165,6 +203,12 @@ impl EncoderApi for VpxEncoder {
                     id: config.codec,
                     i444,
                     yuvfmt: Self::get_yuvfmt(config.width, config.height, i444),
+                    lossless,
+                    active_map: Vec::new(),
+                    active_map_set: false,
+                    active_map_frames: 0,
+                    force_keyframe: false,
+                    roi_map: Vec::new(),
                 })
             }
             _ => Err(anyhow!("encoder type mismatch")),
@@ -201,6 +245,9 @@ impl EncoderApi for VpxEncoder {
     }
 
     fn set_quality(&mut self, ratio: f32) -> ResultType<()> {
+        if self.lossless {
+            return Ok(());
+        }
         let mut c = unsafe { *self.ctx.config.enc.to_owned() };
         let (q_min, q_max) = Self::calc_q_values(ratio);
         c.rc_min_quantizer = q_min;
@@ -228,6 +275,99 @@ impl EncoderApi for VpxEncoder {
     }
 
     fn disable(&self) {}
+
+    // The macroblocks out of the damaged regions are skipped and copied from the last frame.
+    fn set_damage(&mut self, damage: Option<&[DamageRect]>) {
+        let (cols, rows) = ((self.width + 15) / 16, (self.height + 15) / 16);
+        self.active_map_frames += 1;
+        let damage = damage.filter(|_| self.active_map_frames < ACTIVE_MAP_REFRESH_FRAMES);
+        let Some(damage) = damage else {
+            self.active_map_frames = 0;
+            if self.active_map_set {
+                self.active_map_set = false;
+                let mut map = vpx_active_map_t {
+                    active_map: ptr::null_mut(),
+                    rows: rows as _,
+                    cols: cols as _,
+                };
+                let ret =
+                    unsafe { vpx_codec_control_(&mut self.ctx, VP8E_SET_ACTIVEMAP as _, &mut map) };
+                if ret != VPX_CODEC_OK {
+                    log::error!("Failed to clear the active map, {:?}", ret);
+                }
+            }
+            return;
+        };
+        self.active_map.clear();
+        self.active_map.resize(cols * rows, 0);
+        for r in damage {
+            let (x0, y0) = (r.x / 16, r.y / 16);
+            let x1 = ((r.x + r.w + 15) / 16).min(cols);
+            let y1 = ((r.y + r.h + 15) / 16).min(rows);
+            if x0 >= x1 {
+                continue;
+            }
+            for y in y0..y1 {
+                self.active_map[y * cols + x0..y * cols + x1].fill(1);
+            }
+        }
+        let mut map = vpx_active_map_t {
+            active_map: self.active_map.as_mut_ptr(),
+            rows: rows as _,
+            cols: cols as _,
+        };
+        let ret = unsafe { vpx_codec_control_(&mut self.ctx, VP8E_SET_ACTIVEMAP as _, &mut map) };
+        self.active_map_set = ret == VPX_CODEC_OK;
+        if ret != VPX_CODEC_OK {
+            log::error!("Failed to set the active map, {:?}", ret);
+        }
+    }
+
+    // The blocks in the region are in segment 1 with a lower quantizer, the rest in segment 0
+    // with a higher one, so the rate control moves the bits to the region.
+    fn set_priority_region(&mut self, region: Option<DamageRect>) -> bool {
+        if self.lossless {
+            return false;
+        }
+        // VP8 maps 16x16 macroblocks, VP9 8x8 blocks.
+        let (block, id) = match self.id {
+            VpxVideoCodecId::VP8 => (16, VP8E_SET_ROI_MAP),
+            VpxVideoCodecId::VP9 => (8, VP9E_SET_ROI_MAP),
+        };
+        let cols = (self.width + block - 1) / block;
+        let rows = (self.height + block - 1) / block;
+        let mut map: vpx_roi_map_t = unsafe { std::mem::zeroed() };
+        map.rows = rows as _;
+        map.cols = cols as _;
+        if let Some(r) = region.filter(|r| r.w > 0 && r.h > 0) {
+            self.roi_map.clear();
+            self.roi_map.resize(cols * rows, 0);
+            let (x0, y0) = ((r.x / block).min(cols), (r.y / block).min(rows));
+            let x1 = ((r.x + r.w + block - 1) / block).min(cols);
+            let y1 = ((r.y + r.h + block - 1) / block).min(rows);
+            if x0 < x1 {
+                for y in y0..y1 {
+                    self.roi_map[y * cols + x0..y * cols + x1].fill(1);
+                }
+            }
+            map.enabled = 1;
+            map.roi_map = self.roi_map.as_mut_ptr();
+            map.delta_q[0] = ROI_DELTA_Q_PERIPHERY;
+            map.delta_q[1] = ROI_DELTA_Q_PRIORITY;
+        }
+        let ret = unsafe { vpx_codec_control_(&mut self.ctx, id as _, &mut map) };
+        if ret != VPX_CODEC_OK {
+            log::error!("Failed to set the region of interest map, {:?}", ret);
+        }
+        ret == VPX_CODEC_OK
+    }
+
+    fn request_keyframe(&mut self) -> bool {
+        self.force_keyframe = true;
+        // The keyframe is encoded as a whole.
+        self.active_map_frames = ACTIVE_MAP_REFRESH_FRAMES;
+        true
+    }
 }
 
 impl VpxEncoder {
@@ -252,12 +392,17 @@ impl VpxEncoder {
             data.as_ptr() as _,
         ));
 
+        let flags = if std::mem::take(&mut self.force_keyframe) {
+            VPX_EFLAG_FORCE_KF
+        } else {
+            0
+        };
         call_vpx!(vpx_codec_encode(
             &mut self.ctx,
             &image,
             pts as _,
             1, // Duration
-            0, // Flags
+            flags as _,
             VPX_DL_REALTIME as _,
         ));
 
@@ -398,6 +543,10 @@ pub struct VpxEncoderConfig {
     pub codec: VpxVideoCodecId,
     /// keyframe interval
     pub keyframe_interval: Option<usize>,
+    /// Lossless mode, only supported by VP9, the quality is ignored.
+    pub lossless: bool,
+    /// The tuning profile
+    pub profile: EncoderProfile,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -5,7 +5,10 @@ use std::{
 };
 
 use crate::{
-    codec::{enable_vram_option, EncoderApi, EncoderCfg},
+    codec::{
+        enable_vram_option, encoder_preference, select_by_preference, EncoderApi, EncoderCfg,
+        ENCODER_SOFTWARE,
+    },
     hwcodec::HwCodecConfig,
     AdapterDevice, CodecFormat, EncodeInput, EncodeYuvFormat, Pixfmt,
 };
@@ -208,6 +211,11 @@ impl EncoderApi for VRamEncoder {
 
 impl VRamEncoder {
     pub fn try_get(device: &AdapterDevice, format: CodecFormat) -> Option<FeatureContext> {
+        let preference = encoder_preference();
+        if !preference.is_empty() {
+            let candidates = Self::candidates(device, format);
+            return select_by_preference(&preference, candidates, &mut vec![]);
+        }
         let v: Vec<_> = Self::available(format)
             .drain(..)
             .filter(|e| e.luid == device.luid)
@@ -223,6 +231,48 @@ impl VRamEncoder {
         }
     }
 
+    /// The available encoders of `format` on the device by their kinds, see [`crate::codec::encoder_kind`].
+    pub fn candidates(
+        device: &AdapterDevice,
+        format: CodecFormat,
+    ) -> Vec<(&'static str, FeatureContext)> {
+        Self::available(format)
+            .drain(..)
+            .filter(|e| e.luid == device.luid)
+            .filter_map(|e| Self::kind(&e).map(|kind| (kind, e)))
+            .collect()
+    }
+
+    // The ffmpeg encoders are of the vendor's driver.
+    fn kind(feature: &FeatureContext) -> Option<&'static str> {
+        let driver = if feature.driver == Driver::FFMPEG {
+            feature.vendor
+        } else {
+            feature.driver
+        };
+        match driver {
+            Driver::NV => Some("nvenc"),
+            Driver::AMF => Some("amf"),
+            Driver::MFX => Some("qsv"),
+            _ => None,
+        }
+    }
+
+    // Only the kinds preferred over the software encoders are used.
+    fn is_preferred(feature: &FeatureContext) -> bool {
+        let preference = encoder_preference();
+        if preference.is_empty() {
+            return true;
+        }
+        let Some(kind) = Self::kind(feature) else {
+            return false;
+        };
+        preference
+            .iter()
+            .take_while(|k| **k != ENCODER_SOFTWARE)
+            .any(|k| *k == kind)
+    }
+
     pub fn available(format: CodecFormat) -> Vec<FeatureContext> {
         let fallbacks = FALLBACK_GDI_DISPLAYS.lock().unwrap().clone();
         if !fallbacks.is_empty() {
@@ -243,6 +293,7 @@ impl VRamEncoder {
             .vram_encode
             .drain(..)
             .filter(|c| c.data_format == data_format)
+            .filter(Self::is_preferred)
             .collect();
         if crate::hwcodec::HwRamEncoder::try_get(format).is_some() {
             // has fallback, no need to require all adapters support
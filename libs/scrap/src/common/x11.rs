@@ -1,4 +1,4 @@
-use crate::{common::TraitCapturer, x11, Frame, Pixfmt, TraitPixelBuffer};
+use crate::{common::TraitCapturer, x11, DamageRect, Frame, Pixfmt, TraitPixelBuffer};
 use std::{io, time::Duration};
 
 pub struct Capturer(x11::Capturer);
@@ -24,12 +24,10 @@ impl TraitCapturer for Capturer {
         let width = self.width();
         let height = self.height();
         let pixfmt = self.0.display().pixfmt();
-        Ok(Frame::PixelBuffer(PixelBuffer::new(
-            self.0.frame()?,
-            pixfmt,
-            width,
-            height,
-        )))
+        let (data, damage) = self.0.frame()?;
+        Ok(Frame::PixelBuffer(
+            PixelBuffer::new(data, pixfmt, width, height).with_damage(damage),
+        ))
     }
 }
 
@@ -39,6 +37,7 @@ pub struct PixelBuffer<'a> {
     width: usize,
     height: usize,
     stride: Vec<usize>,
+    damage: Option<&'a [DamageRect]>,
 }
 
 impl<'a> PixelBuffer<'a> {
@@ -52,8 +51,14 @@ impl<'a> PixelBuffer<'a> {
             width,
             height,
             stride,
+            damage: None,
         }
     }
+
+    pub fn with_damage(mut self, damage: &'a [DamageRect]) -> Self {
+        self.damage = Some(damage);
+        self
+    }
 }
 
 impl<'a> TraitPixelBuffer for PixelBuffer<'a> {
@@ -76,6 +81,10 @@ impl<'a> TraitPixelBuffer for PixelBuffer<'a> {
     fn pixfmt(&self) -> crate::Pixfmt {
         self.pixfmt
     }
+
+    fn damage(&self) -> Option<&[DamageRect]> {
+        self.damage
+    }
 }
 
 pub struct Display(x11::Display);
@@ -22,7 +22,7 @@ use winapi::{
 
 use crate::RotationMode::*;
 
-use crate::{AdapterDevice, Frame, PixelBuffer};
+use crate::{AdapterDevice, DamageRect, Frame, PixelBuffer};
 use std::ffi::c_void;
 
 pub struct ComPtr<T>(*mut T);
@@ -55,6 +55,9 @@ pub struct Capturer {
     gdi_capturer: Option<CapturerGDI>,
     gdi_buffer: Vec<u8>,
     saved_raw_data: Vec<u8>, // for faster compare and copy
+    // The changed regions of the current frame, valid if `damage_known`.
+    damage: Vec<DamageRect>,
+    damage_known: bool,
     output_texture: bool,
     adapter_desc1: DXGI_ADAPTER_DESC1,
     rotate: Rotate,
@@ -171,6 +174,8 @@ impl Capturer {
             gdi_capturer,
             gdi_buffer: Vec::new(),
             saved_raw_data: Vec::new(),
+            damage: Vec::new(),
+            damage_known: false,
             output_texture: false,
             adapter_desc1,
             rotate,
@@ -340,6 +345,10 @@ impl Capturer {
         if *info.LastPresentTime.QuadPart() == 0 {
             return Err(std::io::ErrorKind::WouldBlock.into());
         }
+        self.update_damage(&info);
+        if self.damage_known && self.damage.is_empty() {
+            return Err(std::io::ErrorKind::WouldBlock.into());
+        }
 
         #[allow(invalid_value)]
         let mut rect = mem::MaybeUninit::uninit().assume_init();
@@ -352,6 +361,57 @@ impl Capturer {
         Ok((rect.pBits, rect.Pitch))
     }
 
+    // The dirty rects and the destinations of the move rects of the acquired frame.
+    unsafe fn update_damage(&mut self, info: &DXGI_OUTDUPL_FRAME_INFO) {
+        self.damage.clear();
+        self.damage_known = false;
+        let size = info.TotalMetadataBufferSize as usize;
+        let rotation = self.display.rotation();
+        if size == 0
+            || (rotation != DXGI_MODE_ROTATION_IDENTITY
+                && rotation != DXGI_MODE_ROTATION_UNSPECIFIED)
+        {
+            return;
+        }
+        let mut required = 0;
+        let mut moves: Vec<DXGI_OUTDUPL_MOVE_RECT> =
+            Vec::with_capacity(size / mem::size_of::<DXGI_OUTDUPL_MOVE_RECT>() + 1);
+        if (*self.duplication.0).GetFrameMoveRects(
+            (moves.capacity() * mem::size_of::<DXGI_OUTDUPL_MOVE_RECT>()) as _,
+            moves.as_mut_ptr(),
+            &mut required,
+        ) != S_OK
+        {
+            return;
+        }
+        moves.set_len(required as usize / mem::size_of::<DXGI_OUTDUPL_MOVE_RECT>());
+        let mut dirty: Vec<RECT> = Vec::with_capacity(size / mem::size_of::<RECT>() + 1);
+        if (*self.duplication.0).GetFrameDirtyRects(
+            (dirty.capacity() * mem::size_of::<RECT>()) as _,
+            dirty.as_mut_ptr(),
+            &mut required,
+        ) != S_OK
+        {
+            return;
+        }
+        dirty.set_len(required as usize / mem::size_of::<RECT>());
+        let (width, height) = (self.width as LONG, self.height as LONG);
+        let rects = moves.iter().map(|m| m.DestinationRect).chain(dirty);
+        for r in rects {
+            let (left, top) = (r.left.max(0), r.top.max(0));
+            let (right, bottom) = (r.right.min(width), r.bottom.min(height));
+            if right > left && bottom > top {
+                self.damage.push(DamageRect {
+                    x: left as _,
+                    y: top as _,
+                    w: (right - left) as _,
+                    h: (bottom - top) as _,
+                });
+            }
+        }
+        self.damage_known = true;
+    }
+
     // copy from GPU memory to system memory
     unsafe fn ohgodwhat(&mut self, frame: *mut IDXGIResource) -> io::Result<*mut IDXGISurface> {
         let mut texture: *mut ID3D11Texture2D = ptr::null_mut();
@@ -396,15 +456,17 @@ impl Capturer {
         } else {
             let width = self.width;
             let height = self.height;
-            Ok(Frame::PixelBuffer(PixelBuffer::with_BGRA(
-                self.get_pixelbuffer(timeout)?,
-                width,
-                height,
-            )))
+            let (data, damage) = self.get_pixelbuffer(timeout)?;
+            Ok(Frame::PixelBuffer(
+                PixelBuffer::with_BGRA(data, width, height).with_damage(damage),
+            ))
         }
     }
 
-    fn get_pixelbuffer<'a>(&'a mut self, timeout: UINT) -> io::Result<&'a [u8]> {
+    fn get_pixelbuffer<'a>(
+        &'a mut self,
+        timeout: UINT,
+    ) -> io::Result<(&'a [u8], Option<&'a [DamageRect]>)> {
         unsafe {
             // Release last frame.
             // No error checking needed because we don't care.
@@ -413,9 +475,15 @@ impl Capturer {
                 if let Some(gdi_capturer) = &self.gdi_capturer {
                     match gdi_capturer.frame(&mut self.gdi_buffer) {
                         Ok(_) => {
-                            crate::would_block_if_equal(
+                            self.damage_known = true;
+                            crate::would_block_or_damage(
                                 &mut self.saved_raw_data,
                                 &self.gdi_buffer,
+                                self.width,
+                                self.height,
+                                self.width * 4,
+                                4,
+                                &mut self.damage,
                             )?;
                             &self.gdi_buffer
                         }
@@ -463,7 +531,7 @@ impl Capturer {
                     }
                 }
             };
-            Ok(result)
+            Ok((result, self.damage_known.then(|| &self.damage[..])))
         }
     }
 
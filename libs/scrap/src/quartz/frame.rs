@@ -5,7 +5,8 @@ use super::ffi::*;
 pub struct Frame {
     surface: IOSurfaceRef,
     inner: &'static [u8],
-    bgra: Vec<u8>,
+    // The BGRA plane of the surface, valid while the surface is locked.
+    bgra: &'static [u8],
     bgra_stride: usize,
 }
 
@@ -24,7 +25,7 @@ impl Frame {
         Frame {
             surface,
             inner,
-            bgra: Vec::new(),
+            bgra: &[],
             bgra_stride: 0,
         }
     }
@@ -38,16 +39,13 @@ impl Frame {
         self.bgra_stride
     }
 
-    pub fn surface_to_bgra<'a>(&'a mut self, h: usize) {
+    /// Map the BGRA plane of the surface without copying it, the surface stays locked until
+    /// the frame is dropped, so the converter reads the captured memory directly.
+    pub fn map_bgra(&mut self, h: usize) {
         unsafe {
             let plane0 = IOSurfaceGetBaseAddressOfPlane(self.surface, 0);
             self.bgra_stride = IOSurfaceGetBytesPerRowOfPlane(self.surface, 0);
-            self.bgra.resize(self.bgra_stride * h, 0);
-            std::ptr::copy_nonoverlapping(
-                plane0 as _,
-                self.bgra.as_mut_ptr(),
-                self.bgra_stride * h,
-            );
+            self.bgra = slice::from_raw_parts(plane0 as *const u8, self.bgra_stride * h);
         }
     }
 }
@@ -55,7 +53,7 @@ impl Frame {
 impl ops::Deref for Frame {
     type Target = [u8];
     fn deref<'a>(&'a self) -> &'a [u8] {
-        &self.bgra
+        self.bgra
     }
 }
 
@@ -9,6 +9,7 @@ mod config;
 mod display;
 pub mod ffi;
 mod frame;
+pub mod screencapturekit;
 
 use std::sync::{Arc, Mutex};
 
@@ -0,0 +1,184 @@
+// The capture with ScreenCaptureKit on macOS 13+, see `screencapturekit.rs`.
+// ScreenCaptureKit is linked weakly, so it must be checked with `sck_is_available` first.
+
+#import <Foundation/Foundation.h>
+#import <CoreMedia/CoreMedia.h>
+#import <CoreVideo/CoreVideo.h>
+#import <ScreenCaptureKit/ScreenCaptureKit.h>
+
+typedef void (*sck_frame_callback)(void *ctx, IOSurfaceRef surface);
+
+API_AVAILABLE(macos(13.0))
+@interface SckOutput : NSObject <SCStreamOutput, SCStreamDelegate>
+@property(nonatomic) sck_frame_callback callback;
+@property(nonatomic) void *ctx;
+@end
+
+@implementation SckOutput
+- (void)stream:(SCStream *)stream
+    didOutputSampleBuffer:(CMSampleBufferRef)sampleBuffer
+                   ofType:(SCStreamOutputType)type {
+    if (type != SCStreamOutputTypeScreen || !CMSampleBufferIsValid(sampleBuffer)) {
+        return;
+    }
+    // Only the complete frames have the content, the idle ones repeat the last frame.
+    CFArrayRef attachments = CMSampleBufferGetSampleAttachmentsArray(sampleBuffer, false);
+    if (attachments == NULL || CFArrayGetCount(attachments) == 0) {
+        return;
+    }
+    NSDictionary *info = (__bridge NSDictionary *)CFArrayGetValueAtIndex(attachments, 0);
+    NSNumber *status = info[SCStreamFrameInfoStatus];
+    if (status == nil || status.integerValue != SCFrameStatusComplete) {
+        return;
+    }
+    CVPixelBufferRef pixelBuffer = CMSampleBufferGetImageBuffer(sampleBuffer);
+    if (pixelBuffer == NULL) {
+        return;
+    }
+    IOSurfaceRef surface = CVPixelBufferGetIOSurface(pixelBuffer);
+    if (surface != NULL) {
+        self.callback(self.ctx, surface);
+    }
+}
+
+- (void)stream:(SCStream *)stream didStopWithError:(NSError *)error {
+    NSLog(@"ScreenCaptureKit stream stopped: %@", error);
+}
+@end
+
+API_AVAILABLE(macos(13.0))
+@interface SckHandle : NSObject
+@property(nonatomic, strong) SCStream *stream;
+@property(nonatomic, strong) SckOutput *output;
+@property(nonatomic, strong) dispatch_queue_t queue;
+@end
+
+@implementation SckHandle
+@end
+
+int sck_is_available(void) {
+    if (@available(macOS 13.0, *)) {
+        return NSClassFromString(@"SCStream") != nil;
+    }
+    return 0;
+}
+
+API_AVAILABLE(macos(13.0))
+static SCShareableContent *sck_shareable_content(void) {
+    __block SCShareableContent *result = nil;
+    dispatch_semaphore_t sem = dispatch_semaphore_create(0);
+    [SCShareableContent getShareableContentExcludingDesktopWindows:NO
+                                               onScreenWindowsOnly:YES
+                                                 completionHandler:^(SCShareableContent *content,
+                                                                     NSError *error) {
+                                                   if (error != nil) {
+                                                       NSLog(@"Failed to get the shareable content: %@",
+                                                             error);
+                                                   }
+                                                   result = content;
+                                                   dispatch_semaphore_signal(sem);
+                                                 }];
+    dispatch_semaphore_wait(sem, dispatch_time(DISPATCH_TIME_NOW, 5 * NSEC_PER_SEC));
+    return result;
+}
+
+// Capture the display `display_id`, or the window `window_id` if it's not 0.
+// Returns the handle to stop, or NULL on failure.
+void *sck_start(uint32_t display_id, uint32_t window_id, size_t width, size_t height,
+                int show_cursor, sck_frame_callback callback, void *ctx) {
+    if (!sck_is_available()) {
+        return NULL;
+    }
+    if (@available(macOS 13.0, *)) {
+        SCShareableContent *content = sck_shareable_content();
+        if (content == nil) {
+            return NULL;
+        }
+        SCContentFilter *filter = nil;
+        if (window_id != 0) {
+            for (SCWindow *window in content.windows) {
+                if (window.windowID == window_id) {
+                    filter = [[SCContentFilter alloc] initWithDesktopIndependentWindow:window];
+                    break;
+                }
+            }
+        } else {
+            for (SCDisplay *display in content.displays) {
+                if (display.displayID == display_id) {
+                    filter = [[SCContentFilter alloc] initWithDisplay:display excludingWindows:@[]];
+                    break;
+                }
+            }
+        }
+        if (filter == nil) {
+            NSLog(@"ScreenCaptureKit: display %u or window %u not found", display_id, window_id);
+            return NULL;
+        }
+
+        SCStreamConfiguration *config = [[SCStreamConfiguration alloc] init];
+        config.width = width;
+        config.height = height;
+        config.pixelFormat = kCVPixelFormatType_32BGRA;
+        config.showsCursor = show_cursor != 0;
+        config.queueDepth = 3;
+        // No limit, the video service controls the frame rate.
+        config.minimumFrameInterval = kCMTimeZero;
+#if defined(__MAC_15_0) && __MAC_OS_X_VERSION_MAX_ALLOWED >= __MAC_15_0
+        if (@available(macOS 15.0, *)) {
+            // Tone map the HDR content to SDR, the encoders take 8-bit BGRA only.
+            config.captureDynamicRange = SCCaptureDynamicRangeSDR;
+        }
+#endif
+
+        SckHandle *handle = [[SckHandle alloc] init];
+        handle.output = [[SckOutput alloc] init];
+        handle.output.callback = callback;
+        handle.output.ctx = ctx;
+        handle.queue = dispatch_queue_create("rustdesk.scrap.sck", DISPATCH_QUEUE_SERIAL);
+        handle.stream = [[SCStream alloc] initWithFilter:filter
+                                           configuration:config
+                                                delegate:handle.output];
+        NSError *error = nil;
+        if (![handle.stream addStreamOutput:handle.output
+                                       type:SCStreamOutputTypeScreen
+                         sampleHandlerQueue:handle.queue
+                                      error:&error]) {
+            NSLog(@"Failed to add the ScreenCaptureKit output: %@", error);
+            return NULL;
+        }
+
+        __block BOOL started = NO;
+        dispatch_semaphore_t sem = dispatch_semaphore_create(0);
+        [handle.stream startCaptureWithCompletionHandler:^(NSError *error) {
+          if (error != nil) {
+              NSLog(@"Failed to start ScreenCaptureKit: %@", error);
+          } else {
+              started = YES;
+          }
+          dispatch_semaphore_signal(sem);
+        }];
+        dispatch_semaphore_wait(sem, dispatch_time(DISPATCH_TIME_NOW, 5 * NSEC_PER_SEC));
+        if (!started) {
+            return NULL;
+        }
+        return (__bridge_retained void *)handle;
+    }
+    return NULL;
+}
+
+// Stop the capture and wait for it, no frame is called back after it returns.
+void sck_stop(void *ptr) {
+    if (ptr == NULL) {
+        return;
+    }
+    if (@available(macOS 13.0, *)) {
+        SckHandle *handle = (__bridge_transfer SckHandle *)ptr;
+        dispatch_semaphore_t sem = dispatch_semaphore_create(0);
+        [handle.stream stopCaptureWithCompletionHandler:^(NSError *error) {
+          dispatch_semaphore_signal(sem);
+        }];
+        dispatch_semaphore_wait(sem, dispatch_time(DISPATCH_TIME_NOW, 5 * NSEC_PER_SEC));
+        // Drain the frames queued before the stop.
+        dispatch_sync(handle.queue, ^{});
+    }
+}
@@ -0,0 +1,114 @@
+//! The capture with ScreenCaptureKit on macOS 13+, with the shim `screencapturekit.m`.
+//!
+//! It captures the displays or the single windows, only delivers the frames when the content
+//! changes, and tone maps the HDR content to SDR on macOS 15+. [`is_available`] must be checked
+//! first, the framework is linked weakly for the older systems to use the CGDisplayStream of
+//! [`super::Capturer`].
+use super::ffi::IOSurfaceRef;
+use super::frame::Frame;
+use hbb_common::libc::c_void;
+use std::io;
+
+type FrameCallback = extern "C" fn(ctx: *mut c_void, surface: IOSurfaceRef);
+
+extern "C" {
+    fn sck_is_available() -> i32;
+    fn sck_start(
+        display_id: u32,
+        window_id: u32,
+        width: usize,
+        height: usize,
+        show_cursor: i32,
+        callback: FrameCallback,
+        ctx: *mut c_void,
+    ) -> *mut c_void;
+    fn sck_stop(handle: *mut c_void);
+}
+
+type Handler = Box<dyn Fn(Frame)>;
+
+pub fn is_available() -> bool {
+    unsafe { sck_is_available() != 0 }
+}
+
+pub enum Source {
+    Display(u32),
+    Window(u32),
+}
+
+pub struct Capturer {
+    handle: *mut c_void,
+    // Boxed twice for a thin pointer to the shim.
+    handler: *mut Handler,
+    width: usize,
+    height: usize,
+}
+
+extern "C" fn on_frame(ctx: *mut c_void, surface: IOSurfaceRef) {
+    let handler = unsafe { &*(ctx as *const Handler) };
+    handler(unsafe { Frame::new(surface) });
+}
+
+impl Capturer {
+    pub fn new<F: Fn(Frame) + 'static>(
+        source: Source,
+        width: usize,
+        height: usize,
+        show_cursor: bool,
+        handler: F,
+    ) -> io::Result<Capturer> {
+        if !is_available() {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "ScreenCaptureKit is not available",
+            ));
+        }
+        let (display_id, window_id) = match source {
+            Source::Display(id) => (id, 0),
+            Source::Window(id) => (0, id),
+        };
+        let handler: *mut Handler = Box::into_raw(Box::new(Box::new(handler)));
+        let handle = unsafe {
+            sck_start(
+                display_id,
+                window_id,
+                width,
+                height,
+                show_cursor as _,
+                on_frame,
+                handler as _,
+            )
+        };
+        if handle.is_null() {
+            drop(unsafe { Box::from_raw(handler) });
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Failed to start ScreenCaptureKit",
+            ));
+        }
+        Ok(Capturer {
+            handle,
+            handler,
+            width,
+            height,
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+}
+
+impl Drop for Capturer {
+    fn drop(&mut self) {
+        unsafe {
+            // No frame is called back after it.
+            sck_stop(self.handle);
+            drop(Box::from_raw(self.handler));
+        }
+    }
+}
@@ -45,8 +45,8 @@ pub fn try_close_session() {
     let mut rdp_info = RDP_SESSION_INFO.lock().unwrap();
     let mut close = false;
     if let Some(rdp_info) = &*rdp_info {
-        // If is server running and restore token is supported, there's no need to keep the session.
-        if is_server_running() && rdp_info.is_support_restore_token {
+        // If restore token is supported, there's no need to keep the session.
+        if rdp_info.is_support_restore_token {
             close = true;
         }
     }
@@ -502,6 +502,19 @@ fn streams_from_response(response: OrgFreedesktopPortalRequestResponse) -> Vec<P
 static mut INIT: bool = false;
 const RESTORE_TOKEN: &str = "restore_token";
 const RESTORE_TOKEN_CONF_KEY: &str = "wayland-restore-token";
+// The tokens of the two portals can't restore the sessions of each other.
+const REMOTE_DESKTOP_RESTORE_TOKEN_CONF_KEY: &str = "wayland-remote-desktop-restore-token";
+// Restore the session until it's revoked explicitly.
+const PERSIST_MODE_PERSISTENT: u32 = 2;
+
+#[inline]
+fn restore_token_conf_key(server_running: bool) -> &'static str {
+    if server_running {
+        RESTORE_TOKEN_CONF_KEY
+    } else {
+        REMOTE_DESKTOP_RESTORE_TOKEN_CONF_KEY
+    }
+}
 
 pub fn get_available_cursor_modes() -> Result<u32, dbus::Error> {
     let conn = SyncConnection::new_session()?;
@@ -546,12 +559,13 @@ pub fn request_remote_desktop() -> Result<
         Variant(Box::new("u1".to_string())),
     );
 
-    let mut is_support_restore_token = false;
-    if let Ok(version) = screencast_portal::version(&portal) {
-        if version >= 4 {
-            is_support_restore_token = true;
-        }
-    }
+    // `restore_token` and `persist_mode` are supported since version 4 of the ScreenCast portal,
+    // and version 2 of the RemoteDesktop portal.
+    let is_support_restore_token = if is_server_running() {
+        screencast_portal::version(&portal).map_or(false, |v| v >= 4)
+    } else {
+        remote_desktop_portal::version(&portal).map_or(false, |v| v >= 2)
+    };
 
     // The following code may be improved.
     // https://flatpak.github.io/xdg-desktop-portal/#:~:text=To%20avoid%20a%20race%20condition
@@ -654,7 +668,10 @@ fn on_create_session_response(
                     args.insert(RESTORE_TOKEN.to_string(), Variant(Box::new(restore_token)));
                 }
                 // persist_mode may be configured by the user.
-                args.insert("persist_mode".to_string(), Variant(Box::new(2u32)));
+                args.insert(
+                    "persist_mode".to_string(),
+                    Variant(Box::new(PERSIST_MODE_PERSISTENT)),
+                );
             }
             args.insert(
                 "handle_token".to_string(),
@@ -680,9 +697,18 @@ fn on_create_session_response(
                 failure.clone(),
             )?;
         } else {
-            // TODO: support persist_mode for remote_desktop_portal
             // https://flatpak.github.io/xdg-desktop-portal/docs/doc-org.freedesktop.portal.RemoteDesktop.html
-
+            if is_support_restore_token {
+                let restore_token =
+                    config::LocalConfig::get_option(REMOTE_DESKTOP_RESTORE_TOKEN_CONF_KEY);
+                if !restore_token.is_empty() {
+                    args.insert(RESTORE_TOKEN.to_string(), Variant(Box::new(restore_token)));
+                }
+                args.insert(
+                    "persist_mode".to_string(),
+                    Variant(Box::new(PERSIST_MODE_PERSISTENT)),
+                );
+            }
             args.insert(
                 "handle_token".to_string(),
                 Variant(Box::new("u2".to_string())),
@@ -804,15 +830,13 @@ fn on_start_response(
     move |r: OrgFreedesktopPortalRequestResponse, c, _| {
         let portal = get_portal(c);
         // See `is_server_running()` to understand the following code.
-        if is_server_running() {
-            if is_support_restore_token {
-                if let Some(restore_token) = r.results.get(RESTORE_TOKEN) {
-                    if let Some(restore_token) = restore_token.as_str() {
-                        config::LocalConfig::set_option(
-                            RESTORE_TOKEN_CONF_KEY.to_owned(),
-                            restore_token.to_owned(),
-                        );
-                    }
+        if is_support_restore_token {
+            if let Some(restore_token) = r.results.get(RESTORE_TOKEN) {
+                if let Some(restore_token) = restore_token.as_str() {
+                    config::LocalConfig::set_option(
+                        restore_token_conf_key(is_server_running()).to_owned(),
+                        restore_token.to_owned(),
+                    );
                 }
             }
         }
@@ -882,7 +906,7 @@ pub fn get_capturables() -> Result<Vec<PipeWireCapturable>, Box<dyn Error>> {
 // Otherwise, we have to use remote_desktop_portal's input method.
 //
 // `screencast_portal` supports restore_token and persist_mode if the version is greater than or equal to 4.
-// `remote_desktop_portal` does not support restore_token and persist_mode.
+// `remote_desktop_portal` supports them if the version is greater than or equal to 2.
 fn is_server_running() -> bool {
     let app_name = config::APP_NAME.read().unwrap().clone().to_lowercase();
     let output = match Command::new(CMD_SH.as_str())
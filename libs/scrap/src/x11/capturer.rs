@@ -1,5 +1,6 @@
 use super::ffi::*;
 use super::Display;
+use crate::DamageRect;
 use hbb_common::libc;
 use std::{io, ptr, slice};
 
@@ -11,6 +12,7 @@ pub struct Capturer {
 
     size: usize,
     saved_raw_data: Vec<u8>, // for faster compare and copy
+    damage: Vec<DamageRect>,
 }
 
 impl Capturer {
@@ -64,6 +66,7 @@ impl Capturer {
             buffer,
             size,
             saved_raw_data: Vec::new(),
+            damage: Vec::new(),
         };
         Ok(c)
     }
@@ -93,11 +96,23 @@ impl Capturer {
         }
     }
 
-    pub fn frame<'b>(&'b mut self) -> std::io::Result<&'b [u8]> {
+    /// The frame and the regions changed since the last frame.
+    pub fn frame<'b>(&'b mut self) -> std::io::Result<(&'b [u8], &'b [DamageRect])> {
         self.get_image();
         let result = unsafe { slice::from_raw_parts(self.buffer, self.size) };
-        crate::would_block_if_equal(&mut self.saved_raw_data, result)?;
-        Ok(result)
+        let rect = self.display.rect();
+        let (width, height) = (rect.w as usize, rect.h as usize);
+        let bytes_per_pixel = self.display.pixfmt().bytes_per_pixel();
+        crate::would_block_or_damage(
+            &mut self.saved_raw_data,
+            result,
+            width,
+            height,
+            width * bytes_per_pixel,
+            bytes_per_pixel,
+            &mut self.damage,
+        )?;
+        Ok((result, &self.damage))
     }
 }
 
@@ -0,0 +1,164 @@
+//! The annotations drawn by the client on the screen of the host, e.g. the arrows and boxes of
+//! the "click here" guidance in the remote support.
+//!
+//! The client sends [`Annotations`] with a plugin request of the reserved id [`REQUEST_ID`]. The
+//! host renders them with the whiteboard overlay, the one showing the cursors of the peers, so
+//! they're also in the video stream for the other viewers. It's allowed if the permission
+//! [`OPTION_ENABLE`] is on, the default, else the host replies with [`Annotations::Error`]. The
+//! annotations of a connection are cleared when it stops annotating or is closed.
+//!
+//! The coordinates are the ones of the mouse events, i.e. of the remote screen.
+use hbb_common::message_proto::Message;
+use serde_derive::{Deserialize, Serialize};
+
+pub const REQUEST_ID: &str = crate::common::plugin_request_id::ANNOTATION;
+pub const OPTION_ENABLE: &str = "enable-annotation";
+pub const ERR_DENIED: &str = "annotation-denied-tip";
+/// The annotations kept for a connection, the oldest ones are dropped beyond it.
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+pub const MAX_ANNOTATIONS: usize = 256;
+/// The points of a freehand stroke beyond it are dropped.
+pub const MAX_STROKE_POINTS: usize = 4096;
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+const ARROW_HEAD_LENGTH: f32 = 16.0;
+// The angle between the shaft and each side of the head, in radians.
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+const ARROW_HEAD_ANGLE: f32 = std::f32::consts::PI / 7.0;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "t", content = "c")]
+pub enum Shape {
+    Arrow {
+        from: (f32, f32),
+        to: (f32, f32),
+    },
+    /// The box with the opposite corners.
+    Rect {
+        from: (f32, f32),
+        to: (f32, f32),
+    },
+    Stroke {
+        points: Vec<(f32, f32)>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Annotation {
+    pub shape: Shape,
+    pub argb: u32,
+    /// The width of the lines in pixels.
+    pub width: f32,
+}
+
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+impl Annotation {
+    /// The polylines to stroke, the arrow head is one of them.
+    pub fn polylines(&self) -> Vec<Vec<(f32, f32)>> {
+        match &self.shape {
+            Shape::Arrow { from, to } => {
+                let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+                if dx == 0.0 && dy == 0.0 {
+                    return vec![];
+                }
+                let angle = dy.atan2(dx);
+                let length = ARROW_HEAD_LENGTH + self.width * 2.0;
+                let side = |a: f32| (to.0 - length * a.cos(), to.1 - length * a.sin());
+                vec![
+                    vec![*from, *to],
+                    vec![
+                        side(angle - ARROW_HEAD_ANGLE),
+                        *to,
+                        side(angle + ARROW_HEAD_ANGLE),
+                    ],
+                ]
+            }
+            Shape::Rect { from, to } => {
+                vec![vec![*from, (to.0, from.1), *to, (from.0, to.1), *from]]
+            }
+            Shape::Stroke { points } => vec![points.clone()],
+        }
+    }
+
+    /// Move it by the offset, e.g. to the coordinates of the overlay window.
+    #[cfg(any(target_os = "macos", test))]
+    pub fn translate(&self, dx: f32, dy: f32) -> Self {
+        let t = |p: &(f32, f32)| (p.0 + dx, p.1 + dy);
+        let shape = match &self.shape {
+            Shape::Arrow { from, to } => Shape::Arrow {
+                from: t(from),
+                to: t(to),
+            },
+            Shape::Rect { from, to } => Shape::Rect {
+                from: t(from),
+                to: t(to),
+            },
+            Shape::Stroke { points } => Shape::Stroke {
+                points: points.iter().map(t).collect(),
+            },
+        };
+        Self {
+            shape,
+            ..self.clone()
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "t", content = "c")]
+pub enum Annotations {
+    /// From the client to show the overlay before drawing.
+    Start,
+    /// From the client to clear its annotations and hide the overlay if no one else uses it.
+    Stop,
+    /// From the client.
+    Draw(Annotation),
+    /// From the client to remove the last annotation.
+    Undo,
+    /// From the client to remove its annotations.
+    Clear,
+    /// From the host.
+    Error { error: String },
+}
+
+impl Annotations {
+    pub fn to_message(&self) -> Message {
+        crate::common::make_plugin_request(REQUEST_ID, serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    pub fn from_message(msg: &Message) -> Option<Self> {
+        serde_json::from_slice(crate::common::get_plugin_request(msg, REQUEST_ID)?).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_polylines() {
+        let arrow = Annotation {
+            shape: Shape::Arrow {
+                from: (0.0, 0.0),
+                to: (100.0, 0.0),
+            },
+            argb: 0xFFFF0000,
+            width: 2.0,
+        };
+        let lines = arrow.polylines();
+        assert_eq!(lines.len(), 2);
+        // The head points back to the start on both sides of the shaft.
+        let (left, right) = (lines[1][0], lines[1][2]);
+        assert!(left.0 < 100.0 && right.0 < 100.0);
+        assert!(left.1 < 0.0 && right.1 > 0.0);
+        let rect = Annotation {
+            shape: Shape::Rect {
+                from: (10.0, 10.0),
+                to: (20.0, 30.0),
+            },
+            ..arrow.clone()
+        };
+        assert_eq!(rect.translate(-10.0, -10.0).polylines()[0][2], (10.0, 20.0));
+        let msg = Annotations::Draw(rect);
+        assert_eq!(Annotations::from_message(&msg.to_message()), Some(msg));
+    }
+}
@@ -0,0 +1,141 @@
+//! Echo cancellation and noise suppression of the voice call, with webrtc-audio-processing.
+//!
+//! The audio played by the [`crate::client::AudioHandler`]s is the far end reference,
+//! the echo of it is removed from the microphone input of the audio service before encoding,
+//! so the peer doesn't hear itself when the remote audio is played on the same device.
+//! It is active only while the voice call input device is set.
+use hbb_common::{lazy_static, log};
+use std::sync::Mutex;
+use webrtc_audio_processing::{
+    Config, EchoCancellation, EchoCancellationSuppressionLevel, InitializationConfig,
+    NoiseSuppression, NoiseSuppressionLevel, Processor, NUM_SAMPLES_PER_FRAME,
+};
+
+// The processor works on 10 ms mono frames at 48 kHz.
+const SAMPLE_RATE: u32 = 48000;
+const FRAME_SIZE: usize = NUM_SAMPLES_PER_FRAME as usize;
+// Drop the reference older than 1 s if the capture stops.
+const MAX_RENDER_BUFFER: usize = FRAME_SIZE * 100;
+
+lazy_static::lazy_static! {
+    static ref PROCESSOR: Mutex<Option<VoiceProcessor>> = Default::default();
+}
+
+struct VoiceProcessor {
+    processor: Processor,
+    // The reference not processed yet, less than a frame.
+    render: Vec<f32>,
+}
+
+impl VoiceProcessor {
+    fn new() -> Result<Self, String> {
+        let mut processor = Processor::new(&InitializationConfig {
+            num_capture_channels: 1,
+            num_render_channels: 1,
+            ..Default::default()
+        })
+        .map_err(|e| format!("{:?}", e))?;
+        processor.set_config(Config {
+            echo_cancellation: Some(EchoCancellation {
+                suppression_level: EchoCancellationSuppressionLevel::High,
+                enable_extended_filter: true,
+                // The delay between the playback and the capture is unknown.
+                enable_delay_agnostic: true,
+                stream_delay_ms: None,
+            }),
+            noise_suppression: Some(NoiseSuppression {
+                suppression_level: NoiseSuppressionLevel::High,
+            }),
+            enable_high_pass_filter: true,
+            ..Default::default()
+        });
+        Ok(Self {
+            processor,
+            render: Vec::new(),
+        })
+    }
+}
+
+/// Start the processing when the voice call starts, stop it when the call ends.
+pub fn set_enabled(enabled: bool) {
+    let mut lock = PROCESSOR.lock().unwrap();
+    if enabled == lock.is_some() {
+        return;
+    }
+    if enabled {
+        match VoiceProcessor::new() {
+            Ok(p) => {
+                log::info!("Voice call echo cancellation and noise suppression started");
+                *lock = Some(p);
+            }
+            Err(e) => log::error!("Failed to create the audio processor: {}", e),
+        }
+    } else {
+        log::info!("Voice call echo cancellation and noise suppression stopped");
+        *lock = None;
+    }
+}
+
+/// Feed the decoded audio to be played as the far end reference.
+pub fn push_render(data: &[f32], sample_rate: u32, channels: u16) {
+    let mut lock = PROCESSOR.lock().unwrap();
+    let Some(p) = lock.as_mut() else {
+        return;
+    };
+    p.render.extend(to_mono_48k(data, sample_rate, channels));
+    if p.render.len() > MAX_RENDER_BUFFER {
+        let n = p.render.len() - MAX_RENDER_BUFFER;
+        p.render.drain(0..n);
+    }
+    let frames = p.render.len() / FRAME_SIZE;
+    for i in 0..frames {
+        let frame = &mut p.render[i * FRAME_SIZE..(i + 1) * FRAME_SIZE];
+        if let Err(e) = p.processor.process_render_frame(frame) {
+            log::trace!("Failed to process the render frame: {:?}", e);
+        }
+    }
+    p.render.drain(0..frames * FRAME_SIZE);
+}
+
+/// Remove the echo and the noise from the captured audio in place.
+///
+/// The input must be whole 10 ms frames, the output has the same length and channels,
+/// with the processed mono signal in all channels.
+pub fn process_capture(data: &mut [f32], sample_rate: u32, channels: u16) {
+    let mut lock = PROCESSOR.lock().unwrap();
+    let Some(p) = lock.as_mut() else {
+        return;
+    };
+    let channels = channels.max(1) as usize;
+    let mut mono = to_mono_48k(data, sample_rate, channels as _);
+    if mono.is_empty() || mono.len() % FRAME_SIZE != 0 {
+        log::trace!("Skip processing the capture of {} samples", data.len());
+        return;
+    }
+    for frame in mono.chunks_mut(FRAME_SIZE) {
+        if let Err(e) = p.processor.process_capture_frame(frame) {
+            log::trace!("Failed to process the capture frame: {:?}", e);
+            return;
+        }
+    }
+    drop(lock);
+    if sample_rate != SAMPLE_RATE {
+        mono = crate::audio_resample(&mono, SAMPLE_RATE, sample_rate, 1);
+    }
+    for (frame, v) in data.chunks_mut(channels).zip(mono.into_iter()) {
+        frame.iter_mut().for_each(|x| *x = v);
+    }
+}
+
+fn to_mono_48k(data: &[f32], sample_rate: u32, channels: u16) -> Vec<f32> {
+    let channels = channels.max(1) as usize;
+    let mono: Vec<f32> = data
+        .chunks_exact(channels)
+        .map(|f| f.iter().sum::<f32>() / channels as f32)
+        .collect();
+    if sample_rate == SAMPLE_RATE {
+        mono
+    } else {
+        crate::audio_resample(&mono, sample_rate, SAMPLE_RATE, 1)
+    }
+}
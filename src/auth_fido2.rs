@@ -0,0 +1,233 @@
+//! FIDO2 security keys as a second factor of the login, for the unattended access
+//! where the passwords alone are not acceptable.
+//!
+//! A key is registered on the host with the key plugged in, only its credential id and public key
+//! are saved to the option [`OPTION_CREDENTIALS`]. If any key is registered, after the password
+//! and the TOTP 2FA, the host sends a random challenge with a plugin request of the reserved id
+//! [`REQUEST_ID`] and the login error [`LOGIN_MSG_REQUIRED`]. The client asks its key to sign
+//! the challenge, the user touches the key, and the host verifies the assertion.
+//!
+//! Talking to the keys and verifying the assertions need the `fido2` feature.
+//! The hosts built without it can't register keys, so they never require them.
+use hbb_common::{config::Config, message_proto::Message};
+use serde_derive::{Deserialize, Serialize};
+
+pub const REQUEST_ID: &str = crate::common::plugin_request_id::FIDO2;
+pub const OPTION_CREDENTIALS: &str = "fido2-credentials";
+pub const LOGIN_MSG_REQUIRED: &str = "Security Key Required";
+pub const LOGIN_MSG_WRONG: &str = "Wrong Security Key";
+// The same relying party for all the hosts, the key has a credential for each host.
+#[cfg(feature = "fido2")]
+const RP_ID: &str = "rustdesk.com";
+const CHALLENGE_LEN: usize = 32;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Credential {
+    pub name: String,
+    pub id: Vec<u8>,
+    /// The PEM of the P-256 public key.
+    pub public_key: String,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "t", content = "c")]
+pub enum Fido2 {
+    /// From the host, the credential ids are the ones allowed to sign.
+    Challenge {
+        challenge: Vec<u8>,
+        credential_ids: Vec<Vec<u8>>,
+    },
+    /// From the client, the signature is of the authenticator data
+    /// followed by the SHA-256 of the challenge.
+    Assertion {
+        credential_id: Vec<u8>,
+        auth_data: Vec<u8>,
+        signature: Vec<u8>,
+    },
+}
+
+impl Fido2 {
+    pub fn to_message(&self) -> Message {
+        crate::common::make_plugin_request(REQUEST_ID, serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    pub fn from_message(msg: &Message) -> Option<Self> {
+        serde_json::from_slice(crate::common::get_plugin_request(msg, REQUEST_ID)?).ok()
+    }
+}
+
+pub fn parse_credentials(raw: &str) -> Vec<Credential> {
+    serde_json::from_str(raw).unwrap_or_default()
+}
+
+/// The keys registered on the host.
+pub fn get_credentials() -> Vec<Credential> {
+    if !cfg!(feature = "fido2") {
+        return vec![];
+    }
+    parse_credentials(&Config::get_option(OPTION_CREDENTIALS))
+}
+
+/// A new random challenge.
+pub fn new_challenge() -> Vec<u8> {
+    (0..CHALLENGE_LEN)
+        .map(|_| hbb_common::rand::random::<u8>())
+        .collect()
+}
+
+/// Verify the assertion of the client with the public key of the credential.
+#[cfg(feature = "fido2")]
+pub fn verify(
+    credentials: &[Credential],
+    challenge: &[u8],
+    credential_id: &[u8],
+    auth_data: &[u8],
+    signature: &[u8],
+) -> bool {
+    use hbb_common::log;
+    use p256::{
+        ecdsa::{signature::Verifier, Signature, VerifyingKey},
+        pkcs8::DecodePublicKey,
+    };
+    use sha2::{Digest, Sha256};
+
+    let Some(credential) = credentials.iter().find(|c| c.id == credential_id) else {
+        log::warn!("Unknown security key credential");
+        return false;
+    };
+    // The authenticator data starts with the hash of the relying party id and the flags,
+    // the user present flag is required.
+    if auth_data.len() < 37
+        || auth_data[..32] != Sha256::digest(RP_ID.as_bytes())[..]
+        || auth_data[32] & 0x01 == 0
+    {
+        log::warn!(
+            "Invalid authenticator data of security key {}",
+            credential.name
+        );
+        return false;
+    }
+    let key = match VerifyingKey::from_public_key_pem(&credential.public_key) {
+        Ok(key) => key,
+        Err(e) => {
+            log::error!(
+                "Invalid public key of security key {}: {}",
+                credential.name,
+                e
+            );
+            return false;
+        }
+    };
+    let Ok(signature) = Signature::from_der(signature) else {
+        return false;
+    };
+    let mut data = auth_data.to_vec();
+    data.extend(Sha256::digest(challenge));
+    key.verify(&data, &signature).is_ok()
+}
+
+#[cfg(not(feature = "fido2"))]
+pub fn verify(
+    _credentials: &[Credential],
+    _challenge: &[u8],
+    _credential_id: &[u8],
+    _auth_data: &[u8],
+    _signature: &[u8],
+) -> bool {
+    false
+}
+
+/// Create a credential on the key plugged in, the `pin` is required if the key has one.
+#[cfg(all(feature = "fido2", not(any(target_os = "android", target_os = "ios"))))]
+pub fn make_credential(name: String, pin: &str) -> hbb_common::ResultType<Credential> {
+    use ctap_hid_fido2::{fidokey::MakeCredentialArgsBuilder, verifier, Cfg, FidoKeyHidFactory};
+
+    let device = FidoKeyHidFactory::create(&Cfg::init())?;
+    let challenge = verifier::create_challenge();
+    let builder = MakeCredentialArgsBuilder::new(RP_ID, &challenge);
+    let args = if pin.is_empty() {
+        builder.without_pin_and_uv()
+    } else {
+        builder.pin(pin)
+    }
+    .build();
+    let attestation = device.make_credential_with_args(&args)?;
+    let res = verifier::verify_attestation(RP_ID, &challenge, &attestation);
+    if !res.is_success {
+        hbb_common::bail!("Failed to verify the attestation of the security key");
+    }
+    Ok(Credential {
+        name,
+        id: res.credential_id,
+        public_key: res.credential_public_key.pem,
+        created_at: hbb_common::get_time(),
+    })
+}
+
+/// Sign the challenge of the host with the key plugged in, it blocks until the user touches it.
+#[cfg(all(feature = "fido2", not(any(target_os = "android", target_os = "ios"))))]
+pub fn get_assertion(
+    challenge: &[u8],
+    credential_ids: &[Vec<u8>],
+) -> hbb_common::ResultType<Fido2> {
+    use ctap_hid_fido2::{fidokey::GetAssertionArgsBuilder, Cfg, FidoKeyHidFactory};
+
+    let device = FidoKeyHidFactory::create(&Cfg::init())?;
+    let mut builder = GetAssertionArgsBuilder::new(RP_ID, challenge).without_pin_and_uv();
+    for id in credential_ids {
+        builder = builder.add_credential_id(id);
+    }
+    let Some(assertion) = device
+        .get_assertion_with_args(&builder.build())?
+        .into_iter()
+        .next()
+    else {
+        hbb_common::bail!("No assertion of the security key");
+    };
+    Ok(Fido2::Assertion {
+        credential_id: assertion.credential_id,
+        auth_data: assertion.auth_data,
+        signature: assertion.signature,
+    })
+}
+
+#[cfg(all(test, feature = "fido2"))]
+mod tests {
+    use super::*;
+    use p256::{
+        ecdsa::{signature::Signer, Signature, SigningKey},
+        pkcs8::{EncodePublicKey, LineEnding},
+    };
+    use sha2::{Digest, Sha256};
+
+    #[test]
+    fn test_verify() {
+        let key = SigningKey::from_bytes(&[7u8; 32].into()).unwrap();
+        let credentials = vec![Credential {
+            name: "key".to_owned(),
+            id: vec![1, 2, 3],
+            public_key: key
+                .verifying_key()
+                .to_public_key_pem(LineEnding::LF)
+                .unwrap(),
+            created_at: 0,
+        }];
+        let challenge = [9u8; CHALLENGE_LEN];
+        let mut auth_data = Sha256::digest(RP_ID.as_bytes()).to_vec();
+        auth_data.extend([0x01, 0, 0, 0, 1]);
+        let mut data = auth_data.clone();
+        data.extend(Sha256::digest(challenge));
+        let signature: Signature = key.sign(&data);
+        let signature = signature.to_der().as_bytes().to_vec();
+        let check = |challenge: &[u8], id: &[u8], auth_data: &[u8]| {
+            verify(&credentials, challenge, id, auth_data, &signature)
+        };
+        assert!(check(&challenge, &[1, 2, 3], &auth_data));
+        assert!(!check(&[0u8; 32], &[1, 2, 3], &auth_data));
+        assert!(!check(&challenge, &[4], &auth_data));
+        // The user is not present.
+        auth_data[32] = 0;
+        assert!(!check(&challenge, &[1, 2, 3], &auth_data));
+    }
+}
@@ -1,17 +1,42 @@
+//! The command line tool, with the subcommands for the scripts, e.g. the CI jobs and the
+//! provisioning.
+//!
+//! The subcommands print one JSON object to stdout, `{"ok": true, ...}` or
+//! `{"ok": false, "error": "..."}`, and exit with one of the `EXIT_*` codes. The logs go to
+//! stderr. They never prompt for the password, the one of `--password` or the saved one of the
+//! peer is used.
 use crate::client::*;
 use async_trait::async_trait;
 use hbb_common::{
     config::PeerConfig,
     config::READ_TIMEOUT,
+    fs,
     futures::{SinkExt, StreamExt},
     log,
     message_proto::*,
     protobuf::Message as _,
     rendezvous_proto::ConnType,
-    tokio::{self, sync::mpsc},
+    timeout,
+    tokio::{self, sync::mpsc, time},
     Stream,
 };
-use std::sync::{Arc, RwLock};
+use serde_json::{json, Value};
+use std::{
+    path::PathBuf,
+    sync::{Arc, RwLock},
+};
+
+pub const EXIT_OK: i32 = 0;
+/// The invalid arguments, or the local failures.
+pub const EXIT_ERROR: i32 = 1;
+/// The peer can't be connected.
+pub const EXIT_CONNECT: i32 = 2;
+/// The login is rejected by the peer, e.g. the wrong password.
+pub const EXIT_AUTH: i32 = 3;
+/// The file transfer failed.
+pub const EXIT_TRANSFER: i32 = 4;
+
+const SEND_FILE_JOB_ID: i32 = 1;
 
 #[derive(Clone)]
 pub struct Session {
@@ -19,28 +44,38 @@ pub struct Session {
     lc: Arc<RwLock<LoginConfigHandler>>,
     sender: mpsc::UnboundedSender<Data>,
     password: String,
+    interactive: bool,
 }
 
 impl Session {
+    /// Prompt for the password if the peer has no saved one.
     pub fn new(id: &str, sender: mpsc::UnboundedSender<Data>) -> Self {
         let mut password = "".to_owned();
         if PeerConfig::load(id).password.is_empty() {
             password = rpassword::prompt_password("Enter password: ").unwrap();
         }
+        Self::new_with(id, ConnType::PORT_FORWARD, password, true, sender)
+    }
+
+    pub fn new_with(
+        id: &str,
+        conn_type: ConnType,
+        password: String,
+        interactive: bool,
+        sender: mpsc::UnboundedSender<Data>,
+    ) -> Self {
         let session = Self {
             id: id.to_owned(),
             sender,
             password,
+            interactive,
             lc: Default::default(),
         };
-        session.lc.write().unwrap().initialize(
-            id.to_owned(),
-            ConnType::PORT_FORWARD,
-            None,
-            false,
-            None,
-            None,
-        );
+        session
+            .lc
+            .write()
+            .unwrap()
+            .initialize(id.to_owned(), conn_type, None, false, None, None);
         session
     }
 }
@@ -55,14 +90,20 @@ impl Interface for Session {
         match msgtype {
             "input-password" => {
                 self.sender
-                    .send(Data::Login((self.password.clone(), true)))
+                    .send(Data::Login((
+                        "".to_owned(),
+                        "".to_owned(),
+                        self.password.clone(),
+                        true,
+                    )))
                     .ok();
             }
-            "re-input-password" => {
+            "re-input-password" if self.interactive => {
                 log::error!("{}: {}", title, text);
                 match rpassword::prompt_password("Enter password: ") {
                     Ok(password) => {
-                        let login_data = Data::Login((password, true));
+                        let login_data =
+                            Data::Login(("".to_owned(), "".to_owned(), password, true));
                         self.sender.send(login_data).ok();
                     }
                     Err(e) => {
@@ -70,7 +111,7 @@ impl Interface for Session {
                     }
                 }
             }
-            msg if msg.contains("error") => {
+            msg if msg.contains("error") || msg == "re-input-password" => {
                 log::error!("{}: {}: {}", msgtype, title, text);
             }
             _ => {
@@ -123,56 +164,254 @@ impl Interface for Session {
     }
 }
 
+/// Print the result of a subcommand, returns the exit code.
+pub fn output(res: Result<Value, (i32, String)>) -> i32 {
+    let (code, mut value) = match res {
+        Ok(value) => (EXIT_OK, value),
+        Err((code, error)) => (code, json!({ "error": error })),
+    };
+    if let Some(obj) = value.as_object_mut() {
+        obj.insert("ok".to_owned(), json!(code == EXIT_OK));
+    }
+    println!("{}", value);
+    code
+}
+
+/// `local-port:remote-port[:remote-host]`, the remote host is `localhost` by default.
+pub fn parse_port_forward(spec: &str) -> Option<(i32, i32, String)> {
+    let mut parts = spec.splitn(3, ':');
+    let port = parts.next()?.parse().ok()?;
+    let remote_port = parts.next()?.parse().ok()?;
+    let remote_host = parts
+        .next()
+        .filter(|h| !h.is_empty())
+        .unwrap_or("localhost")
+        .to_owned();
+    Some((port, remote_port, remote_host))
+}
+
+pub fn get_id() -> Result<Value, (i32, String)> {
+    let id = crate::ipc::get_id();
+    if id.is_empty() {
+        return Err((EXIT_ERROR, "No id".to_owned()));
+    }
+    Ok(json!({ "id": id }))
+}
+
+/// Set the option of the server, like `--option`, an empty value to remove it.
+pub fn set_option(key: &str, value: &str) -> Result<Value, (i32, String)> {
+    if hbb_common::config::is_disable_settings() {
+        return Err((EXIT_ERROR, "Settings are disabled".to_owned()));
+    }
+    if crate::platform::is_installed() && !crate::platform::is_root() {
+        return Err((EXIT_ERROR, "Administrative privileges required".to_owned()));
+    }
+    crate::ipc::set_option(key, value);
+    let current = crate::ipc::get_options()
+        .get(key)
+        .cloned()
+        .unwrap_or_default();
+    if current != value {
+        return Err((EXIT_ERROR, "Failed to set the option".to_owned()));
+    }
+    Ok(json!({ "key": key, "value": current }))
+}
+
+/// The authorized sessions of the local server.
+pub fn list_sessions() -> Result<Value, (i32, String)> {
+    match crate::ipc::get_authed_sessions() {
+        Ok(sessions) => Ok(json!({ "sessions": sessions })),
+        Err(err) => Err((EXIT_ERROR, format!("The server is not running: {}", err))),
+    }
+}
+
+/// Connect and log in to the peer, returns the stream and the peer info.
+async fn login(
+    session: &Session,
+    receiver: &mut mpsc::UnboundedReceiver<Data>,
+    key: &str,
+    token: &str,
+) -> Result<(Stream, PeerInfo), (i32, String)> {
+    let conn_type = session.lc.read().unwrap().conn_type;
+    let ((mut stream, direct, _pk, _kcp, _stream_type), _) =
+        Client::start(&session.id, key, token, conn_type, session.clone())
+            .await
+            .map_err(|e| (EXIT_CONNECT, e.to_string()))?;
+    log::info!("direct: {}", direct);
+    loop {
+        tokio::select! {
+            res = timeout(READ_TIMEOUT, stream.next()) => match res {
+                Err(_) => return Err((EXIT_CONNECT, "Timeout".to_owned())),
+                Ok(Some(Ok(bytes))) => {
+                    let Ok(msg_in) = Message::parse_from_bytes(&bytes) else {
+                        continue;
+                    };
+                    match msg_in.union {
+                        Some(message::Union::Hash(hash)) => {
+                            session.handle_hash(&session.password, hash, &mut stream).await;
+                        }
+                        Some(message::Union::LoginResponse(lr)) => match lr.union {
+                            Some(login_response::Union::Error(err)) => {
+                                return Err((EXIT_AUTH, err));
+                            }
+                            Some(login_response::Union::PeerInfo(pi)) => {
+                                session.handle_peer_info(pi.clone());
+                                return Ok((stream, pi));
+                            }
+                            _ => {}
+                        },
+                        Some(message::Union::TestDelay(t)) => {
+                            session.handle_test_delay(t, &mut stream).await;
+                        }
+                        _ => {}
+                    }
+                }
+                Ok(Some(Err(err))) => {
+                    return Err((EXIT_CONNECT, format!("Connection closed: {}", err)));
+                }
+                _ => return Err((EXIT_CONNECT, "Reset by the peer".to_owned())),
+            },
+            Some(data) = receiver.recv() => {
+                if let Data::Login((os_username, os_password, password, remember)) = data {
+                    session
+                        .handle_login_from_ui(os_username, os_password, password, remember, &mut stream)
+                        .await;
+                }
+            }
+        }
+    }
+}
+
+/// Log in to the peer to check the password, and disconnect.
 #[tokio::main(flavor = "current_thread")]
-pub async fn connect_test(id: &str, key: String, token: String) {
+pub async fn connect(
+    id: &str,
+    password: String,
+    key: String,
+    token: String,
+) -> Result<Value, (i32, String)> {
     let (sender, mut receiver) = mpsc::unbounded_channel::<Data>();
-    let handler = Session::new(&id, sender);
-    match crate::client::Client::start(id, &key, &token, ConnType::PORT_FORWARD, handler).await {
-        Err(err) => {
-            log::error!("Failed to connect {}: {}", &id, err);
-        }
-        Ok((mut stream, direct)) => {
-            log::info!("direct: {}", direct);
-            // rpassword::prompt_password("Input anything to exit").ok();
-            loop {
-                tokio::select! {
-                    res = hbb_common::timeout(READ_TIMEOUT, stream.next()) => match res {
-                        Err(_) => {
-                            log::error!("Timeout");
-                            break;
+    let session = Session::new_with(id, ConnType::DEFAULT_CONN, password, false, sender);
+    let (_stream, pi) = login(&session, &mut receiver, &key, &token).await?;
+    Ok(json!({
+        "peer": id,
+        "username": pi.username,
+        "hostname": pi.hostname,
+        "platform": pi.platform,
+        "version": pi.version,
+    }))
+}
+
+/// Upload the file or the directory `path` to `to` of the peer, the existing files are
+/// overwritten.
+#[tokio::main(flavor = "current_thread")]
+pub async fn send_file(
+    id: &str,
+    password: String,
+    path: &str,
+    to: &str,
+    key: String,
+    token: String,
+) -> Result<Value, (i32, String)> {
+    let (sender, mut receiver) = mpsc::unbounded_channel::<Data>();
+    let session = Session::new_with(id, ConnType::FILE_TRANSFER, password, false, sender);
+    let (mut stream, _pi) = login(&session, &mut receiver, &key, &token).await?;
+    let job = fs::TransferJob::new_read(
+        SEND_FILE_JOB_ID,
+        fs::JobType::Generic,
+        to.to_owned(),
+        fs::DataSource::FilePath(PathBuf::from(path)),
+        0,
+        false,
+        false,
+        false,
+    )
+    .map_err(|e| (EXIT_ERROR, e.to_string()))?;
+    #[cfg(not(windows))]
+    let files = job.files().clone();
+    #[cfg(windows)]
+    let mut files = job.files().clone();
+    #[cfg(windows)]
+    if _pi.platform != "Windows" {
+        fs::transform_windows_path(&mut files);
+    }
+    let (file_num, total_size) = (files.len(), job.total_size());
+    let msg = fs::new_receive(SEND_FILE_JOB_ID, to.to_owned(), 0, files, total_size);
+    stream
+        .send(&msg)
+        .await
+        .map_err(|e| (EXIT_CONNECT, e.to_string()))?;
+    log::info!(
+        "Sending {} files ({} bytes) to {}",
+        file_num,
+        total_size,
+        to
+    );
+    let mut jobs = vec![job];
+    let mut timer = crate::rustdesk_interval(time::interval(time::Duration::from_millis(1)));
+    loop {
+        tokio::select! {
+            res = timeout(READ_TIMEOUT, stream.next()) => match res {
+                Err(_) => return Err((EXIT_CONNECT, "Timeout".to_owned())),
+                Ok(Some(Ok(bytes))) => {
+                    let Ok(msg_in) = Message::parse_from_bytes(&bytes) else {
+                        continue;
+                    };
+                    match msg_in.union {
+                        Some(message::Union::FileResponse(FileResponse {
+                            union: Some(file_response::Union::Done(d)),
+                            ..
+                        })) if d.id == SEND_FILE_JOB_ID => break,
+                        Some(message::Union::FileResponse(FileResponse {
+                            union: Some(file_response::Union::Error(e)),
+                            ..
+                        })) if e.id == SEND_FILE_JOB_ID => {
+                            return Err((EXIT_TRANSFER, e.error));
                         }
-                        Ok(Some(Ok(bytes))) => {
-                            if let Ok(msg_in) = Message::parse_from_bytes(&bytes) {
-                                match msg_in.union {
-                                    Some(message::Union::Hash(hash)) => {
-                                        log::info!("Got hash");
-                                        break;
-                                    }
-                                    _ => {}
-                                }
-                            }
+                        Some(message::Union::TestDelay(t)) => {
+                            session.handle_test_delay(t, &mut stream).await;
                         }
                         _ => {}
                     }
                 }
+                Ok(Some(Err(err))) => {
+                    return Err((EXIT_CONNECT, format!("Connection closed: {}", err)));
+                }
+                _ => return Err((EXIT_CONNECT, "Reset by the peer".to_owned())),
+            },
+            _ = timer.tick(), if !jobs.is_empty() => {
+                if let Err(err) = fs::handle_read_jobs(&mut jobs, &mut stream).await {
+                    return Err((EXIT_TRANSFER, err.to_string()));
+                }
             }
         }
     }
+    Ok(json!({
+        "peer": id,
+        "path": path,
+        "to": to,
+        "files": file_num,
+        "size": total_size,
+    }))
 }
 
 #[tokio::main(flavor = "current_thread")]
 pub async fn start_one_port_forward(
     id: String,
+    password: Option<String>,
     port: i32,
     remote_host: String,
     remote_port: i32,
     key: String,
     token: String,
-) {
+) -> Result<Value, (i32, String)> {
     crate::common::test_rendezvous_server();
     crate::common::test_nat_type();
-    let (sender, mut receiver) = mpsc::unbounded_channel::<Data>();
-    let handler = Session::new(&id, sender);
+    let (sender, receiver) = mpsc::unbounded_channel::<Data>();
+    let handler = match password {
+        Some(password) => Session::new_with(&id, ConnType::PORT_FORWARD, password, false, sender),
+        None => Session::new(&id, sender),
+    };
     if let Err(err) = crate::port_forward::listen(
         handler.id.clone(),
         handler.password.clone(),
@@ -188,6 +427,27 @@ pub async fn start_one_port_forward(
     .await
     {
         log::error!("Failed to listen on {}: {}", port, err);
+        return Err((EXIT_ERROR, err.to_string()));
     }
     log::info!("port forward (:{}) exit", port);
+    Ok(json!({ "peer": id, "local_port": port }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_port_forward() {
+        assert_eq!(
+            parse_port_forward("8080:80"),
+            Some((8080, 80, "localhost".to_owned()))
+        );
+        assert_eq!(
+            parse_port_forward("2222:22:10.0.0.2"),
+            Some((2222, 22, "10.0.0.2".to_owned()))
+        );
+        assert_eq!(parse_port_forward("8080"), None);
+        assert_eq!(parse_port_forward("a:80"), None);
+    }
 }
@@ -89,15 +89,22 @@ use crate::ui_session_interface::SessionPermissionConfig;
 
 pub use super::lang::*;
 
+pub mod feedback;
 pub mod file_trait;
+pub mod frame_delivery;
 pub mod helper;
 pub mod io_loop;
+pub mod prewarm;
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+pub mod relative_mouse;
 pub mod screenshot;
 
 pub const MILLI1: Duration = Duration::from_millis(1);
 pub const SEC30: Duration = Duration::from_secs(30);
 pub const VIDEO_QUEUE_SIZE: usize = 120;
 const MAX_DECODE_FAIL_COUNTER: usize = 3;
+// The error of `create_relay` if the relay server is not reachable.
+const RELAY_CONNECT_FAILURE: &str = "Failed to connect to relay server";
 
 #[cfg(target_os = "linux")]
 pub const LOGIN_MSG_DESKTOP_NOT_INITED: &str = "Desktop env is not inited";
@@ -128,6 +135,41 @@ pub const SCRAP_X11_REF_URL: &str = "https://rustdesk.com/docs/en/manual/linux/#
 
 #[cfg(not(target_os = "linux"))]
 pub const AUDIO_BUFFER_MS: usize = 3000;
+const MIN_JITTER_BUFFER_MS: usize = 10;
+const MAX_JITTER_BUFFER_MS: usize = 3000;
+
+/// The jitter buffer of the audio playback, a lower latency causes more dropouts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct JitterBufferConfig {
+    /// The buffered data is kept around it, `None` adapts to the jitter.
+    pub target_ms: Option<usize>,
+    /// The oldest data over it is dropped, `None` is 3 seconds.
+    pub max_ms: Option<usize>,
+}
+
+impl JitterBufferConfig {
+    /// Parse the session options "audio-buffer-target-ms" and "audio-buffer-max-ms",
+    /// empty or 0 means the default.
+    pub fn from_options(target_ms: &str, max_ms: &str) -> Self {
+        let parse = |v: &str| {
+            v.parse::<usize>()
+                .ok()
+                .filter(|v| *v > 0)
+                .map(|v| v.clamp(MIN_JITTER_BUFFER_MS, MAX_JITTER_BUFFER_MS))
+        };
+        Self {
+            target_ms: parse(target_ms),
+            max_ms: parse(max_ms),
+        }
+    }
+
+    /// The capacity of the buffer, not less than the target.
+    pub fn capacity_ms(&self) -> usize {
+        self.max_ms
+            .unwrap_or(MAX_JITTER_BUFFER_MS)
+            .max(self.target_ms.unwrap_or(0))
+    }
+}
 
 #[cfg(feature = "flutter")]
 #[cfg(not(any(target_os = "android", target_os = "ios")))]
@@ -204,6 +246,8 @@ impl Client {
         debug_assert!(peer == interface.get_id());
         interface.update_direct(None);
         interface.update_received(false);
+        interface.get_lch().write().unwrap().relay_server = None;
+        interface.get_lch().write().unwrap().session_fingerprint = "".to_owned();
         match Self::_start(peer, key, token, conn_type, interface.clone()).await {
             Err(err) => {
                 let err_str = err.to_string();
@@ -251,6 +295,19 @@ impl Client {
             bail!("Incoming only mode");
         }
         // to-do: remember the port for each peer, so that we can retry easier
+        if let Some(addr) = crate::parse_direct_ip(peer, (RELAY_PORT + 1) as _) {
+            return Ok((
+                (
+                    connect_tcp_local(addr.to_string(), None, CONNECT_TIMEOUT).await?,
+                    true,
+                    None,
+                    None,
+                    "TCP",
+                ),
+                (0, "".to_owned()),
+                false,
+            ));
+        }
         if hbb_common::is_ip_str(peer) {
             return Ok((
                 (
@@ -546,6 +603,8 @@ impl Client {
                             }
                         }
                         signed_id_pk = rr.pk().into();
+                        interface.get_lch().write().unwrap().relay_server =
+                            Some(rr.relay_server.clone());
                         let fut = Self::create_relay(
                             &peer,
                             rr.uuid,
@@ -570,8 +629,14 @@ impl Client {
                         let mut conn = conn?;
                         feedback = rr.feedback;
                         log::info!("{:?} used to establish {typ} connection", start.elapsed());
-                        let pk =
-                            Self::secure_connection(&peer, signed_id_pk, &key, &mut conn).await?;
+                        let pk = Self::secure_connection(
+                            &peer,
+                            signed_id_pk,
+                            &key,
+                            &mut conn,
+                            &interface.get_lch(),
+                        )
+                        .await?;
                         return Ok((
                             (conn, typ == "IPv6", pk, kcp, typ),
                             (feedback, rendezvous_server),
@@ -710,17 +775,36 @@ impl Client {
 
         let mut direct = !conn.is_err();
         if interface.is_force_relay() || conn.is_err() {
-            if !relay_server.is_empty() {
-                conn = Self::request_relay(
-                    peer_id,
-                    relay_server.to_owned(),
-                    rendezvous_server,
-                    !signed_id_pk.is_empty(),
-                    key,
-                    token,
-                    conn_type,
-                )
-                .await;
+            crate::relay_servers::measure_if_needed().await;
+            let relay_servers = crate::relay_servers::candidates(relay_server);
+            if !relay_servers.is_empty() {
+                for (i, relay_server) in relay_servers.iter().enumerate() {
+                    conn = Self::request_relay(
+                        peer_id,
+                        relay_server.to_owned(),
+                        rendezvous_server,
+                        !signed_id_pk.is_empty(),
+                        key,
+                        token,
+                        conn_type,
+                    )
+                    .await;
+                    match &conn {
+                        Ok(_) => {
+                            interface.get_lch().write().unwrap().relay_server =
+                                Some(relay_server.to_owned());
+                            break;
+                        }
+                        // Fail over to the next relay server only if this one is not reachable.
+                        Err(e)
+                            if i + 1 < relay_servers.len()
+                                && e.to_string() == RELAY_CONNECT_FAILURE =>
+                        {
+                            log::warn!("Relay server {} failed, try the next one", relay_server);
+                        }
+                        Err(_) => break,
+                    }
+                }
                 if let Err(e) = conn {
                     // this direct is mainly used by on_establish_connection_error, so we update it here before bail
                     interface.update_direct(Some(false));
@@ -738,7 +822,9 @@ impl Client {
             start.elapsed(),
             punch_type
         );
-        let res = Self::secure_connection(peer_id, signed_id_pk, key, &mut conn).await;
+        let res =
+            Self::secure_connection(peer_id, signed_id_pk, key, &mut conn, &interface.get_lch())
+                .await;
         let pk: Option<Vec<u8>> = match res {
             Ok(pk) => pk,
             Err(e) => {
@@ -757,6 +843,7 @@ impl Client {
         signed_id_pk: Vec<u8>,
         key: &str,
         conn: &mut Stream,
+        lc: &Arc<RwLock<LoginConfigHandler>>,
     ) -> ResultType<Option<Vec<u8>>> {
         let rs_pk = get_rs_pk(if key.is_empty() {
             config::RS_PUB_KEY
@@ -795,6 +882,11 @@ impl Client {
                             if id == peer_id {
                                 let (asymmetric_value, symmetric_value, key) =
                                     create_symmetric_key_msg(their_pk_b);
+                                let session_fingerprint = crate::common::session_fingerprint(
+                                    &their_pk_b,
+                                    &asymmetric_value,
+                                    &symmetric_value,
+                                );
                                 let mut msg_out = Message::new();
                                 msg_out.set_public_key(PublicKey {
                                     asymmetric_value,
@@ -803,6 +895,7 @@ impl Client {
                                 });
                                 timeout(CONNECT_TIMEOUT, conn.send(&msg_out)).await??;
                                 conn.set_key(key);
+                                lc.write().unwrap().session_fingerprint = session_fingerprint;
                             } else {
                                 log::error!("Handshake failed: sign failure");
                                 conn.send(&Message::new()).await?;
@@ -903,12 +996,15 @@ impl Client {
         conn_type: ConnType,
         ipv4: bool,
     ) -> ResultType<Stream> {
-        let mut conn = connect_tcp(
-            ipv4_to_ipv6(check_port(relay_server, RELAY_PORT), ipv4),
+        let conn = connect_tcp(
+            ipv4_to_ipv6(check_port(relay_server.clone(), RELAY_PORT), ipv4),
             CONNECT_TIMEOUT,
         )
-        .await
-        .with_context(|| "Failed to connect to relay server")?;
+        .await;
+        if conn.is_err() {
+            crate::relay_servers::on_failure(&relay_server);
+        }
+        let mut conn = conn.with_context(|| RELAY_CONNECT_FAILURE)?;
         let mut msg_out = RendezvousMessage::new();
         msg_out.set_request_relay(RequestRelay {
             licence_key: key.to_owned(),
@@ -1187,6 +1283,9 @@ pub struct AudioHandler {
     device_channel: u16,
     #[cfg(not(target_os = "linux"))]
     ready: Arc<std::sync::Mutex<bool>>,
+    output: Option<Arc<crate::unity::audio::SessionAudioOutput>>,
+    format: Option<AudioFormat>,
+    peer_id: Option<String>,
 }
 
 #[cfg(not(target_os = "linux"))]
@@ -1194,6 +1293,7 @@ struct AudioBuffer(
     pub Arc<std::sync::Mutex<ringbuf::HeapRb<f32>>>,
     usize,
     [usize; 30],
+    Option<JitterTarget>,
 );
 
 #[cfg(not(target_os = "linux"))]
@@ -1205,20 +1305,63 @@ impl Default for AudioBuffer {
             )),
             48000 * 2,
             [0; 30],
+            None,
         )
     }
 }
 
+// Keeps the buffered data around the target, the data that stays unplayed for a second is dropped.
+#[cfg(not(target_os = "linux"))]
+struct JitterTarget {
+    target: usize,
+    channels: usize,
+    // the lowest level of the buffer in the current second
+    floor: usize,
+    since: Instant,
+}
+
+#[cfg(not(target_os = "linux"))]
+impl JitterTarget {
+    // Returns the number of samples to drop.
+    fn update(&mut self, level: usize) -> usize {
+        self.floor = self.floor.min(level);
+        if self.since.elapsed() < Duration::from_secs(1) {
+            return 0;
+        }
+        self.since = Instant::now();
+        let floor = std::mem::replace(&mut self.floor, usize::MAX);
+        if floor > self.target {
+            (floor - self.target) / self.channels * self.channels
+        } else {
+            0
+        }
+    }
+}
+
 #[cfg(not(target_os = "linux"))]
 impl AudioBuffer {
-    pub fn resize(&mut self, sample_rate: usize, channels: usize) {
-        let capacity = sample_rate * channels * AUDIO_BUFFER_MS / 1000;
+    pub fn resize(&mut self, sample_rate: usize, channels: usize, jitter: &JitterBufferConfig) {
+        let capacity = sample_rate * channels * jitter.capacity_ms() / 1000;
         let old_capacity = self.0.lock().unwrap().capacity();
         if capacity != old_capacity {
             *self.0.lock().unwrap() = ringbuf::HeapRb::<f32>::new(capacity);
             self.1 = sample_rate * channels;
             log::info!("Audio buffer resized from {old_capacity} to {capacity}");
         }
+        self.3 = jitter.target_ms.map(|ms| JitterTarget {
+            target: sample_rate * channels * ms / 1000,
+            channels: channels.max(1),
+            floor: usize::MAX,
+            since: Instant::now(),
+        });
+    }
+
+    /// The buffered data in ms.
+    fn depth_ms(&self) -> usize {
+        if self.1 == 0 {
+            return 0;
+        }
+        self.0.lock().unwrap().occupied_len() * 1000 / self.1
     }
 
     fn try_shrink(&mut self, having: usize) {
@@ -1304,14 +1447,43 @@ impl AudioBuffer {
 
     /// append pcm to audio buffer, trying to drop data
     /// when data is too much (per 12 seconds) based
-    /// statistics.
+    /// statistics, or above the target of the jitter buffer.
     pub fn append_pcm(&mut self, buffer: &[f32]) {
         let having = self.append_pcm2(buffer);
-        self.try_shrink(having);
+        if let Some(target) = self.3.as_mut() {
+            let skip = target.update(having.saturating_sub(buffer.len()));
+            if skip > 0 {
+                self.0.lock().unwrap().skip(skip);
+                log::debug!("skip {skip} above the jitter buffer target");
+            }
+        } else {
+            self.try_shrink(having);
+        }
     }
 }
 
 impl AudioHandler {
+    pub fn new(peer_id: Option<&str>) -> Self {
+        Self {
+            output: peer_id.map(crate::unity::audio::get_session_audio_output),
+            peer_id: peer_id.map(|id| id.to_owned()),
+            ..Default::default()
+        }
+    }
+
+    #[inline]
+    fn output_device(&self) -> Option<String> {
+        self.output.as_ref().and_then(|o| o.device())
+    }
+
+    #[inline]
+    fn jitter_buffer(&self) -> JitterBufferConfig {
+        self.output
+            .as_ref()
+            .map(|o| o.jitter_buffer())
+            .unwrap_or_default()
+    }
+
     #[cfg(target_os = "linux")]
     fn start_audio(&mut self, format0: AudioFormat) -> ResultType<()> {
         use psimple::Simple;
@@ -1327,15 +1499,28 @@ impl AudioHandler {
             bail!("Invalid audio format");
         }
 
+        let device = self.output_device();
+        let jitter = self.jitter_buffer();
+        let attr = (jitter != JitterBufferConfig::default()).then(|| {
+            let bytes =
+                |ms: usize| spec.usec_to_bytes(pulse::time::MicroSeconds(ms as u64 * 1000)) as u32;
+            pulse::def::BufferAttr {
+                maxlength: bytes(jitter.capacity_ms()),
+                tlength: jitter.target_ms.map_or(u32::MAX, bytes),
+                prebuf: u32::MAX,
+                minreq: u32::MAX,
+                fragsize: u32::MAX,
+            }
+        });
         self.simple = Some(Simple::new(
             None,                   // Use the default server
             &crate::get_app_name(), // Our application’s name
             Direction::Playback,    // We want a playback stream
-            None,                   // Use the default device
+            device.as_deref(),      // None to use the default device
             "playback",             // Description of our stream
             &spec,                  // Our sample format
             None,                   // Use default channel map
-            None,                   // Use default buffering attributes
+            attr.as_ref(),          // None to use default buffering attributes
         )?);
         self.sample_rate = (format0.sample_rate, format0.sample_rate);
         Ok(())
@@ -1344,11 +1529,24 @@ impl AudioHandler {
     /// Start the audio playback.
     #[cfg(not(target_os = "linux"))]
     fn start_audio(&mut self, format0: AudioFormat) -> ResultType<()> {
-        let device = AUDIO_HOST
-            .default_output_device()
-            .with_context(|| "Failed to get default output device")?;
+        let selected = self.output_device().and_then(|name| {
+            let device = AUDIO_HOST
+                .output_devices()
+                .ok()?
+                .find(|d| d.name().map(|n| n == name).unwrap_or(false));
+            if device.is_none() {
+                log::warn!("Output device \"{}\" not found, fallback to default", name);
+            }
+            device
+        });
+        let device = match selected {
+            Some(device) => device,
+            None => AUDIO_HOST
+                .default_output_device()
+                .with_context(|| "Failed to get default output device")?,
+        };
         log::info!(
-            "Using default output device: \"{}\"",
+            "Using output device: \"{}\"",
             device.name().unwrap_or("".to_owned())
         );
         let config = device.default_output_config().map_err(|e| anyhow!(e))?;
@@ -1399,6 +1597,7 @@ impl AudioHandler {
                 let buffer = vec![0.; f.sample_rate as usize * f.channels as usize];
                 self.audio_decoder = Some((d, buffer));
                 self.channels = f.channels as _;
+                self.format = Some(f.clone());
                 allow_err!(self.start_audio(f));
             }
             Err(err) => {
@@ -1410,6 +1609,14 @@ impl AudioHandler {
     /// Handle audio frame and play it.
     #[inline]
     pub fn handle_frame(&mut self, frame: AudioFrame) {
+        if self
+            .output
+            .as_ref()
+            .map(|o| o.take_device_changed() | o.take_jitter_buffer_changed())
+            .unwrap_or(false)
+        {
+            self.restart_audio();
+        }
         #[cfg(not(target_os = "linux"))]
         if self.audio_stream.is_none() || !self.ready.lock().unwrap().clone() {
             return;
@@ -1423,6 +1630,32 @@ impl AudioHandler {
             if let Ok(n) = d.decode_float(&frame.data, buffer, false) {
                 let channels = self.channels;
                 let n = n * (channels as usize);
+                if let Some(peer_id) = self.peer_id.as_ref() {
+                    crate::unity::audio::notify_audio_frame(
+                        peer_id,
+                        self.sample_rate.0,
+                        channels,
+                        &buffer[0..n],
+                    );
+                }
+                if let Some(output) = self.output.as_ref() {
+                    let gain = output.gain();
+                    if gain != 1. {
+                        buffer[0..n].iter_mut().for_each(|x| *x *= gain);
+                    }
+                }
+                #[cfg(all(feature = "flutter", feature = "plugin_framework"))]
+                #[cfg(not(any(target_os = "android", target_os = "ios")))]
+                if let Some(peer_id) = self.peer_id.as_ref() {
+                    crate::plugin::native_handlers::hook::on_audio(
+                        peer_id,
+                        self.sample_rate.0,
+                        channels,
+                        &mut buffer[0..n],
+                    );
+                }
+                #[cfg(feature = "audio_processing")]
+                crate::audio_processing::push_render(&buffer[0..n], self.sample_rate.0, channels);
                 #[cfg(not(target_os = "linux"))]
                 {
                     let sample_rate0 = self.sample_rate.0;
@@ -1446,17 +1679,43 @@ impl AudioHandler {
                         );
                     }
                     self.audio_buffer.append_pcm(&buffer);
+                    if let Some(output) = self.output.as_ref() {
+                        output.set_buffer_ms(self.audio_buffer.depth_ms() as _);
+                    }
                 }
                 #[cfg(target_os = "linux")]
                 {
                     let data_u8 =
                         unsafe { std::slice::from_raw_parts::<u8>(buffer.as_ptr() as _, n * 4) };
                     self.simple.as_mut().map(|x| x.write(data_u8));
+                    if let (Some(output), Some(Ok(latency))) = (
+                        self.output.as_ref(),
+                        self.simple.as_ref().map(|x| x.get_latency()),
+                    ) {
+                        output.set_buffer_ms((latency.0 / 1000) as _);
+                    }
                 }
             }
         });
     }
 
+    /// Restart the playback on the newly selected output device.
+    fn restart_audio(&mut self) {
+        let Some(format) = self.format.clone() else {
+            return;
+        };
+        #[cfg(not(target_os = "linux"))]
+        {
+            self.audio_stream = None;
+            *self.ready.lock().unwrap() = false;
+        }
+        #[cfg(target_os = "linux")]
+        {
+            self.simple = None;
+        }
+        allow_err!(self.start_audio(format));
+    }
+
     /// Build audio output stream for current device.
     #[cfg(not(target_os = "linux"))]
     fn build_output_stream<T: cpal::Sample + cpal::SizedSample + cpal::FromSample<f32>>(
@@ -1469,8 +1728,11 @@ impl AudioHandler {
             // too many errors, will improve later
             log::trace!("an error occurred on stream: {}", err);
         };
-        self.audio_buffer
-            .resize(config.sample_rate.0 as _, config.channels as _);
+        self.audio_buffer.resize(
+            config.sample_rate.0 as _,
+            config.channels as _,
+            &self.jitter_buffer(),
+        );
         let audio_buffer = self.audio_buffer.0.clone();
         let ready = self.ready.clone();
         let timeout = None;
@@ -1658,6 +1920,7 @@ impl VideoHandler {
                 display_idx,
                 camera,
                 tx: None,
+                template: Default::default(),
             })
             .map_or(Default::default(), |r| Arc::new(Mutex::new(Some(r))));
         } else {
@@ -1722,6 +1985,45 @@ struct ConnToken {
     session_id: u64,
 }
 
+/// Parse the option "display-settings" of the peer config, saved as `{display: [quality, fps, codec]}`.
+pub fn parse_display_settings(value: &str) -> Vec<scrap::codec::DisplaySettings> {
+    let mut v: Vec<_> = serde_json::from_str::<HashMap<usize, (i32, u32, String)>>(value)
+        .unwrap_or_default()
+        .into_iter()
+        .map(
+            |(display, (quality, fps, codec))| scrap::codec::DisplaySettings {
+                display,
+                quality,
+                fps,
+                codec: scrap::codec::DisplaySettings::codec_from_name(&codec),
+            },
+        )
+        .collect();
+    v.sort_by_key(|s| s.display);
+    v
+}
+
+/// The option "display-settings" with the settings of a display updated,
+/// the default settings remove the display.
+pub fn update_display_settings(value: &str, settings: scrap::codec::DisplaySettings) -> String {
+    let mut map: HashMap<usize, (i32, u32, String)> = parse_display_settings(value)
+        .into_iter()
+        .filter(|s| s.display != settings.display)
+        .map(|s| (s.display, (s.quality, s.fps, s.codec_name())))
+        .collect();
+    if !settings.is_default() {
+        map.insert(
+            settings.display,
+            (settings.quality, settings.fps, settings.codec_name()),
+        );
+    }
+    if map.is_empty() {
+        "".to_owned()
+    } else {
+        serde_json::to_string(&map).unwrap_or_default()
+    }
+}
+
 /// Login config handler for [`Client`].
 #[derive(Default)]
 pub struct LoginConfigHandler {
@@ -1741,6 +2043,8 @@ pub struct LoginConfigHandler {
     pub force_relay: bool,
     pub direct: Option<bool>,
     pub received: bool,
+    pub relay_server: Option<String>, // the relay server of the current connection
+    pub session_fingerprint: String, // the fingerprint of the end-to-end key of the current connection
     switch_uuid: Option<String>,
     pub save_ab_password_to_recent: bool, // true: connected with ab password
     pub other_server: Option<(String, String, String)>,
@@ -2216,8 +2520,13 @@ impl LoginConfigHandler {
             }
         }
         let q = self.image_quality.clone();
-        if let Some(q) = self.get_image_quality_enum(&q, ignore_default) {
+        // The encoder profile is sent with the image quality.
+        let ignore_default_quality =
+            ignore_default && self.get_encoder_profile() == scrap::codec::EncoderProfile::Balanced;
+        if let Some(q) = self.get_image_quality_enum(&q, ignore_default_quality) {
             msg.image_quality = q.into();
+        } else if q == "lossless" {
+            self.set_lossless_option(&mut msg);
         } else if q == "custom" {
             let config = self.load_config();
             let allow_more = !crate::using_public_server() || self.direct == Some(true);
@@ -2241,6 +2550,7 @@ impl LoginConfigHandler {
                 *self.custom_fps.lock().unwrap() = Some(custom_fps as _);
             }
         }
+        self.set_encoder_profile_option(&mut msg);
         let view_only = self.get_toggle_option("view-only");
         if view_only {
             msg.disable_keyboard = BoolOption::Yes.into();
@@ -2282,6 +2592,32 @@ impl LoginConfigHandler {
         )
     }
 
+    /// Request the lossless mode, which is allowed only if the custom image quality over 100 is allowed.
+    /// The peers not supporting it use the max custom image quality.
+    fn set_lossless_option(&self, msg: &mut OptionMessage) {
+        let allow_more = !crate::using_public_server() || self.direct == Some(true);
+        if allow_more {
+            msg.custom_image_quality = scrap::codec::IMAGE_QUALITY_LOSSLESS << 8
+                | scrap::codec::IMAGE_QUALITY_LOSSLESS_FLAG;
+        } else {
+            msg.image_quality = ImageQuality::Best.into();
+        }
+    }
+
+    pub fn get_encoder_profile(&self) -> scrap::codec::EncoderProfile {
+        scrap::codec::EncoderProfile::from_name(&self.get_option("encoder-profile"))
+    }
+
+    /// Add the encoder profile to the message if it contains the image quality.
+    /// The peers not supporting it ignore the bits of the custom image quality.
+    fn set_encoder_profile_option(&self, msg: &mut OptionMessage) {
+        if msg.image_quality.enum_value_or_default() != ImageQuality::NotSet
+            || msg.custom_image_quality > 0
+        {
+            msg.custom_image_quality |= self.get_encoder_profile().image_quality_flag();
+        }
+    }
+
     /// Parse the image quality option.
     /// Return [`ImageQuality`] if the option is valid, otherwise return `None`.
     ///
@@ -2379,11 +2715,13 @@ impl LoginConfigHandler {
     /// * `bitrate` - The given bitrate.
     /// * `quantizer` - The given quantizer.
     pub fn save_custom_image_quality(&mut self, image_quality: i32) -> Message {
-        let mut misc = Misc::new();
-        misc.set_option(OptionMessage {
+        let mut option = OptionMessage {
             custom_image_quality: image_quality << 8,
             ..Default::default()
-        });
+        };
+        self.set_encoder_profile_option(&mut option);
+        let mut misc = Misc::new();
+        misc.set_option(option);
         let mut msg_out = Message::new();
         msg_out.set_misc(misc);
         let mut config = self.load_config();
@@ -2393,6 +2731,43 @@ impl LoginConfigHandler {
         msg_out
     }
 
+    /// Get the image quality of the session as the equivalent custom image quality.
+    pub fn get_custom_image_quality_equivalent(&self) -> i32 {
+        // custom image quality `q` is the bitrate ratio `q * 2 / 100`
+        let from_ratio = |ratio: f32| (ratio * 50.0) as i32;
+        match self.image_quality.as_str() {
+            "best" => from_ratio(scrap::codec::BR_BEST),
+            "low" => from_ratio(scrap::codec::BR_SPEED),
+            "lossless" => scrap::codec::IMAGE_QUALITY_LOSSLESS,
+            "custom" => self
+                .load_config()
+                .custom_image_quality
+                .first()
+                .cloned()
+                .unwrap_or(50),
+            _ => from_ratio(scrap::codec::BR_BALANCED),
+        }
+    }
+
+    /// Create a [`Message`] that restores the image quality of the session settings,
+    /// after the quality has been changed temporarily, e.g. by the bandwidth limiter.
+    pub fn restore_image_quality_message(&self) -> Message {
+        let mut option = OptionMessage::default();
+        if let Some(q) = self.get_image_quality_enum(&self.image_quality, false) {
+            option.image_quality = q.into();
+        } else if self.image_quality == "lossless" {
+            self.set_lossless_option(&mut option);
+        } else {
+            option.custom_image_quality = self.get_custom_image_quality_equivalent() << 8;
+        }
+        self.set_encoder_profile_option(&mut option);
+        let mut misc = Misc::new();
+        misc.set_option(option);
+        let mut msg_out = Message::new();
+        msg_out.set_misc(misc);
+        msg_out
+    }
+
     /// Save the given image quality to the config.
     /// Return a [`Message`] that contains image quality, or `None` if the image quality is not valid.
     /// # Arguments
@@ -2401,11 +2776,22 @@ impl LoginConfigHandler {
     pub fn save_image_quality(&mut self, value: String) -> Option<Message> {
         let mut res = None;
         if let Some(q) = self.get_image_quality_enum(&value, false) {
-            let mut misc = Misc::new();
-            misc.set_option(OptionMessage {
+            let mut option = OptionMessage {
                 image_quality: q.into(),
                 ..Default::default()
-            });
+            };
+            self.set_encoder_profile_option(&mut option);
+            let mut misc = Misc::new();
+            misc.set_option(option);
+            let mut msg_out = Message::new();
+            msg_out.set_misc(misc);
+            res = Some(msg_out);
+        } else if value == "lossless" {
+            let mut option = OptionMessage::default();
+            self.set_lossless_option(&mut option);
+            self.set_encoder_profile_option(&mut option);
+            let mut misc = Misc::new();
+            misc.set_option(option);
             let mut msg_out = Message::new();
             msg_out.set_misc(misc);
             res = Some(msg_out);
@@ -2416,6 +2802,70 @@ impl LoginConfigHandler {
         res
     }
 
+    /// Save the encoder profile to the config.
+    /// Return a [`Message`] that sends it with the image quality.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The name of the encoder profile.
+    pub fn save_encoder_profile(&mut self, value: String) -> Message {
+        self.set_option("encoder-profile".to_owned(), value);
+        self.restore_image_quality_message()
+    }
+
+    /// The jitter buffer of the audio playback in the session options.
+    pub fn get_jitter_buffer_config(&self) -> JitterBufferConfig {
+        JitterBufferConfig::from_options(
+            &self.get_option("audio-buffer-target-ms"),
+            &self.get_option("audio-buffer-max-ms"),
+        )
+    }
+
+    /// The settings of the displays overriding the session ones, see [`parse_display_settings`].
+    pub fn get_display_settings(&self) -> Vec<scrap::codec::DisplaySettings> {
+        parse_display_settings(&self.get_option("display-settings"))
+    }
+
+    /// Save the settings of a display, the default settings remove the override.
+    /// Return a [`Message`] that sends them, or `None` if the peer doesn't support them.
+    ///
+    /// # Arguments
+    ///
+    /// * `settings` - The settings of the display.
+    pub fn save_display_settings(
+        &mut self,
+        settings: scrap::codec::DisplaySettings,
+    ) -> Option<Message> {
+        let value = update_display_settings(&self.get_option("display-settings"), settings);
+        self.set_option("display-settings".to_owned(), value);
+        if crate::common::is_support_display_settings_num(self.version) {
+            Some(Self::display_settings_message(&settings))
+        } else {
+            None
+        }
+    }
+
+    /// The messages of the saved display settings, sent after the connection is established.
+    pub fn display_settings_messages(&self) -> Vec<Message> {
+        if !crate::common::is_support_display_settings_num(self.version) {
+            return vec![];
+        }
+        self.get_display_settings()
+            .iter()
+            .map(Self::display_settings_message)
+            .collect()
+    }
+
+    fn display_settings_message(settings: &scrap::codec::DisplaySettings) -> Message {
+        let mut option = OptionMessage::default();
+        settings.write(&mut option);
+        let mut misc = Misc::new();
+        misc.set_option(option);
+        let mut msg_out = Message::new();
+        msg_out.set_misc(misc);
+        msg_out
+    }
+
     pub fn save_trackpad_speed(&mut self, speed: i32) {
         let mut config = self.load_config();
         config.trackpad_speed = speed;
@@ -2783,20 +3233,31 @@ pub fn start_video_thread<F, T>(
     fps: Arc<RwLock<Option<usize>>>,
     chroma: Arc<RwLock<Option<Chroma>>>,
     discard_queue: Arc<RwLock<bool>>,
+    buffer_allocs: Arc<RwLock<usize>>,
     video_callback: F,
 ) where
     F: 'static + FnMut(usize, &mut scrap::ImageRgb, *mut c_void, bool) + Send,
     T: InvokeUiSession,
 {
-    let mut video_callback = video_callback;
+    let video_callback = Arc::new(Mutex::new(video_callback));
     let mut last_chroma = None;
     let is_view_camera = session.is_view_camera();
+    let latency_probe = session.latency_probe.clone();
 
     std::thread::spawn(move || {
+        let mut delivery = {
+            let video_callback = video_callback.clone();
+            let latency_probe = latency_probe.clone();
+            frame_delivery::FrameDelivery::start(move |display, rgb| {
+                (video_callback.lock().unwrap())(display, rgb, std::ptr::null_mut(), true);
+                latency_probe.on_delivered(display);
+            })
+        };
         #[cfg(windows)]
         sync_cpu_usage();
         get_hwcodec_config();
         let mut video_handler = None;
+        let mut keyframe_request = KeyframeRequest::default();
         let mut count = 0;
         let mut duration = std::time::Duration::ZERO;
         let mut skip_beginning = 0;
@@ -2836,19 +3297,45 @@ pub fn start_video_thread<F, T>(
                                 handler.record_screen(true, id, display, is_view_camera);
                             }
                             video_handler = Some(handler);
+                            keyframe_request.reset();
+                        }
+                        match keyframe_request.on_frame(&vf) {
+                            KeyframeAction::Decode => {}
+                            action => {
+                                handle_keyframe_action(&session, display, action);
+                                continue;
+                            }
                         }
                         if let Some(handler) = video_handler.as_mut() {
                             let mut pixelbuffer = true;
                             let mut tmp_chroma = None;
                             let format_changed = handler.decoder.format() != format;
+                            // The rgb buffer is resized in place, or swapped with the one
+                            // returned by the delivery, so it only grows on the size changes.
+                            let capacity = handler.rgb.raw.capacity();
+                            let pts = if latency_probe.is_enabled() {
+                                feedback::last_pts(&vf)
+                            } else {
+                                None
+                            };
                             match handler.handle_frame(vf, &mut pixelbuffer, &mut tmp_chroma) {
                                 Ok(true) => {
-                                    video_callback(
-                                        display,
-                                        &mut handler.rgb,
-                                        handler.texture.texture,
-                                        pixelbuffer,
-                                    );
+                                    latency_probe.on_decoded(display, pts);
+                                    if handler.rgb.raw.capacity() > capacity {
+                                        *buffer_allocs.write().unwrap() += 1;
+                                    }
+                                    if pixelbuffer {
+                                        delivery.deliver(display, &mut handler.rgb);
+                                    } else {
+                                        // The texture is owned by the decoder.
+                                        (video_callback.lock().unwrap())(
+                                            display,
+                                            &mut handler.rgb,
+                                            handler.texture.texture,
+                                            false,
+                                        );
+                                        latency_probe.on_delivered(display);
+                                    }
 
                                     // chroma
                                     if tmp_chroma.is_some() && last_chroma != tmp_chroma {
@@ -2879,7 +3366,8 @@ pub fn start_video_thread<F, T>(
                                     //
                                     // to-do: fix the error
                                     log::error!("handle video frame error, {}", e);
-                                    session.refresh_video(display as _);
+                                    let action = keyframe_request.on_error();
+                                    handle_keyframe_action(&session, display, action);
                                 }
                                 _ => {}
                             }
@@ -2909,6 +3397,7 @@ pub fn start_video_thread<F, T>(
                     MediaData::Reset => {
                         if let Some(handler) = video_handler.as_mut() {
                             handler.reset(None);
+                            keyframe_request.reset();
                         }
                     }
                     MediaData::RecordScreen(start) => {
@@ -2927,12 +3416,26 @@ pub fn start_video_thread<F, T>(
     });
 }
 
+fn handle_keyframe_action<T: InvokeUiSession>(
+    session: &Session<T>,
+    display: usize,
+    action: KeyframeAction,
+) {
+    match action {
+        KeyframeAction::Request => session.request_keyframe(display as _),
+        KeyframeAction::Refresh => session.refresh_video(display as _),
+        KeyframeAction::Decode | KeyframeAction::Skip => {}
+    }
+}
+
 /// Start an audio thread
 /// Return a audio [`MediaSender`]
-pub fn start_audio_thread() -> MediaSender {
+///
+/// `peer_id` binds the playback to the per-session output settings, see [`crate::unity::audio`].
+pub fn start_audio_thread(peer_id: Option<String>) -> MediaSender {
     let (audio_sender, audio_receiver) = mpsc::channel::<MediaData>();
     std::thread::spawn(move || {
-        let mut audio_handler = AudioHandler::default();
+        let mut audio_handler = AudioHandler::new(peer_id.as_deref());
         loop {
             if let Ok(data) = audio_receiver.recv() {
                 match data {
@@ -3332,7 +3835,20 @@ pub fn handle_login_error(
                 .set_option("trust-this-device".to_string(), "".to_string());
         }
         interface.msgbox("input-2fa", err, "", "");
+        crate::unity::auth_2fa::on_required(&interface.get_id(), err == LOGIN_MSG_2FA_WRONG);
         true
+    } else if err == crate::auth_fido2::LOGIN_MSG_REQUIRED {
+        // The challenge of the host is signed once it arrives, see `handle_fido2_challenge`.
+        if cfg!(all(
+            feature = "fido2",
+            not(any(target_os = "android", target_os = "ios"))
+        )) {
+            interface.msgbox("wait-fido2", err, "fido2-touch-tip", "");
+            true
+        } else {
+            interface.msgbox("error", err, "fido2-not-supported-tip", "");
+            false
+        }
     } else if LOGIN_ERROR_MAP.contains_key(err) {
         if let Some(msgbox_info) = LOGIN_ERROR_MAP.get(err) {
             interface.msgbox(
@@ -3514,6 +4030,19 @@ async fn send_login(
     password: Vec<u8>,
     peer: &mut Stream,
 ) {
+    let token = lc
+        .read()
+        .unwrap()
+        .get_option(crate::session_handoff::OPTION_TOKEN);
+    if !token.is_empty() {
+        // Single use, the next logins use the password.
+        lc.write().unwrap().set_option(
+            crate::session_handoff::OPTION_TOKEN.to_owned(),
+            "".to_owned(),
+        );
+        let msg = crate::session_handoff::Handoff::Redeem { token }.to_message();
+        allow_err!(peer.send(&msg).await);
+    }
     let msg_out = lc
         .read()
         .unwrap()
@@ -3684,6 +4213,10 @@ pub enum Data {
     SetConfirmOverrideFile((i32, i32, bool, bool, bool)),
     AddJob((i32, JobType, String, String, i32, bool, bool)),
     ResumeJob((i32, bool)),
+    StartFolderSync((i32, String, String, u32, bool)),
+    StopFolderSync(i32),
+    SetTransferLimit(u32),
+    SetJobTransferLimit((i32, bool, u32)),
     RecordScreen(bool),
     ElevateDirect,
     ElevateWithLogon(String, String),
@@ -0,0 +1,126 @@
+//! Receiver side of the congestion control of the video stream.
+//!
+//! The receiver measures the one-way delay gradient of the video frames and counts the frames
+//! it has to drop, the report is returned to the host in the echo of the host's `TestDelay`.
+//! It is written to `time`, which is not set by the host, so the hosts without the congestion
+//! control ignore it, and the receivers without it echo zero.
+use hbb_common::message_proto::{video_frame, TestDelay, VideoFrame};
+use std::{collections::HashMap, time::Instant};
+
+// Distinguishes a report from a zero `time`.
+const REPORT_MARKER: i64 = 1 << 62;
+// Larger pts gaps are pauses of the stream, not delay variation.
+const MAX_PTS_DELTA_MS: i64 = 1000;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ReceiverReport {
+    /// Sum of the one-way delay variation since the last report in ms,
+    /// positive if the frames arrive slower than they are captured.
+    pub delay_gradient_ms: i32,
+    /// Frames dropped by the receiver per mille of the frames received since the last report.
+    pub loss_permille: u16,
+}
+
+impl ReceiverReport {
+    pub fn write(&self, t: &mut TestDelay) {
+        t.time = REPORT_MARKER
+            | ((self.loss_permille as i64) << 32)
+            | (self.delay_gradient_ms as u32 as i64);
+    }
+
+    pub fn read(t: &TestDelay) -> Option<Self> {
+        if t.time < 0 || t.time & REPORT_MARKER == 0 {
+            return None;
+        }
+        Some(Self {
+            delay_gradient_ms: t.time as u32 as i32,
+            loss_permille: (t.time >> 32 & 0xFFFF) as u16,
+        })
+    }
+
+    #[inline]
+    pub fn loss(&self) -> f32 {
+        self.loss_permille as f32 / 1000.0
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ReceiverFeedback {
+    // display -> (arrival, pts) of the last frame
+    last: HashMap<usize, (Instant, i64)>,
+    delay_gradient_ms: i64,
+    received: u32,
+    dropped: u32,
+}
+
+impl ReceiverFeedback {
+    pub fn on_frame(&mut self, vf: &VideoFrame) {
+        let Some(pts) = last_pts(vf) else {
+            return;
+        };
+        let now = Instant::now();
+        self.received += 1;
+        if let Some((arrival, last_pts)) = self.last.insert(vf.display as _, (now, pts)) {
+            let pts_delta = pts - last_pts;
+            if pts_delta > 0 && pts_delta < MAX_PTS_DELTA_MS {
+                self.delay_gradient_ms +=
+                    now.duration_since(arrival).as_millis() as i64 - pts_delta;
+            }
+        }
+    }
+
+    #[inline]
+    pub fn on_frame_dropped(&mut self) {
+        self.dropped += 1;
+    }
+
+    /// The report since the last call.
+    pub fn take_report(&mut self) -> ReceiverReport {
+        let loss_permille = if self.received > 0 {
+            (self.dropped as u64 * 1000 / self.received as u64).min(1000) as u16
+        } else {
+            0
+        };
+        let report = ReceiverReport {
+            delay_gradient_ms: self.delay_gradient_ms.clamp(i32::MIN as _, i32::MAX as _) as _,
+            loss_permille,
+        };
+        self.delay_gradient_ms = 0;
+        self.received = 0;
+        self.dropped = 0;
+        report
+    }
+}
+
+pub(crate) fn last_pts(vf: &VideoFrame) -> Option<i64> {
+    use video_frame::Union::*;
+    match vf.union.as_ref()? {
+        Vp8s(f) | Vp9s(f) | Av1s(f) | H264s(f) | H265s(f) => f.frames.last().map(|f| f.pts),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_in_test_delay() {
+        let mut t = TestDelay::default();
+        assert_eq!(ReceiverReport::read(&t), None);
+        for report in [
+            ReceiverReport {
+                delay_gradient_ms: -35,
+                loss_permille: 120,
+            },
+            ReceiverReport {
+                delay_gradient_ms: i32::MAX,
+                loss_permille: 1000,
+            },
+            ReceiverReport::default(),
+        ] {
+            report.write(&mut t);
+            assert_eq!(ReceiverReport::read(&t), Some(report));
+        }
+    }
+}
@@ -190,4 +190,36 @@ pub trait FileManager: Interface {
     fn rename_file(&self, act_id: i32, path: String, new_name: String, is_remote: bool) {
         self.send(Data::RenameFile((act_id, path, new_name, is_remote)));
     }
+
+    /// Mirror the local folder to the remote one, again every `interval_mins` if not 0.
+    fn start_folder_sync(
+        &self,
+        act_id: i32,
+        local: String,
+        remote: String,
+        interval_mins: u32,
+        delete: bool,
+    ) {
+        self.send(Data::StartFolderSync((
+            act_id,
+            local,
+            remote,
+            interval_mins,
+            delete,
+        )));
+    }
+
+    fn stop_folder_sync(&self, act_id: i32) {
+        self.send(Data::StopFolderSync(act_id));
+    }
+
+    /// Limit the rate of all the jobs of the session together in KB/s, 0 for no limit.
+    fn set_transfer_limit(&self, kbps: u32) {
+        self.send(Data::SetTransferLimit(kbps));
+    }
+
+    /// Limit the rate of the job in KB/s, 0 for no limit.
+    fn set_job_transfer_limit(&self, act_id: i32, is_remote: bool, kbps: u32) {
+        self.send(Data::SetJobTransferLimit((act_id, is_remote, kbps)));
+    }
 }
@@ -0,0 +1,136 @@
+//! Delivery of the decoded frames to the video callback on its own thread.
+//!
+//! The network loop queues the received frames to the decoding thread of each display, which
+//! hands the decoded frames here instead of calling the callback itself, so the decoding of
+//! the next frame overlaps with a slow callback, e.g. of Unity or a plugin. Each display has a
+//! slot of one frame waiting for the delivery, and the decoding waits while the slot of its
+//! display is full, so no frame is dropped and the displays don't replace each other's frames.
+//! Dropping the late frames is the opt-in newest-frame-wins mode of the Unity sessions, see
+//! `unity/latest_frame.rs`. The buffers of the delivered frames are returned to the decoding
+//! thread to decode the next frames into.
+use scrap::ImageRgb;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Condvar, Mutex},
+};
+
+#[derive(Default)]
+struct Slots {
+    frames: HashMap<usize, ImageRgb>,
+    // The displays of `frames` in the order they're queued.
+    order: VecDeque<usize>,
+    returned: Vec<Vec<u8>>,
+    closed: bool,
+}
+
+pub struct FrameDelivery {
+    shared: Arc<(Mutex<Slots>, Condvar)>,
+    spare: Vec<Vec<u8>>,
+}
+
+impl FrameDelivery {
+    /// Start the delivery thread, which calls `deliver` with the display and the frame.
+    pub fn start<F>(mut deliver: F) -> Self
+    where
+        F: 'static + FnMut(usize, &mut ImageRgb) + Send,
+    {
+        let shared = Arc::new((Mutex::new(Slots::default()), Condvar::new()));
+        let cloned = shared.clone();
+        std::thread::spawn(move || {
+            let (lock, cvar) = &*cloned;
+            loop {
+                let (display, mut frame) = {
+                    let mut slots = lock.lock().unwrap();
+                    while slots.order.is_empty() && !slots.closed {
+                        slots = cvar.wait(slots).unwrap();
+                    }
+                    let Some(display) = slots.order.pop_front() else {
+                        break;
+                    };
+                    let Some(frame) = slots.frames.remove(&display) else {
+                        continue;
+                    };
+                    // The slot of the display is free for the next frame.
+                    cvar.notify_all();
+                    (display, frame)
+                };
+                deliver(display, &mut frame);
+                lock.lock().unwrap().returned.push(frame.raw);
+            }
+        });
+        Self {
+            shared,
+            spare: Vec::new(),
+        }
+    }
+
+    /// Queue the frame decoded into `rgb`, which gets a free buffer to decode the next frame.
+    /// Waits until the previous frame of `display` is taken by the delivery thread.
+    pub fn deliver(&mut self, display: usize, rgb: &mut ImageRgb) {
+        let (lock, cvar) = &*self.shared;
+        let mut slots = lock.lock().unwrap();
+        while slots.frames.contains_key(&display) && !slots.closed {
+            slots = cvar.wait(slots).unwrap();
+        }
+        self.spare.append(&mut slots.returned);
+        let raw = std::mem::replace(&mut rgb.raw, self.spare.pop().unwrap_or_default());
+        let frame = ImageRgb {
+            raw,
+            w: rgb.w,
+            h: rgb.h,
+            fmt: rgb.fmt,
+            align: rgb.align,
+        };
+        slots.frames.insert(display, frame);
+        slots.order.push_back(display);
+        cvar.notify_all();
+    }
+}
+
+impl Drop for FrameDelivery {
+    fn drop(&mut self) {
+        let (lock, cvar) = &*self.shared;
+        lock.lock().unwrap().closed = true;
+        cvar.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scrap::ImageFormat;
+    use std::{sync::mpsc, time::Duration};
+
+    #[test]
+    fn test_frames_delivered() {
+        let (tx, rx) = mpsc::channel();
+        let (block_tx, block_rx) = mpsc::channel::<()>();
+        let mut delivery = FrameDelivery::start(move |display, frame: &mut ImageRgb| {
+            block_rx.recv().ok();
+            tx.send((display, frame.raw[0])).ok();
+        });
+        let mut rgb = ImageRgb::new(ImageFormat::ARGB, 64);
+        // The first frame is taken by the blocked callback, the next ones wait in the slots.
+        for (display, i) in [(0, 0u8), (1, 1), (0, 2)] {
+            rgb.raw = vec![i; 4];
+            delivery.deliver(display, &mut rgb);
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        let (done_tx, done_rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            rgb.raw = vec![3; 4];
+            delivery.deliver(0, &mut rgb);
+            done_tx.send(()).ok();
+        });
+        // The slot of the display 0 is full.
+        assert!(done_rx.recv_timeout(Duration::from_millis(100)).is_err());
+        for _ in 0..4 {
+            block_tx.send(()).unwrap();
+        }
+        assert_eq!(rx.recv().unwrap(), (0, 0));
+        assert_eq!(rx.recv().unwrap(), (1, 1));
+        assert_eq!(rx.recv().unwrap(), (0, 2));
+        assert_eq!(rx.recv().unwrap(), (0, 3));
+        done_rx.recv().unwrap();
+    }
+}
@@ -1,9 +1,12 @@
 use hbb_common::{
     get_time,
-    message_proto::{Message, VoiceCallRequest, VoiceCallResponse},
+    message_proto::{video_frame, Message, VideoFrame, VoiceCallRequest, VoiceCallResponse},
 };
 use scrap::CodecFormat;
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
 
 #[derive(Debug, Default)]
 pub struct QualityStatus {
@@ -13,6 +16,9 @@ pub struct QualityStatus {
     pub target_bitrate: Option<i32>,
     pub codec_format: Option<CodecFormat>,
     pub chroma: Option<String>,
+    pub audio_buffer_ms: Option<u32>,
+    /// The frame buffers allocated by the decoding since the session started.
+    pub frame_buffer_allocs: Option<usize>,
 }
 
 #[inline]
@@ -35,3 +41,78 @@ pub fn new_voice_call_response(request_timestamp: i64, accepted: bool) -> Messag
     msg.set_voice_call_response(resp);
     msg
 }
+
+// The video is refreshed if the keyframe doesn't arrive in time, e.g. the peer ignores the request.
+const KEYFRAME_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyframeAction {
+    Decode,
+    Skip,
+    /// Skip the frame and request a keyframe.
+    Request,
+    /// Skip the frame and refresh the video.
+    Refresh,
+}
+
+/// The delta frames can't be decoded after a decode error or the reset of the decoder,
+/// they are skipped until the requested keyframe arrives.
+#[derive(Debug, Default)]
+pub struct KeyframeRequest {
+    broken: bool,
+    requested: Option<Instant>,
+}
+
+impl KeyframeRequest {
+    /// The decoder needs a keyframe, but the next frame may be one.
+    #[inline]
+    pub fn reset(&mut self) {
+        self.broken = true;
+        self.requested = None;
+    }
+
+    /// The decoding failed.
+    pub fn on_error(&mut self) -> KeyframeAction {
+        self.broken = true;
+        self.next_action()
+    }
+
+    pub fn on_frame(&mut self, vf: &VideoFrame) -> KeyframeAction {
+        if is_keyframe(vf) {
+            self.broken = false;
+            self.requested = None;
+        }
+        if !self.broken {
+            return KeyframeAction::Decode;
+        }
+        self.next_action()
+    }
+
+    fn next_action(&mut self) -> KeyframeAction {
+        match self.requested {
+            Some(t) if t.elapsed() < KEYFRAME_TIMEOUT => KeyframeAction::Skip,
+            Some(_) => {
+                // The first frame of the refreshed video is a keyframe, don't wait for it in case
+                // the encoder doesn't mark it.
+                self.broken = false;
+                self.requested = None;
+                KeyframeAction::Refresh
+            }
+            None => {
+                self.requested = Some(Instant::now());
+                KeyframeAction::Request
+            }
+        }
+    }
+}
+
+fn is_keyframe(vf: &VideoFrame) -> bool {
+    use video_frame::Union::*;
+    match &vf.union {
+        Some(Vp8s(f) | Vp9s(f) | Av1s(f) | H264s(f) | H265s(f)) => {
+            f.frames.first().map_or(false, |f| f.key)
+        }
+        // The raw frames
+        _ => true,
+    }
+}
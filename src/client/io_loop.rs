@@ -4,11 +4,12 @@ use crate::clipboard::{update_clipboard, ClipboardSide};
 use crate::{audio_service, clipboard::CLIPBOARD_INTERVAL, ConnInner, CLIENT_SERVER};
 use crate::{
     client::{
-        self, new_voice_call_request, Client, Data, Interface, MediaData, MediaSender,
-        QualityStatus, MILLI1, SEC30,
+        self, feedback::ReceiverFeedback, new_voice_call_request, Client, Data, Interface,
+        MediaData, MediaSender, QualityStatus, MILLI1, SEC30,
     },
     common::get_default_sound_input,
     ui_session_interface::{InvokeUiSession, Session},
+    unity::bandwidth::{DownlinkAdjust, DownlinkLimiter},
 };
 #[cfg(feature = "unix-file-copy-paste")]
 use crate::{clipboard::try_empty_clipboard_files, clipboard_file::unix_file_clip};
@@ -76,8 +77,20 @@ pub struct Remote<T: InvokeUiSession> {
     peer_info: ParsedPeerInfo,
     video_threads: HashMap<usize, VideoThread>,
     chroma: Arc<RwLock<Option<Chroma>>>,
+    // The frame buffers allocated by the video threads.
+    frame_buffer_allocs: Arc<RwLock<usize>>,
     last_record_state: bool,
     sent_close_reason: bool,
+    // Set if the connection is lost or failed to establish, not closed on purpose.
+    connection_lost_reason: Option<String>,
+    downlink_limiter: DownlinkLimiter,
+    feedback: ReceiverFeedback,
+    // (job id, file num)
+    pending_checksums: HashMap<(i32, i32), PendingChecksum>,
+    folder_syncs: crate::folder_sync::FolderSyncs,
+    transfer_limiter: crate::transfer_limit::TransferLimiter,
+    // The attempt of this round, recorded to the connection history when it ends.
+    history: crate::connection_history::Entry,
 }
 
 #[derive(Default)]
@@ -103,9 +116,10 @@ impl<T: InvokeUiSession> Remote<T> {
         receiver: mpsc::UnboundedReceiver<Data>,
         sender: mpsc::UnboundedSender<Data>,
     ) -> Self {
+        let id = handler.get_id();
         Self {
             handler,
-            audio_sender: crate::client::start_audio_thread(),
+            audio_sender: crate::client::start_audio_thread(Some(id)),
             receiver,
             sender,
             read_jobs: Vec::new(),
@@ -125,8 +139,78 @@ impl<T: InvokeUiSession> Remote<T> {
             peer_info: Default::default(),
             video_threads: Default::default(),
             chroma: Default::default(),
+            frame_buffer_allocs: Default::default(),
             last_record_state: false,
             sent_close_reason: false,
+            connection_lost_reason: None,
+            downlink_limiter: Default::default(),
+            feedback: Default::default(),
+            pending_checksums: Default::default(),
+            folder_syncs: Default::default(),
+            transfer_limiter: crate::transfer_limit::TransferLimiter::new(
+                crate::transfer_limit::get_global_limit(),
+            ),
+            history: Default::default(),
+        }
+    }
+
+    #[inline]
+    pub fn connection_lost_reason(&self) -> Option<&str> {
+        self.connection_lost_reason.as_deref()
+    }
+
+    fn record_history(&mut self) {
+        let mut entry = std::mem::take(&mut self.history);
+        // The last login error is why it failed, else why it's lost.
+        if entry.success || entry.reason.is_empty() {
+            entry.reason = self.connection_lost_reason.clone().unwrap_or_default();
+        }
+        crate::connection_history::record(entry);
+    }
+
+    // Fail over to another relay server if the relayed session is lost,
+    // the retry of the message box reconnects with the next relay server.
+    fn try_relay_failover(&self, received: bool) -> bool {
+        if !received {
+            return false;
+        }
+        let Some(relay_server) = self.handler.lc.read().unwrap().relay_server.clone() else {
+            return false;
+        };
+        if !crate::relay_servers::on_failure(&relay_server) {
+            return false;
+        }
+        self.handler
+            .msgbox("error", "Connection Error", "relay-failover-tip", "");
+        true
+    }
+
+    // Sign the challenge of the host with the security key in another thread,
+    // it blocks until the user touches the key.
+    fn handle_fido2_challenge(&self, _content: &[u8]) {
+        #[cfg(all(feature = "fido2", not(any(target_os = "android", target_os = "ios"))))]
+        {
+            use crate::auth_fido2::{get_assertion, Fido2, LOGIN_MSG_REQUIRED};
+
+            let Ok(Fido2::Challenge {
+                challenge,
+                credential_ids,
+            }) = serde_json::from_slice(_content)
+            else {
+                log::error!("Invalid security key challenge");
+                return;
+            };
+            let sender = self.sender.clone();
+            let handler = self.handler.clone();
+            std::thread::spawn(move || match get_assertion(&challenge, &credential_ids) {
+                Ok(assertion) => {
+                    sender.send(Data::Message(assertion.to_message())).ok();
+                }
+                Err(e) => {
+                    log::error!("Failed to get the assertion of the security key: {}", e);
+                    handler.msgbox("error", LOGIN_MSG_REQUIRED, &e.to_string(), "");
+                }
+            });
         }
     }
 
@@ -164,29 +248,76 @@ impl<T: InvokeUiSession> Remote<T> {
         } else {
             ConnType::default()
         };
+        self.history = crate::connection_history::Entry {
+            direction: crate::connection_history::DIRECTION_OUTGOING.to_owned(),
+            peer_id: self.handler.get_id(),
+            conn_type: match conn_type {
+                ConnType::FILE_TRANSFER => "FileTransfer",
+                ConnType::VIEW_CAMERA => "ViewCamera",
+                ConnType::TERMINAL => "Terminal",
+                _ => "Remote",
+            }
+            .to_owned(),
+            start: hbb_common::get_time(),
+            ..Default::default()
+        };
 
-        match Client::start(
-            &self.handler.get_id(),
-            key,
-            token,
-            conn_type,
-            self.handler.clone(),
-        )
-        .await
-        {
+        // The messages received by the pre-warmed connection before this session took it.
+        let mut received_before = vec![];
+        let prewarmed = if conn_type == ConnType::DEFAULT_CONN {
+            client::prewarm::take(&self.handler.get_id()).await
+        } else {
+            None
+        };
+        let res = match prewarmed {
+            Some(p) => {
+                self.handler.lc.write().unwrap().relay_server = p.relay_server;
+                self.handler.lc.write().unwrap().session_fingerprint = p.session_fingerprint;
+                received_before = p.received;
+                Ok((
+                    (p.peer, p.direct, p.pk, p.kcp, p.stream_type),
+                    (p.feedback, p.rendezvous_server),
+                ))
+            }
+            None => {
+                Client::start(
+                    &self.handler.get_id(),
+                    key,
+                    token,
+                    conn_type,
+                    self.handler.clone(),
+                )
+                .await
+            }
+        };
+        match res {
             Ok(((mut peer, direct, pk, kcp, stream_type), (feedback, rendezvous_server))) => {
                 self.handler
                     .connection_round_state
                     .lock()
                     .unwrap()
                     .set_connected();
+                crate::unity::reconnect::on_connected(&self.handler.get_id());
                 self.handler
                     .set_connection_type(peer.is_secured(), direct, stream_type); // flutter -> connection_ready
                 self.handler.update_direct(Some(direct));
+                self.history.route = if direct {
+                    crate::connection_history::ROUTE_DIRECT
+                } else {
+                    crate::connection_history::ROUTE_RELAY
+                }
+                .to_owned();
                 if conn_type == ConnType::DEFAULT_CONN || conn_type == ConnType::VIEW_CAMERA {
                     self.handler
                         .set_fingerprint(crate::common::pk_to_fingerprint(pk.unwrap_or_default()));
                 }
+                let session_fingerprint =
+                    self.handler.lc.read().unwrap().session_fingerprint.clone();
+                crate::unity::fingerprint::on_session_fingerprint(
+                    &self.handler.get_id(),
+                    &session_fingerprint,
+                );
+                self.handler.set_session_fingerprint(session_fingerprint);
 
                 // just build for now
                 #[cfg(not(any(target_os = "windows", feature = "unix-file-copy-paste")))]
@@ -217,16 +348,27 @@ impl<T: InvokeUiSession> Remote<T> {
                 let mut status_timer =
                     crate::rustdesk_interval(time::interval(Duration::new(1, 0)));
                 let mut fps_instant = Instant::now();
+                let mut latency_report_instant = Instant::now();
 
                 let _keep_it = client::hc_connection(feedback, rendezvous_server, token).await;
 
-                loop {
+                let mut running = true;
+                for bytes in received_before {
+                    if !self.handle_msg_from_peer(&bytes, &mut peer).await {
+                        running = false;
+                        break;
+                    }
+                }
+                while running {
                     tokio::select! {
                         res = peer.next() => {
                             if let Some(res) = res {
                                 match res {
                                     Err(err) => {
-                                        self.handler.on_establish_connection_error(err.to_string());
+                                        self.connection_lost_reason = Some(err.to_string());
+                                        if !self.try_relay_failover(received) {
+                                            self.handler.on_establish_connection_error(err.to_string());
+                                        }
                                         break;
                                     }
                                     Ok(ref bytes) => {
@@ -236,6 +378,7 @@ impl<T: InvokeUiSession> Remote<T> {
                                             self.handler.update_received(true);
                                         }
                                         self.data_count.fetch_add(bytes.len(), Ordering::Relaxed);
+                                        self.history.bytes_received += bytes.len() as u64;
                                         if !self.handle_msg_from_peer(bytes, &mut peer).await {
                                             break
                                         }
@@ -247,7 +390,10 @@ impl<T: InvokeUiSession> Remote<T> {
                                     self.handler.msgbox("restarting", "Restarting remote device", "remote_restarting_tip", "");
                                 } else {
                                     log::info!("Reset by the peer");
-                                    self.handler.msgbox("error", "Connection Error", "Reset by the peer", "");
+                                    self.connection_lost_reason = Some("Reset by the peer".to_owned());
+                                    if !self.try_relay_failover(received) {
+                                        self.handler.msgbox("error", "Connection Error", "Reset by the peer", "");
+                                    }
                                 }
                                 break;
                             }
@@ -265,18 +411,23 @@ impl<T: InvokeUiSession> Remote<T> {
                         }
                         _ = self.timer.tick() => {
                             if last_recv_time.elapsed() >= SEC30 {
-                                self.handler.msgbox("error", "Connection Error", "Timeout", "");
+                                self.connection_lost_reason = Some("Timeout".to_owned());
+                                if !self.try_relay_failover(received) {
+                                    self.handler.msgbox("error", "Connection Error", "Timeout", "");
+                                }
                                 break;
                             }
                             if !self.read_jobs.is_empty() {
-                                if let Err(err) = fs::handle_read_jobs(&mut self.read_jobs, &mut peer).await {
+                                if let Err(err) = self.transfer_limiter.handle_read_jobs(&mut self.read_jobs, &mut peer).await {
                                     self.handler.msgbox("error", "Connection Error", &err.to_string(), "");
                                     break;
                                 }
                                 self.update_jobs_status();
-                            } else {
+                            } else if !self.folder_syncs.is_sending() {
                                 self.timer = crate::rustdesk_interval(time::interval_at(Instant::now() + SEC30, SEC30));
                             }
+                            let output = self.folder_syncs.tick();
+                            self.handle_folder_sync_output(output, &mut peer).await;
                         }
                         _ = status_timer.tick() => {
                             let elapsed = fps_instant.elapsed().as_millis();
@@ -286,6 +437,7 @@ impl<T: InvokeUiSession> Remote<T> {
                             fps_instant = Instant::now();
                             let mut speed = self.data_count.swap(0, Ordering::Relaxed);
                             speed = speed * 1000 / elapsed as usize;
+                            self.limit_downlink(speed);
                             let speed = format!("{:.2}kB/s", speed as f32 / 1024 as f32);
 
                             let fps = self.video_threads.iter().map(|(k, v)| {
@@ -308,17 +460,28 @@ impl<T: InvokeUiSession> Remote<T> {
                             } else {
                                 Some(self.video_format.clone())
                             };
+                            let audio_buffer_ms =
+                                crate::unity::audio::get_session_audio_output(&self.handler.get_id())
+                                    .buffer_ms();
+                            let frame_buffer_allocs = Some(*self.frame_buffer_allocs.read().unwrap());
                             self.handler.update_quality_status(QualityStatus {
                                 speed: Some(speed),
                                 fps,
                                 chroma,
                                 codec_format,
+                                audio_buffer_ms,
+                                frame_buffer_allocs,
                                 ..Default::default()
                             });
+                            if latency_report_instant.elapsed() >= crate::latency_probe::SAVE_INTERVAL {
+                                latency_report_instant = Instant::now();
+                                self.handler.save_latency_report();
+                            }
                         }
                     }
                 }
                 log::debug!("Exit io_loop of id={}", self.handler.get_id());
+                self.handler.save_latency_report();
                 // Stop client audio server.
                 if let Some(s) = self.stop_voice_call_sender.take() {
                     s.send(()).ok();
@@ -332,9 +495,15 @@ impl<T: InvokeUiSession> Remote<T> {
                 }
             }
             Err(err) => {
+                // Only retry establishing if the session was connected before.
+                if crate::unity::reconnect::is_reconnecting(&self.handler.get_id()) {
+                    self.connection_lost_reason = Some(err.to_string());
+                }
+                self.history.reason = err.to_string();
                 self.handler.on_establish_connection_error(err.to_string());
             }
         }
+        self.record_history();
         // set_disconnected_ok is used to check if new connection round is started.
         let _set_disconnected_ok = self
             .handler
@@ -565,6 +734,7 @@ impl<T: InvokeUiSession> Remote<T> {
                     },
                     _ => {}
                 }
+                self.history.bytes_sent += msg.compute_size() as u64;
                 allow_err!(peer.send(&msg).await);
             }
             Data::SendFiles((id, r#type, path, to, file_num, include_hidden, is_remote)) => {
@@ -857,6 +1027,7 @@ impl<T: InvokeUiSession> Remote<T> {
                     job.remove_download_file();
                 }
                 let _ = fs::remove_job(id, &mut self.read_jobs);
+                self.transfer_limiter.remove_job(id);
                 self.remove_jobs.remove(&id);
             }
             Data::RemoveDir((id, path)) => {
@@ -916,6 +1087,33 @@ impl<T: InvokeUiSession> Remote<T> {
                     }
                 }
             }
+            Data::StartFolderSync((id, local, remote, interval_mins, delete)) => {
+                let peer_ver = self.handler.lc.read().unwrap().version;
+                if crate::is_support_folder_sync_num(peer_ver) {
+                    let msg = self
+                        .folder_syncs
+                        .start(id, local, remote, interval_mins, delete);
+                    allow_err!(peer.send(&msg).await);
+                } else {
+                    self.handle_job_status(
+                        id,
+                        -1,
+                        Some("Folder sync is not supported by the remote side".to_owned()),
+                    );
+                }
+            }
+            Data::StopFolderSync(id) => self.folder_syncs.stop(id),
+            Data::SetTransferLimit(kbps) => {
+                self.transfer_limiter.set_limit(None, kbps);
+                self.send_transfer_limit(None, kbps, peer).await;
+            }
+            Data::SetJobTransferLimit((id, is_remote, kbps)) => {
+                if is_remote {
+                    self.send_transfer_limit(Some(id), kbps, peer).await;
+                } else {
+                    self.transfer_limiter.set_limit(Some(id), kbps);
+                }
+            }
             Data::RenameFile((id, path, new_name, is_remote)) => {
                 if is_remote {
                     let mut msg_out = Message::new();
@@ -1012,9 +1210,9 @@ impl<T: InvokeUiSession> Remote<T> {
         elapsed: i32,
         last_update_jobs_status: &mut (Instant, HashMap<i32, u64>),
         handler: &Session<T>,
-    ) {
+    ) -> u64 {
         if elapsed <= 0 {
-            return;
+            return 0;
         }
         let transferred = job.transferred();
         let last_transferred = {
@@ -1028,13 +1226,14 @@ impl<T: InvokeUiSession> Remote<T> {
         let speed = (transferred - last_transferred) as f64 / (elapsed as f64 / 1000.);
         let file_num = job.file_num() - 1;
         handler.job_progress(job.id(), file_num, speed, job.finished_size() as f64);
+        transferred.saturating_sub(last_transferred)
     }
 
     fn update_jobs_status(&mut self) {
         let elapsed = self.last_update_jobs_status.0.elapsed().as_millis() as i32;
         if elapsed >= 1000 {
             for job in self.read_jobs.iter() {
-                Self::update_job_status(
+                self.history.bytes_sent += Self::update_job_status(
                     job,
                     elapsed,
                     &mut self.last_update_jobs_status,
@@ -1171,6 +1370,11 @@ impl<T: InvokeUiSession> Remote<T> {
         if limited_fps > custom_fps {
             limited_fps = custom_fps;
         }
+        if let Some(fps) = self.downlink_limiter.limited_fps() {
+            if limited_fps > fps as usize {
+                limited_fps = fps as usize;
+            }
+        }
         let last_auto_fps = self.handler.lc.read().unwrap().last_auto_fps.clone();
         let displays = self.video_threads.keys().cloned().collect::<Vec<_>>();
         let mut fps_trending = |display: usize| {
@@ -1241,6 +1445,45 @@ impl<T: InvokeUiSession> Remote<T> {
         }
     }
 
+    // Keep the received stream under the downlink cap set through `crate::unity::bandwidth`.
+    fn limit_downlink(&mut self, bytes_per_second: usize) {
+        if !self.handler.is_default() && !self.handler.is_view_camera() {
+            return;
+        }
+        let cap = crate::unity::bandwidth::get_downlink_cap(&self.handler.get_id());
+        let kbps = (bytes_per_second * 8 / 1000) as u32;
+        let base = {
+            let lc = self.handler.lc.read().unwrap();
+            let fps = lc.custom_fps.lock().unwrap().unwrap_or(30) as i32;
+            (lc.get_custom_image_quality_equivalent(), fps)
+        };
+        match self.downlink_limiter.update(kbps, cap, base) {
+            Some(DownlinkAdjust::Limit { quality, fps }) => {
+                log::info!(
+                    "Limit downlink {}kbps to {:?}kbps, quality: {}, fps: {}",
+                    kbps,
+                    cap,
+                    quality,
+                    fps
+                );
+                let mut misc = Misc::new();
+                misc.set_option(OptionMessage {
+                    custom_image_quality: quality << 8,
+                    ..Default::default()
+                });
+                let mut msg = Message::new();
+                msg.set_misc(misc);
+                self.sender.send(Data::Message(msg)).ok();
+            }
+            Some(DownlinkAdjust::Restore) => {
+                log::info!("Downlink cap removed, restore image quality");
+                let msg = self.handler.lc.read().unwrap().restore_image_quality_message();
+                self.sender.send(Data::Message(msg)).ok();
+            }
+            None => {}
+        }
+    }
+
     fn check_view_camera_support(&self, peer_version: &str, peer_platform: &str) -> bool {
         if self.peer_info.support_view_camera {
             return true;
@@ -1278,6 +1521,95 @@ impl<T: InvokeUiSession> Remote<T> {
         return false;
     }
 
+    async fn handle_folder_sync_output(
+        &mut self,
+        output: crate::folder_sync::Output,
+        peer: &mut Stream,
+    ) {
+        for msg in output.messages {
+            allow_err!(peer.send(&msg).await);
+        }
+        for status in output.statuses {
+            self.handler
+                .update_folder_sync(&serde_json::to_string(&status).unwrap_or_default());
+        }
+    }
+
+    // The downloads are limited by the peer reading the files.
+    async fn send_transfer_limit(&self, id: Option<i32>, kbps: u32, peer: &mut Stream) {
+        let peer_ver = self.handler.lc.read().unwrap().version;
+        if !crate::is_support_transfer_limit_num(peer_ver) {
+            log::info!("Transfer limit is not supported by the remote side");
+            return;
+        }
+        let msg = crate::transfer_limit::LimitRequest { id, kbps }.to_message();
+        allow_err!(peer.send(&msg).await);
+    }
+
+    // Confirm the offset of the resumed file with the chunks verified by the checksums of the peer.
+    async fn handle_file_checksum(&mut self, content: &[u8], peer: &mut Stream) {
+        use crate::file_checksum::{self, FileChecksum};
+
+        let Ok(FileChecksum::Response {
+            id,
+            file_num,
+            size,
+            checksums,
+        }) = serde_json::from_slice::<FileChecksum>(content)
+        else {
+            log::error!("Invalid file checksums");
+            return;
+        };
+        let Some(pending) = self.pending_checksums.remove(&(id, file_num)) else {
+            return;
+        };
+        let local_path = pending.local_path.clone();
+        let verified = match tokio::task::spawn_blocking(move || {
+            file_checksum::verified_size(&local_path, size, &checksums)
+        })
+        .await
+        {
+            Ok(res) => res,
+            Err(e) => Err(e.into()),
+        };
+        let offset = match verified {
+            Ok(verified) => {
+                let partial_size = pending.partial_size.unwrap_or(size);
+                let offset = file_checksum::resume_offset(pending.offset, partial_size, verified);
+                if offset < pending.offset {
+                    log::warn!(
+                        "File {} of job {} is corrupted after {} bytes, resume at {} instead of {}",
+                        file_num,
+                        id,
+                        verified,
+                        offset,
+                        pending.offset
+                    );
+                }
+                offset
+            }
+            Err(e) => {
+                log::error!("Failed to verify file {} of job {}: {}", file_num, id, e);
+                0
+            }
+        };
+        let jobs = if pending.is_upload {
+            &mut self.read_jobs
+        } else {
+            &mut self.write_jobs
+        };
+        if let Some(job) = fs::get_job(id, jobs) {
+            let req = FileTransferSendConfirmRequest {
+                id,
+                file_num,
+                union: Some(file_transfer_send_confirm_request::Union::OffsetBlk(offset)),
+                ..Default::default()
+            };
+            job.confirm(&req).await;
+            allow_err!(peer.send(&new_send_confirm(req)).await);
+        }
+    }
+
     async fn handle_msg_from_peer(&mut self, data: &[u8], peer: &mut Stream) -> bool {
         if let Ok(msg_in) = Message::parse_from_bytes(&data) {
             match msg_in.union {
@@ -1288,8 +1620,16 @@ impl<T: InvokeUiSession> Remote<T> {
                         self.handler.adapt_size();
                         self.send_toggle_virtual_display_msg(peer).await;
                         self.send_toggle_privacy_mode_msg(peer).await;
+                        // Also restarted on the host after reconnecting.
+                        if self.handler.latency_probe.is_enabled()
+                            || LocalConfig::get_bool_option(crate::latency_probe::OPTION_ENABLE)
+                        {
+                            self.handler.start_latency_probe();
+                        }
                     }
                     self.video_format = CodecFormat::from(&vf);
+                    self.feedback.on_frame(&vf);
+                    self.handler.latency_probe.on_received(&vf);
 
                     let display = vf.display as usize;
                     if !self.video_threads.contains_key(&display) {
@@ -1307,6 +1647,7 @@ impl<T: InvokeUiSession> Remote<T> {
                         let video_queue = thread.video_queue.read().unwrap();
                         if video_queue.force_push(vf).is_some() {
                             drop(video_queue);
+                            self.feedback.on_frame_dropped();
                             self.handler.refresh_video(display as _);
                         } else {
                             thread.video_sender.send(MediaData::VideoQueue).ok();
@@ -1324,6 +1665,7 @@ impl<T: InvokeUiSession> Remote<T> {
                             self.handler.lc.write().unwrap().enable_trusted_devices =
                                 lr.enable_trusted_devices;
                         }
+                        self.history.reason = err.clone();
                         if !self.handler.handle_login_error(&err) {
                             return false;
                         }
@@ -1331,6 +1673,8 @@ impl<T: InvokeUiSession> Remote<T> {
                     Some(login_response::Union::PeerInfo(pi)) => {
                         let peer_version = pi.version.clone();
                         let peer_platform = pi.platform.clone();
+                        self.history.success = true;
+                        self.history.peer_name = pi.hostname.clone();
                         self.set_peer_info(&pi);
                         if self.handler.is_view_camera() {
                             if !self.check_view_camera_support(&peer_version, &peer_platform) {
@@ -1406,6 +1750,10 @@ impl<T: InvokeUiSession> Remote<T> {
 
                         if self.handler.is_file_transfer() {
                             self.handler.load_last_jobs();
+                            let kbps = crate::transfer_limit::get_global_limit();
+                            if kbps > 0 {
+                                self.send_transfer_limit(None, kbps, peer).await;
+                            }
                         }
 
                         self.is_connected = true;
@@ -1494,7 +1842,25 @@ impl<T: InvokeUiSession> Remote<T> {
                                                     offset = digest.transferred_size as _;
                                                 }
                                             }
-                                            if let Some(overwrite) = overwrite_strategy {
+                                            let checksum_request = if offset > 0
+                                                && crate::is_support_file_checksum_num(
+                                                    self.handler.lc.read().unwrap().version,
+                                                ) {
+                                                new_file_checksum_request(
+                                                    digest.id,
+                                                    digest.file_num,
+                                                    offset,
+                                                    true,
+                                                    PathBuf::from(&read_path),
+                                                )
+                                            } else {
+                                                None
+                                            };
+                                            if let Some((pending, msg)) = checksum_request {
+                                                self.pending_checksums
+                                                    .insert((digest.id, digest.file_num), pending);
+                                                allow_err!(peer.send(&msg).await);
+                                            } else if let Some(overwrite) = overwrite_strategy {
                                                 let req = FileTransferSendConfirmRequest {
                                                     id: digest.id,
                                                     file_num: digest.file_num,
@@ -1562,7 +1928,32 @@ impl<T: InvokeUiSession> Remote<T> {
                                                             overwrite_strategy = Some(true);
                                                             offset = digest.transferred_size as _;
                                                         }
-                                                        if let Some(overwrite) = overwrite_strategy
+                                                        let checksum_request = if offset > 0
+                                                            && crate::is_support_file_checksum_num(
+                                                                peer_ver,
+                                                            ) {
+                                                            new_file_checksum_request(
+                                                                digest.id,
+                                                                digest.file_num,
+                                                                offset,
+                                                                false,
+                                                                crate::file_checksum::partial_path(
+                                                                    &write_path,
+                                                                ),
+                                                            )
+                                                        } else {
+                                                            None
+                                                        };
+                                                        if let Some((pending, msg)) =
+                                                            checksum_request
+                                                        {
+                                                            self.pending_checksums.insert(
+                                                                (digest.id, digest.file_num),
+                                                                pending,
+                                                            );
+                                                            allow_err!(peer.send(&msg).await);
+                                                        } else if let Some(overwrite) =
+                                                            overwrite_strategy
                                                         {
                                                             let req =
                                                                 FileTransferSendConfirmRequest {
@@ -1647,7 +2038,11 @@ impl<T: InvokeUiSession> Remote<T> {
                                             "Receive print job done, data len: {:?}",
                                             printer_data.as_ref().map(|d| d.len()).unwrap_or(0)
                                         );
-                                        #[cfg(target_os = "windows")]
+                                        #[cfg(any(
+                                            target_os = "windows",
+                                            target_os = "linux",
+                                            target_os = "macos"
+                                        ))]
                                         if let Some(data) = printer_data {
                                             let printer_name = self
                                                 .handler
@@ -1658,12 +2053,18 @@ impl<T: InvokeUiSession> Remote<T> {
                                             // Spawn a new thread to handle the print job.
                                             // Or print job will block the ui thread.
                                             std::thread::spawn(move || {
-                                                if let Err(e) =
-                                                    crate::platform::send_raw_data_to_printer(
+                                                #[cfg(target_os = "windows")]
+                                                let res = crate::platform::send_raw_data_to_printer(
+                                                    printer_name,
+                                                    data,
+                                                );
+                                                #[cfg(not(target_os = "windows"))]
+                                                let res =
+                                                    crate::platform::cups::send_raw_data_to_printer(
                                                         printer_name,
                                                         data,
-                                                    )
-                                                {
+                                                    );
+                                                if let Err(e) = res {
                                                     log::error!("Print job error: {}", e);
                                                 }
                                             });
@@ -1690,6 +2091,10 @@ impl<T: InvokeUiSession> Remote<T> {
                 }
                 Some(message::Union::Misc(misc)) => match misc.union {
                     Some(misc::Union::AudioFormat(f)) => {
+                        let jitter_buffer =
+                            self.handler.lc.read().unwrap().get_jitter_buffer_config();
+                        crate::unity::audio::get_session_audio_output(&self.handler.get_id())
+                            .set_jitter_buffer(jitter_buffer);
                         self.audio_sender.send(MediaData::AudioFormat(f)).ok();
                     }
                     Some(misc::Union::ChatMessage(c)) => {
@@ -1865,14 +2270,205 @@ impl<T: InvokeUiSession> Remote<T> {
                         #[cfg(feature = "flutter")]
                         self.handler.switch_back(&self.handler.get_id());
                     }
+                    Some(misc::Union::PluginRequest(p))
+                        if p.id == crate::folder_sync::REQUEST_ID =>
+                    {
+                        let was_sending = self.folder_syncs.is_sending();
+                        let output = self.folder_syncs.handle(&p.content);
+                        self.handle_folder_sync_output(output, peer).await;
+                        if !was_sending && self.folder_syncs.is_sending() {
+                            self.timer = crate::rustdesk_interval(time::interval(MILLI1));
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p))
+                        if p.id == crate::file_checksum::REQUEST_ID =>
+                    {
+                        self.handle_file_checksum(&p.content, peer).await;
+                    }
+                    Some(misc::Union::PluginRequest(p))
+                        if p.id == crate::auth_fido2::REQUEST_ID =>
+                    {
+                        self.handle_fido2_challenge(&p.content);
+                    }
+                    Some(misc::Union::PluginRequest(p))
+                        if p.id == crate::session_expiry::REQUEST_ID =>
+                    {
+                        match serde_json::from_slice::<crate::session_expiry::SessionExpiry>(
+                            &p.content,
+                        ) {
+                            Ok(expiry) => {
+                                log::info!("Session expires in {}s", expiry.remaining);
+                                let view_only = expiry.is_view_only();
+                                self.handler
+                                    .update_session_expiry(expiry.remaining, view_only);
+                                crate::unity::session_expiry::on_expiry(
+                                    &self.handler.get_id(),
+                                    expiry.remaining,
+                                    view_only,
+                                );
+                            }
+                            Err(e) => log::error!("Invalid session expiry: {}", e),
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p))
+                        if p.id == crate::permission_revocation::REQUEST_ID =>
+                    {
+                        match serde_json::from_slice::<
+                            crate::permission_revocation::PermissionsRevoked,
+                        >(&p.content)
+                        {
+                            Ok(revoked) => {
+                                log::info!(
+                                    "Permissions revoked {:?}: {}",
+                                    revoked.permissions,
+                                    revoked.reason
+                                );
+                                // Switched off by the permission infos following.
+                                self.handler
+                                    .permissions_revoked(&revoked.permissions, &revoked.reason);
+                            }
+                            Err(e) => log::error!("Invalid permission revocation: {}", e),
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p))
+                        if p.id == crate::session_handoff::REQUEST_ID =>
+                    {
+                        match serde_json::from_slice(&p.content) {
+                            Ok(crate::session_handoff::Handoff::Token {
+                                token,
+                                expires_in,
+                                error,
+                            }) => {
+                                let link = if token.is_empty() {
+                                    "".to_owned()
+                                } else {
+                                    crate::session_handoff::link(&self.handler.get_id(), &token)
+                                };
+                                self.handler
+                                    .on_handoff_token(&link, &token, expires_in, &error);
+                            }
+                            Ok(_) => {}
+                            Err(e) => log::error!("Invalid session handoff: {}", e),
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p))
+                        if p.id == crate::windows_session::REQUEST_ID =>
+                    {
+                        match serde_json::from_slice(&p.content) {
+                            Ok(crate::windows_session::WindowsSessions::Sessions {
+                                sessions,
+                                current_sid,
+                                switchable,
+                            }) => {
+                                if switchable {
+                                    self.handler.update_windows_sessions(
+                                        sessions.iter().map(|s| s.to_proto(current_sid)).collect(),
+                                    );
+                                } else {
+                                    self.handler.msgbox(
+                                        "custom-nook-nocancel-hasclose",
+                                        "Windows sessions",
+                                        "windows-session-not-switchable-tip",
+                                        "",
+                                    );
+                                }
+                            }
+                            Ok(_) => {}
+                            Err(e) => log::error!("Invalid Windows sessions: {}", e),
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p))
+                        if p.id == crate::latency_probe::REQUEST_ID =>
+                    {
+                        match serde_json::from_slice(&p.content) {
+                            Ok(crate::latency_probe::LatencyProbe::Host { stages }) => {
+                                self.handler.latency_probe.set_host(stages);
+                            }
+                            Ok(_) => {}
+                            Err(e) => log::error!("Invalid latency probe: {}", e),
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p))
+                        if p.id == crate::input_arbitration::REQUEST_ID =>
+                    {
+                        // The state is passed to the ui as it is.
+                        match std::str::from_utf8(&p.content) {
+                            Ok(state) => self.handler.update_input_control(state),
+                            Err(e) => log::error!("Invalid input control: {}", e),
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p))
+                        if p.id == crate::wake_on_lan::REQUEST_ID =>
+                    {
+                        match serde_json::from_slice(&p.content) {
+                            Ok(crate::wake_on_lan::WakeOnLan::Info { mac, subnet }) => {
+                                let mut lc = self.handler.lc.write().unwrap();
+                                crate::wake_on_lan::save_info(&mut lc, mac, subnet);
+                            }
+                            Ok(_) => {}
+                            Err(e) => log::error!("Invalid wake-on-lan info: {}", e),
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p))
+                        if p.id == crate::task_manager::REQUEST_ID =>
+                    {
+                        // The reply is passed to the ui as it is.
+                        match std::str::from_utf8(&p.content) {
+                            Ok(reply) => self.handler.update_task_manager(reply),
+                            Err(e) => log::error!("Invalid task manager reply: {}", e),
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p))
+                        if p.id == crate::remote_command::REQUEST_ID =>
+                    {
+                        match std::str::from_utf8(&p.content) {
+                            Ok(reply) => self.handler.update_remote_command(reply),
+                            Err(e) => log::error!("Invalid remote command reply: {}", e),
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p))
+                        if p.id == crate::annotation::REQUEST_ID =>
+                    {
+                        match std::str::from_utf8(&p.content) {
+                            Ok(reply) => self.handler.update_annotation(reply),
+                            Err(e) => log::error!("Invalid annotation reply: {}", e),
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p))
+                        if p.id == crate::system_info::REQUEST_ID =>
+                    {
+                        match serde_json::from_slice(&p.content) {
+                            Ok(info) => {
+                                let info = crate::system_info::save(
+                                    &mut self.handler.lc.write().unwrap(),
+                                    &info,
+                                );
+                                self.handler.update_system_info(&info);
+                            }
+                            Err(e) => log::error!("Invalid system info: {}", e),
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p))
+                        if p.id == crate::common::AUDIO_SOURCE_REQUEST_ID =>
+                    {
+                        match serde_json::from_slice::<crate::common::AudioSources>(&p.content) {
+                            Ok(sources) => self.handler.update_audio_sources(
+                                &serde_json::to_string(&sources).unwrap_or_default(),
+                            ),
+                            Err(e) => log::error!("Invalid audio sources: {}", e),
+                        }
+                    }
                     #[cfg(all(feature = "flutter", feature = "plugin_framework"))]
                     #[cfg(not(any(target_os = "android", target_os = "ios")))]
-                    Some(misc::Union::PluginRequest(p)) => {
-                        allow_err!(crate::plugin::handle_server_event(
-                            &p.id,
-                            &self.handler.get_id(),
-                            &p.content
-                        ));
+                    Some(misc::Union::PluginRequest(p))
+                        if !crate::common::is_reserved_plugin_request_id(&p.id) =>
+                    {
+                        let peer = self.handler.get_id();
+                        if crate::plugin::peer_msg::accept_incoming(&p.id, &peer, p.content.len()) {
+                            allow_err!(crate::plugin::handle_server_event(
+                                &p.id, &peer, &p.content
+                            ));
+                        }
                         // to-do: show message box on UI when error occurs?
                     }
                     #[cfg(all(feature = "flutter", feature = "plugin_framework"))]
@@ -1894,7 +2490,11 @@ impl<T: InvokeUiSession> Remote<T> {
                     }
                     _ => {}
                 },
-                Some(message::Union::TestDelay(t)) => {
+                Some(message::Union::TestDelay(mut t)) => {
+                    if !t.from_client {
+                        self.feedback.take_report().write(&mut t);
+                        self.handler.latency_probe.on_network_delay(t.last_delay);
+                    }
                     self.handler.handle_test_delay(t, peer).await;
                 }
                 Some(message::Union::AudioFrame(frame)) => {
@@ -2004,6 +2604,7 @@ impl<T: InvokeUiSession> Remote<T> {
                             lc.set_option(key, opened.service_id.clone());
                         }
                     }
+                    crate::unity::terminal::on_response(&self.handler.get_id(), &response);
                     self.handler.handle_terminal_response(response);
                 }
                 _ => {}
@@ -2317,6 +2918,7 @@ impl<T: InvokeUiSession> Remote<T> {
             fps_control: Default::default(),
             discard_queue: discard_queue.clone(),
         };
+        let buffer_allocs = self.frame_buffer_allocs.clone();
         let handler = self.handler.ui_handler.clone();
         let unity_peer_id: Arc<str> = Arc::from(self.handler.get_id());
         crate::client::start_video_thread(
@@ -2327,6 +2929,7 @@ impl<T: InvokeUiSession> Remote<T> {
             decode_fps,
             self.chroma.clone(),
             discard_queue,
+            buffer_allocs,
             {
                 let unity_peer_id = unity_peer_id.clone();
                 move |display: usize,
@@ -2335,12 +2938,20 @@ impl<T: InvokeUiSession> Remote<T> {
                       pixelbuffer: bool| {
                 *frame_count.write().unwrap() += 1;
                 if pixelbuffer {
+                    #[cfg(all(feature = "flutter", feature = "plugin_framework"))]
+                    #[cfg(not(any(target_os = "android", target_os = "ios")))]
+                    crate::plugin::native_handlers::hook::on_video_frame(
+                        unity_peer_id.as_ref(),
+                        display,
+                        data,
+                    );
                     crate::unity::notify_video_frame(
                         unity_peer_id.as_ref(),
                         display,
                         data.w,
                         data.h,
                         data.align(),
+                        data.fmt,
                         data.raw.as_slice(),
                     );
                     handler.on_rgba(display, data);
@@ -2417,6 +3028,44 @@ impl RemoveJob {
     }
 }
 
+// A resumed file waiting for the checksums of the peer, see `crate::file_checksum`.
+struct PendingChecksum {
+    // The offset of the digest.
+    offset: u32,
+    is_upload: bool,
+    // The source file when uploading, the partial file when downloading.
+    local_path: PathBuf,
+    // The bytes of the partial file, only known when downloading.
+    partial_size: Option<u64>,
+}
+
+// Returns `None` if the partial file to verify is not found, then the offset is confirmed directly.
+fn new_file_checksum_request(
+    id: i32,
+    file_num: i32,
+    offset: u32,
+    is_upload: bool,
+    local_path: PathBuf,
+) -> Option<(PendingChecksum, Message)> {
+    let (size, partial_size) = if is_upload {
+        // All the partial file of the peer.
+        (u64::MAX, None)
+    } else {
+        let size = std::fs::metadata(&local_path).ok()?.len();
+        (size, Some(size))
+    };
+    let msg = crate::file_checksum::FileChecksum::Request { id, file_num, size }.to_message();
+    Some((
+        PendingChecksum {
+            offset,
+            is_upload,
+            local_path,
+            partial_size,
+        },
+        msg,
+    ))
+}
+
 #[derive(Debug, Default)]
 struct FpsControl {
     refresh_times: usize,
@@ -0,0 +1,295 @@
+//! Pre-warming of the connections to the peers used frequently, so that a new session skips
+//! the rendezvous, the hole punching and the key exchange, and gets the first frame sooner.
+//!
+//! The peers are configured with the local option [`OPTION_PEERS`], separated by commas.
+//! A connection to each one is established in the background and kept alive by replying to
+//! the test delays of the host, until a session takes it with [`take`], then a new one
+//! is established for the next session after [`RETRY_INTERVAL`].
+//! The connection is not logged in, so the host only sees a pending connection,
+//! the messages received meanwhile, e.g. the hash, are replayed to the session.
+use super::{Client, Data, Interface, LoginConfigHandler};
+use async_trait::async_trait;
+use hbb_common::{
+    bytes::BytesMut,
+    config::LocalConfig,
+    kcp_stream::KcpStream,
+    log,
+    message_proto::*,
+    protobuf::Message as _,
+    rendezvous_proto::ConnType,
+    tokio::{
+        self,
+        runtime::{Builder, Runtime},
+        sync::{mpsc, oneshot},
+        time::{self, Duration, Instant},
+    },
+    Stream,
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, RwLock},
+};
+
+pub const OPTION_PEERS: &str = "prewarm-peers";
+const RETRY_INTERVAL: Duration = Duration::from_secs(30);
+const CHECK_INTERVAL: Duration = Duration::from_secs(3);
+// The host sends a test delay every second.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+type TakeRequest = oneshot::Sender<Prewarmed>;
+
+lazy_static::lazy_static! {
+    // The streams are registered to the reactor of this runtime, which must keep running
+    // after they are taken by the sessions, so it's not a current thread runtime.
+    static ref RUNTIME: Runtime = Builder::new_multi_thread()
+        .worker_threads(1)
+        .thread_name("prewarm")
+        .enable_all()
+        .build()
+        .unwrap();
+    // The task of each peer, dropping the sender stops it.
+    static ref PEERS: Mutex<HashMap<String, mpsc::UnboundedSender<TakeRequest>>> = Default::default();
+    static ref STARTED: Mutex<bool> = Mutex::new(false);
+}
+
+/// A connection established in advance, the same as the result of [`Client::start`].
+pub struct Prewarmed {
+    pub peer: Stream,
+    pub direct: bool,
+    pub pk: Option<Vec<u8>>,
+    pub kcp: Option<KcpStream>,
+    pub stream_type: &'static str,
+    pub feedback: i32,
+    pub rendezvous_server: String,
+    pub relay_server: Option<String>,
+    pub session_fingerprint: String,
+    /// The messages received before the session took the connection.
+    pub received: Vec<BytesMut>,
+}
+
+/// The peers to pre-warm the connections to.
+pub fn get_peers() -> Vec<String> {
+    LocalConfig::get_option(OPTION_PEERS)
+        .split(',')
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_owned())
+        .collect()
+}
+
+/// Set the peers to pre-warm the connections to, an empty list stops all.
+pub fn set_peers(peers: Vec<String>) {
+    LocalConfig::set_option(OPTION_PEERS.to_owned(), peers.join(","));
+    start();
+}
+
+/// Start pre-warming in the background if some peers are configured, it's fine to call it
+/// several times. The configured peers are checked periodically.
+pub fn start() {
+    let mut started = STARTED.lock().unwrap();
+    if *started || get_peers().is_empty() {
+        return;
+    }
+    *started = true;
+    RUNTIME.spawn(async {
+        let mut interval = time::interval(CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            let peers = get_peers();
+            let mut tasks = PEERS.lock().unwrap();
+            tasks.retain(|id, tx| peers.contains(id) && !tx.is_closed());
+            for id in peers {
+                if !tasks.contains_key(&id) {
+                    let (tx, rx) = mpsc::unbounded_channel();
+                    tasks.insert(id.clone(), tx);
+                    tokio::spawn(keep_warm(id, rx));
+                }
+            }
+        }
+    });
+}
+
+/// Take the pre-warmed connection to the peer, if any is ready.
+pub async fn take(id: &str) -> Option<Prewarmed> {
+    let tx = PEERS.lock().unwrap().get(id)?.clone();
+    let (tx_res, rx_res) = oneshot::channel();
+    tx.send(tx_res).ok()?;
+    let res = rx_res.await.ok();
+    if res.is_some() {
+        log::info!("Use the pre-warmed connection to {}", id);
+    }
+    res
+}
+
+async fn keep_warm(id: String, mut rx: mpsc::UnboundedReceiver<TakeRequest>) {
+    let interface = PrewarmInterface::new(&id);
+    let token = LocalConfig::get_option("access_token");
+    loop {
+        let key = crate::get_key(false).await;
+        let res = tokio::select! {
+            res = Client::start(&id, &key, &token, ConnType::DEFAULT_CONN, interface.clone()) => res,
+            // Not ready yet, the session connects itself.
+            req = rx.recv() => match req {
+                Some(_) => continue,
+                None => break,
+            },
+        };
+        let prewarmed = match res {
+            Ok(((peer, direct, pk, kcp, stream_type), (feedback, rendezvous_server))) => {
+                log::info!("Pre-warmed the connection to {}, direct: {}", id, direct);
+                Prewarmed {
+                    peer,
+                    direct,
+                    pk,
+                    kcp,
+                    stream_type,
+                    feedback,
+                    rendezvous_server,
+                    relay_server: interface.lc.read().unwrap().relay_server.clone(),
+                    session_fingerprint: interface.lc.read().unwrap().session_fingerprint.clone(),
+                    received: vec![],
+                }
+            }
+            Err(e) => {
+                log::warn!("Failed to pre-warm the connection to {}: {}", id, e);
+                if !wait(&mut rx, RETRY_INTERVAL).await {
+                    break;
+                }
+                continue;
+            }
+        };
+        match hold(prewarmed, &mut rx).await {
+            Some(true) => {
+                if !wait(&mut rx, RETRY_INTERVAL).await {
+                    break;
+                }
+            }
+            Some(false) => {}
+            None => break,
+        }
+    }
+    log::info!("Stop pre-warming the connection to {}", id);
+}
+
+// Keep the connection alive until it's taken or lost.
+// Returns Some(true) if taken, Some(false) if lost, None if stopped.
+async fn hold(
+    mut prewarmed: Prewarmed,
+    rx: &mut mpsc::UnboundedReceiver<TakeRequest>,
+) -> Option<bool> {
+    let mut last_recv_time = Instant::now();
+    let mut timer = time::interval(CHECK_INTERVAL);
+    loop {
+        tokio::select! {
+            res = prewarmed.peer.next() => match res {
+                Some(Ok(bytes)) => {
+                    last_recv_time = Instant::now();
+                    match Message::parse_from_bytes(&bytes) {
+                        Ok(Message {
+                            union: Some(message::Union::TestDelay(t)),
+                            ..
+                        }) => super::handle_test_delay(t, &mut prewarmed.peer).await,
+                        _ => prewarmed.received.push(bytes),
+                    }
+                }
+                Some(Err(e)) => {
+                    log::warn!("Pre-warmed connection lost: {}", e);
+                    return Some(false);
+                }
+                None => {
+                    log::info!("Pre-warmed connection reset by the peer");
+                    return Some(false);
+                }
+            },
+            req = rx.recv() => match req {
+                Some(tx_res) => match tx_res.send(prewarmed) {
+                    Ok(()) => return Some(true),
+                    // The session is gone, keep it for the next one.
+                    Err(p) => prewarmed = p,
+                },
+                None => return None,
+            },
+            _ = timer.tick() => {
+                if last_recv_time.elapsed() >= IDLE_TIMEOUT {
+                    log::info!("Pre-warmed connection timeout");
+                    return Some(false);
+                }
+            }
+        }
+    }
+}
+
+// Wait before connecting again, the take requests are declined meanwhile.
+// Returns false if stopped.
+async fn wait(rx: &mut mpsc::UnboundedReceiver<TakeRequest>, duration: Duration) -> bool {
+    let sleep = time::sleep(duration);
+    tokio::pin!(sleep);
+    loop {
+        tokio::select! {
+            _ = &mut sleep => return true,
+            req = rx.recv() => if req.is_none() {
+                return false;
+            },
+        }
+    }
+}
+
+// The connection is not logged in, so only the parts of the interface used by
+// `Client::start` matter.
+#[derive(Clone)]
+struct PrewarmInterface {
+    lc: Arc<RwLock<LoginConfigHandler>>,
+}
+
+impl PrewarmInterface {
+    fn new(id: &str) -> Self {
+        let lc: Arc<RwLock<LoginConfigHandler>> = Default::default();
+        lc.write().unwrap().initialize(
+            id.to_owned(),
+            ConnType::DEFAULT_CONN,
+            None,
+            false,
+            None,
+            None,
+            None,
+        );
+        Self { lc }
+    }
+}
+
+#[async_trait]
+impl Interface for PrewarmInterface {
+    fn send(&self, _data: Data) {}
+
+    fn msgbox(&self, msgtype: &str, title: &str, text: &str, _link: &str) {
+        log::debug!("Pre-warm {}: {}: {}", msgtype, title, text);
+    }
+
+    fn handle_login_error(&self, _err: &str) -> bool {
+        false
+    }
+
+    fn handle_peer_info(&self, _pi: PeerInfo) {}
+
+    fn set_multiple_windows_session(&self, _sessions: Vec<WindowsSession>) {}
+
+    async fn handle_hash(&self, _pass: &str, _hash: Hash, _peer: &mut Stream) {}
+
+    async fn handle_login_from_ui(
+        &self,
+        _os_username: String,
+        _os_password: String,
+        _password: String,
+        _remember: bool,
+        _peer: &mut Stream,
+    ) {
+    }
+
+    async fn handle_test_delay(&self, t: TestDelay, peer: &mut Stream) {
+        super::handle_test_delay(t, peer).await;
+    }
+
+    fn get_lch(&self) -> Arc<RwLock<LoginConfigHandler>> {
+        self.lc.clone()
+    }
+}
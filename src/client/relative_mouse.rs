@@ -0,0 +1,83 @@
+//! Relative mouse mode of the sessions.
+//!
+//! The local cursor is pinned where the mode starts, and its movements are sent to the peer
+//! as the distance moved with `MOUSE_TYPE_MOVE_RELATIVE`, so the first-person cameras and
+//! the 3D viewports reading the raw mouse input keep turning when the cursor would have reached
+//! the edge of the screen. The cursor is hidden by the UI. Only one session captures the mouse,
+//! pressing Ctrl+Alt releases it.
+use hbb_common::{bail, lazy_static, log, ResultType};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+/// Sends the distance moved to the session.
+pub type Sender = Box<dyn Fn(i32, i32) + Send>;
+
+// About the report rate of a mouse, the higher the smoother.
+const POLL_INTERVAL: Duration = Duration::from_millis(4);
+
+lazy_static::lazy_static! {
+    // (session key, stop)
+    static ref CAPTURE: Mutex<Option<(u64, Arc<AtomicBool>)>> = Default::default();
+}
+
+/// Capture the mouse for the session `key`, releasing the one of the other session.
+///
+/// `on_released` is called if it's released by the keys instead of [`stop`].
+pub fn start(key: u64, sender: Sender, on_released: Box<dyn FnOnce() + Send>) -> ResultType<()> {
+    stop(None);
+    let Some(anchor) = crate::get_cursor_pos() else {
+        bail!("Failed to get the cursor position");
+    };
+    if !crate::platform::set_cursor_pos(anchor.0, anchor.1) {
+        bail!("Failed to set the cursor position");
+    }
+    let stopped = Arc::new(AtomicBool::new(false));
+    *CAPTURE.lock().unwrap() = Some((key, stopped.clone()));
+    log::info!("Start relative mouse mode at {:?}", anchor);
+    std::thread::spawn(move || {
+        while !stopped.load(Ordering::SeqCst) {
+            std::thread::sleep(POLL_INTERVAL);
+            let (alt, ctrl, _, _) =
+                crate::keyboard::client::get_modifiers_state(false, false, false, false);
+            if alt && ctrl {
+                stop(Some(key));
+                on_released();
+                break;
+            }
+            let Some((x, y)) = crate::get_cursor_pos() else {
+                continue;
+            };
+            if (x, y) == anchor {
+                continue;
+            }
+            crate::platform::set_cursor_pos(anchor.0, anchor.1);
+            // Stopped while sleeping, the cursor is moved by the user again.
+            if stopped.load(Ordering::SeqCst) {
+                break;
+            }
+            sender(x - anchor.0, y - anchor.1);
+        }
+        log::info!("Relative mouse mode stopped");
+    });
+    Ok(())
+}
+
+/// Release the mouse captured by the session `key`, or by any session if `None`.
+pub fn stop(key: Option<u64>) {
+    let mut capture = CAPTURE.lock().unwrap();
+    if let Some((k, stopped)) = capture.as_ref() {
+        if key.map_or(true, |key| key == *k) {
+            stopped.store(true, Ordering::SeqCst);
+            *capture = None;
+        }
+    }
+}
+
+pub fn is_captured(key: u64) -> bool {
+    matches!(CAPTURE.lock().unwrap().as_ref(), Some((k, _)) if *k == key)
+}
@@ -17,6 +17,13 @@ const RUSTDESK_CLIPBOARD_OWNER_FORMAT: &'static str = "dyn.com.rustdesk.owner";
 // Add special format for Excel XML Spreadsheet
 const CLIPBOARD_FORMAT_EXCEL_XML_SPREADSHEET: &'static str = "XML Spreadsheet";
 
+// The bitmap images copied by the apps not providing PNG, converted to PNG before sending.
+// The device independent bitmaps of Windows are read as RGBA images by `arboard`.
+#[cfg(target_os = "linux")]
+const CLIPBOARD_FORMAT_BMP: &'static str = "image/bmp";
+#[cfg(target_os = "macos")]
+const CLIPBOARD_FORMAT_BMP: &'static str = "com.microsoft.bmp";
+
 #[cfg(not(target_os = "android"))]
 lazy_static::lazy_static! {
     static ref ARBOARD_MTX: Arc<Mutex<()>> = Arc::new(Mutex::new(()));
@@ -45,6 +52,8 @@ const SUPPORTED_FORMATS: &[ClipboardFormat] = &[
     #[cfg(feature = "unix-file-copy-paste")]
     ClipboardFormat::FileUrl,
     ClipboardFormat::Special(CLIPBOARD_FORMAT_EXCEL_XML_SPREADSHEET),
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    ClipboardFormat::Special(CLIPBOARD_FORMAT_BMP),
     ClipboardFormat::Special(RUSTDESK_CLIPBOARD_OWNER_FORMAT),
 ];
 
@@ -313,7 +322,10 @@ impl ClipboardContext {
     }
 
     pub fn get(&mut self, side: ClipboardSide, force: bool) -> ResultType<Vec<ClipboardData>> {
-        let data = self.get_formats_filter(SUPPORTED_FORMATS, side, force)?;
+        #[allow(unused_mut)]
+        let mut data = self.get_formats_filter(SUPPORTED_FORMATS, side, force)?;
+        #[cfg(any(target_os = "linux", target_os = "macos"))]
+        bmp_to_png(&mut data);
         // We have a separate service named `file-clipboard` to handle file copy-paste.
         // We need to read the file urls because file copy may set the other clipboard formats such as text.
         #[cfg(feature = "unix-file-copy-paste")]
@@ -508,6 +520,34 @@ pub fn get_current_clipboard_msg(
     }
 }
 
+// The name of the bitmap format is specific to the platform, the peer receives a PNG image instead,
+// if there's no other image.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn bmp_to_png(data: &mut Vec<ClipboardData>) {
+    let Some(pos) = data
+        .iter()
+        .position(|c| matches!(c, ClipboardData::Special((s, _)) if s == CLIPBOARD_FORMAT_BMP))
+    else {
+        return;
+    };
+    let ClipboardData::Special((_, bmp)) = data.remove(pos) else {
+        return;
+    };
+    if data.iter().any(|c| matches!(c, ClipboardData::Image(_))) {
+        return;
+    }
+    let mut png = Vec::new();
+    match image::load_from_memory_with_format(&bmp, image::ImageFormat::Bmp).and_then(|img| {
+        img.write_to(
+            &mut std::io::Cursor::new(&mut png),
+            image::ImageOutputFormat::Png,
+        )
+    }) {
+        Ok(()) => data.push(ClipboardData::Image(arboard::ImageData::png(png.into()))),
+        Err(e) => log::error!("Failed to convert the clipboard bitmap to PNG: {}", e),
+    }
+}
+
 #[derive(PartialEq, Eq, Clone, Copy)]
 pub enum ClipboardSide {
     Host,
@@ -613,11 +653,7 @@ mod proto {
     fn special_to_proto(d: Vec<u8>, s: String) -> Clipboard {
         let compressed = compress_func(&d);
         let compress = compressed.len() < d.len();
-        let content = if compress {
-            compressed
-        } else {
-            s.bytes().collect::<Vec<u8>>()
-        };
+        let content = if compress { compressed } else { d };
         Clipboard {
             compress,
             content: content.into(),
@@ -6,6 +6,7 @@ use std::{
     task::Poll,
 };
 
+use serde_derive::{Deserialize, Serialize};
 use serde_json::{json, Map, Value};
 
 #[cfg(not(target_os = "ios"))]
@@ -65,12 +66,18 @@ pub const DEFAULT_KEEP_ALIVE: i32 = 60_000;
 
 const MIN_VER_MULTI_UI_SESSION: &str = "1.2.4";
 
+/// Set in the display of `RefreshVideoDisplay` to request a keyframe instead of restarting the video.
+/// The peers not supporting it find no such display and ignore the request.
+pub const REFRESH_KEYFRAME_FLAG: i32 = 0x4000_0000;
+
 pub mod input {
     pub const MOUSE_TYPE_MOVE: i32 = 0;
     pub const MOUSE_TYPE_DOWN: i32 = 1;
     pub const MOUSE_TYPE_UP: i32 = 2;
     pub const MOUSE_TYPE_WHEEL: i32 = 3;
     pub const MOUSE_TYPE_TRACKPAD: i32 = 4;
+    /// `x` and `y` are the distance moved, not the position.
+    pub const MOUSE_TYPE_MOVE_RELATIVE: i32 = 5;
 
     pub const MOUSE_BUTTON_LEFT: i32 = 0x01;
     pub const MOUSE_BUTTON_RIGHT: i32 = 0x02;
@@ -151,6 +158,11 @@ pub fn is_support_remote_print(ver: &str) -> bool {
     hbb_common::get_version_number(ver) >= hbb_common::get_version_number("1.3.9")
 }
 
+// The clients on Linux and macOS print with CUPS, see `crate::platform::cups`.
+pub fn is_support_remote_print_cups(ver: &str) -> bool {
+    hbb_common::get_version_number(ver) >= hbb_common::get_version_number("1.4.5")
+}
+
 pub fn is_support_file_paste_if_macos(ver: &str) -> bool {
     hbb_common::get_version_number(ver) >= hbb_common::get_version_number("1.3.9")
 }
@@ -175,6 +187,177 @@ pub fn is_support_file_transfer_resume_num(ver: i64) -> bool {
     ver >= hbb_common::get_version_number("1.4.2")
 }
 
+#[inline]
+pub fn is_support_keyframe_request_num(ver: i64) -> bool {
+    ver >= hbb_common::get_version_number("1.4.4")
+}
+
+#[inline]
+pub fn is_support_display_settings_num(ver: i64) -> bool {
+    ver >= hbb_common::get_version_number("1.4.4")
+}
+
+#[inline]
+pub fn is_support_audio_app_capture_num(ver: i64) -> bool {
+    ver >= hbb_common::get_version_number("1.4.4")
+}
+
+#[inline]
+pub fn is_support_gamepad_num(ver: i64) -> bool {
+    ver >= hbb_common::get_version_number("1.4.4")
+}
+
+#[inline]
+pub fn is_support_pen_num(ver: i64) -> bool {
+    ver >= hbb_common::get_version_number("1.4.4")
+}
+
+#[inline]
+pub fn is_support_relative_mouse_num(ver: i64) -> bool {
+    ver >= hbb_common::get_version_number("1.4.4")
+}
+
+/// Compare the secrets, e.g. the tokens, in the time independent of where they differ.
+pub fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+#[inline]
+pub fn is_support_file_checksum_num(ver: i64) -> bool {
+    ver >= hbb_common::get_version_number("1.4.4")
+}
+
+#[inline]
+pub fn is_support_folder_sync_num(ver: i64) -> bool {
+    ver >= hbb_common::get_version_number("1.4.4")
+}
+
+#[inline]
+pub fn is_support_transfer_limit_num(ver: i64) -> bool {
+    ver >= hbb_common::get_version_number("1.4.4")
+}
+
+#[inline]
+pub fn is_support_wake_on_lan_num(ver: i64) -> bool {
+    ver >= hbb_common::get_version_number("1.4.4")
+}
+
+/// The ids of the plugin requests reserved for the built-in channels.
+///
+/// Every channel takes its id from here. Plugins with one of these ids are not loaded,
+/// and the requests of these ids are never passed to the plugins.
+///
+/// An id is the name of the channel and the version of its payload, `<name>/<version>`.
+/// The version is bumped when the payload changes incompatibly, the peers of the other
+/// versions ignore the requests instead of misreading them, and the name stays reserved
+/// whatever the version is.
+pub mod plugin_request_id {
+    pub const ANNOTATION: &str = "annotation/1";
+    pub const AUDIO_SOURCE: &str = "audio-source/1";
+    pub const FIDO2: &str = "fido2/1";
+    pub const FILE_CHECKSUM: &str = "file-checksum/1";
+    pub const FOLDER_SYNC: &str = "folder-sync/1";
+    pub const GAMEPAD: &str = "gamepad/1";
+    pub const INPUT_CONTROL: &str = "input-control/1";
+    pub const LATENCY_PROBE: &str = "latency-probe/1";
+    pub const PEN: &str = "pen/1";
+    pub const PERMISSIONS_REVOKED: &str = "permissions-revoked/1";
+    pub const PRIORITY_REGION: &str = "priority-region/1";
+    pub const REMOTE_COMMAND: &str = "remote-command/1";
+    pub const SESSION_EXPIRY: &str = "session-expiry/1";
+    pub const SESSION_HANDOFF: &str = "session-handoff/1";
+    pub const SYSTEM_INFO: &str = "system-info/1";
+    pub const TASK_MANAGER: &str = "task-manager/1";
+    pub const TRANSFER_LIMIT: &str = "transfer-limit/1";
+    pub const VIRTUAL_MONITOR: &str = "virtual-monitor/1";
+    pub const WAKE_ON_LAN: &str = "wake-on-lan/1";
+    pub const WINDOWS_SESSIONS: &str = "windows-sessions/1";
+
+    pub const ALL: &[&str] = &[
+        ANNOTATION,
+        AUDIO_SOURCE,
+        FIDO2,
+        FILE_CHECKSUM,
+        FOLDER_SYNC,
+        GAMEPAD,
+        INPUT_CONTROL,
+        LATENCY_PROBE,
+        PEN,
+        PERMISSIONS_REVOKED,
+        PRIORITY_REGION,
+        REMOTE_COMMAND,
+        SESSION_EXPIRY,
+        SESSION_HANDOFF,
+        SYSTEM_INFO,
+        TASK_MANAGER,
+        TRANSFER_LIMIT,
+        VIRTUAL_MONITOR,
+        WAKE_ON_LAN,
+        WINDOWS_SESSIONS,
+    ];
+}
+
+/// Whether `id` has the name of a reserved id, of any version.
+pub fn is_reserved_plugin_request_id(id: &str) -> bool {
+    let name = id.split('/').next().unwrap_or(id);
+    plugin_request_id::ALL
+        .iter()
+        .any(|reserved| reserved.split('/').next() == Some(name))
+}
+
+/// Wrap the content of a built-in channel in a plugin request of its reserved `id`.
+pub fn make_plugin_request(id: &str, content: impl Into<Bytes>) -> Message {
+    let mut misc = Misc::new();
+    misc.set_plugin_request(PluginRequest {
+        id: id.to_owned(),
+        content: content.into(),
+        ..Default::default()
+    });
+    let mut msg_out = Message::new();
+    msg_out.set_misc(misc);
+    msg_out
+}
+
+/// The content of `msg` if it is a plugin request of the reserved `id`.
+pub fn get_plugin_request<'a>(msg: &'a Message, id: &str) -> Option<&'a [u8]> {
+    match &msg.union {
+        Some(message::Union::Misc(Misc {
+            union: Some(misc::Union::PluginRequest(p)),
+            ..
+        })) if p.id == id => Some(&p.content[..]),
+        _ => None,
+    }
+}
+
+/// The reserved id of the plugin requests listing and selecting the application
+/// whose audio is captured by the host, instead of the whole system mix.
+pub const AUDIO_SOURCE_REQUEST_ID: &str = plugin_request_id::AUDIO_SOURCE;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AudioApp {
+    pub pid: u32,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AudioSourceRequest {
+    List,
+    // 0 is the whole system mix.
+    Select { pid: u32 },
+}
+
+/// The reply of [`AudioSourceRequest`], `selected` is 0 if the system mix is captured.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct AudioSources {
+    pub apps: Vec<AudioApp>,
+    pub selected: u32,
+}
+
 // is server process, with "--server" args
 #[inline]
 pub fn is_server() -> bool {
@@ -872,6 +1055,70 @@ pub fn increase_port<T: std::string::ToString>(host: T, offset: i32) -> String {
     hbb_common::socket_client::increase_port(host, offset)
 }
 
+/// Parse the peer of the direct IP access, an IPv4 or IPv6 literal with or without a port,
+/// e.g. `192.168.1.2`, `::1`, `[2001:db8::1]` or `[2001:db8::1]:21118`.
+/// `port` is used if the peer has no port.
+pub fn parse_direct_ip(peer: &str, port: u16) -> Option<SocketAddr> {
+    let peer = peer.trim();
+    if let Ok(addr) = peer.parse::<SocketAddr>() {
+        return Some(addr);
+    }
+    let ip = peer
+        .strip_prefix('[')
+        .and_then(|s| s.strip_suffix(']'))
+        .unwrap_or(peer);
+    ip.parse::<std::net::IpAddr>()
+        .ok()
+        .map(|ip| SocketAddr::new(ip, port))
+}
+
+/// The CIDR ranges of the direct IP access allowed, comma separated, all if empty.
+pub const OPTION_DIRECT_ACCESS_ALLOW: &str = "direct-access-allow";
+/// The CIDR ranges of the direct IP access denied, comma separated, checked before the allowed ones.
+pub const OPTION_DIRECT_ACCESS_DENY: &str = "direct-access-deny";
+
+/// Parse the comma separated CIDR ranges, e.g. `10.0.0.0/24,192.168.1.2,fd00::/8`.
+pub fn parse_cidrs(s: &str) -> ResultType<Vec<cidr_utils::cidr::IpCidr>> {
+    let mut cidrs = vec![];
+    for x in s.split(',').map(|x| x.trim()).filter(|x| !x.is_empty()) {
+        match x.parse::<cidr_utils::cidr::IpCidr>() {
+            Ok(cidr) => cidrs.push(cidr),
+            Err(e) => bail!("Invalid CIDR \"{}\": {:?}", x, e),
+        }
+    }
+    Ok(cidrs)
+}
+
+/// Check the peer of the direct IP access with the options [`OPTION_DIRECT_ACCESS_ALLOW`]
+/// and [`OPTION_DIRECT_ACCESS_DENY`], before the handshake.
+pub fn is_direct_access_allowed(ip: std::net::IpAddr) -> bool {
+    is_ip_allowed(
+        ip,
+        &Config::get_option(OPTION_DIRECT_ACCESS_ALLOW),
+        &Config::get_option(OPTION_DIRECT_ACCESS_DENY),
+    )
+}
+
+fn is_ip_allowed(ip: std::net::IpAddr, allow: &str, deny: &str) -> bool {
+    // The IPv4 peers of the dual stack listener are mapped to IPv6.
+    let ip = match ip {
+        std::net::IpAddr::V6(v6) => v6.to_ipv4_mapped().map(std::net::IpAddr::V4).unwrap_or(ip),
+        _ => ip,
+    };
+    let contains = |s: &str| match parse_cidrs(s) {
+        Ok(cidrs) => Some(cidrs.iter().any(|cidr| cidr.contains(ip))),
+        Err(e) => {
+            log::error!("{}", e);
+            None
+        }
+    };
+    // The invalid ranges deny all, safer than ignoring a typo.
+    if !deny.trim().is_empty() && contains(deny) != Some(false) {
+        return false;
+    }
+    allow.trim().is_empty() || contains(allow) == Some(true)
+}
+
 pub const POSTFIX_SERVICE: &'static str = "_service";
 
 #[inline]
@@ -921,7 +1168,7 @@ pub async fn do_check_software_update() -> hbb_common::ResultType<()> {
     let tls_type = get_cached_tls_type(tls_url);
     let is_tls_not_cached = tls_type.is_none();
     let tls_type = tls_type.unwrap_or(TlsType::Rustls);
-    let client = create_http_client_async(tls_type, false);
+    let client = create_http_client_async(&url, tls_type, false)?;
     let latest_release_response = match client.post(&url).json(&request).send().await {
         Ok(resp) => {
             upsert_tls_cache(tls_url, tls_type, false);
@@ -930,7 +1177,7 @@ pub async fn do_check_software_update() -> hbb_common::ResultType<()> {
         Err(err) => {
             if is_tls_not_cached && err.is_request() {
                 let tls_type = TlsType::NativeTls;
-                let client = create_http_client_async(tls_type, false);
+                let client = create_http_client_async(&url, tls_type, false)?;
                 let resp = client.post(&url).json(&request).send().await?;
                 upsert_tls_cache(tls_url, tls_type, false);
                 resp
@@ -1117,9 +1364,10 @@ async fn post_request_(
     original_danger_accept_invalid_cert: Option<bool>,
 ) -> ResultType<reqwest::Response> {
     let mut req = create_http_client_async(
+        url,
         tls_type.unwrap_or(TlsType::Rustls),
         danger_accept_invalid_cert.unwrap_or(false),
-    )
+    )?
     .post(url);
     if !header.is_empty() {
         let tmp: Vec<&str> = header.split(": ").collect();
@@ -1208,9 +1456,10 @@ async fn get_http_response_async(
     original_danger_accept_invalid_cert: Option<bool>,
 ) -> ResultType<reqwest::Response> {
     let http_client = create_http_client_async(
+        url,
         tls_type.unwrap_or(TlsType::Rustls),
         danger_accept_invalid_cert.unwrap_or(false),
-    );
+    )?;
     let mut http_client = match method {
         "get" => http_client.get(url),
         "post" => http_client.post(url),
@@ -1478,6 +1727,15 @@ pub fn decode64<T: AsRef<[u8]>>(input: T) -> Result<Vec<u8>, base64::DecodeError
     base64::decode(input)
 }
 
+/// A random token of `len` characters picked uniformly from `chars`, for the secrets.
+pub fn random_token(len: usize, chars: &[u8]) -> String {
+    use hbb_common::rand::Rng;
+    let mut rng = hbb_common::rand::thread_rng();
+    (0..len)
+        .map(|_| chars[rng.gen_range(0..chars.len())] as char)
+        .collect()
+}
+
 pub async fn get_key(sync: bool) -> String {
     #[cfg(windows)]
     if let Ok(lic) = crate::platform::windows::get_license_from_exe_name() {
@@ -1514,6 +1772,25 @@ pub fn pk_to_fingerprint(pk: Vec<u8>) -> String {
         .collect()
 }
 
+/// The fingerprint of the end-to-end key of a session, the same on both sides if no one is
+/// in the middle, so that the users can compare it out-of-band, e.g. on a phone call.
+///
+/// It's of the one-time public key of the host and the key exchange message of the client,
+/// which seals the symmetric key of the session.
+pub fn session_fingerprint(
+    host_pk_b: &[u8],
+    asymmetric_value: &[u8],
+    symmetric_value: &[u8],
+) -> String {
+    use sha2::{Digest, Sha256};
+    let mut hasher = Sha256::new();
+    hasher.update(b"rustdesk-session-fingerprint");
+    hasher.update(host_pk_b);
+    hasher.update(asymmetric_value);
+    hasher.update(symmetric_value);
+    pk_to_fingerprint(hasher.finalize()[..16].to_vec())
+}
+
 #[inline]
 pub async fn get_next_nonkeyexchange_msg(
     conn: &mut Stream,
@@ -2284,6 +2561,70 @@ mod tests {
             / 1000
     }
 
+    #[test]
+    fn test_parse_direct_ip() {
+        let addr = |s: &str| parse_direct_ip(s, 21118).map(|a| a.to_string());
+        assert_eq!(addr("192.168.1.2").as_deref(), Some("192.168.1.2:21118"));
+        assert_eq!(addr("192.168.1.2:80").as_deref(), Some("192.168.1.2:80"));
+        assert_eq!(addr("::1").as_deref(), Some("[::1]:21118"));
+        assert_eq!(addr("2001:db8::1").as_deref(), Some("[2001:db8::1]:21118"));
+        assert_eq!(
+            addr("[2001:db8::1]").as_deref(),
+            Some("[2001:db8::1]:21118")
+        );
+        assert_eq!(
+            addr("[2001:db8::1]:80").as_deref(),
+            Some("[2001:db8::1]:80")
+        );
+        assert_eq!(addr("123456789"), None);
+        assert_eq!(addr("example.com:80"), None);
+    }
+
+    #[test]
+    fn test_is_ip_allowed() {
+        let ip = |s: &str| s.parse::<std::net::IpAddr>().unwrap();
+        assert!(is_ip_allowed(ip("10.1.2.3"), "", ""));
+        assert!(is_ip_allowed(ip("10.1.2.3"), "10.1.2.0/24", ""));
+        assert!(is_ip_allowed(ip("::ffff:10.1.2.3"), "10.1.2.0/24", ""));
+        assert!(!is_ip_allowed(
+            ip("10.1.3.3"),
+            "10.1.2.0/24, 192.168.1.2",
+            ""
+        ));
+        assert!(!is_ip_allowed(ip("10.1.2.3"), "10.0.0.0/8", "10.1.2.3"));
+        assert!(is_ip_allowed(ip("10.1.2.4"), "10.0.0.0/8", "10.1.2.3"));
+        assert!(!is_ip_allowed(ip("10.1.2.3"), "10.1.2.0/33", ""));
+        assert!(!is_ip_allowed(ip("10.1.2.3"), "", "invalid"));
+    }
+
+    #[test]
+    fn test_plugin_request() {
+        let ids: HashSet<&str> = plugin_request_id::ALL.iter().cloned().collect();
+        assert_eq!(ids.len(), plugin_request_id::ALL.len());
+        let msg = make_plugin_request(plugin_request_id::PEN, vec![1, 2]);
+        assert_eq!(
+            get_plugin_request(&msg, plugin_request_id::PEN),
+            Some(&[1, 2][..])
+        );
+        assert_eq!(get_plugin_request(&msg, plugin_request_id::GAMEPAD), None);
+        assert!(is_reserved_plugin_request_id(plugin_request_id::PEN));
+        assert!(is_reserved_plugin_request_id("pen"));
+        assert!(is_reserved_plugin_request_id("pen/2"));
+        assert!(!is_reserved_plugin_request_id("rustdesk.example"));
+        for id in plugin_request_id::ALL {
+            let (_, version) = id.split_once('/').unwrap();
+            assert!(version.parse::<u32>().is_ok());
+        }
+    }
+
+    #[test]
+    fn test_random_token() {
+        let token = random_token(32, b"ab");
+        assert_eq!(token.len(), 32);
+        assert!(token.chars().all(|c| c == 'a' || c == 'b'));
+        assert_ne!(random_token(32, b"ab"), token);
+    }
+
     fn interval_maker() -> Interval {
         interval(Duration::from_secs(1))
     }
@@ -2405,4 +2746,18 @@ mod tests {
             Duration::from_nanos(0)
         );
     }
+    #[test]
+    fn test_session_fingerprint() {
+        let (host_pk_b, _) = box_::gen_keypair();
+        let (asymmetric_value, symmetric_value, _) = create_symmetric_key_msg(host_pk_b.0);
+        let fingerprint = session_fingerprint(&host_pk_b.0, &asymmetric_value, &symmetric_value);
+        // 16 bytes in 8 groups of 4 hex digits.
+        assert_eq!(fingerprint.len(), 39);
+        assert_eq!(fingerprint.split(' ').count(), 8);
+        let (asymmetric_value, symmetric_value, _) = create_symmetric_key_msg(host_pk_b.0);
+        assert_ne!(
+            fingerprint,
+            session_fingerprint(&host_pk_b.0, &asymmetric_value, &symmetric_value)
+        );
+    }
 }
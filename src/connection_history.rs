@@ -0,0 +1,198 @@
+//! The local history of the connections, outgoing and incoming, with their outcomes, for the
+//! troubleshooting and the usage reports.
+//!
+//! Each attempt is recorded as an [`Entry`] when it ends, a JSON line of [`FILE_NAME`] in
+//! [`dir`], rotated as the audit log. The outgoing connections are recorded by the client in
+//! the config of the user, the incoming ones by the server, queried with the ipc if it runs as
+//! another user. They're queried with [`query_all`], e.g. by the CLI `--history list [--json]`.
+//!
+//! The bytes sent of the outgoing connections count the messages of the ui, e.g. the input, and
+//! the files uploaded, not the replies of the session itself.
+use crate::server::audit_log::{append_jsonl, read_jsonl};
+use hbb_common::{config::Config, log, ResultType};
+use serde_derive::{Deserialize, Serialize};
+use std::{path::PathBuf, sync::Mutex};
+
+pub const FILE_NAME: &str = "history.jsonl";
+pub const DIRECTION_OUTGOING: &str = "outgoing";
+pub const DIRECTION_INCOMING: &str = "incoming";
+pub const ROUTE_DIRECT: &str = "direct";
+pub const ROUTE_RELAY: &str = "relay";
+const MAX_SIZE: u64 = 4 << 20;
+
+lazy_static::lazy_static! {
+    static ref LOCK: Mutex<()> = Default::default();
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Entry {
+    pub direction: String,
+    pub peer_id: String,
+    pub peer_name: String,
+    /// e.g. `Remote`, `FileTransfer`, `PortForward`.
+    pub conn_type: String,
+    /// The unix time in milliseconds.
+    pub start: i64,
+    pub end: i64,
+    /// [`ROUTE_DIRECT`] or [`ROUTE_RELAY`], empty if not connected.
+    pub route: String,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    /// Connected and logged in.
+    pub success: bool,
+    /// Why it failed or was closed.
+    pub reason: String,
+}
+
+impl Entry {
+    #[inline]
+    pub fn duration_ms(&self) -> i64 {
+        (self.end - self.start).max(0)
+    }
+}
+
+/// The filter of [`query`], the empty fields match all.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Query {
+    pub direction: String,
+    pub peer_id: String,
+    /// The unix time in milliseconds, inclusive.
+    pub from: i64,
+    /// The latest entries to return, 0 for all.
+    pub limit: usize,
+}
+
+impl Query {
+    fn matches(&self, entry: &Entry) -> bool {
+        (self.direction.is_empty() || entry.direction == self.direction)
+            && (self.peer_id.is_empty() || entry.peer_id == self.peer_id)
+            && entry.start >= self.from
+    }
+}
+
+pub fn dir() -> PathBuf {
+    Config::path("history")
+}
+
+/// Append the entry, the end is filled if 0, and add its bytes to the usage of the peer, see
+/// [`crate::peer_usage`].
+pub fn record(mut entry: Entry) {
+    if entry.end == 0 {
+        entry.end = hbb_common::get_time();
+    }
+    crate::peer_usage::add(&entry.peer_id, entry.bytes_sent, entry.bytes_received);
+    let _lock = LOCK.lock().unwrap();
+    if let Err(e) = append_jsonl(&dir(), FILE_NAME, &entry, MAX_SIZE) {
+        log::error!("Failed to write the connection history: {}", e);
+    }
+}
+
+/// The entries matching `q` recorded by this process, the oldest first.
+pub fn query(q: &Query) -> ResultType<Vec<Entry>> {
+    let entries = {
+        let _lock = LOCK.lock().unwrap();
+        read_jsonl(&dir(), FILE_NAME, |e| q.matches(e))?
+    };
+    Ok(limit(entries, q.limit))
+}
+
+/// The entries matching `q` of the client and the server, which may be in another process.
+pub fn query_all(q: &Query) -> Vec<Entry> {
+    let mut entries = vec![];
+    #[allow(unused_mut)]
+    let mut local = q.clone();
+    // The incoming ones are written by the server, queried with the ipc if it's running, else
+    // read here as well, readable if it's run by the same user.
+    #[cfg(not(any(target_os = "android", target_os = "ios")))]
+    if q.direction != DIRECTION_OUTGOING {
+        let incoming = Query {
+            direction: DIRECTION_INCOMING.to_owned(),
+            ..q.clone()
+        };
+        if let Ok(mut incoming) = crate::ipc::get_connection_history(incoming) {
+            entries.append(&mut incoming);
+            if q.direction == DIRECTION_INCOMING {
+                return limit(entries, q.limit);
+            }
+            local.direction = DIRECTION_OUTGOING.to_owned();
+        }
+    }
+    match query(&local) {
+        Ok(mut local) => entries.append(&mut local),
+        Err(e) => log::error!("Failed to query the connection history: {}", e),
+    }
+    limit(entries, q.limit)
+}
+
+/// Sort the entries by the start, and keep the latest `limit` ones if not 0.
+pub fn limit(mut entries: Vec<Entry>, limit: usize) -> Vec<Entry> {
+    entries.sort_by_key(|e| e.start);
+    if limit > 0 && entries.len() > limit {
+        entries.drain(..entries.len() - limit);
+    }
+    entries
+}
+
+pub fn format_entry(e: &Entry) -> String {
+    let start = chrono::DateTime::from_timestamp_millis(e.start)
+        .map(|t| {
+            t.with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string()
+        })
+        .unwrap_or_default();
+    format!(
+        "{} {:<8} {:<12} {:<14} {:<6} {:>8}s {:>10}B {:>10}B {}{}",
+        start,
+        e.direction,
+        e.peer_id,
+        e.conn_type,
+        if e.route.is_empty() { "-" } else { &e.route },
+        e.duration_ms() / 1000,
+        e.bytes_sent,
+        e.bytes_received,
+        if e.success { "ok" } else { "failed" },
+        if e.reason.is_empty() {
+            "".to_owned()
+        } else {
+            format!(": {}", e.reason)
+        }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query() {
+        let dir = std::env::temp_dir().join(format!("rustdesk-history-{}", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        for i in 0..6 {
+            let entry = Entry {
+                direction: if i % 2 == 0 {
+                    DIRECTION_OUTGOING
+                } else {
+                    DIRECTION_INCOMING
+                }
+                .to_owned(),
+                peer_id: "123".to_owned(),
+                start: i,
+                end: i + 1000,
+                ..Default::default()
+            };
+            append_jsonl(&dir, FILE_NAME, &entry, MAX_SIZE).unwrap();
+        }
+        let q = Query {
+            direction: DIRECTION_OUTGOING.to_owned(),
+            from: 1,
+            ..Default::default()
+        };
+        let entries = read_jsonl(&dir, FILE_NAME, |e| q.matches(e)).unwrap();
+        let starts: Vec<i64> = limit(entries, 1).iter().map(|e| e.start).collect();
+        assert_eq!(starts, vec![4]);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -134,6 +134,9 @@ pub fn core_main() -> Option<Vec<String>> {
         } else if args[0] == "--build-date" {
             println!("{}", crate::BUILD_DATE);
             return None;
+        } else if args[0] == "--diagnose" {
+            println!("{}", crate::diagnostics::run_json());
+            return None;
         }
     }
     #[cfg(windows)]
@@ -331,6 +334,33 @@ pub fn core_main() -> Option<Vec<String>> {
             log::info!("start --uninstall-service");
             crate::platform::uninstall_service(false, true);
             return None;
+        } else if args[0] == "--manage-service" {
+            // --manage-service <install [exe]|uninstall|status>
+            #[cfg(any(target_os = "linux", target_os = "macos"))]
+            {
+                use crate::service_manager;
+                let res = match args.get(1).map(|s| s.as_str()) {
+                    Some("install") => service_manager::install(args.get(2).cloned()),
+                    Some("uninstall") => service_manager::uninstall(),
+                    Some("status") => {
+                        println!(
+                            "{}",
+                            serde_json::to_string(&service_manager::status()).unwrap_or_default()
+                        );
+                        Ok(())
+                    }
+                    _ => {
+                        println!("Usage: --manage-service <install [exe]|uninstall|status>");
+                        Ok(())
+                    }
+                };
+                if let Err(err) = res {
+                    println!("{err}");
+                }
+            }
+            #[cfg(windows)]
+            println!("Use --install-service and --uninstall-service on Windows");
+            return None;
         } else if args[0] == "--service" {
             log::info!("start --service");
             crate::start_os_service();
@@ -409,6 +439,97 @@ pub fn core_main() -> Option<Vec<String>> {
         } else if args[0] == "--get-id" {
             println!("{}", crate::ipc::get_id());
             return None;
+        } else if args[0] == "--audit-log" {
+            // The optional query is the JSON of `audit_log::Query`.
+            let query: crate::server::audit_log::Query = args
+                .get(1)
+                .map(|q| serde_json::from_str(q).unwrap_or_default())
+                .unwrap_or_default();
+            let entries = crate::ipc::get_audit_log(query.clone())
+                .or_else(|_| crate::server::audit_log::query(&query));
+            match entries {
+                Ok(entries) => {
+                    for entry in entries {
+                        println!("{}", serde_json::to_string(&entry).unwrap_or_default());
+                    }
+                }
+                Err(err) => println!("{err}"),
+            }
+            return None;
+        } else if args[0] == "--history" {
+            // --history list [--json] [peer-id]
+            if args.get(1).map(|s| s.as_str()) != Some("list") {
+                println!("Usage: --history list [--json] [peer-id]");
+                return None;
+            }
+            let json = args.iter().any(|a| a == "--json");
+            let query = crate::connection_history::Query {
+                peer_id: args
+                    .iter()
+                    .skip(2)
+                    .find(|a| !a.starts_with("--"))
+                    .cloned()
+                    .unwrap_or_default(),
+                ..Default::default()
+            };
+            for entry in crate::connection_history::query_all(&query) {
+                if json {
+                    println!("{}", serde_json::to_string(&entry).unwrap_or_default());
+                } else {
+                    println!("{}", crate::connection_history::format_entry(&entry));
+                }
+            }
+            return None;
+        } else if args[0] == "--latency-report" {
+            // --latency-report [peer-id]
+            let reports =
+                crate::latency_probe::load_reports(args.get(1).map(|s| s.as_str()).unwrap_or(""));
+            if reports.is_empty() {
+                println!(
+                    "No latency reports, enable the option {}",
+                    crate::latency_probe::OPTION_ENABLE
+                );
+            }
+            for report in reports {
+                println!("{}\n", crate::latency_probe::format_report(&report));
+            }
+            return None;
+        } else if args[0] == "--revoke-permission" {
+            // --revoke-permission <peer-id> <permissions> [reason]
+            if args.len() < 3 {
+                println!(
+                    "Usage: --revoke-permission <peer-id> <clipboard,file,input,...|all> [reason]"
+                );
+                return None;
+            }
+            let res = crate::permission_revocation::parse_names(&args[2]).and_then(|names| {
+                crate::ipc::revoke_peer_permissions(
+                    args[1].clone(),
+                    names,
+                    args.get(3).cloned().unwrap_or_default(),
+                )
+            });
+            match res {
+                Ok(count) => println!("Revoked in {count} session(s)"),
+                Err(err) => println!("{err}"),
+            }
+            return None;
+        } else if args[0] == "--rotate-key" {
+            if config::is_disable_settings() {
+                println!("Settings are disabled!");
+                return None;
+            }
+            if crate::platform::is_installed() && is_root() {
+                // The active sessions are closed by the restart of the server only with `--force`.
+                let force = args.get(1).map(|s| s == "--force").unwrap_or_default();
+                match crate::ipc::rotate_key_pair(force) {
+                    Ok(fingerprint) => println!("New fingerprint: {fingerprint}"),
+                    Err(err) => println!("{err}"),
+                }
+            } else {
+                println!("Installation and administrative privileges required!");
+            }
+            return None;
         } else if args[0] == "--set-id" {
             if config::is_disable_settings() {
                 println!("Settings are disabled!");
@@ -468,6 +589,46 @@ pub fn core_main() -> Option<Vec<String>> {
                 println!("Installation and administrative privileges required!");
             }
             return None;
+        } else if args[0] == "--direct-access-acl" {
+            // --direct-access-acl [allow|deny <CIDR,...>], an empty list to clear
+            if config::is_disable_settings() {
+                println!("Settings are disabled!");
+                return None;
+            }
+            if crate::platform::is_installed() && is_root() {
+                let key = match args.get(1).map(|x| x.as_str()) {
+                    Some("allow") => crate::common::OPTION_DIRECT_ACCESS_ALLOW,
+                    Some("deny") => crate::common::OPTION_DIRECT_ACCESS_DENY,
+                    _ => "",
+                };
+                if args.len() == 1 {
+                    let options = crate::ipc::get_options();
+                    for (name, key) in [
+                        ("allow", crate::common::OPTION_DIRECT_ACCESS_ALLOW),
+                        ("deny", crate::common::OPTION_DIRECT_ACCESS_DENY),
+                    ] {
+                        println!("{}: {}", name, options.get(key).unwrap_or(&"".to_owned()));
+                    }
+                } else if args.len() == 3 && !key.is_empty() {
+                    match crate::common::parse_cidrs(&args[2]) {
+                        Ok(cidrs) => {
+                            let value = cidrs
+                                .iter()
+                                .map(|x| x.to_string())
+                                .collect::<Vec<_>>()
+                                .join(",");
+                            crate::ipc::set_option(key, &value);
+                            println!("Done!");
+                        }
+                        Err(err) => println!("{err}"),
+                    }
+                } else {
+                    println!("Usage: --direct-access-acl [allow|deny <CIDR,...>]");
+                }
+            } else {
+                println!("Installation and administrative privileges required!");
+            }
+            return None;
         } else if args[0] == "--assign" {
             if config::Config::no_register_device() {
                 println!("Cannot assign an unregistrable device!");
@@ -608,8 +769,9 @@ pub fn core_main() -> Option<Vec<String>> {
             if args[0] == "--plugin-install" {
                 if args.len() == 2 {
                     crate::plugin::change_uninstall_plugin(&args[1], false);
-                } else if args.len() == 3 {
-                    crate::plugin::install_plugin_with_url(&args[1], &args[2]);
+                } else if args.len() == 3 || args.len() == 4 {
+                    let sha256 = args.get(3).map(|s| s.as_str()).unwrap_or_default();
+                    crate::plugin::install_plugin_with_url(&args[1], &args[2], sha256);
                 }
                 return None;
             } else if args[0] == "--plugin-uninstall" {
@@ -617,6 +779,11 @@ pub fn core_main() -> Option<Vec<String>> {
                     crate::plugin::change_uninstall_plugin(&args[1], true);
                 }
                 return None;
+            } else if args[0] == "--plugin-host" {
+                if args.len() == 3 {
+                    crate::plugin::run_plugin_host(&args[1], &args[2]);
+                }
+                return None;
             }
         }
     }
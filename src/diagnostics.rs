@@ -0,0 +1,194 @@
+//! Connection diagnostics, so the "cannot connect" reports come with actionable data.
+//!
+//! [`run`] classifies the local NAT, tests the UDP reachability of the rendezvous server,
+//! the TCP connectivity of the rendezvous and relay servers, and returns a JSON report.
+//! It's exposed by the command line `--diagnose` and by `rustdesk_unity_run_diagnostics`.
+//!
+//! Unlike [`crate::test_nat_type`], nothing is saved to the config.
+use hbb_common::{
+    bail,
+    config::{self, Config, RELAY_PORT, RENDEZVOUS_PORT},
+    futures::future::join_all,
+    log,
+    protobuf::Message as _,
+    rendezvous_proto::*,
+    socket_client::{self, connect_tcp, connect_tcp_local},
+    timeout, tokio, ResultType,
+};
+use serde_derive::Serialize;
+use std::{net::SocketAddr, time::Instant};
+
+// Shorter than `CONNECT_TIMEOUT` to keep the whole report within a few seconds.
+const TIMEOUT: u64 = 5_000;
+
+#[derive(Debug, Default, Serialize)]
+pub struct Report {
+    pub version: String,
+    pub os: String,
+    /// "direct", "proxy" or "websocket", the NAT and UDP are not tested if not direct.
+    pub network: String,
+    /// "asymmetric", "symmetric" or "unknown".
+    pub nat_type: String,
+    pub public_ipv6: bool,
+    pub udp: Option<Check>,
+    pub rendezvous_servers: Vec<Check>,
+    pub relay_servers: Vec<Check>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Check {
+    pub host: String,
+    pub ok: bool,
+    pub rtt_ms: Option<u64>,
+    pub error: Option<String>,
+}
+
+impl Check {
+    fn new(host: String, start: Instant, res: ResultType<()>) -> Self {
+        match res {
+            Ok(()) => Self {
+                host,
+                ok: true,
+                rtt_ms: Some(start.elapsed().as_millis() as _),
+                error: None,
+            },
+            Err(e) => Self {
+                host,
+                ok: false,
+                rtt_ms: None,
+                error: Some(e.to_string()),
+            },
+        }
+    }
+}
+
+/// [`run`] from a thread without a runtime, returns the report as a JSON string.
+#[tokio::main(flavor = "current_thread")]
+pub async fn run_json() -> String {
+    serde_json::to_string_pretty(&run().await).unwrap_or_default()
+}
+
+pub async fn run() -> Report {
+    let network = if config::use_ws() {
+        "websocket"
+    } else if Config::get_socks().is_some() {
+        "proxy"
+    } else {
+        "direct"
+    };
+    let rendezvous_servers: Vec<String> = Config::get_rendezvous_servers()
+        .into_iter()
+        .map(|s| crate::check_port(s, RENDEZVOUS_PORT))
+        .collect();
+    let mut relay_servers: Vec<String> = crate::relay_servers::get_configured()
+        .into_iter()
+        .map(|s| crate::check_port(s, RELAY_PORT))
+        .collect();
+    if relay_servers.is_empty() {
+        // The default relay server of hbbs.
+        relay_servers = rendezvous_servers
+            .first()
+            .map(|s| vec![crate::increase_port(s, 1)])
+            .unwrap_or_default();
+    }
+    let mut report = Report {
+        version: crate::VERSION.to_owned(),
+        os: std::env::consts::OS.to_owned(),
+        network: network.to_owned(),
+        nat_type: "unknown".to_owned(),
+        ..Default::default()
+    };
+    let tcp = join_all(rendezvous_servers.iter().cloned().map(check_tcp));
+    let relay = join_all(relay_servers.into_iter().map(check_tcp));
+    if network == "direct" {
+        let server = rendezvous_servers.first().cloned().unwrap_or_default();
+        let (nat_type, udp, ipv6, rendezvous, relay) = tokio::join!(
+            detect_nat_type(&server),
+            check_udp(server.clone()),
+            check_public_ipv6(),
+            tcp,
+            relay
+        );
+        match nat_type {
+            Ok(nat_type) => report.nat_type = format!("{:?}", nat_type).to_lowercase(),
+            Err(e) => log::warn!("Failed to detect the NAT type: {}", e),
+        }
+        report.udp = Some(udp);
+        report.public_ipv6 = ipv6;
+        report.rendezvous_servers = rendezvous;
+        report.relay_servers = relay;
+    } else {
+        let (rendezvous, relay) = tokio::join!(tcp, relay);
+        report.rendezvous_servers = rendezvous;
+        report.relay_servers = relay;
+    }
+    log::info!("Diagnostics: {:?}", report);
+    report
+}
+
+async fn check_tcp(host: String) -> Check {
+    let start = Instant::now();
+    let res = connect_tcp(host.clone(), TIMEOUT).await.map(|_| ());
+    Check::new(host, start, res)
+}
+
+// The rendezvous server replies to the registration, the same as the rendezvous mediator sends.
+async fn check_udp(host: String) -> Check {
+    let start = Instant::now();
+    let res = async {
+        let (mut socket, addr) = socket_client::new_udp_for(&host, TIMEOUT).await?;
+        let mut msg_out = RendezvousMessage::new();
+        msg_out.set_register_peer(RegisterPeer {
+            id: Config::get_id(),
+            serial: Config::get_serial(),
+            ..Default::default()
+        });
+        socket.send(&msg_out, addr).await?;
+        let Some(res) = timeout(TIMEOUT, socket.next()).await? else {
+            bail!("No UDP response");
+        };
+        let (bytes, _) = res?;
+        RendezvousMessage::parse_from_bytes(&bytes)?;
+        Ok(())
+    }
+    .await;
+    Check::new(host, start, res)
+}
+
+// The same as `test_nat_type_`, the NAT is symmetric if the ports seen by the two ports
+// of the rendezvous server are different.
+async fn detect_nat_type(server1: &str) -> ResultType<NatType> {
+    let server2 = crate::increase_port(server1, -1);
+    let mut msg_out = RendezvousMessage::new();
+    msg_out.set_test_nat_request(TestNatRequest {
+        serial: Config::get_serial(),
+        ..Default::default()
+    });
+    let mut ports = vec![];
+    let mut local_addr: Option<SocketAddr> = None;
+    for server in [server1, server2.as_str()] {
+        let mut socket = connect_tcp_local(server, local_addr, TIMEOUT).await?;
+        // reuse the local addr is required for nat test
+        local_addr = Some(socket.local_addr());
+        socket.send(&msg_out).await?;
+        match crate::get_next_nonkeyexchange_msg(&mut socket, Some(TIMEOUT)).await {
+            Some(RendezvousMessage {
+                union: Some(rendezvous_message::Union::TestNatResponse(tnr)),
+                ..
+            }) if tnr.port > 0 => ports.push(tnr.port),
+            _ => bail!("No NAT test response from {}", server),
+        }
+    }
+    Ok(if ports[0] == ports[1] {
+        NatType::ASYMMETRIC
+    } else {
+        NatType::SYMMETRIC
+    })
+}
+
+async fn check_public_ipv6() -> bool {
+    if let Some(job) = crate::test_ipv6().await {
+        timeout(TIMEOUT, job).await.ok();
+    }
+    crate::get_ipv6_socket().await.is_some()
+}
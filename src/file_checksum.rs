@@ -0,0 +1,162 @@
+//! Verification of the partial files before resuming a file transfer.
+//!
+//! The resumed transfer continues after the data received before, which may be corrupted,
+//! e.g. written partly when the connection or the program was interrupted.
+//! Before confirming the offset, the client requests the checksums of the first bytes of
+//! the file on the host with plugin requests of the reserved id [`REQUEST_ID`],
+//! and compares them with the ones of its copy, chunk by chunk of [`CHUNK_SIZE`].
+//! The transfer resumes from the first chunk not matching, so the corrupted chunks
+//! and the ones after them are sent again.
+//!
+//! The client is the one confirming the offset in both directions:
+//! when downloading, the host hashes the source file and the client its partial file;
+//! when uploading, the host hashes its partial file and the client the source file.
+use hbb_common::{
+    log,
+    message_proto::Message,
+    sha2::{Digest, Sha256},
+    ResultType,
+};
+use serde_derive::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+pub const REQUEST_ID: &str = crate::common::plugin_request_id::FILE_CHECKSUM;
+pub const CHUNK_SIZE: u64 = 4 * 1024 * 1024;
+// The extension of the file being written, renamed after the transfer is done.
+const PARTIAL_EXT: &str = ".download";
+const READ_BUF_SIZE: usize = 128 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "t", content = "c")]
+pub enum FileChecksum {
+    /// Request the checksums of the first `size` bytes of the file, all the file if it's shorter.
+    Request { id: i32, file_num: i32, size: u64 },
+    /// The checksums of the chunks of the first `size` bytes.
+    Response {
+        id: i32,
+        file_num: i32,
+        size: u64,
+        checksums: Vec<[u8; 32]>,
+    },
+}
+
+impl FileChecksum {
+    pub fn to_message(&self) -> Message {
+        crate::common::make_plugin_request(REQUEST_ID, serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    /// The response to the request with the checksums of the file at `path`,
+    /// [`Self::empty`] if it fails to read the file.
+    pub fn respond(id: i32, file_num: i32, path: &Path, size: u64) -> Self {
+        match checksums(path, size) {
+            Ok((size, checksums)) => Self::Response {
+                id,
+                file_num,
+                size,
+                checksums,
+            },
+            Err(e) => {
+                log::error!("Failed to hash {:?}: {}", path, e);
+                Self::empty(id, file_num)
+            }
+        }
+    }
+
+    /// The response without checksums, the file is sent again from the beginning.
+    pub fn empty(id: i32, file_num: i32) -> Self {
+        Self::Response {
+            id,
+            file_num,
+            size: 0,
+            checksums: vec![],
+        }
+    }
+}
+
+/// The file being written to `path` before the transfer is done.
+pub fn partial_path(path: &str) -> PathBuf {
+    let partial = PathBuf::from(format!("{}{}", path, PARTIAL_EXT));
+    if partial.exists() {
+        partial
+    } else {
+        PathBuf::from(path)
+    }
+}
+
+/// The checksums of the chunks of the first `size` bytes of the file,
+/// with the number of bytes hashed.
+pub fn checksums(path: &Path, size: u64) -> ResultType<(u64, Vec<[u8; 32]>)> {
+    let mut file = File::open(path)?;
+    let size = size.min(file.metadata()?.len());
+    let mut checksums = Vec::with_capacity(size.div_ceil(CHUNK_SIZE) as usize);
+    let mut buf = vec![0u8; READ_BUF_SIZE];
+    let mut hashed = 0;
+    while hashed < size {
+        let chunk = CHUNK_SIZE.min(size - hashed);
+        let mut hasher = Sha256::new();
+        let mut read = 0;
+        while read < chunk {
+            let n = ((chunk - read) as usize).min(buf.len());
+            file.read_exact(&mut buf[..n])?;
+            hasher.update(&buf[..n]);
+            read += n as u64;
+        }
+        checksums.push(hasher.finalize().into());
+        hashed += chunk;
+    }
+    Ok((size, checksums))
+}
+
+/// The number of the first bytes of the local file matching the checksums of the peer.
+pub fn verified_size(path: &Path, size: u64, peer_checksums: &[[u8; 32]]) -> ResultType<u64> {
+    let (size, checksums) = checksums(path, size)?;
+    let matched = checksums
+        .iter()
+        .zip(peer_checksums)
+        .take_while(|(a, b)| a == b)
+        .count() as u64;
+    Ok((matched * CHUNK_SIZE).min(size))
+}
+
+/// Scale the offset of the digest, counted for `partial_size` bytes received,
+/// down to the `verified` bytes. Rounding down sends a little more data again.
+pub fn resume_offset(offset: u32, partial_size: u64, verified: u64) -> u32 {
+    if partial_size == 0 {
+        return 0;
+    }
+    if verified >= partial_size {
+        return offset;
+    }
+    (offset as u64 * verified / partial_size) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verified_size() {
+        let path = std::env::temp_dir().join(format!("rustdesk_checksum_{}", std::process::id()));
+        let len = CHUNK_SIZE as usize * 2 + 100;
+        let mut data: Vec<u8> = (0..len).map(|i| i as u8).collect();
+        std::fs::write(&path, &data).unwrap();
+        let (size, source) = checksums(&path, u64::MAX).unwrap();
+        assert_eq!(size, len as u64);
+        assert_eq!(source.len(), 3);
+        assert_eq!(verified_size(&path, size, &source).unwrap(), size);
+        data[CHUNK_SIZE as usize + 1] ^= 0xff;
+        std::fs::write(&path, &data).unwrap();
+        assert_eq!(verified_size(&path, size, &source).unwrap(), CHUNK_SIZE);
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(resume_offset(300, 300, 300), 300);
+        assert_eq!(resume_offset(300, 300, 100), 100);
+        assert_eq!(resume_offset(3, 300, 150), 1);
+        assert_eq!(resume_offset(3, 300, 0), 0);
+        assert_eq!(resume_offset(3, 0, 0), 0);
+    }
+}
@@ -712,6 +712,14 @@ impl InvokeUiSession for FlutterHandler {
                     &status.codec_format.map_or(NULL, |it| it.to_string()),
                 ),
                 ("chroma", &status.chroma.map_or(NULL, |it| it.to_string())),
+                (
+                    "audio_buffer_ms",
+                    &status.audio_buffer_ms.map_or(NULL, |it| it.to_string()),
+                ),
+                (
+                    "frame_buffer_allocs",
+                    &status.frame_buffer_allocs.map_or(NULL, |it| it.to_string()),
+                ),
             ],
             &[],
         );
@@ -733,6 +741,10 @@ impl InvokeUiSession for FlutterHandler {
         self.push_event("fingerprint", &[("fingerprint", &fingerprint)], &[]);
     }
 
+    fn set_session_fingerprint(&self, fingerprint: String) {
+        self.push_event("session_fingerprint", &[("fingerprint", &fingerprint)], &[]);
+    }
+
     fn job_error(&self, id: i32, err: String, file_num: i32) {
         self.push_event(
             "job_error",
@@ -934,21 +946,91 @@ impl InvokeUiSession for FlutterHandler {
         )
     }
 
+    fn update_audio_sources(&self, sources: &str) {
+        self.push_event("update_audio_sources", &[("sources", &sources)], &[])
+    }
+
+    fn update_relative_mouse_mode(&self, enabled: bool) {
+        self.push_event(
+            "update_relative_mouse_mode",
+            &[("enabled", &enabled.to_string())],
+            &[],
+        )
+    }
+
+    fn keyboard_grab_released(&self) {
+        let (hotkey, _) = crate::keyboard::get_grab_escape_hotkey();
+        self.push_event("keyboard_grab_released", &[("hotkey", &hotkey)], &[]);
+    }
+
+    fn update_folder_sync(&self, status: &str) {
+        self.push_event("update_folder_sync", &[("status", status)], &[]);
+    }
+
+    fn update_session_expiry(&self, remaining: u64, view_only: bool) {
+        self.push_event(
+            "update_session_expiry",
+            &[
+                ("remaining", &remaining.to_string()),
+                ("view_only", &view_only.to_string()),
+            ],
+            &[],
+        );
+    }
+
+    fn on_handoff_token(&self, link: &str, token: &str, expires_in: u64, error: &str) {
+        self.push_event(
+            "handoff_token",
+            &[
+                ("link", link),
+                ("token", token),
+                ("expires_in", &expires_in.to_string()),
+                ("error", error),
+            ],
+            &[],
+        );
+    }
+
+    fn update_input_control(&self, state: &str) {
+        self.push_event("update_input_control", &[("state", state)], &[]);
+    }
+
+    fn update_system_info(&self, info: &str) {
+        self.push_event("update_system_info", &[("info", info)], &[]);
+    }
+
+    fn update_task_manager(&self, reply: &str) {
+        self.push_event("update_task_manager", &[("reply", reply)], &[]);
+    }
+
+    fn update_remote_command(&self, reply: &str) {
+        self.push_event("update_remote_command", &[("reply", reply)], &[]);
+    }
+
+    fn update_annotation(&self, reply: &str) {
+        self.push_event("update_annotation", &[("reply", reply)], &[]);
+    }
+
+    fn permissions_revoked(&self, permissions: &[String], reason: &str) {
+        self.push_event(
+            "permissions_revoked",
+            &[("permissions", &permissions.join(",")), ("reason", reason)],
+            &[],
+        );
+    }
+
     fn set_multiple_windows_session(&self, sessions: Vec<WindowsSession>) {
-        let mut msg_vec = Vec::new();
-        let mut sessions = sessions;
-        for d in sessions.drain(..) {
-            let mut h: HashMap<&str, String> = Default::default();
-            h.insert("sid", d.sid.to_string());
-            h.insert("name", d.name);
-            msg_vec.push(h);
-        }
         self.push_event(
             "set_multiple_windows_session",
-            &[(
-                "windows_sessions",
-                &serde_json::ser::to_string(&msg_vec).unwrap_or("".to_owned()),
-            )],
+            &[("windows_sessions", &windows_sessions_to_json(sessions))],
+            &[],
+        );
+    }
+
+    fn update_windows_sessions(&self, sessions: Vec<WindowsSession>) {
+        self.push_event(
+            "update_windows_sessions",
+            &[("windows_sessions", &windows_sessions_to_json(sessions))],
             &[],
         );
     }
@@ -1534,6 +1616,21 @@ pub mod connection_manager {
             self.push_event("update_voice_call_state", &[("client", &client_json)]);
         }
 
+        fn update_session_expiry(&self, client: &crate::ui_cm_interface::Client) {
+            let client_json = serde_json::to_string(&client).unwrap_or("".into());
+            self.push_event("update_session_expiry", &[("client", &client_json)]);
+        }
+
+        fn update_permissions(&self, client: &crate::ui_cm_interface::Client) {
+            let client_json = serde_json::to_string(&client).unwrap_or("".into());
+            self.push_event("update_permissions", &[("client", &client_json)]);
+        }
+
+        fn update_command_requests(&self, client: &crate::ui_cm_interface::Client) {
+            let client_json = serde_json::to_string(&client).unwrap_or("".into());
+            self.push_event("update_command_requests", &[("client", &client_json)]);
+        }
+
         fn file_transfer_log(&self, action: &str, log: &str) {
             self.push_event("cm_file_transfer_log", &[(action, log)]);
         }
@@ -1675,6 +1772,15 @@ fn serialize_resolutions(resolutions: &Vec<Resolution>) -> String {
     serde_json::ser::to_string(&v).unwrap_or("".to_string())
 }
 
+#[inline]
+fn windows_sessions_to_json(sessions: Vec<WindowsSession>) -> String {
+    let msg_vec: Vec<HashMap<&str, String>> = sessions
+        .into_iter()
+        .map(|d| HashMap::from([("sid", d.sid.to_string()), ("name", d.name)]))
+        .collect();
+    serde_json::ser::to_string(&msg_vec).unwrap_or("".to_owned())
+}
+
 fn char_to_session_id(c: *const char) -> ResultType<SessionID> {
     if c.is_null() {
         bail!("Session id ptr is null");
@@ -2079,6 +2185,18 @@ pub mod sessions {
         SESSIONS.read().unwrap().get(&(peer_id, conn_type)).cloned()
     }
 
+    #[inline]
+    pub fn get_session_ids(session: &FlutterSession) -> Vec<SessionID> {
+        session
+            .ui_handler
+            .session_handlers
+            .read()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect()
+    }
+
     #[inline]
     pub fn remove_session_by_session_id(id: &SessionID) -> Option<FlutterSession> {
         let mut remove_peer_key = None;
@@ -2097,7 +2215,16 @@ pub mod sessions {
                 None => {}
             }
         }
-        let s = SESSIONS.write().unwrap().remove(&remove_peer_key?);
+        let remove_peer_key = remove_peer_key?;
+        let s = SESSIONS.write().unwrap().remove(&remove_peer_key);
+        if !SESSIONS
+            .read()
+            .unwrap()
+            .keys()
+            .any(|(peer_id, _)| peer_id == &remove_peer_key.0)
+        {
+            crate::unity::audio::remove_session_audio_output(&remove_peer_key.0);
+        }
         #[cfg(not(any(target_os = "android", target_os = "ios")))]
         update_session_count_to_server();
         s
@@ -428,6 +428,133 @@ pub fn session_set_image_quality(session_id: SessionID, value: String) {
     }
 }
 
+pub fn session_get_encoder_profile(session_id: SessionID) -> Option<String> {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        Some(session.get_option("encoder-profile".to_owned()))
+    } else {
+        None
+    }
+}
+
+pub fn session_set_encoder_profile(session_id: SessionID, value: String) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.save_encoder_profile(value);
+    }
+}
+
+pub fn session_get_audio_jitter_buffer(session_id: SessionID) -> Option<String> {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        let config = session.lc.read().unwrap().get_jitter_buffer_config();
+        Some(
+            serde_json::json!({
+                "target_ms": config.target_ms.unwrap_or(0),
+                "max_ms": config.max_ms.unwrap_or(0),
+            })
+            .to_string(),
+        )
+    } else {
+        None
+    }
+}
+
+pub fn session_set_audio_jitter_buffer(session_id: SessionID, target_ms: usize, max_ms: usize) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.save_audio_jitter_buffer(target_ms, max_ms);
+    }
+}
+
+/// The peer replies with the event "update_audio_sources".
+pub fn session_list_audio_sources(session_id: SessionID) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.request_audio_source(None);
+    }
+}
+
+/// Capture the audio of the application `pid` on the peer, 0 for the whole system mix.
+pub fn session_select_audio_source(session_id: SessionID, pid: u32) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.request_audio_source(Some(pid));
+    }
+}
+
+/// The local game controllers as a JSON list of `{"index": 0, "name": "Xbox Controller"}`.
+pub fn main_get_local_gamepads() -> String {
+    #[cfg(feature = "gamepad")]
+    let gamepads = crate::gamepad::local::get_gamepads()
+        .into_iter()
+        .map(|(index, name)| serde_json::json!({ "index": index, "name": name }))
+        .collect::<Vec<_>>();
+    #[cfg(not(feature = "gamepad"))]
+    let gamepads: Vec<serde_json::Value> = vec![];
+    serde_json::to_string(&gamepads).unwrap_or_default()
+}
+
+pub fn session_set_gamepad_forwarding(_session_id: SessionID, _enabled: bool) {
+    #[cfg(feature = "gamepad")]
+    if let Some(session) = sessions::get_session_by_session_id(&_session_id) {
+        session.set_gamepad_forwarding(_enabled);
+    }
+}
+
+/// Pin the local cursor and send its movements to the peer as the distance moved.
+///
+/// Returns false if it's not supported. The event "update_relative_mouse_mode" is pushed
+/// when it's released by Ctrl+Alt.
+pub fn session_set_relative_mouse_mode(_session_id: SessionID, _enabled: bool) -> SyncReturn<bool> {
+    #[cfg(not(any(target_os = "android", target_os = "ios")))]
+    if let Some(session) = sessions::get_session_by_session_id(&_session_id) {
+        return SyncReturn(session.set_relative_mouse_mode(_enabled));
+    }
+    SyncReturn(false)
+}
+
+/// Send the pen at `x`, `y` on the peer, `flags` are the bits of `crate::pen::flags`.
+pub fn session_send_pen_event(
+    session_id: SessionID,
+    x: i32,
+    y: i32,
+    pressure: u16,
+    tilt_x: i8,
+    tilt_y: i8,
+    flags: u8,
+) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.send_pen_event(crate::pen::PenEvent {
+            x,
+            y,
+            pressure: pressure.min(crate::pen::MAX_PRESSURE),
+            tilt_x: tilt_x.clamp(-crate::pen::MAX_TILT, crate::pen::MAX_TILT),
+            tilt_y: tilt_y.clamp(-crate::pen::MAX_TILT, crate::pen::MAX_TILT),
+            flags,
+        });
+    }
+}
+
+pub fn session_get_display_settings(session_id: SessionID) -> Option<String> {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        Some(session.get_option("display-settings".to_owned()))
+    } else {
+        None
+    }
+}
+
+pub fn session_set_display_settings(
+    session_id: SessionID,
+    display: usize,
+    quality: i32,
+    fps: u32,
+    codec: String,
+) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.save_display_settings(scrap::codec::DisplaySettings {
+            display,
+            quality,
+            fps,
+            codec: scrap::codec::DisplaySettings::codec_from_name(&codec),
+        });
+    }
+}
+
 pub fn session_get_keyboard_mode(session_id: SessionID) -> Option<String> {
     if let Some(session) = sessions::get_session_by_session_id(&session_id) {
         Some(session.get_keyboard_mode())
@@ -643,6 +770,118 @@ pub fn session_input_string(session_id: SessionID, value: String) {
 }
 
 // chat_client_mode
+pub fn session_request_handoff(session_id: SessionID) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.request_handoff();
+    }
+}
+
+pub fn session_pass_input_control(session_id: SessionID, to: i32) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.pass_input_control(to);
+    }
+}
+
+pub fn session_add_virtual_monitor(session_id: SessionID, width: u32, height: u32) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.add_virtual_monitor(width, height, crate::virtual_monitor::DEFAULT_REFRESH_RATE);
+    }
+}
+
+pub fn session_request_windows_sessions(session_id: SessionID) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.request_windows_sessions();
+    }
+}
+
+pub fn session_request_processes(session_id: SessionID) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.request_processes();
+    }
+}
+
+pub fn session_kill_process(session_id: SessionID, pid: u32) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.kill_process(pid);
+    }
+}
+
+pub fn session_run_remote_command(
+    session_id: SessionID,
+    command: String,
+    args: Vec<String>,
+    cwd: String,
+) -> SyncReturn<u32> {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        SyncReturn(session.run_remote_command(command, args, cwd))
+    } else {
+        SyncReturn(0)
+    }
+}
+
+pub fn session_cancel_remote_command(session_id: SessionID, id: u32) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.cancel_remote_command(id);
+    }
+}
+
+pub fn session_set_annotating(session_id: SessionID, on: bool) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.set_annotating(on);
+    }
+}
+
+pub fn session_draw_annotation(session_id: SessionID, annotation: String) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.draw_annotation(annotation);
+    }
+}
+
+pub fn session_undo_annotation(session_id: SessionID) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.undo_annotation();
+    }
+}
+
+pub fn session_clear_annotations(session_id: SessionID) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.clear_annotations();
+    }
+}
+
+pub fn session_set_priority_region(
+    session_id: SessionID,
+    display: usize,
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.set_priority_region(display, x, y, w, h);
+    }
+}
+
+pub fn session_start_latency_probe(session_id: SessionID) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.start_latency_probe();
+    }
+}
+
+pub fn session_stop_latency_probe(session_id: SessionID) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.stop_latency_probe();
+    }
+}
+
+pub fn session_get_latency_report(session_id: SessionID) -> SyncReturn<String> {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        SyncReturn(session.get_latency_report())
+    } else {
+        SyncReturn("".to_owned())
+    }
+}
+
 pub fn session_send_chat(session_id: SessionID, text: String) {
     if let Some(session) = sessions::get_session_by_session_id(&session_id) {
         session.send_chat(text);
@@ -879,6 +1118,42 @@ pub fn session_rename_file(
     }
 }
 
+pub fn session_start_folder_sync(
+    session_id: SessionID,
+    act_id: i32,
+    local: String,
+    remote: String,
+    interval_mins: u32,
+    delete: bool,
+) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.start_folder_sync(act_id, local, remote, interval_mins, delete);
+    }
+}
+
+pub fn session_stop_folder_sync(session_id: SessionID, act_id: i32) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.stop_folder_sync(act_id);
+    }
+}
+
+pub fn session_set_transfer_limit(session_id: SessionID, kbps: u32) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.set_transfer_limit(kbps);
+    }
+}
+
+pub fn session_set_job_transfer_limit(
+    session_id: SessionID,
+    act_id: i32,
+    is_remote: bool,
+    kbps: u32,
+) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.set_job_transfer_limit(act_id, is_remote, kbps);
+    }
+}
+
 pub fn session_elevate_direct(session_id: SessionID) {
     if let Some(session) = sessions::get_session_by_session_id(&session_id) {
         session.elevate_direct();
@@ -1014,6 +1289,10 @@ pub fn main_test_if_valid_server(server: String, test_with_proxy: bool) -> Strin
     test_if_valid_server(server, test_with_proxy)
 }
 
+pub fn main_test_proxy(proxy: String, username: String, password: String) -> String {
+    test_proxy(proxy, username, password)
+}
+
 pub fn main_set_socks(proxy: String, username: String, password: String) {
     set_socks(proxy, username, password)
 }
@@ -1199,6 +1478,11 @@ pub fn main_get_input_source() -> SyncReturn<String> {
     SyncReturn(input_source)
 }
 
+/// The hotkey releasing the full keyboard grab, such as "ctrl+alt+shift+escape".
+pub fn main_get_keyboard_grab_escape_hotkey() -> SyncReturn<String> {
+    SyncReturn(crate::keyboard::get_grab_escape_hotkey().0)
+}
+
 pub fn main_set_input_source(session_id: SessionID, value: String) {
     #[cfg(not(any(target_os = "android", target_os = "ios")))]
     {
@@ -1249,6 +1533,28 @@ pub fn main_set_peer_alias(id: String, alias: String) {
     set_peer_option(id, "alias".to_owned(), alias)
 }
 
+pub fn main_set_peer_note(id: String, note: String) {
+    crate::peer_metadata::set_note(&id, note)
+}
+
+/// `tags` is a JSON list.
+pub fn main_set_peer_tags(id: String, tags: String) {
+    crate::peer_metadata::set_tags(&id, crate::peer_metadata::parse_tags(&tags))
+}
+
+/// A JSON map of the tag to the ARGB color.
+pub fn main_get_peer_tag_colors() -> SyncReturn<String> {
+    SyncReturn(serde_json::to_string(&crate::peer_metadata::get_tag_colors()).unwrap_or_default())
+}
+
+pub fn main_set_peer_tag_color(tag: String, color: u32) {
+    crate::peer_metadata::set_tag_color(&tag, Some(color))
+}
+
+pub fn main_remove_peer_tag_color(tag: String) {
+    crate::peer_metadata::set_tag_color(&tag, None)
+}
+
 pub fn main_get_new_stored_peers() -> String {
     let peers: Vec<String> = config::NEW_STORED_PEER_CONFIG
         .lock()
@@ -1439,6 +1745,9 @@ pub fn main_change_language(lang: String) {
     main_broadcast_message(&HashMap::from([("name", "language"), ("lang", &lang)]));
     #[cfg(not(any(target_os = "ios")))]
     send_to_cm(&crate::ipc::Data::Language(lang));
+    #[cfg(feature = "plugin_framework")]
+    #[cfg(not(any(target_os = "android", target_os = "ios")))]
+    crate::plugin::lang::notify_lang_changed();
 }
 
 pub fn main_video_save_directory(root: bool) -> SyncReturn<String> {
@@ -1546,10 +1855,22 @@ pub fn session_get_conn_token(session_id: SessionID) -> SyncReturn<Option<String
     }
 }
 
+pub fn session_get_session_fingerprint(session_id: SessionID) -> SyncReturn<String> {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        SyncReturn(session.lc.read().unwrap().session_fingerprint.clone())
+    } else {
+        SyncReturn("".to_owned())
+    }
+}
+
 pub fn cm_handle_incoming_voice_call(id: i32, accept: bool) {
     crate::ui_cm_interface::handle_incoming_voice_call(id, accept);
 }
 
+pub fn cm_handle_remote_command(id: i32, command_id: u32, approved: bool) {
+    crate::ui_cm_interface::handle_remote_command(id, command_id, approved);
+}
+
 pub fn cm_close_voice_call(id: i32) {
     crate::ui_cm_interface::close_voice_call(id);
 }
@@ -1623,6 +1944,8 @@ pub fn cm_get_clients_length() -> usize {
 
 pub fn main_init(app_dir: String, custom_client_config: String) {
     initialize(&app_dir, &custom_client_config);
+    crate::client::prewarm::start();
+    crate::unity::event_bridge::start_with_options();
 }
 
 pub fn main_device_id(id: String) {
@@ -1645,6 +1968,22 @@ pub fn main_has_vram() -> SyncReturn<bool> {
     SyncReturn(has_vram())
 }
 
+/// The encoders in use by the displays of the controlled side, in json.
+pub fn main_get_encoder_selections() -> String {
+    #[cfg(target_os = "android")]
+    return crate::server::video_service::get_encoder_selections();
+    #[cfg(target_os = "ios")]
+    return "".to_owned();
+    #[cfg(not(any(target_os = "android", target_os = "ios")))]
+    match crate::ipc::get_encoder_selections() {
+        Ok(v) => v,
+        Err(e) => {
+            log::debug!("Failed to get encoder selections, {}", e);
+            "".to_owned()
+        }
+    }
+}
+
 pub fn main_supported_hwdecodings() -> SyncReturn<String> {
     let decoding = supported_hwdecodings();
     let msg = HashMap::from([("h264", decoding.0), ("h265", decoding.1)]);
@@ -1737,6 +2076,7 @@ pub fn session_send_mouse(session_id: SessionID, msg: String) {
                 "up" => MOUSE_TYPE_UP,
                 "wheel" => MOUSE_TYPE_WHEEL,
                 "trackpad" => MOUSE_TYPE_TRACKPAD,
+                "move_relative" => MOUSE_TYPE_MOVE_RELATIVE,
                 _ => 0,
             };
         }
@@ -1891,6 +2231,16 @@ pub fn main_wol(id: String) {
     crate::lan::send_wol(id)
 }
 
+/// Wake the peer via the sessions to the other peers on its LAN, returns the number of the peers asked.
+pub fn main_wol_via_peers(id: String) -> i32 {
+    crate::wake_on_lan::wake_via_peers(&id) as _
+}
+
+/// Set the peers to keep the connections warm to, see [`crate::client::prewarm`].
+pub fn main_set_prewarm_peers(ids: Vec<String>) {
+    crate::client::prewarm::set_peers(ids);
+}
+
 pub fn main_create_shortcut(_id: String) {
     #[cfg(windows)]
     create_shortcut(_id);
@@ -1937,6 +2287,11 @@ pub fn cm_switch_permission(conn_id: i32, name: String, enabled: bool) {
     crate::ui_cm_interface::switch_permission(conn_id, name, enabled)
 }
 
+pub fn cm_assign_input_control(conn_id: i32) {
+    #[cfg(not(any(target_os = "ios")))]
+    crate::ui_cm_interface::assign_input_control(conn_id)
+}
+
 pub fn cm_can_elevate() -> SyncReturn<bool> {
     SyncReturn(crate::ui_cm_interface::can_elevate())
 }
@@ -2304,6 +2659,43 @@ pub fn plugin_set_shared_option(_id: String, _key: String, _value: String) {
     }
 }
 
+/// Translate the UI string `key` of the plugin, `locale` is the current UI language if empty.
+pub fn plugin_translate(_id: String, _locale: String, _key: String) -> SyncReturn<String> {
+    #[cfg(feature = "plugin_framework")]
+    #[cfg(not(any(target_os = "android", target_os = "ios")))]
+    {
+        SyncReturn(crate::plugin::lang::translate(&_id, &_locale, &_key))
+    }
+    #[cfg(any(
+        not(feature = "plugin_framework"),
+        target_os = "android",
+        target_os = "ios"
+    ))]
+    {
+        SyncReturn(_key)
+    }
+}
+
+/// The metrics reported by the plugin, a json object of the metric names.
+pub fn plugin_get_metrics(_id: String) -> SyncReturn<String> {
+    #[cfg(feature = "plugin_framework")]
+    #[cfg(not(any(target_os = "android", target_os = "ios")))]
+    {
+        SyncReturn(
+            serde_json::to_string(&crate::plugin::metrics::get(&_id).unwrap_or_default())
+                .unwrap_or_default(),
+        )
+    }
+    #[cfg(any(
+        not(feature = "plugin_framework"),
+        target_os = "android",
+        target_os = "ios"
+    ))]
+    {
+        SyncReturn("{}".to_owned())
+    }
+}
+
 #[inline]
 pub fn plugin_reload(_id: String) {
     #[cfg(feature = "plugin_framework")]
@@ -2406,6 +2798,42 @@ pub fn plugin_install(_id: String, _b: bool) {
     }
 }
 
+/// Search the plugin marketplace, returns the json of the matched plugins, or `{"error": ...}`.
+pub fn plugin_marketplace_search(_query: String, _refresh: bool) -> String {
+    #[cfg(feature = "plugin_framework")]
+    #[cfg(not(any(target_os = "android", target_os = "ios")))]
+    {
+        match crate::plugin::marketplace::search(&_query, _refresh) {
+            Ok(plugins) => serde_json::to_string(&plugins).unwrap_or("[]".to_owned()),
+            Err(e) => serde_json::json!({ "error": e.to_string() }).to_string(),
+        }
+    }
+    #[cfg(any(
+        not(feature = "plugin_framework"),
+        target_os = "android",
+        target_os = "ios"
+    ))]
+    {
+        "[]".to_owned()
+    }
+}
+
+/// Install or update the plugin from the marketplace, the progress is sent as plugin manager events.
+pub fn plugin_marketplace_install(_id: String, _update: bool) {
+    #[cfg(feature = "plugin_framework")]
+    #[cfg(not(any(target_os = "android", target_os = "ios")))]
+    {
+        let res = if _update {
+            crate::plugin::marketplace::update(&_id)
+        } else {
+            crate::plugin::marketplace::install(&_id)
+        };
+        if let Err(e) = res {
+            log::error!("Failed to install plugin '{}' from marketplace: {}", _id, e);
+        }
+    }
+}
+
 pub fn is_support_multi_ui_session(version: String) -> SyncReturn<bool> {
     SyncReturn(crate::common::is_support_multi_ui_session(&version))
 }
@@ -2458,6 +2886,42 @@ pub fn main_has_valid_2fa_sync() -> SyncReturn<bool> {
     SyncReturn(has_valid_2fa())
 }
 
+pub fn main_get_permission_profiles() -> SyncReturn<String> {
+    SyncReturn(get_permission_profiles())
+}
+
+pub fn main_check_access_windows(windows: String, timezone: String) -> SyncReturn<String> {
+    SyncReturn(check_access_windows(windows, timezone))
+}
+
+pub fn main_set_permission_profile_assignment(kind: String, key: String, profile: String) {
+    set_permission_profile_assignment(kind, key, profile)
+}
+
+pub fn main_get_audit_log(query: String) -> String {
+    get_audit_log(query)
+}
+
+pub fn main_get_connection_history(query: String) -> String {
+    get_connection_history(query)
+}
+
+pub fn main_get_fido2_keys() -> SyncReturn<String> {
+    SyncReturn(get_fido2_keys())
+}
+
+pub fn main_register_fido2_key(name: String, pin: String) -> String {
+    register_fido2_key(name, pin)
+}
+
+pub fn main_remove_fido2_key(name: String) {
+    remove_fido2_key(name)
+}
+
+pub fn main_rotate_key_pair(force: bool) -> String {
+    rotate_key_pair(force)
+}
+
 pub fn main_verify_bot(token: String) -> String {
     verify_bot(token)
 }
@@ -2516,7 +2980,12 @@ pub fn main_get_printer_names() -> SyncReturn<String> {
         serde_json::to_string(&crate::platform::windows::get_printer_names().unwrap_or_default())
             .unwrap_or_default(),
     );
-    #[cfg(not(target_os = "windows"))]
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    return SyncReturn(
+        serde_json::to_string(&crate::platform::cups::get_printer_names().unwrap_or_default())
+            .unwrap_or_default(),
+    );
+    #[cfg(not(any(target_os = "windows", target_os = "linux", target_os = "macos")))]
     return SyncReturn("".to_owned());
 }
 
@@ -0,0 +1,802 @@
+//! Folder synchronization of the file transfer sessions.
+//!
+//! A sync job mirrors a local folder to a folder on the host. The trees are compared by
+//! the size and the modified time of the files, the files differing are compared by the
+//! checksums of their blocks of [`BLOCK_SIZE`], and only the blocks differing are sent.
+//! The blocks are compared at the same offsets, unlike rsync the data moved by an insertion
+//! is sent again. With an interval, the job runs again while the session is open.
+//!
+//! The messages are plugin requests of the reserved id [`REQUEST_ID`], a JSON header
+//! prefixed by its length and followed by the data of the blocks. The host reads the trees
+//! and the checksums in the connection, and writes the files in the cm like the uploads.
+use hbb_common::{
+    bail, log,
+    message_proto::Message,
+    sha2::{Digest, Sha256},
+    ResultType,
+};
+use serde_derive::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, VecDeque},
+    fs::{self, File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Component, Path, PathBuf},
+    time::{Duration, Instant, UNIX_EPOCH},
+};
+
+pub const REQUEST_ID: &str = crate::common::plugin_request_id::FOLDER_SYNC;
+pub const BLOCK_SIZE: u64 = 64 * 1024;
+// The max data of a write message.
+const MAX_WRITE_SIZE: u64 = 512 * 1024;
+// The max data sent on each tick of the timer of the file transfer.
+const MAX_TICK_SIZE: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    /// The path relative to the root, separated by '/'.
+    pub rel: String,
+    pub is_dir: bool,
+    pub size: u64,
+    /// Seconds since the epoch.
+    pub mtime: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "t", content = "c")]
+pub enum SyncMessage {
+    // From the client.
+    ListTree {
+        id: i32,
+        root: String,
+    },
+    Checksums {
+        id: i32,
+        root: String,
+        rel: String,
+    },
+    MakeDir {
+        id: i32,
+        root: String,
+        rel: String,
+    },
+    /// Followed by the data written at `offset`.
+    Write {
+        id: i32,
+        root: String,
+        rel: String,
+        offset: u64,
+    },
+    /// Truncate the file to `size` and set the modified time, create it if not found.
+    Finish {
+        id: i32,
+        root: String,
+        rel: String,
+        size: u64,
+        mtime: u64,
+    },
+    Remove {
+        id: i32,
+        root: String,
+        rel: String,
+        is_dir: bool,
+    },
+    /// Answered after all the writes before.
+    Done {
+        id: i32,
+    },
+    // From the host.
+    Tree {
+        id: i32,
+        entries: Vec<Entry>,
+    },
+    BlockChecksums {
+        id: i32,
+        rel: String,
+        checksums: Vec<u64>,
+    },
+    Error {
+        id: i32,
+        rel: String,
+        error: String,
+    },
+    Finished {
+        id: i32,
+    },
+}
+
+impl SyncMessage {
+    pub fn to_message(&self, data: &[u8]) -> Message {
+        let header = serde_json::to_vec(self).unwrap_or_default();
+        let mut content = Vec::with_capacity(4 + header.len() + data.len());
+        content.extend((header.len() as u32).to_le_bytes());
+        content.extend(header);
+        content.extend(data);
+        crate::common::make_plugin_request(REQUEST_ID, content)
+    }
+
+    /// The message and the data following it.
+    pub fn decode(content: &[u8]) -> Option<(Self, &[u8])> {
+        let len = u32::from_le_bytes(content.get(..4)?.try_into().ok()?) as usize;
+        let header = content.get(4..4 + len)?;
+        let msg = serde_json::from_slice(header).ok()?;
+        Some((msg, &content[4 + len..]))
+    }
+
+    /// If it's handled by the cm, which writes the files.
+    pub fn is_write(&self) -> bool {
+        matches!(
+            self,
+            Self::MakeDir { .. }
+                | Self::Write { .. }
+                | Self::Finish { .. }
+                | Self::Remove { .. }
+                | Self::Done { .. }
+        )
+    }
+}
+
+fn mtime_of(meta: &fs::Metadata) -> u64 {
+    meta.modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// All the files and folders under `root`, the symbolic links are skipped.
+pub fn list_tree(root: &Path) -> ResultType<Vec<Entry>> {
+    let mut entries = Vec::new();
+    if !root.exists() {
+        return Ok(entries);
+    }
+    let mut dirs = vec![(root.to_path_buf(), String::new())];
+    while let Some((dir, rel)) = dirs.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let meta = entry.path().symlink_metadata()?;
+            if meta.file_type().is_symlink() {
+                continue;
+            }
+            let name = entry.file_name().to_string_lossy().to_string();
+            let rel = if rel.is_empty() {
+                name
+            } else {
+                format!("{}/{}", rel, name)
+            };
+            if meta.is_dir() {
+                dirs.push((entry.path(), rel.clone()));
+            }
+            entries.push(Entry {
+                rel,
+                is_dir: meta.is_dir(),
+                size: if meta.is_dir() { 0 } else { meta.len() },
+                mtime: mtime_of(&meta),
+            });
+        }
+    }
+    entries.sort_by(|a, b| a.rel.cmp(&b.rel));
+    Ok(entries)
+}
+
+/// The path of `rel` under `root`, which must not leave the root.
+pub fn resolve(root: &str, rel: &str) -> ResultType<PathBuf> {
+    let mut path = PathBuf::from(root);
+    for part in rel.split('/').filter(|x| !x.is_empty()) {
+        match Path::new(part).components().next() {
+            Some(Component::Normal(_)) if Path::new(part).components().count() == 1 => {
+                path.push(part)
+            }
+            _ => bail!("Invalid path {}", rel),
+        }
+    }
+    Ok(path)
+}
+
+/// The checksums of the blocks of the file, the first 8 bytes of the SHA-256.
+pub fn block_checksums(path: &Path) -> ResultType<Vec<u64>> {
+    let mut file = File::open(path)?;
+    let mut checksums = Vec::new();
+    let mut buf = vec![0u8; BLOCK_SIZE as usize];
+    loop {
+        let n = read_full(&mut file, &mut buf)?;
+        if n == 0 {
+            break;
+        }
+        let hash = Sha256::digest(&buf[..n]);
+        checksums.push(u64::from_le_bytes(hash[..8].try_into()?));
+        if n < buf.len() {
+            break;
+        }
+    }
+    Ok(checksums)
+}
+
+fn read_full(file: &mut File, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut n = 0;
+    while n < buf.len() {
+        match file.read(&mut buf[n..])? {
+            0 => break,
+            m => n += m,
+        }
+    }
+    Ok(n)
+}
+
+/// The ranges of the blocks of the local file differing from the checksums of the peer,
+/// the contiguous ones merged.
+pub fn diff_ranges(local: &[u64], peer: &[u64], size: u64) -> Vec<(u64, u64)> {
+    let mut ranges: Vec<(u64, u64)> = Vec::new();
+    for (i, checksum) in local.iter().enumerate() {
+        if peer.get(i) == Some(checksum) {
+            continue;
+        }
+        let offset = i as u64 * BLOCK_SIZE;
+        let len = BLOCK_SIZE.min(size.saturating_sub(offset));
+        match ranges.last_mut() {
+            Some((start, l)) if *start + *l == offset => *l += len,
+            _ => ranges.push((offset, len)),
+        }
+    }
+    ranges
+}
+
+/// Handle the requests reading the files on the host.
+pub fn handle_read(msg: SyncMessage) -> Option<SyncMessage> {
+    match msg {
+        SyncMessage::ListTree { id, root } => Some(match list_tree(Path::new(&root)) {
+            Ok(entries) => SyncMessage::Tree { id, entries },
+            Err(e) => SyncMessage::Error {
+                id,
+                rel: String::new(),
+                error: e.to_string(),
+            },
+        }),
+        SyncMessage::Checksums { id, root, rel } => {
+            // The file is sent entirely if it fails to read.
+            let checksums = resolve(&root, &rel)
+                .and_then(|path| block_checksums(&path))
+                .unwrap_or_default();
+            Some(SyncMessage::BlockChecksums { id, rel, checksums })
+        }
+        _ => None,
+    }
+}
+
+/// Handle the requests writing the files on the host, returns the message to send back.
+pub fn handle_write(msg: SyncMessage, data: &[u8]) -> Option<SyncMessage> {
+    let (id, rel) = match &msg {
+        SyncMessage::Done { id } => return Some(SyncMessage::Finished { id: *id }),
+        SyncMessage::MakeDir { id, rel, .. }
+        | SyncMessage::Write { id, rel, .. }
+        | SyncMessage::Finish { id, rel, .. }
+        | SyncMessage::Remove { id, rel, .. } => (*id, rel.clone()),
+        _ => return None,
+    };
+    match write(msg, data) {
+        Ok(()) => None,
+        Err(e) => {
+            log::error!("Failed to sync {}: {}", rel, e);
+            Some(SyncMessage::Error {
+                id,
+                rel,
+                error: e.to_string(),
+            })
+        }
+    }
+}
+
+fn write(msg: SyncMessage, data: &[u8]) -> ResultType<()> {
+    match msg {
+        SyncMessage::MakeDir { root, rel, .. } => fs::create_dir_all(resolve(&root, &rel)?)?,
+        SyncMessage::Write {
+            root, rel, offset, ..
+        } => {
+            let mut file = OpenOptions::new()
+                .write(true)
+                .create(true)
+                .open(resolve(&root, &rel)?)?;
+            file.seek(SeekFrom::Start(offset))?;
+            file.write_all(data)?;
+        }
+        SyncMessage::Finish {
+            root,
+            rel,
+            size,
+            mtime,
+            ..
+        } => {
+            let file = OpenOptions::new()
+                .write(true)
+                .create(true)
+                .open(resolve(&root, &rel)?)?;
+            file.set_len(size)?;
+            file.set_modified(UNIX_EPOCH + Duration::from_secs(mtime))?;
+        }
+        SyncMessage::Remove {
+            root, rel, is_dir, ..
+        } => {
+            if rel.is_empty() {
+                bail!("Can not remove the root");
+            }
+            let path = resolve(&root, &rel)?;
+            if is_dir {
+                fs::remove_dir_all(path)?;
+            } else {
+                fs::remove_file(path)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+enum Op {
+    MakeDir(String),
+    Send {
+        rel: String,
+        ranges: VecDeque<(u64, u64)>,
+        size: u64,
+        mtime: u64,
+    },
+    Remove {
+        rel: String,
+        is_dir: bool,
+    },
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum State {
+    Listing,
+    Syncing,
+    Finishing,
+    Idle(Instant),
+    Stopped,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Status {
+    pub id: i32,
+    /// "syncing", "done" or "error".
+    pub state: &'static str,
+    pub files: usize,
+    pub bytes: u64,
+    pub errors: Vec<String>,
+    /// Seconds to the next run, 0 if it does not run again.
+    pub next_run: u64,
+}
+
+struct SyncJob {
+    id: i32,
+    local: PathBuf,
+    remote: String,
+    delete: bool,
+    interval: Option<Duration>,
+    state: State,
+    // The local files waiting for the checksums of the peer.
+    waiting: HashMap<String, Entry>,
+    ops: VecDeque<Op>,
+    files: usize,
+    bytes: u64,
+    errors: Vec<String>,
+}
+
+impl SyncJob {
+    fn start(&mut self) -> Message {
+        self.state = State::Listing;
+        self.waiting.clear();
+        self.ops.clear();
+        self.files = 0;
+        self.bytes = 0;
+        self.errors.clear();
+        SyncMessage::ListTree {
+            id: self.id,
+            root: self.remote.clone(),
+        }
+        .to_message(&[])
+    }
+
+    fn status(&self) -> Status {
+        let (state, next_run) = match self.state {
+            State::Idle(next) => (
+                if self.errors.is_empty() {
+                    "done"
+                } else {
+                    "error"
+                },
+                next.saturating_duration_since(Instant::now()).as_secs(),
+            ),
+            State::Stopped if self.errors.is_empty() => ("done", 0),
+            State::Stopped => ("error", 0),
+            _ => ("syncing", 0),
+        };
+        Status {
+            id: self.id,
+            state,
+            files: self.files,
+            bytes: self.bytes,
+            errors: self.errors.clone(),
+            next_run,
+        }
+    }
+
+    // Compare the trees, queue the operations and request the checksums of the files changed.
+    fn on_tree(&mut self, remote: Vec<Entry>) -> Vec<Message> {
+        let local = match list_tree(&self.local) {
+            Ok(local) => local,
+            Err(e) => {
+                self.errors.push(format!("{}: {}", self.local.display(), e));
+                self.finish();
+                return vec![];
+            }
+        };
+        let remote: HashMap<String, Entry> =
+            remote.into_iter().map(|e| (e.rel.clone(), e)).collect();
+        let mut msgs = vec![];
+        self.state = State::Syncing;
+        self.ops.push_back(Op::MakeDir(String::new()));
+        for entry in local.iter() {
+            let peer = remote.get(&entry.rel);
+            if let Some(peer) = peer {
+                if peer.is_dir != entry.is_dir {
+                    self.ops.push_back(Op::Remove {
+                        rel: peer.rel.clone(),
+                        is_dir: peer.is_dir,
+                    });
+                }
+            }
+            let peer = peer.filter(|p| p.is_dir == entry.is_dir);
+            if entry.is_dir {
+                if peer.is_none() {
+                    self.ops.push_back(Op::MakeDir(entry.rel.clone()));
+                }
+                continue;
+            }
+            match peer {
+                Some(peer) if peer.size == entry.size && peer.mtime == entry.mtime => {}
+                Some(_) => {
+                    self.waiting.insert(entry.rel.clone(), entry.clone());
+                    msgs.push(
+                        SyncMessage::Checksums {
+                            id: self.id,
+                            root: self.remote.clone(),
+                            rel: entry.rel.clone(),
+                        }
+                        .to_message(&[]),
+                    );
+                }
+                None => self.push_send(entry, vec![(0, entry.size)]),
+            }
+        }
+        if self.delete {
+            let local: HashMap<&str, bool> =
+                local.iter().map(|e| (e.rel.as_str(), e.is_dir)).collect();
+            let mut removed: Vec<String> = vec![];
+            let mut extra: Vec<&Entry> = remote
+                .values()
+                .filter(|e| local.get(e.rel.as_str()).is_none())
+                .collect();
+            extra.sort_by(|a, b| a.rel.cmp(&b.rel));
+            for entry in extra {
+                if removed
+                    .iter()
+                    .any(|dir| entry.rel.starts_with(&format!("{}/", dir)))
+                {
+                    continue;
+                }
+                if entry.is_dir {
+                    removed.push(entry.rel.clone());
+                }
+                self.ops.push_back(Op::Remove {
+                    rel: entry.rel.clone(),
+                    is_dir: entry.is_dir,
+                });
+            }
+        }
+        msgs
+    }
+
+    fn on_checksums(&mut self, rel: String, checksums: Vec<u64>) {
+        let Some(entry) = self.waiting.remove(&rel) else {
+            return;
+        };
+        let path = match resolve(&self.local.to_string_lossy(), &rel) {
+            Ok(path) => path,
+            Err(e) => {
+                self.errors.push(format!("{}: {}", rel, e));
+                return;
+            }
+        };
+        match block_checksums(&path) {
+            Ok(local) => self.push_send(&entry, diff_ranges(&local, &checksums, entry.size)),
+            Err(e) => self.errors.push(format!("{}: {}", rel, e)),
+        }
+    }
+
+    fn push_send(&mut self, entry: &Entry, ranges: Vec<(u64, u64)>) {
+        self.files += 1;
+        self.ops.push_back(Op::Send {
+            rel: entry.rel.clone(),
+            ranges: ranges.into_iter().filter(|(_, len)| *len > 0).collect(),
+            size: entry.size,
+            mtime: entry.mtime,
+        });
+    }
+
+    fn is_sending(&self) -> bool {
+        self.state == State::Syncing
+    }
+
+    // Send the queued operations up to `budget` bytes of data.
+    fn tick(&mut self, budget: &mut u64) -> Vec<Message> {
+        let mut msgs = vec![];
+        match self.state {
+            State::Idle(next) if Instant::now() >= next => {
+                msgs.push(self.start());
+                return msgs;
+            }
+            State::Syncing => {}
+            _ => return msgs,
+        }
+        let (id, root) = (self.id, self.remote.clone());
+        while *budget > 0 {
+            let Some(op) = self.ops.front_mut() else {
+                break;
+            };
+            match op {
+                Op::MakeDir(rel) => {
+                    let rel = rel.clone();
+                    msgs.push(
+                        SyncMessage::MakeDir {
+                            id,
+                            root: root.clone(),
+                            rel,
+                        }
+                        .to_message(&[]),
+                    );
+                }
+                Op::Remove { rel, is_dir } => {
+                    msgs.push(
+                        SyncMessage::Remove {
+                            id,
+                            root: root.clone(),
+                            rel: rel.clone(),
+                            is_dir: *is_dir,
+                        }
+                        .to_message(&[]),
+                    );
+                }
+                Op::Send {
+                    rel,
+                    ranges,
+                    size,
+                    mtime,
+                } => {
+                    if let Some((offset, len)) = ranges.front_mut() {
+                        let n = (*len).min(MAX_WRITE_SIZE).min(*budget);
+                        let data = resolve(&self.local.to_string_lossy(), rel)
+                            .and_then(|path| read_at(&path, *offset, n));
+                        match data {
+                            Ok(data) => {
+                                msgs.push(
+                                    SyncMessage::Write {
+                                        id,
+                                        root: root.clone(),
+                                        rel: rel.clone(),
+                                        offset: *offset,
+                                    }
+                                    .to_message(&data),
+                                );
+                                self.bytes += n;
+                                *budget = budget.saturating_sub(n);
+                                *offset += n;
+                                *len -= n;
+                                if *len == 0 {
+                                    ranges.pop_front();
+                                }
+                            }
+                            Err(e) => {
+                                self.errors.push(format!("{}: {}", rel, e));
+                                self.ops.pop_front();
+                            }
+                        }
+                        continue;
+                    }
+                    msgs.push(
+                        SyncMessage::Finish {
+                            id,
+                            root: root.clone(),
+                            rel: rel.clone(),
+                            size: *size,
+                            mtime: *mtime,
+                        }
+                        .to_message(&[]),
+                    );
+                }
+            }
+            self.ops.pop_front();
+        }
+        if self.ops.is_empty() && self.waiting.is_empty() {
+            self.state = State::Finishing;
+            msgs.push(SyncMessage::Done { id }.to_message(&[]));
+        }
+        msgs
+    }
+
+    fn finish(&mut self) {
+        self.state = match self.interval {
+            Some(interval) => State::Idle(Instant::now() + interval),
+            None => State::Stopped,
+        };
+        log::info!(
+            "Folder sync {} done, {} files, {} bytes, {} errors",
+            self.id,
+            self.files,
+            self.bytes,
+            self.errors.len()
+        );
+    }
+}
+
+fn read_at(path: &Path, offset: u64, len: u64) -> ResultType<Vec<u8>> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(offset))?;
+    let mut data = vec![0u8; len as usize];
+    let n = read_full(&mut file, &mut data)?;
+    data.truncate(n);
+    Ok(data)
+}
+
+/// The sync jobs of a file transfer session on the client.
+#[derive(Default)]
+pub struct FolderSyncs {
+    jobs: HashMap<i32, SyncJob>,
+}
+
+/// The messages to send to the peer and the status updates of the jobs.
+#[derive(Default)]
+pub struct Output {
+    pub messages: Vec<Message>,
+    pub statuses: Vec<Status>,
+}
+
+impl FolderSyncs {
+    /// Mirror `local` to `remote` on the peer, again every `interval_mins` if not 0.
+    /// `delete` removes the files on the peer which are not in `local`.
+    pub fn start(
+        &mut self,
+        id: i32,
+        local: String,
+        remote: String,
+        interval_mins: u32,
+        delete: bool,
+    ) -> Message {
+        log::info!(
+            "Start folder sync {} from {} to {}, interval {} mins",
+            id,
+            local,
+            remote,
+            interval_mins
+        );
+        let mut job = SyncJob {
+            id,
+            local: PathBuf::from(local),
+            remote,
+            delete,
+            interval: (interval_mins > 0).then_some(Duration::from_secs(interval_mins as u64 * 60)),
+            state: State::Stopped,
+            waiting: Default::default(),
+            ops: Default::default(),
+            files: 0,
+            bytes: 0,
+            errors: vec![],
+        };
+        let msg = job.start();
+        self.jobs.insert(id, job);
+        msg
+    }
+
+    pub fn stop(&mut self, id: i32) {
+        if self.jobs.remove(&id).is_some() {
+            log::info!("Stop folder sync {}", id);
+        }
+    }
+
+    pub fn is_sending(&self) -> bool {
+        self.jobs.values().any(|job| job.is_sending())
+    }
+
+    pub fn handle(&mut self, content: &[u8]) -> Output {
+        let mut output = Output::default();
+        let Some((msg, _)) = SyncMessage::decode(content) else {
+            log::error!("Invalid folder sync message");
+            return output;
+        };
+        let id = match &msg {
+            SyncMessage::Tree { id, .. }
+            | SyncMessage::BlockChecksums { id, .. }
+            | SyncMessage::Error { id, .. }
+            | SyncMessage::Finished { id } => *id,
+            _ => return output,
+        };
+        let Some(job) = self.jobs.get_mut(&id) else {
+            return output;
+        };
+        match msg {
+            SyncMessage::Tree { entries, .. } if job.state == State::Listing => {
+                output.messages = job.on_tree(entries);
+            }
+            SyncMessage::BlockChecksums { rel, checksums, .. } => job.on_checksums(rel, checksums),
+            SyncMessage::Error { rel, error, .. } => {
+                job.errors.push(format!("{}: {}", rel, error));
+                // The tree of the peer is not read.
+                if job.state == State::Listing {
+                    job.finish();
+                }
+            }
+            SyncMessage::Finished { .. } if job.state == State::Finishing => job.finish(),
+            _ => {}
+        }
+        output.statuses.push(job.status());
+        output
+    }
+
+    pub fn tick(&mut self) -> Output {
+        let mut output = Output::default();
+        let mut budget = MAX_TICK_SIZE;
+        for job in self.jobs.values_mut() {
+            let restarted = matches!(job.state, State::Idle(next) if Instant::now() >= next);
+            let syncing = job.state == State::Syncing;
+            output.messages.extend(job.tick(&mut budget));
+            if restarted || (syncing && job.state == State::Finishing) {
+                output.statuses.push(job.status());
+            }
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        assert_eq!(
+            resolve("/a", "b/c").unwrap(),
+            Path::new("/a").join("b").join("c")
+        );
+        assert_eq!(resolve("/a", "").unwrap(), PathBuf::from("/a"));
+        assert!(resolve("/a", "b/../../c").is_err());
+        assert!(resolve("/a", "/etc").is_ok_and(|p| p == Path::new("/a").join("etc")));
+        assert!(resolve("/a", "..").is_err());
+    }
+
+    #[test]
+    fn test_diff_ranges() {
+        let size = BLOCK_SIZE * 4 + 10;
+        assert_eq!(
+            diff_ranges(&[1, 2, 3, 4, 5], &[1, 2, 3, 4, 5], size),
+            vec![]
+        );
+        assert_eq!(
+            diff_ranges(&[1, 2, 3, 4, 5], &[1, 0, 0, 4], size),
+            vec![(BLOCK_SIZE, BLOCK_SIZE * 2), (BLOCK_SIZE * 4, 10)]
+        );
+    }
+
+    #[test]
+    fn test_message_codec() {
+        let msg = SyncMessage::Write {
+            id: 1,
+            root: "/a".to_owned(),
+            rel: "b".to_owned(),
+            offset: 3,
+        };
+        let out = msg.to_message(&[1, 2, 3]);
+        let content = out.misc().plugin_request().content.to_vec();
+        let (decoded, data) = SyncMessage::decode(&content).unwrap();
+        assert!(matches!(decoded, SyncMessage::Write { offset: 3, .. }));
+        assert_eq!(data, &[1, 2, 3]);
+        assert!(SyncMessage::decode(&content[..5]).is_none());
+    }
+}
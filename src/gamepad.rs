@@ -0,0 +1,385 @@
+//! Forwarding of the game controllers of the client to the host.
+//!
+//! The client sends the state of each controller, in the layout of XInput, with plugin requests
+//! of the reserved id [`REQUEST_ID`]. The host injects it into a virtual Xbox 360 controller,
+//! with ViGEm on Windows and uinput on Linux, which is unplugged when the connection closes.
+use serde_derive::{Deserialize, Serialize};
+
+pub const REQUEST_ID: &str = crate::common::plugin_request_id::GAMEPAD;
+/// The max number of the controllers of a connection, as XInput.
+pub const MAX_GAMEPADS: u8 = 4;
+// index, connected, buttons, triggers, thumbs.
+const EVENT_LEN: usize = 1 + 1 + 2 + 2 + 8;
+
+/// The bits of [`GamepadState::buttons`], the same as `XINPUT_GAMEPAD`.
+pub mod buttons {
+    pub const DPAD_UP: u16 = 0x0001;
+    pub const DPAD_DOWN: u16 = 0x0002;
+    pub const DPAD_LEFT: u16 = 0x0004;
+    pub const DPAD_RIGHT: u16 = 0x0008;
+    pub const START: u16 = 0x0010;
+    pub const BACK: u16 = 0x0020;
+    pub const LEFT_THUMB: u16 = 0x0040;
+    pub const RIGHT_THUMB: u16 = 0x0080;
+    pub const LEFT_SHOULDER: u16 = 0x0100;
+    pub const RIGHT_SHOULDER: u16 = 0x0200;
+    pub const GUIDE: u16 = 0x0400;
+    pub const A: u16 = 0x1000;
+    pub const B: u16 = 0x2000;
+    pub const X: u16 = 0x4000;
+    pub const Y: u16 = 0x8000;
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GamepadState {
+    pub buttons: u16,
+    pub left_trigger: u8,
+    pub right_trigger: u8,
+    // Up is positive.
+    pub thumb_lx: i16,
+    pub thumb_ly: i16,
+    pub thumb_rx: i16,
+    pub thumb_ry: i16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GamepadEvent {
+    pub index: u8,
+    /// `None` if the controller is disconnected.
+    pub state: Option<GamepadState>,
+}
+
+impl GamepadEvent {
+    pub fn encode(&self) -> Vec<u8> {
+        let state = self.state.unwrap_or_default();
+        let mut data = Vec::with_capacity(EVENT_LEN);
+        data.push(self.index);
+        data.push(self.state.is_some() as u8);
+        data.extend(state.buttons.to_le_bytes());
+        data.push(state.left_trigger);
+        data.push(state.right_trigger);
+        for v in [
+            state.thumb_lx,
+            state.thumb_ly,
+            state.thumb_rx,
+            state.thumb_ry,
+        ] {
+            data.extend(v.to_le_bytes());
+        }
+        data
+    }
+
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        if data.len() != EVENT_LEN || data[0] >= MAX_GAMEPADS {
+            return None;
+        }
+        let i16_at = |i: usize| i16::from_le_bytes([data[i], data[i + 1]]);
+        let state = match data[1] {
+            0 => None,
+            1 => Some(GamepadState {
+                buttons: u16::from_le_bytes([data[2], data[3]]),
+                left_trigger: data[4],
+                right_trigger: data[5],
+                thumb_lx: i16_at(6),
+                thumb_ly: i16_at(8),
+                thumb_rx: i16_at(10),
+                thumb_ry: i16_at(12),
+            }),
+            _ => return None,
+        };
+        Some(Self {
+            index: data[0],
+            state,
+        })
+    }
+}
+
+/// The virtual controllers of a connection on the host, unplugged when dropped.
+#[cfg(any(windows, target_os = "linux"))]
+#[derive(Default)]
+pub struct VirtualGamepads {
+    #[cfg(windows)]
+    pads: std::collections::HashMap<u8, vigem_client::Xbox360Wired<vigem_client::Client>>,
+    #[cfg(target_os = "linux")]
+    uinput: Option<crate::uinput::client::UInputGamepad>,
+    // Do not retry after the driver or the service is unavailable.
+    failed: bool,
+}
+
+#[cfg(any(windows, target_os = "linux"))]
+impl VirtualGamepads {
+    pub fn handle(&mut self, event: GamepadEvent) {
+        if self.failed {
+            return;
+        }
+        if let Err(e) = self.handle_(event) {
+            hbb_common::log::error!("Failed to inject the gamepad event {:?}: {}", event, e);
+            self.failed = true;
+        }
+    }
+
+    #[cfg(windows)]
+    fn handle_(&mut self, event: GamepadEvent) -> hbb_common::ResultType<()> {
+        use vigem_client::{Client, TargetId, XButtons, XGamepad, Xbox360Wired};
+
+        let Some(state) = event.state else {
+            // Unplugged when dropped.
+            self.pads.remove(&event.index);
+            return Ok(());
+        };
+        if !self.pads.contains_key(&event.index) {
+            let mut pad = Xbox360Wired::new(Client::connect()?, TargetId::XBOX360_WIRED);
+            pad.plugin()?;
+            pad.wait_ready()?;
+            hbb_common::log::info!("Virtual gamepad {} plugged in", event.index);
+            self.pads.insert(event.index, pad);
+        }
+        if let Some(pad) = self.pads.get_mut(&event.index) {
+            pad.update(&XGamepad {
+                buttons: XButtons { raw: state.buttons },
+                left_trigger: state.left_trigger,
+                right_trigger: state.right_trigger,
+                thumb_lx: state.thumb_lx,
+                thumb_ly: state.thumb_ly,
+                thumb_rx: state.thumb_rx,
+                thumb_ry: state.thumb_ry,
+            })?;
+        }
+        Ok(())
+    }
+
+    // The uinput devices are created by the service running as root.
+    #[cfg(target_os = "linux")]
+    fn handle_(&mut self, event: GamepadEvent) -> hbb_common::ResultType<()> {
+        if self.uinput.is_none() {
+            self.uinput = Some(crate::uinput::client::UInputGamepad::new()?);
+        }
+        if let Some(uinput) = self.uinput.as_mut() {
+            uinput.send(event)?;
+        }
+        Ok(())
+    }
+}
+
+/// The controllers connected to the client, forwarded to the sessions enabling the forwarding.
+#[cfg(feature = "gamepad")]
+pub mod local {
+    use super::*;
+    use gilrs::{Axis, Button, Gamepad, GamepadId, Gilrs};
+    use hbb_common::{lazy_static, log};
+    use std::{
+        collections::HashMap,
+        sync::{Mutex, Once},
+        time::Duration,
+    };
+
+    pub type Forwarder = Box<dyn Fn(&GamepadEvent) + Send>;
+
+    const POLL_TIMEOUT: Duration = Duration::from_millis(500);
+
+    lazy_static::lazy_static! {
+        static ref FORWARDERS: Mutex<HashMap<u64, Forwarder>> = Default::default();
+        // index -> (name, state)
+        static ref GAMEPADS: Mutex<HashMap<u8, (String, GamepadState)>> = Default::default();
+    }
+    static START: Once = Once::new();
+
+    /// The index and name of the controllers connected.
+    pub fn get_gamepads() -> Vec<(u8, String)> {
+        start();
+        let mut v: Vec<_> = GAMEPADS
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(i, (name, _))| (*i, name.clone()))
+            .collect();
+        v.sort_by_key(|x| x.0);
+        v
+    }
+
+    /// Forward the controllers to `forwarder` of the session `key`, `None` to stop forwarding.
+    ///
+    /// The connected controllers are sent at once when it starts,
+    /// and sent as disconnected when it stops.
+    pub fn set_forwarder(key: u64, forwarder: Option<Forwarder>) {
+        if forwarder.is_some() {
+            start();
+        }
+        let gamepads = GAMEPADS.lock().unwrap();
+        let mut forwarders = FORWARDERS.lock().unwrap();
+        match forwarder {
+            Some(f) => {
+                for (index, (_, state)) in gamepads.iter() {
+                    f(&GamepadEvent {
+                        index: *index,
+                        state: Some(*state),
+                    });
+                }
+                forwarders.insert(key, f);
+            }
+            None => {
+                if let Some(f) = forwarders.remove(&key) {
+                    for index in gamepads.keys() {
+                        f(&GamepadEvent {
+                            index: *index,
+                            state: None,
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    fn start() {
+        START.call_once(|| {
+            std::thread::spawn(run);
+        });
+    }
+
+    fn forward(event: GamepadEvent) {
+        for f in FORWARDERS.lock().unwrap().values() {
+            f(&event);
+        }
+    }
+
+    // `Gilrs` is not `Send` on all platforms, it lives in this thread.
+    fn run() {
+        let mut gilrs = match Gilrs::new() {
+            Ok(gilrs) => gilrs,
+            Err(e) => {
+                log::error!("Failed to initialize the gamepads: {}", e);
+                return;
+            }
+        };
+        let mut indexes: HashMap<GamepadId, u8> = HashMap::new();
+        let ids: Vec<_> = gilrs.gamepads().map(|(id, _)| id).collect();
+        for id in ids {
+            connect(&gilrs, &mut indexes, id);
+        }
+        loop {
+            let Some(event) = gilrs.next_event_blocking(Some(POLL_TIMEOUT)) else {
+                continue;
+            };
+            match event.event {
+                gilrs::EventType::Connected => connect(&gilrs, &mut indexes, event.id),
+                gilrs::EventType::Disconnected => {
+                    if let Some(index) = indexes.remove(&event.id) {
+                        log::info!("Gamepad {} disconnected", index);
+                        GAMEPADS.lock().unwrap().remove(&index);
+                        forward(GamepadEvent { index, state: None });
+                    }
+                }
+                _ => {
+                    let Some(index) = indexes.get(&event.id).cloned() else {
+                        continue;
+                    };
+                    let state = get_state(&gilrs.gamepad(event.id));
+                    let changed = match GAMEPADS.lock().unwrap().get_mut(&index) {
+                        Some((_, s)) if *s != state => {
+                            *s = state;
+                            true
+                        }
+                        _ => false,
+                    };
+                    if changed {
+                        forward(GamepadEvent {
+                            index,
+                            state: Some(state),
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    fn connect(gilrs: &Gilrs, indexes: &mut HashMap<GamepadId, u8>, id: GamepadId) {
+        if indexes.contains_key(&id) {
+            return;
+        }
+        let Some(index) = (0..MAX_GAMEPADS).find(|i| !indexes.values().any(|x| x == i)) else {
+            log::warn!("Ignore the gamepad, at most {} are supported", MAX_GAMEPADS);
+            return;
+        };
+        let gamepad = gilrs.gamepad(id);
+        let state = get_state(&gamepad);
+        log::info!("Gamepad {} connected: {}", index, gamepad.name());
+        indexes.insert(id, index);
+        GAMEPADS
+            .lock()
+            .unwrap()
+            .insert(index, (gamepad.name().to_owned(), state));
+        forward(GamepadEvent {
+            index,
+            state: Some(state),
+        });
+    }
+
+    fn get_state(gamepad: &Gamepad) -> GamepadState {
+        const BUTTONS: [(Button, u16); 15] = [
+            (Button::DPadUp, buttons::DPAD_UP),
+            (Button::DPadDown, buttons::DPAD_DOWN),
+            (Button::DPadLeft, buttons::DPAD_LEFT),
+            (Button::DPadRight, buttons::DPAD_RIGHT),
+            (Button::Start, buttons::START),
+            (Button::Select, buttons::BACK),
+            (Button::LeftThumb, buttons::LEFT_THUMB),
+            (Button::RightThumb, buttons::RIGHT_THUMB),
+            (Button::LeftTrigger, buttons::LEFT_SHOULDER),
+            (Button::RightTrigger, buttons::RIGHT_SHOULDER),
+            (Button::Mode, buttons::GUIDE),
+            (Button::South, buttons::A),
+            (Button::East, buttons::B),
+            (Button::West, buttons::X),
+            (Button::North, buttons::Y),
+        ];
+        let mut state = GamepadState::default();
+        for (button, bit) in BUTTONS {
+            if gamepad.is_pressed(button) {
+                state.buttons |= bit;
+            }
+        }
+        let trigger = |button| {
+            let v = gamepad.button_data(button).map(|d| d.value()).unwrap_or(0.);
+            (v.clamp(0., 1.) * u8::MAX as f32) as u8
+        };
+        state.left_trigger = trigger(Button::LeftTrigger2);
+        state.right_trigger = trigger(Button::RightTrigger2);
+        let thumb = |axis| (gamepad.value(axis).clamp(-1., 1.) * i16::MAX as f32) as i16;
+        state.thumb_lx = thumb(Axis::LeftStickX);
+        state.thumb_ly = thumb(Axis::LeftStickY);
+        state.thumb_rx = thumb(Axis::RightStickX);
+        state.thumb_ry = thumb(Axis::RightStickY);
+        state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gamepad_event_codec() {
+        let event = GamepadEvent {
+            index: 3,
+            state: Some(GamepadState {
+                buttons: buttons::A | buttons::DPAD_LEFT,
+                left_trigger: 255,
+                right_trigger: 1,
+                thumb_lx: i16::MIN,
+                thumb_ly: i16::MAX,
+                thumb_rx: -1,
+                thumb_ry: 0,
+            }),
+        };
+        assert_eq!(GamepadEvent::decode(&event.encode()), Some(event));
+        let unplugged = GamepadEvent {
+            index: 0,
+            state: None,
+        };
+        assert_eq!(GamepadEvent::decode(&unplugged.encode()), Some(unplugged));
+        let mut data = event.encode();
+        data[0] = MAX_GAMEPADS;
+        assert_eq!(GamepadEvent::decode(&data), None);
+        assert_eq!(GamepadEvent::decode(&data[1..]), None);
+    }
+}
@@ -4,9 +4,11 @@ use serde_json::{Map, Value};
 
 #[cfg(feature = "flutter")]
 pub mod account;
+pub mod cert_pinning;
 pub mod downloader;
 mod http_client;
 pub mod record_upload;
+pub mod s3_upload;
 pub mod sync;
 pub use http_client::{
     create_http_client_async, create_http_client_async_with_url, create_http_client_with_url,
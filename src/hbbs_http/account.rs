@@ -147,8 +147,10 @@ impl OidcSession {
         if write_guard.client.is_none() {
             // This URL is used to detect the appropriate TLS implementation for the server.
             let login_option_url = format!("{}/api/login-options", &api_server);
-            let client = create_http_client_with_url(&login_option_url);
-            write_guard.client = Some(client);
+            match create_http_client_with_url(&login_option_url) {
+                Ok(client) => write_guard.client = Some(client),
+                Err(e) => log::error!("Failed to create the http client: {}", e),
+            }
         }
     }
 
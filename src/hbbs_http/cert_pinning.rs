@@ -0,0 +1,238 @@
+//! Pinning of the server certificates of the web API, for the deployments that can't rely on
+//! the public CA store, e.g. with a self-signed certificate or a private CA.
+//!
+//! The option [`OPTION_PINS`] is a JSON map of the hosts to their pins, `*` for the other hosts:
+//! `{"api.example.com": ["sha256/<base64>"], "*": ["cert-sha256/<base64>"]}`.
+//! - `sha256/<base64>`: the SHA-256 of the SubjectPublicKeyInfo of the certificate,
+//!   which survives the renewals with the same key.
+//! - `cert-sha256/<base64>`: the SHA-256 of the whole certificate.
+//!
+//! The connections to a pinned host accept only the server certificates matching one of its pins,
+//! instead of the ones of the CA store, and reject the substituted ones even if they are
+//! signed by a trusted CA. Only the certificate of the server itself is pinned,
+//! not the ones of its CAs, because the chain is not verified.
+//!
+//! The pinning fails closed: a pinned host without a valid pin, or an invalid option, rejects
+//! every certificate instead of falling back to the CA store, and the requests to a pinned host
+//! fail if its client can't be created.
+//!
+//! Not pinned yet: only the HTTP requests of the web API use the pins. The WebSocket/TLS
+//! connections to the rendezvous and relay servers are made by `hbb_common` with the CA store,
+//! a warning is logged if they are enabled with the pins.
+use hbb_common::{
+    base64::{engine::general_purpose::STANDARD, Engine as _},
+    config::Config,
+    log, ResultType,
+};
+use rustls::{
+    client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+    crypto::{verify_tls12_signature, verify_tls13_signature, CryptoProvider},
+    pki_types::{CertificateDer, ServerName, UnixTime},
+    DigitallySignedStruct, SignatureScheme,
+};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Once},
+};
+
+pub const OPTION_PINS: &str = "tls-pins";
+const PREFIX_SPKI: &str = "sha256/";
+const PREFIX_CERT: &str = "cert-sha256/";
+const ANY_HOST: &str = "*";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pin {
+    Spki(Vec<u8>),
+    Cert(Vec<u8>),
+}
+
+impl Pin {
+    fn parse(s: &str) -> Option<Self> {
+        let s = s.trim();
+        if let Some(hash) = s.strip_prefix(PREFIX_CERT) {
+            Some(Self::Cert(STANDARD.decode(hash).ok()?))
+        } else if let Some(hash) = s.strip_prefix(PREFIX_SPKI) {
+            Some(Self::Spki(STANDARD.decode(hash).ok()?))
+        } else {
+            None
+        }
+    }
+
+    fn matches(&self, der: &[u8]) -> bool {
+        match self {
+            Self::Cert(hash) => Sha256::digest(der)[..] == hash[..],
+            Self::Spki(hash) => match x509_parser::parse_x509_certificate(der) {
+                Ok((_, cert)) => {
+                    Sha256::digest(cert.tbs_certificate.subject_pki.raw)[..] == hash[..]
+                }
+                Err(e) => {
+                    log::warn!("Failed to parse the server certificate: {}", e);
+                    false
+                }
+            },
+        }
+    }
+}
+
+/// The pins of the host of `url`, `None` if it's not pinned.
+///
+/// The pins are empty if the host is pinned without a valid one, no certificate is accepted then.
+pub fn get_pins(url: &str) -> Option<Vec<Pin>> {
+    let raw = Config::get_option(OPTION_PINS);
+    if raw.is_empty() {
+        return None;
+    }
+    warn_unpinned_websocket();
+    let Some(host) = url::Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(|h| h.to_owned()))
+    else {
+        log::error!("No host in the pinned url {}", url);
+        return Some(vec![]);
+    };
+    parse_pins(&raw, &host)
+}
+
+fn warn_unpinned_websocket() {
+    static WARN: Once = Once::new();
+    if hbb_common::config::use_ws() {
+        WARN.call_once(|| {
+            log::warn!("The WebSocket connections to the servers don't use the pins");
+        });
+    }
+}
+
+fn parse_pins(raw: &str, host: &str) -> Option<Vec<Pin>> {
+    let map: HashMap<String, Vec<String>> = match serde_json::from_str(raw) {
+        Ok(map) => map,
+        Err(e) => {
+            log::error!("Invalid {}: {}", OPTION_PINS, e);
+            return Some(vec![]);
+        }
+    };
+    let pins = map.get(host).or_else(|| map.get(ANY_HOST))?;
+    let pins: Vec<Pin> = pins
+        .iter()
+        .filter_map(|s| {
+            let pin = Pin::parse(s);
+            if pin.is_none() {
+                log::error!("Invalid pin of {}: {}", host, s);
+            }
+            pin
+        })
+        .collect();
+    if pins.is_empty() {
+        log::error!("No valid pin of {}, its certificates are rejected", host);
+    }
+    Some(pins)
+}
+
+/// The TLS config of the connections to a host with `pins`.
+pub fn client_config(pins: Vec<Pin>) -> ResultType<rustls::ClientConfig> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    Ok(
+        rustls::ClientConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()?
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(PinVerifier { pins, provider }))
+            .with_no_client_auth(),
+    )
+}
+
+#[derive(Debug)]
+struct PinVerifier {
+    pins: Vec<Pin>,
+    provider: Arc<CryptoProvider>,
+}
+
+impl ServerCertVerifier for PinVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        if self.pins.iter().any(|p| p.matches(end_entity.as_ref())) {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            log::error!(
+                "The certificate of {} doesn't match the pins",
+                server_name.to_str()
+            );
+            Err(rustls::Error::General(
+                "The server certificate doesn't match the pins".to_owned(),
+            ))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pins() {
+        let cert = STANDARD.encode([1u8; 32]);
+        let raw = format!(
+            r#"{{"api.example.com": ["cert-sha256/{cert}", "md5/abc"], "*": ["sha256/{cert}"]}}"#
+        );
+        assert_eq!(
+            parse_pins(&raw, "api.example.com"),
+            Some(vec![Pin::Cert(vec![1u8; 32])])
+        );
+        assert_eq!(
+            parse_pins(&raw, "other.example.com"),
+            Some(vec![Pin::Spki(vec![1u8; 32])])
+        );
+        assert_eq!(
+            parse_pins(r#"{"api.example.com": []}"#, "other.example.com"),
+            None
+        );
+        // Fail closed.
+        assert_eq!(
+            parse_pins(r#"{"api.example.com": ["md5/abc"]}"#, "api.example.com"),
+            Some(vec![])
+        );
+        assert_eq!(parse_pins("{", "api.example.com"), Some(vec![]));
+        let der = b"not a certificate";
+        assert!(Pin::Cert(Sha256::digest(der).to_vec()).matches(der));
+        assert!(!Pin::Spki(Sha256::digest(der).to_vec()).matches(der));
+    }
+}
@@ -1,5 +1,6 @@
 use hbb_common::{
     async_recursion::async_recursion,
+    bail,
     config::{Config, Socks5Server},
     log::{self, info},
     proxy::{Proxy, ProxyScheme},
@@ -7,44 +8,71 @@ use hbb_common::{
         get_cached_tls_accept_invalid_cert, get_cached_tls_type, is_plain, upsert_tls_cache,
         TlsType,
     },
+    ResultType,
 };
 use reqwest::{blocking::Client as SyncClient, Client as AsyncClient};
 
 macro_rules! configure_http_client {
-    ($builder:expr, $tls_type:expr, $danger_accept_invalid_cert:expr, $Client: ty) => {{
+    ($builder:expr, $url:expr, $tls_type:expr, $danger_accept_invalid_cert:expr, $Client: ty) => {{
         // https://github.com/rustdesk/rustdesk/issues/11569
         // https://docs.rs/reqwest/latest/reqwest/struct.ClientBuilder.html#method.no_proxy
         let mut builder = $builder.no_proxy();
 
-        match $tls_type {
-            TlsType::Plain => {}
-            TlsType::NativeTls => {
-                builder = builder.use_native_tls();
-                if $danger_accept_invalid_cert {
-                    builder = builder.danger_accept_invalid_certs(true);
+        let pins = super::cert_pinning::get_pins($url);
+        let pinned = pins.is_some();
+        let mut rejected = false;
+        if let Some(pins) = pins {
+            // The pins replace the CA store whatever the TLS type is.
+            match super::cert_pinning::client_config(pins) {
+                Ok(client_config) => {
+                    builder = builder.use_preconfigured_tls(client_config);
                 }
-            }
-            TlsType::Rustls => {
-                #[cfg(any(target_os = "android", target_os = "ios"))]
-                match hbb_common::verifier::client_config($danger_accept_invalid_cert) {
-                    Ok(client_config) => {
-                        builder = builder.use_preconfigured_tls(client_config);
-                    }
-                    Err(e) => {
-                        hbb_common::log::error!("Failed to get client config: {}", e);
-                    }
+                Err(e) => {
+                    hbb_common::log::error!("Failed to get the pinned client config: {}", e);
+                    rejected = true;
                 }
-                #[cfg(not(any(target_os = "android", target_os = "ios")))]
-                {
-                    builder = builder.use_rustls_tls();
+            }
+        } else {
+            match $tls_type {
+                TlsType::Plain => {}
+                TlsType::NativeTls => {
+                    builder = builder.use_native_tls();
                     if $danger_accept_invalid_cert {
                         builder = builder.danger_accept_invalid_certs(true);
                     }
                 }
+                TlsType::Rustls => {
+                    #[cfg(any(target_os = "android", target_os = "ios"))]
+                    match hbb_common::verifier::client_config($danger_accept_invalid_cert) {
+                        Ok(client_config) => {
+                            builder = builder.use_preconfigured_tls(client_config);
+                        }
+                        Err(e) => {
+                            hbb_common::log::error!("Failed to get client config: {}", e);
+                        }
+                    }
+                    #[cfg(not(any(target_os = "android", target_os = "ios")))]
+                    {
+                        builder = builder.use_rustls_tls();
+                        if $danger_accept_invalid_cert {
+                            builder = builder.danger_accept_invalid_certs(true);
+                        }
+                    }
+                }
             }
         }
 
-        let client = if let Some(conf) = Config::get_socks() {
+        // The fallback client trusts the CA store, a pinned host has none.
+        let new_client = || -> ResultType<$Client> {
+            if pinned {
+                bail!("Failed to create a client with the pins of {}", $url);
+            }
+            Ok(<$Client>::new())
+        };
+
+        let client = if rejected {
+            new_client()
+        } else if let Some(conf) = Config::get_socks() {
             let proxy_result = Proxy::from_conf(&conf, None);
 
             match proxy_result {
@@ -69,26 +97,26 @@ macro_rules! configure_http_client {
                                 }
                             }
                             builder = builder.proxy(p);
-                            builder.build().unwrap_or_else(|e| {
+                            builder.build().or_else(|e| {
                                 info!("Failed to create a proxied client: {}", e);
-                                <$Client>::new()
+                                new_client()
                             })
                         }
                         Err(e) => {
                             info!("Failed to set up proxy: {}", e);
-                            <$Client>::new()
+                            new_client()
                         }
                     }
                 }
                 Err(e) => {
                     info!("Failed to configure proxy: {}", e);
-                    <$Client>::new()
+                    new_client()
                 }
             }
         } else {
-            builder.build().unwrap_or_else(|e| {
+            builder.build().or_else(|e| {
                 info!("Failed to create a client: {}", e);
-                <$Client>::new()
+                new_client()
             })
         };
 
@@ -96,17 +124,36 @@ macro_rules! configure_http_client {
     }};
 }
 
-pub fn create_http_client(tls_type: TlsType, danger_accept_invalid_cert: bool) -> SyncClient {
+/// `url` is the one to request, the client accepts only the pinned certificates if it's pinned,
+/// see [`super::cert_pinning`]. Fails if the client of a pinned host can't be created.
+pub fn create_http_client(
+    url: &str,
+    tls_type: TlsType,
+    danger_accept_invalid_cert: bool,
+) -> ResultType<SyncClient> {
     let builder = SyncClient::builder();
-    configure_http_client!(builder, tls_type, danger_accept_invalid_cert, SyncClient)
+    configure_http_client!(
+        builder,
+        url,
+        tls_type,
+        danger_accept_invalid_cert,
+        SyncClient
+    )
 }
 
 pub fn create_http_client_async(
+    url: &str,
     tls_type: TlsType,
     danger_accept_invalid_cert: bool,
-) -> AsyncClient {
+) -> ResultType<AsyncClient> {
     let builder = AsyncClient::builder();
-    configure_http_client!(builder, tls_type, danger_accept_invalid_cert, AsyncClient)
+    configure_http_client!(
+        builder,
+        url,
+        tls_type,
+        danger_accept_invalid_cert,
+        AsyncClient
+    )
 }
 
 pub fn get_url_for_tls<'a>(url: &'a str, proxy_conf: &'a Option<Socks5Server>) -> &'a str {
@@ -120,7 +167,7 @@ pub fn get_url_for_tls<'a>(url: &'a str, proxy_conf: &'a Option<Socks5Server>) -
     url
 }
 
-pub fn create_http_client_with_url(url: &str) -> SyncClient {
+pub fn create_http_client_with_url(url: &str) -> ResultType<SyncClient> {
     let proxy_conf = Config::get_socks();
     let tls_url = get_url_for_tls(url, &proxy_conf);
     let tls_type = get_cached_tls_type(tls_url);
@@ -144,10 +191,11 @@ fn create_http_client_with_url_(
     is_tls_type_cached: bool,
     danger_accept_invalid_cert: Option<bool>,
     original_danger_accept_invalid_cert: Option<bool>,
-) -> SyncClient {
-    let mut client = create_http_client(tls_type, danger_accept_invalid_cert.unwrap_or(false));
+) -> ResultType<SyncClient> {
+    let mut client =
+        create_http_client(url, tls_type, danger_accept_invalid_cert.unwrap_or(false))?;
     if is_tls_type_cached && original_danger_accept_invalid_cert.is_some() {
-        return client;
+        return Ok(client);
     }
     if let Err(e) = client.head(url).send() {
         if e.is_request() {
@@ -165,7 +213,7 @@ fn create_http_client_with_url_(
                         is_tls_type_cached,
                         Some(true),
                         original_danger_accept_invalid_cert,
-                    );
+                    )?;
                 }
                 (TlsType::Rustls, false, Some(_)) => {
                     log::warn!(
@@ -180,7 +228,7 @@ fn create_http_client_with_url_(
                         is_tls_type_cached,
                         original_danger_accept_invalid_cert,
                         original_danger_accept_invalid_cert,
-                    );
+                    )?;
                 }
                 (TlsType::NativeTls, _, None) => {
                     log::warn!(
@@ -195,7 +243,7 @@ fn create_http_client_with_url_(
                         is_tls_type_cached,
                         Some(true),
                         original_danger_accept_invalid_cert,
-                    );
+                    )?;
                 }
                 _ => {
                     log::error!(
@@ -226,10 +274,10 @@ fn create_http_client_with_url_(
             danger_accept_invalid_cert.unwrap_or(false),
         );
     }
-    client
+    Ok(client)
 }
 
-pub async fn create_http_client_async_with_url(url: &str) -> AsyncClient {
+pub async fn create_http_client_async_with_url(url: &str) -> ResultType<AsyncClient> {
     let proxy_conf = Config::get_socks();
     let tls_url = get_url_for_tls(url, &proxy_conf);
     let tls_type = get_cached_tls_type(tls_url);
@@ -255,11 +303,11 @@ async fn create_http_client_async_with_url_(
     is_tls_type_cached: bool,
     danger_accept_invalid_cert: Option<bool>,
     original_danger_accept_invalid_cert: Option<bool>,
-) -> AsyncClient {
+) -> ResultType<AsyncClient> {
     let mut client =
-        create_http_client_async(tls_type, danger_accept_invalid_cert.unwrap_or(false));
+        create_http_client_async(url, tls_type, danger_accept_invalid_cert.unwrap_or(false))?;
     if is_tls_type_cached && original_danger_accept_invalid_cert.is_some() {
-        return client;
+        return Ok(client);
     }
     if let Err(e) = client.head(url).send().await {
         match (tls_type, is_tls_type_cached, danger_accept_invalid_cert) {
@@ -277,7 +325,7 @@ async fn create_http_client_async_with_url_(
                     Some(true),
                     original_danger_accept_invalid_cert,
                 )
-                .await;
+                .await?;
             }
             (TlsType::Rustls, false, Some(_)) => {
                 log::warn!(
@@ -293,7 +341,7 @@ async fn create_http_client_async_with_url_(
                     original_danger_accept_invalid_cert,
                     original_danger_accept_invalid_cert,
                 )
-                .await;
+                .await?;
             }
             (TlsType::NativeTls, _, None) => {
                 log::warn!(
@@ -309,7 +357,7 @@ async fn create_http_client_async_with_url_(
                     Some(true),
                     original_danger_accept_invalid_cert,
                 )
-                .await;
+                .await?;
             }
             _ => {
                 log::error!(
@@ -332,5 +380,5 @@ async fn create_http_client_async_with_url_(
             danger_accept_invalid_cert.unwrap_or(false),
         );
     }
-    client
+    Ok(client)
 }
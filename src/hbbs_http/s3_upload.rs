@@ -0,0 +1,207 @@
+//! Upload of the recordings of the incoming sessions to an S3-compatible storage,
+//! e.g. AWS S3, MinIO or Ceph, besides the upload to the API server of [`super::record_upload`].
+//!
+//! The option [`OPTION_S3`] is a JSON of [`S3Config`]. Each recording is uploaded with one
+//! `PUT` of the path-style URL `<endpoint>/<bucket>/<prefix><file name>` signed with AWS
+//! Signature Version 4 once it's finished, and removed locally after the upload unless
+//! `keep_local` is set.
+use hbb_common::{bail, chrono, config::Config, log, ResultType};
+use hmac::{Hmac, Mac};
+use reqwest::blocking::Body;
+use scrap::record::RecordState;
+use serde_derive::Deserialize;
+use sha2::{Digest, Sha256};
+use std::{
+    fs::File,
+    path::Path,
+    sync::mpsc::{Receiver, Sender},
+};
+
+pub const OPTION_S3: &str = "record-s3";
+const DEFAULT_REGION: &str = "us-east-1";
+// The payload is streamed from the file, so it's not hashed.
+const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct S3Config {
+    /// e.g. `https://s3.eu-central-1.amazonaws.com` or `https://minio.example.com:9000`.
+    pub endpoint: String,
+    pub bucket: String,
+    pub region: String,
+    pub access_key: String,
+    pub secret_key: String,
+    /// The prefix of the object keys, e.g. `recordings/`.
+    pub prefix: String,
+    pub keep_local: bool,
+}
+
+impl S3Config {
+    /// The config of [`OPTION_S3`], `None` if not set or invalid.
+    pub fn load() -> Option<Self> {
+        let raw = Config::get_option(OPTION_S3);
+        if raw.is_empty() {
+            return None;
+        }
+        match serde_json::from_str::<Self>(&raw) {
+            Ok(c) if !c.endpoint.is_empty() && !c.bucket.is_empty() => Some(c),
+            Ok(_) => {
+                log::error!("The endpoint and the bucket of {} are required", OPTION_S3);
+                None
+            }
+            Err(e) => {
+                log::error!("Invalid {}: {}", OPTION_S3, e);
+                None
+            }
+        }
+    }
+
+    fn region(&self) -> &str {
+        if self.region.is_empty() {
+            DEFAULT_REGION
+        } else {
+            &self.region
+        }
+    }
+
+    fn upload(&self, filepath: &str) -> ResultType<()> {
+        let Some(filename) = Path::new(filepath).file_name().and_then(|f| f.to_str()) else {
+            bail!("can't parse filepath:{}", filepath);
+        };
+        let path = format!(
+            "/{}/{}",
+            uri_encode(&self.bucket, false),
+            uri_encode(&format!("{}{}", self.prefix, filename), false)
+        );
+        let url = url::Url::parse(&format!("{}{}", self.endpoint.trim_end_matches('/'), path))?;
+        let Some(host) = url.host_str() else {
+            bail!("Invalid endpoint: {}", self.endpoint);
+        };
+        let host = match url.port() {
+            Some(port) => format!("{}:{}", host, port),
+            None => host.to_owned(),
+        };
+        let amz_date = chrono::Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+        let authorization = self.authorization("PUT", &path, &host, &amz_date);
+        let file = File::open(filepath)?;
+        let len = file.metadata()?.len();
+        let resp = super::create_http_client_with_url(url.as_str())?
+            .put(url)
+            .header("x-amz-date", &amz_date)
+            .header("x-amz-content-sha256", UNSIGNED_PAYLOAD)
+            .header("authorization", authorization)
+            .body(Body::sized(file, len))
+            .send()?;
+        if !resp.status().is_success() {
+            bail!("{}: {}", resp.status(), resp.text().unwrap_or_default());
+        }
+        log::info!("upload to s3 success, file: {}", filename);
+        Ok(())
+    }
+
+    fn authorization(&self, method: &str, path: &str, host: &str, amz_date: &str) -> String {
+        let date = &amz_date[..8];
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region());
+        let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+        let canonical_request = format!(
+            "{method}\n{path}\n\nhost:{host}\nx-amz-content-sha256:{UNSIGNED_PAYLOAD}\nx-amz-date:{amz_date}\n\n{signed_headers}\n{UNSIGNED_PAYLOAD}"
+        );
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+        let mut key = hmac_sha256(
+            format!("AWS4{}", self.secret_key).as_bytes(),
+            date.as_bytes(),
+        );
+        for s in [self.region(), "s3", "aws4_request"] {
+            key = hmac_sha256(&key, s.as_bytes());
+        }
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            self.access_key,
+            scope,
+            signed_headers,
+            hex::encode(hmac_sha256(&key, string_to_sign.as_bytes()))
+        )
+    }
+}
+
+/// Upload the recordings of `rx` when they are finished, and pass the states on to `next`.
+pub fn run(config: S3Config, rx: Receiver<RecordState>, next: Option<Sender<RecordState>>) {
+    std::thread::spawn(move || {
+        let mut filepath = String::new();
+        while let Ok(state) = rx.recv() {
+            match &state {
+                RecordState::NewFile(path) => filepath = path.clone(),
+                RecordState::WriteTail if !filepath.is_empty() => match config.upload(&filepath) {
+                    Ok(()) if !config.keep_local => {
+                        std::fs::remove_file(&filepath).ok();
+                    }
+                    Ok(()) => {}
+                    Err(e) => log::error!("upload to s3 failed, file: {}, {}", filepath, e),
+                },
+                _ => {}
+            }
+            if let Some(next) = &next {
+                next.send(state).ok();
+            }
+        }
+        log::trace!("s3 upload thread stop");
+    });
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    // HMAC accepts the keys of any length.
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).unwrap();
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn uri_encode(s: &str, encode_slash: bool) -> String {
+    let mut res = String::new();
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                res.push(b as char)
+            }
+            b'/' if !encode_slash => res.push('/'),
+            _ => res.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_authorization() {
+        assert_eq!(
+            uri_encode("rec/incoming 1+2.webm", false),
+            "rec/incoming%201%2B2.webm"
+        );
+        assert_eq!(uri_encode("a/b", true), "a%2Fb");
+        let config = S3Config {
+            endpoint: "https://s3.example.com".to_owned(),
+            bucket: "bucket".to_owned(),
+            access_key: "AKID".to_owned(),
+            secret_key: "secret".to_owned(),
+            ..Default::default()
+        };
+        let auth = config.authorization(
+            "PUT",
+            "/bucket/a.webm",
+            "s3.example.com",
+            "20240101T000000Z",
+        );
+        assert!(auth.starts_with(
+            "AWS4-HMAC-SHA256 Credential=AKID/20240101/us-east-1/s3/aws4_request, SignedHeaders=host;x-amz-content-sha256;x-amz-date, Signature="
+        ));
+        // The hex of the SHA-256 HMAC.
+        assert_eq!(auth.rsplit('=').next().unwrap().len(), 64);
+    }
+}
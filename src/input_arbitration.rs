@@ -0,0 +1,229 @@
+//! Arbitration of the input of the simultaneous remote sessions, e.g. for the training sessions
+//! with an instructor and the observers.
+//!
+//! All the sessions with the keyboard permission control the input by default. With the option
+//! [`OPTION_POLICY`], only one of them does at a time:
+//! - [`POLICY_FIRST_COME`]: the earliest session, then the next one when it leaves.
+//! - [`POLICY_TOKEN`]: the earliest session, which passes the control to another one explicitly.
+//! - [`POLICY_HOST`]: the session assigned in the connection manager, none before.
+//!
+//! The host sends [`InputControl::State`] to all the remote sessions with a plugin request of the
+//! reserved id [`REQUEST_ID`] when a viewer joins or leaves and when the control changes.
+use hbb_common::{config::Config, log, message_proto::Message};
+use serde_derive::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+pub const REQUEST_ID: &str = crate::common::plugin_request_id::INPUT_CONTROL;
+pub const OPTION_POLICY: &str = "input-control-policy";
+pub const POLICY_FIRST_COME: &str = "first-come";
+pub const POLICY_TOKEN: &str = "token";
+pub const POLICY_HOST: &str = "host";
+pub const EVENT_JOIN: &str = "join";
+pub const EVENT_LEAVE: &str = "leave";
+pub const EVENT_CONTROL: &str = "control";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Viewer {
+    pub conn_id: i32,
+    pub peer_id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "t", content = "c")]
+pub enum InputControl {
+    /// From the host on the changes, `controller` is the conn id of the session in control,
+    /// 0 for none, and `you` the one of the session receiving it.
+    /// `viewer` is the one joined, left or taking the control by the `event`.
+    State {
+        policy: String,
+        controller: i32,
+        you: i32,
+        viewers: Vec<Viewer>,
+        event: String,
+        viewer: Viewer,
+    },
+    /// From the session in control with [`POLICY_TOKEN`], to pass the control to the session `to`.
+    Pass { to: i32 },
+}
+
+impl InputControl {
+    pub fn to_message(&self) -> Message {
+        crate::common::make_plugin_request(REQUEST_ID, serde_json::to_vec(self).unwrap_or_default())
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref ARBITER: Mutex<Arbiter> = Default::default();
+}
+
+#[derive(Debug, Default)]
+struct Arbiter {
+    // In the order of joining.
+    viewers: Vec<Viewer>,
+    controller: i32,
+}
+
+impl Arbiter {
+    fn join(&mut self, viewer: Viewer, policy: &str) {
+        if self.controller == 0 && !policy.is_empty() && policy != POLICY_HOST {
+            self.controller = viewer.conn_id;
+        }
+        self.viewers.push(viewer);
+    }
+
+    fn leave(&mut self, conn_id: i32, policy: &str) -> Option<Viewer> {
+        let i = self.viewers.iter().position(|v| v.conn_id == conn_id)?;
+        let viewer = self.viewers.remove(i);
+        if self.controller == conn_id {
+            self.controller = if policy == POLICY_HOST {
+                0
+            } else {
+                self.viewers.first().map(|v| v.conn_id).unwrap_or(0)
+            };
+        }
+        Some(viewer)
+    }
+
+    fn assign(&mut self, conn_id: i32) -> Option<Viewer> {
+        let viewer = self.viewers.iter().find(|v| v.conn_id == conn_id)?.clone();
+        self.controller = conn_id;
+        Some(viewer)
+    }
+
+    fn can_input(&self, conn_id: i32, policy: &str) -> bool {
+        policy.is_empty() || self.controller == conn_id
+    }
+
+    fn state(&self, policy: String, event: &str, viewer: Viewer) -> InputControl {
+        InputControl::State {
+            policy,
+            controller: self.controller,
+            you: 0,
+            viewers: self.viewers.clone(),
+            event: event.to_owned(),
+            viewer,
+        }
+    }
+}
+
+/// The policy, empty if all the sessions control the input.
+pub fn policy() -> String {
+    let policy = Config::get_option(OPTION_POLICY);
+    if [POLICY_FIRST_COME, POLICY_TOKEN, POLICY_HOST].contains(&policy.as_str()) {
+        policy
+    } else {
+        "".to_owned()
+    }
+}
+
+/// Whether the session `conn_id` may control the input, besides its keyboard permission.
+pub fn can_input(conn_id: i32) -> bool {
+    ARBITER.lock().unwrap().can_input(conn_id, &policy())
+}
+
+/// On the authorization of a remote session.
+#[cfg(not(target_os = "ios"))]
+pub fn join(viewer: Viewer) {
+    let policy = policy();
+    let state = {
+        let mut arbiter = ARBITER.lock().unwrap();
+        arbiter.join(viewer.clone(), &policy);
+        arbiter.state(policy, EVENT_JOIN, viewer)
+    };
+    broadcast(state);
+}
+
+/// On the close of a remote session.
+#[cfg(not(target_os = "ios"))]
+pub fn leave(conn_id: i32) {
+    let policy = policy();
+    let state = {
+        let mut arbiter = ARBITER.lock().unwrap();
+        let Some(viewer) = arbiter.leave(conn_id, &policy) else {
+            return;
+        };
+        arbiter.state(policy, EVENT_LEAVE, viewer)
+    };
+    broadcast(state);
+}
+
+/// Pass the control from the session `from` to `to` with [`POLICY_TOKEN`].
+#[cfg(not(target_os = "ios"))]
+pub fn pass(from: i32, to: i32) -> bool {
+    let policy = policy();
+    if policy != POLICY_TOKEN || !can_input(from) {
+        log::warn!("Session {} can't pass the input control", from);
+        return false;
+    }
+    assign_(policy, to)
+}
+
+/// Give the control to the session `conn_id` by the host, with any policy but the empty one.
+#[cfg(not(target_os = "ios"))]
+pub fn assign(conn_id: i32) -> bool {
+    let policy = policy();
+    if policy.is_empty() {
+        return false;
+    }
+    assign_(policy, conn_id)
+}
+
+#[cfg(not(target_os = "ios"))]
+fn assign_(policy: String, conn_id: i32) -> bool {
+    let state = {
+        let mut arbiter = ARBITER.lock().unwrap();
+        let Some(viewer) = arbiter.assign(conn_id) else {
+            return false;
+        };
+        arbiter.state(policy, EVENT_CONTROL, viewer)
+    };
+    log::info!("Input control given to the session {}", conn_id);
+    broadcast(state);
+    true
+}
+
+#[cfg(not(target_os = "ios"))]
+fn broadcast(state: InputControl) {
+    for c in crate::server::AUTHED_CONNS.lock().unwrap().iter() {
+        if c.conn_type == crate::server::AuthConnType::Remote {
+            c.sender
+                .send(crate::ipc::Data::InputControl(state.clone()))
+                .ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn viewer(conn_id: i32) -> Viewer {
+        Viewer {
+            conn_id,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_arbiter() {
+        let mut arbiter = Arbiter::default();
+        for i in 1..=3 {
+            arbiter.join(viewer(i), POLICY_FIRST_COME);
+        }
+        assert!(arbiter.can_input(1, POLICY_FIRST_COME));
+        assert!(!arbiter.can_input(2, POLICY_FIRST_COME));
+        assert!(arbiter.can_input(2, ""));
+        arbiter.leave(1, POLICY_FIRST_COME);
+        assert_eq!(arbiter.controller, 2);
+        assert!(arbiter.assign(4).is_none());
+        assert!(arbiter.assign(3).is_some());
+        arbiter.leave(3, POLICY_HOST);
+        assert_eq!(arbiter.controller, 0);
+
+        let mut arbiter = Arbiter::default();
+        arbiter.join(viewer(1), POLICY_HOST);
+        assert_eq!(arbiter.controller, 0);
+    }
+}
@@ -107,6 +107,12 @@ pub enum FS {
         is_resume: bool,
     },
     SendConfirm(Vec<u8>),
+    Checksum {
+        id: i32,
+        file_num: i32,
+        size: u64,
+    },
+    FolderSync(Vec<u8>),
     Rename {
         id: i32,
         path: String,
@@ -206,6 +212,7 @@ pub enum Data {
         recording: bool,
         block_input: bool,
         from_switch: bool,
+        session_fingerprint: String,
     },
     ChatMessage {
         text: String,
@@ -247,6 +254,10 @@ pub enum Data {
     #[cfg(not(any(target_os = "android", target_os = "ios")))]
     Mouse(DataMouse),
     Control(DataControl),
+    #[cfg(target_os = "linux")]
+    Gamepad(crate::gamepad::GamepadEvent),
+    #[cfg(target_os = "linux")]
+    Pen(crate::pen::PenEvent),
     Theme(String),
     Language(String),
     Empty,
@@ -259,6 +270,59 @@ pub enum Data {
     StartVoiceCall,
     VoiceCallResponse(bool),
     CloseVoiceCall(String),
+    /// From the connection to the cm to prompt for the consent of the command,
+    /// see [`crate::remote_command`].
+    RemoteCommand(crate::remote_command::Request),
+    /// From the connection to the cm, the command prompted is cancelled by the peer.
+    RemoteCommandCancelled(u32),
+    /// From the cm to the connection, the consent of the command.
+    RemoteCommandResponse {
+        id: u32,
+        approved: bool,
+    },
+    /// From the connection to the cm, the unix time in seconds when the session expires,
+    /// see [`crate::session_expiry`].
+    SessionExpiry {
+        expire_time: i64,
+        expired: bool,
+    },
+    /// From the server to the connection to switch off the permissions with the reason,
+    /// and from the connection to the cm with the ones switched off,
+    /// see [`crate::permission_revocation`].
+    RevokePermissions {
+        names: Vec<String>,
+        reason: String,
+    },
+    /// From the server to the connection, and from the connection to the cm,
+    /// see [`crate::input_arbitration`].
+    InputControl(crate::input_arbitration::InputControl),
+    /// From the cm to give the input control to the connection.
+    AssignInputControl,
+    /// From the server to the connection handed off, with the id of the new connection,
+    /// see [`crate::session_handoff`].
+    SessionHandedOff(i32),
+    /// The peer id, the permissions and the reason, and the count of the sessions revoked.
+    RevokePeerPermissions((String, Vec<String>, String, Option<usize>)),
+    /// The query of the audit log written by the server, and the entries matching it.
+    AuditLog(
+        (
+            crate::server::audit_log::Query,
+            Option<Vec<crate::server::audit_log::Entry>>,
+        ),
+    ),
+    /// The query of the incoming connection history written by the server, and the entries
+    /// matching it.
+    ConnectionHistory(
+        (
+            crate::connection_history::Query,
+            Option<Vec<crate::connection_history::Entry>>,
+        ),
+    ),
+    /// The authorized sessions of the server.
+    AuthedSessions(Option<Vec<crate::server::AuthedSession>>),
+    /// The `force` and the new fingerprint or the error.
+    #[cfg(not(any(target_os = "android", target_os = "ios")))]
+    RotateKeyPair((bool, Option<Result<String, String>>)),
     #[cfg(all(feature = "flutter", feature = "plugin_framework"))]
     #[cfg(not(any(target_os = "android", target_os = "ios")))]
     Plugin(Plugin),
@@ -274,6 +338,8 @@ pub enum Data {
     // Although the key is not necessary, it is used to avoid hardcoding the key.
     WaylandScreencastRestoreToken((String, String)),
     HwCodecConfig(Option<String>),
+    // The json of the encoders in use by the displays.
+    EncoderSelection(Option<String>),
     RemoveTrustedDevices(Vec<Bytes>),
     ClearTrustedDevices,
     #[cfg(all(target_os = "windows", feature = "flutter"))]
@@ -602,6 +668,53 @@ async fn handle(data: Data, stream: &mut Connection) {
                 allow_err!(stream.send(&Data::Options(None)).await);
             }
         },
+        Data::AuditLog((query, None)) => {
+            let entries = crate::server::audit_log::query(&query).unwrap_or_else(|e| {
+                log::error!("Failed to read the audit log: {}", e);
+                vec![]
+            });
+            allow_err!(stream.send(&Data::AuditLog((query, Some(entries)))).await);
+        }
+        Data::ConnectionHistory((query, None)) => {
+            let entries = crate::connection_history::query(&query).unwrap_or_else(|e| {
+                log::error!("Failed to read the connection history: {}", e);
+                vec![]
+            });
+            allow_err!(
+                stream
+                    .send(&Data::ConnectionHistory((query, Some(entries))))
+                    .await
+            );
+        }
+        Data::AuthedSessions(None) => {
+            let sessions = crate::server::authed_sessions();
+            allow_err!(stream.send(&Data::AuthedSessions(Some(sessions))).await);
+        }
+        Data::RevokePeerPermissions((peer_id, names, reason, None)) => {
+            let count = crate::server::revoke_permissions(&peer_id, names.clone(), reason.clone());
+            allow_err!(
+                stream
+                    .send(&Data::RevokePeerPermissions((
+                        peer_id,
+                        names,
+                        reason,
+                        Some(count)
+                    )))
+                    .await
+            );
+        }
+        #[cfg(not(any(target_os = "android", target_os = "ios")))]
+        Data::RotateKeyPair((force, None)) => {
+            let res = crate::server::key_rotation::rotate(force).map_err(|e| e.to_string());
+            let restart = res.is_ok() && crate::is_server();
+            allow_err!(stream.send(&Data::RotateKeyPair((force, Some(res)))).await);
+            if restart {
+                // The service starts the server again, which loads and registers the new key.
+                hbb_common::sleep(crate::server::key_rotation::RESTART_DELAY_SECS).await;
+                log::info!("Restart the server to load the new key pair");
+                std::process::exit(-1);
+            }
+        }
         Data::NatType(_) => {
             let t = Config::get_nat_type();
             allow_err!(stream.send(&Data::NatType(Some(t))).await);
@@ -691,6 +804,10 @@ async fn handle(data: Data, stream: &mut Connection) {
                 }
             }
         }
+        Data::EncoderSelection(None) => {
+            let v = crate::server::video_service::get_encoder_selections();
+            allow_err!(stream.send(&Data::EncoderSelection(Some(v))).await);
+        }
         Data::WaylandScreencastRestoreToken((key, value)) => {
             let v = if value == "get" {
                 let opt = get_local_option(key.clone());
@@ -797,7 +914,12 @@ pub async fn start_pa() {
                             {
                                 device = x;
                             }
-                            if !device.is_empty() {
+                            let app = device
+                                .strip_prefix(crate::audio_service::APP_INPUT_PREFIX)
+                                .map(|pid| pid.parse::<u32>().unwrap_or_default());
+                            if let Some(pid) = app {
+                                device = crate::platform::linux::get_pa_app_target(pid);
+                            } else if !device.is_empty() {
                                 device = crate::platform::linux::get_pa_source_name(&device);
                             }
                             if device.is_empty() {
@@ -825,17 +947,22 @@ pub async fn start_pa() {
                                 None, // Use default buffering attributes
                             ) {
                                 Ok(s) => loop {
-                                    if let Ok(_) = s.read(&mut buf) {
-                                        let out =
-                                            if buf.iter().filter(|x| **x != 0).next().is_none() {
-                                                vec![]
-                                            } else {
-                                                buf.clone()
-                                            };
-                                        if let Err(err) = stream.send_raw(out.into()).await {
-                                            log::error!("Failed to send audio data:{}", err);
+                                    if let Err(err) = s.read(&mut buf) {
+                                        // The stream of the application ends with it.
+                                        if app.is_some() {
+                                            log::info!("pa app stream ended: {}", err);
                                             break;
                                         }
+                                        continue;
+                                    }
+                                    let out = if buf.iter().filter(|x| **x != 0).next().is_none() {
+                                        vec![]
+                                    } else {
+                                        buf.clone()
+                                    };
+                                    if let Err(err) = stream.send_raw(out.into()).await {
+                                        log::error!("Failed to send audio data:{}", err);
+                                        break;
                                     }
                                 },
                                 Err(err) => {
@@ -1140,6 +1267,78 @@ async fn get_options_(ms_timeout: u64) -> ResultType<HashMap<String, String>> {
     }
 }
 
+/// Query the audit log of the server process, which may not be readable by this process.
+#[tokio::main(flavor = "current_thread")]
+pub async fn get_audit_log(
+    query: crate::server::audit_log::Query,
+) -> ResultType<Vec<crate::server::audit_log::Entry>> {
+    let mut c = connect(1000, "").await?;
+    c.send(&Data::AuditLog((query, None))).await?;
+    if let Some(Data::AuditLog((_, Some(entries)))) = c.next_timeout(3000).await? {
+        Ok(entries)
+    } else {
+        bail!("No audit log");
+    }
+}
+
+/// Query the connection history of the server process, which may not be readable by this
+/// process.
+#[tokio::main(flavor = "current_thread")]
+pub async fn get_connection_history(
+    query: crate::connection_history::Query,
+) -> ResultType<Vec<crate::connection_history::Entry>> {
+    let mut c = connect(1000, "").await?;
+    c.send(&Data::ConnectionHistory((query, None))).await?;
+    if let Some(Data::ConnectionHistory((_, Some(entries)))) = c.next_timeout(3000).await? {
+        Ok(entries)
+    } else {
+        bail!("No connection history");
+    }
+}
+
+/// Revoke the permissions of the sessions of the peer in the server process,
+/// returns the count of the sessions.
+#[tokio::main(flavor = "current_thread")]
+pub async fn revoke_peer_permissions(
+    peer_id: String,
+    names: Vec<String>,
+    reason: String,
+) -> ResultType<usize> {
+    let mut c = connect(1000, "").await?;
+    c.send(&Data::RevokePeerPermissions((peer_id, names, reason, None)))
+        .await?;
+    if let Some(Data::RevokePeerPermissions((_, _, _, Some(count)))) = c.next_timeout(3000).await? {
+        Ok(count)
+    } else {
+        bail!("No response of the revocation");
+    }
+}
+
+/// The authorized sessions of the server process.
+#[tokio::main(flavor = "current_thread")]
+pub async fn get_authed_sessions() -> ResultType<Vec<crate::server::AuthedSession>> {
+    let mut c = connect(1000, "").await?;
+    c.send(&Data::AuthedSessions(None)).await?;
+    if let Some(Data::AuthedSessions(Some(sessions))) = c.next_timeout(3000).await? {
+        Ok(sessions)
+    } else {
+        bail!("No response of the sessions");
+    }
+}
+
+/// Rotate the key pair of the server process, returns the new fingerprint.
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+#[tokio::main(flavor = "current_thread")]
+pub async fn rotate_key_pair(force: bool) -> ResultType<String> {
+    let mut c = connect(1000, "").await?;
+    c.send(&Data::RotateKeyPair((force, None))).await?;
+    match c.next_timeout(3000).await? {
+        Some(Data::RotateKeyPair((_, Some(Ok(fingerprint))))) => Ok(fingerprint),
+        Some(Data::RotateKeyPair((_, Some(Err(e))))) => bail!(e),
+        _ => bail!("No response of the key rotation"),
+    }
+}
+
 pub async fn get_options_async() -> HashMap<String, String> {
     get_options_(1000).await.unwrap_or(Config::get_options())
 }
@@ -1336,6 +1535,17 @@ pub async fn get_hwcodec_config_from_server() -> ResultType<()> {
     bail!("failed to get hwcodec config");
 }
 
+#[cfg(not(target_os = "android"))]
+#[tokio::main(flavor = "current_thread")]
+pub async fn get_encoder_selections() -> ResultType<String> {
+    let mut c = connect(1000, "").await?;
+    c.send(&Data::EncoderSelection(None)).await?;
+    if let Some(Data::EncoderSelection(Some(v))) = c.next_timeout(1000).await? {
+        return Ok(v);
+    }
+    bail!("failed to get encoder selections");
+}
+
 #[cfg(feature = "hwcodec")]
 #[cfg(not(any(target_os = "android", target_os = "ios")))]
 pub fn client_get_hwcodec_config_thread(wait_sec: u64) {
@@ -266,6 +266,191 @@ fn get_keyboard_mode() -> String {
     "legacy".to_string()
 }
 
+/// The session option to grab the system shortcuts, such as Alt+Tab, the Windows key
+/// and the media keys, while the session window is focused instead of hovered.
+pub const OPTION_FULL_KEYBOARD_GRAB: &str = "full-keyboard-grab";
+/// The local option of the hotkey releasing the grab, such as "ctrl+alt+shift+escape".
+pub const OPTION_GRAB_ESCAPE_HOTKEY: &str = "keyboard-grab-escape-hotkey";
+const DEFAULT_GRAB_ESCAPE_HOTKEY: &str = "ctrl+alt+shift+escape";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hotkey {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub meta: bool,
+    pub key: Key,
+}
+
+impl Hotkey {
+    /// Parse the modifiers and one key joined by '+', case insensitive.
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut hotkey = Self {
+            ctrl: false,
+            alt: false,
+            shift: false,
+            meta: false,
+            key: Key::Unknown(0),
+        };
+        let mut key = None;
+        for part in s.split('+').map(|x| x.trim().to_lowercase()) {
+            match part.as_str() {
+                "ctrl" | "control" => hotkey.ctrl = true,
+                "alt" | "option" => hotkey.alt = true,
+                "shift" => hotkey.shift = true,
+                "meta" | "win" | "cmd" | "super" => hotkey.meta = true,
+                _ => {
+                    if key.is_some() {
+                        return None;
+                    }
+                    key = Some(Self::parse_key(&part)?);
+                }
+            }
+        }
+        hotkey.key = key?;
+        Some(hotkey)
+    }
+
+    fn parse_key(s: &str) -> Option<Key> {
+        const LETTERS: [Key; 26] = [
+            Key::KeyA,
+            Key::KeyB,
+            Key::KeyC,
+            Key::KeyD,
+            Key::KeyE,
+            Key::KeyF,
+            Key::KeyG,
+            Key::KeyH,
+            Key::KeyI,
+            Key::KeyJ,
+            Key::KeyK,
+            Key::KeyL,
+            Key::KeyM,
+            Key::KeyN,
+            Key::KeyO,
+            Key::KeyP,
+            Key::KeyQ,
+            Key::KeyR,
+            Key::KeyS,
+            Key::KeyT,
+            Key::KeyU,
+            Key::KeyV,
+            Key::KeyW,
+            Key::KeyX,
+            Key::KeyY,
+            Key::KeyZ,
+        ];
+        const DIGITS: [Key; 10] = [
+            Key::Num0,
+            Key::Num1,
+            Key::Num2,
+            Key::Num3,
+            Key::Num4,
+            Key::Num5,
+            Key::Num6,
+            Key::Num7,
+            Key::Num8,
+            Key::Num9,
+        ];
+        const FUNCTIONS: [Key; 12] = [
+            Key::F1,
+            Key::F2,
+            Key::F3,
+            Key::F4,
+            Key::F5,
+            Key::F6,
+            Key::F7,
+            Key::F8,
+            Key::F9,
+            Key::F10,
+            Key::F11,
+            Key::F12,
+        ];
+        let mut chars = s.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            if c.is_ascii_lowercase() {
+                return Some(LETTERS[(c as u8 - b'a') as usize]);
+            }
+            if c.is_ascii_digit() {
+                return Some(DIGITS[(c as u8 - b'0') as usize]);
+            }
+        }
+        if let Some(n) = s.strip_prefix('f').and_then(|n| n.parse::<usize>().ok()) {
+            return FUNCTIONS.get(n.checked_sub(1)?).copied();
+        }
+        match s {
+            "escape" | "esc" => Some(Key::Escape),
+            "pause" => Some(Key::Pause),
+            "scrolllock" => Some(Key::ScrollLock),
+            "home" => Some(Key::Home),
+            "end" => Some(Key::End),
+            "insert" => Some(Key::Insert),
+            "delete" => Some(Key::Delete),
+            "backspace" => Some(Key::Backspace),
+            "tab" => Some(Key::Tab),
+            "space" => Some(Key::Space),
+            "enter" => Some(Key::Return),
+            _ => None,
+        }
+    }
+
+    /// If `key` is pressed with the modifiers of the hotkey held.
+    #[cfg(not(any(target_os = "android", target_os = "ios")))]
+    fn is_pressed(&self, key: Key) -> bool {
+        if key != self.key {
+            return false;
+        }
+        let (alt, ctrl, shift, meta) = client::get_modifiers_state(false, false, false, false);
+        (!self.ctrl || ctrl) && (!self.alt || alt) && (!self.shift || shift) && (!self.meta || meta)
+    }
+}
+
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+fn is_full_keyboard_grab() -> bool {
+    #[cfg(not(any(feature = "flutter", feature = "cli")))]
+    if let Some(session) = CUR_SESSION.lock().unwrap().as_ref() {
+        return session.get_toggle_option(OPTION_FULL_KEYBOARD_GRAB.to_owned());
+    }
+    #[cfg(feature = "flutter")]
+    if let Some(session) = flutter::get_cur_session() {
+        return session.get_toggle_option(OPTION_FULL_KEYBOARD_GRAB.to_owned());
+    }
+    false
+}
+
+/// The hotkey releasing the full keyboard grab, the default one if the option is invalid.
+pub fn get_grab_escape_hotkey() -> (String, Hotkey) {
+    let option = hbb_common::config::LocalConfig::get_option(OPTION_GRAB_ESCAPE_HOTKEY);
+    match Hotkey::parse(&option) {
+        Some(hotkey) => (option, hotkey),
+        None => (
+            DEFAULT_GRAB_ESCAPE_HOTKEY.to_owned(),
+            Hotkey::parse(DEFAULT_GRAB_ESCAPE_HOTKEY).unwrap(),
+        ),
+    }
+}
+
+// Release the grab if the escape hotkey is pressed in the full keyboard grab.
+// Returns true if the key should not be sent to the peer.
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+fn try_release_full_keyboard_grab(key: Key, is_press: bool) -> bool {
+    if !is_press || !is_full_keyboard_grab() || !get_grab_escape_hotkey().1.is_pressed(key) {
+        return false;
+    }
+    // Not in the hook, which the grab status is changed by.
+    std::thread::spawn(|| {
+        #[cfg(not(any(feature = "flutter", feature = "cli")))]
+        if let Some(session) = CUR_SESSION.lock().unwrap().as_ref() {
+            session.release_keyboard_grab();
+        }
+        #[cfg(feature = "flutter")]
+        if let Some(session) = flutter::get_cur_session() {
+            session.release_keyboard_grab();
+        }
+    });
+    true
+}
+
 fn start_grab_loop() {
     std::env::set_var("KEYBOARD_ONLY", "y");
     #[cfg(any(target_os = "windows", target_os = "macos"))]
@@ -279,6 +464,9 @@ fn start_grab_loop() {
             let _scan_code = event.position_code;
             let _code = event.platform_code as KeyCode;
             let res = if KEYBOARD_HOOKED.load(Ordering::SeqCst) {
+                if try_release_full_keyboard_grab(key, is_press) {
+                    return None;
+                }
                 client::process_event(&get_keyboard_mode(), &event, None);
                 if is_press {
                     None
@@ -339,7 +527,10 @@ fn start_grab_loop() {
         EventType::KeyPress(key) | EventType::KeyRelease(key) => {
             if let Key::Unknown(keycode) = key {
                 log::error!("rdev get unknown key, keycode is {:?}", keycode);
-            } else {
+            } else if !try_release_full_keyboard_grab(
+                key,
+                matches!(event.event_type, EventType::KeyPress(_)),
+            ) {
                 client::process_event(&get_keyboard_mode(), &event, None);
             }
             None
@@ -1234,3 +1425,24 @@ pub mod input_source {
         ]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hotkey() {
+        let hotkey = Hotkey::parse(DEFAULT_GRAB_ESCAPE_HOTKEY).unwrap();
+        assert!(hotkey.ctrl && hotkey.alt && hotkey.shift && !hotkey.meta);
+        assert_eq!(hotkey.key, Key::Escape);
+        let hotkey = Hotkey::parse("Win + F12").unwrap();
+        assert!(hotkey.meta && !hotkey.ctrl);
+        assert_eq!(hotkey.key, Key::F12);
+        assert_eq!(Hotkey::parse("cmd+q").unwrap().key, Key::KeyQ);
+        assert_eq!(Hotkey::parse("ctrl+7").unwrap().key, Key::Num7);
+        assert_eq!(Hotkey::parse("ctrl+alt"), None);
+        assert_eq!(Hotkey::parse("ctrl+a+b"), None);
+        assert_eq!(Hotkey::parse("ctrl+f13"), None);
+        assert_eq!(Hotkey::parse("ctrl+f0"), None);
+    }
+}
@@ -84,26 +84,30 @@ pub async fn discover() -> ResultType<()> {
 }
 
 pub fn send_wol(id: String) {
-    let interfaces = default_net::get_interfaces();
     for peer in &config::LanPeers::load().peers {
         if peer.id == id {
             for (_, mac) in peer.ip_mac.iter() {
-                if let Ok(mac_addr) = mac.parse() {
-                    for interface in &interfaces {
-                        for ipv4 in &interface.ipv4 {
-                            // remove below mask check to avoid unexpected bug
-                            // if (u32::from(ipv4.addr) & u32::from(ipv4.netmask)) == (u32::from(peer_ip) & u32::from(ipv4.netmask))
-                            log::info!("Send wol to {mac_addr} of {}", ipv4.addr);
-                            allow_err!(wol::send_wol(mac_addr, None, Some(IpAddr::V4(ipv4.addr))));
-                        }
-                    }
-                }
+                send_wol_to_mac(mac);
             }
             break;
         }
     }
 }
 
+/// Send the magic packet of `mac` on all the interfaces.
+pub fn send_wol_to_mac(mac: &str) {
+    if let Ok(mac_addr) = mac.parse() {
+        for interface in &default_net::get_interfaces() {
+            for ipv4 in &interface.ipv4 {
+                // remove below mask check to avoid unexpected bug
+                // if (u32::from(ipv4.addr) & u32::from(ipv4.netmask)) == (u32::from(peer_ip) & u32::from(ipv4.netmask))
+                log::info!("Send wol to {mac_addr} of {}", ipv4.addr);
+                allow_err!(wol::send_wol(mac_addr, None, Some(IpAddr::V4(ipv4.addr))));
+            }
+        }
+    }
+}
+
 #[inline]
 fn get_broadcast_port() -> u16 {
     (RENDEZVOUS_PORT + 3) as _
@@ -261,5 +261,40 @@ pub static ref T: std::collections::HashMap<&'static str, &'static str> =
         ("allow-insecure-tls-fallback-tip", "By default, RustDesk verifies the server certificate for protocols using TLS.\nWith this option enabled, RustDesk will fall back to skipping the verification step and proceed in case of verification failure."),
         ("disable-udp-tip", "Controls whether to use TCP only.\nWhen this option enabled, RustDesk will not use UDP 21116 any more, TCP 21116 will be used instead."),
         ("server-oss-not-support-tip", "NOTE: RustDesk server OSS doesn't include this feature."),
+        ("lossless-high-bitrate-tip", "The lossless stream is using a high bitrate, the video may lag on a slow network. Select another image quality to reduce it."),
+        ("relative-mouse-mode-tip", "The mouse is captured by the remote session, press Ctrl+Alt to release it."),
+        ("relative-mouse-mode-not-supported-tip", "Relative mouse mode is not supported by the remote device, or the cursor can not be captured on Wayland."),
+        ("full-keyboard-grab-{}-tip", "The system shortcuts are sent to the remote session while it is focused, press {} to release the keyboard."),
+        ("keyboard-grab-released-{}-tip", "The keyboard is released by {}, click the remote screen to grab it again."),
+        ("folder-sync-tip", "Only the changed parts of the files are sent to the remote folder."),
+        ("folder-sync-delete-tip", "Delete the remote files not in the local folder"),
+        ("folder-sync-failed-tip", "Folder sync failed."),
+        ("folder-sync-{}-done-tip", "Folder sync done, {} files updated."),
+        ("transfer-rate-limit-tip", "Empty or 0 for no limit"),
+        ("proxy-test-failed-tip", "Failed to connect to the server through the proxy"),
+        ("relay-failover-tip", "The relay server is lost, reconnecting via another relay server."),
+        ("wol-via-peers-tip", "The wake-on-LAN request is sent via the online peers on the same LAN."),
+        ("fido2-touch-tip", "Touch your security key to continue."),
+        ("fido2-not-supported-tip", "Security keys are not supported by this build."),
+        ("fido2-register-tip", "Plug the security key into this device, enter its PIN if it has one, then touch it. The connections will require the key after the password."),
+        ("session-expires-in-{}-minutes-tip", "The session granted by the remote device expires in {} minute(s)."),
+        ("session-expired-view-only-tip", "The session has expired, you can only view the remote screen now."),
+        ("session-expiry-view-only-tip", "Keep the expired sessions for viewing only instead of disconnecting them"),
+        ("enable-audit-log-tip", "Record the connections, logins, permission changes, file transfers and clipboard syncs to the local audit log"),
+        ("rotate-key-confirm-tip", "Replace the key pair of this device? The active sessions will be closed, and the peers will see the new fingerprint."),
+        ("key-rotated-tip", "The key pair is replaced, it's used after the service restarts."),
+        ("permissions-revoked-tip", "The remote side revoked the permissions"),
+        ("viewer-joined-{}-tip", "{} joined the session."),
+        ("viewer-left-{}-tip", "{} left the session."),
+        ("input-control-given-tip", "You control the input now."),
+        ("input-control-of-{}-tip", "{} controls the input now."),
+        ("enable-session-handoff-tip", "Allow the sessions to be handed off to another device of the same user without the password"),
+        ("handoff-token-{}-tip", "Open the link below on the other device within {} seconds, this session will be closed then."),
+        ("windows-session-not-switchable-tip", "The Windows session can't be switched now. It requires the keyboard permission, the installed service, and no other connection."),
+        ("task-manager-denied-tip", "The task manager is not permitted by the remote side. It requires the keyboard permission."),
+        ("remote-command-denied-tip", "Remote commands are not permitted by the remote side. It requires the keyboard permission."),
+        ("remote-command-rejected-tip", "The remote side rejected the command."),
+        ("printer-cups-requirement-tip", "The print jobs are printed with CUPS. The ones from Windows require xpstopdf of libgxps to be installed."),
+        ("annotation-denied-tip", "Annotations are not permitted by the remote side."),
     ].iter().cloned().collect();
 }
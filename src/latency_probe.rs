@@ -0,0 +1,382 @@
+//! Opt-in measurement of the end-to-end latency of the video frames, split into the stages:
+//!
+//! - [`Stage::Encode`]: the conversion and the encoding of the captured frame, on the host.
+//! - [`Stage::Send`]: from the encoded frame queued to the connection until it's written to the
+//!   socket, on the host.
+//! - [`Stage::Network`]: half of the round trip time measured by the host.
+//! - [`Stage::Decode`]: from the frame received until it's decoded, on the client, including
+//!   the wait in the queue of the decoding thread.
+//! - [`Stage::Deliver`]: from the frame decoded until the video callback returns, on the client.
+//!
+//! The client correlates the frames between its stages by the display and the pts of the frame.
+//! The client starts the probe with [`LatencyProbe::Start`] of the reserved id [`REQUEST_ID`] on
+//! the first frame if the local option [`OPTION_ENABLE`] is set, the host then records its
+//! stages and replies its percentiles with [`LatencyProbe::Host`] every second. The client
+//! saves the [`Report`] of the session in [`dir`] every [`SAVE_INTERVAL`] and on the exit,
+//! printed by the CLI `--latency-report`.
+use hbb_common::{
+    config::Config,
+    log,
+    message_proto::{Message, VideoFrame},
+    ResultType,
+};
+use serde_derive::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, VecDeque},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+pub const REQUEST_ID: &str = crate::common::plugin_request_id::LATENCY_PROBE;
+/// The local option of the client to probe the sessions.
+pub const OPTION_ENABLE: &str = "enable-latency-probe";
+pub const SAVE_INTERVAL: Duration = Duration::from_secs(10);
+// The samples of a stage used for the percentiles.
+const MAX_SAMPLES: usize = 1000;
+// The received frames waiting for the decoding.
+const MAX_PENDING: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    Encode,
+    Send,
+    Network,
+    Decode,
+    Deliver,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 5] = [
+        Stage::Encode,
+        Stage::Send,
+        Stage::Network,
+        Stage::Decode,
+        Stage::Deliver,
+    ];
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StageStats {
+    pub stage: Stage,
+    pub count: usize,
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+#[derive(Debug, Default)]
+struct Samples(VecDeque<f64>);
+
+impl Samples {
+    fn push(&mut self, ms: f64) {
+        if self.0.len() >= MAX_SAMPLES {
+            self.0.pop_front();
+        }
+        self.0.push_back(ms);
+    }
+
+    fn stats(&self, stage: Stage) -> Option<StageStats> {
+        if self.0.is_empty() {
+            return None;
+        }
+        let mut sorted = self.0.iter().cloned().collect::<Vec<_>>();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let percentile = |p: f64| sorted[((sorted.len() - 1) as f64 * p).round() as usize];
+        Some(StageStats {
+            stage,
+            count: sorted.len(),
+            p50_ms: percentile(0.5),
+            p90_ms: percentile(0.9),
+            p99_ms: percentile(0.99),
+            max_ms: sorted[sorted.len() - 1],
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+struct StageSamples(HashMap<Stage, Samples>);
+
+impl StageSamples {
+    fn push(&mut self, stage: Stage, elapsed: Duration) {
+        self.0
+            .entry(stage)
+            .or_default()
+            .push(elapsed.as_secs_f64() * 1000.);
+    }
+
+    fn stats(&self) -> Vec<StageStats> {
+        Stage::ALL
+            .iter()
+            .filter_map(|s| self.0.get(s).and_then(|samples| samples.stats(*s)))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "t", content = "c")]
+pub enum LatencyProbe {
+    /// From the client.
+    Start,
+    /// From the client.
+    Stop,
+    /// From the host, the percentiles of its stages.
+    Host { stages: Vec<StageStats> },
+}
+
+impl LatencyProbe {
+    pub fn to_message(&self) -> Message {
+        crate::common::make_plugin_request(REQUEST_ID, serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    pub fn from_message(msg: &Message) -> Option<Self> {
+        serde_json::from_slice(crate::common::get_plugin_request(msg, REQUEST_ID)?).ok()
+    }
+}
+
+// The connections probing the host.
+static HOST_PROBES: AtomicUsize = AtomicUsize::new(0);
+
+lazy_static::lazy_static! {
+    static ref HOST_SAMPLES: Mutex<StageSamples> = Default::default();
+}
+
+#[inline]
+pub fn is_host_probing() -> bool {
+    HOST_PROBES.load(Ordering::Relaxed) > 0
+}
+
+pub fn host_start() {
+    HOST_PROBES.fetch_add(1, Ordering::SeqCst);
+}
+
+pub fn host_stop() {
+    if HOST_PROBES.fetch_sub(1, Ordering::SeqCst) == 1 {
+        *HOST_SAMPLES.lock().unwrap() = Default::default();
+    }
+}
+
+/// Record a stage of the host if it's probed.
+#[inline]
+pub fn host_record(stage: Stage, elapsed: Duration) {
+    if is_host_probing() {
+        HOST_SAMPLES.lock().unwrap().push(stage, elapsed);
+    }
+}
+
+pub fn host_report() -> LatencyProbe {
+    LatencyProbe::Host {
+        stages: HOST_SAMPLES.lock().unwrap().stats(),
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Report {
+    pub peer_id: String,
+    /// The unix time in milliseconds.
+    pub time: i64,
+    pub stages: Vec<StageStats>,
+    /// The sum of the medians of the stages.
+    pub total_p50_ms: f64,
+}
+
+#[derive(Debug, Default)]
+struct ClientState {
+    // (display, pts) -> the time received
+    received: HashMap<(usize, i64), Instant>,
+    // display -> the time decoded
+    decoded: HashMap<usize, Instant>,
+    samples: StageSamples,
+    host: Vec<StageStats>,
+}
+
+/// The probe of a session on the client.
+#[derive(Debug, Default)]
+pub struct ClientProbe {
+    enabled: AtomicBool,
+    state: Mutex<ClientState>,
+}
+
+impl ClientProbe {
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+        *self.state.lock().unwrap() = Default::default();
+    }
+
+    pub fn on_received(&self, vf: &VideoFrame) {
+        if !self.is_enabled() {
+            return;
+        }
+        let Some(pts) = crate::client::feedback::last_pts(vf) else {
+            return;
+        };
+        let mut state = self.state.lock().unwrap();
+        if state.received.len() >= MAX_PENDING {
+            // The frames dropped from the queue are never decoded.
+            state.received.clear();
+        }
+        state
+            .received
+            .insert((vf.display as _, pts), Instant::now());
+    }
+
+    pub fn on_decoded(&self, display: usize, pts: Option<i64>) {
+        if !self.is_enabled() {
+            return;
+        }
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+        if let Some(received) = pts.and_then(|pts| state.received.remove(&(display, pts))) {
+            state.samples.push(Stage::Decode, now - received);
+        }
+        state.decoded.insert(display, now);
+    }
+
+    pub fn on_delivered(&self, display: usize) {
+        if !self.is_enabled() {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        if let Some(decoded) = state.decoded.remove(&display) {
+            state.samples.push(Stage::Deliver, decoded.elapsed());
+        }
+    }
+
+    /// `rtt_ms` is the round trip time measured by the host.
+    pub fn on_network_delay(&self, rtt_ms: u32) {
+        if !self.is_enabled() {
+            return;
+        }
+        self.state
+            .lock()
+            .unwrap()
+            .samples
+            .push(Stage::Network, Duration::from_millis(rtt_ms as u64 / 2));
+    }
+
+    pub fn set_host(&self, stages: Vec<StageStats>) {
+        if self.is_enabled() {
+            self.state.lock().unwrap().host = stages;
+        }
+    }
+
+    pub fn report(&self, peer_id: &str) -> Report {
+        let state = self.state.lock().unwrap();
+        let client = state.samples.stats();
+        let stages = Stage::ALL
+            .iter()
+            .filter_map(|s| {
+                state
+                    .host
+                    .iter()
+                    .chain(client.iter())
+                    .find(|stats| stats.stage == *s)
+                    .cloned()
+            })
+            .collect::<Vec<_>>();
+        Report {
+            peer_id: peer_id.to_owned(),
+            time: hbb_common::get_time(),
+            total_p50_ms: stages.iter().map(|s| s.p50_ms).sum(),
+            stages,
+        }
+    }
+}
+
+pub fn dir() -> PathBuf {
+    Config::path("latency")
+}
+
+pub fn save_report(report: &Report) {
+    let res = || -> ResultType<()> {
+        let dir = dir();
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join(format!("{}.json", report.peer_id));
+        std::fs::write(path, serde_json::to_vec_pretty(report)?)?;
+        Ok(())
+    }();
+    if let Err(err) = res {
+        log::error!("Failed to save the latency report: {}", err);
+    }
+}
+
+/// The saved reports, the latest first, only of `peer_id` if not empty.
+pub fn load_reports(peer_id: &str) -> Vec<Report> {
+    let mut reports = std::fs::read_dir(dir())
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .filter_map(|e| std::fs::read(e.path()).ok())
+                .filter_map(|data| serde_json::from_slice::<Report>(&data).ok())
+                .filter(|r| peer_id.is_empty() || r.peer_id == peer_id)
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    reports.sort_by(|a, b| b.time.cmp(&a.time));
+    reports
+}
+
+pub fn format_report(report: &Report) -> String {
+    let mut lines = vec![
+        format!("Peer {}:", report.peer_id),
+        format!(
+            "{:<10}{:>8}{:>10}{:>10}{:>10}{:>10}",
+            "stage", "count", "p50 ms", "p90 ms", "p99 ms", "max ms"
+        ),
+    ];
+    for s in report.stages.iter() {
+        lines.push(format!(
+            "{:<10}{:>8}{:>10.1}{:>10.1}{:>10.1}{:>10.1}",
+            format!("{:?}", s.stage).to_lowercase(),
+            s.count,
+            s.p50_ms,
+            s.p90_ms,
+            s.p99_ms,
+            s.max_ms
+        ));
+    }
+    lines.push(format!("{:<18}{:>10.1}", "total", report.total_p50_ms));
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats() {
+        let mut samples = StageSamples::default();
+        for ms in 1..=100 {
+            samples.push(Stage::Decode, Duration::from_millis(ms));
+        }
+        let stats = samples.stats();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].count, 100);
+        assert!((stats[0].p50_ms - 51.).abs() < 1e-6);
+        assert!((stats[0].p99_ms - 99.).abs() < 1e-6);
+        assert!((stats[0].max_ms - 100.).abs() < 1e-6);
+        let msg = host_report();
+        assert_eq!(LatencyProbe::from_message(&msg.to_message()), Some(msg));
+
+        let probe = ClientProbe::default();
+        probe.on_network_delay(40);
+        assert!(probe.report("1").stages.is_empty());
+        probe.set_enabled(true);
+        probe.on_network_delay(40);
+        probe.set_host(stats);
+        let report = probe.report("1");
+        assert_eq!(report.stages[0].stage, Stage::Network);
+        assert_eq!(report.stages[1].stage, Stage::Decode);
+        assert!((report.total_p50_ms - 71.).abs() < 1e-6);
+    }
+}
@@ -29,7 +29,11 @@ pub mod ipc;
 pub mod ui;
 mod version;
 pub use version::*;
-#[cfg(any(target_os = "android", target_os = "ios", feature = "flutter"))]
+// Not in the Unity static library, it has no Dart side.
+#[cfg(all(
+    any(target_os = "android", target_os = "ios", feature = "flutter"),
+    not(feature = "unity-static")
+))]
 mod bridge_generated;
 #[cfg(any(target_os = "android", target_os = "ios", feature = "flutter"))]
 pub mod flutter;
@@ -37,6 +41,7 @@ pub mod flutter;
 pub mod flutter_ffi;
 use common::*;
 mod auth_2fa;
+mod auth_fido2;
 #[cfg(feature = "cli")]
 pub mod cli;
 #[cfg(not(target_os = "ios"))]
@@ -44,10 +49,40 @@ mod clipboard;
 #[cfg(not(any(target_os = "android", target_os = "ios", feature = "cli")))]
 pub mod core_main;
 mod custom_server;
+mod gamepad;
+#[cfg(feature = "audio_processing")]
+mod audio_processing;
 mod lang;
 pub mod unity;
+pub mod unreal;
+mod pen;
+mod file_checksum;
+mod folder_sync;
+mod transfer_limit;
+mod relay_servers;
+mod diagnostics;
+mod wake_on_lan;
+mod session_expiry;
+mod permission_revocation;
+mod input_arbitration;
+mod session_handoff;
+mod virtual_monitor;
+mod windows_session;
+mod latency_probe;
+mod priority_region;
+mod peer_metadata;
+mod connection_history;
+mod peer_usage;
+mod system_info;
+mod task_manager;
+mod remote_command;
+mod annotation;
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+mod service_manager;
 #[cfg(not(any(target_os = "android", target_os = "ios")))]
 mod port_forward;
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+mod local_api;
 
 #[cfg(all(feature = "flutter", feature = "plugin_framework"))]
 #[cfg(not(any(target_os = "android", target_os = "ios")))]
@@ -0,0 +1,473 @@
+//! The local REST API of the server process, for the orchestration tools to drive RustDesk
+//! without linking the FFI.
+//!
+//! It's off by default, enabled with the option [`OPTION_ENABLE`], and only listens on
+//! `127.0.0.1:<`[`OPTION_PORT`]`>`, [`DEFAULT_PORT`] if not set. Every request must carry
+//! `Authorization: Bearer <token>` with the token of the option [`OPTION_TOKEN`], which is
+//! generated on the first start if empty, e.g. read by the administrator with
+//! `--option local-api-token`. The requests and the responses are JSON, one per connection.
+//!
+//! | Method   | Path                      | Body                                         |
+//! |----------|---------------------------|----------------------------------------------|
+//! | `GET`    | `/api/v1/status`          |                                              |
+//! | `GET`    | `/api/v1/peers`           |                                              |
+//! | `GET`    | `/api/v1/peers/<id>`      |                                              |
+//! | `PUT`    | `/api/v1/peers/<id>`      | the peer options, e.g. `{"alias": "NAS"}`    |
+//! | `DELETE` | `/api/v1/peers/<id>`      |                                              |
+//! | `POST`   | `/api/v1/connect`         | [`ConnectRequest`]                           |
+//! | `GET`    | `/api/v1/sessions`        |                                              |
+//! | `DELETE` | `/api/v1/sessions/<conn>` |                                              |
+//! | `GET`    | `/api/v1/config`          |                                              |
+//! | `PUT`    | `/api/v1/config`          | the options, an empty value to remove one    |
+//!
+//! The empty values of the options are removed. Only the options of `ALLOWED_OPTIONS`, which
+//! don't grant access, can be changed.
+use hbb_common::{
+    bail,
+    config::{self, Config, PeerConfig},
+    log,
+    tokio::{
+        self,
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+        time::{timeout, Duration},
+    },
+    ResultType,
+};
+use serde_derive::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+pub const OPTION_ENABLE: &str = "enable-local-api";
+pub const OPTION_PORT: &str = "local-api-port";
+pub const OPTION_TOKEN: &str = "local-api-token";
+pub const DEFAULT_PORT: u16 = 21120;
+const TOKEN_LEN: usize = 32;
+const TOKEN_CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+// The options `PUT /api/v1/config` can change. The permissions, the passwords, the access
+// control and the server settings are left to the settings of the host.
+const ALLOWED_OPTIONS: &[&str] = &[
+    "enable-abr",
+    "enable-hwcodec",
+    config::keys::OPTION_DISABLE_UDP,
+    crate::server::recording_policy::OPTION_ENABLE,
+];
+const MAX_HEAD: usize = 16 * 1024;
+const MAX_BODY: usize = 64 * 1024;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const PREFIX: &str = "/api/v1/";
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ConnectRequest {
+    pub id: String,
+    /// `remote`, `file-transfer`, `view-camera`, `port-forward` or `terminal`.
+    #[serde(default, rename = "type")]
+    pub conn_type: String,
+    #[serde(default)]
+    pub password: String,
+    #[serde(default)]
+    pub relay: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct Peer {
+    id: String,
+    alias: String,
+    note: String,
+    tags: Vec<String>,
+    username: String,
+    hostname: String,
+    platform: String,
+    usage: crate::peer_usage::PeerUsage,
+}
+
+#[derive(Debug, PartialEq)]
+struct Request {
+    method: String,
+    path: String,
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+struct Response {
+    status: u16,
+    body: Value,
+}
+
+impl Response {
+    fn ok(body: Value) -> Self {
+        Self { status: 200, body }
+    }
+
+    fn error(status: u16, error: &str) -> Self {
+        Self {
+            status,
+            body: json!({ "error": error }),
+        }
+    }
+}
+
+#[inline]
+fn is_enabled() -> bool {
+    Config::get_bool_option(OPTION_ENABLE)
+}
+
+fn port() -> u16 {
+    Config::get_option(OPTION_PORT)
+        .parse()
+        .ok()
+        .filter(|p| *p > 0)
+        .unwrap_or(DEFAULT_PORT)
+}
+
+fn token() -> String {
+    let token = Config::get_option(OPTION_TOKEN);
+    if !token.is_empty() {
+        return token;
+    }
+    let token = crate::common::random_token(TOKEN_LEN, TOKEN_CHARS);
+    Config::set_option(OPTION_TOKEN.to_owned(), token.clone());
+    token
+}
+
+/// Serve the API while it's enabled, restarted if the port is changed.
+pub async fn start() {
+    loop {
+        if !is_enabled() {
+            hbb_common::sleep(1.).await;
+            continue;
+        }
+        let port = port();
+        let listener = match TcpListener::bind(("127.0.0.1", port)).await {
+            Ok(listener) => listener,
+            Err(err) => {
+                log::error!("Failed to listen on the local api port {}: {}", port, err);
+                hbb_common::sleep(10.).await;
+                continue;
+            }
+        };
+        token();
+        log::info!("Local api listening on 127.0.0.1:{}", port);
+        let mut timer = tokio::time::interval(Duration::from_secs(1));
+        loop {
+            tokio::select! {
+                res = listener.accept() => {
+                    if let Ok((stream, _)) = res {
+                        tokio::spawn(async move {
+                            if let Err(err) = handle(stream).await {
+                                log::debug!("Local api request failed: {}", err);
+                            }
+                        });
+                    }
+                }
+                _ = timer.tick() => {
+                    if !is_enabled() || port() != port {
+                        break;
+                    }
+                }
+            }
+        }
+        log::info!("Local api stopped");
+    }
+}
+
+async fn handle(mut stream: TcpStream) -> ResultType<()> {
+    let res = match timeout(REQUEST_TIMEOUT, read_request(&mut stream)).await {
+        Ok(Ok(req)) => {
+            if authorized(&req, &Config::get_option(OPTION_TOKEN)) {
+                route(&req)
+            } else {
+                log::warn!("Unauthorized local api request {} {}", req.method, req.path);
+                Response::error(401, "Unauthorized")
+            }
+        }
+        Ok(Err(err)) => Response::error(400, &err.to_string()),
+        Err(_) => Response::error(408, "Request timeout"),
+    };
+    let body = serde_json::to_vec(&res.body)?;
+    let head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        res.status,
+        reason(res.status),
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(&body).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        _ => "Internal Server Error",
+    }
+}
+
+async fn read_request(stream: &mut TcpStream) -> ResultType<Request> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let head_len = loop {
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+        if buf.len() > MAX_HEAD {
+            bail!("Request header too large");
+        }
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            bail!("Connection closed");
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    };
+    let mut req = parse_head(&buf[..head_len])?;
+    let len: usize = req
+        .headers
+        .get("content-length")
+        .map(|l| l.parse())
+        .transpose()?
+        .unwrap_or(0);
+    if len > MAX_BODY {
+        bail!("Request body too large");
+    }
+    let mut body = buf.split_off(head_len);
+    while body.len() < len {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            bail!("Connection closed");
+        }
+        body.extend_from_slice(&chunk[..n]);
+    }
+    body.truncate(len);
+    req.body = body;
+    Ok(req)
+}
+
+fn parse_head(head: &[u8]) -> ResultType<Request> {
+    let head = std::str::from_utf8(head)?;
+    let mut lines = head.split("\r\n");
+    let mut start = lines.next().unwrap_or_default().split(' ');
+    let (Some(method), Some(target)) = (start.next(), start.next()) else {
+        bail!("Invalid request line");
+    };
+    let path = target.split('?').next().unwrap_or_default();
+    let headers = lines
+        .filter_map(|l| l.split_once(':'))
+        .map(|(k, v)| (k.trim().to_lowercase(), v.trim().to_owned()))
+        .collect();
+    Ok(Request {
+        method: method.to_owned(),
+        path: path.to_owned(),
+        headers,
+        body: vec![],
+    })
+}
+
+fn authorized(req: &Request, token: &str) -> bool {
+    let Some(bearer) = req
+        .headers
+        .get("authorization")
+        .and_then(|a| a.strip_prefix("Bearer "))
+    else {
+        return false;
+    };
+    !token.is_empty() && crate::common::constant_time_eq(bearer, token)
+}
+
+fn route(req: &Request) -> Response {
+    let Some(path) = req.path.strip_prefix(PREFIX) else {
+        return Response::error(404, "Not found");
+    };
+    let parts: Vec<&str> = path.trim_end_matches('/').split('/').collect();
+    let res = match (req.method.as_str(), parts.as_slice()) {
+        ("GET", ["status"]) => Ok(status()),
+        ("GET", ["peers"]) => Ok(json!(PeerConfig::peers(None)
+            .into_iter()
+            .map(|(id, _, p)| to_peer(id, p))
+            .collect::<Vec<_>>())),
+        ("GET", ["peers", id]) => {
+            if !PeerConfig::exists(id) {
+                return Response::error(404, "Peer not found");
+            }
+            Ok(json!(to_peer(id.to_string(), PeerConfig::load(id))))
+        }
+        ("PUT", ["peers", id]) => set_peer_options(id, &req.body),
+        ("DELETE", ["peers", id]) => {
+            PeerConfig::remove(id);
+            Ok(json!({}))
+        }
+        ("POST", ["connect"]) => serde_json::from_slice::<ConnectRequest>(&req.body)
+            .map_err(Into::into)
+            .and_then(connect),
+        ("GET", ["sessions"]) => Ok(sessions()),
+        ("DELETE", ["sessions", conn_id]) => match conn_id.parse() {
+            Ok(conn_id) if crate::server::close_authed_conn(conn_id) => Ok(json!({})),
+            _ => return Response::error(404, "Session not found"),
+        },
+        ("GET", ["config"]) => {
+            let mut options = Config::get_options();
+            options.remove(OPTION_TOKEN);
+            Ok(json!(options))
+        }
+        ("PUT", ["config"]) => {
+            if config::is_disable_settings() {
+                return Response::error(403, "Settings are disabled");
+            }
+            set_options(&req.body)
+        }
+        (
+            _,
+            ["status"]
+            | ["peers"]
+            | ["peers", _]
+            | ["connect"]
+            | ["sessions"]
+            | ["sessions", _]
+            | ["config"],
+        ) => return Response::error(405, "Method not allowed"),
+        _ => return Response::error(404, "Not found"),
+    };
+    match res {
+        Ok(body) => Response::ok(body),
+        Err(err) => Response::error(400, &err.to_string()),
+    }
+}
+
+fn status() -> Value {
+    json!({
+        "id": Config::get_id(),
+        "version": crate::VERSION,
+        "sessions": crate::server::AUTHED_CONNS.lock().unwrap().len(),
+    })
+}
+
+fn to_peer(id: String, p: PeerConfig) -> Peer {
+    let metadata = crate::peer_metadata::PeerMetadata::from_options(&p.options);
+    Peer {
+        alias: metadata.alias,
+        note: metadata.note,
+        tags: metadata.tags,
+        username: p.info.username,
+        hostname: p.info.hostname,
+        platform: p.info.platform,
+        usage: crate::peer_usage::get(&id),
+        id,
+    }
+}
+
+fn set_peer_options(id: &str, body: &[u8]) -> ResultType<Value> {
+    let options: HashMap<String, String> = serde_json::from_slice(body)?;
+    let mut c = PeerConfig::load(id);
+    for (k, v) in options {
+        if v.is_empty() {
+            c.options.remove(&k);
+        } else {
+            c.options.insert(k, v);
+        }
+    }
+    c.store(id);
+    Ok(json!(to_peer(id.to_owned(), c)))
+}
+
+fn set_options(body: &[u8]) -> ResultType<Value> {
+    let changes: HashMap<String, String> = serde_json::from_slice(body)?;
+    if let Some(k) = changes
+        .keys()
+        .find(|k| !ALLOWED_OPTIONS.contains(&k.as_str()))
+    {
+        bail!("The option {} can't be changed with the api", k);
+    }
+    let mut options = Config::get_options();
+    for (k, v) in changes {
+        if v.is_empty() {
+            options.remove(&k);
+        } else {
+            options.insert(k, v);
+        }
+    }
+    let _chk = crate::ipc::CheckIfRestart::new();
+    Config::set_options(options);
+    Ok(json!({}))
+}
+
+fn connect(req: ConnectRequest) -> ResultType<Value> {
+    if req.id.is_empty() {
+        bail!("The id is required");
+    }
+    let arg = match req.conn_type.as_str() {
+        "" | "remote" => "--connect",
+        "file-transfer" => "--file-transfer",
+        "view-camera" => "--view-camera",
+        "port-forward" => "--port-forward",
+        "terminal" => "--terminal",
+        t => bail!("Unknown connection type: {}", t),
+    };
+    let mut args = vec![arg, req.id.as_str()];
+    if !req.password.is_empty() {
+        args.extend(["--password", req.password.as_str()]);
+    }
+    if req.relay {
+        args.push("--relay");
+    }
+    log::info!("Local api connects to {} with {}", req.id, arg);
+    // The server may run as root or the system, the ui is for the user of the session.
+    let child = if crate::platform::is_root() {
+        #[cfg(target_os = "linux")]
+        let res = crate::platform::run_as_user(args, None, None::<(&str, &str)>);
+        #[cfg(not(target_os = "linux"))]
+        let res = crate::platform::run_as_user(args);
+        res?
+    } else {
+        Some(crate::run_me(args)?)
+    };
+    if let Some(child) = child {
+        crate::server::CHILD_PROCESS.lock().unwrap().push(child);
+    }
+    Ok(json!({}))
+}
+
+fn sessions() -> Value {
+    json!(crate::server::authed_sessions())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_authorize() {
+        let req = parse_head(
+            b"GET /api/v1/peers?x=1 HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer abc\r\n\r\n",
+        )
+        .unwrap();
+        assert_eq!(req.method, "GET");
+        assert_eq!(req.path, "/api/v1/peers");
+        assert!(authorized(&req, "abc"));
+        assert!(!authorized(&req, "abd"));
+        assert!(!authorized(&req, "abcd"));
+        assert!(!authorized(&req, ""));
+        let req = parse_head(b"GET /api/v1/peers HTTP/1.1\r\n\r\n").unwrap();
+        assert!(!authorized(&req, "abc"));
+        assert!(parse_head(b"\r\n\r\n").is_err());
+        let req = Request {
+            method: "PATCH".to_owned(),
+            path: "/api/v1/status".to_owned(),
+            headers: Default::default(),
+            body: vec![],
+        };
+        assert_eq!(route(&req).status, 405);
+    }
+
+    #[test]
+    fn test_set_options_allowed() {
+        assert!(set_options(br#"{"local-api-token": "x"}"#).is_err());
+        assert!(set_options(br#"{"enable-keyboard": "Y"}"#).is_err());
+        assert!(set_options(br#"{"allow-remote-command": "Y", "enable-abr": "N"}"#).is_err());
+    }
+}
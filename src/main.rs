@@ -38,67 +38,162 @@ fn main() {
     if !common::global_init() {
         return;
     }
-    use clap::App;
+    use clap::{Arg, ArgAction, Command};
     use hbb_common::log;
-    let args = format!(
-        "-p, --port-forward=[PORT-FORWARD-OPTIONS] 'Format: remote-id:local-port:remote-port[:remote-host]'
-        -c, --connect=[REMOTE_ID] 'test only'
-        -k, --key=[KEY] ''
-       -s, --server=[] 'Start server'",
-    );
-    let matches = App::new("rustdesk")
+    let peer = || {
+        Arg::new("peer")
+            .long("peer")
+            .value_name("ID")
+            .required(true)
+    };
+    let password = || {
+        Arg::new("password")
+            .long("password")
+            .help("The saved password of the peer if not set")
+    };
+    let matches = Command::new("rustdesk")
         .version(crate::VERSION)
         .author("Purslane Ltd<info@rustdesk.com>")
         .about("RustDesk command line tool")
-        .args_from_usage(&args)
+        .arg(
+            Arg::new("port-forward")
+                .short('p')
+                .long("port-forward")
+                .value_name("PORT-FORWARD-OPTIONS")
+                .help("Format: remote-id:local-port:remote-port[:remote-host]"),
+        )
+        .arg(
+            Arg::new("connect")
+                .short('c')
+                .long("connect")
+                .value_name("REMOTE_ID")
+                .help("test only"),
+        )
+        .arg(Arg::new("key").short('k').long("key").global(true))
+        .arg(
+            Arg::new("server")
+                .short('s')
+                .long("server")
+                .action(ArgAction::SetTrue)
+                .help("Start server"),
+        )
+        .subcommand(
+            Command::new("connect")
+                .about("Log in to the peer, or forward a local port to it")
+                .arg(peer())
+                .arg(password())
+                .arg(
+                    Arg::new("port-forward")
+                        .long("port-forward")
+                        .value_name("LOCAL-PORT:REMOTE-PORT[:REMOTE-HOST]"),
+                ),
+        )
+        .subcommand(
+            Command::new("send-file")
+                .about("Send the file or the directory to the peer, overwriting the existing files")
+                .arg(peer())
+                .arg(password())
+                .arg(Arg::new("path").required(true))
+                .arg(Arg::new("to").required(true).help("The path on the peer")),
+        )
+        .subcommand(Command::new("get-id").about("Print the id of this device"))
+        .subcommand(
+            Command::new("set-option")
+                .about("Set the option of the service, an empty value to remove it")
+                .arg(Arg::new("key").required(true))
+                .arg(Arg::new("value").required(true)),
+        )
+        .subcommand(Command::new("list-sessions").about("List the sessions of this device"))
         .get_matches();
     use hbb_common::{config::LocalConfig, env_logger::*};
     init_from_env(Env::default().filter_or(DEFAULT_FILTER_ENV, "info"));
-    if let Some(p) = matches.value_of("port-forward") {
-        let options: Vec<String> = p.split(":").map(|x| x.to_owned()).collect();
-        if options.len() < 3 {
-            log::error!("Wrong port-forward options");
-            return;
+    let key = matches
+        .get_one::<String>("key")
+        .cloned()
+        .unwrap_or_default();
+    let token = LocalConfig::get_option("access_token");
+    let arg = |m: &clap::ArgMatches, name: &str| m.get_one::<String>(name).cloned();
+    let code = match matches.subcommand() {
+        Some(("connect", m)) => {
+            let id = arg(m, "peer").unwrap_or_default();
+            let password = arg(m, "password").unwrap_or_default();
+            if let Some(spec) = arg(m, "port-forward") {
+                match cli::parse_port_forward(&spec) {
+                    Some((port, remote_port, remote_host)) => {
+                        // Running until it's killed.
+                        cli::output(cli::start_one_port_forward(
+                            id,
+                            Some(password),
+                            port,
+                            remote_host,
+                            remote_port,
+                            key,
+                            token,
+                        ))
+                    }
+                    None => cli::output(Err((
+                        cli::EXIT_ERROR,
+                        "Wrong port-forward options".to_owned(),
+                    ))),
+                }
+            } else {
+                common::test_rendezvous_server();
+                common::test_nat_type();
+                cli::output(cli::connect(&id, password, key, token))
+            }
         }
-        let mut port = 0;
-        if let Ok(v) = options[1].parse::<i32>() {
-            port = v;
-        } else {
-            log::error!("Wrong local-port");
-            return;
+        Some(("send-file", m)) => {
+            common::test_rendezvous_server();
+            common::test_nat_type();
+            cli::output(cli::send_file(
+                &arg(m, "peer").unwrap_or_default(),
+                arg(m, "password").unwrap_or_default(),
+                &arg(m, "path").unwrap_or_default(),
+                &arg(m, "to").unwrap_or_default(),
+                key,
+                token,
+            ))
         }
-        let mut remote_port = 0;
-        if let Ok(v) = options[2].parse::<i32>() {
-            remote_port = v;
-        } else {
-            log::error!("Wrong remote-port");
-            return;
+        Some(("get-id", _)) => cli::output(cli::get_id()),
+        Some(("set-option", m)) => cli::output(cli::set_option(
+            &arg(m, "key").unwrap_or_default(),
+            &arg(m, "value").unwrap_or_default(),
+        )),
+        Some(("list-sessions", _)) => cli::output(cli::list_sessions()),
+        _ => {
+            if let Some(p) = matches.get_one::<String>("port-forward") {
+                let options: Vec<String> = p.split(":").map(|x| x.to_owned()).collect();
+                if options.len() < 3 {
+                    log::error!("Wrong port-forward options");
+                    return;
+                }
+                let Some((port, remote_port, remote_host)) =
+                    cli::parse_port_forward(&options[1..].join(":"))
+                else {
+                    log::error!("Wrong local-port or remote-port");
+                    return;
+                };
+                cli::start_one_port_forward(
+                    options[0].clone(),
+                    None,
+                    port,
+                    remote_host,
+                    remote_port,
+                    key,
+                    token,
+                )
+                .ok();
+            } else if let Some(p) = matches.get_one::<String>("connect") {
+                common::test_rendezvous_server();
+                common::test_nat_type();
+                cli::output(cli::connect(p, "".to_owned(), key, token));
+            } else if matches.get_flag("server") {
+                log::info!("id={}", hbb_common::config::Config::get_id());
+                crate::start_server(true, false);
+            }
+            cli::EXIT_OK
         }
-        let mut remote_host = "localhost".to_owned();
-        if options.len() > 3 {
-            remote_host = options[3].clone();
-        }
-        common::test_rendezvous_server();
-        common::test_nat_type();
-        let key = matches.value_of("key").unwrap_or("").to_owned();
-        let token = LocalConfig::get_option("access_token");
-        cli::start_one_port_forward(
-            options[0].clone(),
-            port,
-            remote_host,
-            remote_port,
-            key,
-            token,
-        );
-    } else if let Some(p) = matches.value_of("connect") {
-        common::test_rendezvous_server();
-        common::test_nat_type();
-        let key = matches.value_of("key").unwrap_or("").to_owned();
-        let token = LocalConfig::get_option("access_token");
-        cli::connect_test(p, key, token);
-    } else if let Some(p) = matches.value_of("server") {
-        log::info!("id={}", hbb_common::config::Config::get_id());
-        crate::start_server(true, false);
-    }
+    };
     common::global_clean();
+    std::process::exit(code);
 }
@@ -0,0 +1,137 @@
+//! The alias, note and tags the user gives to the peers, stored locally in the options of the
+//! peer config, so the peers are recognized by name instead of by ID in all the peer lists.
+//!
+//! The alias is the existing `alias` option. The tags are a JSON list in the `tags` option,
+//! their colors are shared by all the peers in the local option [`OPTION_TAG_COLORS`], a JSON
+//! map of the tag to the ARGB color. The address book keeps its own copy of them, the ui saves
+//! them here as well when they're changed in the address book.
+use hbb_common::config::{LocalConfig, PeerConfig};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+pub const OPTION_ALIAS: &str = "alias";
+pub const OPTION_NOTE: &str = "note";
+pub const OPTION_TAGS: &str = "tags";
+pub const OPTION_TAG_COLORS: &str = "peer-tag-colors";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PeerMetadata {
+    pub alias: String,
+    pub note: String,
+    pub tags: Vec<String>,
+}
+
+impl PeerMetadata {
+    pub fn from_options(options: &HashMap<String, String>) -> Self {
+        let get = |k: &str| options.get(k).cloned().unwrap_or_default();
+        Self {
+            alias: get(OPTION_ALIAS),
+            note: get(OPTION_NOTE),
+            tags: parse_tags(&get(OPTION_TAGS)),
+        }
+    }
+
+    pub fn load(id: &str) -> Self {
+        Self::from_options(&PeerConfig::load(id).options)
+    }
+
+    /// Save the metadata of the peer, the empty fields are removed.
+    pub fn store(&self, id: &str) {
+        let mut config = PeerConfig::load(id);
+        let tags = normalize_tags(self.tags.clone());
+        for (k, v) in [
+            (OPTION_ALIAS, self.alias.trim().to_owned()),
+            (OPTION_NOTE, self.note.clone()),
+            (
+                OPTION_TAGS,
+                if tags.is_empty() {
+                    "".to_owned()
+                } else {
+                    serde_json::to_string(&tags).unwrap_or_default()
+                },
+            ),
+        ] {
+            if v.is_empty() {
+                config.options.remove(k);
+            } else {
+                config.options.insert(k.to_owned(), v);
+            }
+        }
+        config.store(id);
+    }
+}
+
+/// The tags in the `tags` option, an empty or invalid one is no tags.
+pub fn parse_tags(value: &str) -> Vec<String> {
+    serde_json::from_str::<Vec<String>>(value)
+        .map(normalize_tags)
+        .unwrap_or_default()
+}
+
+// Trimmed, no empty ones or duplicates, in the order given.
+fn normalize_tags(tags: Vec<String>) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim();
+        if !tag.is_empty() && !out.iter().any(|t| t == tag) {
+            out.push(tag.to_owned());
+        }
+    }
+    out
+}
+
+pub fn set_note(id: &str, note: String) {
+    let mut metadata = PeerMetadata::load(id);
+    metadata.note = note;
+    metadata.store(id);
+}
+
+pub fn set_tags(id: &str, tags: Vec<String>) {
+    let mut metadata = PeerMetadata::load(id);
+    metadata.tags = tags;
+    metadata.store(id);
+}
+
+pub fn get_tag_colors() -> BTreeMap<String, u32> {
+    serde_json::from_str(&LocalConfig::get_option(OPTION_TAG_COLORS)).unwrap_or_default()
+}
+
+/// Set the color of the tag, `None` to use the default one.
+pub fn set_tag_color(tag: &str, color: Option<u32>) {
+    let mut colors = get_tag_colors();
+    match color {
+        Some(color) => colors.insert(tag.trim().to_owned(), color),
+        None => colors.remove(tag.trim()),
+    };
+    LocalConfig::set_option(
+        OPTION_TAG_COLORS.to_owned(),
+        if colors.is_empty() {
+            "".to_owned()
+        } else {
+            serde_json::to_string(&colors).unwrap_or_default()
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_options() {
+        let options = HashMap::from([
+            (OPTION_ALIAS.to_owned(), "Lab PC".to_owned()),
+            (
+                OPTION_TAGS.to_owned(),
+                r#"[" lab ", "gpu", "lab", ""]"#.to_owned(),
+            ),
+        ]);
+        let metadata = PeerMetadata::from_options(&options);
+        assert_eq!(metadata.alias, "Lab PC");
+        assert_eq!(metadata.note, "");
+        assert_eq!(metadata.tags, vec!["lab", "gpu"]);
+        assert!(parse_tags("").is_empty());
+        assert!(parse_tags("lab").is_empty());
+    }
+}
@@ -0,0 +1,161 @@
+//! The bytes sent to and received from each peer over the sessions, for the users on metered
+//! connections to see which peers use their quota.
+//!
+//! The bytes of a connection are added when it's recorded to the connection history, see
+//! [`crate::connection_history`], so the outgoing ones to the config of the user and the
+//! incoming ones to the config of the server. They're kept for the current month and the last
+//! one in [`FILE_NAME`], the current month becomes the last one on the first update or read in
+//! a new month.
+use hbb_common::{config::Config, log};
+use serde_derive::{Deserialize, Serialize};
+use std::{collections::HashMap, path::PathBuf, sync::Mutex, time::SystemTime};
+
+pub const FILE_NAME: &str = "peer_usage.json";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Usage {
+    pub sent: u64,
+    pub received: u64,
+}
+
+/// The usage of a peer, in the peer info JSON.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PeerUsage {
+    /// e.g. `2026-10`.
+    pub month: String,
+    pub this_month: Usage,
+    pub last_month: Usage,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Store {
+    month: String,
+    peers: HashMap<String, Usage>,
+    last_month: HashMap<String, Usage>,
+}
+
+impl Store {
+    fn roll_over(&mut self, month: &str) -> bool {
+        if self.month == month {
+            return false;
+        }
+        self.last_month = if self.month == previous_month(month) {
+            std::mem::take(&mut self.peers)
+        } else {
+            Default::default()
+        };
+        self.peers.clear();
+        self.month = month.to_owned();
+        true
+    }
+}
+
+lazy_static::lazy_static! {
+    // The store read and its modified time, it's updated by the other processes too.
+    static ref STORE: Mutex<(Option<SystemTime>, Store)> = Default::default();
+}
+
+fn path() -> PathBuf {
+    Config::path(FILE_NAME)
+}
+
+fn current_month() -> String {
+    chrono::Local::now().format("%Y-%m").to_string()
+}
+
+// `2026-01` -> `2025-12`.
+fn previous_month(month: &str) -> String {
+    let mut parts = month
+        .splitn(2, '-')
+        .map(|p| p.parse::<i32>().unwrap_or_default());
+    let (y, m) = (
+        parts.next().unwrap_or_default(),
+        parts.next().unwrap_or_default(),
+    );
+    if m <= 1 {
+        format!("{:04}-12", y - 1)
+    } else {
+        format!("{:04}-{:02}", y, m - 1)
+    }
+}
+
+fn with_store<R>(f: impl FnOnce(&mut Store) -> (R, bool)) -> R {
+    let path = path();
+    let mut lock = STORE.lock().unwrap();
+    let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok();
+    if modified.is_none() || modified != lock.0 {
+        lock.1 = std::fs::read(&path)
+            .ok()
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_default();
+        lock.0 = modified;
+    }
+    let rolled = lock.1.roll_over(&current_month());
+    let (res, changed) = f(&mut lock.1);
+    if changed || rolled {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).ok();
+        }
+        match std::fs::write(&path, serde_json::to_vec(&lock.1).unwrap_or_default()) {
+            Ok(()) => lock.0 = std::fs::metadata(&path).and_then(|m| m.modified()).ok(),
+            Err(e) => log::error!("Failed to write the peer usage: {}", e),
+        }
+    }
+    res
+}
+
+/// Add the bytes of a connection with the peer to this month.
+pub fn add(peer_id: &str, sent: u64, received: u64) {
+    if peer_id.is_empty() || sent == 0 && received == 0 {
+        return;
+    }
+    with_store(|store| {
+        let usage = store.peers.entry(peer_id.to_owned()).or_default();
+        usage.sent += sent;
+        usage.received += received;
+        ((), true)
+    })
+}
+
+pub fn get(peer_id: &str) -> PeerUsage {
+    with_store(|store| (peer_usage(store, peer_id), false))
+}
+
+fn peer_usage(store: &Store, peer_id: &str) -> PeerUsage {
+    PeerUsage {
+        month: store.month.clone(),
+        this_month: store.peers.get(peer_id).copied().unwrap_or_default(),
+        last_month: store.last_month.get(peer_id).copied().unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roll_over() {
+        assert_eq!(previous_month("2026-10"), "2026-09");
+        assert_eq!(previous_month("2026-01"), "2025-12");
+        let usage = Usage {
+            sent: 1,
+            received: 2,
+        };
+        let mut store = Store {
+            month: "2026-09".to_owned(),
+            peers: HashMap::from([("123".to_owned(), usage)]),
+            ..Default::default()
+        };
+        assert!(!store.roll_over("2026-09"));
+        assert!(store.roll_over("2026-10"));
+        assert!(store.peers.is_empty());
+        assert_eq!(peer_usage(&store, "123").last_month, usage);
+        // The last month is cleared if a month is skipped.
+        assert!(store.roll_over("2026-12"));
+        assert!(store.last_month.is_empty());
+        assert_eq!(store.month, "2026-12");
+    }
+}
@@ -0,0 +1,278 @@
+//! Forwarding of the pen and stylus of the client to the host.
+//!
+//! The client sends the position, pressure, tilt and buttons of the pen with plugin requests
+//! of the reserved id [`REQUEST_ID`]. The host injects them into a virtual pen,
+//! a synthetic pointer device on Windows and a uinput tablet on Linux,
+//! which is removed when the connection closes. macOS is not supported,
+//! it has no public API to inject tablet events.
+use serde_derive::{Deserialize, Serialize};
+
+pub const REQUEST_ID: &str = crate::common::plugin_request_id::PEN;
+/// The max pressure, the same as `POINTER_PEN_INFO`.
+pub const MAX_PRESSURE: u16 = 1024;
+/// The max tilt in degrees, the same as `POINTER_PEN_INFO`.
+pub const MAX_TILT: i8 = 90;
+// x, y, pressure, tilt_x, tilt_y, flags.
+const EVENT_LEN: usize = 4 + 4 + 2 + 1 + 1 + 1;
+
+/// The bits of [`PenEvent::flags`].
+pub mod flags {
+    /// The pen is hovering or touching the tablet.
+    pub const IN_RANGE: u8 = 0x01;
+    /// The tip or the eraser is touching the tablet.
+    pub const CONTACT: u8 = 0x02;
+    /// The barrel button is pressed.
+    pub const BARREL: u8 = 0x04;
+    /// The eraser is touching the tablet.
+    pub const ERASER: u8 = 0x08;
+    /// The pen is inverted, the eraser is facing the tablet.
+    pub const INVERTED: u8 = 0x10;
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PenEvent {
+    /// The position on the host, in the same coordinates as `MouseEvent`.
+    pub x: i32,
+    pub y: i32,
+    /// 0..=[`MAX_PRESSURE`].
+    pub pressure: u16,
+    /// -[`MAX_TILT`]..=[`MAX_TILT`], right and toward the user are positive.
+    pub tilt_x: i8,
+    pub tilt_y: i8,
+    pub flags: u8,
+}
+
+impl PenEvent {
+    #[inline]
+    pub fn has(&self, flag: u8) -> bool {
+        self.flags & flag != 0
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(EVENT_LEN);
+        data.extend(self.x.to_le_bytes());
+        data.extend(self.y.to_le_bytes());
+        data.extend(self.pressure.to_le_bytes());
+        data.push(self.tilt_x as u8);
+        data.push(self.tilt_y as u8);
+        data.push(self.flags);
+        data
+    }
+
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        if data.len() != EVENT_LEN {
+            return None;
+        }
+        let i32_at =
+            |i: usize| i32::from_le_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]);
+        let tilt = |v: u8| (v as i8).clamp(-MAX_TILT, MAX_TILT);
+        Some(Self {
+            x: i32_at(0),
+            y: i32_at(4),
+            pressure: u16::from_le_bytes([data[8], data[9]]).min(MAX_PRESSURE),
+            tilt_x: tilt(data[10]),
+            tilt_y: tilt(data[11]),
+            flags: data[12],
+        })
+    }
+}
+
+/// The virtual pen of a connection on the host, removed when dropped.
+#[cfg(any(windows, target_os = "linux"))]
+#[derive(Default)]
+pub struct VirtualPen {
+    #[cfg(windows)]
+    device: Option<windows_pen::SyntheticPen>,
+    #[cfg(target_os = "linux")]
+    uinput: Option<crate::uinput::client::UInputPen>,
+    // Do not retry after the device or the service is unavailable.
+    failed: bool,
+}
+
+#[cfg(any(windows, target_os = "linux"))]
+impl VirtualPen {
+    pub fn handle(&mut self, event: PenEvent) {
+        if self.failed {
+            return;
+        }
+        if let Err(e) = self.handle_(event) {
+            hbb_common::log::error!("Failed to inject the pen event {:?}: {}", event, e);
+            self.failed = true;
+        }
+    }
+
+    #[cfg(windows)]
+    fn handle_(&mut self, event: PenEvent) -> hbb_common::ResultType<()> {
+        if self.device.is_none() {
+            self.device = Some(windows_pen::SyntheticPen::new()?);
+            hbb_common::log::info!("Virtual pen created");
+        }
+        if let Some(device) = self.device.as_mut() {
+            crate::platform::windows::try_change_desktop();
+            device.inject(&event)?;
+        }
+        Ok(())
+    }
+
+    // The uinput device is created by the service running as root,
+    // with the range of all the displays.
+    #[cfg(target_os = "linux")]
+    fn handle_(&mut self, event: PenEvent) -> hbb_common::ResultType<()> {
+        if self.uinput.is_none() {
+            let displays = crate::display_service::try_get_displays()?;
+            let minx = displays.iter().map(|d| d.origin().0).min().unwrap_or(0);
+            let maxx = displays
+                .iter()
+                .map(|d| d.origin().0 + d.width() as i32)
+                .max()
+                .unwrap_or(0);
+            let miny = displays.iter().map(|d| d.origin().1).min().unwrap_or(0);
+            let maxy = displays
+                .iter()
+                .map(|d| d.origin().1 + d.height() as i32)
+                .max()
+                .unwrap_or(0);
+            if minx >= maxx || miny >= maxy {
+                hbb_common::bail!("No display for the pen");
+            }
+            self.uinput = Some(crate::uinput::client::UInputPen::new(
+                (minx, maxx),
+                (miny, maxy),
+            )?);
+        }
+        if let Some(uinput) = self.uinput.as_mut() {
+            uinput.send(event)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+mod windows_pen {
+    use super::{flags, PenEvent};
+    use hbb_common::ResultType;
+    use windows::Win32::{
+        Foundation::POINT,
+        UI::{
+            Controls::{
+                CreateSyntheticPointerDevice, DestroySyntheticPointerDevice,
+                InjectSyntheticPointerInput, HSYNTHETICPOINTERDEVICE, POINTER_FEEDBACK_DEFAULT,
+                POINTER_TYPE_INFO, POINTER_TYPE_INFO_0,
+            },
+            Input::Pointer::{
+                POINTER_FLAG_DOWN, POINTER_FLAG_INCONTACT, POINTER_FLAG_INRANGE, POINTER_FLAG_UP,
+                POINTER_FLAG_UPDATE, POINTER_INFO, POINTER_PEN_INFO,
+            },
+            WindowsAndMessaging::{
+                PEN_FLAG_BARREL, PEN_FLAG_ERASER, PEN_FLAG_INVERTED, PEN_MASK_PRESSURE,
+                PEN_MASK_TILT_X, PEN_MASK_TILT_Y, PT_PEN,
+            },
+        },
+    };
+
+    // https://learn.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-injectsyntheticpointerinput
+    pub struct SyntheticPen {
+        device: HSYNTHETICPOINTERDEVICE,
+        contact: bool,
+        in_range: bool,
+    }
+
+    impl SyntheticPen {
+        pub fn new() -> ResultType<Self> {
+            let device =
+                unsafe { CreateSyntheticPointerDevice(PT_PEN, 1, POINTER_FEEDBACK_DEFAULT)? };
+            Ok(Self {
+                device,
+                contact: false,
+                in_range: false,
+            })
+        }
+
+        pub fn inject(&mut self, event: &PenEvent) -> ResultType<()> {
+            let contact = event.has(flags::CONTACT);
+            let in_range = event.has(flags::IN_RANGE) || contact;
+            if !in_range && !self.in_range {
+                return Ok(());
+            }
+            // The transitions of the pointer, a release is always reported in range.
+            let pointer_flags = match (self.contact, contact) {
+                (false, true) => POINTER_FLAG_INRANGE | POINTER_FLAG_INCONTACT | POINTER_FLAG_DOWN,
+                (true, true) => POINTER_FLAG_INRANGE | POINTER_FLAG_INCONTACT | POINTER_FLAG_UPDATE,
+                (true, false) => POINTER_FLAG_INRANGE | POINTER_FLAG_UP,
+                (false, false) if in_range => POINTER_FLAG_INRANGE | POINTER_FLAG_UPDATE,
+                (false, false) => POINTER_FLAG_UPDATE,
+            };
+            let mut pen_flags = 0;
+            for (flag, pen_flag) in [
+                (flags::BARREL, PEN_FLAG_BARREL),
+                (flags::ERASER, PEN_FLAG_ERASER),
+                (flags::INVERTED, PEN_FLAG_INVERTED),
+            ] {
+                if event.has(flag) {
+                    pen_flags |= pen_flag;
+                }
+            }
+            let info = POINTER_TYPE_INFO {
+                r#type: PT_PEN,
+                Anonymous: POINTER_TYPE_INFO_0 {
+                    penInfo: POINTER_PEN_INFO {
+                        pointerInfo: POINTER_INFO {
+                            pointerType: PT_PEN,
+                            pointerFlags: pointer_flags,
+                            ptPixelLocation: POINT {
+                                x: event.x,
+                                y: event.y,
+                            },
+                            ..Default::default()
+                        },
+                        penFlags: pen_flags,
+                        penMask: PEN_MASK_PRESSURE | PEN_MASK_TILT_X | PEN_MASK_TILT_Y,
+                        pressure: if contact { event.pressure as _ } else { 0 },
+                        tiltX: event.tilt_x as _,
+                        tiltY: event.tilt_y as _,
+                        ..Default::default()
+                    },
+                },
+            };
+            unsafe { InjectSyntheticPointerInput(self.device, &[info])? };
+            self.contact = contact;
+            self.in_range = in_range;
+            Ok(())
+        }
+    }
+
+    impl Drop for SyntheticPen {
+        fn drop(&mut self) {
+            if self.contact {
+                let event = PenEvent::default();
+                self.inject(&event).ok();
+            }
+            unsafe { DestroySyntheticPointerDevice(self.device) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pen_event_codec() {
+        let event = PenEvent {
+            x: -1920,
+            y: 1080,
+            pressure: MAX_PRESSURE,
+            tilt_x: -MAX_TILT,
+            tilt_y: 30,
+            flags: flags::IN_RANGE | flags::CONTACT | flags::ERASER,
+        };
+        assert_eq!(PenEvent::decode(&event.encode()), Some(event));
+        let mut data = event.encode();
+        data[8..10].copy_from_slice(&u16::MAX.to_le_bytes());
+        data[11] = i8::MAX as u8;
+        let clamped = PenEvent::decode(&data).unwrap();
+        assert_eq!(clamped.pressure, MAX_PRESSURE);
+        assert_eq!(clamped.tilt_y, MAX_TILT);
+        assert_eq!(PenEvent::decode(&data[1..]), None);
+    }
+}
@@ -0,0 +1,81 @@
+//! Revocation of the individual permissions of a live session by the host, without closing it.
+//!
+//! The server revokes the permissions of the sessions of a peer with
+//! [`crate::server::revoke_permissions`], e.g. by the CLI `--revoke-permission`. The connection
+//! switches them off the same as the connection manager, then notifies the client with a plugin
+//! request of the reserved id [`REQUEST_ID`], for it to tell the user which permissions are
+//! revoked and why, and the connection manager to update its switches.
+use hbb_common::{bail, message_proto::Message, ResultType};
+use serde_derive::{Deserialize, Serialize};
+
+pub const REQUEST_ID: &str = crate::common::plugin_request_id::PERMISSIONS_REVOKED;
+/// The names of the permissions, the same as the ones switched in the connection manager.
+pub const PERMISSIONS: [&str; 7] = [
+    "keyboard",
+    "clipboard",
+    "audio",
+    "file",
+    "restart",
+    "recording",
+    "block_input",
+];
+// The keyboard permission controls the mouse as well.
+const ALIAS_INPUT: &str = "input";
+const ALIAS_ALL: &str = "all";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PermissionsRevoked {
+    /// The names of [`PERMISSIONS`] revoked.
+    pub permissions: Vec<String>,
+    /// The reason given by the host, may be empty.
+    pub reason: String,
+}
+
+impl PermissionsRevoked {
+    pub fn to_message(&self) -> Message {
+        crate::common::make_plugin_request(REQUEST_ID, serde_json::to_vec(self).unwrap_or_default())
+    }
+}
+
+/// Parse the comma separated names of the permissions, `input` for the keyboard
+/// and `all` for all of them.
+pub fn parse_names(names: &str) -> ResultType<Vec<String>> {
+    let mut res: Vec<String> = vec![];
+    for name in names.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+        let name = if name == ALIAS_INPUT {
+            "keyboard"
+        } else {
+            name
+        };
+        if name == ALIAS_ALL {
+            return Ok(PERMISSIONS.iter().map(|s| s.to_string()).collect());
+        }
+        if !PERMISSIONS.contains(&name) {
+            bail!("Unknown permission: {}", name);
+        }
+        if !res.iter().any(|s| s == name) {
+            res.push(name.to_owned());
+        }
+    }
+    if res.is_empty() {
+        bail!("No permission to revoke");
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_names() {
+        assert_eq!(
+            parse_names("clipboard, input,file,input").unwrap(),
+            vec!["clipboard", "keyboard", "file"]
+        );
+        assert_eq!(parse_names("file,all").unwrap().len(), PERMISSIONS.len());
+        assert!(parse_names("camera").is_err());
+        assert!(parse_names(" , ").is_err());
+    }
+}
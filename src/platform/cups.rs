@@ -0,0 +1,108 @@
+//! Print the jobs of the remote printer with CUPS on Linux and macOS.
+//!
+//! The jobs of the printer driver on the Windows host are XPS, they're converted to PDF with
+//! `xpstopdf` of libgxps, which must be installed, then printed with `lp`. The PDF jobs are
+//! printed as they are.
+use hbb_common::{bail, log, ResultType};
+use std::{path::Path, process::Command};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Pdf,
+    Xps,
+}
+
+fn format(data: &[u8]) -> Option<Format> {
+    if data.starts_with(b"%PDF-") {
+        Some(Format::Pdf)
+    } else if data.starts_with(b"PK\x03\x04") {
+        // XPS is a zip package.
+        Some(Format::Xps)
+    } else {
+        None
+    }
+}
+
+fn run(command: &mut Command) -> ResultType<String> {
+    let output = command.output()?;
+    if !output.status.success() {
+        bail!(
+            "{:?} failed: {}",
+            command.get_program(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The names of the printers of CUPS.
+pub fn get_printer_names() -> ResultType<Vec<String>> {
+    Ok(parse_printer_names(&run(Command::new("lpstat").arg("-e"))?))
+}
+
+fn parse_printer_names(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty())
+        .map(|l| l.to_owned())
+        .collect()
+}
+
+/// Print the job to the printer, the default one if the name is empty.
+pub fn send_raw_data_to_printer(printer_name: Option<String>, data: Vec<u8>) -> ResultType<()> {
+    let Some(format) = format(&data) else {
+        bail!("Unknown format of the print job");
+    };
+    let name = format!("rustdesk_print_{}", hbb_common::get_time());
+    let pdf = std::env::temp_dir().join(format!("{name}.pdf"));
+    let res = print(&name, &pdf, printer_name, format, &data);
+    std::fs::remove_file(&pdf).ok();
+    res
+}
+
+fn print(
+    name: &str,
+    pdf: &Path,
+    printer_name: Option<String>,
+    format: Format,
+    data: &[u8],
+) -> ResultType<()> {
+    if format == Format::Xps {
+        let xps = std::env::temp_dir().join(format!("{name}.xps"));
+        std::fs::write(&xps, data)?;
+        let res = run(Command::new("xpstopdf").arg(&xps).arg(pdf));
+        std::fs::remove_file(&xps).ok();
+        if let Err(e) = res {
+            bail!(
+                "Failed to convert the print job to PDF, is libgxps installed? {}",
+                e
+            );
+        }
+    } else {
+        std::fs::write(pdf, data)?;
+    }
+    let mut command = Command::new("lp");
+    if let Some(name) = printer_name.filter(|n| !n.is_empty()) {
+        command.arg("-d").arg(name);
+    }
+    let res = run(command.arg("-t").arg(crate::get_app_name()).arg(pdf))?;
+    log::info!("Print job sent: {}", res.trim());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format() {
+        assert_eq!(format(b"%PDF-1.7\n"), Some(Format::Pdf));
+        assert_eq!(format(b"PK\x03\x04\x14\x00"), Some(Format::Xps));
+        assert_eq!(format(b"\x00\x01"), None);
+        assert_eq!(
+            parse_printer_names("HP_LaserJet\n\nBrother \n"),
+            vec!["HP_LaserJet", "Brother"]
+        );
+    }
+}
@@ -62,6 +62,7 @@ extern "C" {
         screen_num: *mut c_int,
     ) -> c_int;
     fn xdo_new(display: *const c_char) -> Xdo;
+    fn xdo_move_mouse(xdo: Xdo, x: c_int, y: c_int, screen: c_int) -> c_int;
     fn xdo_get_active_window(xdo: Xdo, window: *mut *mut c_void) -> c_int;
     fn xdo_get_window_location(
         xdo: Xdo,
@@ -138,6 +139,23 @@ pub fn get_cursor_pos() -> Option<(i32, i32)> {
     res
 }
 
+// The cursor can not be moved by the clients on Wayland.
+pub fn set_cursor_pos(x: i32, y: i32) -> bool {
+    if !is_x11() {
+        return false;
+    }
+    let mut res = false;
+    XDO.with(|xdo| {
+        if let Ok(xdo) = xdo.try_borrow_mut() {
+            if xdo.is_null() {
+                return;
+            }
+            res = unsafe { xdo_move_mouse(*xdo, x, y, 0) } == 0;
+        }
+    });
+    res
+}
+
 pub fn reset_input_cache() {}
 
 pub fn get_focused_display(displays: Vec<DisplayInfo>) -> Option<usize> {
@@ -266,6 +284,12 @@ fn start_uinput_service() {
     std::thread::spawn(|| {
         service::start_service_mouse();
     });
+    std::thread::spawn(|| {
+        service::start_service_gamepad();
+    });
+    std::thread::spawn(|| {
+        service::start_service_pen();
+    });
 }
 
 /// Suggests the best terminal type based on the environment.
@@ -822,6 +846,101 @@ pub fn get_pa_sources() -> Vec<(String, String)> {
     out
 }
 
+pub fn get_pa_sinks() -> Vec<(String, String)> {
+    use pulsectl::controllers::*;
+    let mut out = Vec::new();
+    match SinkController::create() {
+        Ok(mut handler) => {
+            if let Ok(devices) = handler.list_devices() {
+                for dev in devices.clone() {
+                    out.push((
+                        dev.name.unwrap_or("".to_owned()),
+                        dev.description.unwrap_or("".to_owned()),
+                    ));
+                }
+            }
+        }
+        Err(err) => {
+            log::error!("Failed to get_pa_sinks: {:?}", err);
+        }
+    }
+    out
+}
+
+// PipeWire links a record stream to the node of a playback stream,
+// plain PulseAudio records only from the sources.
+pub fn is_pa_app_capture_supported() -> bool {
+    use pulsectl::controllers::*;
+    match SinkController::create() {
+        Ok(mut handler) => handler
+            .get_server_info()
+            .ok()
+            .and_then(|info| info.server_name)
+            .map(|name| name.contains("PipeWire"))
+            .unwrap_or(false),
+        Err(err) => {
+            log::error!("Failed to get pa server info: {:?}", err);
+            false
+        }
+    }
+}
+
+// (pid, application name, node serial or name) of the playback streams.
+fn get_pa_sink_inputs() -> Vec<(u32, String, String)> {
+    use pulsectl::controllers::*;
+    let mut out = Vec::new();
+    match SinkController::create() {
+        Ok(mut handler) => {
+            if let Ok(apps) = handler.list_applications() {
+                for app in apps {
+                    let props = &app.proplist;
+                    let Some(pid) = props
+                        .get_str("application.process.id")
+                        .and_then(|x| x.parse::<u32>().ok())
+                    else {
+                        continue;
+                    };
+                    let Some(target) = props
+                        .get_str("object.serial")
+                        .or_else(|| props.get_str("node.name"))
+                    else {
+                        continue;
+                    };
+                    let name = props
+                        .get_str("application.name")
+                        .or(app.name)
+                        .unwrap_or_default();
+                    out.push((pid, name, target));
+                }
+            }
+        }
+        Err(err) => {
+            log::error!("Failed to get_pa_sink_inputs: {:?}", err);
+        }
+    }
+    out
+}
+
+pub fn get_pa_audio_apps() -> Vec<crate::common::AudioApp> {
+    let mut apps: Vec<crate::common::AudioApp> = Vec::new();
+    for (pid, name, _) in get_pa_sink_inputs() {
+        if !apps.iter().any(|x| x.pid == pid) {
+            apps.push(crate::common::AudioApp { pid, name });
+        }
+    }
+    apps
+}
+
+// The PipeWire node to record the application, empty if it plays nothing now.
+pub fn get_pa_app_target(pid: u32) -> String {
+    get_pa_sink_inputs()
+        .drain(..)
+        .filter(|x| x.0 == pid)
+        .map(|x| x.2)
+        .next()
+        .unwrap_or("".to_owned())
+}
+
 pub fn get_default_pa_source() -> Option<(String, String)> {
     use pulsectl::controllers::*;
     match SourceController::create() {
@@ -1075,6 +1194,28 @@ pub fn current_resolution(name: &str) -> ResultType<Resolution> {
 }
 
 pub fn change_resolution_directly(name: &str, width: usize, height: usize) -> ResultType<()> {
+    // The virtual display of the headless session has only the modes of `xorg.conf`,
+    // add the one requested.
+    if super::linux_desktop_manager::is_virtual_display()
+        && !resolutions(name)
+            .iter()
+            .any(|r| r.width == width as i32 && r.height == height as i32)
+    {
+        let mode = format!("{}x{}", width, height);
+        let new_mode = Command::new("xrandr")
+            .args(super::linux_desktop_manager::new_mode_args(width, height))
+            .status()?;
+        if !new_mode.success() {
+            log::warn!("Failed to create the mode {}, it may exist", mode);
+        }
+        if !Command::new("xrandr")
+            .args(["--addmode", name, &mode])
+            .status()?
+            .success()
+        {
+            bail!("Failed to add the mode {} to {}", mode, name);
+        }
+    }
     Command::new("xrandr")
         .args(vec![
             "--output",
@@ -4,7 +4,7 @@ use crate::client::{
     LOGIN_MSG_DESKTOP_SESSION_NOT_READY, LOGIN_MSG_DESKTOP_XORG_NOT_FOUND,
     LOGIN_MSG_DESKTOP_XSESSION_FAILED,
 };
-use hbb_common::{allow_err, bail, log, rand::prelude::*, tokio::time};
+use hbb_common::{allow_err, bail, config::Config, log, rand::prelude::*, tokio::time};
 use pam;
 use std::{
     collections::HashMap,
@@ -20,6 +20,14 @@ use std::{
 };
 use users::{get_user_by_name, os::unix::UserExt, User};
 
+/// The initial size of the virtual display of the headless sessions, e.g. `2560x1440`,
+/// the client can change it with the custom resolutions during the session.
+pub const OPTION_HEADLESS_RESOLUTION: &str = "linux-headless-resolution";
+const DEFAULT_RESOLUTION: (usize, usize) = (1920, 1080);
+// The same as the `Virtual` of `res/xorg.conf`.
+const MAX_RESOLUTION: (usize, usize) = (3840, 2160);
+const XVFB: &str = "Xvfb";
+
 lazy_static::lazy_static! {
     static ref DESKTOP_RUNNING: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
     static ref DESKTOP_MANAGER: Arc<Mutex<Option<DesktopManager>>> = Arc::new(Mutex::new(None));
@@ -64,16 +72,14 @@ pub fn stop_xdesktop() {
     *DESKTOP_MANAGER.lock().unwrap() = None;
 }
 
+fn has_x_server(x_server: &str) -> bool {
+    run_cmds(&format!("which {}", x_server)).map_or(false, |output| !output.trim().is_empty())
+}
+
 fn detect_headless() -> Option<&'static str> {
-    match run_cmds(&format!("which {}", DesktopManager::get_xorg())) {
-        Ok(output) => {
-            if output.trim().is_empty() {
-                return Some(LOGIN_MSG_DESKTOP_XORG_NOT_FOUND);
-            }
-        }
-        _ => {
-            return Some(LOGIN_MSG_DESKTOP_XORG_NOT_FOUND);
-        }
+    // Xvfb is the fallback without the dummy video driver of Xorg, e.g. on the minimal cloud images.
+    if !has_x_server(DesktopManager::get_xorg()) && !has_x_server(XVFB) {
+        return Some(LOGIN_MSG_DESKTOP_XORG_NOT_FOUND);
     }
 
     match run_cmds("ls /usr/share/xsessions/") {
@@ -172,6 +178,71 @@ pub fn is_headless() -> bool {
         })
 }
 
+/// Whether the displays are the virtual one of the headless session started by us,
+/// whose resolution can be set to any size.
+pub fn is_virtual_display() -> bool {
+    DESKTOP_MANAGER
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map_or(false, |manager| {
+            manager.get_supported_display_seat0_username().is_none() && manager.is_running()
+        })
+}
+
+/// The size of the virtual display, see [`OPTION_HEADLESS_RESOLUTION`].
+fn headless_resolution() -> (usize, usize) {
+    let option = Config::get_option(OPTION_HEADLESS_RESOLUTION);
+    if option.is_empty() {
+        return DEFAULT_RESOLUTION;
+    }
+    parse_resolution(&option).unwrap_or_else(|| {
+        log::error!("Invalid {}: {}", OPTION_HEADLESS_RESOLUTION, option);
+        DEFAULT_RESOLUTION
+    })
+}
+
+fn parse_resolution(s: &str) -> Option<(usize, usize)> {
+    let (w, h) = s.trim().split_once('x')?;
+    let (w, h) = (
+        w.trim().parse::<usize>().ok()?,
+        h.trim().parse::<usize>().ok()?,
+    );
+    if (640..=MAX_RESOLUTION.0).contains(&w) && (480..=MAX_RESOLUTION.1).contains(&h) {
+        Some((w, h))
+    } else {
+        None
+    }
+}
+
+/// The arguments of `xrandr --newmode` of the mode `<width>x<height>` at 60Hz, with the rough
+/// reduced blanking timings, which are enough for the virtual displays.
+pub fn new_mode_args(width: usize, height: usize) -> Vec<String> {
+    let (h_sync_start, h_sync_end, h_total) = (width + 48, width + 80, width + 160);
+    let v_blank = (height * 28 / 1000 + 1).max(14);
+    let (v_sync_start, v_sync_end, v_total) = (height + 3, height + 8, height + v_blank);
+    let clock = (h_total * v_total * 60) as f64 / 1_000_000.0;
+    let mut args = vec!["--newmode".to_owned(), format!("{}x{}", width, height)];
+    args.push(format!("{:.2}", clock));
+    args.extend(
+        [
+            width,
+            h_sync_start,
+            h_sync_end,
+            h_total,
+            height,
+            v_sync_start,
+            v_sync_end,
+            v_total,
+        ]
+        .iter()
+        .map(|n| n.to_string()),
+    );
+    args.push("+hsync".to_owned());
+    args.push("-vsync".to_owned());
+    args
+}
+
 pub fn get_username() -> String {
     match &*DESKTOP_MANAGER.lock().unwrap() {
         Some(manager) => {
@@ -507,6 +578,7 @@ impl DesktopManager {
 
         std::env::set_var("DISPLAY", &display);
         std::env::set_var("XAUTHORITY", &xauth);
+        Self::set_x_resolution();
         // start window manager (startwm.sh)
         let child_wm = match Self::start_x_window_manager(uid, gid, &envs) {
             Ok(c) => c,
@@ -667,36 +739,72 @@ impl DesktopManager {
         envs: &HashMap<&str, String>,
     ) -> ResultType<Child> {
         let xorg = Self::get_xorg();
-        log::info!("Use xorg: {}", &xorg);
+        let mut args = vec![
+            "-noreset",
+            "+extension",
+            "GLX",
+            "+extension",
+            "RANDR",
+            "+extension",
+            "RENDER",
+        ];
         let app_name = crate::get_app_name().to_lowercase();
         let conf = format!("/etc/{app_name}/xorg.conf");
-        match Command::new(xorg)
+        let (width, height) = headless_resolution();
+        let screen = format!("{}x{}x24", width, height);
+        let x_server = if has_x_server(xorg) {
+            args.extend(["-config", conf.as_ref()]);
+            xorg
+        } else {
+            // Xvfb can't add the modes, so the screen is of the initial resolution.
+            args.extend(["-screen", "0", screen.as_ref(), "-nolisten", "tcp"]);
+            XVFB
+        };
+        args.extend(["-auth", xauth, display]);
+        log::info!("Use x server: {}", x_server);
+        match Command::new(x_server)
             .envs(envs)
             .uid(uid)
             .gid(gid)
-            .args(vec![
-                "-noreset",
-                "+extension",
-                "GLX",
-                "+extension",
-                "RANDR",
-                "+extension",
-                "RENDER",
-                "-config",
-                conf.as_ref(),
-                "-auth",
-                xauth,
-                display,
-            ])
+            .args(args)
             .spawn()
         {
             Ok(c) => Ok(c),
             Err(e) => {
-                bail!("Failed to start Xorg with display {}, {}", display, e);
+                bail!(
+                    "Failed to start {} with display {}, {}",
+                    x_server,
+                    display,
+                    e
+                );
             }
         }
     }
 
+    fn set_x_resolution() {
+        let (width, height) = headless_resolution();
+        let output = match run_cmds("xrandr --query | grep ' connected' | cut -d' ' -f1") {
+            Ok(output) => output.lines().next().unwrap_or_default().trim().to_owned(),
+            Err(e) => {
+                log::error!("Failed to query the outputs of the virtual display, {}", e);
+                return;
+            }
+        };
+        if output.is_empty() {
+            log::error!("No output of the virtual display");
+            return;
+        }
+        if let Err(e) = change_resolution_directly(&output, width, height) {
+            log::error!(
+                "Failed to set the virtual display {} to {}x{}, {}",
+                output,
+                width,
+                height,
+                e
+            );
+        }
+    }
+
     fn start_x_window_manager(
         uid: u32,
         gid: u32,
@@ -738,3 +846,19 @@ fn pam_get_service_name() -> String {
         "gdm".to_owned()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolution() {
+        assert_eq!(parse_resolution(" 2560x1440"), Some((2560, 1440)));
+        assert_eq!(parse_resolution("7680x4320"), None);
+        assert_eq!(parse_resolution("1920*1080"), None);
+        let args = new_mode_args(1920, 1080);
+        assert_eq!(args[1], "1920x1080");
+        assert_eq!(args[2], "138.65");
+        assert_eq!(args.len(), 13);
+    }
+}
@@ -44,6 +44,8 @@ extern "C" {
     fn CGSCurrentCursorSeed() -> i32;
     fn CGEventCreate(r: *const c_void) -> *const c_void;
     fn CGEventGetLocation(e: *const c_void) -> CGPoint;
+    fn CGWarpMouseCursorPosition(p: CGPoint) -> i32;
+    fn CGAssociateMouseAndMouseCursorPosition(connected: BOOL) -> i32;
     static kAXTrustedCheckOptionPrompt: CFStringRef;
     fn AXIsProcessTrustedWithOptions(options: CFDictionaryRef) -> BOOL;
     fn InputMonitoringAuthStatus(_: BOOL) -> BOOL;
@@ -304,6 +306,16 @@ fn correct_app_name(s: &str) -> String {
     s
 }
 
+/// The plists of the daemon and the agent, for the app in `/Applications`.
+pub fn service_plists() -> Option<(String, String)> {
+    let daemon = PRIVILEGES_SCRIPTS_DIR.get_file("daemon.plist")?;
+    let agent = PRIVILEGES_SCRIPTS_DIR.get_file("agent.plist")?;
+    Some((
+        daemon.contents_utf8().map(correct_app_name)?,
+        agent.contents_utf8().map(correct_app_name)?,
+    ))
+}
+
 pub fn uninstall_service(show_new_window: bool, sync: bool) -> bool {
     // to-do: do together with win/linux about refactory start/stop service
     if !is_installed_daemon(false) {
@@ -385,6 +397,18 @@ pub fn get_cursor_pos() -> Option<(i32, i32)> {
     */
 }
 
+pub fn set_cursor_pos(x: i32, y: i32) -> bool {
+    unsafe {
+        let ok = CGWarpMouseCursorPosition(CGPoint {
+            x: x as _,
+            y: y as _,
+        }) == 0;
+        // Do not suppress the mouse events for a while after warping.
+        CGAssociateMouseAndMouseCursorPosition(YES);
+        ok
+    }
+}
+
 pub fn get_focused_display(displays: Vec<DisplayInfo>) -> Option<usize> {
     autoreleasepool(|| unsafe_get_focused_display(displays))
 }
@@ -11,6 +11,9 @@ pub mod windows;
 #[cfg(windows)]
 pub mod win_device;
 
+#[cfg(windows)]
+pub mod win_audio_loopback;
+
 #[cfg(target_os = "macos")]
 pub mod macos;
 
@@ -26,6 +29,9 @@ pub mod linux_desktop_manager;
 #[cfg(target_os = "linux")]
 pub mod gtk_sudo;
 
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+pub mod cups;
+
 #[cfg(not(any(target_os = "android", target_os = "ios")))]
 use hbb_common::{
     message_proto::CursorData,
@@ -0,0 +1,294 @@
+// Capture the audio of a process tree with the WASAPI process loopback.
+// https://learn.microsoft.com/en-us/windows/win32/api/audioclientactivationparams/ns-audioclientactivationparams-audioclient_process_loopback_params
+// https://github.com/microsoft/windows-classic-samples/tree/main/Samples/ApplicationLoopback
+
+use crate::common::AudioApp;
+use hbb_common::{bail, log, ResultType};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Condvar, Mutex,
+    },
+    thread::JoinHandle,
+};
+use windows::{
+    core::{implement, Interface, Ref, HRESULT},
+    Win32::{
+        Foundation::{CloseHandle, S_OK, WAIT_OBJECT_0},
+        Media::Audio::{
+            eConsole, eRender, ActivateAudioInterfaceAsync, IActivateAudioInterfaceAsyncOperation,
+            IActivateAudioInterfaceCompletionHandler,
+            IActivateAudioInterfaceCompletionHandler_Impl, IAudioCaptureClient, IAudioClient,
+            IAudioSessionControl2, IAudioSessionManager2, IMMDeviceEnumerator, MMDeviceEnumerator,
+            AUDCLNT_BUFFERFLAGS_SILENT, AUDCLNT_SHAREMODE_SHARED,
+            AUDCLNT_STREAMFLAGS_AUTOCONVERTPCM, AUDCLNT_STREAMFLAGS_EVENTCALLBACK,
+            AUDCLNT_STREAMFLAGS_LOOPBACK, AUDIOCLIENT_ACTIVATION_TYPE_PROCESS_LOOPBACK,
+            PROCESS_LOOPBACK_MODE_INCLUDE_TARGET_PROCESS_TREE,
+            VIRTUAL_AUDIO_DEVICE_PROCESS_LOOPBACK, WAVEFORMATEX,
+        },
+        System::{
+            Com::{
+                CoCreateInstance, CoInitializeEx, CoUninitialize, CLSCTX_ALL, COINIT_MULTITHREADED,
+            },
+            Threading::{CreateEventW, WaitForSingleObject},
+        },
+    },
+};
+
+pub const SAMPLE_RATE: u32 = 48000;
+pub const CHANNELS: u16 = 2;
+// The process loopback is available since Windows 10 build 20348.
+const MIN_BUILD_NUMBER: u32 = 20348;
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 3;
+const VT_BLOB: u16 = 65;
+// 20 ms in 100 ns.
+const BUFFER_DURATION: i64 = 200_000;
+const WAIT_TIMEOUT_MS: u32 = 100;
+
+// PROPVARIANT holding a VT_BLOB, the layout of the fields read by `ActivateAudioInterfaceAsync`.
+#[repr(C)]
+struct BlobPropVariant {
+    vt: u16,
+    reserved: [u16; 3],
+    size: u32,
+    data: *const u8,
+}
+
+// AUDIOCLIENT_ACTIVATION_PARAMS with AUDIOCLIENT_PROCESS_LOOPBACK_PARAMS.
+#[repr(C)]
+struct ProcessLoopbackActivationParams {
+    activation_type: i32,
+    target_process_id: u32,
+    process_loopback_mode: i32,
+}
+
+pub fn is_supported() -> bool {
+    use winreg::{enums::HKEY_LOCAL_MACHINE, RegKey};
+    RegKey::predef(HKEY_LOCAL_MACHINE)
+        .open_subkey("SOFTWARE\\Microsoft\\Windows NT\\CurrentVersion")
+        .and_then(|key| key.get_value::<String, _>("CurrentBuildNumber"))
+        .ok()
+        .and_then(|x| x.parse::<u32>().ok())
+        .map(|x| x >= MIN_BUILD_NUMBER)
+        .unwrap_or(false)
+}
+
+/// The processes of the audio sessions on the default output device.
+pub fn get_audio_apps() -> Vec<AudioApp> {
+    let _com = ComGuard::new();
+    let pids = match unsafe { get_session_pids() } {
+        Ok(pids) => pids,
+        Err(e) => {
+            log::error!("Failed to get the audio sessions: {}", e);
+            return vec![];
+        }
+    };
+    let mut sys = hbb_common::sysinfo::System::new();
+    sys.refresh_processes();
+    let mut apps: Vec<AudioApp> = Vec::new();
+    for pid in pids {
+        if pid == 0 || apps.iter().any(|x| x.pid == pid) {
+            continue;
+        }
+        let name = sys
+            .process((pid as usize).into())
+            .map(|p| p.name().to_owned())
+            .unwrap_or_default();
+        apps.push(AudioApp { pid, name });
+    }
+    apps
+}
+
+unsafe fn get_session_pids() -> ResultType<Vec<u32>> {
+    let enumerator: IMMDeviceEnumerator = CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)?;
+    let device = enumerator.GetDefaultAudioEndpoint(eRender, eConsole)?;
+    let manager: IAudioSessionManager2 = device.Activate(CLSCTX_ALL, None)?;
+    let sessions = manager.GetSessionEnumerator()?;
+    let mut pids = Vec::new();
+    for i in 0..sessions.GetCount()? {
+        let control: IAudioSessionControl2 = sessions.GetSession(i)?.cast()?;
+        if control.IsSystemSoundsSession() == S_OK {
+            continue;
+        }
+        if let Ok(pid) = control.GetProcessId() {
+            pids.push(pid);
+        }
+    }
+    Ok(pids)
+}
+
+/// The capture of a process tree, in 48 kHz stereo f32, stopped when dropped.
+pub struct LoopbackCapture {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl LoopbackCapture {
+    pub fn start<F>(pid: u32, callback: F) -> ResultType<Self>
+    where
+        F: FnMut(&[f32]) + Send + 'static,
+    {
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_cloned = stop.clone();
+        let (tx, rx) = mpsc::channel();
+        let thread = std::thread::spawn(move || {
+            let _com = ComGuard::new();
+            let client = match unsafe { activate(pid) } {
+                Ok(client) => {
+                    tx.send(Ok(())).ok();
+                    client
+                }
+                Err(e) => {
+                    tx.send(Err(e)).ok();
+                    return;
+                }
+            };
+            if let Err(e) = unsafe { capture(client, stop_cloned, callback) } {
+                log::error!("Process loopback capture of {} stopped: {}", pid, e);
+            }
+        });
+        match rx.recv() {
+            Ok(Ok(())) => {
+                log::info!("Process loopback capture of {} started", pid);
+                Ok(Self {
+                    stop,
+                    thread: Some(thread),
+                })
+            }
+            Ok(Err(e)) => Err(e),
+            Err(_) => bail!("Process loopback capture thread exited"),
+        }
+    }
+}
+
+impl Drop for LoopbackCapture {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            thread.join().ok();
+        }
+    }
+}
+
+struct ComGuard(bool);
+
+impl ComGuard {
+    fn new() -> Self {
+        Self(unsafe { CoInitializeEx(None, COINIT_MULTITHREADED) }.is_ok())
+    }
+}
+
+impl Drop for ComGuard {
+    fn drop(&mut self) {
+        if self.0 {
+            unsafe { CoUninitialize() };
+        }
+    }
+}
+
+#[implement(IActivateAudioInterfaceCompletionHandler)]
+struct ActivateHandler(Arc<(Mutex<bool>, Condvar)>);
+
+impl IActivateAudioInterfaceCompletionHandler_Impl for ActivateHandler_Impl {
+    fn ActivateCompleted(
+        &self,
+        _operation: Ref<'_, IActivateAudioInterfaceAsyncOperation>,
+    ) -> windows::core::Result<()> {
+        let (lock, cvar) = &*self.0;
+        *lock.lock().unwrap() = true;
+        cvar.notify_all();
+        Ok(())
+    }
+}
+
+unsafe fn activate(pid: u32) -> ResultType<IAudioClient> {
+    let params = ProcessLoopbackActivationParams {
+        activation_type: AUDIOCLIENT_ACTIVATION_TYPE_PROCESS_LOOPBACK.0,
+        target_process_id: pid,
+        process_loopback_mode: PROCESS_LOOPBACK_MODE_INCLUDE_TARGET_PROCESS_TREE.0,
+    };
+    let prop = BlobPropVariant {
+        vt: VT_BLOB,
+        reserved: [0; 3],
+        size: std::mem::size_of::<ProcessLoopbackActivationParams>() as _,
+        data: &params as *const _ as *const u8,
+    };
+    let done = Arc::new((Mutex::new(false), Condvar::new()));
+    let handler: IActivateAudioInterfaceCompletionHandler = ActivateHandler(done.clone()).into();
+    let operation = ActivateAudioInterfaceAsync(
+        VIRTUAL_AUDIO_DEVICE_PROCESS_LOOPBACK,
+        &IAudioClient::IID,
+        Some(&prop as *const BlobPropVariant as *const _),
+        &handler,
+    )?;
+    let (lock, cvar) = &*done;
+    let mut completed = lock.lock().unwrap();
+    while !*completed {
+        completed = cvar.wait(completed).unwrap();
+    }
+    let mut hr = HRESULT(0);
+    let mut interface = None;
+    operation.GetActivateResult(&mut hr, &mut interface)?;
+    hr.ok()?;
+    let Some(interface) = interface else {
+        bail!("No audio client activated for process {}", pid);
+    };
+    let client: IAudioClient = interface.cast()?;
+    // The process loopback client has no mix format, the format is converted to this one.
+    let format = WAVEFORMATEX {
+        wFormatTag: WAVE_FORMAT_IEEE_FLOAT,
+        nChannels: CHANNELS,
+        nSamplesPerSec: SAMPLE_RATE,
+        nAvgBytesPerSec: SAMPLE_RATE * CHANNELS as u32 * 4,
+        nBlockAlign: CHANNELS * 4,
+        wBitsPerSample: 32,
+        cbSize: 0,
+    };
+    client.Initialize(
+        AUDCLNT_SHAREMODE_SHARED,
+        AUDCLNT_STREAMFLAGS_LOOPBACK
+            | AUDCLNT_STREAMFLAGS_EVENTCALLBACK
+            | AUDCLNT_STREAMFLAGS_AUTOCONVERTPCM,
+        BUFFER_DURATION,
+        0,
+        &format,
+        None,
+    )?;
+    Ok(client)
+}
+
+unsafe fn capture<F>(client: IAudioClient, stop: Arc<AtomicBool>, mut callback: F) -> ResultType<()>
+where
+    F: FnMut(&[f32]),
+{
+    let event = CreateEventW(None, false, false, None)?;
+    let res = (|| -> ResultType<()> {
+        client.SetEventHandle(event)?;
+        let capture: IAudioCaptureClient = client.GetService()?;
+        client.Start()?;
+        let mut silence = Vec::new();
+        while !stop.load(Ordering::SeqCst) {
+            if WaitForSingleObject(event, WAIT_TIMEOUT_MS) != WAIT_OBJECT_0 {
+                continue;
+            }
+            while capture.GetNextPacketSize()? > 0 {
+                let mut data = std::ptr::null_mut();
+                let mut frames = 0;
+                let mut flags = 0;
+                capture.GetBuffer(&mut data, &mut frames, &mut flags, None, None)?;
+                let len = frames as usize * CHANNELS as usize;
+                if flags & AUDCLNT_BUFFERFLAGS_SILENT.0 as u32 != 0 || data.is_null() {
+                    silence.resize(len, 0.);
+                    callback(&silence);
+                } else {
+                    callback(std::slice::from_raw_parts(data as *const f32, len));
+                }
+                capture.ReleaseBuffer(frames)?;
+            }
+        }
+        client.Stop()?;
+        Ok(())
+    })();
+    CloseHandle(event).ok();
+    res
+}
@@ -125,6 +125,10 @@ pub fn get_cursor_pos() -> Option<(i32, i32)> {
     }
 }
 
+pub fn set_cursor_pos(x: i32, y: i32) -> bool {
+    unsafe { SetCursorPos(x, y) != FALSE }
+}
+
 pub fn reset_input_cache() {}
 
 pub fn get_cursor() -> ResultType<Option<u64>> {
@@ -977,7 +981,7 @@ fn get_current_session_username() -> Option<String> {
     Some(get_session_username(sid))
 }
 
-fn get_session_username(session_id: u32) -> String {
+pub fn get_session_username(session_id: u32) -> String {
     extern "C" {
         fn get_session_user_info(path: *mut u16, n: u32, session_id: u32) -> u32;
     }
@@ -995,7 +999,8 @@ fn get_session_username(session_id: u32) -> String {
         .to_owned()
 }
 
-pub fn get_available_sessions(name: bool) -> Vec<WindowsSession> {
+/// The ids and the station names of the sessions, the physical console first.
+pub fn get_session_stations() -> Vec<(u32, String)> {
     extern "C" {
         fn get_available_session_ids(buf: *mut wchar_t, buf_size: c_int, include_rdp: bool);
     }
@@ -1007,51 +1012,47 @@ pub fn get_available_sessions(name: bool) -> Vec<WindowsSession> {
         let session_ids = String::from_utf16_lossy(&buf);
         session_ids.trim_matches(char::from(0)).trim().to_string()
     };
-    let mut v: Vec<WindowsSession> = vec![];
+    let mut v: Vec<(u32, String)> = vec![];
     // https://learn.microsoft.com/en-us/windows/win32/api/winbase/nf-winbase-wtsgetactiveconsolesessionid
     let physical_console_sid = unsafe { get_current_session(FALSE) };
     if physical_console_sid != u32::MAX {
-        let physical_console_name = if name {
-            let physical_console_username = get_session_username(physical_console_sid);
-            if physical_console_username.is_empty() {
-                "Console".to_owned()
-            } else {
-                format!("Console: {physical_console_username}")
-            }
-        } else {
-            "".to_owned()
-        };
-        v.push(WindowsSession {
-            sid: physical_console_sid,
-            name: physical_console_name,
-            ..Default::default()
-        });
+        v.push((physical_console_sid, "Console".to_owned()));
     }
     // https://learn.microsoft.com/en-us/previous-versions//cc722458(v=technet.10)?redirectedfrom=MSDN
     for type_session_id in station_session_id_array.split(",") {
         let split: Vec<_> = type_session_id.split(":").collect();
         if split.len() == 2 {
             if let Ok(sid) = split[1].parse::<u32>() {
-                if !v.iter().any(|e| (*e).sid == sid) {
-                    let name = if name {
-                        let name = get_session_username(sid);
-                        if name.is_empty() {
-                            split[0].to_string()
-                        } else {
-                            format!("{}: {}", split[0], name)
-                        }
-                    } else {
-                        "".to_owned()
-                    };
-                    v.push(WindowsSession {
-                        sid,
-                        name,
-                        ..Default::default()
-                    });
+                if !v.iter().any(|e| e.0 == sid) {
+                    v.push((sid, split[0].to_string()));
                 }
             }
         }
     }
+    v
+}
+
+pub fn get_available_sessions(name: bool) -> Vec<WindowsSession> {
+    let mut v: Vec<WindowsSession> = get_session_stations()
+        .into_iter()
+        .map(|(sid, station)| {
+            let name = if name {
+                let username = get_session_username(sid);
+                if username.is_empty() {
+                    station
+                } else {
+                    format!("{station}: {username}")
+                }
+            } else {
+                "".to_owned()
+            };
+            WindowsSession {
+                sid,
+                name,
+                ..Default::default()
+            }
+        })
+        .collect();
     if name {
         let mut name_count: HashMap<String, usize> = HashMap::new();
         for session in &v {
@@ -13,6 +13,9 @@ pub(super) fn ext_support_callback(
 ) -> PluginReturn {
     match &msg.r#type as _ {
         EXT_SUPPORT_BLOCK_INPUT => {
+            if let Err(ret) = permission::check(id, permission::PERMISSION_INPUT) {
+                return ret;
+            }
             // let supported_plugins = [];
             // let supported = supported_plugins.contains(&id);
             let supported = true;
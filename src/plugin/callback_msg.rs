@@ -20,6 +20,7 @@ const MSG_TO_PEER_TARGET: &str = "peer";
 const MSG_TO_UI_TARGET: &str = "ui";
 const MSG_TO_CONFIG_TARGET: &str = "config";
 const MSG_TO_EXT_SUPPORT_TARGET: &str = "ext-support";
+const MSG_TO_METRICS_TARGET: &str = "metrics";
 
 const MSG_TO_RUSTDESK_SIGNATURE_VERIFICATION: &str = "signature_verification";
 
@@ -120,7 +121,7 @@ macro_rules! early_return_value {
 /// peer, target, id are utf8 strings(null terminated).
 ///
 /// peer:    The peer id.
-/// target:  "peer" or "ui".
+/// target:  "peer", "ui", "config", "ext-support", "metrics" or "rustdesk".
 /// id:      The id of this plugin.
 /// content: The content.
 /// len:     The length of the content.
@@ -141,24 +142,39 @@ pub(super) extern "C" fn cb_msg(
 
     match &target as _ {
         MSG_TO_PEER_TARGET => {
+            if let Err(ret) = permission::check(&id, permission::PERMISSION_NETWORK) {
+                return ret;
+            }
             cb_msg_field!(peer);
+            if let Err(ret) = super::peer_msg::check_outgoing(&id, &peer, len) {
+                return ret;
+            }
+            let content_slice = unsafe { std::slice::from_raw_parts(content as *const u8, len) };
+            let request = PluginRequest {
+                id,
+                content: bytes::Bytes::from(content_slice.to_vec()),
+                ..Default::default()
+            };
+            // The controlling side sends by the session, the controlled side by the connection.
             if let Some(session) = SESSIONS.write().unwrap().get_mut(&peer) {
-                let content_slice =
-                    unsafe { std::slice::from_raw_parts(content as *const u8, len) };
-                let content_vec = Vec::from(content_slice);
-                let request = PluginRequest {
-                    id,
-                    content: bytes::Bytes::from(content_vec),
-                    ..Default::default()
-                };
                 session.send_plugin_request(request);
-                PluginReturn::success()
-            } else {
-                PluginReturn::new(
-                    errno::ERR_CALLBACK_PEER_NOT_FOUND,
-                    &format!("Failed to find session for peer '{}'", peer),
-                )
+                return PluginReturn::success();
             }
+            #[cfg(not(any(target_os = "android", target_os = "ios")))]
+            {
+                use hbb_common::message_proto::{Message, Misc};
+                let mut misc = Misc::new();
+                misc.set_plugin_request(request);
+                let mut msg = Message::new();
+                msg.set_misc(misc);
+                if crate::server::plugin_send_to_peer(&peer, msg) {
+                    return PluginReturn::success();
+                }
+            }
+            PluginReturn::new(
+                errno::ERR_CALLBACK_PEER_NOT_FOUND,
+                &format!("Failed to find session or connection for peer '{}'", peer),
+            )
         }
         MSG_TO_UI_TARGET => {
             cb_msg_field!(peer);
@@ -228,6 +244,20 @@ pub(super) extern "C" fn cb_msg(
             );
             super::callback_ext::ext_support_callback(&id, &peer, &msg)
         }
+        MSG_TO_METRICS_TARGET => {
+            let s = early_return_value!(
+                std::str::from_utf8(unsafe { std::slice::from_raw_parts(content as _, len) }),
+                ERR_CALLBACK_INVALID_MSG,
+                "parse msg string"
+            );
+            let msg = early_return_value!(
+                serde_json::from_str::<super::metrics::MsgToMetrics>(s),
+                ERR_CALLBACK_INVALID_MSG,
+                "parse msg '{}'",
+                s
+            );
+            super::metrics::report(&id, msg)
+        }
         MSG_TO_RUSTDESK_TARGET => handle_msg_to_rustdesk(id, content, len),
         _ => PluginReturn::new(
             errno::ERR_CALLBACK_TARGET,
@@ -369,7 +399,7 @@ fn push_event_to_ui(channel: u16, peer: &str, content: &str) {
     m.insert("peer", &peer);
     m.insert("content", &content);
     let event = serde_json::to_string(&m).unwrap_or("".to_string());
-    super::unity::notify_plugin_event(&event);
+    super::unity::notify_plugin_event(peer, &event);
     // Send to main and cm
     for (k, v) in MSG_TO_UI_FLUTTER_CHANNELS.iter() {
         if channel & k != 0 {
@@ -385,6 +415,20 @@ fn push_event_to_ui(channel: u16, peer: &str, content: &str) {
     }
 }
 
+/// Notify the embedder that a peer option of the plugin is changed.
+pub(super) fn notify_peer_option_changed(id: &str, peer: &str, key: &str, value: &str) {
+    let m = HashMap::from([
+        ("name", MSG_TO_UI_TYPE_PLUGIN_OPTION),
+        ("id", id),
+        ("peer", peer),
+        ("key", key),
+        ("value", value),
+    ]);
+    if let Ok(event) = serde_json::to_string(&m) {
+        super::unity::notify_option_event(peer, &event);
+    }
+}
+
 fn push_option_to_ui(channel: u16, id: &str, peer: &str, msg: &MsgToConfig, ui: &ConfigToUi) {
     let v = [
         ("id", id),
@@ -397,7 +441,10 @@ fn push_option_to_ui(channel: u16, id: &str, peer: &str, msg: &MsgToConfig, ui:
     let mut m = HashMap::from(v);
     m.insert("name", MSG_TO_UI_TYPE_PLUGIN_OPTION);
     let event = serde_json::to_string(&m).unwrap_or("".to_string());
-    super::unity::notify_option_event(&event);
+    // The peer options are notified in `PeerConfig::set()`.
+    if peer.is_empty() {
+        super::unity::notify_option_event("", &event);
+    }
     for (k, v) in MSG_TO_UI_FLUTTER_CHANNELS.iter() {
         if channel & k != 0 {
             let _res = flutter::push_global_event(v as _, event.to_string());
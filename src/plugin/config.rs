@@ -1,5 +1,11 @@
 use super::{cstr_to_string, str_to_cstr_ret};
-use hbb_common::{allow_err, bail, config::Config as HbbConfig, lazy_static, log, ResultType};
+use hbb_common::{
+    allow_err, bail,
+    config::{Config as HbbConfig, PeerConfig as HbbPeerConfig},
+    lazy_static, log,
+    rendezvous_proto::ConnType,
+    ResultType,
+};
 use serde_derive::{Deserialize, Serialize};
 use std::{
     collections::HashMap,
@@ -133,6 +139,8 @@ impl SharedConfig {
     }
 }
 
+// The peer options are stored in the config of the peer, with the keys prefixed by the plugin id.
+// The files in the plugin dir are only read, they were used by the older versions.
 impl PeerConfig {
     #[inline]
     fn path(id: &str, peer: &str) -> PathBuf {
@@ -141,6 +149,25 @@ impl PeerConfig {
             .join(format!("{}.toml", peer))
     }
 
+    #[inline]
+    fn option_prefix(id: &str) -> String {
+        format!("plugin:{}:", id)
+    }
+
+    fn store_peer_option(peer: &str, key: String, value: String) {
+        // The session saves its own copy of the peer config, update the copy if it exists.
+        if let Some(session) = crate::flutter::sessions::get_session_by_peer_id(
+            peer.to_owned(),
+            ConnType::DEFAULT_CONN,
+        ) {
+            session.lc.write().unwrap().set_option(key, value);
+        } else {
+            let mut config = HbbPeerConfig::load(peer);
+            config.options.insert(key, value);
+            config.store(peer);
+        }
+    }
+
     #[inline]
     fn load(id: &str, peer: &str) {
         let mut lock = CONFIG_PEERS.lock().unwrap();
@@ -152,6 +179,12 @@ impl PeerConfig {
 
         let conf = hbb_common::config::load_path::<HashMap<String, String>>(Self::path(id, peer));
         let mut conf = PeerConfig(conf);
+        let prefix = Self::option_prefix(id);
+        for (k, v) in HbbPeerConfig::load(peer).options.iter() {
+            if let Some(key) = k.strip_prefix(&prefix) {
+                conf.insert(key.to_owned(), v.to_owned());
+            }
+        }
         if let Some(desc_conf) = super::plugins::get_desc_conf(id) {
             for item in desc_conf.peer.iter() {
                 if !conf.contains_key(&item.key) {
@@ -192,14 +225,25 @@ impl PeerConfig {
             .map(|s| s.to_owned())
     }
 
-    #[inline]
+    /// Set the option of the plugin for the peer, a `plugin_option` event with the peer id is sent if it is changed.
     pub fn set(id: &str, peer: &str, key: &str, value: &str) -> ResultType<()> {
         Self::load_if_not_exists(id, peer);
-        match CONFIG_PEERS.lock().unwrap().get_mut(id) {
+        let mut lock = CONFIG_PEERS.lock().unwrap();
+        match lock.get_mut(id) {
             Some(peers) => match peers.get_mut(peer) {
                 Some(config) => {
+                    if config.get(key).map(|v| v.as_str()) == Some(value) {
+                        return Ok(());
+                    }
                     config.insert(key.to_owned(), value.to_owned());
-                    hbb_common::config::store_path(Self::path(id, peer), config)
+                    drop(lock);
+                    Self::store_peer_option(
+                        peer,
+                        format!("{}{}", Self::option_prefix(id), key),
+                        value.to_owned(),
+                    );
+                    super::callback_msg::notify_peer_option_changed(id, peer, key, value);
+                    Ok(())
                 }
                 None => {
                     // unreachable
@@ -214,9 +258,15 @@ impl PeerConfig {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct PluginStatus {
     pub enabled: bool,
+    // Load the plugin in a helper process.
+    #[serde(default)]
+    pub isolated: bool,
+    // Restart the isolated plugin if its helper process crashes.
+    #[serde(default)]
+    pub auto_restart: bool,
 }
 
 const MANAGER_VERSION: &str = "0.1.0";
@@ -277,20 +327,38 @@ impl ManagerConfig {
                     .to_string();
                 Some(enabled)
             }
+            "isolated" => Some(
+                lock.plugins
+                    .get(id)
+                    .map_or(false, |status| status.isolated)
+                    .to_string(),
+            ),
+            "auto_restart" => Some(
+                lock.plugins
+                    .get(id)
+                    .map_or(false, |status| status.auto_restart)
+                    .to_string(),
+            ),
             _ => None,
         }
     }
 
-    fn set_plugin_option_enabled(id: &str, enabled: bool) -> ResultType<()> {
+    fn set_plugin_status(id: &str, f: impl FnOnce(&mut PluginStatus)) -> ResultType<()> {
         let mut lock = CONFIG_MANAGER.lock().unwrap();
-        if let Some(status) = lock.plugins.get_mut(id) {
-            status.enabled = enabled;
-        } else {
-            lock.plugins.insert(id.to_owned(), PluginStatus { enabled });
-        }
+        f(lock
+            .plugins
+            .entry(id.to_owned())
+            .or_insert_with(|| PluginStatus {
+                enabled: true,
+                ..Default::default()
+            }));
         hbb_common::config::store_path(Self::path(), &*lock)
     }
 
+    fn set_plugin_option_enabled(id: &str, enabled: bool) -> ResultType<()> {
+        Self::set_plugin_status(id, |status| status.enabled = enabled)
+    }
+
     pub fn set_plugin_option(id: &str, key: &str, value: &str) {
         match key {
             "enabled" => {
@@ -302,6 +370,18 @@ impl ManagerConfig {
                     super::unload_plugin(id);
                 }
             }
+            "isolated" => {
+                let isolated = bool::from_str(value).unwrap_or(false);
+                allow_err!(Self::set_plugin_status(id, |status| status.isolated = isolated));
+                // Reload the loaded plugin in the new mode.
+                if super::plugins::get_version(id).is_some() {
+                    allow_err!(super::reload_plugin(id));
+                }
+            }
+            "auto_restart" => {
+                let auto_restart = bool::from_str(value).unwrap_or(false);
+                allow_err!(Self::set_plugin_status(id, |status| status.auto_restart = auto_restart));
+            }
             _ => log::error!("No such option {}", key),
         }
     }
@@ -309,8 +389,7 @@ impl ManagerConfig {
     #[inline]
     pub fn add_plugin(id: &str) -> ResultType<()> {
         let mut lock = CONFIG_MANAGER.lock().unwrap();
-        lock.plugins
-            .insert(id.to_owned(), PluginStatus { enabled: true });
+        lock.plugins.entry(id.to_owned()).or_default().enabled = true;
         hbb_common::config::store_path(Self::path(), &*lock)
     }
 
@@ -0,0 +1,159 @@
+//! Plugin dependencies and host version requirements.
+use super::desc::Desc;
+use std::collections::{HashMap, HashSet};
+
+type Version = (u64, u64, u64);
+
+/// Parse "1", "1.2", "1.2.3" or "1.2.3-beta", the missing parts are 0.
+fn parse_version(v: &str) -> Option<Version> {
+    let v = v.trim().trim_start_matches('v');
+    let v = v.split(|c| c == '-' || c == '+').next()?;
+    let mut parts = v.split('.').map(|p| p.trim().parse::<u64>());
+    let major = parts.next()?.ok()?;
+    let minor = parts.next().unwrap_or(Ok(0)).ok()?;
+    let patch = parts.next().unwrap_or(Ok(0)).ok()?;
+    Some((major, minor, patch))
+}
+
+fn comparator_matches(version: Version, comparator: &str) -> Option<bool> {
+    let comparator = comparator.trim();
+    if comparator.is_empty() || comparator == "*" {
+        return Some(true);
+    }
+    let (op, v) = [">=", "<=", ">", "<", "=", "^", "~"]
+        .iter()
+        .find_map(|op| comparator.strip_prefix(op).map(|v| (*op, v)))
+        .unwrap_or(("=", comparator));
+    let target = parse_version(v)?;
+    Some(match op {
+        ">=" => version >= target,
+        "<=" => version <= target,
+        ">" => version > target,
+        "<" => version < target,
+        "^" => {
+            let upper = if target.0 > 0 {
+                (target.0 + 1, 0, 0)
+            } else {
+                (0, target.1 + 1, 0)
+            };
+            version >= target && version < upper
+        }
+        "~" => version >= target && version < (target.0, target.1 + 1, 0),
+        _ => version == target,
+    })
+}
+
+/// Whether `version` is in `range`, the comparators of the range are separated by "," or spaces.
+pub(super) fn version_matches(version: &str, range: &str) -> bool {
+    let Some(version) = parse_version(version) else {
+        return false;
+    };
+    range
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|c| !c.is_empty())
+        // ">= 1.2" is also accepted
+        .fold(Vec::<String>::new(), |mut acc, c| {
+            match acc.last_mut() {
+                Some(last) if last.chars().all(|c| "<>=^~".contains(c)) => last.push_str(c),
+                _ => acc.push(c.to_owned()),
+            }
+            acc
+        })
+        .iter()
+        .all(|c| comparator_matches(version, c).unwrap_or(false))
+}
+
+/// Check the host version and the requirements of `desc`.
+///
+/// `loaded_version` returns the version of a loaded plugin.
+pub(super) fn check(
+    desc: &Desc,
+    loaded_version: impl Fn(&str) -> Option<String>,
+) -> Result<(), String> {
+    let min_host_version = desc.min_host_version();
+    if !min_host_version.is_empty()
+        && !version_matches(crate::VERSION, &format!(">={}", min_host_version))
+    {
+        return Err(format!(
+            "requires RustDesk {} or later, current version is {}",
+            min_host_version,
+            crate::VERSION
+        ));
+    }
+    for req in desc.requires() {
+        match loaded_version(&req.id) {
+            Some(version) => {
+                if !version_matches(&version, &req.version) {
+                    return Err(format!(
+                        "requires plugin {} {}, but {} is loaded",
+                        req.id, req.version, version
+                    ));
+                }
+            }
+            None => {
+                return Err(format!("requires plugin {} which is not loaded", req.id));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Order the plugins so that every plugin comes after the plugins it requires.
+///
+/// Returns the indexes in load order, and the indexes of the plugins in a dependency cycle.
+/// Requirements not in `descs` are left to [`check`].
+pub(super) fn load_order(descs: &[&Desc]) -> (Vec<usize>, Vec<usize>) {
+    let index: HashMap<&str, usize> = descs
+        .iter()
+        .enumerate()
+        .map(|(i, d)| (d.meta().id.as_str(), i))
+        .collect();
+    let mut in_degree = vec![0usize; descs.len()];
+    let mut dependents = vec![Vec::new(); descs.len()];
+    for (i, desc) in descs.iter().enumerate() {
+        let deps: HashSet<usize> = desc
+            .requires()
+            .iter()
+            .filter_map(|r| index.get(r.id.as_str()).cloned())
+            .collect();
+        in_degree[i] = deps.len();
+        for dep in deps {
+            dependents[dep].push(i);
+        }
+    }
+    let mut order = Vec::with_capacity(descs.len());
+    let mut ready: Vec<usize> = (0..descs.len()).filter(|i| in_degree[*i] == 0).collect();
+    while let Some(i) = ready.pop() {
+        order.push(i);
+        for d in dependents[i].iter() {
+            in_degree[*d] -= 1;
+            if in_degree[*d] == 0 {
+                ready.push(*d);
+            }
+        }
+    }
+    let cyclic = (0..descs.len()).filter(|i| in_degree[*i] > 0).collect();
+    (order, cyclic)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_matches() {
+        assert!(version_matches("1.2.3", ""));
+        assert!(version_matches("1.2.3", "*"));
+        assert!(version_matches("1.2.3", "1.2.3"));
+        assert!(version_matches("1.2.3", ">=1.2, <2"));
+        assert!(version_matches("1.2.3", ">= 1.2.0 < 2.0.0"));
+        assert!(!version_matches("2.0.0", ">=1.2, <2"));
+        assert!(version_matches("1.9.0", "^1.2.3"));
+        assert!(!version_matches("2.0.0", "^1.2.3"));
+        assert!(!version_matches("0.3.0", "^0.2.1"));
+        assert!(version_matches("1.2.9", "~1.2.3"));
+        assert!(!version_matches("1.3.0", "~1.2.3"));
+        assert!(version_matches("1.4.4-beta", ">=1.4.4"));
+        assert!(!version_matches("abc", "*"));
+    }
+}
@@ -6,19 +6,19 @@ use std::ffi::{c_char, CStr};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UiButton {
-    key: String,
-    text: String,
-    icon: String, // icon can be int in flutter, but string in other ui framework. And it is flexible to use string.
-    tooltip: String,
-    action: String, // The action to be triggered when the button is clicked.
+    pub key: String,
+    pub text: String,
+    pub icon: String, // icon can be int in flutter, but string in other ui framework. And it is flexible to use string.
+    pub tooltip: String,
+    pub action: String, // The action to be triggered when the button is clicked.
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UiCheckbox {
-    key: String,
-    text: String,
-    tooltip: String,
-    action: String, // The action to be triggered when the checkbox is checked or unchecked.
+    pub key: String,
+    pub text: String,
+    pub tooltip: String,
+    pub action: String, // The action to be triggered when the checkbox is checked or unchecked.
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -28,6 +28,19 @@ pub enum UiType {
     Checkbox(UiCheckbox),
 }
 
+/// The range of the UI schema versions the plugin supports, see [`super::ui_schema`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UiSchema {
+    pub min: u32,
+    pub max: u32,
+}
+
+impl Default for UiSchema {
+    fn default() -> Self {
+        Self { min: 1, max: 1 }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Location {
     pub ui: HashMap<String, Vec<UiType>>,
@@ -67,13 +80,37 @@ pub struct Meta {
     pub publish_info: PublishInfo,
 }
 
+/// A plugin this plugin depends on.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Requirement {
+    pub id: String,
+    /// Version range, e.g. ">=1.2.0, <2.0.0", "^1.2.0", "~1.2.0". Empty or "*" means any version.
+    #[serde(default)]
+    pub version: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Desc {
     meta: Meta,
     need_reboot: bool,
     location: Location,
+    /// The plugins without it support the first version only.
+    #[serde(default)]
+    ui_schema: UiSchema,
     config: Config,
     listen_events: Vec<String>,
+    #[serde(default)]
+    requires: Vec<Requirement>,
+    /// The minimum RustDesk version, empty means any version.
+    #[serde(default)]
+    min_host_version: String,
+    /// The capabilities the plugin is allowed to use, see [`super::permission`].
+    /// `None` for the plugins without a permission manifest, they are not restricted.
+    #[serde(default)]
+    permissions: Option<Vec<String>>,
+    /// The translations of the UI strings, locale -> key -> text, see [`super::lang`].
+    #[serde(default)]
+    translations: HashMap<String, HashMap<String, String>>,
 }
 
 impl Desc {
@@ -90,6 +127,10 @@ impl Desc {
         &self.location
     }
 
+    pub fn ui_schema(&self) -> &UiSchema {
+        &self.ui_schema
+    }
+
     pub fn config(&self) -> &Config {
         &self.config
     }
@@ -97,4 +138,20 @@ impl Desc {
     pub fn listen_events(&self) -> &Vec<String> {
         &self.listen_events
     }
+
+    pub fn requires(&self) -> &Vec<Requirement> {
+        &self.requires
+    }
+
+    pub fn min_host_version(&self) -> &str {
+        &self.min_host_version
+    }
+
+    pub fn permissions(&self) -> Option<&Vec<String>> {
+        self.permissions.as_ref()
+    }
+
+    pub fn translations(&self) -> &HashMap<String, HashMap<String, String>> {
+        &self.translations
+    }
 }
@@ -0,0 +1,122 @@
+//! Dispatch the events to the plugins on their own worker threads.
+//!
+//! The events come from the session threads, a slow plugin must not stall the stream.
+//! Each plugin has a bounded queue, the oldest events are dropped when it is full,
+//! and a `plugin_overload` manager event is sent.
+use super::plugins;
+use hbb_common::log;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Condvar, Mutex},
+    time::{Duration, Instant},
+};
+
+const QUEUE_CAPACITY: usize = 64;
+// At most one overload event of a plugin in this interval.
+const OVERLOAD_EVENT_INTERVAL: Duration = Duration::from_secs(1);
+
+struct Task {
+    method: &'static [u8],
+    // null terminated
+    peer: String,
+    event: Vec<u8>,
+}
+
+#[derive(Default)]
+struct Queue {
+    tasks: VecDeque<Task>,
+    closed: bool,
+    dropped: usize,
+    last_overload_event: Option<Instant>,
+}
+
+type Worker = Arc<(Mutex<Queue>, Condvar)>;
+
+lazy_static::lazy_static! {
+    static ref WORKERS: Mutex<HashMap<String, Worker>> = Default::default();
+}
+
+fn get_worker(id: &str) -> Worker {
+    let mut workers = WORKERS.lock().unwrap();
+    if let Some(worker) = workers.get(id) {
+        return worker.clone();
+    }
+    let worker: Worker = Default::default();
+    workers.insert(id.to_owned(), worker.clone());
+    let id = id.to_owned();
+    let worker_cloned = worker.clone();
+    std::thread::spawn(move || run(id, worker_cloned));
+    worker
+}
+
+fn run(id: String, worker: Worker) {
+    log::debug!("Plugin {} dispatch worker started", id);
+    let (lock, cvar) = &*worker;
+    loop {
+        let task = {
+            let mut queue = lock.lock().unwrap();
+            while queue.tasks.is_empty() && !queue.closed {
+                queue = cvar.wait(queue).unwrap();
+            }
+            if queue.closed {
+                break;
+            }
+            queue.tasks.pop_front()
+        };
+        if let Some(task) = task {
+            if let Err(e) = plugins::plugin_call(&id, task.method, &task.peer, &task.event) {
+                log::error!("{}", e);
+            }
+        }
+    }
+    log::debug!("Plugin {} dispatch worker exited", id);
+}
+
+/// Queue the call of `method` to plugin `id`, `peer` must be null terminated.
+pub(super) fn post(id: &str, method: &'static [u8], peer: String, event: Vec<u8>) {
+    let worker = get_worker(id);
+    let (lock, cvar) = &*worker;
+    let mut overload_dropped = None;
+    {
+        let mut queue = lock.lock().unwrap();
+        queue.tasks.push_back(Task {
+            method,
+            peer,
+            event,
+        });
+        if queue.tasks.len() > QUEUE_CAPACITY {
+            queue.tasks.pop_front();
+            queue.dropped += 1;
+            if queue
+                .last_overload_event
+                .map_or(true, |t| t.elapsed() >= OVERLOAD_EVENT_INTERVAL)
+            {
+                queue.last_overload_event = Some(Instant::now());
+                overload_dropped = Some(std::mem::take(&mut queue.dropped));
+            }
+        }
+    }
+    cvar.notify_one();
+    if let Some(dropped) = overload_dropped {
+        log::warn!("Plugin {} is overloaded, {} events dropped", id, dropped);
+        super::manager::push_overload_event(id, dropped);
+    }
+}
+
+/// Stop the worker of the plugin, the queued events are dropped.
+pub(super) fn remove(id: &str) {
+    if let Some(worker) = WORKERS.lock().unwrap().remove(id) {
+        let (lock, cvar) = &*worker;
+        let mut queue = lock.lock().unwrap();
+        queue.closed = true;
+        queue.tasks.clear();
+        cvar.notify_one();
+    }
+}
+
+pub(super) fn remove_all() {
+    let ids = WORKERS.lock().unwrap().keys().cloned().collect::<Vec<_>>();
+    for id in ids {
+        remove(&id);
+    }
+}
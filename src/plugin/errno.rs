@@ -37,6 +37,7 @@ pub const ERR_CALLBACK_INVALID_MSG: i32 = 20003;
 pub const ERR_CALLBACK_TARGET: i32 = 20004;
 pub const ERR_CALLBACK_TARGET_TYPE: i32 = 20005;
 pub const ERR_CALLBACK_PEER_NOT_FOUND: i32 = 20006;
+pub const ERR_CALLBACK_PERMISSION_DENIED: i32 = 20007;
 
 pub const ERR_CALLBACK_FAILED: i32 = 21001;
 
@@ -10,6 +10,7 @@ pub enum InstallStatus {
     Finished,
     FailedCreating,
     FailedDownloading,
+    FailedVerifying,
     FailedInstalling,
 }
 
@@ -23,6 +24,7 @@ pub enum Plugin {
     Reload(String),
     InstallStatus((String, InstallStatus)),
     Uninstall(String),
+    Isolated(super::isolated::Frame),
 }
 
 #[tokio::main(flavor = "current_thread")]
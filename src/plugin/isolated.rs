@@ -0,0 +1,776 @@
+//! Isolated native plugins, loaded in a helper process instead of the host process.
+//!
+//! The helper is the current executable started with `--plugin-host <path> <ipc postfix>`.
+//! It loads the library and proxies the calls and the callbacks over a local ipc channel,
+//! so a crashing plugin only takes down its helper. The host detects the dead helper,
+//! unloads the plugin, sends a `plugin_crashed` manager event, and restarts it if `auto_restart` is set.
+//!
+//! A plugin is isolated if the manager plugin option `isolated` of its id is "true".
+//! The `native` callback is not available to isolated plugins, the raw data can't cross processes.
+use super::{
+    callback_msg, config, errno, free_c_ptr, ipc::Plugin as IpcPlugin, native::NativeReturnValue,
+    plugins, str_to_cstr_ret, ManagerConfig, PluginReturn,
+};
+use crate::ipc::Data;
+use hbb_common::{bail, lazy_static, log, tokio, ResultType};
+use serde_derive::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    ffi::{c_char, c_void, CString},
+    process::{Child, Command},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc, Arc, Mutex, RwLock,
+    },
+    time::{Duration, Instant},
+};
+
+const ARG_PLUGIN_HOST: &str = "--plugin-host";
+const IPC_POSTFIX_PREFIX: &str = "_plugin_host_";
+
+const START_TIMEOUT: Duration = Duration::from_secs(10);
+const CALL_TIMEOUT: Duration = Duration::from_secs(30);
+const EXIT_TIMEOUT: Duration = Duration::from_secs(3);
+
+// At most `MAX_RESTARTS` automatic restarts in `RESTART_WINDOW`.
+const MAX_RESTARTS: usize = 3;
+const RESTART_WINDOW: Duration = Duration::from_secs(60);
+
+/// The messages between the host and the helper process.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "t", content = "c")]
+pub enum Frame {
+    // helper -> host
+    Desc(String),
+    Failed(String),
+    Msg {
+        seq: u64,
+        peer: String,
+        target: String,
+        content: Vec<u8>,
+    },
+    GetConf {
+        seq: u64,
+        peer: Option<String>,
+        key: String,
+    },
+    GetId {
+        seq: u64,
+    },
+    Log {
+        level: String,
+        msg: String,
+    },
+    // host -> helper
+    Init {
+        seq: u64,
+        info: String,
+    },
+    Call {
+        seq: u64,
+        method: String,
+        peer: String,
+        args: Vec<u8>,
+        with_out: bool,
+    },
+    Exit,
+    // replies of both sides
+    Return {
+        seq: u64,
+        code: i32,
+        msg: String,
+        out: Option<Vec<u8>>,
+    },
+    Value {
+        seq: u64,
+        value: Option<String>,
+    },
+}
+
+impl Frame {
+    fn reply_seq(&self) -> Option<u64> {
+        match self {
+            Frame::Return { seq, .. } | Frame::Value { seq, .. } => Some(*seq),
+            _ => None,
+        }
+    }
+}
+
+// The channel of one side, the requests wait for the replies with the same `seq`.
+struct Channel {
+    tx: tokio::sync::mpsc::UnboundedSender<Frame>,
+    seq: AtomicU64,
+    pending: Mutex<HashMap<u64, mpsc::Sender<Frame>>>,
+}
+
+impl Channel {
+    fn new(tx: tokio::sync::mpsc::UnboundedSender<Frame>) -> Self {
+        Self {
+            tx,
+            seq: AtomicU64::new(0),
+            pending: Default::default(),
+        }
+    }
+
+    fn send(&self, frame: Frame) -> ResultType<()> {
+        if self.tx.send(frame).is_err() {
+            bail!("plugin host channel is closed");
+        }
+        Ok(())
+    }
+
+    fn request(&self, make: impl FnOnce(u64) -> Frame, timeout: Duration) -> ResultType<Frame> {
+        let seq = self.seq.fetch_add(1, Ordering::SeqCst);
+        let (tx, rx) = mpsc::channel();
+        self.pending.lock().unwrap().insert(seq, tx);
+        if let Err(e) = self.send(make(seq)) {
+            self.pending.lock().unwrap().remove(&seq);
+            return Err(e);
+        }
+        let res = rx.recv_timeout(timeout);
+        self.pending.lock().unwrap().remove(&seq);
+        match res {
+            Ok(frame) => Ok(frame),
+            Err(mpsc::RecvTimeoutError::Timeout) => bail!("plugin host request timeout"),
+            Err(mpsc::RecvTimeoutError::Disconnected) => bail!("plugin host exited"),
+        }
+    }
+
+    fn resolve(&self, frame: Frame) {
+        if let Some(seq) = frame.reply_seq() {
+            if let Some(tx) = self.pending.lock().unwrap().remove(&seq) {
+                tx.send(frame).ok();
+            }
+        }
+    }
+
+    // Fail the waiting requests.
+    fn close(&self) {
+        self.pending.lock().unwrap().clear();
+    }
+}
+
+#[inline]
+fn to_plugin_return(code: i32, msg: &str) -> PluginReturn {
+    if code == errno::ERR_SUCCESS {
+        PluginReturn::success()
+    } else {
+        PluginReturn::new(code, msg)
+    }
+}
+
+// The native calls pass null terminated strings.
+#[inline]
+fn trim_nul(s: &[u8]) -> String {
+    String::from_utf8_lossy(s.strip_suffix(&[0]).unwrap_or(s)).to_string()
+}
+
+// ======================================================
+// Host side
+
+lazy_static::lazy_static! {
+    static ref ISOLATED_PLUGINS: RwLock<HashMap<String, Arc<IsolatedPlugin>>> = Default::default();
+    static ref RESTARTS: Mutex<HashMap<String, Vec<Instant>>> = Default::default();
+}
+
+static POSTFIX_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+struct HostState {
+    // The plugin id after `init()`, the path before.
+    id: RwLock<String>,
+    channel: Channel,
+    child: Mutex<Option<Child>>,
+    // Set when the host stops the helper, the exit is not a crash.
+    stopping: AtomicBool,
+}
+
+pub(super) struct IsolatedPlugin {
+    path: String,
+    desc: super::desc::Desc,
+    state: Arc<HostState>,
+}
+
+/// The plugin in `path` is isolated, the id is the name of the plugin dir.
+pub(super) fn is_enabled(path: &str) -> bool {
+    plugins::plugin_id_of_path(path)
+        .and_then(|id| ManagerConfig::get_plugin_option(id, "isolated"))
+        .map_or(false, |v| v == "true")
+}
+
+impl IsolatedPlugin {
+    /// Start the helper process and read the description of the plugin.
+    pub(super) fn new(path: &str) -> ResultType<Self> {
+        let postfix = format!(
+            "{}{}_{}",
+            IPC_POSTFIX_PREFIX,
+            std::process::id(),
+            POSTFIX_COUNTER.fetch_add(1, Ordering::SeqCst)
+        );
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let state = Arc::new(HostState {
+            id: RwLock::new(path.to_owned()),
+            channel: Channel::new(tx),
+            child: Mutex::new(None),
+            stopping: AtomicBool::new(false),
+        });
+        let (desc_tx, desc_rx) = mpsc::channel();
+        let state_cloned = state.clone();
+        let path_cloned = path.to_owned();
+        std::thread::spawn(move || {
+            if let Err(e) = run_host_conn(&path_cloned, &postfix, state_cloned.clone(), rx, desc_tx)
+            {
+                log::error!("Isolated plugin {} connection error, {}", path_cloned, e);
+            }
+            on_host_conn_closed(state_cloned);
+        });
+        // Starting the helper and loading the library are both bounded by `START_TIMEOUT`.
+        let desc = match desc_rx.recv_timeout(START_TIMEOUT * 2) {
+            Ok(Ok(desc)) => desc,
+            Ok(Err(e)) => {
+                stop_helper(&state);
+                bail!("Failed to load isolated plugin {}, {}", path, e);
+            }
+            Err(_) => {
+                stop_helper(&state);
+                bail!("Failed to start the helper of plugin {}", path);
+            }
+        };
+        Ok(Self {
+            path: path.to_owned(),
+            desc,
+            state,
+        })
+    }
+
+    #[inline]
+    pub(super) fn desc(&self) -> super::desc::Desc {
+        self.desc.clone()
+    }
+
+    pub(super) fn init(&self, id: &str, info: &str) -> ResultType<()> {
+        *self.state.id.write().unwrap() = id.to_owned();
+        let frame = self.state.channel.request(
+            |seq| Frame::Init {
+                seq,
+                info: info.to_owned(),
+            },
+            CALL_TIMEOUT,
+        )?;
+        match frame {
+            Frame::Return { code, msg, .. } if code != errno::ERR_SUCCESS => {
+                bail!(
+                    "Failed to init plugin {}, code: {}, msg: {}",
+                    self.path,
+                    code,
+                    msg
+                )
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn call(
+        &self,
+        method: &str,
+        peer: &str,
+        args: &[u8],
+        with_out: bool,
+    ) -> ResultType<(PluginReturn, Option<Vec<u8>>)> {
+        let frame = self.state.channel.request(
+            |seq| Frame::Call {
+                seq,
+                method: method.to_owned(),
+                peer: peer.to_owned(),
+                args: args.to_vec(),
+                with_out,
+            },
+            CALL_TIMEOUT,
+        )?;
+        match frame {
+            Frame::Return { code, msg, out, .. } => Ok((to_plugin_return(code, &msg), out)),
+            _ => bail!("Unexpected reply from isolated plugin {}", self.path),
+        }
+    }
+}
+
+impl Drop for IsolatedPlugin {
+    fn drop(&mut self) {
+        stop_helper(&self.state);
+    }
+}
+
+// Ask the helper to clear the plugin and exit, kill it if it doesn't exit in time.
+fn stop_helper(state: &HostState) {
+    state.stopping.store(true, Ordering::SeqCst);
+    state.channel.send(Frame::Exit).ok();
+    let start = Instant::now();
+    loop {
+        let mut child = state.child.lock().unwrap();
+        let Some(c) = child.as_mut() else {
+            return;
+        };
+        match c.try_wait() {
+            Ok(Some(_)) => return,
+            Ok(None) if start.elapsed() < EXIT_TIMEOUT => {}
+            _ => {
+                c.kill().ok();
+                c.wait().ok();
+                return;
+            }
+        }
+        drop(child);
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn run_host_conn(
+    path: &str,
+    postfix: &str,
+    state: Arc<HostState>,
+    mut rx: tokio::sync::mpsc::UnboundedReceiver<Frame>,
+    desc_tx: mpsc::Sender<ResultType<super::desc::Desc>>,
+) -> ResultType<()> {
+    let mut incoming = crate::ipc::new_listener(postfix).await?;
+    let child = Command::new(std::env::current_exe()?)
+        .args([ARG_PLUGIN_HOST, path, postfix])
+        .spawn()?;
+    *state.child.lock().unwrap() = Some(child);
+    let stream = match tokio::time::timeout(START_TIMEOUT, incoming.next()).await {
+        Ok(Some(Ok(stream))) => stream,
+        _ => bail!("the helper process didn't connect"),
+    };
+    let mut conn = crate::ipc::Connection::new(stream);
+    loop {
+        tokio::select! {
+            res = conn.next() => {
+                match res {
+                    Ok(Some(Data::Plugin(IpcPlugin::Isolated(frame)))) => {
+                        handle_helper_frame(&state, frame, &desc_tx);
+                    }
+                    Ok(Some(_)) => {}
+                    Ok(None) | Err(_) => break,
+                }
+            }
+            Some(frame) = rx.recv() => {
+                conn.send(&Data::Plugin(IpcPlugin::Isolated(frame))).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn handle_helper_frame(
+    state: &Arc<HostState>,
+    frame: Frame,
+    desc_tx: &mpsc::Sender<ResultType<super::desc::Desc>>,
+) {
+    match frame {
+        Frame::Desc(desc) => {
+            desc_tx
+                .send(serde_json::from_str(&desc).map_err(|e| e.into()))
+                .ok();
+        }
+        Frame::Failed(e) => {
+            desc_tx.send(Err(hbb_common::anyhow::anyhow!(e))).ok();
+        }
+        Frame::Log { level, msg } => {
+            let id = state.id.read().unwrap().clone();
+            match level.as_str() {
+                "error" => log::error!("[{}] {}", id, msg),
+                "warn" => log::warn!("[{}] {}", id, msg),
+                "info" => log::info!("[{}] {}", id, msg),
+                "debug" => log::debug!("[{}] {}", id, msg),
+                _ => log::trace!("[{}] {}", id, msg),
+            }
+        }
+        Frame::Msg { .. } | Frame::GetConf { .. } | Frame::GetId { .. } => {
+            // The callbacks may block, e.g. the http requests of the signature verification.
+            let state = state.clone();
+            std::thread::spawn(move || {
+                let reply = handle_helper_callback(&state.id.read().unwrap().clone(), frame);
+                if let Some(reply) = reply {
+                    state.channel.send(reply).ok();
+                }
+            });
+        }
+        Frame::Return { .. } | Frame::Value { .. } => state.channel.resolve(frame),
+        _ => {}
+    }
+}
+
+// The id is the one known by the host, the helper can't act as another plugin.
+fn handle_helper_callback(id: &str, frame: Frame) -> Option<Frame> {
+    let c_id = CString::new(id).ok()?;
+    match frame {
+        Frame::Msg {
+            seq,
+            peer,
+            target,
+            content,
+        } => {
+            let (Ok(peer), Ok(target)) = (CString::new(peer), CString::new(target)) else {
+                return Some(Frame::Return {
+                    seq,
+                    code: errno::ERR_CALLBACK_INVALID_ARGS,
+                    msg: "invalid peer or target".to_owned(),
+                    out: None,
+                });
+            };
+            let mut ret = callback_msg::cb_msg(
+                peer.as_ptr(),
+                target.as_ptr(),
+                c_id.as_ptr(),
+                content.as_ptr() as _,
+                content.len(),
+            );
+            let (code, msg) = ret.get_code_msg(id);
+            Some(Frame::Return {
+                seq,
+                code,
+                msg,
+                out: None,
+            })
+        }
+        Frame::GetConf { seq, peer, key } => {
+            let peer = peer.and_then(|p| CString::new(p).ok());
+            let key = CString::new(key).ok()?;
+            let value = config::cb_get_conf(
+                peer.as_ref().map_or(std::ptr::null(), |p| p.as_ptr()),
+                c_id.as_ptr(),
+                key.as_ptr(),
+            );
+            Some(Frame::Value {
+                seq,
+                value: take_c_string(value),
+            })
+        }
+        Frame::GetId { seq } => Some(Frame::Value {
+            seq,
+            value: take_c_string(config::cb_get_local_peer_id()),
+        }),
+        _ => None,
+    }
+}
+
+fn take_c_string(s: *const c_char) -> Option<String> {
+    if s.is_null() {
+        return None;
+    }
+    let value = super::cstr_to_string(s).ok();
+    free_c_ptr(s as _);
+    value
+}
+
+fn on_host_conn_closed(state: Arc<HostState>) {
+    state.channel.close();
+    if state.stopping.load(Ordering::SeqCst) {
+        return;
+    }
+    // The helper may still be alive if only the connection is broken.
+    let status = match state.child.lock().unwrap().as_mut().map(|c| {
+        if let Ok(None) = c.try_wait() {
+            c.kill().ok();
+        }
+        c.wait()
+    }) {
+        Some(Ok(status)) => status.to_string(),
+        Some(Err(e)) => e.to_string(),
+        None => "not started".to_owned(),
+    };
+    let id = state.id.read().unwrap().clone();
+    if !ISOLATED_PLUGINS.read().unwrap().contains_key(&id) {
+        // Not started yet, `IsolatedPlugin::new()` reports the error.
+        return;
+    }
+    log::error!("Isolated plugin {} crashed, {}", id, status);
+    super::manager::push_crashed_event(&id, &status);
+    plugins::unload_plugin(&id);
+    if let Some(delay) = next_restart_delay(&id) {
+        std::thread::sleep(delay);
+        log::info!("Restart isolated plugin {}", id);
+        if let Err(e) = plugins::reload_plugin(&id) {
+            log::error!("Failed to restart isolated plugin {}, {}", id, e);
+        }
+    }
+}
+
+// `None` if the plugin should not be restarted.
+fn next_restart_delay(id: &str) -> Option<Duration> {
+    if ManagerConfig::get_plugin_option(id, "auto_restart").map_or(true, |v| v != "true") {
+        return None;
+    }
+    let mut restarts = RESTARTS.lock().unwrap();
+    let times = restarts.entry(id.to_owned()).or_default();
+    times.retain(|t| t.elapsed() < RESTART_WINDOW);
+    if times.len() >= MAX_RESTARTS {
+        log::error!(
+            "Isolated plugin {} crashed {} times in {:?}, stop restarting it",
+            id,
+            times.len(),
+            RESTART_WINDOW
+        );
+        return None;
+    }
+    times.push(Instant::now());
+    Some(Duration::from_secs(times.len() as _))
+}
+
+pub(super) fn insert(id: String, plugin: IsolatedPlugin) {
+    ISOLATED_PLUGINS
+        .write()
+        .unwrap()
+        .insert(id, Arc::new(plugin));
+}
+
+#[inline]
+pub(super) fn contains(id: &str) -> bool {
+    ISOLATED_PLUGINS.read().unwrap().contains_key(id)
+}
+
+pub(super) fn remove(id: &str) {
+    let plugin = ISOLATED_PLUGINS.write().unwrap().remove(id);
+    drop(plugin);
+}
+
+pub(super) fn remove_all() {
+    let plugins = std::mem::take(&mut *ISOLATED_PLUGINS.write().unwrap());
+    drop(plugins);
+}
+
+/// Call a loaded isolated plugin, `None` if `id` is not an isolated plugin.
+pub(super) fn call(
+    id: &str,
+    method: &[u8],
+    peer: &str,
+    args: &[u8],
+    with_out: bool,
+) -> Option<ResultType<(PluginReturn, Option<Vec<u8>>)>> {
+    let plugin = ISOLATED_PLUGINS.read().unwrap().get(id).cloned()?;
+    Some(plugin.call(
+        &trim_nul(method),
+        &trim_nul(peer.as_bytes()),
+        args,
+        with_out,
+    ))
+}
+
+// ======================================================
+// Helper process side
+
+lazy_static::lazy_static! {
+    static ref HELPER_CHANNEL: RwLock<Option<Arc<Channel>>> = Default::default();
+}
+
+#[inline]
+fn helper_channel() -> Option<Arc<Channel>> {
+    HELPER_CHANNEL.read().unwrap().clone()
+}
+
+extern "C" fn helper_cb_msg(
+    peer: *const c_char,
+    target: *const c_char,
+    _id: *const c_char,
+    content: *const c_void,
+    len: usize,
+) -> PluginReturn {
+    let (Ok(peer), Ok(target)) = (super::cstr_to_string(peer), super::cstr_to_string(target))
+    else {
+        return PluginReturn::new(errno::ERR_CALLBACK_INVALID_ARGS, "invalid peer or target");
+    };
+    let Some(channel) = helper_channel() else {
+        return PluginReturn::new(errno::ERR_CALLBACK_FAILED, "no plugin host");
+    };
+    let content = if content.is_null() {
+        Vec::new()
+    } else {
+        unsafe { std::slice::from_raw_parts(content as *const u8, len) }.to_vec()
+    };
+    match channel.request(
+        |seq| Frame::Msg {
+            seq,
+            peer,
+            target,
+            content,
+        },
+        CALL_TIMEOUT,
+    ) {
+        Ok(Frame::Return { code, msg, .. }) => to_plugin_return(code, &msg),
+        Ok(_) => PluginReturn::new(errno::ERR_CALLBACK_FAILED, "unexpected reply"),
+        Err(e) => PluginReturn::new(errno::ERR_CALLBACK_FAILED, &e.to_string()),
+    }
+}
+
+fn helper_request_value(make: impl FnOnce(u64) -> Frame) -> *const c_char {
+    match helper_channel().map(|c| c.request(make, CALL_TIMEOUT)) {
+        Some(Ok(Frame::Value {
+            value: Some(value), ..
+        })) => str_to_cstr_ret(&value),
+        _ => std::ptr::null(),
+    }
+}
+
+extern "C" fn helper_cb_get_conf(
+    peer: *const c_char,
+    _id: *const c_char,
+    key: *const c_char,
+) -> *const c_char {
+    let Ok(key) = super::cstr_to_string(key) else {
+        return std::ptr::null();
+    };
+    let peer = super::cstr_to_string(peer).ok();
+    helper_request_value(|seq| Frame::GetConf { seq, peer, key })
+}
+
+extern "C" fn helper_cb_get_id() -> *const c_char {
+    helper_request_value(|seq| Frame::GetId { seq })
+}
+
+extern "C" fn helper_cb_log(level: *const c_char, msg: *const c_char) {
+    if let (Ok(level), Ok(msg), Some(channel)) = (
+        super::cstr_to_string(level),
+        super::cstr_to_string(msg),
+        helper_channel(),
+    ) {
+        channel.send(Frame::Log { level, msg }).ok();
+    }
+}
+
+extern "C" fn helper_cb_native(
+    _method: *const c_char,
+    _json: *const c_char,
+    _raw: *const c_void,
+    _raw_len: usize,
+) -> NativeReturnValue {
+    NativeReturnValue {
+        return_type: errno::ERR_NOT_HANDLED,
+        data: std::ptr::null(),
+    }
+}
+
+/// The entry of the helper process, `--plugin-host <path> <ipc postfix>`.
+pub fn run_plugin_host(path: &str, postfix: &str) {
+    if !postfix.starts_with(IPC_POSTFIX_PREFIX) {
+        log::error!("Invalid plugin host ipc postfix {}", postfix);
+        return;
+    }
+    if let Err(e) = run_helper_conn(path, postfix) {
+        log::error!("Plugin host of {} exited, {}", path, e);
+    }
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn run_helper_conn(path: &str, postfix: &str) -> ResultType<()> {
+    let mut conn = crate::ipc::connect(START_TIMEOUT.as_millis() as _, postfix).await?;
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let channel = Arc::new(Channel::new(tx));
+    *HELPER_CHANNEL.write().unwrap() = Some(channel.clone());
+
+    let plugin = match plugins::Plugin::new(path).and_then(|p| Ok((p.desc()?, p))) {
+        Ok((desc, plugin)) => {
+            let desc = serde_json::to_string(&desc)?;
+            conn.send(&Data::Plugin(IpcPlugin::Isolated(Frame::Desc(desc))))
+                .await?;
+            Arc::new(RwLock::new(plugin))
+        }
+        Err(e) => {
+            conn.send(&Data::Plugin(IpcPlugin::Isolated(Frame::Failed(
+                e.to_string(),
+            ))))
+            .await?;
+            return Ok(());
+        }
+    };
+
+    loop {
+        tokio::select! {
+            res = conn.next() => {
+                match res {
+                    Ok(Some(Data::Plugin(IpcPlugin::Isolated(frame)))) => {
+                        match frame {
+                            Frame::Exit => break,
+                            Frame::Init { .. } | Frame::Call { .. } => {
+                                // The plugin may call back into the host during the call.
+                                let plugin = plugin.clone();
+                                let channel = channel.clone();
+                                let path = path.to_owned();
+                                std::thread::spawn(move || {
+                                    let reply = handle_host_request(&plugin, &path, frame);
+                                    if let Some(reply) = reply {
+                                        channel.send(reply).ok();
+                                    }
+                                });
+                            }
+                            _ => channel.resolve(frame),
+                        }
+                    }
+                    Ok(Some(_)) => {}
+                    Ok(None) | Err(_) => break,
+                }
+            }
+            Some(frame) = rx.recv() => {
+                conn.send(&Data::Plugin(IpcPlugin::Isolated(frame))).await?;
+            }
+        }
+    }
+    *HELPER_CHANNEL.write().unwrap() = None;
+    channel.close();
+    // `clear()` of the plugin is called when the last call returns.
+    drop(plugin);
+    Ok(())
+}
+
+fn handle_host_request(
+    plugin: &RwLock<plugins::Plugin>,
+    path: &str,
+    frame: Frame,
+) -> Option<Frame> {
+    match frame {
+        Frame::Init { seq, info } => {
+            let init_data = plugins::InitData::new(
+                &info,
+                plugins::Callbacks {
+                    msg: helper_cb_msg,
+                    get_conf: helper_cb_get_conf,
+                    get_id: helper_cb_get_id,
+                    log: helper_cb_log,
+                    native: helper_cb_native,
+                },
+            );
+            let (code, msg) = match plugin.write().unwrap().init(&init_data, path) {
+                Ok(()) => (errno::ERR_SUCCESS, "".to_owned()),
+                Err(e) => (errno::ERR_PLUGIN_MSG_INIT, e.to_string()),
+            };
+            Some(Frame::Return {
+                seq,
+                code,
+                msg,
+                out: None,
+            })
+        }
+        Frame::Call {
+            seq,
+            mut method,
+            mut peer,
+            args,
+            with_out,
+        } => {
+            method.push('\0');
+            peer.push('\0');
+            let (mut ret, out) = plugin.read().unwrap().call_raw(
+                method.as_bytes(),
+                peer.as_bytes(),
+                &args,
+                with_out,
+            );
+            let (code, msg) = ret.get_code_msg(path);
+            Some(Frame::Return {
+                seq,
+                code,
+                msg,
+                out,
+            })
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,70 @@
+//! Translations of the plugin-provided UI strings.
+//!
+//! A plugin ships its table in the `translations` field of the description,
+//! e.g. `{"en": {"btn_start": "Start"}, "zh-cn": {"btn_start": "开始"}}`.
+use super::{
+    plugins::{self, MsgListenEvent},
+    EVENT_ON_LANG_CHANGED,
+};
+use hbb_common::{config::LocalConfig, log};
+
+const FALLBACK_LOCALE: &str = "en";
+
+/// The current UI language, the system locale if it is not set.
+pub fn current_lang() -> String {
+    let lang = LocalConfig::get_option("lang");
+    let lang = if lang.is_empty() || lang == "default" {
+        sys_locale::get_locale().unwrap_or_default()
+    } else {
+        lang
+    };
+    normalize(&lang)
+}
+
+// "zh_CN" -> "zh-cn"
+#[inline]
+fn normalize(locale: &str) -> String {
+    locale.to_lowercase().replace('_', "-")
+}
+
+/// Translate `key` of the plugin, `locale` is the current UI language if empty.
+///
+/// The lookup falls back to the primary language ("pt-br" -> "pt"), then "en", then `key` itself.
+pub fn translate(id: &str, locale: &str, key: &str) -> String {
+    let locale = if locale.is_empty() {
+        current_lang()
+    } else {
+        normalize(locale)
+    };
+    let infos = plugins::get_plugin_infos();
+    let infos = infos.read().unwrap();
+    let Some(info) = infos.get(id) else {
+        return key.to_owned();
+    };
+    let translations = info.desc.translations();
+    let lookup = |locale: &str| {
+        translations
+            .iter()
+            .find(|(l, _)| normalize(l) == locale)
+            .and_then(|(_, table)| table.get(key))
+    };
+    let primary = locale.split('-').next().unwrap_or_default();
+    lookup(&locale)
+        .or_else(|| lookup(primary))
+        .or_else(|| lookup(FALLBACK_LOCALE))
+        .cloned()
+        .unwrap_or_else(|| key.to_owned())
+}
+
+/// Notify the plugins listening to `on_lang_changed`, the data of the event is the new language.
+pub fn notify_lang_changed() {
+    let lang = current_lang();
+    log::debug!("Notify plugins the language is changed to {}", lang);
+    plugins::handle_listen_event_with(
+        MsgListenEvent {
+            event: EVENT_ON_LANG_CHANGED.to_owned(),
+            data: Some(lang),
+        },
+        "".to_owned(),
+    );
+}
@@ -17,15 +17,27 @@ use std::{
 const MSG_TO_UI_PLUGIN_MANAGER_LIST: &str = "plugin_list";
 const MSG_TO_UI_PLUGIN_MANAGER_INSTALL: &str = "plugin_install";
 const MSG_TO_UI_PLUGIN_MANAGER_UNINSTALL: &str = "plugin_uninstall";
+const MSG_TO_UI_PLUGIN_MANAGER_INCOMPATIBLE: &str = "plugin_incompatible";
+const MSG_TO_UI_PLUGIN_MANAGER_PERMISSION_DENIED: &str = "plugin_permission_denied";
+const MSG_TO_UI_PLUGIN_MANAGER_OVERLOAD: &str = "plugin_overload";
+const MSG_TO_UI_PLUGIN_MANAGER_CRASHED: &str = "plugin_crashed";
+const MSG_TO_UI_PLUGIN_MANAGER_LIFECYCLE: &str = "plugin_lifecycle";
+const MSG_TO_UI_PLUGIN_MANAGER_INVALID_UI: &str = "plugin_invalid_ui";
+
+pub(super) const LIFECYCLE_LOAD: &str = "load";
+pub(super) const LIFECYCLE_INIT: &str = "init";
+pub(super) const LIFECYCLE_UNLOAD: &str = "unload";
+pub(super) const LIFECYCLE_RELOAD: &str = "reload";
+const LIFECYCLE_UNINSTALL: &str = "uninstall";
 
 const IPC_PLUGIN_POSTFIX: &str = "_plugin";
 
 #[cfg(target_os = "windows")]
-const PLUGIN_PLATFORM: &str = "windows";
+pub(super) const PLUGIN_PLATFORM: &str = "windows";
 #[cfg(target_os = "linux")]
-const PLUGIN_PLATFORM: &str = "linux";
+pub(super) const PLUGIN_PLATFORM: &str = "linux";
 #[cfg(target_os = "macos")]
-const PLUGIN_PLATFORM: &str = "macos";
+pub(super) const PLUGIN_PLATFORM: &str = "macos";
 
 lazy_static::lazy_static! {
     static ref PLUGIN_INFO: Arc<Mutex<HashMap<String, PluginInfo>>> = Arc::new(Mutex::new(HashMap::new()));
@@ -165,14 +177,25 @@ pub fn load_plugin_list() {
 fn elevate_install(
     plugin_id: &str,
     plugin_url: &str,
+    sha256: &str,
     same_plugin_exists: bool,
 ) -> ResultType<bool> {
-    // to-do: Support args with space in quotes. 'arg 1' and "arg 2"
-    let args = if same_plugin_exists {
-        format!("--plugin-install {}", plugin_id)
-    } else {
-        format!("--plugin-install {} {}", plugin_id, plugin_url)
-    };
+    let mut args = vec!["--plugin-install", plugin_id];
+    if !same_plugin_exists {
+        args.push(plugin_url);
+        if !sha256.is_empty() {
+            args.push(sha256);
+        }
+    }
+    // Quote every argument, a quote or a trailing backslash would end the quoting.
+    if args.iter().any(|a| a.contains('"') || a.ends_with('\\')) {
+        bail!("Invalid argument to install plugin {}", plugin_id);
+    }
+    let args = args
+        .iter()
+        .map(|a| format!("\"{}\"", a))
+        .collect::<Vec<_>>()
+        .join(" ");
     crate::platform::elevate(&args)
 }
 
@@ -180,11 +203,15 @@ fn elevate_install(
 fn elevate_install(
     plugin_id: &str,
     plugin_url: &str,
+    sha256: &str,
     same_plugin_exists: bool,
 ) -> ResultType<bool> {
     let mut args = vec!["--plugin-install", plugin_id];
     if !same_plugin_exists {
         args.push(&plugin_url);
+        if !sha256.is_empty() {
+            args.push(sha256);
+        }
     }
     crate::platform::elevate(args)
 }
@@ -193,11 +220,15 @@ fn elevate_install(
 fn elevate_install(
     plugin_id: &str,
     plugin_url: &str,
+    sha256: &str,
     same_plugin_exists: bool,
 ) -> ResultType<bool> {
     let mut args = vec!["--plugin-install", plugin_id];
     if !same_plugin_exists {
         args.push(&plugin_url);
+        if !sha256.is_empty() {
+            args.push(sha256);
+        }
     }
     crate::platform::elevate(args, "RustDesk wants to install then plugin")
 }
@@ -226,12 +257,6 @@ fn elevate_uninstall(plugin_id: &str) -> ResultType<bool> {
 pub fn install_plugin(id: &str) -> ResultType<()> {
     match PLUGIN_INFO.lock().unwrap().get(id) {
         Some(plugin) => {
-            let mut same_plugin_exists = false;
-            if let Some(version) = super::plugins::get_version(id) {
-                if version == plugin.meta.version {
-                    same_plugin_exists = true;
-                }
-            }
             let plugin_url = format!(
                 "{}/plugins/{}/{}/{}_{}.zip",
                 plugin.source.url,
@@ -240,14 +265,7 @@ pub fn install_plugin(id: &str) -> ResultType<()> {
                 plugin.meta.id,
                 plugin.meta.version
             );
-            let allowed_install = elevate_install(id, &plugin_url, same_plugin_exists)?;
-            if allowed_install && same_plugin_exists {
-                super::ipc::load_plugin(id)?;
-                super::plugins::load_plugin(id)?;
-                super::plugins::mark_uninstalled(id, false);
-                push_install_event(id, "finished");
-            }
-            Ok(())
+            install_plugin_package(id, &plugin.meta.version, &plugin_url, "")
         }
         None => {
             bail!("Plugin not found: {}", id);
@@ -255,6 +273,28 @@ pub fn install_plugin(id: &str) -> ResultType<()> {
     }
 }
 
+/// Install the package of `version` from `url`, the plugin is only reloaded if the same version exists.
+/// The downloaded package must match `sha256` if it is not empty.
+pub(super) fn install_plugin_package(
+    id: &str,
+    version: &str,
+    url: &str,
+    sha256: &str,
+) -> ResultType<()> {
+    if !super::is_valid_id(id) {
+        bail!("Invalid plugin id: {}", id);
+    }
+    let same_plugin_exists = super::plugins::get_version(id).map_or(false, |v| v == version);
+    let allowed_install = elevate_install(id, url, sha256, same_plugin_exists)?;
+    if allowed_install && same_plugin_exists {
+        super::ipc::load_plugin(id)?;
+        super::plugins::load_plugin(id)?;
+        super::plugins::mark_uninstalled(id, false);
+        push_install_event(id, "finished");
+    }
+    Ok(())
+}
+
 fn get_uninstalled_plugins(uninstalled_plugin_set: &HashSet<String>) -> ResultType<Vec<String>> {
     let plugins_dir = super::get_plugins_dir()?;
     let mut plugins = Vec::new();
@@ -297,19 +337,49 @@ pub fn remove_uninstalled() -> ResultType<()> {
 
 pub fn uninstall_plugin(id: &str, called_by_ui: bool) {
     if called_by_ui {
+        push_lifecycle_event(
+            id,
+            &LifecycleEvent::stage(LIFECYCLE_UNINSTALL, "elevating", 0),
+        );
         match elevate_uninstall(id) {
             Ok(true) => {
+                push_lifecycle_event(
+                    id,
+                    &LifecycleEvent::stage(LIFECYCLE_UNINSTALL, "uninstalling", 25),
+                );
                 if let Err(e) = super::ipc::uninstall_plugin(id) {
                     log::error!("Failed to uninstall plugin '{}': {}", id, e);
                     push_uninstall_event(id, "failed");
+                    push_lifecycle_event(id, &LifecycleEvent::failed(LIFECYCLE_UNINSTALL, &e));
                     return;
                 }
+                push_lifecycle_event(
+                    id,
+                    &LifecycleEvent::stage(LIFECYCLE_UNINSTALL, "unloading", 50),
+                );
                 super::plugins::unload_plugin(id);
                 super::plugins::mark_uninstalled(id, true);
+                push_lifecycle_event(
+                    id,
+                    &LifecycleEvent::stage(LIFECYCLE_UNINSTALL, "removing config", 75),
+                );
                 super::config::remove(id);
                 push_uninstall_event(id, "");
+                push_lifecycle_event(
+                    id,
+                    &LifecycleEvent::stage(LIFECYCLE_UNINSTALL, "finished", 100),
+                );
             }
             Ok(false) => {
+                push_lifecycle_event(
+                    id,
+                    &LifecycleEvent {
+                        action: LIFECYCLE_UNINSTALL,
+                        ok: false,
+                        stage: Some("cancelled"),
+                        ..Default::default()
+                    },
+                );
                 return;
             }
             Err(e) => {
@@ -319,6 +389,7 @@ pub fn uninstall_plugin(id: &str, called_by_ui: bool) {
                     e
                 );
                 push_uninstall_event(id, "failed");
+                push_lifecycle_event(id, &LifecycleEvent::failed(LIFECYCLE_UNINSTALL, &e));
                 return;
             }
         }
@@ -340,6 +411,74 @@ fn push_event(id: &str, r#type: &str, msg: &str) {
     }
 }
 
+/// The result of a lifecycle action of the plugin, sent as json in the `plugin_lifecycle` event.
+///
+/// `category` is the kind of the failure, "library", "symbol", "desc", "init", "incompatible" or "other".
+/// `symbol` is the missing or mismatched export for the "symbol" category.
+#[derive(Debug, Default, Serialize)]
+pub(super) struct LifecycleEvent {
+    action: &'static str,
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stage: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    progress: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    category: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    symbol: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl LifecycleEvent {
+    pub(super) fn ok(action: &'static str) -> Self {
+        Self {
+            action,
+            ok: true,
+            ..Default::default()
+        }
+    }
+
+    pub(super) fn failed(action: &'static str, err: &hbb_common::anyhow::Error) -> Self {
+        let mut event = Self {
+            action,
+            ok: false,
+            category: Some("other"),
+            error: Some(err.to_string()),
+            ..Default::default()
+        };
+        if let Some(e) = err.downcast_ref::<super::plugins::LoadError>() {
+            event.category = Some(e.category());
+            match e {
+                super::plugins::LoadError::Symbol { symbol, .. } => event.symbol = Some(symbol),
+                super::plugins::LoadError::Init { code, .. } => event.code = Some(*code),
+                _ => {}
+            }
+        }
+        event
+    }
+
+    fn stage(action: &'static str, stage: &'static str, progress: u8) -> Self {
+        Self {
+            action,
+            ok: true,
+            stage: Some(stage),
+            progress: Some(progress),
+            ..Default::default()
+        }
+    }
+}
+
+pub(super) fn push_lifecycle_event(id: &str, event: &LifecycleEvent) {
+    match serde_json::to_string(event) {
+        Ok(event) => push_event(id, MSG_TO_UI_PLUGIN_MANAGER_LIFECYCLE, &event),
+        Err(e) => log::error!("Failed to serialize the lifecycle event, {}", e),
+    }
+}
+
 #[inline]
 fn push_uninstall_event(id: &str, msg: &str) {
     push_event(id, MSG_TO_UI_PLUGIN_MANAGER_UNINSTALL, msg);
@@ -350,6 +489,35 @@ fn push_install_event(id: &str, msg: &str) {
     push_event(id, MSG_TO_UI_PLUGIN_MANAGER_INSTALL, msg);
 }
 
+/// The plugin is refused or unloaded because of its dependencies or the host version.
+#[inline]
+pub(super) fn push_incompatible_event(id: &str, msg: &str) {
+    push_event(id, MSG_TO_UI_PLUGIN_MANAGER_INCOMPATIBLE, msg);
+}
+
+/// The UI descriptors of the plugin are rejected, `msg` tells the location and the item.
+#[inline]
+pub(super) fn push_invalid_ui_event(id: &str, msg: &str) {
+    push_event(id, MSG_TO_UI_PLUGIN_MANAGER_INVALID_UI, msg);
+}
+
+#[inline]
+pub(super) fn push_permission_denied_event(id: &str, permission: &str) {
+    push_event(id, MSG_TO_UI_PLUGIN_MANAGER_PERMISSION_DENIED, permission);
+}
+
+/// The events queued to the plugin are dropped, `dropped` is the number since the last overload event.
+#[inline]
+pub(super) fn push_overload_event(id: &str, dropped: usize) {
+    push_event(id, MSG_TO_UI_PLUGIN_MANAGER_OVERLOAD, &dropped.to_string());
+}
+
+/// The helper process of the isolated plugin exited unexpectedly, `status` is the exit status.
+#[inline]
+pub(super) fn push_crashed_event(id: &str, status: &str) {
+    push_event(id, MSG_TO_UI_PLUGIN_MANAGER_CRASHED, status);
+}
+
 async fn handle_conn(mut stream: crate::ipc::Connection) {
     loop {
         tokio::select! {
@@ -381,6 +549,9 @@ async fn handle_conn(mut stream: crate::ipc::Connection) {
                                     InstallStatus::FailedDownloading => {
                                         push_install_event(&id, "failed-downloading");
                                     }
+                                    InstallStatus::FailedVerifying => {
+                                        push_install_event(&id, "failed-verifying");
+                                    }
                                     InstallStatus::FailedInstalling => {
                                         push_install_event(&id, "failed-installing");
                                     }
@@ -451,9 +622,10 @@ pub(super) mod install {
         plugin::ipc::{InstallStatus, Plugin},
     };
     use hbb_common::{allow_err, bail, log, tokio, ResultType};
+    use sha2::{Digest, Sha256};
     use std::{
         fs::File,
-        io::{BufReader, BufWriter, Write},
+        io::{BufReader, BufWriter, Read, Write},
         path::Path,
     };
     use zip::ZipArchive;
@@ -473,8 +645,9 @@ pub(super) mod install {
         Ok(())
     }
 
-    fn download_to_file(url: &str, file: File) -> ResultType<()> {
-        let resp = match create_http_client().get(url).send() {
+    // Returns the sha256 hex digest of the downloaded file.
+    fn download_to_file(id: &str, url: &str, file: File) -> ResultType<String> {
+        let mut resp = match create_http_client().get(url).send() {
             Ok(resp) => resp,
             Err(e) => {
                 bail!("get plugin from '{}', {}", url, e);
@@ -485,33 +658,61 @@ pub(super) mod install {
             bail!("get plugin from '{}', status code: {}", url, resp.status());
         }
 
+        let total = resp.content_length().unwrap_or(0);
         let mut writer = BufWriter::new(file);
-        writer.write_all(resp.bytes()?.as_ref())?;
-        Ok(())
+        let mut hasher = Sha256::new();
+        let mut buf = vec![0u8; 64 * 1024];
+        let mut downloaded = 0u64;
+        let mut last_percent = 0u8;
+        loop {
+            let n = resp.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            writer.write_all(&buf[..n])?;
+            hasher.update(&buf[..n]);
+            downloaded += n as u64;
+            if total > 0 {
+                // Every 10 percent, each status is sent through a new ipc connection.
+                let percent = (downloaded * 100 / total).min(100) as u8;
+                if percent >= last_percent + 10 {
+                    last_percent = percent;
+                    send_install_status(id, InstallStatus::Downloading(percent));
+                }
+            }
+        }
+        writer.flush()?;
+        Ok(hex::encode(hasher.finalize()))
     }
 
-    fn download_file(id: &str, url: &str, filename: &Path) -> bool {
+    fn download_file(id: &str, url: &str, filename: &Path) -> Option<String> {
         let file = match File::create(filename) {
             Ok(f) => f,
             Err(e) => {
                 log::error!("Failed to create plugin file: {}", e);
                 send_install_status(id, InstallStatus::FailedCreating);
-                return false;
+                return None;
             }
         };
-        if let Err(e) = download_to_file(url, file) {
-            log::error!("Failed to download plugin '{}', {}", id, e);
-            send_install_status(id, InstallStatus::FailedDownloading);
-            return false;
+        match download_to_file(id, url, file) {
+            Ok(digest) => Some(digest),
+            Err(e) => {
+                log::error!("Failed to download plugin '{}', {}", id, e);
+                send_install_status(id, InstallStatus::FailedDownloading);
+                None
+            }
         }
-        true
     }
 
     fn do_install_file(filename: &Path, target_dir: &Path) -> ResultType<()> {
         let mut zip = ZipArchive::new(BufReader::new(File::open(filename)?))?;
         for i in 0..zip.len() {
             let mut file = zip.by_index(i)?;
-            let file_path = target_dir.join(file.name());
+            // Refuse the entries outside of the plugin directory, e.g. `../x` or `/x`.
+            let Some(name) = file.enclosed_name().map(Path::to_path_buf) else {
+                bail!("Invalid path in the plugin package: {}", file.name());
+            };
+            let file_path = target_dir.join(name);
             if file.name().ends_with("/") {
                 std::fs::create_dir_all(&file_path)?;
             } else {
@@ -546,7 +747,8 @@ pub(super) mod install {
         }
     }
 
-    pub fn install_plugin_with_url(id: &str, url: &str) {
+    /// Install the plugin package from `url`, it is refused if `sha256` is not empty and doesn't match.
+    pub fn install_plugin_with_url(id: &str, url: &str, sha256: &str) {
         log::info!("Installing plugin '{}', url: {}", id, url);
         let plugin_dir = match super::super::get_plugin_dir(id) {
             Ok(d) => d,
@@ -564,13 +766,10 @@ pub(super) mod install {
             }
         }
 
+        // The name in the url is only used if it's a plain file name.
         let filename = match url.rsplit('/').next() {
-            Some(filename) => plugin_dir.join(filename),
-            None => {
-                send_install_status(id, InstallStatus::FailedDownloading);
-                log::error!("Failed to download plugin file, invalid url: {}", url);
-                return;
-            }
+            Some(filename) if super::super::is_valid_id(filename) => plugin_dir.join(filename),
+            _ => plugin_dir.join(format!("{}.zip", id)),
         };
 
         let filename_to_remove = filename.clone();
@@ -584,7 +783,17 @@ pub(super) mod install {
         };
 
         // download
-        if !download_file(id, url, &filename) {
+        let Some(digest) = download_file(id, url, &filename) else {
+            return;
+        };
+        if !sha256.is_empty() && !digest.eq_ignore_ascii_case(sha256) {
+            log::error!(
+                "Plugin '{}' package sha256 mismatch, expected {}, got {}",
+                id,
+                sha256,
+                digest
+            );
+            send_install_status(id, InstallStatus::FailedVerifying);
             return;
         }
 
@@ -0,0 +1,214 @@
+//! Plugin marketplace, an index of plugins to search, install and update.
+//!
+//! The index is a json file at the url of the local option `plugin-marketplace-url`:
+//! ```json
+//! {
+//!     "version": 1,
+//!     "plugins": [{
+//!         "id": "rustdesk.example",
+//!         "name": "Example",
+//!         "version": "1.0.0",
+//!         "description": "An example plugin",
+//!         "author": "rustdesk",
+//!         "packages": {
+//!             "windows": { "url": "https://.../example_1.0.0.zip", "sha256": "<hex digest of the zip>" },
+//!             "linux": { ... },
+//!             "macos": { ... }
+//!         }
+//!     }]
+//! }
+//! ```
+//! The downloaded package is checked against `sha256` before it is extracted,
+//! packages without a valid `sha256` are not listed and can't be installed.
+//! The ids are the names of the plugin directories, the plugins with other characters
+//! than `[A-Za-z0-9._-]` or with `..` are not listed.
+//! The progress of installing is sent as the `plugin_install` manager events.
+use super::{deps, manager};
+use crate::hbbs_http::create_http_client;
+use hbb_common::{bail, config::LocalConfig, log, ResultType};
+use serde_derive::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::Mutex};
+
+pub const OPTION_PLUGIN_MARKETPLACE_URL: &str = "plugin-marketplace-url";
+
+lazy_static::lazy_static! {
+    static ref INDEX: Mutex<Option<(String, Index)>> = Default::default();
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Index {
+    #[serde(default)]
+    pub version: u32,
+    #[serde(default)]
+    pub plugins: Vec<IndexPlugin>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexPlugin {
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub author: String,
+    /// Platform ("windows", "linux", "macos") -> package.
+    #[serde(default)]
+    pub packages: HashMap<String, IndexPackage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexPackage {
+    pub url: String,
+    /// Hex digest of the package.
+    #[serde(default)]
+    pub sha256: String,
+}
+
+impl IndexPackage {
+    #[inline]
+    fn is_verifiable(&self) -> bool {
+        self.sha256.len() == 64 && self.sha256.bytes().all(|b| b.is_ascii_hexdigit())
+    }
+}
+
+/// A plugin of the index with its local state.
+#[derive(Debug, Serialize)]
+pub struct MarketplacePlugin {
+    #[serde(flatten)]
+    pub plugin: IndexPlugin,
+    pub installed_version: String,
+    pub update_available: bool,
+}
+
+fn get_index_url() -> ResultType<String> {
+    let url = LocalConfig::get_option(OPTION_PLUGIN_MARKETPLACE_URL);
+    if url.is_empty() {
+        bail!("Plugin marketplace url is not set");
+    }
+    Ok(url)
+}
+
+fn fetch_index(url: &str) -> ResultType<Index> {
+    let resp = match create_http_client().get(url).send() {
+        Ok(resp) => resp,
+        Err(e) => bail!("Failed to get plugin index from '{}', {}", url, e),
+    };
+    if !resp.status().is_success() {
+        bail!(
+            "Failed to get plugin index from '{}', status code: {}",
+            url,
+            resp.status()
+        );
+    }
+    match serde_json::from_str::<Index>(&resp.text()?) {
+        Ok(index) => Ok(index),
+        Err(e) => bail!("Failed to parse plugin index from '{}', {}", url, e),
+    }
+}
+
+/// Get the index, `refresh` to fetch it again even if it is cached.
+pub fn get_index(refresh: bool) -> ResultType<Index> {
+    let url = get_index_url()?;
+    if !refresh {
+        if let Some((cached_url, index)) = INDEX.lock().unwrap().as_ref() {
+            if *cached_url == url {
+                return Ok(index.clone());
+            }
+        }
+    }
+    // Fetch without the lock, the other calls are not blocked by the slow server.
+    let index = fetch_index(&url)?;
+    log::info!(
+        "Plugin index fetched from '{}', {} plugins",
+        url,
+        index.plugins.len()
+    );
+    *INDEX.lock().unwrap() = Some((url, index.clone()));
+    Ok(index)
+}
+
+fn to_marketplace_plugin(plugin: IndexPlugin) -> MarketplacePlugin {
+    let installed_version = super::plugins::get_version(&plugin.id).unwrap_or_default();
+    let update_available = !installed_version.is_empty()
+        && deps::version_matches(&plugin.version, &format!(">{}", installed_version));
+    MarketplacePlugin {
+        plugin,
+        installed_version,
+        update_available,
+    }
+}
+
+/// Search the plugins available on this platform by id, name or description, empty `query` for all.
+pub fn search(query: &str, refresh: bool) -> ResultType<Vec<MarketplacePlugin>> {
+    let query = query.trim().to_lowercase();
+    let mut plugins = get_index(refresh)?
+        .plugins
+        .into_iter()
+        .filter(|p| super::is_valid_id(&p.id))
+        .filter(|p| {
+            p.packages
+                .get(manager::PLUGIN_PLATFORM)
+                .map_or(false, IndexPackage::is_verifiable)
+        })
+        .filter(|p| {
+            query.is_empty()
+                || p.id.to_lowercase().contains(&query)
+                || p.name.to_lowercase().contains(&query)
+                || p.description.to_lowercase().contains(&query)
+        })
+        .map(to_marketplace_plugin)
+        .collect::<Vec<_>>();
+    plugins.sort_by(|a, b| a.plugin.name.cmp(&b.plugin.name));
+    Ok(plugins)
+}
+
+fn find(id: &str) -> ResultType<(IndexPlugin, IndexPackage)> {
+    if !super::is_valid_id(id) {
+        bail!("Invalid plugin id: {}", id);
+    }
+    let Some(plugin) = get_index(false)?.plugins.into_iter().find(|p| p.id == id) else {
+        bail!("Plugin {} is not in the marketplace", id);
+    };
+    let Some(package) = plugin.packages.get(manager::PLUGIN_PLATFORM).cloned() else {
+        bail!(
+            "Plugin {} is not available on {}",
+            id,
+            manager::PLUGIN_PLATFORM
+        );
+    };
+    if !package.is_verifiable() {
+        bail!("Plugin {} package has no valid sha256", id);
+    }
+    Ok((plugin, package))
+}
+
+/// Install the plugin from the marketplace, the progress is sent as manager events.
+pub fn install(id: &str) -> ResultType<()> {
+    let (plugin, package) = find(id)?;
+    log::info!("Install plugin {} {} from marketplace", id, plugin.version);
+    manager::install_plugin_package(id, &plugin.version, &package.url, &package.sha256)
+}
+
+/// Update the installed plugin to the version in the marketplace.
+pub fn update(id: &str) -> ResultType<()> {
+    let Some(installed_version) = super::plugins::get_version(id) else {
+        bail!("Plugin {} is not installed", id);
+    };
+    let (plugin, package) = find(id)?;
+    if !deps::version_matches(&plugin.version, &format!(">{}", installed_version)) {
+        bail!("Plugin {} {} is up to date", id, installed_version);
+    }
+    log::info!(
+        "Update plugin {} from {} to {}",
+        id,
+        installed_version,
+        plugin.version
+    );
+    manager::install_plugin_package(id, &plugin.version, &package.url, &package.sha256)
+}
+
+#[inline]
+pub fn uninstall(id: &str) {
+    manager::uninstall_plugin(id, true);
+}
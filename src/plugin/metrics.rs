@@ -0,0 +1,142 @@
+//! Named counters and gauges reported by the plugins.
+//!
+//! A plugin reports a metric by the `msg` callback with target "metrics" and the json content
+//! `{"name": "frames_processed", "type": "counter", "value": 1}`.
+//! A counter adds `value` to its total, a gauge is set to `value`.
+//!
+//! The metrics of a plugin are pushed in a `plugin_metrics` event after they are changed,
+//! at most once per `EVENT_INTERVAL`.
+use super::{errno, PluginReturn, MSG_TO_UI_TYPE_PLUGIN_METRICS};
+use crate::flutter;
+use hbb_common::{get_time, lazy_static, log};
+use serde_derive::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, RwLock,
+    },
+    time::Duration,
+};
+
+const EVENT_INTERVAL: Duration = Duration::from_secs(1);
+// Limit the metrics of one plugin, the names are chosen by the plugins.
+const MAX_METRICS_PER_PLUGIN: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MetricType {
+    Counter,
+    Gauge,
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct MsgToMetrics {
+    pub name: String,
+    pub r#type: MetricType,
+    pub value: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Metric {
+    pub r#type: MetricType,
+    pub value: f64,
+    /// Unix time in milliseconds.
+    pub updated: i64,
+}
+
+lazy_static::lazy_static! {
+    static ref METRICS: RwLock<HashMap<String, HashMap<String, Metric>>> = Default::default();
+    // The plugins whose metrics are changed since the last event.
+    static ref DIRTY: Mutex<HashSet<String>> = Default::default();
+}
+
+static NOTIFIER_STARTED: AtomicBool = AtomicBool::new(false);
+
+pub(super) fn report(id: &str, msg: MsgToMetrics) -> PluginReturn {
+    if !msg.value.is_finite() {
+        return PluginReturn::new(
+            errno::ERR_CALLBACK_INVALID_ARGS,
+            &format!("Invalid value of metric '{}'", msg.name),
+        );
+    }
+    {
+        let mut metrics = METRICS.write().unwrap();
+        let metrics = metrics.entry(id.to_owned()).or_default();
+        if !metrics.contains_key(&msg.name) && metrics.len() >= MAX_METRICS_PER_PLUGIN {
+            return PluginReturn::new(
+                errno::ERR_CALLBACK_INVALID_ARGS,
+                &format!("Too many metrics, max {}", MAX_METRICS_PER_PLUGIN),
+            );
+        }
+        let metric = metrics.entry(msg.name).or_insert(Metric {
+            r#type: msg.r#type,
+            value: 0.0,
+            updated: 0,
+        });
+        if metric.r#type != msg.r#type {
+            // The type is changed by the plugin, start over.
+            metric.r#type = msg.r#type;
+            metric.value = 0.0;
+        }
+        match msg.r#type {
+            MetricType::Counter => metric.value += msg.value,
+            MetricType::Gauge => metric.value = msg.value,
+        }
+        metric.updated = get_time();
+    }
+    DIRTY.lock().unwrap().insert(id.to_owned());
+    start_notifier();
+    PluginReturn::success()
+}
+
+fn start_notifier() {
+    if NOTIFIER_STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    std::thread::spawn(|| loop {
+        std::thread::sleep(EVENT_INTERVAL);
+        let ids = std::mem::take(&mut *DIRTY.lock().unwrap());
+        for id in ids {
+            push_metrics_event(&id);
+        }
+    });
+}
+
+fn push_metrics_event(id: &str) {
+    let Some(metrics) = get(id) else {
+        return;
+    };
+    let event = serde_json::json!({
+        "name": MSG_TO_UI_TYPE_PLUGIN_METRICS,
+        "id": id,
+        "metrics": metrics,
+    });
+    match serde_json::to_string(&event) {
+        Ok(event) => {
+            super::unity::notify_metrics_event(&event);
+            let _res = flutter::push_global_event(flutter::APP_TYPE_MAIN, event);
+        }
+        Err(e) => log::error!("Failed to serialize the metrics of plugin {}, {}", id, e),
+    }
+}
+
+#[inline]
+pub fn get(id: &str) -> Option<HashMap<String, Metric>> {
+    METRICS.read().unwrap().get(id).cloned()
+}
+
+#[inline]
+pub fn get_all() -> HashMap<String, HashMap<String, Metric>> {
+    METRICS.read().unwrap().clone()
+}
+
+pub(super) fn remove(id: &str) {
+    METRICS.write().unwrap().remove(id);
+    DIRTY.lock().unwrap().remove(id);
+}
+
+pub(super) fn remove_all() {
+    METRICS.write().unwrap().clear();
+    DIRTY.lock().unwrap().clear();
+}
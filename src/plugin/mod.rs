@@ -5,21 +5,35 @@ use std::{
     ffi::{c_char, c_int, c_void, CStr},
     path::PathBuf,
     ptr::null,
+    sync::atomic::{AtomicBool, Ordering},
 };
 
 mod callback_ext;
 mod callback_msg;
 mod config;
+mod deps;
 pub mod desc;
+mod dispatch;
 mod errno;
 pub mod ipc;
+mod isolated;
+pub mod lang;
 mod manager;
+pub mod marketplace;
+pub mod metrics;
 pub mod native;
 pub mod native_handlers;
+pub mod peer_msg;
+pub mod permission;
 mod plog;
 mod plugins;
+mod ui_schema;
 mod unity;
+#[cfg(feature = "plugin_wasm")]
+mod wasm;
+pub mod watcher;
 
+pub use isolated::run_plugin_host;
 pub use manager::{
     install::{change_uninstall_plugin, install_plugin_with_url},
     install_plugin, load_plugin_list, remove_uninstalled, uninstall_plugin,
@@ -33,14 +47,19 @@ const MSG_TO_UI_TYPE_PLUGIN_EVENT: &str = "plugin_event";
 const MSG_TO_UI_TYPE_PLUGIN_RELOAD: &str = "plugin_reload";
 const MSG_TO_UI_TYPE_PLUGIN_OPTION: &str = "plugin_option";
 const MSG_TO_UI_TYPE_PLUGIN_MANAGER: &str = "plugin_manager";
+const MSG_TO_UI_TYPE_PLUGIN_METRICS: &str = "plugin_metrics";
 
 pub const EVENT_ON_CONN_CLIENT: &str = "on_conn_client";
 pub const EVENT_ON_CONN_SERVER: &str = "on_conn_server";
 pub const EVENT_ON_CONN_CLOSE_CLIENT: &str = "on_conn_close_client";
 pub const EVENT_ON_CONN_CLOSE_SERVER: &str = "on_conn_close_server";
+pub const EVENT_ON_LANG_CHANGED: &str = "on_lang_changed";
 
 static PLUGIN_SOURCE_LOCAL_DIR: &str = "plugins";
 
+static INITIALIZED: AtomicBool = AtomicBool::new(false);
+static IPC_STARTED: AtomicBool = AtomicBool::new(false);
+
 pub use config::{ManagerConfig, PeerConfig, SharedConfig};
 
 /// Common plugin return.
@@ -99,9 +118,17 @@ fn is_server_running() -> bool {
     crate::common::is_server() || crate::common::is_server_running()
 }
 
+/// Initialize the plugin framework, it's ok to call this function multiple times.
 pub fn init() {
+    if INITIALIZED.swap(true, Ordering::SeqCst) {
+        log::info!("Plugin framework is already initialized");
+        return;
+    }
     if !is_server_running() {
-        std::thread::spawn(move || manager::start_ipc());
+        // The ipc listener lives until the process exits, and survives `uninit()`.
+        if !IPC_STARTED.swap(true, Ordering::SeqCst) {
+            std::thread::spawn(move || manager::start_ipc());
+        }
     } else {
         if let Err(e) = remove_uninstalled() {
             log::error!("Failed to remove plugins: {}", e);
@@ -117,6 +144,20 @@ pub fn init() {
             log::error!("Failed to load plugins: {}", e);
         }
     }
+    if watcher::is_enabled_by_config() {
+        watcher::start();
+    }
+}
+
+/// Unload all plugins and unregister the embedder callbacks, `init()` can be called again after this.
+pub fn uninit() {
+    if !INITIALIZED.swap(false, Ordering::SeqCst) {
+        return;
+    }
+    watcher::stop();
+    unity::unregister_event_callback();
+    plugins::unload_plugins();
+    log::info!("Plugin framework uninitialized");
 }
 
 #[inline]
@@ -144,8 +185,22 @@ fn get_plugins_dir() -> ResultType<PathBuf> {
         .join(PLUGIN_SOURCE_LOCAL_DIR))
 }
 
+/// The ids are the names of the plugin directories, only `[A-Za-z0-9._-]` without `..`.
+#[inline]
+pub(super) fn is_valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && !id.starts_with('.')
+        && !id.contains("..")
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'.' || b == b'_' || b == b'-')
+}
+
 #[inline]
 fn get_plugin_dir(id: &str) -> ResultType<PathBuf> {
+    if !is_valid_id(id) {
+        bail!("Invalid plugin id: {}", id);
+    }
     Ok(get_plugins_dir()?.join(id))
 }
 
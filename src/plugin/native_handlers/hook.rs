@@ -0,0 +1,378 @@
+use std::{
+    collections::HashMap,
+    ffi::{c_char, c_int, c_void, CString},
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use hbb_common::{
+    log,
+    message_proto::{message, Message},
+    protobuf::Message as _,
+};
+
+use crate::{client::Data, define_method_prefix, plugin::permission};
+
+use super::PluginNativeHandler;
+
+/// Callback to process a decoded frame of all the sessions in place,
+/// e.g. to annotate, redact regions or watermark it, requires the `video` permission.
+///
+/// It is called on the video thread before the frame is passed to the UI renderer and the
+/// Unity/Unreal bridges, so it should return quickly. The frame size can't be changed.
+/// Frames rendered as textures (`vram`) don't go through the hooks.
+///
+/// format: 0 raw, 1 ABGR, 2 ARGB, the same as `rustdesk_unity_get_capabilities`.
+pub type OnFrameHookCallback = unsafe extern "C" fn(
+    peer_id: *const c_char,
+    display: usize,
+    data: *mut u8,
+    len: usize,
+    width: usize,
+    height: usize,
+    stride: usize,
+    format: u32,
+);
+
+pub const INPUT_HOOK_PASS: c_int = 0;
+pub const INPUT_HOOK_REPLACE: c_int = 1;
+pub const INPUT_HOOK_DROP: c_int = 2;
+
+/// Callback to observe, modify or suppress the input events sent to the peers, requires the `input` permission.
+///
+/// msg, len: The protobuf `Message` with a `key_event`, `mouse_event` or `pointer_device_event`.
+/// out, out_len: The replacement `Message` if `INPUT_HOOK_REPLACE` is returned.
+///               The plugin allocate memory with `libc::malloc` and return the pointer.
+///
+/// Returns `INPUT_HOOK_PASS`, `INPUT_HOOK_REPLACE` or `INPUT_HOOK_DROP`.
+pub type OnInputHookCallback = unsafe extern "C" fn(
+    peer_id: *const c_char,
+    msg: *const u8,
+    len: usize,
+    out: *mut *mut c_void,
+    out_len: *mut usize,
+) -> c_int;
+
+/// Callback to observe or transform the decoded audio of the sessions in place,
+/// e.g. to normalize the volume or to record it, requires the `audio` permission.
+///
+/// It is called on the audio thread before the audio is played, so it should return quickly.
+/// data: The interleaved f32 samples, `samples` is the number of the frames multiplied by `channels`.
+pub type OnAudioHookCallback = unsafe extern "C" fn(
+    peer_id: *const c_char,
+    data: *mut f32,
+    samples: usize,
+    sample_rate: u32,
+    channels: u16,
+);
+
+// (plugin id, callback, running guard of the plugin), called in the order of registration.
+type Hooks<T> = Vec<(String, T, Arc<()>)>;
+
+lazy_static::lazy_static! {
+    static ref FRAME_HOOKS: Arc<RwLock<Hooks<OnFrameHookCallback>>> = Default::default();
+    static ref INPUT_HOOKS: Arc<RwLock<Hooks<OnInputHookCallback>>> = Default::default();
+    static ref AUDIO_HOOKS: Arc<RwLock<Hooks<OnAudioHookCallback>>> = Default::default();
+    // The guard of each plugin with hooks, cloned while its callbacks are running,
+    // so the plugin is unloaded only after they return.
+    static ref RUNNING: Arc<RwLock<HashMap<String, Arc<()>>>> = Default::default();
+}
+
+fn running_guard(id: &str) -> Arc<()> {
+    RUNNING
+        .write()
+        .unwrap()
+        .entry(id.to_owned())
+        .or_default()
+        .clone()
+}
+
+fn add_hook<T>(hooks: &RwLock<Hooks<T>>, id: &str, cb: T) {
+    let running = running_guard(id);
+    let mut hooks = hooks.write().unwrap();
+    hooks.retain(|(i, _, _)| i != id);
+    hooks.push((id.to_owned(), cb, running));
+}
+
+// Copy the callbacks to call them without the lock, the callbacks may add or remove hooks.
+fn get_hooks<T: Clone>(hooks: &RwLock<Hooks<T>>) -> Hooks<T> {
+    hooks.read().unwrap().clone()
+}
+
+fn wait_running(running: Arc<()>) {
+    while Arc::strong_count(&running) > 1 {
+        std::thread::sleep(Duration::from_millis(1));
+    }
+}
+
+fn error_return(msg: &'static str) -> super::NR {
+    super::NR {
+        return_type: -1,
+        data: msg.as_ptr() as _,
+    }
+}
+
+/// Handler of the hooks on the media and input pipelines.
+///
+/// The json data of the methods is `{"id": "<plugin id>"}`, and the callback is passed as the raw data.
+/// `send_input` synthesizes an input event, its json data is `{"id": "<plugin id>", "peer": "<peer id>"}`
+/// and the raw data is the protobuf `Message`, it doesn't pass the input hooks.
+#[derive(Default)]
+pub struct PluginNativeHookHandler;
+
+impl PluginNativeHandler for PluginNativeHookHandler {
+    define_method_prefix!("hook_");
+
+    fn on_message(
+        &self,
+        method: &str,
+        data: &serde_json::Map<String, serde_json::Value>,
+    ) -> Option<super::NR> {
+        let id = data.get("id").and_then(|id| id.as_str())?;
+        match method {
+            "remove_frame_hook" => {
+                FRAME_HOOKS.write().unwrap().retain(|(i, _, _)| i != id);
+                Some(super::NR {
+                    return_type: 0,
+                    data: std::ptr::null(),
+                })
+            }
+            "remove_input_hook" => {
+                INPUT_HOOKS.write().unwrap().retain(|(i, _, _)| i != id);
+                Some(super::NR {
+                    return_type: 0,
+                    data: std::ptr::null(),
+                })
+            }
+            "remove_audio_hook" => {
+                AUDIO_HOOKS.write().unwrap().retain(|(i, _, _)| i != id);
+                Some(super::NR {
+                    return_type: 0,
+                    data: std::ptr::null(),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    fn on_message_raw(
+        &self,
+        method: &str,
+        data: &serde_json::Map<String, serde_json::Value>,
+        raw: *const c_void,
+        raw_len: usize,
+    ) -> Option<super::NR> {
+        let id = data.get("id").and_then(|id| id.as_str())?;
+        match method {
+            "add_frame_hook" => {
+                if permission::check(id, permission::PERMISSION_VIDEO).is_err() {
+                    return Some(error_return("video permission denied\0"));
+                }
+                if raw.is_null() {
+                    return Some(error_return("null callback\0"));
+                }
+                let cb: OnFrameHookCallback = unsafe { std::mem::transmute(raw) };
+                add_hook(&FRAME_HOOKS, id, cb);
+                log::info!("Plugin {} added a frame hook", id);
+                Some(super::NR {
+                    return_type: 0,
+                    data: std::ptr::null(),
+                })
+            }
+            "add_input_hook" => {
+                if permission::check(id, permission::PERMISSION_INPUT).is_err() {
+                    return Some(error_return("input permission denied\0"));
+                }
+                if raw.is_null() {
+                    return Some(error_return("null callback\0"));
+                }
+                let cb: OnInputHookCallback = unsafe { std::mem::transmute(raw) };
+                add_hook(&INPUT_HOOKS, id, cb);
+                log::info!("Plugin {} added an input hook", id);
+                Some(super::NR {
+                    return_type: 0,
+                    data: std::ptr::null(),
+                })
+            }
+            "add_audio_hook" => {
+                if permission::check(id, permission::PERMISSION_AUDIO).is_err() {
+                    return Some(error_return("audio permission denied\0"));
+                }
+                if raw.is_null() {
+                    return Some(error_return("null callback\0"));
+                }
+                let cb: OnAudioHookCallback = unsafe { std::mem::transmute(raw) };
+                add_hook(&AUDIO_HOOKS, id, cb);
+                log::info!("Plugin {} added an audio hook", id);
+                Some(super::NR {
+                    return_type: 0,
+                    data: std::ptr::null(),
+                })
+            }
+            "send_input" => {
+                if permission::check(id, permission::PERMISSION_INPUT).is_err() {
+                    return Some(error_return("input permission denied\0"));
+                }
+                let Some(peer) = data.get("peer").and_then(|p| p.as_str()) else {
+                    return Some(error_return("missing peer field\0"));
+                };
+                let bytes = unsafe { std::slice::from_raw_parts(raw as *const u8, raw_len) };
+                let msg = match Message::parse_from_bytes(bytes) {
+                    Ok(msg) if is_input(&msg) => msg,
+                    _ => return Some(error_return("invalid input message\0")),
+                };
+                let Some(session) = crate::flutter::sessions::get_session_by_peer_id(
+                    peer.to_owned(),
+                    hbb_common::rendezvous_proto::ConnType::DEFAULT_CONN,
+                ) else {
+                    return Some(error_return("session not found\0"));
+                };
+                session.send_unhooked(Data::Message(msg));
+                Some(super::NR {
+                    return_type: 0,
+                    data: std::ptr::null(),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Remove the hooks of the plugin and wait for its running callbacks,
+/// they must not be called after the plugin is unloaded.
+///
+/// Must not be called by the callbacks, which would wait for themselves.
+pub(crate) fn remove_plugin_hooks(id: &str) {
+    FRAME_HOOKS.write().unwrap().retain(|(i, _, _)| i != id);
+    INPUT_HOOKS.write().unwrap().retain(|(i, _, _)| i != id);
+    AUDIO_HOOKS.write().unwrap().retain(|(i, _, _)| i != id);
+    let running = RUNNING.write().unwrap().remove(id);
+    if let Some(running) = running {
+        wait_running(running);
+    }
+}
+
+pub(crate) fn remove_all_hooks() {
+    FRAME_HOOKS.write().unwrap().clear();
+    INPUT_HOOKS.write().unwrap().clear();
+    AUDIO_HOOKS.write().unwrap().clear();
+    let running = std::mem::take(&mut *RUNNING.write().unwrap());
+    running.into_values().for_each(wait_running);
+}
+
+/// Pass the decoded frame through the frame hooks of the plugins.
+pub fn on_video_frame(peer_id: &str, display: usize, rgb: &mut scrap::ImageRgb) {
+    let hooks = get_hooks(&FRAME_HOOKS);
+    if hooks.is_empty() {
+        return;
+    }
+    let Ok(c_peer_id) = CString::new(peer_id) else {
+        return;
+    };
+    let stride = if rgb.h > 0 {
+        rgb.raw.len() / rgb.h
+    } else {
+        rgb.w * 4
+    };
+    let format = crate::unity::image_format_to_u32(rgb.fmt);
+    for (_, cb, _) in hooks.iter() {
+        unsafe {
+            cb(
+                c_peer_id.as_ptr(),
+                display,
+                rgb.raw.as_mut_ptr(),
+                rgb.raw.len(),
+                rgb.w,
+                rgb.h,
+                stride,
+                format,
+            );
+        }
+    }
+}
+
+#[inline]
+fn is_input(msg: &Message) -> bool {
+    matches!(
+        msg.union,
+        Some(message::Union::KeyEvent(_))
+            | Some(message::Union::MouseEvent(_))
+            | Some(message::Union::PointerDeviceEvent(_))
+    )
+}
+
+#[inline]
+pub fn has_input_hooks() -> bool {
+    !INPUT_HOOKS.read().unwrap().is_empty()
+}
+
+/// Pass the input event sent to the peer through the input hooks of the plugins.
+///
+/// Returns `None` if the event is dropped by a hook.
+pub fn on_input(peer_id: &str, data: Data) -> Option<Data> {
+    let Data::Message(mut msg) = data else {
+        return Some(data);
+    };
+    if !is_input(&msg) {
+        return Some(Data::Message(msg));
+    }
+    let Ok(c_peer_id) = CString::new(peer_id) else {
+        return Some(Data::Message(msg));
+    };
+    let hooks = get_hooks(&INPUT_HOOKS);
+    for (id, cb, _) in hooks.iter() {
+        let Ok(bytes) = msg.write_to_bytes() else {
+            break;
+        };
+        let mut out: *mut c_void = std::ptr::null_mut();
+        let mut out_len = 0usize;
+        let ret = unsafe {
+            cb(
+                c_peer_id.as_ptr(),
+                bytes.as_ptr(),
+                bytes.len(),
+                &mut out,
+                &mut out_len,
+            )
+        };
+        let replacement = if out.is_null() {
+            None
+        } else {
+            let slice = unsafe { std::slice::from_raw_parts(out as *const u8, out_len) };
+            let replacement = Message::parse_from_bytes(slice);
+            super::super::free_c_ptr(out);
+            Some(replacement)
+        };
+        match ret {
+            INPUT_HOOK_DROP => return None,
+            INPUT_HOOK_REPLACE => match replacement {
+                Some(Ok(m)) if is_input(&m) => msg = m,
+                _ => log::error!("Plugin {} replaced the input with an invalid message", id),
+            },
+            _ => {}
+        }
+    }
+    Some(Data::Message(msg))
+}
+
+/// Pass the decoded audio of the session through the audio hooks of the plugins.
+pub fn on_audio(peer_id: &str, sample_rate: u32, channels: u16, data: &mut [f32]) {
+    let hooks = get_hooks(&AUDIO_HOOKS);
+    if hooks.is_empty() {
+        return;
+    }
+    let Ok(c_peer_id) = CString::new(peer_id) else {
+        return;
+    };
+    for (_, cb, _) in hooks.iter() {
+        unsafe {
+            cb(
+                c_peer_id.as_ptr(),
+                data.as_mut_ptr(),
+                data.len(),
+                sample_rate,
+                channels,
+            );
+        }
+    }
+}
@@ -10,10 +10,13 @@ use serde_json::Map;
 
 use crate::return_if_not_method;
 
-use self::{session::PluginNativeSessionHandler, ui::PluginNativeUIHandler};
+use self::{
+    hook::PluginNativeHookHandler, session::PluginNativeSessionHandler, ui::PluginNativeUIHandler,
+};
 
 use super::cstr_to_string;
 
+pub mod hook;
 mod macros;
 pub mod session;
 pub mod ui;
@@ -38,6 +41,7 @@ impl Default for PluginNativeHandlerRegistrar {
                 // Add prebuilt native handlers here.
                 Box::new(PluginNativeSessionHandler::default()),
                 Box::new(PluginNativeUIHandler::default()),
+                Box::new(PluginNativeHookHandler::default()),
             ])),
         }
     }
@@ -0,0 +1,108 @@
+//! Limits of the plugin messages exchanged between the peers.
+//!
+//! The messages are the `PluginRequest` misc frames on the established connection,
+//! sent by the `msg` callback with target "peer" on either side, and passed to the plugin
+//! with the same id on the other side as "handle_peer" calls.
+//!
+//! The size of a message and the rate per plugin and peer are limited in both directions,
+//! the incoming messages over the limits are dropped.
+use super::{errno, PluginReturn};
+use hbb_common::{lazy_static, log};
+use std::{collections::HashMap, sync::Mutex, time::Instant};
+
+pub const MAX_MESSAGE_SIZE: usize = 1024 * 1024;
+const MAX_MESSAGES_PER_SEC: f64 = 100.;
+const MAX_BYTES_PER_SEC: f64 = 4. * 1024. * 1024.;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Direction {
+    Outgoing,
+    Incoming,
+}
+
+// A token bucket of the messages and the bytes, refilled per second.
+struct Bucket {
+    messages: f64,
+    bytes: f64,
+    last: Instant,
+}
+
+impl Bucket {
+    fn new() -> Self {
+        Self {
+            messages: MAX_MESSAGES_PER_SEC,
+            bytes: MAX_BYTES_PER_SEC,
+            last: Instant::now(),
+        }
+    }
+
+    fn take(&mut self, len: usize) -> bool {
+        let elapsed = self.last.elapsed().as_secs_f64();
+        self.last = Instant::now();
+        self.messages = (self.messages + elapsed * MAX_MESSAGES_PER_SEC).min(MAX_MESSAGES_PER_SEC);
+        self.bytes = (self.bytes + elapsed * MAX_BYTES_PER_SEC).min(MAX_BYTES_PER_SEC);
+        if self.messages < 1. || self.bytes < len as f64 {
+            return false;
+        }
+        self.messages -= 1.;
+        self.bytes -= len as f64;
+        true
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref BUCKETS: Mutex<HashMap<(String, String, Direction), Bucket>> = Default::default();
+}
+
+fn take(id: &str, peer: &str, direction: Direction, len: usize) -> bool {
+    BUCKETS
+        .lock()
+        .unwrap()
+        .entry((id.to_owned(), peer.to_owned(), direction))
+        .or_insert_with(Bucket::new)
+        .take(len)
+}
+
+/// Check the message of plugin `id` to be sent to `peer`.
+pub(super) fn check_outgoing(id: &str, peer: &str, len: usize) -> Result<(), PluginReturn> {
+    if len > MAX_MESSAGE_SIZE {
+        return Err(PluginReturn::new(
+            errno::ERR_CALLBACK_INVALID_MSG,
+            &format!("Message is too large, max {} bytes", MAX_MESSAGE_SIZE),
+        ));
+    }
+    if !take(id, peer, Direction::Outgoing, len) {
+        return Err(PluginReturn::new(
+            errno::ERR_CALLBACK_FAILED,
+            "Message rate limit exceeded",
+        ));
+    }
+    Ok(())
+}
+
+/// Whether the message of plugin `id` from `peer` should be handled.
+pub fn accept_incoming(id: &str, peer: &str, len: usize) -> bool {
+    if len > MAX_MESSAGE_SIZE {
+        log::warn!(
+            "Drop the plugin {} message from {}, {} bytes is too large",
+            id,
+            peer,
+            len
+        );
+        return false;
+    }
+    if !take(id, peer, Direction::Incoming, len) {
+        log::warn!(
+            "Drop the plugin {} message from {}, rate limit exceeded",
+            id,
+            peer
+        );
+        return false;
+    }
+    true
+}
+
+/// Remove the limits of the peer after the connection is closed.
+pub fn remove_peer(peer: &str) {
+    BUCKETS.lock().unwrap().retain(|(_, p, _), _| p != peer);
+}
@@ -0,0 +1,43 @@
+//! Capability permissions of the plugins.
+//!
+//! A plugin declares the capabilities it needs in the `permissions` of its description,
+//! e.g. `"permissions": ["network"]`. The host callbacks check them before doing the operation,
+//! and return `ERR_CALLBACK_PERMISSION_DENIED` with a `plugin_permission_denied` manager event if denied.
+//! Plugins without `permissions` are not restricted.
+//!
+//! Native plugins run in the host process, only the operations done through the `msg` callback are checked.
+//! Wasm plugins have no other way to reach the host.
+use super::{errno, plugins, PluginReturn};
+use hbb_common::log;
+
+/// Send messages to the peers and requests to the servers.
+pub const PERMISSION_NETWORK: &str = "network";
+/// Read and modify the decoded video frames.
+pub const PERMISSION_VIDEO: &str = "video";
+/// Read and modify the decoded audio.
+pub const PERMISSION_AUDIO: &str = "audio";
+/// Inject or block the keyboard and mouse input.
+pub const PERMISSION_INPUT: &str = "input";
+
+pub fn is_granted(id: &str, permission: &str) -> bool {
+    match plugins::get_plugin_infos().read().unwrap().get(id) {
+        Some(info) => info
+            .desc
+            .permissions()
+            .map_or(true, |p| p.iter().any(|p| p == permission)),
+        None => false,
+    }
+}
+
+/// Returns the error to return to the plugin if `permission` is not granted to plugin `id`.
+pub(super) fn check(id: &str, permission: &str) -> Result<(), PluginReturn> {
+    if is_granted(id, permission) {
+        return Ok(());
+    }
+    log::warn!("Plugin {} is denied the permission '{}'", id, permission);
+    super::manager::push_permission_denied_event(id, permission);
+    Err(PluginReturn::new(
+        errno::ERR_CALLBACK_PERMISSION_DENIED,
+        &format!("Plugin '{}' has no '{}' permission", id, permission),
+    ))
+}
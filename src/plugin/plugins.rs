@@ -1,4 +1,10 @@
-use super::{desc::Desc, errno::*, *};
+use super::{
+    deps,
+    desc::Desc,
+    errno::*,
+    manager::{LifecycleEvent, LIFECYCLE_INIT, LIFECYCLE_LOAD, LIFECYCLE_RELOAD, LIFECYCLE_UNLOAD},
+    *,
+};
 #[cfg(not(debug_assertions))]
 use crate::common::is_server;
 use crate::flutter;
@@ -34,6 +40,49 @@ pub(super) struct PluginInfo {
     pub desc: Desc,
 }
 
+/// The errors of loading a plugin, the category and the details are sent in the lifecycle events.
+#[derive(Debug)]
+pub(super) enum LoadError {
+    Library(String),
+    // A missing or mismatched export, e.g. built against another ABI.
+    Symbol { symbol: &'static str, error: String },
+    Desc(String),
+    Init { code: i32, msg: String },
+    Incompatible(String),
+    // The UI descriptors do not match the schema, see `super::ui_schema`.
+    Ui(String),
+}
+
+impl LoadError {
+    pub(super) fn category(&self) -> &'static str {
+        match self {
+            LoadError::Library(_) => "library",
+            LoadError::Symbol { .. } => "symbol",
+            LoadError::Desc(_) => "desc",
+            LoadError::Init { .. } => "init",
+            LoadError::Incompatible(_) => "incompatible",
+            LoadError::Ui(_) => "ui",
+        }
+    }
+}
+
+impl std::fmt::Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadError::Library(e) => write!(f, "failed to load library, {}", e),
+            LoadError::Symbol { symbol, error } => {
+                write!(f, "failed to load func {}, {}", symbol, error)
+            }
+            LoadError::Desc(e) => write!(f, "invalid description, {}", e),
+            LoadError::Init { code, msg } => write!(f, "init failed, code: {}, msg: {}", code, msg),
+            LoadError::Incompatible(e) => write!(f, "incompatible, {}", e),
+            LoadError::Ui(e) => write!(f, "invalid ui, {}", e),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
 /// Initialize the plugins.
 ///
 /// data: The initialize data.
@@ -133,18 +182,22 @@ type PluginFuncCallWithOutData = extern "C" fn(
 /// log: The callback to log.
 #[repr(C)]
 #[derive(Copy, Clone)]
-struct Callbacks {
-    msg: CallbackMsg,
-    get_conf: CallbackGetConf,
-    get_id: CallbackGetId,
-    log: CallbackLog,
-    native: CallbackNative,
+pub(super) struct Callbacks {
+    pub(super) msg: CallbackMsg,
+    pub(super) get_conf: CallbackGetConf,
+    pub(super) get_id: CallbackGetId,
+    pub(super) log: CallbackLog,
+    pub(super) native: CallbackNative,
 }
 
 #[derive(Serialize)]
 #[repr(C)]
 struct InitInfo {
     is_server: bool,
+    // The current UI language, e.g. "en", "zh-cn".
+    lang: String,
+    // The negotiated version of the UI schema.
+    ui_schema_version: u32,
 }
 
 /// The plugin initialize data.
@@ -152,12 +205,22 @@ struct InitInfo {
 /// local_peer_id: The local peer id, can't be nullptr.
 /// cbs: The callbacks.
 #[repr(C)]
-struct InitData {
+pub(super) struct InitData {
     version: *const c_char,
     info: *const c_char,
     cbs: Callbacks,
 }
 
+impl InitData {
+    pub(super) fn new(info: &str, cbs: Callbacks) -> Self {
+        Self {
+            version: str_to_cstr_ret(crate::VERSION),
+            info: str_to_cstr_ret(info) as _,
+            cbs,
+        }
+    }
+}
+
 impl Drop for InitData {
     fn drop(&mut self) {
         free_c_ptr(self.version as _);
@@ -172,15 +235,17 @@ macro_rules! make_plugin {
             _lib: Library,
             id: Option<String>,
             path: String,
+            // `clear()` is only called if `init()` was called.
+            initialized: bool,
             $($field: $tp),+
         }
 
         impl Plugin {
-            fn new(path: &str) -> ResultType<Self> {
+            pub(super) fn new(path: &str) -> ResultType<Self> {
                 let lib = match Library::open(path) {
                     Ok(lib) => lib,
                     Err(e) => {
-                        bail!("Failed to load library {}, {}", path, e);
+                        return Err(LoadError::Library(e.to_string()).into());
                     }
                 };
 
@@ -189,7 +254,10 @@ macro_rules! make_plugin {
                             *m
                         },
                         Err(e) => {
-                            bail!("Failed to load {} func {}, {}", path, stringify!($field), e);
+                            return Err(LoadError::Symbol {
+                                symbol: stringify!($field),
+                                error: e.to_string(),
+                            }.into());
                         }
                     }
                 ;)+
@@ -198,27 +266,27 @@ macro_rules! make_plugin {
                     _lib: lib,
                     id: None,
                     path: path.to_string(),
+                    initialized: false,
                     $( $field ),+
                 })
             }
 
-            fn desc(&self) -> ResultType<Desc> {
+            pub(super) fn desc(&self) -> ResultType<Desc> {
                 let desc_ret = (self.desc)();
+                if desc_ret.is_null() {
+                    return Err(LoadError::Desc("null description".to_owned()).into());
+                }
                 let desc = Desc::from_cstr(desc_ret);
                 free_c_ptr(desc_ret as _);
-                desc
+                desc.map_err(|e| LoadError::Desc(e.to_string()).into())
             }
 
-            fn init(&self, data: &InitData, path: &str) -> ResultType<()> {
+            pub(super) fn init(&mut self, data: &InitData, path: &str) -> ResultType<()> {
                 let mut init_ret = (self.init)(data as _);
+                self.initialized = true;
                 if !init_ret.is_success() {
                     let (code, msg) = init_ret.get_code_msg(path);
-                    bail!(
-                        "Failed to init plugin {}, code: {}, msg: {}",
-                        path,
-                        code,
-                        msg
-                    );
+                    return Err(LoadError::Init { code, msg }.into());
                 }
                 Ok(())
             }
@@ -239,6 +307,9 @@ macro_rules! make_plugin {
 
         impl Drop for Plugin {
             fn drop(&mut self) {
+                if !self.initialized {
+                    return;
+                }
                 let id = self.id.as_ref().unwrap_or(&self.path);
                 self.clear(id);
             }
@@ -255,9 +326,52 @@ make_plugin!(
     call_with_out_data: PluginFuncCallWithOutData
 );
 
+impl Plugin {
+    /// Call the plugin, `method` and `peer` are null terminated.
+    ///
+    /// Returns the out data if `with_out` is set and the plugin returns it.
+    pub(super) fn call_raw(
+        &self,
+        method: &[u8],
+        peer: &[u8],
+        args: &[u8],
+        with_out: bool,
+    ) -> (PluginReturn, Option<Vec<u8>>) {
+        if !with_out {
+            let ret = (self.call)(
+                method.as_ptr() as _,
+                peer.as_ptr() as _,
+                args.as_ptr() as _,
+                args.len(),
+            );
+            return (ret, None);
+        }
+        let mut out = std::ptr::null_mut();
+        let mut out_len: usize = 0;
+        let ret = (self.call_with_out_data)(
+            method.as_ptr() as _,
+            peer.as_ptr() as _,
+            args.as_ptr() as _,
+            args.len(),
+            &mut out as _,
+            &mut out_len as _,
+        );
+        let out_data = if out.is_null() {
+            None
+        } else {
+            let data = unsafe { std::slice::from_raw_parts(out as *const u8, out_len) }.to_vec();
+            free_c_ptr(out as _);
+            Some(data)
+        };
+        (ret, out_data)
+    }
+}
+
 #[derive(Serialize)]
 pub struct MsgListenEvent {
     pub event: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>,
 }
 
 #[cfg(target_os = "windows")]
@@ -272,6 +386,7 @@ pub(super) fn load_plugins(uninstalled_ids: &HashSet<String>) -> ResultType<()>
     if !plugins_dir.exists() {
         std::fs::create_dir_all(&plugins_dir)?;
     } else {
+        let mut paths = Vec::new();
         for entry in std::fs::read_dir(plugins_dir)? {
             match entry {
                 Ok(entry) => {
@@ -285,7 +400,7 @@ pub(super) fn load_plugins(uninstalled_ids: &HashSet<String>) -> ResultType<()>
                                 );
                                 continue;
                             }
-                            load_plugin_dir(&plugin_dir);
+                            paths.extend(get_plugin_paths(&plugin_dir));
                         }
                     }
                 }
@@ -294,12 +409,15 @@ pub(super) fn load_plugins(uninstalled_ids: &HashSet<String>) -> ResultType<()>
                 }
             }
         }
+        // Load all plugins together, so that they can be ordered by their dependencies.
+        load_plugin_paths(paths);
     }
     Ok(())
 }
 
-fn load_plugin_dir(dir: &Path) {
+fn get_plugin_paths(dir: &Path) -> Vec<String> {
     log::debug!("Begin load plugin dir: {}", dir.display());
+    let mut paths = Vec::new();
     if let Ok(rd) = std::fs::read_dir(dir) {
         for entry in rd {
             match entry {
@@ -308,11 +426,14 @@ fn load_plugin_dir(dir: &Path) {
                     if path.is_file() {
                         let filename = entry.file_name();
                         let filename = filename.to_str().unwrap_or("");
-                        if filename.starts_with("plugin_") && filename.ends_with(DYLIB_SUFFIX) {
+                        #[cfg(feature = "plugin_wasm")]
+                        let is_plugin = filename.ends_with(DYLIB_SUFFIX)
+                            || filename.ends_with(super::wasm::WASM_SUFFIX);
+                        #[cfg(not(feature = "plugin_wasm"))]
+                        let is_plugin = filename.ends_with(DYLIB_SUFFIX);
+                        if filename.starts_with("plugin_") && is_plugin {
                             if let Some(path) = path.to_str() {
-                                if let Err(e) = load_plugin_path(path) {
-                                    log::error!("Failed to load plugin {}, {}", filename, e);
-                                }
+                                paths.push(path.to_owned());
                             }
                         }
                     }
@@ -327,11 +448,148 @@ fn load_plugin_dir(dir: &Path) {
             }
         }
     }
+    paths
+}
+
+/// The id of the plugin in `path` before its description is read, the name of the plugin dir.
+pub(super) fn plugin_id_of_path(path: &str) -> Option<&str> {
+    Path::new(path)
+        .parent()
+        .and_then(|p| p.file_name())
+        .and_then(|f| f.to_str())
+}
+
+fn push_load_result(id: &str, action: &'static str, res: &ResultType<()>) {
+    let event = match res {
+        Ok(()) => LifecycleEvent::ok(action),
+        Err(e) => LifecycleEvent::failed(action, e),
+    };
+    super::manager::push_lifecycle_event(id, &event);
+}
+
+/// Load the plugins after the plugins they require.
+fn load_plugin_paths(paths: Vec<String>) {
+    let mut opened = Vec::new();
+    for path in paths {
+        match open_plugin_path(&path) {
+            Ok(plugin) => opened.push(plugin),
+            Err(e) => {
+                log::error!("Failed to load plugin {}, {}", path, e);
+                let id = plugin_id_of_path(&path).unwrap_or(&path);
+                push_load_result(id, LIFECYCLE_LOAD, &Err(e));
+            }
+        }
+    }
+    let (order, cyclic) = deps::load_order(&opened.iter().map(|p| &p.desc).collect::<Vec<_>>());
+    let mut opened = opened.into_iter().map(Some).collect::<Vec<_>>();
+    for i in cyclic {
+        if let Some(plugin) = opened[i].take() {
+            let id = &plugin.desc.meta().id;
+            log::error!("Failed to load plugin {}, circular dependency", id);
+            super::manager::push_incompatible_event(id, "circular dependency");
+            let e = LoadError::Incompatible("circular dependency".to_owned()).into();
+            push_load_result(id, LIFECYCLE_LOAD, &Err(e));
+        }
+    }
+    for i in order {
+        if let Some(plugin) = opened[i].take() {
+            let path = plugin.path.clone();
+            let id = plugin.desc.meta().id.clone();
+            let res = start_plugin(plugin);
+            if let Err(e) = &res {
+                log::error!("Failed to load plugin {}, {}", path, e);
+            }
+            push_load_result(&id, LIFECYCLE_LOAD, &res);
+        }
+    }
+}
+
+#[inline]
+fn is_loaded(id: &str) -> bool {
+    if super::isolated::contains(id) {
+        return true;
+    }
+    #[cfg(feature = "plugin_wasm")]
+    if super::wasm::contains(id) {
+        return true;
+    }
+    PLUGINS.read().unwrap().contains_key(id)
+}
+
+fn loaded_version(id: &str) -> Option<String> {
+    if !is_loaded(id) {
+        return None;
+    }
+    PLUGIN_INFO
+        .read()
+        .unwrap()
+        .get(id)
+        .map(|info| info.desc.meta().version.clone())
+}
+
+// The loaded plugins which require `id`.
+fn loaded_dependents(id: &str) -> Vec<String> {
+    PLUGIN_INFO
+        .read()
+        .unwrap()
+        .iter()
+        .filter(|(_, info)| info.desc.requires().iter().any(|r| r.id == id))
+        .map(|(dep, _)| dep.clone())
+        .filter(|dep| is_loaded(dep))
+        .collect()
 }
 
 pub fn unload_plugin(id: &str) {
+    unload_plugin_with_dependents(id);
+}
+
+/// Unload the plugin and the loaded plugins depending on it.
+///
+/// Returns the unloaded ids, the dependents come before the plugins they require.
+pub(super) fn unload_plugin_with_dependents(id: &str) -> Vec<String> {
+    let mut unloaded = Vec::new();
+    unload_plugin_recursive(id, &mut HashSet::new(), &mut unloaded);
+    unloaded
+}
+
+fn unload_plugin_recursive(id: &str, visited: &mut HashSet<String>, unloaded: &mut Vec<String>) {
+    visited.insert(id.to_owned());
+    for dep in loaded_dependents(id) {
+        if !visited.contains(&dep) {
+            log::info!("Plugin {} requires {}, unload it", dep, id);
+            super::manager::push_incompatible_event(
+                &dep,
+                &format!("required plugin {} is unloaded", id),
+            );
+            unload_plugin_recursive(&dep, visited, unloaded);
+        }
+    }
     log::info!("Plugin {} unloaded", id);
+    super::manager::push_lifecycle_event(id, &LifecycleEvent::ok(LIFECYCLE_UNLOAD));
+    super::dispatch::remove(id);
+    super::metrics::remove(id);
+    super::native_handlers::hook::remove_plugin_hooks(id);
     PLUGINS.write().unwrap().remove(id);
+    super::isolated::remove(id);
+    #[cfg(feature = "plugin_wasm")]
+    super::wasm::remove(id);
+    unloaded.push(id.to_owned());
+}
+
+pub(super) fn unload_plugins() {
+    super::dispatch::remove_all();
+    super::metrics::remove_all();
+    super::native_handlers::hook::remove_all_hooks();
+    // Take the plugins out first, `clear()` of the plugins may call back into the host.
+    let plugins = std::mem::take(&mut *PLUGINS.write().unwrap());
+    for id in plugins.keys() {
+        log::info!("Plugin {} unloaded", id);
+    }
+    drop(plugins);
+    super::isolated::remove_all();
+    #[cfg(feature = "plugin_wasm")]
+    super::wasm::remove_all();
+    PLUGIN_INFO.write().unwrap().clear();
 }
 
 pub(super) fn mark_uninstalled(id: &str, uninstalled: bool) {
@@ -348,54 +606,118 @@ pub fn reload_plugin(id: &str) -> ResultType<()> {
         Some(plugin) => plugin.path.clone(),
         None => bail!("Plugin {} not found", id),
     };
-    unload_plugin(id);
-    load_plugin_path(&path)
+    let unloaded = unload_plugin_with_dependents(id);
+    let res = load_plugin_path(&path);
+    push_load_result(id, LIFECYCLE_RELOAD, &res);
+    res?;
+    // Load the dependents back, they may be incompatible with the new version.
+    let paths = unloaded
+        .iter()
+        .filter(|dep| dep.as_str() != id)
+        .filter_map(|dep| PLUGIN_INFO.read().unwrap().get(dep).map(|p| p.path.clone()))
+        .collect();
+    load_plugin_paths(paths);
+    Ok(())
 }
 
-fn load_plugin_path(path: &str) -> ResultType<()> {
-    log::info!("Begin load plugin {}", path);
+enum PluginHandle {
+    Native(Plugin),
+    Isolated(super::isolated::IsolatedPlugin),
+    #[cfg(feature = "plugin_wasm")]
+    Wasm(super::wasm::WasmPlugin),
+}
+
+// A plugin whose description is read, but not initialized yet.
+struct OpenedPlugin {
+    path: String,
+    desc: Desc,
+    handle: PluginHandle,
+}
 
+fn open_plugin_path(path: &str) -> ResultType<OpenedPlugin> {
+    #[cfg(feature = "plugin_wasm")]
+    if path.ends_with(super::wasm::WASM_SUFFIX) {
+        let plugin = super::wasm::WasmPlugin::new(path)?;
+        return Ok(OpenedPlugin {
+            path: path.to_owned(),
+            desc: plugin.desc()?,
+            handle: PluginHandle::Wasm(plugin),
+        });
+    }
+    if super::isolated::is_enabled(path) {
+        let plugin = super::isolated::IsolatedPlugin::new(path)?;
+        return Ok(OpenedPlugin {
+            path: path.to_owned(),
+            desc: plugin.desc(),
+            handle: PluginHandle::Isolated(plugin),
+        });
+    }
     let plugin = Plugin::new(path)?;
-    let desc = plugin.desc()?;
+    Ok(OpenedPlugin {
+        path: path.to_owned(),
+        desc: plugin.desc()?,
+        handle: PluginHandle::Native(plugin),
+    })
+}
+
+fn load_plugin_path(path: &str) -> ResultType<()> {
+    start_plugin(open_plugin_path(path)?)
+}
+
+fn start_plugin(plugin: OpenedPlugin) -> ResultType<()> {
+    let OpenedPlugin { path, desc, handle } = plugin;
+    // The requests of the reserved ids are handled by the built-in channels.
+    if crate::common::is_reserved_plugin_request_id(&desc.meta().id) {
+        bail!("Plugin id {} is reserved", desc.meta().id);
+    }
+    if let Err(e) = deps::check(&desc, loaded_version) {
+        super::manager::push_incompatible_event(&desc.meta().id, &e);
+        return Err(LoadError::Incompatible(e).into());
+    }
+    if let Err(e) = super::ui_schema::check(&desc) {
+        super::manager::push_invalid_ui_event(&desc.meta().id, &e);
+        return Err(LoadError::Ui(e).into());
+    }
+    match handle {
+        PluginHandle::Native(plugin) => start_native_plugin(&path, &desc, plugin),
+        PluginHandle::Isolated(plugin) => start_isolated_plugin(&path, &desc, plugin),
+        #[cfg(feature = "plugin_wasm")]
+        PluginHandle::Wasm(plugin) => start_wasm_plugin(&path, &desc, plugin),
+    }
+}
+
+fn start_native_plugin(path: &str, desc: &Desc, mut plugin: Plugin) -> ResultType<()> {
+    log::info!("Begin load plugin {}", path);
 
     // to-do validate plugin
     // to-do check the plugin id (make sure it does not use another plugin's id)
 
     let id = desc.meta().id.clone();
-    let plugin_info = PluginInfo {
-        path: path.to_string(),
-        uninstalled: false,
-        desc: desc.clone(),
-    };
-    PLUGIN_INFO.write().unwrap().insert(id.clone(), plugin_info);
+    add_plugin_info(path, desc);
 
-    let init_info = serde_json::to_string(&InitInfo {
-        is_server: super::is_server_running(),
-    })?;
-    let init_data = InitData {
-        version: str_to_cstr_ret(crate::VERSION),
-        info: str_to_cstr_ret(&init_info) as _,
-        cbs: Callbacks {
+    let init_info = get_init_info(desc)?;
+    let init_data = InitData::new(
+        &init_info,
+        Callbacks {
             msg: callback_msg::cb_msg,
             get_conf: config::cb_get_conf,
             get_id: config::cb_get_local_peer_id,
             log: super::plog::plugin_log,
             native: super::native::cb_native_data,
         },
-    };
+    );
     // If do not load the plugin when init failed, the ui will not show the installed plugin.
     if let Err(e) = plugin.init(&init_data, path) {
         log::error!("Failed to init plugin '{}', {}", desc.meta().id, e);
+        push_load_result(&id, LIFECYCLE_INIT, &Err(e));
     }
 
-    if super::is_server_running() {
-        super::config::ManagerConfig::add_plugin(&desc.meta().id)?;
+    if let Err(e) = on_plugin_loaded(desc) {
+        // The hooks added by `init()` must not outlive the library.
+        super::native_handlers::hook::remove_plugin_hooks(&id);
+        return Err(e);
     }
 
-    // update ui
-    // Ui may be not ready now, so we need to update again once ui is ready.
-    reload_ui(&desc, None);
-
     // add plugins
     PLUGINS.write().unwrap().insert(id.clone(), plugin);
 
@@ -403,6 +725,91 @@ fn load_plugin_path(path: &str) -> ResultType<()> {
     Ok(())
 }
 
+fn start_isolated_plugin(
+    path: &str,
+    desc: &Desc,
+    plugin: super::isolated::IsolatedPlugin,
+) -> ResultType<()> {
+    log::info!("Begin load isolated plugin {}", path);
+
+    let id = desc.meta().id.clone();
+    if PLUGINS.read().unwrap().contains_key(&id) {
+        bail!("Plugin {} is already loaded as a native plugin", id);
+    }
+    add_plugin_info(path, desc);
+
+    if let Err(e) = plugin.init(&id, &get_init_info(desc)?) {
+        log::error!("Failed to init isolated plugin '{}', {}", id, e);
+        push_load_result(&id, LIFECYCLE_INIT, &Err(e));
+    }
+
+    on_plugin_loaded(desc)?;
+
+    super::isolated::insert(id.clone(), plugin);
+
+    log::info!("Isolated plugin {} loaded, {}", id, path);
+    Ok(())
+}
+
+#[cfg(feature = "plugin_wasm")]
+fn start_wasm_plugin(
+    path: &str,
+    desc: &Desc,
+    mut plugin: super::wasm::WasmPlugin,
+) -> ResultType<()> {
+    log::info!("Begin load wasm plugin {}", path);
+
+    let id = desc.meta().id.clone();
+    if PLUGINS.read().unwrap().contains_key(&id) {
+        bail!("Plugin {} is already loaded as a native plugin", id);
+    }
+    add_plugin_info(path, desc);
+
+    if let Err(e) = plugin.init(&id, &get_init_info(desc)?) {
+        log::error!("Failed to init wasm plugin '{}', {}", id, e);
+        push_load_result(&id, LIFECYCLE_INIT, &Err(e));
+    }
+
+    on_plugin_loaded(desc)?;
+
+    super::wasm::insert(id.clone(), plugin);
+
+    log::info!("Wasm plugin {} loaded, {}", id, path);
+    Ok(())
+}
+
+fn add_plugin_info(path: &str, desc: &Desc) {
+    let plugin_info = PluginInfo {
+        path: path.to_string(),
+        uninstalled: false,
+        desc: desc.clone(),
+    };
+    PLUGIN_INFO
+        .write()
+        .unwrap()
+        .insert(desc.meta().id.clone(), plugin_info);
+}
+
+#[inline]
+fn get_init_info(desc: &Desc) -> ResultType<String> {
+    Ok(serde_json::to_string(&InitInfo {
+        is_server: super::is_server_running(),
+        lang: super::lang::current_lang(),
+        ui_schema_version: ui_schema_version(desc),
+    })?)
+}
+
+fn on_plugin_loaded(desc: &Desc) -> ResultType<()> {
+    if super::is_server_running() {
+        super::config::ManagerConfig::add_plugin(&desc.meta().id)?;
+    }
+
+    // update ui
+    // Ui may be not ready now, so we need to update again once ui is ready.
+    reload_ui(desc, None);
+    Ok(())
+}
+
 pub fn sync_ui(sync_to: String) {
     for plugin in PLUGIN_INFO.read().unwrap().values() {
         reload_ui(&plugin.desc, Some(&sync_to));
@@ -411,7 +818,7 @@ pub fn sync_ui(sync_to: String) {
 
 #[inline]
 pub fn load_plugin(id: &str) -> ResultType<()> {
-    load_plugin_dir(&super::get_plugin_dir(id)?);
+    load_plugin_paths(get_plugin_paths(&super::get_plugin_dir(id)?));
     Ok(())
 }
 
@@ -452,7 +859,16 @@ pub fn plugin_call_get_return(
             event.as_ptr() as _,
             event.len(),
         )),
-        None => bail!("Plugin {} not found", id),
+        None => {
+            if let Some(ret) = super::isolated::call(id, method, peer, event, false) {
+                return ret.map(|(ret, _)| ret);
+            }
+            #[cfg(feature = "plugin_wasm")]
+            if let Some(ret) = super::wasm::call(id, method, peer, event) {
+                return ret.map(|(ret, _)| ret);
+            }
+            bail!("Plugin {} not found", id)
+        }
     }
 }
 
@@ -461,15 +877,26 @@ pub fn handle_ui_event(id: &str, peer: &str, event: &[u8]) -> ResultType<()> {
     handle_event(METHOD_HANDLE_UI, id, peer, event)
 }
 
-#[inline]
+/// The event is handled on the worker of the plugin, the errors of the plugin are only logged.
 pub fn handle_server_event(id: &str, peer: &str, event: &[u8]) -> ResultType<()> {
-    handle_event(METHOD_HANDLE_PEER, id, peer, event)
+    if !is_loaded(id) {
+        bail!("Plugin {} not found", id);
+    }
+    let mut peer: String = peer.to_owned();
+    peer.push('\0');
+    super::dispatch::post(id, METHOD_HANDLE_PEER, peer, event.to_vec());
+    Ok(())
+}
+
+#[inline]
+pub fn handle_listen_event(event: String, peer: String) {
+    handle_listen_event_with(MsgListenEvent { event, data: None }, peer);
 }
 
-fn _handle_listen_event(event: String, peer: String) {
+pub(super) fn handle_listen_event_with(event: MsgListenEvent, peer: String) {
     let mut plugins = Vec::new();
     for info in PLUGIN_INFO.read().unwrap().values() {
-        if info.desc.listen_events().contains(&event.to_string()) {
+        if info.desc.listen_events().contains(&event.event) {
             plugins.push(info.desc.meta().id.clone());
         }
     }
@@ -478,46 +905,26 @@ fn _handle_listen_event(event: String, peer: String) {
         return;
     }
 
-    if let Ok(evt) = serde_json::to_string(&MsgListenEvent {
-        event: event.clone(),
-    }) {
+    if let Ok(evt) = serde_json::to_string(&event) {
         let mut evt_bytes = evt.as_bytes().to_vec();
         evt_bytes.push(0);
         let mut peer: String = peer.to_owned();
         peer.push('\0');
         for id in plugins {
-            match PLUGINS.read().unwrap().get(&id) {
-                Some(plugin) => {
-                    let mut ret = (plugin.call)(
-                        METHOD_HANDLE_LISTEN_EVENT.as_ptr() as _,
-                        peer.as_ptr() as _,
-                        evt_bytes.as_ptr() as _,
-                        evt_bytes.len(),
-                    );
-                    if !ret.is_success() {
-                        let (code, msg) = ret.get_code_msg(&id);
-                        log::error!(
-                            "Failed to handle plugin listen event, id: {}, event: {}, code: {}, msg: {}",
-                            id,
-                            event,
-                            code,
-                            msg
-                        );
-                    }
-                }
-                None => {
-                    log::error!("Plugin {} not found when handle_listen_event", id);
-                }
+            if !is_loaded(&id) {
+                log::error!("Plugin {} not found when handle_listen_event", id);
+                continue;
             }
+            super::dispatch::post(
+                &id,
+                METHOD_HANDLE_LISTEN_EVENT,
+                peer.clone(),
+                evt_bytes.clone(),
+            );
         }
     }
 }
 
-#[inline]
-pub fn handle_listen_event(event: String, peer: String) {
-    std::thread::spawn(|| _handle_listen_event(event, peer));
-}
-
 #[inline]
 pub fn handle_client_event(id: &str, peer: &str, event: &[u8]) -> Message {
     let mut peer: String = peer.to_owned();
@@ -574,7 +981,50 @@ pub fn handle_client_event(id: &str, peer: &str, event: &[u8]) -> Message {
                 }
             }
         }
-        None => make_plugin_failure(id, "", "Plugin not found"),
+        None => {
+            if let Some(ret) = super::isolated::call(id, METHOD_HANDLE_PEER, &peer, event, true) {
+                return handle_proxied_client_event_return(id, ret);
+            }
+            #[cfg(feature = "plugin_wasm")]
+            if let Some(ret) = super::wasm::call(id, METHOD_HANDLE_PEER, &peer, event) {
+                return handle_proxied_client_event_return(id, ret);
+            }
+            make_plugin_failure(id, "", "Plugin not found")
+        }
+    }
+}
+
+// The return of the isolated and wasm plugins, the reply is already copied out of the plugin.
+fn handle_proxied_client_event_return(
+    id: &str,
+    ret: ResultType<(PluginReturn, Option<Vec<u8>>)>,
+) -> Message {
+    let name = PLUGIN_INFO
+        .read()
+        .unwrap()
+        .get(id)
+        .map(|p| p.desc.meta().name.clone())
+        .unwrap_or_default();
+    match ret {
+        Ok((mut ret, reply)) => {
+            if ret.is_success() {
+                let reply = reply.unwrap_or_default();
+                make_plugin_request(id, reply.as_ptr() as _, reply.len())
+            } else {
+                let (code, msg) = ret.get_code_msg(id);
+                log::debug!(
+                    "Plugin {} failed to handle client event, code: {}, msg: {}",
+                    id,
+                    code,
+                    msg
+                );
+                make_plugin_failure(id, &name, &msg)
+            }
+        }
+        Err(e) => {
+            log::error!("Plugin {} failed to handle client event, {}", id, e);
+            make_plugin_failure(id, &name, &e.to_string())
+        }
     }
 }
 
@@ -605,7 +1055,14 @@ fn make_plugin_failure(id: &str, name: &str, msg: &str) -> Message {
     msg_out
 }
 
+// The descriptors are checked when the plugin is loaded.
+#[inline]
+fn ui_schema_version(desc: &Desc) -> u32 {
+    super::ui_schema::negotiate(desc).unwrap_or(super::ui_schema::UI_SCHEMA_VERSION_MIN)
+}
+
 fn reload_ui(desc: &Desc, sync_to: Option<&str>) {
+    let ui_schema_version = ui_schema_version(desc).to_string();
     for (location, ui) in desc.location().ui.iter() {
         if let Ok(ui) = serde_json::to_string(&ui) {
             let make_event = |ui: &str| {
@@ -616,6 +1073,7 @@ fn reload_ui(desc: &Desc, sync_to: Option<&str>) {
                 // Do not depend on the "location" and plugin desc on the ui side.
                 // Send the ui field to ensure the ui is valid.
                 m.insert("ui", ui);
+                m.insert("ui_schema_version", &ui_schema_version);
                 serde_json::to_string(&m).unwrap_or("".to_owned())
             };
             let event_payload = make_event(&ui);
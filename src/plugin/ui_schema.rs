@@ -0,0 +1,91 @@
+//! Validation of the UI descriptors in the plugin description.
+//!
+//! The descriptors are sent to the UI side as they are, an invalid one must be rejected
+//! when the plugin is loaded instead.
+//!
+//! A plugin declares the range of the schema versions it supports by `ui_schema`,
+//! e.g. `{"min": 1, "max": 2}`. The highest version supported by both sides is used,
+//! it is passed to the plugin in the init info and sent with the UI reload events.
+use super::desc::{Desc, UiType};
+use std::collections::HashSet;
+
+/// The range of the schema versions supported by the host.
+pub const UI_SCHEMA_VERSION_MIN: u32 = 1;
+pub const UI_SCHEMA_VERSION_MAX: u32 = 1;
+
+const MAX_ITEMS_PER_LOCATION: usize = 64;
+const LOCATION_SIDES: [&str; 2] = ["client", "host"];
+const LOCATION_WINDOWS: [&str; 5] = ["main", "remote", "cm", "file transfer", "port forward"];
+
+/// The schema version used with the plugin.
+pub(super) fn negotiate(desc: &Desc) -> Result<u32, String> {
+    let range = desc.ui_schema();
+    if range.min > range.max {
+        return Err(format!(
+            "invalid ui schema range {}-{}",
+            range.min, range.max
+        ));
+    }
+    let version = range.max.min(UI_SCHEMA_VERSION_MAX);
+    if version < range.min.max(UI_SCHEMA_VERSION_MIN) {
+        return Err(format!(
+            "ui schema {}-{} is not supported, supported: {}-{}",
+            range.min, range.max, UI_SCHEMA_VERSION_MIN, UI_SCHEMA_VERSION_MAX
+        ));
+    }
+    Ok(version)
+}
+
+/// Negotiate the schema version and validate the UI descriptors against it.
+pub(super) fn check(desc: &Desc) -> Result<u32, String> {
+    let version = negotiate(desc)?;
+    for (location, items) in desc.location().ui.iter() {
+        check_location(location).map_err(|e| format!("location '{}': {}", location, e))?;
+        if items.len() > MAX_ITEMS_PER_LOCATION {
+            return Err(format!(
+                "location '{}': too many items, max {}",
+                location, MAX_ITEMS_PER_LOCATION
+            ));
+        }
+        let mut keys = HashSet::new();
+        for (i, item) in items.iter().enumerate() {
+            let key = check_item(item).map_err(|e| format!("{}[{}]: {}", location, i, e))?;
+            if !keys.insert(key) {
+                return Err(format!("{}[{}]: duplicate key '{}'", location, i, key));
+            }
+        }
+    }
+    Ok(version)
+}
+
+// "client|remote|toolbar|display"
+fn check_location(location: &str) -> Result<(), String> {
+    let v: Vec<&str> = location.split('|').collect();
+    if v.len() < 2 || v.iter().any(|s| s.is_empty()) {
+        return Err("expected '<side>|<window>[|...]'".to_owned());
+    }
+    if !LOCATION_SIDES.contains(&v[0]) {
+        return Err(format!("unknown side '{}'", v[0]));
+    }
+    if !LOCATION_WINDOWS.contains(&v[1]) {
+        return Err(format!("unknown window '{}'", v[1]));
+    }
+    Ok(())
+}
+
+fn check_item(item: &UiType) -> Result<&str, String> {
+    let (kind, key, text, action) = match item {
+        UiType::Button(b) => ("button", &b.key, &b.text, &b.action),
+        UiType::Checkbox(c) => ("checkbox", &c.key, &c.text, &c.action),
+    };
+    let empty = if key.is_empty() {
+        "key"
+    } else if text.is_empty() {
+        "text"
+    } else if action.is_empty() {
+        "action"
+    } else {
+        return Ok(key);
+    };
+    Err(format!("{} has an empty {}", kind, empty))
+}
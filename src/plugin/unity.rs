@@ -3,10 +3,11 @@ use std::{
     sync::RwLock,
 };
 
-use hbb_common::{log, ResultType};
+use hbb_common::{config::LocalConfig, log, ResultType};
 use serde_json::json;
 
 use super::{cstr_to_string, errno, plugins, str_to_cstr_ret, PluginReturn};
+use crate::unity::subscription::Subscription;
 
 pub type UnityEventCallback =
     Option<extern "C" fn(event_type: *const c_char, payload: *const c_char)>;
@@ -14,6 +15,7 @@ pub type UnityEventCallback =
 lazy_static::lazy_static! {
     static ref EVENT_CALLBACK: RwLock<Option<extern "C" fn(event_type: *const c_char, payload: *const c_char)>> =
         RwLock::new(None);
+    static ref SUBSCRIPTION: RwLock<Subscription> = Default::default();
 }
 
 fn make_error(code: i32, msg: &str) -> PluginReturn {
@@ -27,7 +29,24 @@ fn dispatch_from_result(result: ResultType<()>, context: &str) -> PluginReturn {
     }
 }
 
-fn dispatch_event(event_type: &str, payload: &str) {
+fn dispatch_event(event_type: &str, peer: &str, payload: &str) {
+    if !SUBSCRIPTION.read().unwrap().matches(event_type, peer) {
+        return;
+    }
+    crate::unity::event_bridge::notify(
+        crate::unity::event_bridge::SOURCE_PLUGIN,
+        peer,
+        event_type,
+        payload,
+    );
+    if crate::unity::batch::push(
+        crate::unity::event_bridge::SOURCE_PLUGIN,
+        peer,
+        event_type,
+        payload,
+    ) {
+        return;
+    }
     if let Some(callback) = *EVENT_CALLBACK.read().unwrap() {
         match (CString::new(event_type), CString::new(payload)) {
             (Ok(event_type), Ok(payload)) => unsafe {
@@ -59,6 +78,25 @@ pub extern "C" fn rustdesk_unity_register_event_callback(callback: UnityEventCal
     *guard = callback;
 }
 
+pub(super) fn unregister_event_callback() {
+    *EVENT_CALLBACK.write().unwrap() = None;
+    *SUBSCRIPTION.write().unwrap() = Default::default();
+}
+
+/// Only pass the plugin events of `event_types` and `peers` to the event callback,
+/// e.g. "plugin_manager,plugin_reload" for the lifecycle events only.
+/// Both are comma separated lists, null or empty for all, the events not bound to a peer are only filtered by the type.
+/// The last call replaces the previous subscription.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_subscribe_plugin_events(
+    event_types: *const c_char,
+    peers: *const c_char,
+) -> PluginReturn {
+    *SUBSCRIPTION.write().unwrap() = Subscription::from_c(event_types, peers);
+    PluginReturn::success()
+}
+
+/// Safe to call again, e.g. after an editor domain reload.
 #[no_mangle]
 pub extern "C" fn rustdesk_unity_init_plugin_framework() -> PluginReturn {
     super::init();
@@ -144,6 +182,148 @@ pub extern "C" fn rustdesk_unity_handle_server_event(
     )
 }
 
+/// Reload the plugins automatically when their binaries change, for plugin development.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_set_plugin_hot_reload(enabled: bool) -> PluginReturn {
+    if enabled {
+        super::watcher::start();
+    } else {
+        super::watcher::stop();
+    }
+    PluginReturn::success()
+}
+
+/// Get the option of the plugin for the peer, null if not set.
+/// The returned string must be freed with `rustdesk_unity_free`.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_get_plugin_peer_option(
+    id: *const c_char,
+    peer: *const c_char,
+    key: *const c_char,
+) -> *const c_char {
+    let (Ok((id, peer)), Ok(key)) = (get_id_and_peer(id, peer), cstr_to_string(key)) else {
+        return std::ptr::null();
+    };
+    match super::PeerConfig::get(&id, &peer, &key) {
+        Some(value) => str_to_cstr_ret(&value),
+        None => std::ptr::null(),
+    }
+}
+
+/// Set the option of the plugin for the peer, a `plugin_option` event with the peer id is sent if it is changed.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_set_plugin_peer_option(
+    id: *const c_char,
+    peer: *const c_char,
+    key: *const c_char,
+    value: *const c_char,
+) -> PluginReturn {
+    let (Ok((id, peer)), Ok(key), Ok(value)) = (
+        get_id_and_peer(id, peer),
+        cstr_to_string(key),
+        cstr_to_string(value),
+    ) else {
+        return make_error(
+            errno::ERR_CALLBACK_INVALID_ARGS,
+            "Invalid plugin peer option arguments",
+        );
+    };
+    dispatch_from_result(
+        super::PeerConfig::set(&id, &peer, &key, &value),
+        "Set plugin peer option",
+    )
+}
+
+/// Translate the UI string `key` of the plugin, `locale` is null or empty for the current UI language.
+/// The returned string must be freed with `rustdesk_unity_free`, it is `key` if there is no translation.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_plugin_translate(
+    id: *const c_char,
+    locale: *const c_char,
+    key: *const c_char,
+) -> *const c_char {
+    let (Ok(id), Ok(key)) = (cstr_to_string(id), cstr_to_string(key)) else {
+        return std::ptr::null();
+    };
+    let locale = cstr_to_string(locale).unwrap_or_default();
+    str_to_cstr_ret(&super::lang::translate(&id, &locale, &key))
+}
+
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_set_plugin_marketplace_url(url: *const c_char) -> PluginReturn {
+    match cstr_to_string(url) {
+        Ok(url) => {
+            LocalConfig::set_option(
+                super::marketplace::OPTION_PLUGIN_MARKETPLACE_URL.to_owned(),
+                url,
+            );
+            PluginReturn::success()
+        }
+        Err(err) => make_error(
+            errno::ERR_CALLBACK_INVALID_ARGS,
+            &format!("Invalid marketplace url: {}", err),
+        ),
+    }
+}
+
+/// Search the marketplace, returns the json of the matched plugins, or `{"error": ...}`.
+/// It may fetch the index, call it off the main thread.
+/// The returned string must be freed with `rustdesk_unity_free`.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_marketplace_search(
+    query: *const c_char,
+    refresh: bool,
+) -> *const c_char {
+    let query = cstr_to_string(query).unwrap_or_default();
+    let json = match super::marketplace::search(&query, refresh) {
+        Ok(plugins) => serde_json::to_string(&plugins).unwrap_or_else(|err| {
+            log::error!("Failed to serialize marketplace plugins: {}", err);
+            "[]".to_string()
+        }),
+        Err(err) => json!({ "error": err.to_string() }).to_string(),
+    };
+    str_to_cstr_ret(&json)
+}
+
+/// Install the plugin from the marketplace, the progress is sent as `plugin_manager` events.
+/// It blocks until the package is downloaded, call it off the main thread.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_marketplace_install(id: *const c_char) -> PluginReturn {
+    match cstr_to_string(id) {
+        Ok(id) => dispatch_from_result(super::marketplace::install(&id), "Install plugin"),
+        Err(err) => make_error(
+            errno::ERR_CALLBACK_INVALID_ARGS,
+            &format!("Invalid plugin id: {}", err),
+        ),
+    }
+}
+
+/// Update the installed plugin to the version in the marketplace, see `rustdesk_unity_marketplace_install`.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_marketplace_update(id: *const c_char) -> PluginReturn {
+    match cstr_to_string(id) {
+        Ok(id) => dispatch_from_result(super::marketplace::update(&id), "Update plugin"),
+        Err(err) => make_error(
+            errno::ERR_CALLBACK_INVALID_ARGS,
+            &format!("Invalid plugin id: {}", err),
+        ),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_marketplace_uninstall(id: *const c_char) -> PluginReturn {
+    match cstr_to_string(id) {
+        Ok(id) => {
+            super::marketplace::uninstall(&id);
+            PluginReturn::success()
+        }
+        Err(err) => make_error(
+            errno::ERR_CALLBACK_INVALID_ARGS,
+            &format!("Invalid plugin id: {}", err),
+        ),
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn rustdesk_unity_get_plugins() -> *const c_char {
     let infos = plugins::get_plugin_infos();
@@ -165,23 +345,36 @@ pub extern "C" fn rustdesk_unity_get_plugins() -> *const c_char {
     str_to_cstr_ret(&json)
 }
 
+/// Get the metrics of the plugin as a json object, `{"<name>": {"type": "counter", "value": 1.0, "updated": <unix ms>}}`.
+/// If `id` is null or empty, get the metrics of all plugins, `{"<id>": {"<name>": ...}}`.
+/// The returned string must be freed with `rustdesk_unity_free`.
 #[no_mangle]
-pub extern "C" fn rustdesk_unity_free(ptr: *mut c_void) {
-    super::free_c_ptr(ptr);
+pub extern "C" fn rustdesk_unity_get_plugin_metrics(id: *const c_char) -> *const c_char {
+    let id = cstr_to_string(id).unwrap_or_default();
+    let json = if id.is_empty() {
+        serde_json::to_string(&super::metrics::get_all())
+    } else {
+        serde_json::to_string(&super::metrics::get(&id).unwrap_or_default())
+    };
+    str_to_cstr_ret(&json.unwrap_or_else(|_| "{}".to_owned()))
 }
 
 pub(super) fn notify_manager_event(payload: &str) {
-    dispatch_event(super::MSG_TO_UI_TYPE_PLUGIN_MANAGER, payload);
+    dispatch_event(super::MSG_TO_UI_TYPE_PLUGIN_MANAGER, "", payload);
 }
 
 pub(super) fn notify_reload_event(payload: &str) {
-    dispatch_event(super::MSG_TO_UI_TYPE_PLUGIN_RELOAD, payload);
+    dispatch_event(super::MSG_TO_UI_TYPE_PLUGIN_RELOAD, "", payload);
+}
+
+pub(super) fn notify_option_event(peer: &str, payload: &str) {
+    dispatch_event(super::MSG_TO_UI_TYPE_PLUGIN_OPTION, peer, payload);
 }
 
-pub(super) fn notify_option_event(payload: &str) {
-    dispatch_event(super::MSG_TO_UI_TYPE_PLUGIN_OPTION, payload);
+pub(super) fn notify_metrics_event(payload: &str) {
+    dispatch_event(super::MSG_TO_UI_TYPE_PLUGIN_METRICS, "", payload);
 }
 
-pub(super) fn notify_plugin_event(payload: &str) {
-    dispatch_event(super::MSG_TO_UI_TYPE_PLUGIN_EVENT, payload);
+pub(super) fn notify_plugin_event(peer: &str, payload: &str) {
+    dispatch_event(super::MSG_TO_UI_TYPE_PLUGIN_EVENT, peer, payload);
 }
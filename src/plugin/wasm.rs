@@ -0,0 +1,347 @@
+//! WebAssembly plugins, loaded from `plugin_*.wasm` next to the native `plugin_*` libraries.
+//!
+//! A wasm plugin runs in a sandbox with no access to the host except the imports below,
+//! and every call is bounded by fuel so a faulty plugin can't hang the host.
+//!
+//! Exports required from the module, pointers and lengths are offsets into the exported `memory`:
+//! - `rustdesk_alloc(len: i32) -> i32`: allocate `len` bytes for the arguments passed in.
+//! - `rustdesk_desc() -> i64`: the description json, `(ptr << 32) | len`.
+//! - `rustdesk_init(info_ptr: i32, info_len: i32) -> i32`
+//! - `rustdesk_clear() -> i32`
+//! - `rustdesk_call(method_ptr, method_len, peer_ptr, peer_len, args_ptr, args_len: i32) -> i32`:
+//!   the same methods as the native `call`, "handle_ui", "handle_peer", "handle_listen_event".
+//!
+//! The `i32` results are the [`super::errno`] codes.
+//!
+//! Imports provided in module `rustdesk`:
+//! - `log(level: i32, msg_ptr: i32, msg_len: i32)`: level 0 to 4 is error, warn, info, debug, trace.
+//! - `msg(target_ptr, target_len, peer_ptr, peer_len, content_ptr, content_len: i32) -> i32`:
+//!   the same as the native `msg` callback, but queued and delivered once the current export returns,
+//!   because the handlers may call back into the plugin. Only invalid arguments are reported.
+//! - `reply(ptr: i32, len: i32)`: set the data to send back to the peer in "handle_peer".
+use super::{callback_msg, desc::Desc, errno, PluginReturn};
+use hbb_common::{bail, lazy_static, log, ResultType};
+use std::{
+    collections::HashMap,
+    ffi::CString,
+    sync::{Arc, Mutex, RwLock},
+};
+use wasmi::{Caller, Config, Engine, Extern, Instance, Linker, Memory, Module, Store, TypedFunc};
+
+pub(super) const WASM_SUFFIX: &str = ".wasm";
+
+// Fuel of one call, about 10^9 simple instructions.
+const FUEL_PER_CALL: u64 = 1_000_000_000;
+
+lazy_static::lazy_static! {
+    static ref WASM_PLUGINS: RwLock<HashMap<String, Arc<WasmPlugin>>> = Default::default();
+}
+
+struct HostState {
+    id: String,
+    reply: Option<Vec<u8>>,
+    // The messages sent during the current export call, (peer, target, content).
+    outbox: Vec<(CString, CString, Vec<u8>)>,
+}
+
+struct WasmInstance {
+    store: Store<HostState>,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    call: TypedFunc<(i32, i32, i32, i32, i32, i32), i32>,
+}
+
+pub(super) struct WasmPlugin {
+    path: String,
+    instance: Mutex<WasmInstance>,
+    instance_ref: Instance,
+    initialized: bool,
+}
+
+fn read_memory(caller: &Caller<'_, HostState>, ptr: i32, len: i32) -> Option<Vec<u8>> {
+    let memory = caller.get_export("memory").and_then(Extern::into_memory)?;
+    let mut buf = vec![0u8; len.max(0) as usize];
+    memory.read(caller, ptr as u32 as usize, &mut buf).ok()?;
+    Some(buf)
+}
+
+fn read_string(caller: &Caller<'_, HostState>, ptr: i32, len: i32) -> Option<String> {
+    read_memory(caller, ptr, len).and_then(|b| String::from_utf8(b).ok())
+}
+
+fn make_linker(engine: &Engine) -> ResultType<Linker<HostState>> {
+    let mut linker = Linker::<HostState>::new(engine);
+    linker.func_wrap(
+        "rustdesk",
+        "log",
+        |caller: Caller<'_, HostState>, level: i32, ptr: i32, len: i32| {
+            let Some(msg) = read_string(&caller, ptr, len) else {
+                return;
+            };
+            let id = &caller.data().id;
+            match level {
+                0 => log::error!("[{}] {}", id, msg),
+                1 => log::warn!("[{}] {}", id, msg),
+                2 => log::info!("[{}] {}", id, msg),
+                3 => log::debug!("[{}] {}", id, msg),
+                _ => log::trace!("[{}] {}", id, msg),
+            }
+        },
+    )?;
+    linker.func_wrap(
+        "rustdesk",
+        "msg",
+        |mut caller: Caller<'_, HostState>,
+         target_ptr: i32,
+         target_len: i32,
+         peer_ptr: i32,
+         peer_len: i32,
+         content_ptr: i32,
+         content_len: i32|
+         -> i32 {
+            let (Some(target), Some(peer), Some(content)) = (
+                read_string(&caller, target_ptr, target_len),
+                read_string(&caller, peer_ptr, peer_len),
+                read_memory(&caller, content_ptr, content_len),
+            ) else {
+                return errno::ERR_CALLBACK_INVALID_ARGS;
+            };
+            let (Ok(target), Ok(peer)) = (CString::new(target), CString::new(peer)) else {
+                return errno::ERR_CALLBACK_INVALID_ARGS;
+            };
+            caller.data_mut().outbox.push((peer, target, content));
+            errno::ERR_SUCCESS
+        },
+    )?;
+    linker.func_wrap(
+        "rustdesk",
+        "reply",
+        |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+            let reply = read_memory(&caller, ptr, len);
+            caller.data_mut().reply = reply;
+        },
+    )?;
+    Ok(linker)
+}
+
+impl WasmPlugin {
+    pub(super) fn new(path: &str) -> ResultType<Self> {
+        let bytes = std::fs::read(path)?;
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+        let module = match Module::new(&engine, &bytes[..]) {
+            Ok(m) => m,
+            Err(e) => bail!("Failed to load wasm module {}, {}", path, e),
+        };
+        let mut store = Store::new(
+            &engine,
+            HostState {
+                id: path.to_owned(),
+                reply: None,
+                outbox: Vec::new(),
+            },
+        );
+        if let Err(e) = store.add_fuel(FUEL_PER_CALL) {
+            bail!("Failed to add fuel, {}", e);
+        }
+        let linker = make_linker(&engine)?;
+        let instance = match linker
+            .instantiate(&mut store, &module)
+            .and_then(|i| i.start(&mut store))
+        {
+            Ok(i) => i,
+            Err(e) => bail!("Failed to instantiate wasm module {}, {}", path, e),
+        };
+        let Some(memory) = instance.get_memory(&store, "memory") else {
+            bail!("Wasm module {} doesn't export memory", path);
+        };
+        let alloc = instance.get_typed_func::<i32, i32>(&store, "rustdesk_alloc")?;
+        let call = instance.get_typed_func(&store, "rustdesk_call")?;
+        Ok(Self {
+            path: path.to_owned(),
+            instance: Mutex::new(WasmInstance {
+                store,
+                memory,
+                alloc,
+                call,
+            }),
+            instance_ref: instance,
+            initialized: false,
+        })
+    }
+
+    // Run `f` with the instance locked, then deliver the messages the plugin sent.
+    // They are sent after unlocking, so the handlers can call into the plugin again.
+    fn with_instance<R>(&self, f: impl FnOnce(&mut WasmInstance) -> R) -> R {
+        let mut inst = self.instance.lock().unwrap();
+        let ret = f(&mut inst);
+        let id = inst.store.data().id.clone();
+        let outbox = std::mem::take(&mut inst.store.data_mut().outbox);
+        drop(inst);
+        for (peer, target, content) in outbox {
+            send_msg(&id, &peer, &target, &content);
+        }
+        ret
+    }
+
+    // Top up the fuel to `FUEL_PER_CALL` before each call.
+    fn refuel(inst: &mut WasmInstance) -> ResultType<()> {
+        let remaining = match inst.store.consume_fuel(0) {
+            Ok(remaining) => remaining,
+            Err(e) => bail!("Failed to get the fuel, {}", e),
+        };
+        if let Err(e) = inst.store.add_fuel(FUEL_PER_CALL.saturating_sub(remaining)) {
+            bail!("Failed to add fuel, {}", e);
+        }
+        Ok(())
+    }
+
+    fn write_arg(inst: &mut WasmInstance, data: &[u8]) -> ResultType<(i32, i32)> {
+        if data.is_empty() {
+            return Ok((0, 0));
+        }
+        let ptr = inst.alloc.call(&mut inst.store, data.len() as i32)?;
+        inst.memory
+            .write(&mut inst.store, ptr as u32 as usize, data)?;
+        Ok((ptr, data.len() as i32))
+    }
+
+    pub(super) fn desc(&self) -> ResultType<Desc> {
+        self.with_instance(|inst| {
+            Self::refuel(inst)?;
+            let func = self
+                .instance_ref
+                .get_typed_func::<(), i64>(&inst.store, "rustdesk_desc")?;
+            let packed = func.call(&mut inst.store, ())?;
+            let (ptr, len) = ((packed >> 32) as u32 as usize, packed as u32 as usize);
+            let mut buf = vec![0u8; len];
+            inst.memory.read(&inst.store, ptr, &mut buf)?;
+            Ok(serde_json::from_slice(&buf)?)
+        })
+    }
+
+    pub(super) fn init(&mut self, id: &str, info: &str) -> ResultType<()> {
+        self.initialized = true;
+        self.with_instance(|inst| {
+            inst.store.data_mut().id = id.to_owned();
+            Self::refuel(inst)?;
+            let (ptr, len) = Self::write_arg(inst, info.as_bytes())?;
+            let func = self
+                .instance_ref
+                .get_typed_func::<(i32, i32), i32>(&inst.store, "rustdesk_init")?;
+            let code = func.call(&mut inst.store, (ptr, len))?;
+            if code != errno::ERR_SUCCESS {
+                bail!("Failed to init wasm plugin {}, code: {}", self.path, code);
+            }
+            Ok(())
+        })
+    }
+
+    fn clear(&self) {
+        self.with_instance(|inst| {
+            let id = inst.store.data().id.clone();
+            if Self::refuel(inst).is_err() {
+                return;
+            }
+            let ret = self
+                .instance_ref
+                .get_typed_func::<(), i32>(&inst.store, "rustdesk_clear")
+                .map_err(|e| e.to_string())
+                .and_then(|f| f.call(&mut inst.store, ()).map_err(|e| e.to_string()));
+            match ret {
+                Ok(code) if code == errno::ERR_SUCCESS => {}
+                Ok(code) => log::error!("Failed to clear wasm plugin {}, code: {}", id, code),
+                Err(e) => log::error!("Failed to clear wasm plugin {}, {}", id, e),
+            }
+        })
+    }
+
+    /// Returns the code and the reply set by the plugin during the call.
+    fn call(&self, method: &str, peer: &str, args: &[u8]) -> ResultType<(i32, Option<Vec<u8>>)> {
+        self.with_instance(|inst| {
+            inst.store.data_mut().reply = None;
+            Self::refuel(inst)?;
+            let (method_ptr, method_len) = Self::write_arg(inst, method.as_bytes())?;
+            let (peer_ptr, peer_len) = Self::write_arg(inst, peer.as_bytes())?;
+            let (args_ptr, args_len) = Self::write_arg(inst, args)?;
+            let WasmInstance { store, call, .. } = inst;
+            let code = call.call(
+                &mut *store,
+                (
+                    method_ptr, method_len, peer_ptr, peer_len, args_ptr, args_len,
+                ),
+            )?;
+            Ok((code, store.data_mut().reply.take()))
+        })
+    }
+}
+
+fn send_msg(id: &str, peer: &CString, target: &CString, content: &[u8]) {
+    let Ok(c_id) = CString::new(id) else {
+        return;
+    };
+    let mut ret = callback_msg::cb_msg(
+        peer.as_ptr(),
+        target.as_ptr(),
+        c_id.as_ptr(),
+        content.as_ptr() as _,
+        content.len(),
+    );
+    let (_, msg) = ret.get_code_msg(id);
+    if !msg.is_empty() {
+        log::warn!("Wasm plugin {} msg failed: {}", id, msg);
+    }
+}
+
+impl Drop for WasmPlugin {
+    fn drop(&mut self) {
+        if self.initialized {
+            self.clear();
+        }
+    }
+}
+
+pub(super) fn insert(id: String, plugin: WasmPlugin) {
+    WASM_PLUGINS.write().unwrap().insert(id, Arc::new(plugin));
+}
+
+#[inline]
+pub(super) fn contains(id: &str) -> bool {
+    WASM_PLUGINS.read().unwrap().contains_key(id)
+}
+
+pub(super) fn remove(id: &str) {
+    WASM_PLUGINS.write().unwrap().remove(id);
+}
+
+pub(super) fn remove_all() {
+    let plugins = std::mem::take(&mut *WASM_PLUGINS.write().unwrap());
+    drop(plugins);
+}
+
+// The native calls pass null terminated strings.
+#[inline]
+fn trim_nul(s: &[u8]) -> &[u8] {
+    s.strip_suffix(&[0]).unwrap_or(s)
+}
+
+/// Call a loaded wasm plugin, `None` if `id` is not a wasm plugin.
+pub(super) fn call(
+    id: &str,
+    method: &[u8],
+    peer: &str,
+    args: &[u8],
+) -> Option<ResultType<(PluginReturn, Option<Vec<u8>>)>> {
+    let plugin = WASM_PLUGINS.read().unwrap().get(id).cloned()?;
+    let method = String::from_utf8_lossy(trim_nul(method)).to_string();
+    let peer = String::from_utf8_lossy(trim_nul(peer.as_bytes())).to_string();
+    Some(plugin.call(&method, &peer, args).map(|(code, reply)| {
+        let ret = if code == errno::ERR_SUCCESS {
+            PluginReturn::success()
+        } else {
+            PluginReturn::new(code, &format!("wasm plugin {} returned {}", id, code))
+        };
+        (ret, reply)
+    }))
+}
@@ -0,0 +1,127 @@
+//! Reload the plugins whose binaries are replaced, for plugin development.
+//!
+//! Disabled by default, enable it with the local option `plugin-hot-reload` set to "Y",
+//! the env `RUSTDESK_PLUGIN_HOT_RELOAD=Y`, or `rustdesk_unity_set_plugin_hot_reload`.
+use super::{plugins, MSG_TO_UI_TYPE_PLUGIN_RELOAD};
+use hbb_common::{config::LocalConfig, log};
+use serde_json::json;
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    time::{Duration, Instant, SystemTime},
+};
+
+pub const OPTION_PLUGIN_HOT_RELOAD: &str = "plugin-hot-reload";
+const ENV_PLUGIN_HOT_RELOAD: &str = "RUSTDESK_PLUGIN_HOT_RELOAD";
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+// The file must stay unchanged for this long before reloading, the build may write it in several steps.
+const DEBOUNCE: Duration = Duration::from_millis(1_000);
+
+static RUNNING: AtomicBool = AtomicBool::new(false);
+// A quick stop and start must not leave two watching threads.
+static GENERATION: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Clone, Copy, PartialEq)]
+struct FileStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+fn stamp(path: &str) -> Option<FileStamp> {
+    let meta = std::fs::metadata(path).ok()?;
+    Some(FileStamp {
+        modified: meta.modified().ok(),
+        len: meta.len(),
+    })
+}
+
+pub fn is_enabled_by_config() -> bool {
+    std::env::var(ENV_PLUGIN_HOT_RELOAD).map_or(false, |v| v == "Y")
+        || LocalConfig::get_option(OPTION_PLUGIN_HOT_RELOAD) == "Y"
+}
+
+#[inline]
+pub fn is_running() -> bool {
+    RUNNING.load(Ordering::SeqCst)
+}
+
+pub fn start() {
+    if RUNNING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    log::info!("Plugin hot reload started");
+    let generation = GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    std::thread::spawn(move || watch(generation));
+}
+
+pub fn stop() {
+    if RUNNING.swap(false, Ordering::SeqCst) {
+        log::info!("Plugin hot reload stopped");
+    }
+}
+
+fn loaded_plugin_paths() -> HashMap<String, String> {
+    plugins::get_plugin_infos()
+        .read()
+        .unwrap()
+        .iter()
+        .filter(|(_, info)| !info.uninstalled)
+        .map(|(id, info)| (id.clone(), info.path.clone()))
+        .collect()
+}
+
+fn watch(generation: usize) {
+    // id -> the stamp of the loaded binary
+    let mut loaded: HashMap<String, FileStamp> = HashMap::new();
+    // id -> (the latest stamp, since when it is unchanged)
+    let mut pending: HashMap<String, (FileStamp, Instant)> = HashMap::new();
+    while RUNNING.load(Ordering::SeqCst) && GENERATION.load(Ordering::SeqCst) == generation {
+        let paths = loaded_plugin_paths();
+        loaded.retain(|id, _| paths.contains_key(id));
+        pending.retain(|id, _| paths.contains_key(id));
+        for (id, path) in paths.iter() {
+            let Some(current) = stamp(path) else {
+                // Being replaced, check it again later.
+                continue;
+            };
+            let Some(last) = loaded.get(id).cloned() else {
+                loaded.insert(id.clone(), current);
+                continue;
+            };
+            if current == last {
+                pending.remove(id);
+                continue;
+            }
+            match pending.get(id) {
+                Some((s, since)) if *s == current => {
+                    if since.elapsed() >= DEBOUNCE {
+                        pending.remove(id);
+                        loaded.insert(id.clone(), current);
+                        reload(id);
+                    }
+                }
+                _ => {
+                    pending.insert(id.clone(), (current, Instant::now()));
+                }
+            }
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+fn reload(id: &str) {
+    log::info!("Plugin {} is changed, reloading", id);
+    let res = plugins::reload_plugin(id);
+    if let Err(e) = &res {
+        log::error!("Failed to hot reload plugin {}, {}", id, e);
+    }
+    let payload = json!({
+        "name": MSG_TO_UI_TYPE_PLUGIN_RELOAD,
+        "id": id,
+        "hot_reload": true,
+        "ok": res.is_ok(),
+        "error": res.err().map(|e| e.to_string()).unwrap_or_default(),
+    });
+    super::unity::notify_reload_event(&payload.to_string());
+}
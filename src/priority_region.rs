@@ -0,0 +1,109 @@
+//! The region of a display the client wants in a higher quality, e.g. around the gaze point of
+//! a VR headset or the focused window.
+//!
+//! The client sends [`PriorityRegion`] of the reserved id [`REQUEST_ID`] in the pixels of the
+//! captured display, or without a rect to clear it. The encoder of the display spends more bits
+//! on the region and less on the rest at the same bitrate, if it supports it, only VP8 and VP9
+//! for now. A display has one region, the last one set by any connection, removed when that
+//! connection closes.
+use hbb_common::message_proto::Message;
+use scrap::DamageRect;
+use serde_derive::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::Mutex};
+
+pub const REQUEST_ID: &str = crate::common::plugin_request_id::PRIORITY_REGION;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub w: u32,
+    pub h: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PriorityRegion {
+    pub display: usize,
+    pub rect: Option<Rect>,
+}
+
+impl PriorityRegion {
+    pub fn to_message(&self) -> Message {
+        crate::common::make_plugin_request(REQUEST_ID, serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    pub fn from_message(msg: &Message) -> Option<Self> {
+        serde_json::from_slice(crate::common::get_plugin_request(msg, REQUEST_ID)?).ok()
+    }
+}
+
+lazy_static::lazy_static! {
+    // display -> (conn id, region)
+    static ref REGIONS: Mutex<HashMap<usize, (i32, DamageRect)>> = Default::default();
+}
+
+/// Set the region of the host from the connection `conn_id`.
+pub fn set(conn_id: i32, region: PriorityRegion) {
+    let mut regions = REGIONS.lock().unwrap();
+    match region.rect.filter(|r| r.w > 0 && r.h > 0) {
+        Some(r) => {
+            let rect = DamageRect {
+                x: r.x as _,
+                y: r.y as _,
+                w: r.w as _,
+                h: r.h as _,
+            };
+            regions.insert(region.display, (conn_id, rect));
+        }
+        None => {
+            regions.remove(&region.display);
+        }
+    }
+}
+
+/// Remove the regions set by the closed connection.
+pub fn remove_conn(conn_id: i32) {
+    REGIONS.lock().unwrap().retain(|_, (id, _)| *id != conn_id);
+}
+
+#[inline]
+pub fn get(display: usize) -> Option<DamageRect> {
+    REGIONS.lock().unwrap().get(&display).map(|(_, r)| *r)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_regions() {
+        let region = PriorityRegion {
+            display: 9,
+            rect: Some(Rect {
+                x: 10,
+                y: 20,
+                w: 300,
+                h: 200,
+            }),
+        };
+        assert_eq!(
+            PriorityRegion::from_message(&region.to_message()),
+            Some(region.clone())
+        );
+        set(1, region.clone());
+        assert_eq!(get(9).map(|r| (r.x, r.w)), Some((10, 300)));
+        remove_conn(2);
+        assert!(get(9).is_some());
+        remove_conn(1);
+        assert!(get(9).is_none());
+        set(1, region);
+        set(
+            2,
+            PriorityRegion {
+                display: 9,
+                rect: None,
+            },
+        );
+        assert!(get(9).is_none());
+    }
+}
@@ -0,0 +1,145 @@
+//! Selection of the relay server among the ones of the option [`OPTION_RELAY_SERVER`],
+//! separated by commas, e.g. "relay1.example.com,relay2.example.com:21117".
+//!
+//! The round trip times of the TCP connections to the relay servers are measured at startup
+//! and again after a relay server fails, the fastest one is used first.
+//! The client requests the relays in this order until one of the relay servers is reachable,
+//! and when a relayed session is lost, the reconnection fails over to the next one.
+//! The host uses the fastest one for the relays it initiates.
+use hbb_common::{
+    config::{Config, RELAY_PORT},
+    futures::future::join_all,
+    log,
+    socket_client::connect_tcp,
+    tokio,
+};
+use std::{collections::HashMap, sync::Mutex, time::Instant};
+
+pub const OPTION_RELAY_SERVER: &str = "relay-server";
+const MEASURE_TIMEOUT: u64 = 3_000;
+
+lazy_static::lazy_static! {
+    // The round trip time in milliseconds of each relay server, `None` if it failed.
+    static ref RTTS: Mutex<HashMap<String, Option<u64>>> = Default::default();
+}
+
+/// The relay servers configured.
+pub fn get_configured() -> Vec<String> {
+    parse(&Config::get_option(OPTION_RELAY_SERVER))
+}
+
+fn parse(option: &str) -> Vec<String> {
+    option
+        .split(',')
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_owned())
+        .collect()
+}
+
+/// The relay servers to try in order: the ones configured sorted by the round trip times,
+/// or the one `provided` by the rendezvous server if none is configured.
+pub fn candidates(provided: &str) -> Vec<String> {
+    let mut servers = get_configured();
+    if servers.is_empty() {
+        if !provided.is_empty() {
+            servers.push(provided.to_owned());
+        }
+        return servers;
+    }
+    sort(&mut servers, &RTTS.lock().unwrap());
+    servers
+}
+
+// The measured ones first, then the ones not measured yet, the failed ones last.
+// The sort is stable, so the configured order is kept for the same round trip times.
+fn sort(servers: &mut Vec<String>, rtts: &HashMap<String, Option<u64>>) {
+    servers.sort_by_key(|s| match rtts.get(s) {
+        Some(Some(rtt)) => (0, *rtt),
+        None => (1, 0),
+        Some(None) => (2, 0),
+    });
+}
+
+/// Measure the round trip times of the relay servers configured, if there are several.
+pub async fn measure() {
+    let servers = get_configured();
+    if servers.len() < 2 {
+        return;
+    }
+    let futs = servers.into_iter().map(|server| async move {
+        let start = Instant::now();
+        let rtt = match connect_tcp(
+            crate::check_port(server.clone(), RELAY_PORT),
+            MEASURE_TIMEOUT,
+        )
+        .await
+        {
+            Ok(_) => Some(start.elapsed().as_millis() as u64),
+            Err(e) => {
+                log::warn!("Failed to connect to relay server {}: {}", server, e);
+                None
+            }
+        };
+        (server, rtt)
+    });
+    let rtts = join_all(futs).await;
+    log::info!("Round trip times of the relay servers: {:?}", rtts);
+    RTTS.lock().unwrap().extend(rtts);
+}
+
+/// [`measure`] if some relay servers configured are not measured yet.
+pub async fn measure_if_needed() {
+    let servers = get_configured();
+    let measured = {
+        let rtts = RTTS.lock().unwrap();
+        servers.iter().all(|s| rtts.contains_key(s))
+    };
+    if !measured {
+        measure().await;
+    }
+}
+
+/// [`measure`] in the background, e.g. at startup.
+pub fn start_measure() {
+    if get_configured().len() < 2 {
+        return;
+    }
+    std::thread::spawn(measure_);
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn measure_() {
+    measure().await;
+}
+
+/// Mark the relay server failed and measure again,
+/// returns true if there's another relay server to fail over to.
+pub fn on_failure(server: &str) -> bool {
+    let servers = get_configured();
+    if !servers.iter().any(|s| s == server) {
+        return false;
+    }
+    log::warn!("Relay server {} failed", server);
+    RTTS.lock().unwrap().insert(server.to_owned(), None);
+    start_measure();
+    servers.len() > 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sort() {
+        let mut servers = parse(" a, b ,c,,d:21117 ");
+        assert_eq!(servers, vec!["a", "b", "c", "d:21117"]);
+        let rtts = HashMap::from([
+            ("a".to_owned(), None),
+            ("c".to_owned(), Some(80)),
+            ("d:21117".to_owned(), Some(20)),
+        ]);
+        sort(&mut servers, &rtts);
+        assert_eq!(servers, vec!["d:21117", "c", "b", "a"]);
+    }
+}
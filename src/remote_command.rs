@@ -0,0 +1,326 @@
+//! The commands run on the host for the client, e.g. the scripted maintenance tasks without an
+//! interactive session.
+//!
+//! The client sends [`RemoteCommand::Run`] with a plugin request of the reserved id
+//! [`REQUEST_ID`]. It's allowed if the option [`OPTION_ALLOW`] is on, off by default, and the
+//! keyboard of the session is enabled. The programs in the option [`OPTION_AUTO_APPROVE`], a
+//! JSON list, or all with `*`, are run at once, the others are prompted in the connection
+//! manager for the consent of each command. A bare name in the list approves the command of the
+//! name only, which is looked up in `PATH`, and a command with a path is approved only by the
+//! same absolute path in the list. The host replies with [`RemoteCommand::Pending`]
+//! while prompting, then streams [`RemoteCommand::Output`] and ends with
+//! [`RemoteCommand::Exited`], the error of which is set if it's denied or failed to start.
+//!
+//! The command runs as the user of the server process, the system account if it's installed
+//! as a service, without a shell, so the shell commands are run with the shell as the program.
+//! The commands of a connection are killed when it's closed. The commands run are recorded to
+//! the audit log.
+use hbb_common::{
+    config::{self, Config},
+    log,
+};
+use serde_derive::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    io::Read,
+    process::{Child, Command, Stdio},
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+pub const REQUEST_ID: &str = crate::common::plugin_request_id::REMOTE_COMMAND;
+pub const OPTION_ALLOW: &str = "allow-remote-command";
+pub const OPTION_AUTO_APPROVE: &str = "remote-command-auto-approve";
+pub const ERR_DENIED: &str = "remote-command-denied-tip";
+pub const ERR_REJECTED: &str = "remote-command-rejected-tip";
+// The output beyond it is dropped.
+const MAX_OUTPUT: usize = 1 << 20;
+const CHUNK_SIZE: usize = 4096;
+const WAIT_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Request {
+    /// Chosen by the client, unique in the session.
+    pub id: u32,
+    pub command: String,
+    pub args: Vec<String>,
+    /// The working directory, the one of the server if empty.
+    pub cwd: String,
+}
+
+impl Request {
+    pub fn display(&self) -> String {
+        std::iter::once(&self.command)
+            .chain(self.args.iter())
+            .map(|s| {
+                if s.contains(' ') {
+                    format!("\"{}\"", s)
+                } else {
+                    s.clone()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "t", content = "c")]
+pub enum RemoteCommand {
+    /// From the client.
+    Run(Request),
+    /// From the client to kill the command.
+    Cancel { id: u32 },
+    /// From the host, waiting for the consent.
+    Pending { id: u32 },
+    /// From the host.
+    Output {
+        id: u32,
+        stream: Stream,
+        data: String,
+    },
+    /// From the host, the exit code is `None` if it's killed or not run.
+    Exited {
+        id: u32,
+        code: Option<i32>,
+        error: String,
+    },
+}
+
+impl RemoteCommand {
+    pub fn to_message(&self) -> hbb_common::message_proto::Message {
+        crate::common::make_plugin_request(REQUEST_ID, serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    pub fn exited_with_error(id: u32, error: impl Into<String>) -> Self {
+        Self::Exited {
+            id,
+            code: None,
+            error: error.into(),
+        }
+    }
+}
+
+#[inline]
+pub fn is_allowed() -> bool {
+    config::option2bool(OPTION_ALLOW, &Config::get_option(OPTION_ALLOW))
+}
+
+/// Whether the command is run without the consent.
+pub fn is_auto_approved(request: &Request) -> bool {
+    let programs: Vec<String> =
+        serde_json::from_str(&Config::get_option(OPTION_AUTO_APPROVE)).unwrap_or_default();
+    auto_approved_in(&programs, &request.command)
+}
+
+fn auto_approved_in(programs: &[String], command: &str) -> bool {
+    if command.is_empty() {
+        return false;
+    }
+    let is_path = |s: &str| s.contains(['/', '\\']);
+    programs.iter().any(|p| {
+        if p == "*" {
+            true
+        } else if is_path(command) {
+            // Or `/tmp/ipconfig` would pass for `ipconfig`.
+            std::path::Path::new(p).is_absolute() && is_same_program(p, command)
+        } else {
+            !is_path(p) && is_same_program(p, command)
+        }
+    })
+}
+
+#[cfg(windows)]
+fn is_same_program(a: &str, b: &str) -> bool {
+    // Case insensitive, and `.exe` is optional like for `Command`.
+    let trim = |s: &str| {
+        let n = s.len().saturating_sub(4);
+        if s.is_char_boundary(n) && s[n..].eq_ignore_ascii_case(".exe") {
+            s[..n].to_owned()
+        } else {
+            s.to_owned()
+        }
+    };
+    trim(a).eq_ignore_ascii_case(&trim(b))
+}
+
+#[cfg(not(windows))]
+#[inline]
+fn is_same_program(a: &str, b: &str) -> bool {
+    a == b
+}
+
+static NEXT_ID: AtomicU32 = AtomicU32::new(1);
+
+/// A new id of the commands of the client.
+#[inline]
+pub fn next_id() -> u32 {
+    NEXT_ID.fetch_add(1, Ordering::SeqCst)
+}
+
+lazy_static::lazy_static! {
+    // (conn id, command id) -> the running child.
+    static ref CHILDREN: Mutex<HashMap<(i32, u32), Arc<Mutex<Child>>>> = Default::default();
+}
+
+/// Run the command of the connection `conn_id`, the replies are passed to `send`.
+pub fn run(conn_id: i32, request: Request, send: impl Fn(RemoteCommand) + Send + Sync + 'static) {
+    let id = request.id;
+    if CHILDREN.lock().unwrap().contains_key(&(conn_id, id)) {
+        send(RemoteCommand::exited_with_error(id, "The id is in use"));
+        return;
+    }
+    let mut command = Command::new(&request.command);
+    command
+        .args(&request.args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if !request.cwd.is_empty() {
+        command.current_dir(&request.cwd);
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        command.creation_flags(winapi::um::winbase::CREATE_NO_WINDOW);
+    }
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            send(RemoteCommand::exited_with_error(id, e.to_string()));
+            return;
+        }
+    };
+    log::info!("#{} Run command {}: {}", conn_id, id, request.display());
+    let send = Arc::new(send);
+    let mut readers = vec![];
+    if let Some(stdout) = child.stdout.take() {
+        let send = send.clone();
+        readers.push(std::thread::spawn(move || {
+            forward_output(id, stdout, Stream::Stdout, send.as_ref())
+        }));
+    }
+    if let Some(stderr) = child.stderr.take() {
+        let send = send.clone();
+        readers.push(std::thread::spawn(move || {
+            forward_output(id, stderr, Stream::Stderr, send.as_ref())
+        }));
+    }
+    let child = Arc::new(Mutex::new(child));
+    CHILDREN
+        .lock()
+        .unwrap()
+        .insert((conn_id, id), child.clone());
+    std::thread::spawn(move || {
+        // Not locked while waiting, to be killed.
+        let status = loop {
+            match child.lock().unwrap().try_wait() {
+                Ok(Some(status)) => break Ok(status),
+                Ok(None) => {}
+                Err(e) => break Err(e),
+            }
+            std::thread::sleep(WAIT_INTERVAL);
+        };
+        CHILDREN.lock().unwrap().remove(&(conn_id, id));
+        // The output is sent before the exit.
+        for reader in readers {
+            reader.join().ok();
+        }
+        let msg = match status {
+            Ok(status) => RemoteCommand::Exited {
+                id,
+                code: status.code(),
+                error: "".to_owned(),
+            },
+            Err(e) => RemoteCommand::exited_with_error(id, e.to_string()),
+        };
+        log::info!("#{} Command {} exited: {:?}", conn_id, id, msg);
+        send(msg);
+    });
+}
+
+fn forward_output(id: u32, mut reader: impl Read, stream: Stream, send: &dyn Fn(RemoteCommand)) {
+    let mut buf = vec![0u8; CHUNK_SIZE];
+    let mut total = 0;
+    loop {
+        match reader.read(&mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => {
+                // Keep reading to the end, so the command is not blocked on a full pipe.
+                if total >= MAX_OUTPUT {
+                    continue;
+                }
+                total += n;
+                send(RemoteCommand::Output {
+                    id,
+                    stream,
+                    data: String::from_utf8_lossy(&buf[..n]).into_owned(),
+                });
+            }
+        }
+    }
+}
+
+/// Kill the command, its exit is sent as usual.
+pub fn cancel(conn_id: i32, id: u32) {
+    let child = CHILDREN.lock().unwrap().get(&(conn_id, id)).cloned();
+    if let Some(child) = child {
+        log::info!("#{} Cancel command {}", conn_id, id);
+        child.lock().unwrap().kill().ok();
+    }
+}
+
+/// Kill the commands of the closed connection.
+pub fn cancel_conn(conn_id: i32) {
+    let ids: Vec<u32> = CHILDREN
+        .lock()
+        .unwrap()
+        .keys()
+        .filter(|(c, _)| *c == conn_id)
+        .map(|(_, id)| *id)
+        .collect();
+    for id in ids {
+        cancel(conn_id, id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_auto_approved() {
+        let programs = vec![
+            "ipconfig".to_owned(),
+            "/usr/bin/df".to_owned(),
+            "bin/du".to_owned(),
+        ];
+        assert!(auto_approved_in(&programs, "ipconfig"));
+        assert!(auto_approved_in(&programs, "/usr/bin/df"));
+        assert!(!auto_approved_in(&programs, "df"));
+        assert!(!auto_approved_in(&programs, "/tmp/ipconfig"));
+        assert!(!auto_approved_in(&programs, "./ipconfig"));
+        assert!(!auto_approved_in(&programs, "bin/du"));
+        assert!(!auto_approved_in(&programs, "rm"));
+        #[cfg(windows)]
+        {
+            assert!(auto_approved_in(&programs, "IPCONFIG.exe"));
+            assert!(!auto_approved_in(&programs, r"C:\Temp\ipconfig.exe"));
+        }
+        #[cfg(not(windows))]
+        assert!(!auto_approved_in(&programs, "IPCONFIG"));
+        assert!(auto_approved_in(&["*".to_owned()], "rm"));
+        assert!(!auto_approved_in(&["*".to_owned()], ""));
+    }
+}
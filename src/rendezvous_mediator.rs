@@ -69,6 +69,7 @@ impl RendezvousMediator {
             crate::updater::start_auto_update();
         }
         check_zombie();
+        crate::relay_servers::start_measure();
         let server = new_server();
         if config::option2bool("stop-service", &Config::get_option("stop-service")) {
             crate::test_rendezvous_server();
@@ -703,10 +704,10 @@ impl RendezvousMediator {
     }
 
     fn get_relay_server(&self, provided_by_rendezvous_server: String) -> String {
-        let mut relay_server = Config::get_option("relay-server");
-        if relay_server.is_empty() {
-            relay_server = provided_by_rendezvous_server;
-        }
+        let mut relay_server = crate::relay_servers::candidates(&provided_by_rendezvous_server)
+            .into_iter()
+            .next()
+            .unwrap_or_default();
         if relay_server.is_empty() {
             relay_server = crate::increase_port(&self.host, 1);
         }
@@ -765,6 +766,10 @@ async fn direct_server(server: ServerPtr) {
                 continue;
             }
             if let Ok(Ok((stream, addr))) = hbb_common::timeout(1000, l.accept()).await {
+                if !crate::common::is_direct_access_allowed(addr.ip()) {
+                    log::warn!("direct access from {} is denied", addr);
+                    continue;
+                }
                 stream.set_nodelay(true).ok();
                 log::info!("direct access from {}", addr);
                 let local_addr = stream
@@ -778,6 +783,7 @@ async fn direct_server(server: ServerPtr) {
                             hbb_common::Stream::from(stream, local_addr),
                             addr,
                             false,
+                            false,
                         )
                         .await
                     );
@@ -838,7 +844,7 @@ async fn udp_nat_listen(
             res,
         )
         .await?;
-        crate::server::create_tcp_connection(server, stream.1, peer_addr_v4, true).await?;
+        crate::server::create_tcp_connection(server, stream.1, peer_addr_v4, true, false).await?;
         Ok(())
     };
     func.await.map_err(|e: anyhow::Error| {
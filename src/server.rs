@@ -64,11 +64,21 @@ pub mod input_service {
     pub const NAME_WINDOW_FOCUS: &'static str = "";
 }
 
+pub mod access_schedule;
+pub mod audit_log;
+mod clipboard_policy;
+mod congestion;
 mod connection;
 pub mod display_service;
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+pub mod key_rotation;
+pub mod metrics;
+pub mod permission_profile;
 #[cfg(windows)]
 pub mod portable_service;
+pub mod recording_policy;
 mod service;
+mod video_catch_up;
 mod video_qos;
 pub mod video_service;
 
@@ -163,7 +173,14 @@ async fn accept_connection_(server: ServerPtr, socket: Stream, secure: bool) ->
     if let Ok((stream, addr)) = timeout(CONNECT_TIMEOUT, listener.accept()).await? {
         stream.set_nodelay(true).ok();
         let stream_addr = stream.local_addr()?;
-        create_tcp_connection(server, Stream::from(stream, stream_addr), addr, secure).await?;
+        create_tcp_connection(
+            server,
+            Stream::from(stream, stream_addr),
+            addr,
+            secure,
+            false,
+        )
+        .await?;
     }
     Ok(())
 }
@@ -173,9 +190,11 @@ pub async fn create_tcp_connection(
     stream: Stream,
     addr: SocketAddr,
     secure: bool,
+    relay: bool,
 ) -> ResultType<()> {
     let mut stream = stream;
     let id = server.write().unwrap().get_new_id();
+    let mut session_fingerprint = String::new();
     let (sk, pk) = Config::get_key_pair();
     if secure && pk.len() == sign::PUBLICKEYBYTES && sk.len() == sign::SECRETKEYBYTES {
         let mut sk_ = [0u8; sign::SECRETKEYBYTES];
@@ -209,6 +228,11 @@ pub async fn create_tcp_connection(
                                 &pk.asymmetric_value,
                                 &our_sk_b,
                             )?);
+                            session_fingerprint = crate::common::session_fingerprint(
+                                &our_pk_b.0,
+                                &pk.asymmetric_value,
+                                &pk.symmetric_value,
+                            );
                         } else if pk.asymmetric_value.is_empty() {
                             Config::set_key_confirmed(false);
                             log::info!("Force to update pk");
@@ -240,7 +264,15 @@ pub async fn create_tcp_connection(
         }
         log::info!("wake up macos");
     }
-    Connection::start(addr, stream, id, Arc::downgrade(&server)).await;
+    Connection::start(
+        addr,
+        stream,
+        id,
+        session_fingerprint,
+        relay,
+        Arc::downgrade(&server),
+    )
+    .await;
     Ok(())
 }
 
@@ -283,11 +315,15 @@ async fn create_relay_connection_(
     secure: bool,
     ipv4: bool,
 ) -> ResultType<()> {
-    let mut stream = socket_client::connect_tcp(
-        socket_client::ipv4_to_ipv6(crate::check_port(relay_server, RELAY_PORT), ipv4),
+    let stream = socket_client::connect_tcp(
+        socket_client::ipv4_to_ipv6(crate::check_port(relay_server.clone(), RELAY_PORT), ipv4),
         CONNECT_TIMEOUT,
     )
-    .await?;
+    .await;
+    if stream.is_err() {
+        crate::relay_servers::on_failure(&relay_server);
+    }
+    let mut stream = stream?;
     let mut msg_out = RendezvousMessage::new();
     let licence_key = crate::get_key(true).await;
     msg_out.set_request_relay(RequestRelay {
@@ -296,7 +332,7 @@ async fn create_relay_connection_(
         ..Default::default()
     });
     stream.send(&msg_out).await?;
-    create_tcp_connection(server, stream, peer_addr, secure).await?;
+    create_tcp_connection(server, stream, peer_addr, secure, true).await?;
     Ok(())
 }
 
@@ -572,6 +608,8 @@ pub async fn start_server(is_server: bool, no_server: bool) {
         crate::platform::try_kill_broker();
         #[cfg(feature = "hwcodec")]
         scrap::hwcodec::start_check_process();
+        tokio::spawn(crate::local_api::start());
+        tokio::spawn(metrics::start());
         crate::RendezvousMediator::start_all().await;
     } else {
         match crate::ipc::connect(1000, "").await {
@@ -0,0 +1,225 @@
+//! Scheduled access windows of the host, out of which the incoming connections are refused,
+//! e.g. for the office machines only reachable in the working hours.
+//!
+//! - [`OPTION_WINDOWS`]: the comma separated windows of the days and the times,
+//!   e.g. `Mon-Fri 09:00-18:00, Sat 10:00-14:00`, `Daily 22:00-06:00`. A window ending before
+//!   its start crosses the midnight, it belongs to the day it starts, and `24:00` is the end of
+//!   the day. No restriction if empty.
+//! - [`OPTION_TIMEZONE`]: the time zone of the windows, the IANA name like `Europe/Berlin`,
+//!   the offset like `+08:00`, or the local time of the host if empty.
+//!
+//! The invalid windows refuse all the connections instead of allowing them, so a typo never
+//! opens the host. The sessions already logged in are not closed at the end of a window,
+//! the idle ones are closed by the auto disconnect options.
+use chrono::{Datelike, FixedOffset, Local, Timelike, Utc};
+use hbb_common::{bail, config::Config, log, ResultType};
+
+pub const OPTION_WINDOWS: &str = "access-windows";
+pub const OPTION_TIMEZONE: &str = "access-windows-timezone";
+const DAYS: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+const DAILY: &str = "daily";
+const MINUTES_PER_DAY: u32 = 24 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Window {
+    // From Monday.
+    days: [bool; 7],
+    // The minutes of the day, `end` may be less than `start`.
+    start: u32,
+    end: u32,
+}
+
+impl Window {
+    fn contains(&self, weekday: usize, minute: u32) -> bool {
+        if self.start < self.end {
+            self.days[weekday] && minute >= self.start && minute < self.end
+        } else {
+            (self.days[weekday] && minute >= self.start)
+                || (self.days[(weekday + 6) % 7] && minute < self.end)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Zone {
+    Local,
+    Offset(FixedOffset),
+    Named(chrono_tz::Tz),
+}
+
+/// Parse the windows of [`OPTION_WINDOWS`].
+pub fn parse_windows(s: &str) -> ResultType<Vec<Window>> {
+    let mut res = vec![];
+    for w in s.split(',').map(|w| w.trim()).filter(|w| !w.is_empty()) {
+        let Some((days, times)) = w.rsplit_once(' ') else {
+            bail!("Invalid access window: {}", w);
+        };
+        let Some((start, end)) = times.split_once('-') else {
+            bail!("Invalid times of the access window: {}", w);
+        };
+        let (start, end) = (parse_time(start)?, parse_time(end)?);
+        if start >= MINUTES_PER_DAY {
+            bail!("Invalid start of the access window: {}", w);
+        }
+        res.push(Window {
+            days: parse_days(days.trim())?,
+            start,
+            end: end % MINUTES_PER_DAY,
+        });
+    }
+    Ok(res)
+}
+
+fn parse_days(s: &str) -> ResultType<[bool; 7]> {
+    let s = s.to_lowercase();
+    if s == DAILY {
+        return Ok([true; 7]);
+    }
+    let index = |d: &str| match DAYS.iter().position(|x| *x == d.trim()) {
+        Some(i) => Ok(i),
+        None => bail!("Invalid day of the access window: {}", d),
+    };
+    let (first, last) = match s.split_once('-') {
+        Some((first, last)) => (index(first)?, index(last)?),
+        None => (index(&s)?, index(&s)?),
+    };
+    let mut days = [false; 7];
+    // Mon-Sun, or Fri-Mon through the weekend.
+    let mut i = first;
+    loop {
+        days[i] = true;
+        if i == last {
+            break;
+        }
+        i = (i + 1) % 7;
+    }
+    Ok(days)
+}
+
+fn parse_time(s: &str) -> ResultType<u32> {
+    let s = s.trim();
+    if let Some((h, m)) = s.split_once(':') {
+        if let (Ok(h), Ok(m)) = (h.parse::<u32>(), m.parse::<u32>()) {
+            if m < 60 && (h < 24 || (h == 24 && m == 0)) {
+                return Ok(h * 60 + m);
+            }
+        }
+    }
+    bail!("Invalid time of the access window: {}", s);
+}
+
+/// Parse the time zone of [`OPTION_TIMEZONE`].
+pub fn parse_zone(s: &str) -> ResultType<Zone> {
+    let s = s.trim();
+    if s.is_empty() {
+        return Ok(Zone::Local);
+    }
+    if s.eq_ignore_ascii_case("utc") || s == "Z" {
+        return Ok(Zone::Offset(FixedOffset::east_opt(0).unwrap()));
+    }
+    if let Some(sign) = match s.chars().next() {
+        Some('+') => Some(1),
+        Some('-') => Some(-1),
+        _ => None,
+    } {
+        let offset = s[1..]
+            .split_once(':')
+            .and_then(|(h, m)| Some((h.parse::<i32>().ok()?, m.parse::<i32>().ok()?)))
+            .filter(|(h, m)| *h <= 14 && *m < 60)
+            .and_then(|(h, m)| FixedOffset::east_opt(sign * (h * 3600 + m * 60)));
+        return match offset {
+            Some(offset) => Ok(Zone::Offset(offset)),
+            None => bail!("Invalid time zone offset: {}", s),
+        };
+    }
+    match s.parse::<chrono_tz::Tz>() {
+        Ok(tz) => Ok(Zone::Named(tz)),
+        Err(_) => bail!("Unknown time zone: {}", s),
+    }
+}
+
+/// The weekday from Monday and the minute of the day now in the `zone`.
+fn now_in(zone: Zone) -> (usize, u32) {
+    let utc = Utc::now();
+    let (weekday, hour, minute) = match zone {
+        Zone::Local => {
+            let t = utc.with_timezone(&Local);
+            (t.weekday(), t.hour(), t.minute())
+        }
+        Zone::Offset(offset) => {
+            let t = utc.with_timezone(&offset);
+            (t.weekday(), t.hour(), t.minute())
+        }
+        Zone::Named(tz) => {
+            let t = utc.with_timezone(&tz);
+            (t.weekday(), t.hour(), t.minute())
+        }
+    };
+    (weekday.num_days_from_monday() as usize, hour * 60 + minute)
+}
+
+pub fn is_allowed(windows: &[Window], weekday: usize, minute: u32) -> bool {
+    windows.iter().any(|w| w.contains(weekday, minute))
+}
+
+/// Validate the windows and the time zone, for the settings.
+pub fn check(windows: &str, zone: &str) -> ResultType<()> {
+    parse_windows(windows)?;
+    parse_zone(zone)?;
+    Ok(())
+}
+
+/// Whether the connections are accepted now.
+pub fn is_allowed_now() -> bool {
+    let windows = Config::get_option(OPTION_WINDOWS);
+    if windows.trim().is_empty() {
+        return true;
+    }
+    let (windows, zone) = match (
+        parse_windows(&windows),
+        parse_zone(&Config::get_option(OPTION_TIMEZONE)),
+    ) {
+        (Ok(windows), Ok(zone)) => (windows, zone),
+        (Err(e), _) | (_, Err(e)) => {
+            log::error!("Invalid access windows, refusing the connection: {}", e);
+            return false;
+        }
+    };
+    if windows.is_empty() {
+        return true;
+    }
+    let (weekday, minute) = now_in(zone);
+    is_allowed(&windows, weekday, minute)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_windows() {
+        let windows = parse_windows("Mon-Fri 09:00-18:00, Sat 22:00-02:00").unwrap();
+        assert!(is_allowed(&windows, 0, 9 * 60));
+        assert!(!is_allowed(&windows, 0, 18 * 60));
+        assert!(!is_allowed(&windows, 5, 9 * 60));
+        assert!(is_allowed(&windows, 5, 23 * 60));
+        // Saturday night through Sunday.
+        assert!(is_allowed(&windows, 6, 60));
+        assert!(!is_allowed(&windows, 0, 60));
+        let windows = parse_windows("Fri-Mon 00:00-24:00").unwrap();
+        assert!(is_allowed(&windows, 6, 0));
+        assert!(is_allowed(&windows, 0, MINUTES_PER_DAY - 1));
+        assert!(!is_allowed(&windows, 2, 12 * 60));
+        assert!(parse_windows("daily 08:00-25:00").is_err());
+        assert!(parse_windows("Weekdays 08:00-12:00").is_err());
+        assert!(parse_windows("Mon 0800").is_err());
+
+        assert_eq!(parse_zone("").unwrap(), Zone::Local);
+        assert_eq!(
+            parse_zone("-05:30").unwrap(),
+            Zone::Offset(FixedOffset::west_opt(5 * 3600 + 30 * 60).unwrap())
+        );
+        assert!(matches!(parse_zone("Asia/Shanghai"), Ok(Zone::Named(_))));
+        assert!(parse_zone("Mars/Olympus").is_err());
+    }
+}
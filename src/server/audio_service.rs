@@ -13,6 +13,7 @@
 // https://github.com/krruzic/pulsectl
 
 use super::*;
+use crate::common::AudioApp;
 #[cfg(not(any(target_os = "linux", target_os = "android")))]
 use hbb_common::anyhow::anyhow;
 use magnum_opus::{Application::*, Channels::*, Encoder};
@@ -21,9 +22,14 @@ use std::sync::atomic::{AtomicBool, Ordering};
 pub const NAME: &'static str = "audio";
 pub const AUDIO_DATA_SIZE_U8: usize = 960 * 4; // 10ms in 48000 stereo
 static RESTARTING: AtomicBool = AtomicBool::new(false);
+// The `_pa` ipc audio input of an application, followed by the pid.
+#[cfg(target_os = "linux")]
+pub const APP_INPUT_PREFIX: &'static str = "app:";
 
 lazy_static::lazy_static! {
     static ref VOICE_CALL_INPUT_DEVICE: Arc::<Mutex::<Option<String>>> = Default::default();
+    // The connection selecting the application and the application captured.
+    static ref APP_SOURCE: Arc::<Mutex::<Option<(i32, AudioApp)>>> = Default::default();
 }
 
 #[cfg(not(any(target_os = "linux", target_os = "android")))]
@@ -54,6 +60,8 @@ pub fn set_voice_call_input_device(device: Option<String>, set_if_present: bool)
     if *VOICE_CALL_INPUT_DEVICE.lock().unwrap() == device {
         return;
     }
+    #[cfg(feature = "audio_processing")]
+    crate::audio_processing::set_enabled(device.is_some());
     *VOICE_CALL_INPUT_DEVICE.lock().unwrap() = device;
     restart();
 }
@@ -67,6 +75,69 @@ fn get_audio_input() -> String {
         .unwrap_or(Config::get_option("audio-input"))
 }
 
+/// Whether the audio of a single application can be captured instead of the system mix.
+pub fn is_support_app_capture() -> bool {
+    #[cfg(windows)]
+    return crate::platform::win_audio_loopback::is_supported();
+    #[cfg(target_os = "linux")]
+    return crate::platform::linux::is_pa_app_capture_supported();
+    #[cfg(not(any(windows, target_os = "linux")))]
+    return false;
+}
+
+/// The applications playing audio now, which can be selected by [`set_app_source`].
+pub fn get_audio_apps() -> Vec<AudioApp> {
+    if !is_support_app_capture() {
+        return vec![];
+    }
+    #[cfg(windows)]
+    return crate::platform::win_audio_loopback::get_audio_apps();
+    #[cfg(target_os = "linux")]
+    return crate::platform::linux::get_pa_audio_apps();
+    #[cfg(not(any(windows, target_os = "linux")))]
+    return vec![];
+}
+
+#[inline]
+pub fn get_app_source() -> Option<AudioApp> {
+    APP_SOURCE.lock().unwrap().as_ref().map(|x| x.1.clone())
+}
+
+/// Capture the audio of `app` instead of the system mix, `None` to capture the system mix again.
+///
+/// The last selection wins if multiple connections select.
+pub fn set_app_source(conn_id: i32, app: Option<AudioApp>) {
+    let mut lock = APP_SOURCE.lock().unwrap();
+    if lock.as_ref().map(|x| &x.1) == app.as_ref() {
+        return;
+    }
+    log::info!("#{} select the audio source: {:?}", conn_id, app);
+    *lock = app.map(|app| (conn_id, app));
+    drop(lock);
+    restart();
+}
+
+/// Capture the system mix again when the connection selecting the application closes.
+pub fn reset_app_source(conn_id: i32) {
+    let mut lock = APP_SOURCE.lock().unwrap();
+    if lock.as_ref().map(|x| x.0) != Some(conn_id) {
+        return;
+    }
+    *lock = None;
+    drop(lock);
+    restart();
+}
+
+// The voice call input device takes precedence over the application.
+#[inline]
+#[cfg(any(windows, target_os = "linux"))]
+fn get_capture_app() -> Option<AudioApp> {
+    if get_voice_call_input_device().is_some() {
+        return None;
+    }
+    get_app_source()
+}
+
 pub fn restart() {
     log::info!("restart the audio service, freezing now...");
     if RESTARTING.load(Ordering::SeqCst) {
@@ -102,11 +173,16 @@ mod pa_impl {
         }
         let mut encoder = Encoder::new(crate::platform::PA_SAMPLE_RATE, Stereo, LowDelay)?;
         #[cfg(target_os = "linux")]
+        let audio_input = match super::get_capture_app() {
+            Some(app) => format!("{}{}", APP_INPUT_PREFIX, app.pid),
+            None => super::get_audio_input(),
+        };
+        #[cfg(target_os = "linux")]
         allow_err!(
             stream
                 .send(&crate::ipc::Data::Config((
                     "audio-input".to_owned(),
-                    Some(super::get_audio_input())
+                    Some(audio_input)
                 )))
                 .await
         );
@@ -135,7 +211,14 @@ mod pa_impl {
                 let data = unsafe {
                     std::slice::from_raw_parts::<f32>(data.as_ptr() as _, data.len() / 4)
                 };
+                #[cfg(feature = "audio_processing")]
+                let data = &mut data.to_vec();
+                #[cfg(feature = "audio_processing")]
+                crate::audio_processing::process_capture(data, crate::platform::PA_SAMPLE_RATE, 2);
                 send_f32(data, &mut encoder, &sp);
+            } else {
+                // The `_pa` capture stops, e.g. the application captured exits.
+                bail!("Failed to receive the audio data from the pa ipc");
             }
 
             #[cfg(target_os = "android")]
@@ -147,6 +230,10 @@ mod pa_impl {
                         android_data.len() / 4,
                     )
                 };
+                #[cfg(feature = "audio_processing")]
+                let data = &mut data.to_vec();
+                #[cfg(feature = "audio_processing")]
+                crate::audio_processing::process_capture(data, crate::platform::PA_SAMPLE_RATE, 2);
                 send_f32(data, &mut encoder, &sp);
             } else {
                 hbb_common::sleep(0.1).await;
@@ -256,6 +343,8 @@ mod cpal_impl {
                 encode_channel,
             )
         }
+        #[cfg(feature = "audio_processing")]
+        crate::audio_processing::process_capture(&mut data, sample_rate, encode_channel);
         send_f32(&data, encoder, sp);
     }
 
@@ -343,11 +432,50 @@ mod cpal_impl {
             .map_err(|e| anyhow!(e))
             .with_context(|| "Failed to get default input format")?;
         log::info!("Default input format: {:?}", format);
+        let format = if super::get_voice_call_input_device().is_none() {
+            prefer_stereo_input(&device, format)
+        } else {
+            format
+        };
         Ok((device, format))
     }
 
+    // Some devices, e.g. the loopback drivers, default to mono while supporting stereo.
+    // The encoder supports up to stereo, the devices with more channels are downmixed in `send`.
+    fn prefer_stereo_input(
+        device: &Device,
+        format: SupportedStreamConfig,
+    ) -> SupportedStreamConfig {
+        if format.channels() != 1 {
+            return format;
+        }
+        let Ok(configs) = device.supported_input_configs() else {
+            return format;
+        };
+        let sample_rate = format.sample_rate();
+        for c in configs {
+            if c.channels() == 2
+                && c.sample_format() == format.sample_format()
+                && c.min_sample_rate() <= sample_rate
+                && sample_rate <= c.max_sample_rate()
+            {
+                let stereo = c.with_sample_rate(sample_rate);
+                log::info!("Use stereo input format: {:?}", stereo);
+                return stereo;
+            }
+        }
+        format
+    }
+
     fn play(sp: &GenericService) -> ResultType<(Box<dyn StreamTrait>, Arc<Message>)> {
         use cpal::SampleFormat::*;
+        #[cfg(windows)]
+        if let Some(app) = super::get_capture_app() {
+            match play_app(sp, &app) {
+                Ok(stream) => return Ok(stream),
+                Err(e) => log::error!("Failed to capture the audio of {:?}: {}", app, e),
+            }
+        }
         let (device, config) = get_device()?;
         let sp = sp.clone();
         // Sample rate must be one of 8000, 12000, 16000, 24000, or 48000.
@@ -384,6 +512,54 @@ mod cpal_impl {
         ))
     }
 
+    #[cfg(windows)]
+    struct AppStream(crate::platform::win_audio_loopback::LoopbackCapture);
+
+    #[cfg(windows)]
+    impl StreamTrait for AppStream {
+        fn play(&self) -> Result<(), cpal::PlayStreamError> {
+            Ok(())
+        }
+
+        fn pause(&self) -> Result<(), cpal::PauseStreamError> {
+            Ok(())
+        }
+    }
+
+    #[cfg(windows)]
+    fn play_app(
+        sp: &GenericService,
+        app: &AudioApp,
+    ) -> ResultType<(Box<dyn StreamTrait>, Arc<Message>)> {
+        use crate::platform::win_audio_loopback::{LoopbackCapture, CHANNELS, SAMPLE_RATE};
+        let sp = sp.clone();
+        unsafe {
+            AUDIO_ZERO_COUNT = 0;
+        }
+        let mut encoder = Encoder::new(SAMPLE_RATE, Stereo, LowDelay)?;
+        let frame_len = SAMPLE_RATE as usize / 100 * CHANNELS as usize; // 10 ms
+        let mut buffer = Vec::new();
+        let capture = LoopbackCapture::start(app.pid, move |data| {
+            buffer.extend_from_slice(data);
+            while buffer.len() >= frame_len {
+                let frame: Vec<f32> = buffer.drain(0..frame_len).collect();
+                send(
+                    frame,
+                    SAMPLE_RATE,
+                    SAMPLE_RATE,
+                    CHANNELS,
+                    CHANNELS,
+                    &mut encoder,
+                    &sp,
+                );
+            }
+        })?;
+        Ok((
+            Box::new(AppStream(capture)),
+            Arc::new(create_format_msg(SAMPLE_RATE, CHANNELS)),
+        ))
+    }
+
     fn build_input_stream<T>(
         device: cpal::Device,
         config: &cpal::SupportedStreamConfig,
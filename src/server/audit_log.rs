@@ -0,0 +1,302 @@
+//! A local append-only audit log of the incoming sessions, for the compliance audits
+//! without an audit server.
+//!
+//! It's enabled with the option [`OPTION_ENABLE`]. Each [`Entry`] is a JSON line of the file
+//! [`FILE_NAME`] in the directory [`dir`], which is rotated to `audit.1.jsonl`, `audit.2.jsonl`
+//! and so on when it's larger than the option [`OPTION_MAX_SIZE`] in MB, the oldest one
+//! beyond [`MAX_ROTATED`] is removed.
+//!
+//! The events recorded:
+//! - [`EVENT_CONNECTION`]: the new and closed connections.
+//! - [`EVENT_AUTH`]: the successful logins and the login errors, e.g. the wrong passwords.
+//! - [`EVENT_PERMISSION`]: the permissions switched during the session.
+//! - [`EVENT_FILE`]: the files sent and received, with their paths, sizes and SHA-256 hashes.
+//!   The hashes of the files received are recorded after they are written.
+//! - [`EVENT_CLIPBOARD`]: the formats and sizes of the clipboards synced in both directions.
+//! - [`EVENT_PROCESS`]: the processes killed with the task manager, see [`crate::task_manager`].
+//! - [`EVENT_COMMAND`]: the commands run for the peer, see [`crate::remote_command`].
+//!
+//! The log is queried with [`query`], e.g. by the CLI `--audit-log`.
+use hbb_common::{
+    config::Config,
+    log,
+    message_proto::{Clipboard, ClipboardFormat},
+    tokio, ResultType,
+};
+use serde::de::DeserializeOwned;
+use serde_derive::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, Read, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+pub const OPTION_ENABLE: &str = "enable-audit-log";
+pub const OPTION_MAX_SIZE: &str = "audit-log-max-size";
+pub const FILE_NAME: &str = "audit.jsonl";
+pub const EVENT_CONNECTION: &str = "connection";
+pub const EVENT_AUTH: &str = "auth";
+pub const EVENT_PERMISSION: &str = "permission";
+pub const EVENT_FILE: &str = "file";
+pub const EVENT_CLIPBOARD: &str = "clipboard";
+pub const EVENT_PROCESS: &str = "process";
+pub const EVENT_COMMAND: &str = "command";
+const DEFAULT_MAX_SIZE_MB: u64 = 10;
+const MAX_ROTATED: usize = 5;
+// The larger files are recorded without the hashes.
+const MAX_HASH_SIZE: u64 = 4 << 30;
+
+lazy_static::lazy_static! {
+    // Serialize the writes and the rotation of the connections.
+    static ref LOCK: Mutex<()> = Default::default();
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Entry {
+    /// The unix time in milliseconds.
+    pub time: i64,
+    pub event: String,
+    /// 0 if not bound to a connection.
+    pub conn_id: i32,
+    pub peer_id: String,
+    pub peer_name: String,
+    pub ip: String,
+    pub info: Value,
+}
+
+/// The filter of [`query`], the empty fields match all.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Query {
+    pub event: String,
+    pub peer_id: String,
+    /// The unix time in milliseconds, inclusive.
+    pub from: i64,
+    /// The unix time in milliseconds, exclusive, 0 for now.
+    pub to: i64,
+    /// The latest entries to return, 0 for all.
+    pub limit: usize,
+}
+
+impl Query {
+    fn matches(&self, entry: &Entry) -> bool {
+        (self.event.is_empty() || entry.event == self.event)
+            && (self.peer_id.is_empty() || entry.peer_id == self.peer_id)
+            && entry.time >= self.from
+            && (self.to == 0 || entry.time < self.to)
+    }
+}
+
+#[inline]
+pub fn is_enabled() -> bool {
+    Config::get_option(OPTION_ENABLE) == "Y"
+}
+
+/// The directory of the log files.
+pub fn dir() -> PathBuf {
+    Config::path("audit")
+}
+
+/// Append the entry if the log is enabled, the time is filled if 0.
+pub fn record(mut entry: Entry) {
+    if !is_enabled() {
+        return;
+    }
+    if entry.time == 0 {
+        entry.time = hbb_common::get_time();
+    }
+    let _lock = LOCK.lock().unwrap();
+    if let Err(e) = append(&dir(), &entry, max_size()) {
+        log::error!("Failed to write the audit log: {}", e);
+    }
+}
+
+/// Record the files in `dir`, with their hashes if `hash`, they are read in a blocking task.
+pub fn record_files(mut entry: Entry, dir: String, files: Vec<(String, i64)>, hash: bool) {
+    if !is_enabled() {
+        return;
+    }
+    entry.time = hbb_common::get_time();
+    tokio::task::spawn_blocking(move || {
+        let files: Vec<Value> = files
+            .into_iter()
+            .map(|(name, size)| {
+                let sha256 = if !hash || dir.is_empty() {
+                    None
+                } else if name.is_empty() {
+                    // `dir` is the file itself.
+                    hash_file(Path::new(&dir))
+                } else {
+                    hash_file(&Path::new(&dir).join(&name))
+                };
+                json!({ "name": name, "size": size, "sha256": sha256 })
+            })
+            .collect();
+        entry.info["path"] = json!(dir);
+        entry.info["files"] = json!(files);
+        record(entry);
+    });
+}
+
+/// The info of the clipboards synced, without their contents.
+pub fn clipboard_info(direction: &str, clipboards: &[Clipboard]) -> Value {
+    let formats: Vec<Value> = clipboards
+        .iter()
+        .map(|c| {
+            let format = match c.format.enum_value() {
+                Ok(ClipboardFormat::Special) => c.special_name.clone(),
+                Ok(format) => format!("{:?}", format),
+                Err(v) => v.to_string(),
+            };
+            json!({ "format": format, "size": c.content.len() })
+        })
+        .collect();
+    json!({ "direction": direction, "formats": formats })
+}
+
+/// The entries matching `q` in the order of the time.
+pub fn query(q: &Query) -> ResultType<Vec<Entry>> {
+    let _lock = LOCK.lock().unwrap();
+    read(&dir(), q)
+}
+
+fn max_size() -> u64 {
+    match Config::get_option(OPTION_MAX_SIZE).parse::<u64>() {
+        Ok(mb) if mb > 0 => mb << 20,
+        _ => DEFAULT_MAX_SIZE_MB << 20,
+    }
+}
+
+fn rotated_path(dir: &Path, file_name: &str, i: usize) -> PathBuf {
+    if i == 0 {
+        dir.join(file_name)
+    } else {
+        dir.join(file_name.replace(".jsonl", &format!(".{}.jsonl", i)))
+    }
+}
+
+fn append(dir: &Path, entry: &Entry, max_size: u64) -> ResultType<()> {
+    append_jsonl(dir, FILE_NAME, entry, max_size)
+}
+
+/// Append `value` as a JSON line of `file_name` in `dir`, the file is rotated as the audit log.
+pub(crate) fn append_jsonl<T: serde::Serialize>(
+    dir: &Path,
+    file_name: &str,
+    value: &T,
+    max_size: u64,
+) -> ResultType<()> {
+    fs::create_dir_all(dir)?;
+    let path = rotated_path(dir, file_name, 0);
+    if fs::metadata(&path).map(|m| m.len()).unwrap_or(0) >= max_size {
+        rotate(dir, file_name)?;
+    }
+    let mut line = serde_json::to_string(value)?;
+    line.push('\n');
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)?
+        .write_all(line.as_bytes())?;
+    Ok(())
+}
+
+fn rotate(dir: &Path, file_name: &str) -> ResultType<()> {
+    let oldest = rotated_path(dir, file_name, MAX_ROTATED);
+    if oldest.exists() {
+        fs::remove_file(&oldest)?;
+    }
+    for i in (0..MAX_ROTATED).rev() {
+        let path = rotated_path(dir, file_name, i);
+        if path.exists() {
+            fs::rename(&path, rotated_path(dir, file_name, i + 1))?;
+        }
+    }
+    Ok(())
+}
+
+fn read(dir: &Path, q: &Query) -> ResultType<Vec<Entry>> {
+    let mut entries = read_jsonl(dir, FILE_NAME, |e| q.matches(e))?;
+    if q.limit > 0 && entries.len() > q.limit {
+        entries.drain(..entries.len() - q.limit);
+    }
+    Ok(entries)
+}
+
+/// The values of the JSON lines of `file_name` and its rotated files in `dir` matching
+/// `filter`, the oldest first.
+pub(crate) fn read_jsonl<T: DeserializeOwned>(
+    dir: &Path,
+    file_name: &str,
+    filter: impl Fn(&T) -> bool,
+) -> ResultType<Vec<T>> {
+    let mut values = vec![];
+    for i in (0..=MAX_ROTATED).rev() {
+        let Ok(file) = File::open(rotated_path(dir, file_name, i)) else {
+            continue;
+        };
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            match serde_json::from_str::<T>(&line) {
+                Ok(value) if filter(&value) => values.push(value),
+                Ok(_) => {}
+                Err(e) => log::warn!("Invalid line of {}: {}", file_name, e),
+            }
+        }
+    }
+    Ok(values)
+}
+
+fn hash_file(path: &Path) -> Option<String> {
+    if !path.is_file() || path.metadata().ok()?.len() > MAX_HASH_SIZE {
+        return None;
+    }
+    let mut file = File::open(path).ok()?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 1 << 16];
+    loop {
+        let n = file.read(&mut buf).ok()?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Some(hex::encode(hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotate_and_read() {
+        let dir = std::env::temp_dir().join(format!("rustdesk-audit-{}", std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        for i in 0..20 {
+            let entry = Entry {
+                time: i,
+                event: if i % 2 == 0 { EVENT_AUTH } else { EVENT_FILE }.to_owned(),
+                peer_id: "123".to_owned(),
+                ..Default::default()
+            };
+            append(&dir, &entry, 200).unwrap();
+        }
+        assert!(rotated_path(&dir, FILE_NAME, 1).exists());
+        let all = read(&dir, &Query::default()).unwrap();
+        assert!(all.windows(2).all(|w| w[0].time < w[1].time));
+        assert_eq!(all.last().unwrap().time, 19);
+        let q = Query {
+            event: EVENT_AUTH.to_owned(),
+            from: 10,
+            limit: 2,
+            ..Default::default()
+        };
+        let times: Vec<i64> = read(&dir, &q).unwrap().iter().map(|e| e.time).collect();
+        assert_eq!(times, vec![16, 18]);
+        fs::remove_dir_all(&dir).ok();
+    }
+}
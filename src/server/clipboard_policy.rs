@@ -0,0 +1,205 @@
+//! The host policy of the clipboard sync, configured with the options:
+//!
+//! - [`OPTION_MAX_SIZE`]: the maximum size in KB of each clipboard format, the larger ones are dropped.
+//! - [`OPTION_DIRECTION`]: [`DIRECTION_HOST_TO_CLIENT`] or [`DIRECTION_CLIENT_TO_HOST`]
+//!   to sync the clipboard in one direction only, both directions if empty.
+//! - [`OPTION_REDACT_REGEX`]: the matches in the text, HTML and RTF formats are replaced
+//!   with [`REDACTED`], e.g. to avoid leaking the passwords and the keys.
+//!
+//! The policy is enforced by the clipboard service for the host clipboard sent to the clients,
+//! and by the connections for the client clipboards. The dropped and redacted contents are
+//! posted to the audit server as connection events of the action "clipboard".
+//! The file clipboard is controlled by the file transfer permission instead.
+use hbb_common::{
+    compress::{compress, decompress},
+    config::Config,
+    log,
+    message_proto::{Clipboard, ClipboardFormat},
+    regex::Regex,
+    tokio,
+};
+use serde_json::{json, Value};
+use std::sync::Mutex;
+
+pub const OPTION_MAX_SIZE: &str = "clipboard-max-size";
+pub const OPTION_DIRECTION: &str = "clipboard-direction";
+pub const OPTION_REDACT_REGEX: &str = "clipboard-redact-regex";
+pub const DIRECTION_HOST_TO_CLIENT: &str = "host-to-client";
+pub const DIRECTION_CLIENT_TO_HOST: &str = "client-to-host";
+pub const REDACTED: &str = "[REDACTED]";
+
+lazy_static::lazy_static! {
+    // The regex is compiled again only when the option changes.
+    static ref REDACT_REGEX: Mutex<Option<(String, Option<Regex>)>> = Default::default();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    HostToClient,
+    ClientToHost,
+}
+
+impl Direction {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Direction::HostToClient => DIRECTION_HOST_TO_CLIENT,
+            Direction::ClientToHost => DIRECTION_CLIENT_TO_HOST,
+        }
+    }
+
+    fn is_allowed(&self) -> bool {
+        let option = Config::get_option(OPTION_DIRECTION);
+        option.is_empty() || option == self.as_str()
+    }
+}
+
+/// Apply the policy to the clipboards sent in `direction`,
+/// returns the audit info if some contents are dropped or redacted.
+pub fn apply(direction: Direction, clipboards: &mut Vec<Clipboard>) -> Option<Value> {
+    if !direction.is_allowed() {
+        log::debug!("Clipboard sync {} is not allowed", direction.as_str());
+        clipboards.clear();
+        return None;
+    }
+    let max_size = Config::get_option(OPTION_MAX_SIZE)
+        .parse::<usize>()
+        .unwrap_or(0)
+        * 1024;
+    let mut dropped = vec![];
+    if max_size > 0 {
+        clipboards.retain(|c| {
+            let keep = c.content.len() <= max_size;
+            if !keep {
+                dropped.push(format_name(c));
+            }
+            keep
+        });
+    }
+    let mut redacted = vec![];
+    if let Some(re) = get_redact_regex() {
+        for c in clipboards.iter_mut() {
+            if redact(&re, c) {
+                redacted.push(format_name(c));
+            }
+        }
+    }
+    if dropped.is_empty() && redacted.is_empty() {
+        return None;
+    }
+    log::info!(
+        "Clipboard policy {}, dropped: {:?}, redacted: {:?}",
+        direction.as_str(),
+        dropped,
+        redacted
+    );
+    Some(json!({
+        "action": "clipboard",
+        "direction": direction.as_str(),
+        "dropped": dropped,
+        "redacted": redacted,
+    }))
+}
+
+/// Post the audit info of the clipboard service, it's not bound to a connection.
+pub fn post_audit(info: Value) {
+    let url = crate::get_audit_server(
+        Config::get_option("api-server"),
+        Config::get_option("custom-rendezvous-server"),
+        "conn".to_owned(),
+    );
+    if url.is_empty() {
+        return;
+    }
+    let mut v = info;
+    v["id"] = json!(Config::get_id());
+    v["uuid"] = json!(crate::encode64(hbb_common::get_uuid()));
+    std::thread::spawn(move || post_audit_(url, v));
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn post_audit_(url: String, v: Value) {
+    if let Err(e) = crate::post_request(url, v.to_string(), "").await {
+        log::error!("Failed to post clipboard audit: {}", e);
+    }
+}
+
+fn get_redact_regex() -> Option<Regex> {
+    let option = Config::get_option(OPTION_REDACT_REGEX);
+    if option.is_empty() {
+        return None;
+    }
+    let mut cache = REDACT_REGEX.lock().unwrap();
+    match cache.as_ref() {
+        Some((s, re)) if *s == option => re.clone(),
+        _ => {
+            let re = Regex::new(&option)
+                .map_err(|e| log::error!("Invalid clipboard redact regex: {}", e))
+                .ok();
+            *cache = Some((option, re.clone()));
+            re
+        }
+    }
+}
+
+fn format_name(c: &Clipboard) -> String {
+    match c.format.enum_value() {
+        Ok(ClipboardFormat::Special) => c.special_name.clone(),
+        Ok(format) => format!("{:?}", format),
+        Err(v) => v.to_string(),
+    }
+}
+
+// Returns true if the content is redacted.
+fn redact(re: &Regex, c: &mut Clipboard) -> bool {
+    if !matches!(
+        c.format.enum_value(),
+        Ok(ClipboardFormat::Text | ClipboardFormat::Html | ClipboardFormat::Rtf)
+    ) {
+        return false;
+    }
+    let data = if c.compress {
+        decompress(&c.content)
+    } else {
+        c.content.to_vec()
+    };
+    let Ok(text) = String::from_utf8(data) else {
+        return false;
+    };
+    if !re.is_match(&text) {
+        return false;
+    }
+    let text = re.replace_all(&text, REDACTED);
+    let compressed = compress(text.as_bytes());
+    c.compress = compressed.len() < text.len();
+    c.content = if c.compress {
+        compressed
+    } else {
+        text.as_bytes().to_vec()
+    }
+    .into();
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact() {
+        let re = Regex::new(r"sk-[A-Za-z0-9]{8,}").unwrap();
+        let mut c = Clipboard {
+            content: b"key: sk-abcdefgh1234".to_vec().into(),
+            format: ClipboardFormat::Text.into(),
+            ..Default::default()
+        };
+        assert!(redact(&re, &mut c));
+        assert_eq!(&c.content[..], format!("key: {}", REDACTED).as_bytes());
+        assert!(!redact(&re, &mut c));
+        let mut c = Clipboard {
+            content: b"sk-abcdefgh1234".to_vec().into(),
+            format: ClipboardFormat::ImagePng.into(),
+            ..Default::default()
+        };
+        assert!(!redact(&re, &mut c));
+    }
+}
@@ -1,3 +1,5 @@
+#[cfg(not(target_os = "android"))]
+use super::clipboard_policy::{self, Direction};
 use super::*;
 #[cfg(not(target_os = "android"))]
 use crate::clipboard::clipboard_listener;
@@ -33,6 +35,8 @@ static CLIPBOARD_SERVICE_OK: AtomicBool = AtomicBool::new(false);
 #[cfg(not(target_os = "android"))]
 struct Handler {
     ctx: Option<ClipboardContext>,
+    // The hash of the content sent last, only when polling.
+    last_polled: Option<u64>,
     #[cfg(target_os = "windows")]
     stream: Option<ipc::ConnectionTmpl<parity_tokio_ipc::ConnectionClient>>,
     #[cfg(target_os = "windows")]
@@ -68,9 +72,25 @@ fn run(sp: EmptyExtraFieldService) -> ResultType<()> {
 
     let (tx_cb_result, rx_cb_result) = channel();
     let ctx = Some(ClipboardContext::new().map_err(|e| io::Error::new(io::ErrorKind::Other, e))?);
-    clipboard_listener::subscribe(sp.name(), tx_cb_result)?;
+    // The selection events of X11 are not available in the Wayland sessions without XWayland,
+    // the clipboard is polled instead.
+    #[cfg(target_os = "linux")]
+    let is_polling = match clipboard_listener::subscribe(sp.name(), tx_cb_result) {
+        Ok(()) => false,
+        Err(e) if crate::platform::current_is_wayland() => {
+            log::warn!("Failed to listen to the clipboard, poll it instead: {}", e);
+            true
+        }
+        Err(e) => return Err(e),
+    };
+    #[cfg(not(target_os = "linux"))]
+    let is_polling = {
+        clipboard_listener::subscribe(sp.name(), tx_cb_result)?;
+        false
+    };
     let mut handler = Handler {
         ctx,
+        last_polled: None,
         #[cfg(target_os = "windows")]
         stream: None,
         #[cfg(target_os = "windows")]
@@ -78,15 +98,25 @@ fn run(sp: EmptyExtraFieldService) -> ResultType<()> {
     };
 
     while sp.ok() {
-        match rx_cb_result.recv_timeout(Duration::from_millis(INTERVAL)) {
+        let res = if is_polling {
+            std::thread::sleep(Duration::from_millis(INTERVAL));
+            Ok(CallbackResult::Next)
+        } else {
+            rx_cb_result.recv_timeout(Duration::from_millis(INTERVAL))
+        };
+        match res {
             Ok(CallbackResult::Next) => {
                 #[cfg(feature = "unix-file-copy-paste")]
                 if sp.name() == FILE_NAME {
-                    handler.check_clipboard_file();
+                    handler.check_clipboard_file(is_polling);
                     continue;
                 }
                 if let Some(msg) = handler.get_clipboard_msg() {
-                    sp.send(msg);
+                    if !is_polling
+                        || handler.is_polled_changed(&msg.write_to_bytes().unwrap_or_default())
+                    {
+                        sp.send(msg);
+                    }
                 }
             }
             Ok(CallbackResult::Stop) => {
@@ -104,16 +134,30 @@ fn run(sp: EmptyExtraFieldService) -> ResultType<()> {
         }
     }
 
-    clipboard_listener::unsubscribe(&sp.name());
+    if !is_polling {
+        clipboard_listener::unsubscribe(&sp.name());
+    }
 
     Ok(())
 }
 
 #[cfg(not(target_os = "android"))]
 impl Handler {
+    // The same content is read again every interval when polling.
+    fn is_polled_changed(&mut self, data: &impl std::hash::Hash) -> bool {
+        use std::{collections::hash_map::DefaultHasher, hash::Hasher};
+        let mut hasher = DefaultHasher::new();
+        data.hash(&mut hasher);
+        let hash = hasher.finish();
+        self.last_polled.replace(hash) != Some(hash)
+    }
+
     #[cfg(feature = "unix-file-copy-paste")]
-    fn check_clipboard_file(&mut self) {
+    fn check_clipboard_file(&mut self, is_polling: bool) {
         if let Some(urls) = check_clipboard_files(&mut self.ctx, ClipboardSide::Host, false) {
+            if is_polling && !self.is_polled_changed(&urls) {
+                return;
+            }
             if !urls.is_empty() {
                 #[cfg(target_os = "macos")]
                 if crate::clipboard::is_file_url_set_by_rustdesk(&urls) {
@@ -136,6 +180,29 @@ impl Handler {
     }
 
     fn get_clipboard_msg(&mut self) -> Option<Message> {
+        let mut msg = self.read_clipboard_msg()?;
+        if let Some(message::Union::MultiClipboards(mcb)) = msg.union.as_mut() {
+            if let Some(info) =
+                clipboard_policy::apply(Direction::HostToClient, &mut mcb.clipboards)
+            {
+                clipboard_policy::post_audit(info);
+            }
+            if mcb.clipboards.is_empty() {
+                return None;
+            }
+            super::audit_log::record(super::audit_log::Entry {
+                event: super::audit_log::EVENT_CLIPBOARD.to_owned(),
+                info: super::audit_log::clipboard_info(
+                    clipboard_policy::DIRECTION_HOST_TO_CLIENT,
+                    &mcb.clipboards,
+                ),
+                ..Default::default()
+            });
+        }
+        Some(msg)
+    }
+
+    fn read_clipboard_msg(&mut self) -> Option<Message> {
         #[cfg(target_os = "windows")]
         if crate::common::is_server() && crate::platform::is_root() {
             match self.read_clipboard_from_cm_ipc() {
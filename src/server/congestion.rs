@@ -0,0 +1,138 @@
+//! Congestion control of the video stream, in the manner of Google Congestion Control.
+//!
+//! The receiver report returned with each `TestDelay` round trip (about once per second)
+//! carries the one-way delay gradient and the frames dropped by the receiver.
+//! The delay-based part detects the overuse of the link from the trend of the gradient with
+//! an adaptive threshold, the loss-based part reacts to the drops, the target bitrate is the
+//! lower of both.
+use crate::client::feedback::ReceiverReport;
+
+// Weight of the history in the smoothed gradient.
+const SMOOTHING: f32 = 0.6;
+const INIT_THRESHOLD_MS: f32 = 12.5;
+const MIN_THRESHOLD_MS: f32 = 6.0;
+const MAX_THRESHOLD_MS: f32 = 600.0;
+// The threshold follows the gradient quickly up and slowly down.
+const THRESHOLD_GAIN_UP: f32 = 0.1;
+const THRESHOLD_GAIN_DOWN: f32 = 0.02;
+
+const DECREASE_FACTOR: f32 = 0.85;
+const MULTIPLICATIVE_INCREASE: f32 = 1.08;
+// Near the rate of the last decrease, increase additively.
+const ADDITIVE_INCREASE_KBPS: f32 = 100.0;
+const NEAR_LAST_DECREASE: f32 = 1.5;
+
+const LOSS_HIGH: f32 = 0.1;
+const LOSS_LOW: f32 = 0.02;
+const LOSS_INCREASE: f32 = 1.05;
+
+const MIN_KBPS: f32 = 100.0;
+// The encoder may not reach the target on a static screen, don't let the target run away.
+const MAX_TARGET_MULTIPLE: f32 = 1.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Usage {
+    Normal,
+    Over,
+    Under,
+}
+
+#[derive(Debug, Clone)]
+pub struct CongestionController {
+    target_kbps: Option<f32>,
+    smoothed_gradient: f32,
+    threshold: f32,
+    last_decrease_kbps: Option<f32>,
+    usage: Usage,
+}
+
+impl Default for CongestionController {
+    fn default() -> Self {
+        Self {
+            target_kbps: None,
+            smoothed_gradient: 0.0,
+            threshold: INIT_THRESHOLD_MS,
+            last_decrease_kbps: None,
+            usage: Usage::Normal,
+        }
+    }
+}
+
+impl CongestionController {
+    /// Whether a report has been received, the receivers without the feedback never send one.
+    #[inline]
+    pub fn active(&self) -> bool {
+        self.target_kbps.is_some()
+    }
+
+    #[inline]
+    pub fn target_kbps(&self) -> Option<f32> {
+        self.target_kbps
+    }
+
+    #[inline]
+    pub fn usage(&self) -> Usage {
+        self.usage
+    }
+
+    /// Update with the report of the receiver, `current_kbps` is the bitrate of the encoder.
+    /// Returns the target bitrate in kbps.
+    pub fn on_report(&mut self, report: ReceiverReport, current_kbps: u32) -> Option<f32> {
+        if current_kbps == 0 {
+            return None;
+        }
+        let current = current_kbps as f32;
+        let base = self
+            .target_kbps
+            .unwrap_or(current)
+            .min(current * MAX_TARGET_MULTIPLE);
+
+        self.usage = self.detect(report.delay_gradient_ms as f32);
+        let delay_based = match self.usage {
+            Usage::Over => {
+                let v = base.min(current) * DECREASE_FACTOR;
+                self.last_decrease_kbps = Some(v);
+                v
+            }
+            // The queues are draining, wait until they are empty.
+            Usage::Under => base,
+            Usage::Normal => match self.last_decrease_kbps {
+                Some(last) if base < last * NEAR_LAST_DECREASE => base + ADDITIVE_INCREASE_KBPS,
+                _ => base * MULTIPLICATIVE_INCREASE,
+            },
+        };
+
+        let loss = report.loss();
+        let loss_based = if loss > LOSS_HIGH {
+            base * (1.0 - 0.5 * loss)
+        } else if loss < LOSS_LOW {
+            base * LOSS_INCREASE
+        } else {
+            base
+        };
+
+        let target = delay_based.min(loss_based).max(MIN_KBPS);
+        self.target_kbps = Some(target);
+        Some(target)
+    }
+
+    fn detect(&mut self, gradient: f32) -> Usage {
+        self.smoothed_gradient = SMOOTHING * self.smoothed_gradient + (1.0 - SMOOTHING) * gradient;
+        let g = self.smoothed_gradient;
+        let usage = if g > self.threshold {
+            Usage::Over
+        } else if g < -self.threshold {
+            Usage::Under
+        } else {
+            Usage::Normal
+        };
+        let gain = if g.abs() > self.threshold {
+            THRESHOLD_GAIN_UP
+        } else {
+            THRESHOLD_GAIN_DOWN
+        };
+        self.threshold = (self.threshold + gain * (g.abs() - self.threshold))
+            .clamp(MIN_THRESHOLD_MS, MAX_THRESHOLD_MS);
+        usage
+    }
+}
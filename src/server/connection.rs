@@ -45,7 +45,7 @@ use hbb_common::{
 #[cfg(any(target_os = "android", target_os = "ios"))]
 use scrap::android::{call_main_service_key_event, call_main_service_pointer_input};
 use scrap::camera;
-use serde_derive::Serialize;
+use serde_derive::{Deserialize, Serialize};
 use serde_json::{json, value::Value};
 #[cfg(not(any(target_os = "android", target_os = "ios")))]
 use std::sync::atomic::Ordering;
@@ -88,6 +88,8 @@ pub static MOUSE_MOVE_TIME: AtomicI64 = AtomicI64::new(0);
 #[cfg(not(any(target_os = "android", target_os = "ios")))]
 lazy_static::lazy_static! {
     static ref PLUGIN_BLOCK_INPUT_TXS: Arc<Mutex<HashMap<String, std_mpsc::Sender<MessageInput>>>> = Default::default();
+    // The connections to send the plugin messages to the peers, by the peer id.
+    static ref PLUGIN_PEER_TXS: Arc<Mutex<HashMap<String, Sender>>> = Default::default();
     static ref PLUGIN_BLOCK_INPUT_TX_RX: (Arc<Mutex<std_mpsc::Sender<bool>>>, Arc<Mutex<std_mpsc::Receiver<bool>>>) = {
         let (tx, rx) = std_mpsc::channel();
         (Arc::new(Mutex::new(tx)), Arc::new(Mutex::new(rx)))
@@ -121,6 +123,16 @@ pub fn plugin_block_input(peer: &str, block: bool) -> bool {
     }
 }
 
+/// Send the plugin message to the connected peer, returns false if the peer is not connected.
+#[cfg(all(feature = "flutter", feature = "plugin_framework"))]
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+pub fn plugin_send_to_peer(peer: &str, msg: Message) -> bool {
+    match PLUGIN_PEER_TXS.lock().unwrap().get(peer) {
+        Some(tx) => tx.send((Instant::now(), Arc::new(msg))).is_ok(),
+        None => false,
+    }
+}
+
 #[derive(Clone, Default)]
 pub struct ConnInner {
     id: i32,
@@ -144,6 +156,10 @@ enum MessageInput {
     Key((KeyEvent, bool)),
     #[cfg(not(any(target_os = "android", target_os = "ios")))]
     Pointer((PointerDeviceEvent, i32)),
+    #[cfg(any(target_os = "windows", target_os = "linux"))]
+    Gamepad(crate::gamepad::GamepadEvent),
+    #[cfg(any(target_os = "windows", target_os = "linux"))]
+    Pen(crate::pen::PenEvent),
     BlockOn,
     BlockOff,
     #[cfg(all(feature = "flutter", feature = "plugin_framework"))]
@@ -210,6 +226,7 @@ pub struct Connection {
     server: super::ServerPtrWeak,
     hash: Hash,
     read_jobs: Vec<fs::TransferJob>,
+    transfer_limiter: crate::transfer_limit::TransferLimiter,
     timer: crate::RustDeskInterval,
     file_timer: crate::RustDeskInterval,
     file_transfer: Option<(String, bool)>,
@@ -220,6 +237,9 @@ pub struct Connection {
     tx_to_cm: mpsc::UnboundedSender<ipc::Data>,
     authorized: bool,
     require_2fa: Option<totp_rs::TOTP>,
+    // The security keys registered, empty if not required or already verified.
+    require_fido2: Vec<crate::auth_fido2::Credential>,
+    fido2_challenge: Option<Vec<u8>>,
     keyboard: bool,
     clipboard: bool,
     audio: bool,
@@ -272,6 +292,22 @@ pub struct Connection {
     #[cfg(not(any(target_os = "android", target_os = "ios")))]
     start_cm_ipc_para: Option<StartCmIpcPara>,
     auto_disconnect_timer: Option<(Instant, u64)>,
+    expiry_timer: Option<crate::session_expiry::ExpiryTimer>,
+    // The path and the files of the receiving jobs, recorded to the audit log when written.
+    audit_receive_jobs: HashMap<i32, (String, Vec<(String, i64)>)>,
+    // The fingerprint of the end-to-end key, empty if not secured.
+    session_fingerprint: String,
+    // The session handed off to this connection, redeemed before the login request.
+    handoff: Option<crate::session_handoff::Ticket>,
+    // The peer probes the latency of the video frames, see `crate::latency_probe`.
+    latency_probe: bool,
+    // Recorded to the connection history when closed.
+    history: crate::connection_history::Entry,
+    // The remote commands waiting for the consent in the cm, by the id.
+    pending_commands: HashMap<u32, crate::remote_command::Request>,
+    // The peer shows its annotations on the screen, see `crate::annotation`.
+    #[cfg(not(any(target_os = "android", target_os = "ios")))]
+    annotating: bool,
     authed_conn_id: Option<self::raii::AuthedConnID>,
     file_remove_log_control: FileRemoveLogControl,
     last_supported_encoding: Option<SupportedEncoding>,
@@ -344,6 +380,8 @@ impl Connection {
         addr: SocketAddr,
         stream: super::Stream,
         id: i32,
+        session_fingerprint: String,
+        relay: bool,
         server: super::ServerPtrWeak,
     ) {
         let _raii_id = raii::ConnectionID::new(id);
@@ -383,11 +421,14 @@ impl Connection {
                 tx_video: Some(tx_video),
             },
             require_2fa: crate::auth_2fa::get_2fa(None),
+            require_fido2: crate::auth_fido2::get_credentials(),
+            fido2_challenge: None,
             display_idx: *display_service::PRIMARY_DISPLAY_IDX,
             stream,
             server,
             hash,
             read_jobs: Vec::new(),
+            transfer_limiter: Default::default(),
             timer: crate::rustdesk_interval(time::interval(SEC30)),
             file_timer: crate::rustdesk_interval(time::interval(SEC30)),
             file_transfer: None,
@@ -449,6 +490,25 @@ impl Connection {
                 tx_cm_stream_ready,
             }),
             auto_disconnect_timer: None,
+            expiry_timer: None,
+            audit_receive_jobs: HashMap::new(),
+            session_fingerprint,
+            handoff: None,
+            latency_probe: false,
+            history: crate::connection_history::Entry {
+                direction: crate::connection_history::DIRECTION_INCOMING.to_owned(),
+                start: hbb_common::get_time(),
+                route: if relay {
+                    crate::connection_history::ROUTE_RELAY
+                } else {
+                    crate::connection_history::ROUTE_DIRECT
+                }
+                .to_owned(),
+                ..Default::default()
+            },
+            pending_commands: HashMap::new(),
+            #[cfg(not(any(target_os = "android", target_os = "ios")))]
+            annotating: false,
             authed_conn_id: None,
             file_remove_log_control: FileRemoveLogControl::new(id),
             last_supported_encoding: None,
@@ -575,8 +635,15 @@ impl Connection {
                             conn.send(msg_out).await;
                             conn.chat_unanswered = false;
                         }
+                        ipc::Data::AssignInputControl => {
+                            crate::input_arbitration::assign(conn.inner.id());
+                        }
                         ipc::Data::SwitchPermission{name, enabled} => {
                             log::info!("Change permission {} -> {}", name, enabled);
+                            super::audit_log::record(conn.audit_entry(
+                                super::audit_log::EVENT_PERMISSION,
+                                json!({ "name": name, "enabled": enabled }),
+                            ));
                             if &name == "keyboard" {
                                 conn.keyboard = enabled;
                                 conn.send_permission(Permission::Keyboard, enabled).await;
@@ -645,10 +712,25 @@ impl Connection {
                                 conn.block_input = enabled;
                                 conn.send_permission(Permission::BlockInput, enabled).await;
                             }
+                            crate::session_handoff::update_permissions(conn.inner.id(), conn.enabled_permissions());
                         }
                         ipc::Data::RawMessage(bytes) => {
                             allow_err!(conn.stream.send_raw(bytes).await);
                         }
+                        ipc::Data::FS(ipc::FS::WriteDone { id, .. }) => {
+                            // The files are written by the cm.
+                            if let Some((path, files)) = conn.audit_receive_jobs.remove(&id) {
+                                super::audit_log::record_files(
+                                    conn.audit_entry(
+                                        super::audit_log::EVENT_FILE,
+                                        json!({ "type": "received" }),
+                                    ),
+                                    path,
+                                    files,
+                                    true,
+                                );
+                            }
+                        }
                         #[cfg(target_os = "windows")]
                         ipc::Data::ClipboardFile(clip) => {
                             if !conn.is_remote() {
@@ -710,6 +792,9 @@ impl Connection {
                         ipc::Data::VoiceCallResponse(accepted) => {
                             conn.handle_voice_call(accepted).await;
                         }
+                        ipc::Data::RemoteCommandResponse { id, approved } => {
+                            conn.handle_remote_command_response(id, approved).await;
+                        }
                         ipc::Data::CloseVoiceCall(_reason) => {
                             log::debug!("Close the voice call from the ipc.");
                             conn.close_voice_call().await;
@@ -724,10 +809,13 @@ impl Connection {
                     if let Some(res) = res {
                         match res {
                             Err(err) => {
+                                super::metrics::on_connection_error();
                                 conn.on_close(&err.to_string(), true).await;
                                 break;
                             },
                             Ok(bytes) => {
+                                super::metrics::add_received(bytes.len() as _);
+                                conn.history.bytes_received += bytes.len() as u64;
                                 last_recv_time = Instant::now();
                                 conn.session_last_recv_time.as_mut().map(|t| *t.lock().unwrap() = Instant::now());
                                 if let Ok(msg_in) = Message::parse_from_bytes(&bytes) {
@@ -752,7 +840,7 @@ impl Connection {
                 _ = conn.file_timer.tick() => {
                     if !conn.read_jobs.is_empty() {
                         conn.send_to_cm(ipc::Data::FileTransferLog(("transfer".to_string(), fs::serialize_transfer_jobs(&conn.read_jobs))));
-                        match fs::handle_read_jobs(&mut conn.read_jobs, &mut conn.stream).await {
+                        match conn.transfer_limiter.handle_read_jobs(&mut conn.read_jobs, &mut conn.stream).await {
                             Ok(log) => {
                                 if !log.is_empty() {
                                     conn.send_to_cm(ipc::Data::FileTransferLog(("transfer".to_string(), log)));
@@ -780,10 +868,13 @@ impl Connection {
                             video_service::notify_video_frame_fetched(vf.display as usize, id, Some(instant.into()));
                         }
                     }
+                    super::metrics::add_sent(value.compute_size());
+                    conn.history.bytes_sent += value.compute_size() as u64;
                     if let Err(err) = conn.stream.send(&value as &Message).await {
                         conn.on_close(&err.to_string(), false).await;
                         break;
                     }
+                    crate::latency_probe::host_record(crate::latency_probe::Stage::Send, instant.elapsed());
                 },
                 Some((instant, value)) = rx.recv() => {
                     let latency = instant.elapsed().as_millis() as i64;
@@ -841,6 +932,8 @@ impl Connection {
                     }
 
                     let msg: &Message = &msg;
+                    super::metrics::add_sent(msg.compute_size());
+                    conn.history.bytes_sent += msg.compute_size() as u64;
                     if let Err(err) = conn.stream.send(msg).await {
                         conn.on_close(&err.to_string(), false).await;
                         break;
@@ -856,6 +949,22 @@ impl Connection {
                                 conn.send_remote_printing_disallowed().await;
                             }
                         }
+                        ipc::Data::RevokePermissions { names, reason } => {
+                            conn.revoke_permissions(names, reason, &tx_from_cm_holder).await;
+                        }
+                        ipc::Data::InputControl(state) => {
+                            conn.on_input_control(state).await;
+                        }
+                        ipc::Data::SessionHandedOff(to) => {
+                            conn.send_close_reason_no_retry(crate::session_handoff::CLOSE_REASON).await;
+                            conn.on_close(&format!("handed off to #{}", to), false).await;
+                            break;
+                        }
+                        ipc::Data::Close => {
+                            conn.send_close_reason_no_retry("Closed manually by the peer").await;
+                            conn.on_close("local api", true).await;
+                            break;
+                        }
                         _ => {}
                     }
                 }
@@ -869,6 +978,11 @@ impl Connection {
                             break;
                         }
                     }
+                    if let Some(expiry) = conn.expiry_timer.as_mut().and_then(|t| t.on_timer()) {
+                        if !conn.on_session_expiry(expiry, &tx_from_cm_holder).await {
+                            break;
+                        }
+                    }
                     conn.file_remove_log_control.on_timer().drain(..).map(|x| conn.send_to_cm(x)).count();
                     #[cfg(feature = "hwcodec")]
                     conn.update_supported_encoding();
@@ -894,6 +1008,9 @@ impl Connection {
                             video_service::VIDEO_QOS.lock().unwrap().user_delay_response_elapsed(id, last_test_delay.elapsed().as_millis());
                         }
                     }
+                    if conn.latency_probe {
+                        conn.send(crate::latency_probe::host_report().to_message()).await;
+                    }
                 }
                 clip_file = rx_clip.recv() => match clip_file {
                     Some(_clip) => {
@@ -926,6 +1043,20 @@ impl Connection {
             crate::plugin::EVENT_ON_CONN_CLOSE_SERVER.to_owned(),
             conn.lr.my_id.clone(),
         );
+        #[cfg(all(feature = "flutter", feature = "plugin_framework"))]
+        #[cfg(not(any(target_os = "android", target_os = "ios")))]
+        {
+            let mut txs = PLUGIN_PEER_TXS.lock().unwrap();
+            // Another connection of the same peer may replace it.
+            let is_mine = match (txs.get(&conn.lr.my_id), &conn.inner.tx) {
+                (Some(tx), Some(my_tx)) => tx.same_channel(my_tx),
+                _ => false,
+            };
+            if is_mine {
+                txs.remove(&conn.lr.my_id);
+                crate::plugin::peer_msg::remove_peer(&conn.lr.my_id);
+            }
+        }
         video_service::notify_video_frame_fetched_by_conn_id(id, None);
         if conn.authorized {
             password::update_temporary_password();
@@ -958,6 +1089,10 @@ impl Connection {
         }
         #[cfg(target_os = "macos")]
         reset_input_ondisconn();
+        #[cfg(any(target_os = "windows", target_os = "linux"))]
+        let mut gamepads = crate::gamepad::VirtualGamepads::default();
+        #[cfg(any(target_os = "windows", target_os = "linux"))]
+        let mut pen = crate::pen::VirtualPen::default();
         loop {
             match receiver.recv_timeout(std::time::Duration::from_millis(500)) {
                 Ok(v) => match v {
@@ -986,6 +1121,14 @@ impl Connection {
                     MessageInput::Pointer((msg, id)) => {
                         handle_pointer(&msg, id);
                     }
+                    #[cfg(any(target_os = "windows", target_os = "linux"))]
+                    MessageInput::Gamepad(event) => {
+                        gamepads.handle(event);
+                    }
+                    #[cfg(any(target_os = "windows", target_os = "linux"))]
+                    MessageInput::Pen(event) => {
+                        pen.handle(event);
+                    }
                     MessageInput::BlockOn => {
                         let (ok, msg) = crate::platform::block_input(true);
                         if ok {
@@ -1172,6 +1315,18 @@ impl Connection {
         if !self.check_whitelist(&addr).await {
             return false;
         }
+        if !super::access_schedule::is_allowed_now() {
+            self.send_login_error("Connection not allowed at this time")
+                .await;
+            return false;
+        }
+        if super::recording_policy::is_enabled()
+            && !super::recording_policy::check_space(&super::recording_policy::directory())
+        {
+            self.send_login_error("Not enough disk space for the session recording")
+                .await;
+            return false;
+        }
         #[cfg(not(any(target_os = "android", target_os = "ios")))]
         if crate::is_server() && Config::get_option("allow-only-conn-window-open") == "Y" {
             if !crate::check_process("", !crate::platform::is_root()) {
@@ -1188,6 +1343,10 @@ impl Connection {
             "ip": addr.ip(),
             "action": "new",
         }));
+        super::audit_log::record(self.audit_entry(
+            super::audit_log::EVENT_CONNECTION,
+            json!({ "action": "new" }),
+        ));
         true
     }
 
@@ -1217,6 +1376,38 @@ impl Connection {
         allow_err!(self.tx_post_seq.send((url, v)));
     }
 
+    fn audit_entry(&self, event: &str, info: Value) -> super::audit_log::Entry {
+        super::audit_log::Entry {
+            event: event.to_owned(),
+            conn_id: self.inner.id,
+            peer_id: self.lr.my_id.clone(),
+            peer_name: self.lr.my_name.clone(),
+            ip: self.ip.clone(),
+            info,
+            ..Default::default()
+        }
+    }
+
+    // Returns false if all the clipboard contents are dropped by the policy.
+    fn apply_clipboard_policy(&self, clipboards: &mut Vec<Clipboard>) -> bool {
+        if let Some(info) = super::clipboard_policy::apply(
+            super::clipboard_policy::Direction::ClientToHost,
+            clipboards,
+        ) {
+            self.post_conn_audit(info);
+        }
+        if !clipboards.is_empty() {
+            super::audit_log::record(self.audit_entry(
+                super::audit_log::EVENT_CLIPBOARD,
+                super::audit_log::clipboard_info(
+                    super::clipboard_policy::DIRECTION_CLIENT_TO_HOST,
+                    clipboards,
+                ),
+            ));
+        }
+        !clipboards.is_empty()
+    }
+
     fn get_files_for_audit(job_type: fs::JobType, mut files: Vec<FileEntry>) -> Vec<(String, i64)> {
         files
             .drain(..)
@@ -1240,6 +1431,17 @@ impl Connection {
         files: Vec<(String, i64)>,
         info: Value,
     ) {
+        // The files to receive are not written yet, see `audit_receive_jobs`.
+        let (typ, hash) = match r#type {
+            FileAuditType::RemoteSend => ("send", true),
+            FileAuditType::RemoteReceive => ("receive", false),
+        };
+        super::audit_log::record_files(
+            self.audit_entry(super::audit_log::EVENT_FILE, json!({ "type": typ })),
+            path.to_owned(),
+            files.clone(),
+            hash,
+        );
         if self.server_audit_file.is_empty() {
             return;
         }
@@ -1296,7 +1498,11 @@ impl Connection {
         if self.authorized {
             return;
         }
-        if self.require_2fa.is_some() && !self.is_recent_session(true) && !self.from_switch {
+        if self.require_2fa.is_some()
+            && !self.is_recent_session(true)
+            && !self.from_switch
+            && self.handoff.is_none()
+        {
             self.require_2fa.as_ref().map(|totp| {
                 let bot = crate::auth_2fa::TelegramBot::get();
                 let bot = match bot {
@@ -1327,6 +1533,19 @@ impl Connection {
             self.send_login_error(crate::client::REQUIRE_2FA).await;
             return;
         }
+        if !self.require_fido2.is_empty() && !self.from_switch && self.handoff.is_none() {
+            let challenge = crate::auth_fido2::new_challenge();
+            let msg = crate::auth_fido2::Fido2::Challenge {
+                challenge: challenge.clone(),
+                credential_ids: self.require_fido2.iter().map(|c| c.id.clone()).collect(),
+            }
+            .to_message();
+            self.fido2_challenge = Some(challenge);
+            self.send(msg).await;
+            self.send_login_error(crate::auth_fido2::LOGIN_MSG_REQUIRED)
+                .await;
+            return;
+        }
         self.authorized = true;
         let (conn_type, auth_conn_type) = if self.file_transfer.is_some() {
             (1, AuthConnType::FileTransfer)
@@ -1339,6 +1558,8 @@ impl Connection {
         } else {
             (0, AuthConnType::Remote)
         };
+        self.history.success = true;
+        self.history.conn_type = format!("{:?}", auth_conn_type);
         self.authed_conn_id = Some(self::raii::AuthedConnID::new(
             self.inner.id(),
             auth_conn_type,
@@ -1354,6 +1575,13 @@ impl Connection {
         self.post_conn_audit(
             json!({"peer": ((&self.lr.my_id, &self.lr.my_name)), "type": conn_type}),
         );
+        let mut info = json!({ "result": "success", "type": conn_type });
+        if let Some(ticket) = self.handoff.as_ref() {
+            info["handoff_from"] = json!(ticket.conn_id);
+            info["handoff_peer_id"] = json!(ticket.peer_id);
+            crate::server::close_handed_off(ticket.conn_id, self.inner.id());
+        }
+        super::audit_log::record(self.audit_entry(super::audit_log::EVENT_AUTH, info));
         #[allow(unused_mut)]
         let mut username = crate::platform::get_active_username();
         let mut res = LoginResponse::new();
@@ -1429,6 +1657,11 @@ impl Connection {
         #[cfg(any(target_os = "windows", target_os = "linux"))]
         {
             platform_additions.insert("support_view_camera".into(), json!(true));
+            platform_additions.insert("support_gamepad".into(), json!(true));
+            platform_additions.insert("support_pen".into(), json!(true));
+            if crate::audio_service::is_support_app_capture() {
+                platform_additions.insert("support_audio_app_capture".into(), json!(true));
+            }
         }
 
         #[cfg(any(target_os = "linux", target_os = "windows", target_os = "macos"))]
@@ -1486,6 +1719,14 @@ impl Connection {
             .lock()
             .unwrap()
             .insert(self.lr.my_id.clone(), self.tx_input.clone());
+        #[cfg(all(feature = "flutter", feature = "plugin_framework"))]
+        #[cfg(not(any(target_os = "android", target_os = "ios")))]
+        if let Some(tx) = self.inner.tx.clone() {
+            PLUGIN_PEER_TXS
+                .lock()
+                .unwrap()
+                .insert(self.lr.my_id.clone(), tx);
+        }
 
         // Terminal feature is supported on desktop only
         #[allow(unused_mut)]
@@ -1505,6 +1746,7 @@ impl Connection {
         .into();
 
         let mut sub_service = false;
+        let mut system_info_displays = None;
         #[allow(unused_mut)]
         let mut wait_session_id_confirm = false;
         #[cfg(windows)]
@@ -1558,6 +1800,9 @@ impl Connection {
                     {
                         self.retina.set_displays(&displays);
                     }
+                    if Self::permission(crate::system_info::OPTION_ENABLE) {
+                        system_info_displays = Some(displays.clone());
+                    }
                     pi.displays = displays;
                     pi.current_display = self.display_idx as _;
                     #[cfg(not(any(target_os = "android", target_os = "ios")))]
@@ -1589,6 +1834,31 @@ impl Connection {
         let mut msg_out = Message::new();
         msg_out.set_login_response(res);
         self.send(msg_out).await;
+        if let Some(displays) = system_info_displays {
+            // It takes a while to look up the graphics adapters.
+            let mut inner = self.inner.clone();
+            let primary = *display_service::PRIMARY_DISPLAY_IDX;
+            std::thread::spawn(move || {
+                let info = crate::system_info::SystemInfo::local(&displays, primary);
+                inner.send(Arc::new(info.to_message()));
+            });
+        }
+        if crate::common::is_support_wake_on_lan_num(hbb_common::get_version_number(
+            &self.lr.version,
+        )) {
+            if let Some(info) = crate::wake_on_lan::WakeOnLan::local_info() {
+                self.send(info.to_message()).await;
+            }
+        }
+        self.expiry_timer = crate::session_expiry::ExpiryTimer::start();
+        if let Some(timer) = self.expiry_timer.as_ref() {
+            let (expiry, expire_time) = (timer.current(), timer.expire_time());
+            self.send(expiry.to_message()).await;
+            self.send_to_cm(ipc::Data::SessionExpiry {
+                expire_time,
+                expired: false,
+            });
+        }
         if let Some(o) = self.options_in_login.take() {
             self.update_options(&o).await;
         }
@@ -1724,6 +1994,11 @@ impl Connection {
         self.keyboard && !self.disable_keyboard
     }
 
+    // The keyboard permission and the input control of the simultaneous sessions.
+    fn input_enabled(&self) -> bool {
+        self.peer_keyboard_enabled() && crate::input_arbitration::can_input(self.inner.id())
+    }
+
     fn clipboard_enabled(&self) -> bool {
         self.clipboard && !self.disable_clipboard
     }
@@ -1770,6 +2045,7 @@ impl Connection {
             recording: self.recording,
             block_input: self.block_input,
             from_switch: self.from_switch,
+            session_fingerprint: self.session_fingerprint.clone(),
         });
     }
 
@@ -1784,6 +2060,12 @@ impl Connection {
     }
 
     async fn send_login_error<T: std::string::ToString>(&mut self, err: T) {
+        super::audit_log::record(self.audit_entry(
+            super::audit_log::EVENT_AUTH,
+            json!({ "result": "error", "error": err.to_string() }),
+        ));
+        super::metrics::on_login_error();
+        self.history.reason = err.to_string();
         let mut msg_out = Message::new();
         let mut res = LoginResponse::new();
         res.set_error(err.to_string());
@@ -1948,6 +2230,28 @@ impl Connection {
     async fn handle_login_request_without_validation(&mut self, lr: &LoginRequest) {
         self.lr = lr.clone();
         self.peer_argb = crate::str2color(&format!("{}{}", &lr.my_id, &lr.my_platform), 0xff);
+        if let Some((name, profile)) = super::permission_profile::get_profile(&lr.my_id) {
+            log::info!("Permission profile of {}: {}", lr.my_id, name);
+            // Only restrict the permission toggles, the id is claimed by the peer.
+            self.keyboard &= profile.keyboard;
+            self.clipboard &= profile.clipboard;
+            self.audio &= profile.audio;
+            self.file &= profile.file;
+            self.restart &= profile.restart;
+            self.recording &= profile.recording;
+            self.block_input &= profile.block_input;
+        }
+        if let Some(ticket) = self.handoff.as_ref() {
+            // No more than the session handed off, whatever the id of the new peer.
+            let allows = |name: &str| ticket.permissions.iter().any(|n| n == name);
+            self.keyboard &= allows("keyboard");
+            self.clipboard &= allows("clipboard");
+            self.audio &= allows("audio");
+            self.file &= allows("file");
+            self.restart &= allows("restart");
+            self.recording &= allows("recording");
+            self.block_input &= allows("block_input");
+        }
         if let Some(o) = lr.option.as_ref() {
             self.options_in_login = Some(o.clone());
         }
@@ -2164,6 +2468,7 @@ impl Connection {
             let is_logon = || crate::platform::is_prelogin();
 
             if !hbb_common::is_ip_str(&lr.username)
+                && crate::parse_direct_ip(&lr.username, 0).is_none()
                 && !hbb_common::is_domain_port_str(&lr.username)
                 && lr.username != Config::get_id()
             {
@@ -2192,6 +2497,15 @@ impl Connection {
                 } else {
                     self.send_login_error(err_msg).await;
                 }
+            } else if self.handoff.is_some() && self.is_remote() {
+                if err_msg.is_empty() {
+                    #[cfg(target_os = "linux")]
+                    self.linux_headless_handle.wait_desktop_cm_ready().await;
+                    self.send_logon_response().await;
+                    self.try_start_cm(lr.my_id.clone(), lr.my_name.clone(), self.authorized);
+                } else {
+                    self.send_login_error(err_msg).await;
+                }
             } else if lr.password.is_empty() {
                 if err_msg.is_empty() {
                     self.try_start_cm(lr.my_id, lr.my_name, false);
@@ -2263,6 +2577,62 @@ impl Connection {
                     }
                 }
             }
+        } else if let Some(crate::session_handoff::Handoff::Redeem { token }) =
+            crate::session_handoff::Handoff::from_message(&msg)
+        {
+            if !self.authorized && crate::session_handoff::is_enabled() {
+                let (failure, res) = self.check_failure(0).await;
+                if !res {
+                    return true;
+                }
+                match crate::session_handoff::redeem(&token)
+                    .filter(|ticket| is_authed_conn(ticket.conn_id))
+                {
+                    Some(ticket) => {
+                        self.update_failure(failure, true, 0);
+                        log::info!("Session handoff from #{}", ticket.conn_id);
+                        self.handoff = Some(ticket);
+                    }
+                    None => {
+                        // Falls back to the password.
+                        self.update_failure(failure, false, 0);
+                        log::warn!("Invalid session handoff token");
+                    }
+                }
+            }
+        } else if let Some(crate::auth_fido2::Fido2::Assertion {
+            credential_id,
+            auth_data,
+            signature,
+        }) = crate::auth_fido2::Fido2::from_message(&msg)
+        {
+            let (failure, res) = self.check_failure(1).await;
+            if !res {
+                return true;
+            }
+            let Some(challenge) = self.fido2_challenge.take() else {
+                return true;
+            };
+            if crate::auth_fido2::verify(
+                &self.require_fido2,
+                &challenge,
+                &credential_id,
+                &auth_data,
+                &signature,
+            ) {
+                self.update_failure(failure, true, 1);
+                self.require_fido2.clear();
+                self.send_logon_response().await;
+                self.try_start_cm(
+                    self.lr.my_id.to_owned(),
+                    self.lr.my_name.to_owned(),
+                    self.authorized,
+                );
+            } else {
+                self.update_failure(failure, false, 1);
+                self.send_login_error(crate::auth_fido2::LOGIN_MSG_WRONG)
+                    .await;
+            }
         } else if let Some(message::Union::TestDelay(t)) = msg.union {
             if t.from_client {
                 let mut msg_out = Message::new();
@@ -2272,10 +2642,12 @@ impl Connection {
                 if let Some(tm) = self.last_test_delay {
                     self.last_test_delay = None;
                     let new_delay = tm.elapsed().as_millis() as u32;
-                    video_service::VIDEO_QOS
-                        .lock()
-                        .unwrap()
-                        .user_network_delay(self.inner.id(), new_delay);
+                    let mut video_qos = video_service::VIDEO_QOS.lock().unwrap();
+                    video_qos.user_network_delay(self.inner.id(), new_delay);
+                    if let Some(report) = crate::client::feedback::ReceiverReport::read(&t) {
+                        video_qos.user_receiver_report(self.inner.id(), report);
+                    }
+                    drop(video_qos);
                     self.network_delay = new_delay;
                 }
             }
@@ -2319,7 +2691,7 @@ impl Connection {
                         log::debug!("call_main_service_pointer_input fail:{}", e);
                     }
                     #[cfg(not(any(target_os = "android", target_os = "ios")))]
-                    if self.peer_keyboard_enabled() {
+                    if self.input_enabled() {
                         if is_left_up(&me) {
                             CLICK_TIME.store(get_time(), Ordering::SeqCst);
                         } else {
@@ -2382,7 +2754,7 @@ impl Connection {
                         log::debug!("call_main_service_pointer_input fail:{}", e);
                     }
                     #[cfg(not(any(target_os = "android", target_os = "ios")))]
-                    if self.peer_keyboard_enabled() {
+                    if self.input_enabled() {
                         MOUSE_MOVE_TIME.store(get_time(), Ordering::SeqCst);
                         self.input_pointer(pde, self.inner.id());
                     }
@@ -2450,7 +2822,7 @@ impl Connection {
                     if self.is_authed_view_camera_conn() {
                         return true;
                     }
-                    if self.peer_keyboard_enabled() {
+                    if self.input_enabled() {
                         if is_enter(&me) {
                             CLICK_TIME.store(get_time(), Ordering::SeqCst);
                         }
@@ -2507,7 +2879,9 @@ impl Connection {
                     self.update_auto_disconnect_timer();
                 }
                 Some(message::Union::Clipboard(cb)) => {
-                    if self.clipboard {
+                    let mut clipboards = vec![cb];
+                    if self.clipboard && self.apply_clipboard_policy(&mut clipboards) {
+                        let cb = clipboards.remove(0);
                         #[cfg(not(any(target_os = "android", target_os = "ios")))]
                         update_clipboard(vec![cb], ClipboardSide::Host);
                         // ios as the controlled side is actually not supported for now.
@@ -2534,9 +2908,10 @@ impl Connection {
                         crate::clipboard::handle_msg_clipboard(cb);
                     }
                 }
-                Some(message::Union::MultiClipboards(_mcb)) => {
+                Some(message::Union::MultiClipboards(mut _mcb)) => {
+                    let _allowed = self.apply_clipboard_policy(&mut _mcb.clipboards);
                     #[cfg(not(any(target_os = "android", target_os = "ios")))]
-                    if self.clipboard {
+                    if self.clipboard && _allowed {
                         update_clipboard(_mcb.clipboards, ClipboardSide::Host);
                     }
                     #[cfg(target_os = "android")]
@@ -2763,10 +3138,14 @@ impl Connection {
                                     total_size: r.total_size,
                                     conn_id: self.inner.id(),
                                 });
+                                let files =
+                                    Self::get_files_for_audit(fs::JobType::Generic, r.files);
+                                self.audit_receive_jobs
+                                    .insert(r.id, (r.path.clone(), files.clone()));
                                 self.post_file_audit(
                                     FileAuditType::RemoteReceive,
                                     &r.path,
-                                    Self::get_files_for_audit(fs::JobType::Generic, r.files),
+                                    files,
                                     json!({}),
                                 );
                                 self.file_transferred = true;
@@ -2805,6 +3184,8 @@ impl Connection {
                             }
                             Some(file_action::Union::Cancel(c)) => {
                                 self.send_fs(ipc::FS::CancelWrite { id: c.id });
+                                self.audit_receive_jobs.remove(&c.id);
+                                self.transfer_limiter.remove_job(c.id);
                                 if let Some(job) = fs::remove_job(c.id, &mut self.read_jobs) {
                                     self.send_to_cm(ipc::Data::FileTransferLog((
                                         "transfer".to_string(),
@@ -2907,7 +3288,13 @@ impl Connection {
                         self.update_auto_disconnect_timer();
                     }
                     Some(misc::Union::RefreshVideoDisplay(display)) => {
-                        self.refresh_video_display(Some(display as usize));
+                        if display & crate::common::REFRESH_KEYFRAME_FLAG != 0 {
+                            self.request_keyframe(
+                                (display & !crate::common::REFRESH_KEYFRAME_FLAG) as usize,
+                            );
+                        } else {
+                            self.refresh_video_display(Some(display as usize));
+                        }
                         self.update_auto_disconnect_timer();
                     }
                     Some(misc::Union::VideoReceived(_)) => {
@@ -2950,7 +3337,7 @@ impl Connection {
                         if !self.disable_audio {
                             // Drop the audio sender previously.
                             drop(std::mem::replace(&mut self.audio_sender, None));
-                            self.audio_sender = Some(start_audio_thread());
+                            self.audio_sender = Some(start_audio_thread(None));
                             self.audio_sender
                                 .as_ref()
                                 .map(|a| allow_err!(a.send(MediaData::AudioFormat(format))));
@@ -2976,12 +3363,169 @@ impl Connection {
                     Some(misc::Union::ChangeDisplayResolution(dr)) => {
                         self.change_resolution(Some(dr.display as _), &dr.resolution)
                     }
+                    Some(misc::Union::PluginRequest(p))
+                        if p.id == crate::common::AUDIO_SOURCE_REQUEST_ID =>
+                    {
+                        self.handle_audio_source_request(&p.content).await;
+                    }
+                    Some(misc::Union::PluginRequest(p))
+                        if p.id == crate::folder_sync::REQUEST_ID =>
+                    {
+                        if self.file_transfer.is_some() {
+                            self.handle_folder_sync(p.content.to_vec()).await;
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p))
+                        if p.id == crate::transfer_limit::REQUEST_ID =>
+                    {
+                        if self.file_transfer.is_some() {
+                            match serde_json::from_slice::<crate::transfer_limit::LimitRequest>(
+                                &p.content,
+                            ) {
+                                Ok(req) => self.transfer_limiter.set_limit(req.id, req.kbps),
+                                Err(e) => log::error!("Invalid transfer limit: {}", e),
+                            }
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p))
+                        if p.id == crate::wake_on_lan::REQUEST_ID =>
+                    {
+                        match serde_json::from_slice(&p.content) {
+                            Ok(crate::wake_on_lan::WakeOnLan::Wake { mac }) => {
+                                log::info!("Wake {} on the LAN for {}", mac, self.lr.my_id);
+                                crate::lan::send_wol_to_mac(&mac);
+                            }
+                            Ok(_) => {}
+                            Err(e) => log::error!("Invalid wake-on-lan request: {}", e),
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p))
+                        if p.id == crate::file_checksum::REQUEST_ID =>
+                    {
+                        if self.file_transfer.is_some() {
+                            self.handle_file_checksum_request(&p.content).await;
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p))
+                        if p.id == crate::session_handoff::REQUEST_ID =>
+                    {
+                        self.handle_handoff_request().await;
+                    }
+                    #[cfg(windows)]
+                    Some(misc::Union::PluginRequest(p))
+                        if p.id == crate::virtual_monitor::REQUEST_ID =>
+                    {
+                        match serde_json::from_slice(&p.content) {
+                            Ok(monitor) => self.add_virtual_monitor(monitor).await,
+                            Err(e) => log::error!("Invalid virtual monitor: {}", e),
+                        }
+                    }
+                    #[cfg(windows)]
+                    Some(misc::Union::PluginRequest(p))
+                        if p.id == crate::windows_session::REQUEST_ID =>
+                    {
+                        if let Ok(crate::windows_session::WindowsSessions::List) =
+                            serde_json::from_slice(&p.content)
+                        {
+                            self.send_windows_sessions().await;
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p))
+                        if p.id == crate::task_manager::REQUEST_ID =>
+                    {
+                        match serde_json::from_slice(&p.content) {
+                            Ok(request) => self.handle_task_manager(request).await,
+                            Err(e) => log::error!("Invalid task manager request: {}", e),
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p))
+                        if p.id == crate::remote_command::REQUEST_ID =>
+                    {
+                        match serde_json::from_slice(&p.content) {
+                            Ok(request) => self.handle_remote_command(request).await,
+                            Err(e) => log::error!("Invalid remote command: {}", e),
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p))
+                        if p.id == crate::annotation::REQUEST_ID =>
+                    {
+                        match serde_json::from_slice(&p.content) {
+                            Ok(request) => self.handle_annotation(request).await,
+                            Err(e) => log::error!("Invalid annotation request: {}", e),
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p))
+                        if p.id == crate::priority_region::REQUEST_ID =>
+                    {
+                        match serde_json::from_slice(&p.content) {
+                            Ok(region) => crate::priority_region::set(self.inner.id(), region),
+                            Err(e) => log::error!("Invalid priority region: {}", e),
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p))
+                        if p.id == crate::latency_probe::REQUEST_ID =>
+                    {
+                        match serde_json::from_slice(&p.content) {
+                            Ok(crate::latency_probe::LatencyProbe::Start) => {
+                                self.set_latency_probe(true)
+                            }
+                            Ok(crate::latency_probe::LatencyProbe::Stop) => {
+                                self.set_latency_probe(false)
+                            }
+                            Ok(_) => {}
+                            Err(e) => log::error!("Invalid latency probe: {}", e),
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p))
+                        if p.id == crate::input_arbitration::REQUEST_ID =>
+                    {
+                        match serde_json::from_slice(&p.content) {
+                            Ok(crate::input_arbitration::InputControl::Pass { to }) => {
+                                crate::input_arbitration::pass(self.inner.id(), to);
+                            }
+                            Ok(_) => {}
+                            Err(e) => log::error!("Invalid input control: {}", e),
+                        }
+                    }
+                    #[cfg(any(target_os = "windows", target_os = "linux"))]
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::gamepad::REQUEST_ID => {
+                        if self.input_enabled() {
+                            match crate::gamepad::GamepadEvent::decode(&p.content) {
+                                Some(event) => {
+                                    self.tx_input.send(MessageInput::Gamepad(event)).ok();
+                                }
+                                None => log::error!("Invalid gamepad event"),
+                            }
+                        }
+                    }
+                    #[cfg(any(target_os = "windows", target_os = "linux"))]
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::pen::REQUEST_ID => {
+                        if self.input_enabled() {
+                            match crate::pen::PenEvent::decode(&p.content) {
+                                Some(event) => {
+                                    self.tx_input.send(MessageInput::Pen(event)).ok();
+                                }
+                                None => log::error!("Invalid pen event"),
+                            }
+                        }
+                    }
                     #[cfg(all(feature = "flutter", feature = "plugin_framework"))]
                     #[cfg(not(any(target_os = "android", target_os = "ios")))]
-                    Some(misc::Union::PluginRequest(p)) => {
-                        let msg =
-                            crate::plugin::handle_client_event(&p.id, &self.lr.my_id, &p.content);
-                        self.send(msg).await;
+                    Some(misc::Union::PluginRequest(p))
+                        if !crate::common::is_reserved_plugin_request_id(&p.id) =>
+                    {
+                        if crate::plugin::peer_msg::accept_incoming(
+                            &p.id,
+                            &self.lr.my_id,
+                            p.content.len(),
+                        ) {
+                            let msg = crate::plugin::handle_client_event(
+                                &p.id,
+                                &self.lr.my_id,
+                                &p.content,
+                            );
+                            self.send(msg).await;
+                        }
                     }
                     Some(misc::Union::AutoAdjustFps(fps)) => video_service::VIDEO_QOS
                         .lock()
@@ -3344,6 +3888,17 @@ impl Connection {
         });
     }
 
+    // The encoders not supporting it restart the video instead.
+    fn request_keyframe(&self, display: usize) {
+        self.server.upgrade().map(|s| {
+            s.read().unwrap().set_video_service_opt(
+                Some((self.video_source(), display)),
+                video_service::OPTION_KEYFRAME,
+                super::service::SERVICE_OPTION_VALUE_TRUE,
+            );
+        });
+    }
+
     async fn handle_switch_display(&mut self, s: SwitchDisplay) {
         let display_idx = s.display as usize;
         if self.display_idx != display_idx {
@@ -3504,6 +4059,228 @@ impl Connection {
         }
     }
 
+    #[cfg(windows)]
+    async fn add_virtual_monitor(&mut self, monitor: crate::virtual_monitor::AddMonitor) {
+        let err = if !self.keyboard {
+            "No permission to add the virtual monitor".to_owned()
+        } else if !virtual_display_manager::is_virtual_display_supported() {
+            "idd_not_support_under_win10_2004_tip".to_owned()
+        } else if !monitor.is_valid() {
+            format!(
+                "Invalid mode of the virtual monitor: {}x{}@{}",
+                monitor.width, monitor.height, monitor.refresh_rate
+            )
+        } else {
+            let modes = vec![vec![virtual_display::MonitorMode {
+                width: monitor.width,
+                height: monitor.height,
+                sync: monitor.refresh_rate,
+            }]];
+            match virtual_display_manager::plug_in_peer_request(modes) {
+                Ok(indices) if indices.is_empty() => "No free virtual monitor".to_owned(),
+                Ok(indices) => {
+                    log::info!(
+                        "Virtual monitor {:?} added with {}x{}@{}",
+                        indices,
+                        monitor.width,
+                        monitor.height,
+                        monitor.refresh_rate
+                    );
+                    return;
+                }
+                Err(e) => format!("Failed to plug in virtual display: {}", e),
+            }
+        };
+        log::error!("Failed to add the virtual monitor: {}", err);
+        let mut msg_out = Message::new();
+        msg_out.set_message_box(MessageBox {
+            msgtype: "nook-nocancel-hasclose".to_owned(),
+            title: "Virtual display".to_owned(),
+            text: err,
+            link: "".to_owned(),
+            ..Default::default()
+        });
+        self.send(msg_out).await;
+    }
+
+    async fn handle_task_manager(&mut self, request: crate::task_manager::TaskManager) {
+        use crate::task_manager::{TaskManager, ERR_DENIED};
+        let allowed =
+            self.peer_keyboard_enabled() && Self::permission(crate::task_manager::OPTION_ENABLE);
+        match request {
+            TaskManager::List => {
+                if !allowed {
+                    let msg = TaskManager::Processes {
+                        processes: vec![],
+                        error: ERR_DENIED.to_owned(),
+                    };
+                    self.send(msg.to_message()).await;
+                    return;
+                }
+                // It blocks to measure the CPU usage.
+                let mut inner = self.inner.clone();
+                std::thread::spawn(move || {
+                    let msg = TaskManager::Processes {
+                        processes: crate::task_manager::list(),
+                        error: "".to_owned(),
+                    };
+                    inner.send(Arc::new(msg.to_message()));
+                });
+            }
+            TaskManager::Kill { pid } => {
+                let error = if !allowed {
+                    ERR_DENIED.to_owned()
+                } else {
+                    match crate::task_manager::kill(pid) {
+                        Ok(name) => {
+                            super::audit_log::record(self.audit_entry(
+                                super::audit_log::EVENT_PROCESS,
+                                json!({ "action": "kill", "pid": pid, "name": name }),
+                            ));
+                            "".to_owned()
+                        }
+                        Err(e) => e,
+                    }
+                };
+                self.send(TaskManager::Killed { pid, error }.to_message())
+                    .await;
+            }
+            TaskManager::Processes { .. } | TaskManager::Killed { .. } => {}
+        }
+    }
+
+    async fn handle_remote_command(&mut self, request: crate::remote_command::RemoteCommand) {
+        use crate::remote_command::{RemoteCommand, ERR_DENIED, ERR_REJECTED};
+        match request {
+            RemoteCommand::Run(req) => {
+                let id = req.id;
+                if !self.peer_keyboard_enabled() || !crate::remote_command::is_allowed() {
+                    self.send(RemoteCommand::exited_with_error(id, ERR_DENIED).to_message())
+                        .await;
+                } else if crate::remote_command::is_auto_approved(&req) {
+                    self.start_remote_command(req);
+                } else if self.pending_commands.contains_key(&id) {
+                    self.send(
+                        RemoteCommand::exited_with_error(id, "The id is in use").to_message(),
+                    )
+                    .await;
+                } else {
+                    self.pending_commands.insert(id, req.clone());
+                    self.send_to_cm(ipc::Data::RemoteCommand(req));
+                    self.send(RemoteCommand::Pending { id }.to_message()).await;
+                }
+            }
+            RemoteCommand::Cancel { id } => {
+                if self.pending_commands.remove(&id).is_some() {
+                    self.send_to_cm(ipc::Data::RemoteCommandCancelled(id));
+                    self.send(RemoteCommand::exited_with_error(id, ERR_REJECTED).to_message())
+                        .await;
+                } else {
+                    crate::remote_command::cancel(self.inner.id(), id);
+                }
+            }
+            RemoteCommand::Pending { .. }
+            | RemoteCommand::Output { .. }
+            | RemoteCommand::Exited { .. } => {}
+        }
+    }
+
+    // The consent of the command in the cm.
+    async fn handle_remote_command_response(&mut self, id: u32, approved: bool) {
+        let Some(req) = self.pending_commands.remove(&id) else {
+            return;
+        };
+        if approved && self.peer_keyboard_enabled() && crate::remote_command::is_allowed() {
+            self.start_remote_command(req);
+        } else {
+            let msg = crate::remote_command::RemoteCommand::exited_with_error(
+                id,
+                crate::remote_command::ERR_REJECTED,
+            );
+            self.send(msg.to_message()).await;
+        }
+    }
+
+    fn start_remote_command(&mut self, req: crate::remote_command::Request) {
+        super::audit_log::record(self.audit_entry(
+            super::audit_log::EVENT_COMMAND,
+            json!({ "command": req.command, "args": req.args, "cwd": req.cwd }),
+        ));
+        let inner = self.inner.clone();
+        crate::remote_command::run(self.inner.id(), req, move |msg| {
+            inner.clone().send(Arc::new(msg.to_message()));
+        });
+    }
+
+    async fn handle_annotation(&mut self, request: crate::annotation::Annotations) {
+        use crate::annotation::Annotations;
+        #[cfg(not(any(target_os = "android", target_os = "ios")))]
+        let unsupported = crate::whiteboard::unsupported_reason();
+        #[cfg(any(target_os = "android", target_os = "ios"))]
+        let unsupported = "Unsupported";
+        let error = if !Self::permission(crate::annotation::OPTION_ENABLE) {
+            crate::annotation::ERR_DENIED
+        } else {
+            unsupported
+        };
+        if !error.is_empty() {
+            if matches!(request, Annotations::Start) {
+                let msg = Annotations::Error {
+                    error: error.to_owned(),
+                };
+                self.send(msg.to_message()).await;
+            }
+            return;
+        }
+        #[cfg(not(any(target_os = "android", target_os = "ios")))]
+        {
+            use crate::whiteboard::{self, CustomEvent};
+            let key = whiteboard::get_key_annotation(self.inner.id());
+            match request {
+                Annotations::Start => {
+                    if !self.annotating {
+                        self.annotating = true;
+                        whiteboard::register_whiteboard(key);
+                    }
+                }
+                Annotations::Stop => {
+                    if self.annotating {
+                        self.annotating = false;
+                        whiteboard::unregister_whiteboard(key);
+                    }
+                }
+                Annotations::Draw(mut annotation) => {
+                    if let crate::annotation::Shape::Stroke { points } = &mut annotation.shape {
+                        points.truncate(crate::annotation::MAX_STROKE_POINTS);
+                    }
+                    whiteboard::update_whiteboard(key, CustomEvent::Annotate(annotation));
+                }
+                Annotations::Undo => {
+                    whiteboard::update_whiteboard(key, CustomEvent::UndoAnnotation);
+                }
+                Annotations::Clear => whiteboard::update_whiteboard(key, CustomEvent::Clear),
+                Annotations::Error { .. } => {}
+            }
+        }
+    }
+
+    #[cfg(windows)]
+    async fn send_windows_sessions(&mut self) {
+        let sessions = crate::windows_session::list();
+        // The same as the switch with `SelectedSid`.
+        let switchable = self.keyboard
+            && crate::platform::is_installed()
+            && crate::platform::is_share_rdp()
+            && raii::AuthedConnID::non_port_forward_conn_count() == 1
+            && sessions.len() > 1;
+        let msg = crate::windows_session::WindowsSessions::Sessions {
+            sessions,
+            current_sid: crate::platform::get_current_process_session_id().unwrap_or_default(),
+            switchable,
+        };
+        self.send(msg.to_message()).await;
+    }
+
     async fn toggle_privacy_mode(&mut self, t: TogglePrivacyMode) {
         if t.on {
             self.turn_on_privacy(t.impl_key).await;
@@ -3608,8 +4385,7 @@ impl Connection {
         }
     }
 
-    async fn update_options(&mut self, o: &OptionMessage) {
-        log::info!("Option update: {:?}", o);
+    fn update_session_quality(&self, o: &OptionMessage) {
         if let Ok(q) = o.image_quality.enum_value() {
             let image_quality;
             if let ImageQuality::NotSet = q {
@@ -3628,12 +4404,36 @@ impl Connection {
                     .user_image_quality(self.inner.id(), image_quality);
             }
         }
+        if let Some(profile) =
+            scrap::codec::EncoderProfile::from_image_quality(o.custom_image_quality)
+        {
+            video_service::VIDEO_QOS
+                .lock()
+                .unwrap()
+                .user_encoder_profile(self.inner.id(), profile);
+        }
         if o.custom_fps > 0 {
             video_service::VIDEO_QOS
                 .lock()
                 .unwrap()
                 .user_custom_fps(self.inner.id(), o.custom_fps as _);
         }
+    }
+
+    async fn update_options(&mut self, o: &OptionMessage) {
+        log::info!("Option update: {:?}", o);
+        if let Some(settings) = scrap::codec::DisplaySettings::read(o) {
+            video_service::VIDEO_QOS
+                .lock()
+                .unwrap()
+                .user_display_settings(
+                    self.inner.id(),
+                    video_service::get_service_name(self.video_source(), settings.display),
+                    settings,
+                );
+        } else {
+            self.update_session_quality(o);
+        }
         if let Some(q) = o.supported_decoding.clone().take() {
             scrap::codec::Encoder::update(scrap::codec::EncodingUpdate::Update(self.inner.id(), q));
         }
@@ -3810,21 +4610,7 @@ impl Connection {
             if q != BoolOption::NotSet {
                 use crate::whiteboard;
                 self.show_my_cursor = q == BoolOption::Yes;
-                #[cfg(target_os = "windows")]
-                let is_lower_win10 = !crate::platform::windows::is_win_10_or_greater();
-                #[cfg(not(target_os = "windows"))]
-                let is_lower_win10 = false;
-                #[cfg(target_os = "linux")]
-                let is_linux_supported = crate::whiteboard::is_supported();
-                #[cfg(not(target_os = "linux"))]
-                let is_linux_supported = false;
-                let not_support_msg = if is_lower_win10 {
-                    "Windows 10 or greater is required."
-                } else if cfg!(target_os = "linux") && !is_linux_supported {
-                    "This feature is not supported on native Wayland, please install XWayland or switch to X11."
-                } else {
-                    ""
-                };
+                let not_support_msg = whiteboard::unsupported_reason();
                 if q == BoolOption::Yes {
                     if not_support_msg.is_empty() {
                         whiteboard::register_whiteboard(whiteboard::get_key_cursor(self.inner.id));
@@ -3967,6 +4753,86 @@ impl Connection {
         }
     }
 
+    async fn handle_audio_source_request(&mut self, content: &[u8]) {
+        use crate::common::{AudioSourceRequest, AudioSources};
+        if !self.audio_enabled() {
+            return;
+        }
+        let request = match serde_json::from_slice::<AudioSourceRequest>(content) {
+            Ok(request) => request,
+            Err(e) => {
+                log::error!("Invalid audio source request: {}", e);
+                return;
+            }
+        };
+        let apps = crate::audio_service::get_audio_apps();
+        if let AudioSourceRequest::Select { pid } = request {
+            let app = apps.iter().find(|x| x.pid == pid).cloned();
+            if pid != 0 && app.is_none() {
+                log::warn!("No audio of the application {} to capture", pid);
+            } else {
+                crate::audio_service::set_app_source(self.inner.id(), app);
+            }
+        }
+        let sources = AudioSources {
+            apps,
+            selected: crate::audio_service::get_app_source()
+                .map(|x| x.pid)
+                .unwrap_or_default(),
+        };
+        self.send(crate::common::make_plugin_request(
+            crate::common::AUDIO_SOURCE_REQUEST_ID,
+            serde_json::to_vec(&sources).unwrap_or_default(),
+        ))
+        .await;
+    }
+
+    // The source file of the download is hashed here, the partial file of the upload by the cm.
+    async fn handle_file_checksum_request(&mut self, content: &[u8]) {
+        use crate::file_checksum::FileChecksum;
+
+        let Ok(FileChecksum::Request { id, file_num, size }) =
+            serde_json::from_slice::<FileChecksum>(content)
+        else {
+            log::error!("Invalid file checksum request");
+            return;
+        };
+        let Some(job) = fs::get_job(id, &mut self.read_jobs) else {
+            self.send_fs(ipc::FS::Checksum { id, file_num, size });
+            return;
+        };
+        let path = match (&job.data_source, job.files().get(file_num as usize)) {
+            (fs::DataSource::FilePath(p), Some(file)) => fs::TransferJob::join(p, &file.name),
+            _ => {
+                self.send(FileChecksum::empty(id, file_num).to_message())
+                    .await;
+                return;
+            }
+        };
+        let response =
+            tokio::task::spawn_blocking(move || FileChecksum::respond(id, file_num, &path, size))
+                .await
+                .unwrap_or_else(|_| FileChecksum::empty(id, file_num));
+        self.send(response.to_message()).await;
+    }
+
+    // The trees and the checksums are read here, the files are written by the cm.
+    async fn handle_folder_sync(&mut self, content: Vec<u8>) {
+        use crate::folder_sync::{handle_read, SyncMessage};
+
+        let Some((msg, _)) = SyncMessage::decode(&content) else {
+            log::error!("Invalid folder sync message");
+            return;
+        };
+        if msg.is_write() {
+            self.send_fs(ipc::FS::FolderSync(content));
+            return;
+        }
+        if let Ok(Some(res)) = tokio::task::spawn_blocking(move || handle_read(msg)).await {
+            self.send(res.to_message(&[])).await;
+        }
+    }
+
     async fn on_close(&mut self, reason: &str, lock: bool) {
         if self.closed {
             return;
@@ -3982,7 +4848,12 @@ impl Connection {
         // We can add a (Vec<conn_id>, input device) to avoid this.
         // But it's not necessary now and we have to consider two audio services(client, server).
         crate::audio_service::set_voice_call_input_device(None, true);
+        crate::audio_service::reset_app_source(self.inner.id());
         log::info!("#{} Connection closed: {}", self.inner.id(), reason);
+        super::audit_log::record(self.audit_entry(
+            super::audit_log::EVENT_CONNECTION,
+            json!({ "action": "close", "reason": reason }),
+        ));
         if lock && self.lock_after_session_end && self.keyboard {
             #[cfg(not(any(target_os = "android", target_os = "ios")))]
             lock_screen().await;
@@ -3997,6 +4868,43 @@ impl Connection {
         let data = ipc::Data::Close;
         self.tx_to_cm.send(data).ok();
         self.port_forward_socket.take();
+        self.set_latency_probe(false);
+        crate::priority_region::remove_conn(self.inner.id());
+        crate::remote_command::cancel_conn(self.inner.id());
+        self.record_history(reason);
+    }
+
+    // The connections closed before the login request are not attempts of a peer, e.g. the
+    // probes of the rendezvous server, not recorded.
+    fn record_history(&mut self, reason: &str) {
+        if self.lr.my_id.is_empty() {
+            return;
+        }
+        let mut entry = std::mem::take(&mut self.history);
+        entry.peer_id = self.lr.my_id.clone();
+        entry.peer_name = self.lr.my_name.clone();
+        // The last login error is why it failed.
+        if entry.success || entry.reason.is_empty() {
+            entry.reason = reason.to_owned();
+        }
+        crate::connection_history::record(entry);
+    }
+
+    fn set_latency_probe(&mut self, enabled: bool) {
+        if self.latency_probe == enabled {
+            return;
+        }
+        self.latency_probe = enabled;
+        if enabled {
+            crate::latency_probe::host_start();
+        } else {
+            crate::latency_probe::host_stop();
+        }
+        log::info!(
+            "#{} Latency probe {}",
+            self.inner.id(),
+            if enabled { "started" } else { "stopped" }
+        );
     }
 
     // The `reason` should be consistent with `check_if_retry` if not empty
@@ -4031,6 +4939,8 @@ impl Connection {
 
     #[inline]
     async fn send(&mut self, msg: Message) {
+        super::metrics::add_sent(msg.compute_size());
+        self.history.bytes_sent += msg.compute_size() as u64;
         allow_err!(self.stream.send(&msg).await);
     }
 
@@ -4108,6 +5018,138 @@ impl Connection {
         }
     }
 
+    // Returns false if the connection is closed.
+    async fn on_session_expiry(
+        &mut self,
+        expiry: crate::session_expiry::SessionExpiry,
+        tx_from_cm: &mpsc::UnboundedSender<ipc::Data>,
+    ) -> bool {
+        self.send(expiry.to_message()).await;
+        if !expiry.is_expired() {
+            return true;
+        }
+        self.send_to_cm(ipc::Data::SessionExpiry {
+            expire_time: 0,
+            expired: true,
+        });
+        if !expiry.is_view_only() {
+            self.send_close_reason_no_retry(crate::session_expiry::CLOSE_REASON)
+                .await;
+            self.on_close("session expired", true).await;
+            return false;
+        }
+        log::info!("Session expired, revoke the permissions");
+        // The same as switching them off in the connection manager.
+        for name in [
+            "keyboard",
+            "clipboard",
+            "audio",
+            "file",
+            "restart",
+            "recording",
+            "block_input",
+        ] {
+            tx_from_cm
+                .send(ipc::Data::SwitchPermission {
+                    name: name.to_owned(),
+                    enabled: false,
+                })
+                .ok();
+        }
+        true
+    }
+
+    /// Switch off the `names` enabled, the same as the connection manager,
+    /// and notify the client and the connection manager of them.
+    async fn revoke_permissions(
+        &mut self,
+        names: Vec<String>,
+        reason: String,
+        tx_from_cm: &mpsc::UnboundedSender<ipc::Data>,
+    ) {
+        let names: Vec<String> = names
+            .into_iter()
+            .filter(|name| self.is_permission_enabled(name))
+            .collect();
+        if names.is_empty() {
+            return;
+        }
+        log::info!("Revoke the permissions {:?}: {}", names, reason);
+        for name in names.iter() {
+            tx_from_cm
+                .send(ipc::Data::SwitchPermission {
+                    name: name.clone(),
+                    enabled: false,
+                })
+                .ok();
+        }
+        let revoked = crate::permission_revocation::PermissionsRevoked {
+            permissions: names,
+            reason,
+        };
+        self.send(revoked.to_message()).await;
+        self.send_to_cm(ipc::Data::RevokePermissions {
+            names: revoked.permissions,
+            reason: revoked.reason,
+        });
+    }
+
+    fn enabled_permissions(&self) -> Vec<String> {
+        crate::permission_revocation::PERMISSIONS
+            .iter()
+            .filter(|name| self.is_permission_enabled(name))
+            .map(|name| name.to_string())
+            .collect()
+    }
+
+    fn is_permission_enabled(&self, name: &str) -> bool {
+        match name {
+            "keyboard" => self.keyboard,
+            "clipboard" => self.clipboard,
+            "audio" => self.audio,
+            "file" => self.file,
+            "restart" => self.restart,
+            "recording" => self.recording,
+            "block_input" => self.block_input,
+            _ => false,
+        }
+    }
+
+    async fn handle_handoff_request(&mut self) {
+        let error = if !crate::session_handoff::is_enabled() {
+            "Session handoff is disabled"
+        } else if !self.is_remote() {
+            "Only the remote control sessions can be handed off"
+        } else {
+            ""
+        };
+        let (token, expires_in) = if error.is_empty() {
+            let token = crate::session_handoff::issue(
+                self.inner.id(),
+                self.lr.my_id.clone(),
+                self.enabled_permissions(),
+            );
+            (token, crate::session_handoff::TOKEN_TTL_SECS)
+        } else {
+            ("".to_owned(), 0)
+        };
+        let msg = crate::session_handoff::Handoff::Token {
+            token,
+            expires_in,
+            error: error.to_owned(),
+        }
+        .to_message();
+        self.send(msg).await;
+    }
+
+    async fn on_input_control(&mut self, mut state: crate::input_arbitration::InputControl) {
+        if let crate::input_arbitration::InputControl::State { you, .. } = &mut state {
+            *you = self.inner.id();
+        }
+        self.send(state.to_message()).await;
+        self.send_to_cm(ipc::Data::InputControl(state));
+    }
+
     fn update_auto_disconnect_timer(&mut self) {
         self.auto_disconnect_timer
             .as_mut()
@@ -4665,6 +5707,108 @@ pub fn on_printer_data(data: Vec<u8>) {
         });
 }
 
+pub fn set_uplink_bandwidth_cap(peer_id: &str, kbps: Option<u32>) {
+    let conn_ids = AUTHED_CONNS
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|c| {
+            c.session_key.peer_id == peer_id
+                && (c.conn_type == AuthConnType::Remote || c.conn_type == AuthConnType::ViewCamera)
+        })
+        .map(|c| c.conn_id)
+        .collect::<Vec<_>>();
+    let mut video_qos = video_service::VIDEO_QOS.lock().unwrap();
+    for conn_id in conn_ids {
+        video_qos.user_bandwidth_cap(conn_id, kbps);
+    }
+}
+
+fn is_authed_conn(conn_id: i32) -> bool {
+    AUTHED_CONNS
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|c| c.conn_id == conn_id)
+}
+
+/// Close the session handed off to the connection `to`, see [`crate::session_handoff`].
+pub fn close_handed_off(conn_id: i32, to: i32) {
+    if let Some(c) = AUTHED_CONNS
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|c| c.conn_id == conn_id)
+    {
+        c.sender.send(Data::SessionHandedOff(to)).ok();
+    }
+}
+
+/// Revoke the permissions of the sessions of the peer, returns the count of the sessions,
+/// see [`crate::permission_revocation`].
+pub fn revoke_permissions(peer_id: &str, names: Vec<String>, reason: String) -> usize {
+    AUTHED_CONNS
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|c| c.session_key.peer_id == peer_id)
+        .filter(|c| {
+            c.sender
+                .send(Data::RevokePermissions {
+                    names: names.clone(),
+                    reason: reason.clone(),
+                })
+                .is_ok()
+        })
+        .count()
+}
+
+/// An authorized session, for the local api and `list-sessions` of the cli.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthedSession {
+    pub conn_id: i32,
+    /// The [`AuthConnType`], e.g. `Remote`.
+    pub conn_type: String,
+    pub peer_id: String,
+    pub name: String,
+}
+
+pub fn authed_sessions() -> Vec<AuthedSession> {
+    AUTHED_CONNS
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|c| AuthedSession {
+            conn_id: c.conn_id,
+            conn_type: format!("{:?}", c.conn_type),
+            peer_id: c.session_key.peer_id.clone(),
+            name: c.session_key.name.clone(),
+        })
+        .collect()
+}
+
+/// Close the authorized session `conn_id`, returns false if not found.
+pub fn close_authed_conn(conn_id: i32) -> bool {
+    AUTHED_CONNS
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|c| c.conn_id == conn_id)
+        .map(|c| c.sender.send(Data::Close).is_ok())
+        .unwrap_or(false)
+}
+
+/// The peer ids of the remote sessions, in the order of the authorization.
+pub fn remote_peer_ids() -> Vec<String> {
+    AUTHED_CONNS
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|c| c.conn_type == AuthConnType::Remote)
+        .map(|c| c.session_key.peer_id.clone())
+        .collect()
+}
+
 #[cfg(windows)]
 pub struct PortableState {
     pub last_uac: bool,
@@ -4774,8 +5918,10 @@ impl Retina {
     #[inline]
     fn on_mouse_event(&mut self, e: &mut MouseEvent, current: usize) {
         let evt_type = e.mask & 0x7;
-        if evt_type == crate::input::MOUSE_TYPE_WHEEL {
-            // x and y are always 0, +1 or -1
+        if evt_type == crate::input::MOUSE_TYPE_WHEEL
+            || evt_type == crate::input::MOUSE_TYPE_MOVE_RELATIVE
+        {
+            // x and y are always 0, +1 or -1, or the distance moved.
             return;
         }
         let Some(d) = self.displays.get(current) else {
@@ -4850,9 +5996,16 @@ mod raii {
             sender: mpsc::UnboundedSender<Data>,
             lr: LoginRequest,
         ) -> Self {
+            // The clients on Linux and macOS print with CUPS.
+            let is_cups_platform = [
+                hbb_common::whoami::Platform::Linux.to_string(),
+                hbb_common::whoami::Platform::MacOS.to_string(),
+            ]
+            .contains(&lr.my_platform);
             let printer = conn_type == crate::server::AuthConnType::Remote
                 && crate::is_support_remote_print(&lr.version)
-                && lr.my_platform == hbb_common::whoami::Platform::Windows.to_string();
+                && (lr.my_platform == hbb_common::whoami::Platform::Windows.to_string()
+                    || is_cups_platform && crate::is_support_remote_print_cups(&lr.version));
             AUTHED_CONNS.lock().unwrap().push(AuthedConn {
                 conn_id,
                 conn_type,
@@ -4867,10 +6020,19 @@ mod raii {
                 shutdown_hooks::add_shutdown_hook(connection_shutdown_hook);
             });
             if conn_type == AuthConnType::Remote || conn_type == AuthConnType::ViewCamera {
-                video_service::VIDEO_QOS
-                    .lock()
-                    .unwrap()
-                    .on_connection_open(conn_id);
+                let mut video_qos = video_service::VIDEO_QOS.lock().unwrap();
+                video_qos.on_connection_open(conn_id);
+                video_qos.user_bandwidth_cap(
+                    conn_id,
+                    crate::unity::bandwidth::get_uplink_cap(&lr.my_id),
+                );
+            }
+            if conn_type == AuthConnType::Remote {
+                crate::input_arbitration::join(crate::input_arbitration::Viewer {
+                    conn_id,
+                    peer_id: lr.my_id.clone(),
+                    name: lr.my_name.clone(),
+                });
             }
             Self(conn_id, conn_type)
         }
@@ -4986,6 +6148,9 @@ mod raii {
                     .on_connection_close(self.0);
             }
             AUTHED_CONNS.lock().unwrap().retain(|c| c.conn_id != self.0);
+            if self.1 == AuthConnType::Remote {
+                crate::input_arbitration::leave(self.0);
+            }
             let remote_count = AUTHED_CONNS
                 .lock()
                 .unwrap()
@@ -5009,6 +6174,7 @@ mod raii {
             {
                 use crate::whiteboard;
                 whiteboard::unregister_whiteboard(whiteboard::get_key_cursor(self.0));
+                whiteboard::unregister_whiteboard(whiteboard::get_key_annotation(self.0));
             }
         }
     }
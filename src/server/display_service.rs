@@ -257,7 +257,9 @@ pub(super) fn get_original_resolution(
     #[cfg(windows)]
     let is_rustdesk_virtual_display =
         crate::virtual_display_manager::rustdesk_idd::is_virtual_display(&display_name);
-    #[cfg(not(windows))]
+    #[cfg(target_os = "linux")]
+    let is_rustdesk_virtual_display = crate::platform::linux_desktop_manager::is_virtual_display();
+    #[cfg(not(any(windows, target_os = "linux")))]
     let is_rustdesk_virtual_display = false;
     Some(if is_rustdesk_virtual_display {
         Resolution {
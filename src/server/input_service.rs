@@ -1054,6 +1054,17 @@ pub fn handle_mouse_simulation_(evt: &MouseEvent, conn: i32) {
                 y: evt.y,
             };
         }
+        MOUSE_TYPE_MOVE_RELATIVE => {
+            en.mouse_move_relative(evt.x, evt.y);
+            if let Some((x, y)) = crate::get_cursor_pos() {
+                *LATEST_PEER_INPUT_CURSOR.lock().unwrap() = Input {
+                    conn,
+                    time: get_time(),
+                    x,
+                    y,
+                };
+            }
+        }
         MOUSE_TYPE_DOWN => match buttons {
             MOUSE_BUTTON_LEFT => {
                 allow_err!(en.mouse_down(MouseButton::Left));
@@ -0,0 +1,49 @@
+//! Rotation of the key pair of the host, e.g. after it may be leaked or on a schedule.
+//!
+//! The public key is published to the rendezvous server, which signs it with the id for the
+//! clients to verify the host. [`rotate`] replaces the key pair in the config and marks it
+//! unconfirmed, then the server restarts to load it and registers it again with the same uuid,
+//! so the rendezvous server accepts the new key for the id without any change of the clients.
+//!
+//! The fingerprint of the previous key and the time of the rotation are kept in the options
+//! [`OPTION_PREVIOUS_FINGERPRINT`] and [`OPTION_ROTATED_AT`], to tell the peers which key
+//! is replaced.
+use hbb_common::{bail, config::Config, log, sodiumoxide::crypto::sign, ResultType};
+use serde_json::json;
+
+pub const OPTION_PREVIOUS_FINGERPRINT: &str = "previous-key-fingerprint";
+pub const OPTION_ROTATED_AT: &str = "key-rotated-at";
+pub const ERR_ACTIVE_SESSIONS: &str = "There are active sessions";
+// Let the config be synced to the root service before the restart.
+pub const RESTART_DELAY_SECS: f32 = 3.;
+
+/// Replace the key pair, returns the fingerprint of the new public key.
+///
+/// It fails if there are active sessions unless `force`, they are closed by the restart.
+pub fn rotate(force: bool) -> ResultType<String> {
+    if !force && !super::AUTHED_CONNS.lock().unwrap().is_empty() {
+        bail!(ERR_ACTIVE_SESSIONS);
+    }
+    let previous = crate::common::pk_to_fingerprint(Config::get_key_pair().1);
+    let (pk, sk) = sign::gen_keypair();
+    set_key_pair(sk.0.to_vec(), pk.0.to_vec())?;
+    Config::set_option(OPTION_PREVIOUS_FINGERPRINT.to_owned(), previous);
+    Config::set_option(
+        OPTION_ROTATED_AT.to_owned(),
+        hbb_common::get_time().to_string(),
+    );
+    let fingerprint = crate::common::pk_to_fingerprint(pk.0.to_vec());
+    log::info!("Key pair rotated, the new fingerprint: {}", fingerprint);
+    Ok(fingerprint)
+}
+
+// The key pair is not settable directly, so it's replaced in the serialized config,
+// the same as the config synced from the root service.
+fn set_key_pair(sk: Vec<u8>, pk: Vec<u8>) -> ResultType<()> {
+    let mut value = serde_json::to_value(Config::get())?;
+    value["key_pair"] = json!([sk, pk]);
+    value["key_confirmed"] = json!(false);
+    value["keys_confirmed"] = json!({});
+    Config::set(serde_json::from_value(value)?);
+    Ok(())
+}
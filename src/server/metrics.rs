@@ -0,0 +1,378 @@
+//! The Prometheus metrics of the server, for the fleet monitoring to scrape the hosts.
+//!
+//! It's off by default, enabled with the option [`OPTION_ENABLE`], and serves
+//! `GET /metrics` in the Prometheus text format on `<`[`OPTION_BIND`]`>:<`[`OPTION_PORT`]`>`,
+//! `127.0.0.1` and [`DEFAULT_PORT`] if not set. Binding to another address exposes the metrics
+//! without any authentication, it's left to the firewall.
+//!
+//! The counters are always collected, they're only atomic additions:
+//! - the sessions by the connection type and the bytes sent and received,
+//! - the frames encoded, the encoding time and the measured frame rate of each display,
+//! - the encoding, capture, connection and login errors.
+use hbb_common::{
+    config::Config,
+    log,
+    tokio::{
+        self,
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+        time::{timeout, Duration},
+    },
+    ResultType,
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+pub const OPTION_ENABLE: &str = "enable-metrics";
+pub const OPTION_PORT: &str = "metrics-port";
+pub const OPTION_BIND: &str = "metrics-bind-address";
+pub const DEFAULT_PORT: u16 = 21121;
+const DEFAULT_BIND: &str = "127.0.0.1";
+const MAX_HEAD: usize = 8 * 1024;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+static BYTES_SENT: AtomicU64 = AtomicU64::new(0);
+static BYTES_RECEIVED: AtomicU64 = AtomicU64::new(0);
+static FRAMES_ENCODED: AtomicU64 = AtomicU64::new(0);
+static ENCODE_MICROS: AtomicU64 = AtomicU64::new(0);
+static ENCODE_ERRORS: AtomicU64 = AtomicU64::new(0);
+static CAPTURE_ERRORS: AtomicU64 = AtomicU64::new(0);
+static CONNECTION_ERRORS: AtomicU64 = AtomicU64::new(0);
+static LOGIN_ERRORS: AtomicU64 = AtomicU64::new(0);
+
+lazy_static::lazy_static! {
+    // The frames sent in the last second, by the video service name.
+    static ref DISPLAY_FPS: Mutex<HashMap<String, f64>> = Default::default();
+}
+
+#[inline]
+pub fn add_sent(bytes: u64) {
+    BYTES_SENT.fetch_add(bytes, Ordering::Relaxed);
+}
+
+#[inline]
+pub fn add_received(bytes: u64) {
+    BYTES_RECEIVED.fetch_add(bytes, Ordering::Relaxed);
+}
+
+#[inline]
+pub fn on_frame_encoded(elapsed: std::time::Duration) {
+    FRAMES_ENCODED.fetch_add(1, Ordering::Relaxed);
+    ENCODE_MICROS.fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+}
+
+#[inline]
+pub fn on_encode_error() {
+    ENCODE_ERRORS.fetch_add(1, Ordering::Relaxed);
+}
+
+#[inline]
+pub fn on_capture_error() {
+    CAPTURE_ERRORS.fetch_add(1, Ordering::Relaxed);
+}
+
+#[inline]
+pub fn on_connection_error() {
+    CONNECTION_ERRORS.fetch_add(1, Ordering::Relaxed);
+}
+
+#[inline]
+pub fn on_login_error() {
+    LOGIN_ERRORS.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn set_fps(video_service_name: &str, fps: f64) {
+    DISPLAY_FPS
+        .lock()
+        .unwrap()
+        .insert(video_service_name.to_owned(), fps);
+}
+
+pub fn remove_display(video_service_name: &str) {
+    DISPLAY_FPS.lock().unwrap().remove(video_service_name);
+}
+
+#[inline]
+fn is_enabled() -> bool {
+    Config::get_bool_option(OPTION_ENABLE)
+}
+
+fn addr() -> (String, u16) {
+    let bind = Config::get_option(OPTION_BIND);
+    let port = Config::get_option(OPTION_PORT)
+        .parse()
+        .ok()
+        .filter(|p| *p > 0)
+        .unwrap_or(DEFAULT_PORT);
+    (
+        if bind.is_empty() {
+            DEFAULT_BIND.to_owned()
+        } else {
+            bind
+        },
+        port,
+    )
+}
+
+/// Serve the metrics while they're enabled, restarted if the address is changed.
+pub async fn start() {
+    loop {
+        if !is_enabled() {
+            hbb_common::sleep(1.).await;
+            continue;
+        }
+        let addr = addr();
+        let listener = match TcpListener::bind((addr.0.as_str(), addr.1)).await {
+            Ok(listener) => listener,
+            Err(err) => {
+                log::error!(
+                    "Failed to listen on the metrics address {:?}: {}",
+                    addr,
+                    err
+                );
+                hbb_common::sleep(10.).await;
+                continue;
+            }
+        };
+        log::info!("Metrics listening on {}:{}", addr.0, addr.1);
+        let mut timer = tokio::time::interval(Duration::from_secs(1));
+        loop {
+            tokio::select! {
+                res = listener.accept() => {
+                    if let Ok((stream, _)) = res {
+                        tokio::spawn(async move {
+                            if let Err(err) = handle(stream).await {
+                                log::debug!("Metrics request failed: {}", err);
+                            }
+                        });
+                    }
+                }
+                _ = timer.tick() => {
+                    if !is_enabled() || self::addr() != addr {
+                        break;
+                    }
+                }
+            }
+        }
+        log::info!("Metrics stopped");
+    }
+}
+
+async fn handle(mut stream: TcpStream) -> ResultType<()> {
+    let (status, body) = match timeout(REQUEST_TIMEOUT, read_request_line(&mut stream)).await {
+        Ok(Ok(line)) => {
+            let mut parts = line.split(' ');
+            let method = parts.next().unwrap_or_default();
+            let path = parts
+                .next()
+                .unwrap_or_default()
+                .split('?')
+                .next()
+                .unwrap_or_default();
+            match (method, path) {
+                ("GET", "/metrics") => ("200 OK", render()),
+                (_, "/metrics") => ("405 Method Not Allowed", String::new()),
+                _ => ("404 Not Found", String::new()),
+            }
+        }
+        Ok(Err(_)) => ("400 Bad Request", String::new()),
+        Err(_) => ("408 Request Timeout", String::new()),
+    };
+    let head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(body.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+// The body of the GET is ignored.
+async fn read_request_line(stream: &mut TcpStream) -> ResultType<String> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 1024];
+    loop {
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            buf.truncate(pos);
+            break;
+        }
+        if buf.len() > MAX_HEAD {
+            hbb_common::bail!("Request header too large");
+        }
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            hbb_common::bail!("Connection closed");
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+    let head = String::from_utf8(buf)?;
+    Ok(head.lines().next().unwrap_or_default().to_owned())
+}
+
+fn render() -> String {
+    let mut sessions = BTreeMap::<String, u64>::new();
+    for s in super::authed_sessions() {
+        *sessions.entry(s.conn_type).or_default() += 1;
+    }
+    let fps = DISPLAY_FPS
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(k, v)| (k.clone(), *v))
+        .collect::<BTreeMap<_, _>>();
+    let video_qos = super::video_service::VIDEO_QOS.lock().unwrap();
+    let (target_fps, target_bitrate) = (video_qos.fps(), video_qos.bitrate());
+    drop(video_qos);
+    to_text(&Snapshot {
+        sessions,
+        fps,
+        target_fps,
+        target_bitrate,
+    })
+}
+
+struct Snapshot {
+    sessions: BTreeMap<String, u64>,
+    fps: BTreeMap<String, f64>,
+    target_fps: u32,
+    target_bitrate: u32,
+}
+
+fn to_text(s: &Snapshot) -> String {
+    let mut out = String::new();
+    let mut metric = |name: &str, kind: &str, help: &str, samples: Vec<(String, String)>| {
+        writeln!(out, "# HELP rustdesk_{} {}", name, help).ok();
+        writeln!(out, "# TYPE rustdesk_{} {}", name, kind).ok();
+        for (labels, value) in samples {
+            writeln!(out, "rustdesk_{}{} {}", name, labels, value).ok();
+        }
+    };
+    let counter = |v: &AtomicU64| vec![(String::new(), v.load(Ordering::Relaxed).to_string())];
+    metric(
+        "sessions",
+        "gauge",
+        "The authorized sessions by the connection type.",
+        s.sessions
+            .iter()
+            .map(|(t, n)| (format!("{{type=\"{}\"}}", escape(t)), n.to_string()))
+            .collect(),
+    );
+    metric(
+        "sent_bytes_total",
+        "counter",
+        "The bytes of the messages sent to the peers.",
+        counter(&BYTES_SENT),
+    );
+    metric(
+        "received_bytes_total",
+        "counter",
+        "The bytes of the messages received from the peers.",
+        counter(&BYTES_RECEIVED),
+    );
+    metric(
+        "frames_encoded_total",
+        "counter",
+        "The video frames encoded.",
+        counter(&FRAMES_ENCODED),
+    );
+    metric(
+        "encode_seconds_total",
+        "counter",
+        "The time spent encoding the video frames.",
+        vec![(
+            String::new(),
+            (ENCODE_MICROS.load(Ordering::Relaxed) as f64 / 1_000_000.).to_string(),
+        )],
+    );
+    metric(
+        "display_fps",
+        "gauge",
+        "The frames sent in the last second by the display.",
+        s.fps
+            .iter()
+            .map(|(d, fps)| {
+                (
+                    format!("{{display=\"{}\"}}", escape(d)),
+                    format!("{:.1}", fps),
+                )
+            })
+            .collect(),
+    );
+    metric(
+        "target_fps",
+        "gauge",
+        "The frame rate targeted by the quality control.",
+        vec![(String::new(), s.target_fps.to_string())],
+    );
+    metric(
+        "target_bitrate_kbps",
+        "gauge",
+        "The bitrate of the encoder.",
+        vec![(String::new(), s.target_bitrate.to_string())],
+    );
+    metric(
+        "encode_errors_total",
+        "counter",
+        "The video frames failed to encode.",
+        counter(&ENCODE_ERRORS),
+    );
+    metric(
+        "capture_errors_total",
+        "counter",
+        "The screen captures failed.",
+        counter(&CAPTURE_ERRORS),
+    );
+    metric(
+        "connection_errors_total",
+        "counter",
+        "The connections closed by an error of the stream.",
+        counter(&CONNECTION_ERRORS),
+    );
+    metric(
+        "login_errors_total",
+        "counter",
+        "The login errors sent to the peers, e.g. the wrong passwords.",
+        counter(&LOGIN_ERRORS),
+    );
+    out
+}
+
+fn escape(label: &str) -> String {
+    label
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_text() {
+        on_login_error();
+        let text = to_text(&Snapshot {
+            sessions: [("Remote".to_owned(), 2)].into_iter().collect(),
+            fps: [("monitor0".to_owned(), 29.96)].into_iter().collect(),
+            target_fps: 30,
+            target_bitrate: 2000,
+        });
+        assert!(
+            text.contains("# TYPE rustdesk_sessions gauge\nrustdesk_sessions{type=\"Remote\"} 2\n")
+        );
+        assert!(text.contains("rustdesk_display_fps{display=\"monitor0\"} 30.0\n"));
+        assert!(text.contains("rustdesk_target_fps 30\n"));
+        assert!(text
+            .contains("# TYPE rustdesk_login_errors_total counter\nrustdesk_login_errors_total "));
+        assert!(!text.contains("rustdesk_login_errors_total 0\n"));
+        assert_eq!(escape("a\"b"), "a\\\"b");
+    }
+}
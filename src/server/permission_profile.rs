@@ -0,0 +1,174 @@
+//! Named permission profiles of the incoming sessions, instead of the flat permission toggles.
+//!
+//! - [`OPTION_PROFILES`]: the custom profiles, a JSON map of the names to the [`Profile`]s,
+//!   they override the built-in "view-only", "support" and "full" of the same names.
+//! - [`OPTION_DEFAULT`]: the profile of the peers without an assigned one,
+//!   the permission toggles are used if empty.
+//! - [`OPTION_ASSIGNMENTS`]: the profiles assigned per peer id and per address book tag,
+//!   see [`Assignments`]. The tags are looked up in the address book cached on the host,
+//!   so it must be logged in.
+//!
+//! The profile of a peer is the one assigned to its id, then the one of its first tag
+//! with a profile, then the default one. It's applied on the login, and the permissions
+//! can still be switched in the connection manager during the session.
+//!
+//! A profile only restricts the permission toggles, it never grants a permission switched
+//! off there. The profile is selected by the id the peer sends in the login request, which
+//! any peer can claim, so a profile assigned to a trusted id must not give more than the
+//! toggles give to everyone.
+//!
+//! The IdP groups of the account of the peer don't select the profiles: the peer can claim
+//! any groups, and the host has no way to verify them, like checking a token signed by the
+//! IdP or looking the account up on the API server.
+use hbb_common::{
+    config::{Ab, Config},
+    log,
+};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+pub const OPTION_PROFILES: &str = "permission-profiles";
+pub const OPTION_DEFAULT: &str = "permission-profile";
+pub const OPTION_ASSIGNMENTS: &str = "permission-profile-assignments";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    pub keyboard: bool,
+    pub clipboard: bool,
+    pub file: bool,
+    pub audio: bool,
+    pub restart: bool,
+    pub recording: bool,
+    pub block_input: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Assignments {
+    /// The peer id to the profile name.
+    pub peers: HashMap<String, String>,
+    /// The address book tag to the profile name.
+    pub tags: HashMap<String, String>,
+}
+
+impl Assignments {
+    pub fn load(raw: &str) -> Self {
+        serde_json::from_str(raw).unwrap_or_default()
+    }
+
+    fn resolve(&self, peer_id: &str, tags: &[String], default: &str) -> Option<String> {
+        if let Some(name) = self.peers.get(peer_id) {
+            return Some(name.clone());
+        }
+        if let Some(name) = tags.iter().find_map(|t| self.tags.get(t)) {
+            return Some(name.clone());
+        }
+        if default.is_empty() {
+            None
+        } else {
+            Some(default.to_owned())
+        }
+    }
+}
+
+fn builtin_profiles() -> BTreeMap<String, Profile> {
+    BTreeMap::from([
+        ("view-only".to_owned(), Profile::default()),
+        (
+            "support".to_owned(),
+            Profile {
+                keyboard: true,
+                clipboard: true,
+                audio: true,
+                restart: true,
+                ..Default::default()
+            },
+        ),
+        (
+            "full".to_owned(),
+            Profile {
+                keyboard: true,
+                clipboard: true,
+                file: true,
+                audio: true,
+                restart: true,
+                recording: true,
+                block_input: true,
+            },
+        ),
+    ])
+}
+
+/// The built-in profiles and the custom ones of `raw`, the value of [`OPTION_PROFILES`].
+pub fn get_profiles(raw: &str) -> BTreeMap<String, Profile> {
+    let mut profiles = builtin_profiles();
+    if !raw.is_empty() {
+        match serde_json::from_str::<BTreeMap<String, Profile>>(raw) {
+            Ok(custom) => profiles.extend(custom),
+            Err(e) => log::error!("Invalid permission profiles: {}", e),
+        }
+    }
+    profiles
+}
+
+/// The name and the profile of the claimed `peer_id`, `None` to use the permission toggles.
+/// The caller must only use it to restrict the permissions.
+pub fn get_profile(peer_id: &str) -> Option<(String, Profile)> {
+    let assignments = Assignments::load(&Config::get_option(OPTION_ASSIGNMENTS));
+    let tags = if assignments.tags.is_empty() {
+        vec![]
+    } else {
+        peer_tags(peer_id)
+    };
+    let name = assignments.resolve(peer_id, &tags, &Config::get_option(OPTION_DEFAULT))?;
+    match get_profiles(&Config::get_option(OPTION_PROFILES)).remove(&name) {
+        Some(profile) => Some((name, profile)),
+        None => {
+            log::warn!("Unknown permission profile {} of {}", name, peer_id);
+            None
+        }
+    }
+}
+
+fn peer_tags(peer_id: &str) -> Vec<String> {
+    Ab::load()
+        .ab_entries
+        .iter()
+        .flat_map(|ab| ab.peers.iter())
+        .filter(|p| p.id == peer_id)
+        .flat_map(|p| p.tags.iter().cloned())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        let assignments = Assignments {
+            peers: HashMap::from([("123".to_owned(), "full".to_owned())]),
+            tags: HashMap::from([("helpdesk".to_owned(), "support".to_owned())]),
+        };
+        let tags = vec!["home".to_owned(), "helpdesk".to_owned()];
+        assert_eq!(
+            assignments.resolve("123", &tags, ""),
+            Some("full".to_owned())
+        );
+        assert_eq!(
+            assignments.resolve("456", &tags, "view-only"),
+            Some("support".to_owned())
+        );
+        assert_eq!(
+            assignments.resolve("456", &[], "view-only"),
+            Some("view-only".to_owned())
+        );
+        assert_eq!(assignments.resolve("456", &[], ""), None);
+        let profiles =
+            get_profiles(r#"{"support": {"keyboard": true}, "audit": {"recording": true}}"#);
+        assert_eq!(profiles.len(), 4);
+        assert!(!profiles["support"].clipboard);
+        assert!(profiles["audit"].recording);
+    }
+}
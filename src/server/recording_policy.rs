@@ -0,0 +1,218 @@
+//! Policy of the recording of the incoming sessions by the host, with the option
+//! `allow-auto-record-incoming`, independent of the recording chosen by the client.
+//!
+//! - [`OPTION_DIR`]: the directory of the recordings, the video save directory if empty.
+//! - [`OPTION_TEMPLATE`]: the template of the file names without the extension, `{id}`,
+//!   `{peer_id}`, `{date}`, `{time}`, `{display}` and `{codec}` are replaced.
+//!   It's prefixed with [`PREFIX`] like the default file names of the incoming recordings.
+//! - [`OPTION_RETENTION_DAYS`]: the recordings older than it are removed.
+//! - [`OPTION_MAX_SIZE`]: the total MB of the recordings, the oldest ones beyond it are removed.
+//! - [`OPTION_MIN_FREE`]: the MB of the disk to keep free, the connections are refused if the
+//!   recordings can't be kept without it after removing the expired ones.
+//!
+//! The recordings are uploaded to an S3-compatible storage as well with
+//! [`crate::hbbs_http::s3_upload`]. Only the `.webm` and `.mp4` files starting with [`PREFIX`]
+//! are removed, the other files of the directory, e.g. the outgoing recordings of the client,
+//! are never touched.
+use hbb_common::{
+    config::{self, Config},
+    log,
+};
+use std::{
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+
+pub const OPTION_ENABLE: &str = "allow-auto-record-incoming";
+pub const OPTION_DIR: &str = "incoming-record-directory";
+pub const OPTION_TEMPLATE: &str = "incoming-record-filename";
+pub const OPTION_RETENTION_DAYS: &str = "incoming-record-retention-days";
+pub const OPTION_MAX_SIZE: &str = "incoming-record-max-size";
+pub const OPTION_MIN_FREE: &str = "incoming-record-min-free-space";
+const EXTENSIONS: [&str; 2] = ["webm", "mp4"];
+/// The prefix of the file names of the incoming recordings.
+pub const PREFIX: &str = "incoming_";
+const MB: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq)]
+struct Record {
+    path: PathBuf,
+    modified: SystemTime,
+    size: u64,
+}
+
+#[inline]
+pub fn is_enabled() -> bool {
+    config::option2bool(OPTION_ENABLE, &Config::get_option(OPTION_ENABLE))
+}
+
+/// The directory of the recordings.
+pub fn directory() -> String {
+    let dir = Config::get_option(OPTION_DIR);
+    if !dir.is_empty() {
+        return dir;
+    }
+    #[cfg(windows)]
+    let root = crate::platform::is_root();
+    #[cfg(not(windows))]
+    let root = false;
+    crate::ui_interface::video_save_directory(root)
+}
+
+/// The template of the file names with the peer ids of the remote sessions now,
+/// the default file names if empty.
+pub fn template() -> String {
+    let template = Config::get_option(OPTION_TEMPLATE);
+    if template.is_empty() {
+        return template;
+    }
+    let template = if template.starts_with(PREFIX) {
+        template
+    } else {
+        format!("{}{}", PREFIX, template)
+    };
+    template.replace("{peer_id}", &super::remote_peer_ids().join("-"))
+}
+
+/// Remove the expired recordings, and whether there is the free space for the new ones.
+pub fn check_space(dir: &str) -> bool {
+    if let Err(e) = std::fs::create_dir_all(dir) {
+        log::error!("Failed to create the recording directory {}: {}", dir, e);
+        return false;
+    }
+    let records = list(dir);
+    let pruned = select_pruned(
+        &records,
+        SystemTime::now(),
+        get_number(OPTION_RETENTION_DAYS).map(|d| Duration::from_secs(d * 24 * 3600)),
+        get_number(OPTION_MAX_SIZE).map(|m| m * MB),
+    );
+    for path in pruned {
+        match std::fs::remove_file(&path) {
+            Ok(()) => log::info!("Removed the expired recording {:?}", path),
+            Err(e) => log::error!("Failed to remove the recording {:?}: {}", path, e),
+        }
+    }
+    let Some(min_free) = get_number(OPTION_MIN_FREE) else {
+        return true;
+    };
+    match fs2::available_space(dir) {
+        Ok(available) if available >= min_free * MB => true,
+        Ok(available) => {
+            log::error!(
+                "Only {} MB free for the recordings in {}, {} MB required",
+                available / MB,
+                dir,
+                min_free
+            );
+            false
+        }
+        Err(e) => {
+            log::error!("Failed to get the free space of {}: {}", dir, e);
+            false
+        }
+    }
+}
+
+fn get_number(option: &str) -> Option<u64> {
+    Config::get_option(option)
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .filter(|n| *n > 0)
+}
+
+fn list(dir: &str) -> Vec<Record> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return vec![];
+    };
+    entries
+        .flatten()
+        .filter_map(|e| {
+            let path = e.path();
+            let name = path.file_name()?.to_str()?;
+            let ext = path.extension()?.to_str()?;
+            if !name.starts_with(PREFIX) || !EXTENSIONS.contains(&ext) {
+                return None;
+            }
+            let meta = e.metadata().ok()?;
+            if !meta.is_file() {
+                return None;
+            }
+            Some(Record {
+                modified: meta.modified().ok()?,
+                size: meta.len(),
+                path,
+            })
+        })
+        .collect()
+}
+
+/// The recordings to remove, the ones older than `retention`, then the oldest ones
+/// beyond `max_size` in total.
+fn select_pruned(
+    records: &[Record],
+    now: SystemTime,
+    retention: Option<Duration>,
+    max_size: Option<u64>,
+) -> Vec<PathBuf> {
+    let mut kept: Vec<&Record> = vec![];
+    let mut pruned = vec![];
+    for r in records {
+        let expired = retention
+            .map(|retention| now.duration_since(r.modified).unwrap_or_default() > retention)
+            .unwrap_or(false);
+        if expired {
+            pruned.push(r.path.clone());
+        } else {
+            kept.push(r);
+        }
+    }
+    if let Some(max_size) = max_size {
+        // The newest first.
+        kept.sort_by(|a, b| b.modified.cmp(&a.modified));
+        let mut total = 0;
+        for r in kept {
+            total += r.size;
+            if total > max_size {
+                pruned.push(r.path.clone());
+            }
+        }
+    }
+    pruned
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_pruned() {
+        let now = SystemTime::now();
+        let day = Duration::from_secs(24 * 3600);
+        let record = |name: &str, days: u32, size: u64| Record {
+            path: PathBuf::from(name),
+            modified: now - day * days,
+            size,
+        };
+        let records = vec![
+            record("a", 10, 1),
+            record("b", 1, 5),
+            record("c", 0, 5),
+            record("d", 2, 1),
+        ];
+        assert!(select_pruned(&records, now, None, None).is_empty());
+        assert_eq!(
+            select_pruned(&records, now, Some(day * 7), None),
+            vec![PathBuf::from("a")]
+        );
+        assert_eq!(
+            select_pruned(&records, now, Some(day * 7), Some(10)),
+            vec![PathBuf::from("a"), PathBuf::from("d")]
+        );
+        assert_eq!(
+            select_pruned(&records, now, None, Some(6)),
+            vec![PathBuf::from("b"), PathBuf::from("d"), PathBuf::from("a")]
+        );
+    }
+}
@@ -23,6 +23,11 @@ const MAX_SERVICES: usize = 100; // Maximum number of persistent terminal servic
 const SERVICE_IDLE_TIMEOUT: Duration = Duration::from_secs(3600); // 1 hour idle timeout
 const CHANNEL_BUFFER_SIZE: usize = 100; // Number of messages to buffer in channel
 const COMPRESS_THRESHOLD: usize = 512; // Compress terminal data larger than this
+const MAX_REPLAY_SIZE: usize = 64 * 1024; // Scrollback replayed to the client on reconnect
+
+// Erase the scrollback and the screen, and home the cursor, before the replay,
+// so the client does not show the old output twice.
+const REPLAY_PREFIX: &[u8] = b"\x1b[3J\x1b[2J\x1b[H";
 
 lazy_static::lazy_static! {
     // Global registry of persistent terminal services indexed by service_id
@@ -458,6 +463,8 @@ pub struct TerminalSession {
     // Track if we've already sent the closed message
     closed_message_sent: bool,
     is_opened: bool,
+    // The scrollback to send before the new output after reconnecting
+    pending_replay: Option<Vec<u8>>,
 }
 
 impl TerminalSession {
@@ -479,6 +486,7 @@ impl TerminalSession {
             cols,
             closed_message_sent: false,
             is_opened: false,
+            pending_replay: None,
         }
     }
 
@@ -737,11 +745,12 @@ impl TerminalServiceProxy {
             }
             response.set_opened(opened);
 
-            // Send buffered output
-            let buffer = session.output_buffer.get_recent(4096);
+            // Replay the buffered output, sent by `read_outputs()` after the opened response
+            let buffer = session.output_buffer.get_recent(MAX_REPLAY_SIZE);
             if !buffer.is_empty() {
-                // We'll need to send this separately or extend the protocol
-                // For now, just acknowledge the reconnection
+                let mut replay = REPLAY_PREFIX.to_vec();
+                replay.extend(buffer);
+                session.pending_replay = Some(replay);
             }
 
             return Ok(Some(response));
@@ -1038,6 +1047,9 @@ impl TerminalServiceProxy {
                 // Read from output channel
                 let mut has_activity = false;
                 let mut received_data = Vec::new();
+                if let Some(replay) = session.pending_replay.take() {
+                    responses.push(Self::data_response(terminal_id, replay));
+                }
                 if let Some(output_rx) = &session.output_rx {
                     // Try to read all available data
                     while let Ok(data) = output_rx.try_recv() {
@@ -1053,26 +1065,7 @@ impl TerminalServiceProxy {
 
                 // Process received data for responses
                 for data in received_data {
-                    let mut response = TerminalResponse::new();
-                    let mut terminal_data = TerminalData::new();
-                    terminal_data.terminal_id = terminal_id;
-
-                    // Compress data if it exceeds threshold
-                    if data.len() > COMPRESS_THRESHOLD {
-                        let compressed = compress::compress(&data);
-                        if compressed.len() < data.len() {
-                            terminal_data.data = bytes::Bytes::from(compressed);
-                            terminal_data.compressed = true;
-                        } else {
-                            // Compression didn't help, send uncompressed
-                            terminal_data.data = bytes::Bytes::from(data);
-                        }
-                    } else {
-                        terminal_data.data = bytes::Bytes::from(data);
-                    }
-
-                    response.set_data(terminal_data);
-                    responses.push(response);
+                    responses.push(Self::data_response(terminal_id, data));
                 }
 
                 if has_activity {
@@ -1126,6 +1119,29 @@ impl TerminalServiceProxy {
         responses
     }
 
+    fn data_response(terminal_id: i32, data: Vec<u8>) -> TerminalResponse {
+        let mut response = TerminalResponse::new();
+        let mut terminal_data = TerminalData::new();
+        terminal_data.terminal_id = terminal_id;
+
+        // Compress data if it exceeds threshold
+        if data.len() > COMPRESS_THRESHOLD {
+            let compressed = compress::compress(&data);
+            if compressed.len() < data.len() {
+                terminal_data.data = bytes::Bytes::from(compressed);
+                terminal_data.compressed = true;
+            } else {
+                // Compression didn't help, send uncompressed
+                terminal_data.data = bytes::Bytes::from(data);
+            }
+        } else {
+            terminal_data.data = bytes::Bytes::from(data);
+        }
+
+        response.set_data(terminal_data);
+        response
+    }
+
     /// Cleanup when connection drops
     pub fn on_disconnect(&self) {
         if !self.is_persistent {
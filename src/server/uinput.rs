@@ -15,6 +15,8 @@ static IPC_REQUEST_TIMEOUT: u64 = 1000;
 static IPC_POSTFIX_KEYBOARD: &str = "_uinput_keyboard";
 static IPC_POSTFIX_MOUSE: &str = "_uinput_mouse";
 static IPC_POSTFIX_CONTROL: &str = "_uinput_control";
+static IPC_POSTFIX_GAMEPAD: &str = "_uinput_gamepad";
+static IPC_POSTFIX_PEN: &str = "_uinput_pen";
 
 pub mod client {
     use super::*;
@@ -161,6 +163,49 @@ pub mod client {
         }
     }
 
+    /// The virtual gamepads of a connection, removed when it's dropped.
+    pub struct UInputGamepad {
+        conn: Connection,
+        rt: Runtime,
+    }
+
+    impl UInputGamepad {
+        pub fn new() -> ResultType<Self> {
+            let rt = Runtime::new()?;
+            let conn = rt.block_on(ipc::connect(IPC_CONN_TIMEOUT, IPC_POSTFIX_GAMEPAD))?;
+            Ok(Self { conn, rt })
+        }
+
+        pub fn send(&mut self, event: crate::gamepad::GamepadEvent) -> ResultType<()> {
+            self.rt.block_on(self.conn.send(&Data::Gamepad(event)))
+        }
+    }
+
+    /// The virtual pen of a connection, removed when it's dropped.
+    pub struct UInputPen {
+        conn: Connection,
+        rt: Runtime,
+    }
+
+    impl UInputPen {
+        /// Create the pen with the range of the coordinates of the displays.
+        pub fn new(rng_x: (i32, i32), rng_y: (i32, i32)) -> ResultType<Self> {
+            let rt = Runtime::new()?;
+            let mut conn = rt.block_on(ipc::connect(IPC_CONN_TIMEOUT, IPC_POSTFIX_PEN))?;
+            rt.block_on(conn.send(&Data::Control(ipc::DataControl::Resolution {
+                minx: rng_x.0,
+                maxx: rng_x.1,
+                miny: rng_y.0,
+                maxy: rng_y.1,
+            })))?;
+            Ok(Self { conn, rt })
+        }
+
+        pub fn send(&mut self, event: crate::pen::PenEvent) -> ResultType<()> {
+            self.rt.block_on(self.conn.send(&Data::Pen(event)))
+        }
+    }
+
     pub async fn set_resolution(minx: i32, maxx: i32, miny: i32, maxy: i32) -> ResultType<()> {
         let mut conn = ipc::connect(IPC_CONN_TIMEOUT, IPC_POSTFIX_CONTROL).await?;
         conn.send(&Data::Control(ipc::DataControl::Resolution {
@@ -579,6 +624,252 @@ pub mod service {
         }
     }
 
+    // A virtual Xbox 360 controller, the same buttons and axes as xpad.
+    fn create_uinput_gamepad(index: u8) -> ResultType<VirtualDevice> {
+        use evdev::{AbsInfo, AbsoluteAxisType, BusType, InputId, UinputAbsSetup};
+
+        let mut keys = AttributeSet::<evdev::Key>::new();
+        for (_, key) in GAMEPAD_BUTTONS.iter() {
+            keys.insert(*key);
+        }
+        let name = format!("RustDesk UInput Gamepad {}", index);
+        let mut builder = VirtualDeviceBuilder::new()?
+            .name(&name)
+            .input_id(InputId::new(BusType::BUS_USB, 0x045e, 0x028e, 0x0110))
+            .with_keys(&keys)?;
+        for axis in [
+            AbsoluteAxisType::ABS_X,
+            AbsoluteAxisType::ABS_Y,
+            AbsoluteAxisType::ABS_RX,
+            AbsoluteAxisType::ABS_RY,
+        ] {
+            builder = builder.with_absolute_axis(&UinputAbsSetup::new(
+                axis,
+                AbsInfo::new(0, i16::MIN as _, i16::MAX as _, 16, 128, 0),
+            ))?;
+        }
+        for axis in [AbsoluteAxisType::ABS_Z, AbsoluteAxisType::ABS_RZ] {
+            builder = builder.with_absolute_axis(&UinputAbsSetup::new(
+                axis,
+                AbsInfo::new(0, 0, u8::MAX as _, 0, 0, 0),
+            ))?;
+        }
+        for axis in [AbsoluteAxisType::ABS_HAT0X, AbsoluteAxisType::ABS_HAT0Y] {
+            builder = builder
+                .with_absolute_axis(&UinputAbsSetup::new(axis, AbsInfo::new(0, -1, 1, 0, 0, 0)))?;
+        }
+        Ok(builder.build()?)
+    }
+
+    const GAMEPAD_BUTTONS: [(u16, evdev::Key); 11] = {
+        use crate::gamepad::buttons::*;
+        [
+            (A, evdev::Key::BTN_SOUTH),
+            (B, evdev::Key::BTN_EAST),
+            (X, evdev::Key::BTN_NORTH),
+            (Y, evdev::Key::BTN_WEST),
+            (LEFT_SHOULDER, evdev::Key::BTN_TL),
+            (RIGHT_SHOULDER, evdev::Key::BTN_TR),
+            (BACK, evdev::Key::BTN_SELECT),
+            (START, evdev::Key::BTN_START),
+            (GUIDE, evdev::Key::BTN_MODE),
+            (LEFT_THUMB, evdev::Key::BTN_THUMBL),
+            (RIGHT_THUMB, evdev::Key::BTN_THUMBR),
+        ]
+    };
+
+    fn emit_gamepad(
+        device: &mut VirtualDevice,
+        state: &crate::gamepad::GamepadState,
+    ) -> ResultType<()> {
+        use crate::gamepad::buttons::*;
+        use evdev::AbsoluteAxisType;
+
+        let hat = |negative: u16, positive: u16| {
+            (state.buttons & positive != 0) as i32 - (state.buttons & negative != 0) as i32
+        };
+        // Up is negative in evdev.
+        let invert = |v: i16| -(v as i32).max(-(i16::MAX as i32));
+        let mut events: Vec<InputEvent> = GAMEPAD_BUTTONS
+            .iter()
+            .map(|(bit, key)| {
+                InputEvent::new(
+                    EventType::KEY,
+                    key.code(),
+                    (state.buttons & bit != 0) as i32,
+                )
+            })
+            .collect();
+        for (axis, value) in [
+            (AbsoluteAxisType::ABS_X, state.thumb_lx as i32),
+            (AbsoluteAxisType::ABS_Y, invert(state.thumb_ly)),
+            (AbsoluteAxisType::ABS_RX, state.thumb_rx as i32),
+            (AbsoluteAxisType::ABS_RY, invert(state.thumb_ry)),
+            (AbsoluteAxisType::ABS_Z, state.left_trigger as i32),
+            (AbsoluteAxisType::ABS_RZ, state.right_trigger as i32),
+            (AbsoluteAxisType::ABS_HAT0X, hat(DPAD_LEFT, DPAD_RIGHT)),
+            (AbsoluteAxisType::ABS_HAT0Y, hat(DPAD_UP, DPAD_DOWN)),
+        ] {
+            events.push(InputEvent::new(EventType::ABSOLUTE, axis.0, value));
+        }
+        device.emit(&events)?;
+        Ok(())
+    }
+
+    fn spawn_gamepad_handler(mut stream: Connection) {
+        tokio::spawn(async move {
+            let mut devices: std::collections::HashMap<u8, VirtualDevice> = Default::default();
+            loop {
+                match stream.next().await {
+                    Err(err) => {
+                        log::info!("UInput gamepad ipc connection closed: {}", err);
+                        break;
+                    }
+                    Ok(Some(Data::Gamepad(event))) => {
+                        let Some(state) = event.state else {
+                            devices.remove(&event.index);
+                            continue;
+                        };
+                        if !devices.contains_key(&event.index) {
+                            match create_uinput_gamepad(event.index) {
+                                Ok(device) => {
+                                    log::info!("UInput gamepad {} created", event.index);
+                                    devices.insert(event.index, device);
+                                }
+                                Err(e) => {
+                                    log::error!("Failed to create gamepad {}", e);
+                                    break;
+                                }
+                            }
+                        }
+                        if let Some(device) = devices.get_mut(&event.index) {
+                            allow_err!(emit_gamepad(device, &state));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        });
+    }
+
+    // A direct tablet covering all the displays, as the pens of the screen tablets.
+    fn create_uinput_pen(rng_x: (i32, i32), rng_y: (i32, i32)) -> ResultType<VirtualDevice> {
+        use crate::pen::{MAX_PRESSURE, MAX_TILT};
+        use evdev::{AbsInfo, AbsoluteAxisType, PropType, UinputAbsSetup};
+
+        let mut keys = AttributeSet::<evdev::Key>::new();
+        for key in [
+            evdev::Key::BTN_TOOL_PEN,
+            evdev::Key::BTN_TOOL_RUBBER,
+            evdev::Key::BTN_TOUCH,
+            evdev::Key::BTN_STYLUS,
+        ] {
+            keys.insert(key);
+        }
+        let mut props = AttributeSet::<PropType>::new();
+        props.insert(PropType::DIRECT);
+        let mut builder = VirtualDeviceBuilder::new()?
+            .name("RustDesk UInput Pen")
+            .with_keys(&keys)?
+            .with_properties(&props)?;
+        for (axis, info) in [
+            (
+                AbsoluteAxisType::ABS_X,
+                AbsInfo::new(0, rng_x.0, rng_x.1, 0, 0, 1),
+            ),
+            (
+                AbsoluteAxisType::ABS_Y,
+                AbsInfo::new(0, rng_y.0, rng_y.1, 0, 0, 1),
+            ),
+            (
+                AbsoluteAxisType::ABS_PRESSURE,
+                AbsInfo::new(0, 0, MAX_PRESSURE as _, 0, 0, 0),
+            ),
+            (
+                AbsoluteAxisType::ABS_TILT_X,
+                AbsInfo::new(0, -MAX_TILT as _, MAX_TILT as _, 0, 0, 0),
+            ),
+            (
+                AbsoluteAxisType::ABS_TILT_Y,
+                AbsInfo::new(0, -MAX_TILT as _, MAX_TILT as _, 0, 0, 0),
+            ),
+        ] {
+            builder = builder.with_absolute_axis(&UinputAbsSetup::new(axis, info))?;
+        }
+        Ok(builder.build()?)
+    }
+
+    fn emit_pen(device: &mut VirtualDevice, event: &crate::pen::PenEvent) -> ResultType<()> {
+        use crate::pen::flags;
+        use evdev::AbsoluteAxisType;
+
+        let in_range = event.has(flags::IN_RANGE) || event.has(flags::CONTACT);
+        let rubber = event.has(flags::ERASER) || event.has(flags::INVERTED);
+        let key =
+            |key: evdev::Key, down: bool| InputEvent::new(EventType::KEY, key.code(), down as i32);
+        let abs = |axis: AbsoluteAxisType, value: i32| {
+            InputEvent::new(EventType::ABSOLUTE, axis.0, value)
+        };
+        let pressure = if event.has(flags::CONTACT) {
+            event.pressure as i32
+        } else {
+            0
+        };
+        device.emit(&[
+            abs(AbsoluteAxisType::ABS_X, event.x),
+            abs(AbsoluteAxisType::ABS_Y, event.y),
+            abs(AbsoluteAxisType::ABS_PRESSURE, pressure),
+            abs(AbsoluteAxisType::ABS_TILT_X, event.tilt_x as i32),
+            abs(AbsoluteAxisType::ABS_TILT_Y, event.tilt_y as i32),
+            key(evdev::Key::BTN_TOOL_PEN, in_range && !rubber),
+            key(evdev::Key::BTN_TOOL_RUBBER, in_range && rubber),
+            key(evdev::Key::BTN_TOUCH, event.has(flags::CONTACT)),
+            key(evdev::Key::BTN_STYLUS, event.has(flags::BARREL)),
+        ])?;
+        Ok(())
+    }
+
+    fn spawn_pen_handler(mut stream: Connection) {
+        tokio::spawn(async move {
+            let mut device: Option<VirtualDevice> = None;
+            loop {
+                match stream.next().await {
+                    Err(err) => {
+                        log::info!("UInput pen ipc connection closed: {}", err);
+                        break;
+                    }
+                    Ok(Some(Data::Control(ipc::DataControl::Resolution {
+                        minx,
+                        maxx,
+                        miny,
+                        maxy,
+                    }))) => match create_uinput_pen((minx, maxx), (miny, maxy)) {
+                        Ok(d) => {
+                            log::info!(
+                                "UInput pen created with rng_x: ({}, {}), rng_y: ({}, {})",
+                                minx,
+                                maxx,
+                                miny,
+                                maxy
+                            );
+                            device = Some(d);
+                        }
+                        Err(e) => {
+                            log::error!("Failed to create pen {}", e);
+                            break;
+                        }
+                    },
+                    Ok(Some(Data::Pen(event))) => {
+                        if let Some(device) = device.as_mut() {
+                            allow_err!(emit_pen(device, &event));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        });
+    }
+
     fn spawn_keyboard_handler(mut stream: Connection) {
         tokio::spawn(async move {
             let mut keyboard = match create_uinput_keyboard() {
@@ -758,6 +1049,20 @@ pub mod service {
         start_service(IPC_POSTFIX_CONTROL, spawn_controller_handler).await;
     }
 
+    /// Start uinput gamepad service.
+    #[tokio::main(flavor = "current_thread")]
+    pub async fn start_service_gamepad() {
+        log::info!("start uinput gamepad service");
+        start_service(IPC_POSTFIX_GAMEPAD, spawn_gamepad_handler).await;
+    }
+
+    /// Start uinput pen service.
+    #[tokio::main(flavor = "current_thread")]
+    pub async fn start_service_pen() {
+        log::info!("start uinput pen service");
+        start_service(IPC_POSTFIX_PEN, spawn_pen_handler).await;
+    }
+
     pub fn stop_service_keyboard() {
         log::info!("stop uinput keyboard service");
     }
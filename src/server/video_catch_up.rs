@@ -0,0 +1,103 @@
+//! The catch-up of the viewers joining a display which is already streamed.
+//!
+//! The video service of a display encodes once and sends the same frames to all its
+//! subscribers. A new subscriber used to restart the service, the capturer and the encoder, so
+//! that its first frame is a keyframe, which stalls the other viewers and costs a new encoder
+//! each time a viewer joins. Now the frames since the last keyframe are kept and sent to the new
+//! subscriber only, so it decodes the current picture without touching the encoder. If the group
+//! of pictures is too long to keep, the encoder is asked for a keyframe instead, and the service
+//! is only restarted if the encoder can't.
+use hbb_common::message_proto::{message, video_frame, Message};
+use std::sync::Arc;
+
+// Below the video queue of the client, `crate::client::VIDEO_QUEUE_SIZE`.
+const MAX_FRAMES: usize = 60;
+const MAX_BYTES: usize = 16 * 1024 * 1024;
+
+#[derive(Default)]
+pub struct CatchUp {
+    // The last keyframe and the frames after it.
+    frames: Vec<Arc<Message>>,
+    bytes: usize,
+}
+
+impl CatchUp {
+    pub fn on_frame(&mut self, msg: &Arc<Message>) {
+        let key = is_key_frame(msg);
+        if !key && self.frames.is_empty() {
+            return;
+        }
+        let size = msg.compute_size() as usize;
+        if key {
+            self.frames.clear();
+            self.bytes = 0;
+        } else if self.frames.len() >= MAX_FRAMES || self.bytes + size > MAX_BYTES {
+            // Wait for the next keyframe.
+            self.frames.clear();
+            self.bytes = 0;
+            return;
+        }
+        self.frames.push(msg.clone());
+        self.bytes += size;
+    }
+
+    /// The frames to send to a new subscriber, `None` if they're not kept.
+    pub fn frames(&self) -> Option<&[Arc<Message>]> {
+        if self.frames.is_empty() {
+            None
+        } else {
+            Some(&self.frames)
+        }
+    }
+}
+
+fn is_key_frame(msg: &Message) -> bool {
+    use video_frame::Union::*;
+    match &msg.union {
+        Some(message::Union::VideoFrame(vf)) => match &vf.union {
+            Some(Vp8s(f) | Vp9s(f) | Av1s(f) | H264s(f) | H265s(f)) => {
+                f.frames.first().map_or(false, |e| e.key)
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hbb_common::message_proto::{EncodedVideoFrame, EncodedVideoFrames, VideoFrame};
+
+    fn frame(key: bool) -> Arc<Message> {
+        let mut vf = VideoFrame::new();
+        vf.set_vp9s(EncodedVideoFrames {
+            frames: vec![EncodedVideoFrame {
+                key,
+                ..Default::default()
+            }],
+            ..Default::default()
+        });
+        let mut msg = Message::new();
+        msg.set_video_frame(vf);
+        Arc::new(msg)
+    }
+
+    #[test]
+    fn test_catch_up() {
+        let mut catch_up = CatchUp::default();
+        catch_up.on_frame(&frame(false));
+        assert!(catch_up.frames().is_none());
+        catch_up.on_frame(&frame(true));
+        catch_up.on_frame(&frame(false));
+        assert_eq!(catch_up.frames().unwrap().len(), 2);
+        catch_up.on_frame(&frame(true));
+        assert_eq!(catch_up.frames().unwrap().len(), 1);
+        for _ in 0..MAX_FRAMES {
+            catch_up.on_frame(&frame(false));
+        }
+        assert!(catch_up.frames().is_none());
+        catch_up.on_frame(&frame(false));
+        assert!(catch_up.frames().is_none());
+    }
+}
@@ -1,5 +1,12 @@
-use super::*;
-use scrap::codec::{Quality, BR_BALANCED, BR_BEST, BR_SPEED};
+use super::{
+    congestion::{CongestionController, Usage},
+    *,
+};
+use crate::client::feedback::ReceiverReport;
+use scrap::{
+    codec::{DisplaySettings, EncoderProfile, Quality, BR_BALANCED, BR_BEST, BR_SPEED},
+    CodecFormat,
+};
 use std::{
     collections::VecDeque,
     time::{Duration, Instant},
@@ -25,6 +32,11 @@ adjust between FPS and ratio:
 
 delay:
     use delay minus RTT as the actual network delay
+
+congestion control:
+    If the receivers report the delay gradient and loss with TestDelay, the ratio follows the
+    target bitrate of the congestion controller on each report instead of the delay thresholds.
+    When the link is still overused at the minimum ratio, the fps is decreased.
 */
 
 // Constants
@@ -93,6 +105,11 @@ struct UserData {
     quality: Option<(i64, Quality)>, // (time, quality)
     delay: UserDelay,
     record: bool,
+    bandwidth_cap: Option<u32>, // kbps
+    lossless: bool,
+    encoder_profile: EncoderProfile,
+    congestion: CongestionController,
+    display_settings: HashMap<String, (i64, DisplaySettings)>, // video service name -> (time, settings)
 }
 
 #[derive(Default, Debug, Clone)]
@@ -228,12 +245,47 @@ impl VideoQoS {
                 Quality::Custom(b.clamp(BR_MIN, BR_MAX))
             }
         };
+        let quality = convert_quality(image_quality);
+        let lossless =
+            quality.is_custom() && image_quality & scrap::codec::IMAGE_QUALITY_LOSSLESS_FLAG != 0;
 
-        let quality = Some((hbb_common::get_time(), convert_quality(image_quality)));
+        let quality = Some((hbb_common::get_time(), quality));
         if let Some(user) = self.users.get_mut(&id) {
             user.quality = quality;
+            user.lossless = lossless;
             // update ratio directly
-            self.ratio = self.latest_quality().ratio();
+            self.ratio = self.cap_ratio(self.latest_quality().ratio());
+        }
+    }
+
+    // Hard limit of the bitrate in kbps, None means no limit
+    pub fn user_bandwidth_cap(&mut self, id: i32, kbps: Option<u32>) {
+        if let Some(user) = self.users.get_mut(&id) {
+            user.bandwidth_cap = kbps;
+            self.ratio = self.cap_ratio(self.ratio);
+        }
+    }
+
+    pub fn user_encoder_profile(&mut self, id: i32, profile: EncoderProfile) {
+        if let Some(user) = self.users.get_mut(&id) {
+            user.encoder_profile = profile;
+        }
+    }
+
+    // The settings of a display overriding the session ones, the default settings remove the override.
+    pub fn user_display_settings(
+        &mut self,
+        id: i32,
+        video_service_name: String,
+        settings: DisplaySettings,
+    ) {
+        if let Some(user) = self.users.get_mut(&id) {
+            if settings.is_default() {
+                user.display_settings.remove(&video_service_name);
+            } else {
+                user.display_settings
+                    .insert(video_service_name, (hbb_common::get_time(), settings));
+            }
         }
     }
 
@@ -333,6 +385,41 @@ impl VideoQoS {
         }
     }
 
+    pub fn user_receiver_report(&mut self, id: i32, report: ReceiverReport) {
+        let current_bitrate = self.bitrate();
+        let Some(user) = self.users.get_mut(&id) else {
+            return;
+        };
+        if user.congestion.on_report(report, current_bitrate).is_none() {
+            return;
+        }
+        let overuse = user.congestion.usage() == Usage::Over;
+        if !self.in_vbr_state() {
+            return;
+        }
+        // Follow the lowest target of all users
+        let Some(target) = self
+            .users
+            .values()
+            .filter_map(|u| u.congestion.target_kbps())
+            .reduce(f32::min)
+        else {
+            return;
+        };
+        let min = self.min_ratio();
+        let max = self.latest_quality().ratio() * MAX_BR_MULTIPLE;
+        let v = self.ratio * target / current_bitrate as f32;
+        self.ratio = self.cap_ratio(v.clamp(min, max));
+        self.adjust_ratio_instant = Instant::now();
+        if overuse && v < min {
+            if let Some(user) = self.users.get_mut(&id) {
+                let fps = user.delay.fps.unwrap_or(INIT_FPS);
+                user.delay.fps = Some((fps * 3 / 4).max(MIN_FPS));
+            }
+            self.adjust_fps();
+        }
+    }
+
     pub fn user_delay_response_elapsed(&mut self, id: i32, elapsed: u128) {
         if let Some(user) = self.users.get_mut(&id) {
             user.delay.response_delayed = elapsed > 2000;
@@ -373,10 +460,24 @@ impl VideoQoS {
                 self.adjust_ratio(dynamic_screen);
             }
         } else {
-            self.ratio = self.latest_quality().ratio();
+            self.ratio = self.cap_ratio(self.latest_quality().ratio());
         }
     }
 
+    // Limit the ratio so that the estimated bitrate doesn't exceed the lowest bandwidth cap of all users
+    fn cap_ratio(&self, ratio: f32) -> f32 {
+        let Some(cap) = self.users.iter().filter_map(|u| u.1.bandwidth_cap).min() else {
+            return ratio;
+        };
+        let current_bitrate = self.bitrate();
+        if current_bitrate == 0 || self.ratio <= 0.0 {
+            return ratio;
+        }
+        // The bitrate is proportional to the ratio
+        let max = (self.ratio * cap as f32 / current_bitrate as f32).max(BR_MIN_HIGH_RESOLUTION);
+        ratio.min(max)
+    }
+
     #[inline]
     fn highest_fps(&self) -> u32 {
         let user_fps = |u: &UserData| {
@@ -401,6 +502,68 @@ impl VideoQoS {
     }
 
     // Get latest quality settings from all users
+    // Lossless if the latest quality is set to lossless.
+    pub fn lossless(&self) -> bool {
+        self.users
+            .values()
+            .filter(|u| u.quality.is_some())
+            .max_by_key(|u| u.quality.unwrap_or_default().0)
+            .map_or(false, |u| u.lossless)
+    }
+
+    // The encoder profile of the user who set the quality latest.
+    pub fn encoder_profile(&self) -> EncoderProfile {
+        self.users
+            .values()
+            .filter(|u| u.quality.is_some())
+            .max_by_key(|u| u.quality.unwrap_or_default().0)
+            .map_or(EncoderProfile::default(), |u| u.encoder_profile)
+    }
+
+    // The display settings set latest by all users.
+    pub fn display_settings(&self, video_service_name: &str) -> Option<DisplaySettings> {
+        self.users
+            .values()
+            .filter_map(|u| u.display_settings.get(video_service_name))
+            .max_by_key(|s| s.0)
+            .map(|s| s.1)
+    }
+
+    // The fps of the display, not higher than the one of the session.
+    pub fn display_spf(&self, video_service_name: &str) -> Duration {
+        let mut fps = self.fps();
+        if let Some(settings) = self.display_settings(video_service_name) {
+            if settings.fps >= MIN_FPS && settings.fps < fps {
+                fps = settings.fps;
+            }
+        }
+        Duration::from_secs_f32(1. / (fps as f32))
+    }
+
+    // The ratio of the display, scaled from the session one by the quality of the display,
+    // so that the adjustment of the session applies as well.
+    pub fn display_ratio(&mut self, video_service_name: &str) -> f32 {
+        let ratio = self.ratio();
+        let Some(settings) = self.display_settings(video_service_name) else {
+            return ratio;
+        };
+        if settings.quality <= 0 {
+            return ratio;
+        }
+        let display_ratio = (settings.quality * 2) as f32 / 100.0;
+        let session_ratio = self.latest_quality().ratio();
+        if session_ratio <= 0.0 {
+            return ratio;
+        }
+        (ratio * display_ratio / session_ratio).clamp(BR_MIN_HIGH_RESOLUTION, BR_MAX)
+    }
+
+    // The codec of the display, `None` follows the negotiation of the session.
+    pub fn display_codec(&self, video_service_name: &str) -> Option<CodecFormat> {
+        self.display_settings(video_service_name)
+            .and_then(|s| s.codec)
+    }
+
     pub fn latest_quality(&self) -> Quality {
         self.users
             .iter()
@@ -412,19 +575,8 @@ impl VideoQoS {
             .1
     }
 
-    // Adjust quality ratio based on network delay and screen changes
-    fn adjust_ratio(&mut self, dynamic_screen: bool) {
-        if !self.in_vbr_state() {
-            return;
-        }
-        // Get maximum delay from all users
-        let max_delay = self.users.iter().map(|u| u.1.delay.avg_delay()).max();
-        let Some(max_delay) = max_delay else {
-            return;
-        };
-
-        let target_quality = self.latest_quality();
-        let target_ratio = self.latest_quality().ratio();
+    // Minimum ratio of the adjustment based on the quality mode
+    fn min_ratio(&self) -> f32 {
         let current_ratio = self.ratio;
         let current_bitrate = self.bitrate();
 
@@ -435,15 +587,7 @@ impl VideoQoS {
             None
         };
 
-        // Calculate ratio for adding 150kbps bandwidth
-        let ratio_add_150kbps = if current_bitrate > 0 {
-            Some((current_bitrate + 150) as f32 * current_ratio / current_bitrate as f32)
-        } else {
-            None
-        };
-
-        // Set minimum ratio based on quality mode
-        let min = match target_quality {
+        match self.latest_quality() {
             Quality::Best => {
                 // For Best quality, ensure minimum 1Mbps for high resolution
                 let mut min = BR_BEST / 2.5;
@@ -465,7 +609,36 @@ impl VideoQoS {
             }
             Quality::Low => BR_MIN_HIGH_RESOLUTION,
             Quality::Custom(_) => BR_MIN_HIGH_RESOLUTION,
+        }
+    }
+
+    // Adjust quality ratio based on network delay and screen changes
+    fn adjust_ratio(&mut self, dynamic_screen: bool) {
+        if !self.in_vbr_state() {
+            return;
+        }
+        // Get maximum delay from all users
+        let max_delay = self.users.iter().map(|u| u.1.delay.avg_delay()).max();
+        let Some(max_delay) = max_delay else {
+            return;
         };
+        // Adjusted by the receiver reports
+        if self.users.values().all(|u| u.congestion.active()) {
+            return;
+        }
+
+        let target_ratio = self.latest_quality().ratio();
+        let current_ratio = self.ratio;
+        let current_bitrate = self.bitrate();
+
+        // Calculate ratio for adding 150kbps bandwidth
+        let ratio_add_150kbps = if current_bitrate > 0 {
+            Some((current_bitrate + 150) as f32 * current_ratio / current_bitrate as f32)
+        } else {
+            None
+        };
+
+        let min = self.min_ratio();
         let max = target_ratio * MAX_BR_MULTIPLE;
 
         let mut v = current_ratio;
@@ -503,7 +676,7 @@ impl VideoQoS {
             }
         }
 
-        self.ratio = v.clamp(min, max);
+        self.ratio = self.cap_ratio(v.clamp(min, max));
         self.adjust_ratio_instant = Instant::now();
     }
 
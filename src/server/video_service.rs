@@ -18,7 +18,10 @@
 // to-do:
 // https://slhck.info/video/2017/03/01/rate-control.html
 
-use super::{display_service::check_display_changed, service::ServiceTmpl, video_qos::VideoQoS, *};
+use super::{
+    display_service::check_display_changed, service::ServiceTmpl, video_catch_up::CatchUp,
+    video_qos::VideoQoS, *,
+};
 #[cfg(target_os = "linux")]
 use crate::common::SimpleCallOnReturn;
 #[cfg(target_os = "linux")]
@@ -32,7 +35,6 @@ use crate::{
 };
 use hbb_common::{
     anyhow::anyhow,
-    config,
     tokio::sync::{
         mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
         Mutex as TokioMutex,
@@ -46,7 +48,7 @@ use scrap::vram::{VRamEncoder, VRamEncoderConfig};
 use scrap::Capturer;
 use scrap::{
     aom::AomEncoderConfig,
-    codec::{Encoder, EncoderCfg},
+    codec::{Encoder, EncoderCfg, EncoderProfile, EncoderSelection},
     record::{Recorder, RecorderContext},
     vpxcodec::{VpxEncoderConfig, VpxVideoCodecId},
     CodecFormat, Display, EncodeInput, TraitCapturer, TraitPixelBuffer,
@@ -61,6 +63,11 @@ use std::{
 };
 
 pub const OPTION_REFRESH: &'static str = "refresh";
+pub const OPTION_KEYFRAME: &'static str = "keyframe";
+// The scene changes if most of the screen is damaged after a frame with small damage.
+const SCENE_CHANGE_RATIO: f32 = 0.9;
+const SCENE_STABLE_RATIO: f32 = 0.5;
+const MIN_SCENE_CHANGE_INTERVAL: Duration = Duration::from_secs(1);
 
 type FrameFetchedNotifierSender = UnboundedSender<(i32, Option<Instant>)>;
 type FrameFetchedNotifierReceiver = Arc<TokioMutex<UnboundedReceiver<(i32, Option<Instant>)>>>;
@@ -78,6 +85,8 @@ lazy_static::lazy_static! {
     pub static ref IS_UAC_RUNNING: Arc<Mutex<bool>> = Default::default();
     pub static ref IS_FOREGROUND_WINDOW_ELEVATED: Arc<Mutex<bool>> = Default::default();
     static ref SCREENSHOTS: Mutex<HashMap<usize, Screenshot>> = Default::default();
+    // display_idx -> the encoder in use and why the preferred ones are not used.
+    static ref ENCODER_SELECTIONS: Mutex<HashMap<usize, EncoderSelection>> = Default::default();
 }
 
 struct Screenshot {
@@ -569,12 +578,9 @@ fn run(vs: VideoService) -> ResultType<()> {
         c.set_gdi();
     }
     let mut video_qos = VIDEO_QOS.lock().unwrap();
-    let mut spf = video_qos.spf();
-    let mut quality = video_qos.ratio();
-    let record_incoming = config::option2bool(
-        "allow-auto-record-incoming",
-        &Config::get_option("allow-auto-record-incoming"),
-    );
+    let mut spf = video_qos.display_spf(&sp.name());
+    let mut quality = video_qos.display_ratio(&sp.name());
+    let record_incoming = super::recording_policy::is_enabled();
     let client_record = video_qos.record();
     drop(video_qos);
     let (mut encoder, encoder_cfg, codec_format, use_i444, recorder) = match setup_encoder(
@@ -596,6 +602,8 @@ fn run(vs: VideoService) -> ResultType<()> {
                 quality,
                 codec: VpxVideoCodecId::VP9,
                 keyframe_interval: None,
+                lossless: false,
+                profile: Default::default(),
             }));
             setup_encoder(
                 &c,
@@ -628,6 +636,13 @@ fn run(vs: VideoService) -> ResultType<()> {
     if sp.is_option_true(OPTION_REFRESH) {
         sp.set_option_bool(OPTION_REFRESH, false);
     }
+    // The first frame of the new encoder is a keyframe.
+    if sp.is_option_true(OPTION_KEYFRAME) {
+        sp.set_option_bool(OPTION_KEYFRAME, false);
+    }
+    let mut scene_change = SceneChangeDetector::default();
+    let mut catch_up = CatchUp::default();
+    let mut priority_region = None;
 
     let mut frame_controller = VideoFrameController::new(display_idx);
 
@@ -651,6 +666,11 @@ fn run(vs: VideoService) -> ResultType<()> {
     let capture_width = c.width;
     let capture_height = c.height;
     let (mut second_instant, mut send_counter) = (Instant::now(), 0);
+    let mut lossless_monitor = if is_lossless(&encoder_cfg) {
+        Some(LosslessMonitor::default())
+    } else {
+        None
+    };
 
     while sp.ok() {
         #[cfg(windows)]
@@ -671,11 +691,18 @@ fn run(vs: VideoService) -> ResultType<()> {
             log::info!("switch to refresh");
             bail!("SWITCH");
         }
-        if codec_format != Encoder::negotiated_codec() {
+        if sp.is_option_true(OPTION_KEYFRAME) {
+            sp.set_option_bool(OPTION_KEYFRAME, false);
+            if !encoder.request_keyframe() {
+                log::info!("switch to refresh, the encoder doesn't support requesting keyframes");
+                bail!("SWITCH");
+            }
+        }
+        if codec_format != display_codec(&sp.name()) {
             log::info!(
                 "switch due to codec changed, {:?} -> {:?}",
                 codec_format,
-                Encoder::negotiated_codec()
+                display_codec(&sp.name())
             );
             bail!("SWITCH");
         }
@@ -688,6 +715,20 @@ fn run(vs: VideoService) -> ResultType<()> {
             log::info!("switch due to i444 changed");
             bail!("SWITCH");
         }
+        if is_hw_av1(&encoder_cfg) && Encoder::prefer_i444() {
+            log::info!("switch to software av1 for i444");
+            bail!("SWITCH");
+        }
+        if is_lossless(&encoder_cfg) != VIDEO_QOS.lock().unwrap().lossless() {
+            log::info!("switch due to lossless changed");
+            bail!("SWITCH");
+        }
+        if let Some(profile) = encoder_profile(&encoder_cfg) {
+            if profile != VIDEO_QOS.lock().unwrap().encoder_profile() {
+                log::info!("switch due to encoder profile changed");
+                bail!("SWITCH");
+            }
+        }
         #[cfg(all(windows, feature = "vram"))]
         if c.is_gdi() && encoder.input_texture() {
             log::info!("changed to gdi when using vram");
@@ -764,6 +805,17 @@ fn run(vs: VideoService) -> ResultType<()> {
                         }
                     }
 
+                    if scene_change.detect(&frame) {
+                        encoder.request_keyframe();
+                    }
+                    encoder.set_damage(frame.damage());
+                    if vs.source.is_monitor() {
+                        let region = crate::priority_region::get(display_idx);
+                        if region != priority_region {
+                            priority_region = region;
+                            encoder.set_priority_region(region);
+                        }
+                    }
                     let frame = frame.to(encoder.yuvfmt(), &mut yuv, &mut mid_data)?;
                     let send_conn_ids = handle_one_frame(
                         display_idx,
@@ -776,6 +828,8 @@ fn run(vs: VideoService) -> ResultType<()> {
                         &mut first_frame,
                         capture_width,
                         capture_height,
+                        &mut lossless_monitor,
+                        &mut catch_up,
                     )?;
                     frame_controller.set_send(now, send_conn_ids);
                     send_counter += 1;
@@ -835,6 +889,8 @@ fn run(vs: VideoService) -> ResultType<()> {
                             &mut first_frame,
                             capture_width,
                             capture_height,
+                            &mut lossless_monitor,
+                            &mut catch_up,
                         )?;
                         frame_controller.set_send(now, send_conn_ids);
                         send_counter += 1;
@@ -842,6 +898,7 @@ fn run(vs: VideoService) -> ResultType<()> {
                 }
             }
             Err(err) => {
+                super::metrics::on_capture_error();
                 // This check may be redundant, but it is better to be safe.
                 // The previous check in `sp.is_option_true(OPTION_REFRESH)` block may be enough.
                 if vs.source.is_monitor() {
@@ -918,7 +975,9 @@ impl Drop for Raii {
         #[cfg(feature = "vram")]
         Encoder::update(scrap::codec::EncodingUpdate::Check);
         VIDEO_QOS.lock().unwrap().remove_display(&self.name);
+        super::metrics::remove_display(&self.name);
         DISPLAY_CONN_IDS.lock().unwrap().remove(&self.display_idx);
+        ENCODER_SELECTIONS.lock().unwrap().remove(&self.display_idx);
     }
 }
 
@@ -938,6 +997,7 @@ fn setup_encoder(
     bool,
     Arc<Mutex<Option<Recorder>>>,
 )> {
+    let mut skipped = vec![];
     let encoder_cfg = get_encoder_config(
         &c,
         name.to_string(),
@@ -945,63 +1005,133 @@ fn setup_encoder(
         client_record || record_incoming,
         last_portable_service_running,
         source,
+        &mut skipped,
     );
     Encoder::set_fallback(&encoder_cfg);
-    let codec_format = Encoder::negotiated_codec();
+    let codec_format = display_codec(&name);
     let recorder = get_recorder(record_incoming, display_idx, source == VideoSource::Camera);
     let use_i444 = Encoder::use_i444(&encoder_cfg);
-    let encoder = Encoder::new(encoder_cfg.clone(), use_i444)?;
+    let encoder = match Encoder::new(encoder_cfg.clone(), use_i444) {
+        Ok(encoder) => encoder,
+        Err(e) => {
+            // Kept for the selection of the fallback encoder.
+            ENCODER_SELECTIONS.lock().unwrap().insert(
+                display_idx,
+                EncoderSelection {
+                    skipped: vec![format!("{}: {}", encoder_cfg.name(), e)],
+                    ..Default::default()
+                },
+            );
+            return Err(e);
+        }
+    };
+    update_encoder_selection(
+        display_idx,
+        codec_format,
+        &encoder_cfg,
+        encoder.is_hardware(),
+        skipped,
+    );
     Ok((encoder, encoder_cfg, codec_format, use_i444, recorder))
 }
 
+fn update_encoder_selection(
+    display_idx: usize,
+    codec_format: CodecFormat,
+    encoder_cfg: &EncoderCfg,
+    hardware: bool,
+    mut skipped: Vec<String>,
+) {
+    let mut selections = ENCODER_SELECTIONS.lock().unwrap();
+    if let Some(failed) = selections.remove(&display_idx) {
+        if failed.encoder.is_empty() {
+            skipped.splice(0..0, failed.skipped);
+        }
+    }
+    let selection = EncoderSelection {
+        codec: format!("{:?}", codec_format),
+        encoder: encoder_cfg.name(),
+        hardware,
+        skipped,
+    };
+    log::info!(
+        "display {} uses encoder {}, hardware: {}, skipped: {:?}",
+        display_idx,
+        selection.encoder,
+        selection.hardware,
+        selection.skipped
+    );
+    selections.insert(display_idx, selection);
+}
+
+/// The encoders in use by the displays, in json.
+pub fn get_encoder_selections() -> String {
+    serde_json::to_string(&*ENCODER_SELECTIONS.lock().unwrap()).unwrap_or_default()
+}
+
 fn get_encoder_config(
     c: &CapturerInfo,
-    _name: String,
+    name: String,
     quality: f32,
     record: bool,
     _portable_service: bool,
     _source: VideoSource,
+    skipped: &mut Vec<String>,
 ) -> EncoderCfg {
     #[cfg(all(windows, feature = "vram"))]
     if _portable_service || c.is_gdi() || _source == VideoSource::Camera {
         log::info!("gdi:{}, portable:{}", c.is_gdi(), _portable_service);
-        VRamEncoder::set_not_use(_name, true);
+        VRamEncoder::set_not_use(name.clone(), true);
     }
     #[cfg(feature = "vram")]
     Encoder::update(scrap::codec::EncodingUpdate::Check);
     // https://www.wowza.com/community/t/the-correct-keyframe-interval-in-obs-studio/95162
-    let keyframe_interval = if record { Some(240) } else { None };
-    let negotiated_codec = Encoder::negotiated_codec();
+    let (lossless, profile) = {
+        let video_qos = VIDEO_QOS.lock().unwrap();
+        (video_qos.lossless(), video_qos.encoder_profile())
+    };
+    let keyframe_interval = if record {
+        Some(240)
+    } else {
+        profile.keyframe_interval()
+    };
+    let negotiated_codec = display_codec(&name);
+    if lossless {
+        // All the peers can decode vp9.
+        if negotiated_codec != CodecFormat::VP9 {
+            skipped.push(format!("{:?}: lossless mode uses vp9", negotiated_codec));
+        }
+        return EncoderCfg::VPX(VpxEncoderConfig {
+            width: c.width as _,
+            height: c.height as _,
+            quality,
+            codec: VpxVideoCodecId::VP9,
+            keyframe_interval,
+            lossless: true,
+            profile,
+        });
+    }
     match negotiated_codec {
         CodecFormat::H264 | CodecFormat::H265 => {
-            #[cfg(feature = "vram")]
-            if let Some(feature) = VRamEncoder::try_get(&c.device(), negotiated_codec) {
-                return EncoderCfg::VRAM(VRamEncoderConfig {
-                    device: c.device(),
-                    width: c.width,
-                    height: c.height,
-                    quality,
-                    feature,
-                    keyframe_interval,
-                });
-            }
-            #[cfg(feature = "hwcodec")]
-            if let Some(hw) = HwRamEncoder::try_get(negotiated_codec) {
-                return EncoderCfg::HWRAM(HwRamEncoderConfig {
-                    name: hw.name,
-                    mc_name: hw.mc_name,
-                    width: c.width,
-                    height: c.height,
-                    quality,
-                    keyframe_interval,
-                });
+            if let Some(cfg) = get_hw_encoder_config(
+                c,
+                negotiated_codec,
+                quality,
+                keyframe_interval,
+                profile,
+                skipped,
+            ) {
+                return cfg;
             }
+            skipped.push(format!("{:?}: no hardware encoder", negotiated_codec));
             EncoderCfg::VPX(VpxEncoderConfig {
                 width: c.width as _,
                 height: c.height as _,
                 quality,
                 codec: VpxVideoCodecId::VP9,
                 keyframe_interval,
+                lossless: false,
+                profile,
             })
         }
         format @ (CodecFormat::VP8 | CodecFormat::VP9) => EncoderCfg::VPX(VpxEncoderConfig {
@@ -1014,35 +1144,122 @@ fn get_encoder_config(
                 VpxVideoCodecId::VP9
             },
             keyframe_interval,
+            lossless: false,
+            profile,
         }),
-        CodecFormat::AV1 => EncoderCfg::AOM(AomEncoderConfig {
-            width: c.width as _,
-            height: c.height as _,
-            quality,
-            keyframe_interval,
-        }),
+        CodecFormat::AV1 => {
+            // The hardware encoders don't support 4:4:4.
+            #[cfg(feature = "hwcodec")]
+            if scrap::codec::enable_hwcodec_option() {
+                if Encoder::prefer_i444() {
+                    skipped.push("AV1: hardware encoders don't support 4:4:4".to_owned());
+                } else if let Some(cfg) = get_hw_encoder_config(
+                    c,
+                    negotiated_codec,
+                    quality,
+                    keyframe_interval,
+                    profile,
+                    skipped,
+                ) {
+                    return cfg;
+                }
+            }
+            EncoderCfg::AOM(AomEncoderConfig {
+                width: c.width as _,
+                height: c.height as _,
+                quality,
+                keyframe_interval,
+                profile,
+            })
+        }
         _ => EncoderCfg::VPX(VpxEncoderConfig {
             width: c.width as _,
             height: c.height as _,
             quality,
             codec: VpxVideoCodecId::VP9,
             keyframe_interval,
+            lossless: false,
+            profile,
         }),
     }
 }
 
+// The hardware encoder in the order of the encoder preference, or vram > hwram if it is not set.
+fn get_hw_encoder_config(
+    _c: &CapturerInfo,
+    _format: CodecFormat,
+    _quality: f32,
+    _keyframe_interval: Option<usize>,
+    _profile: EncoderProfile,
+    _skipped: &mut Vec<String>,
+) -> Option<EncoderCfg> {
+    #[cfg(feature = "vram")]
+    let vram_cfg = |feature| {
+        EncoderCfg::VRAM(VRamEncoderConfig {
+            device: _c.device(),
+            width: _c.width,
+            height: _c.height,
+            quality: _quality,
+            feature,
+            keyframe_interval: _keyframe_interval,
+        })
+    };
+    #[cfg(feature = "hwcodec")]
+    let hwram_cfg = |hw: scrap::hwcodec::CodecInfo| {
+        EncoderCfg::HWRAM(HwRamEncoderConfig {
+            name: hw.name,
+            mc_name: hw.mc_name,
+            width: _c.width,
+            height: _c.height,
+            quality: _quality,
+            keyframe_interval: _keyframe_interval,
+            profile: _profile,
+        })
+    };
+    let preference = scrap::codec::encoder_preference();
+    if !preference.is_empty() {
+        #[allow(unused_mut)]
+        let mut candidates: Vec<(&'static str, EncoderCfg)> = vec![];
+        #[cfg(feature = "vram")]
+        candidates.extend(
+            VRamEncoder::candidates(&_c.device(), _format)
+                .drain(..)
+                .map(|(kind, feature)| (kind, vram_cfg(feature))),
+        );
+        #[cfg(feature = "hwcodec")]
+        candidates.extend(
+            HwRamEncoder::candidates(_format)
+                .drain(..)
+                .map(|(kind, hw)| (kind, hwram_cfg(hw))),
+        );
+        return scrap::codec::select_by_preference(&preference, candidates, _skipped);
+    }
+    #[cfg(feature = "vram")]
+    match VRamEncoder::try_get(&_c.device(), _format) {
+        Some(feature) => return Some(vram_cfg(feature)),
+        None => _skipped.push("vram: not available".to_owned()),
+    }
+    #[cfg(feature = "hwcodec")]
+    match HwRamEncoder::try_get(_format) {
+        Some(hw) => return Some(hwram_cfg(hw)),
+        None => _skipped.push("hwram: not available".to_owned()),
+    }
+    None
+}
+
 fn get_recorder(
     record_incoming: bool,
     display_idx: usize,
     camera: bool,
 ) -> Arc<Mutex<Option<Recorder>>> {
-    #[cfg(windows)]
-    let root = crate::platform::is_root();
-    #[cfg(not(windows))]
-    let root = false;
     let recorder = if record_incoming {
-        use crate::hbbs_http::record_upload;
+        use super::recording_policy;
+        use crate::hbbs_http::{record_upload, s3_upload};
 
+        let dir = recording_policy::directory();
+        if !recording_policy::check_space(&dir) {
+            return Default::default();
+        }
         let tx = if record_upload::is_enable() {
             let (tx, rx) = std::sync::mpsc::channel();
             record_upload::run(rx);
@@ -1050,13 +1267,22 @@ fn get_recorder(
         } else {
             None
         };
+        let tx = match s3_upload::S3Config::load() {
+            Some(s3) => {
+                let (s3_tx, rx) = std::sync::mpsc::channel();
+                s3_upload::run(s3, rx, tx);
+                Some(s3_tx)
+            }
+            None => tx,
+        };
         Recorder::new(RecorderContext {
             server: true,
             id: Config::get_id(),
-            dir: crate::ui_interface::video_save_directory(root),
+            dir,
             display_idx,
             camera,
             tx,
+            template: recording_policy::template(),
         })
         .map_or(Default::default(), |r| Arc::new(Mutex::new(Some(r))))
     } else {
@@ -1137,21 +1363,44 @@ fn handle_one_frame(
     first_frame: &mut bool,
     width: usize,
     height: usize,
+    lossless_monitor: &mut Option<LosslessMonitor>,
+    catch_up: &mut CatchUp,
 ) -> ResultType<HashSet<i32>> {
+    let mut request_keyframe = false;
     sp.snapshot(|sps| {
-        // so that new sub and old sub share the same encoder after switch
-        if sps.has_subscribes() {
-            log::info!("switch due to new subscriber");
-            bail!("SWITCH");
+        // The new sub shares the encoder with the old subs, see `video_catch_up`.
+        if !sps.has_subscribes() {
+            return Ok(());
+        }
+        match catch_up.frames() {
+            Some(frames) => {
+                log::info!("new subscriber catches up with {} frames", frames.len());
+                for msg in frames {
+                    sps.send_shared(msg.clone());
+                }
+            }
+            None => request_keyframe = true,
         }
         Ok(())
     })?;
+    if request_keyframe && !encoder.request_keyframe() {
+        log::info!(
+            "switch due to new subscriber, the encoder doesn't support requesting keyframes"
+        );
+        bail!("SWITCH");
+    }
 
     let mut send_conn_ids: HashSet<i32> = Default::default();
     let first = *first_frame;
     *first_frame = false;
+    let encode_begin = Instant::now();
     match encoder.encode_to_message(frame, ms) {
         Ok(mut vf) => {
+            super::metrics::on_frame_encoded(encode_begin.elapsed());
+            crate::latency_probe::host_record(
+                crate::latency_probe::Stage::Encode,
+                encode_begin.elapsed(),
+            );
             *encode_fail_counter = 0;
             vf.display = display as _;
             let mut msg = Message::new();
@@ -1161,9 +1410,15 @@ fn handle_one_frame(
                 .unwrap()
                 .as_mut()
                 .map(|r| r.write_message(&msg, width, height));
-            send_conn_ids = sp.send_video_frame(msg);
+            if let Some(monitor) = lossless_monitor {
+                monitor.on_frame(sp, msg.compute_size());
+            }
+            let msg = Arc::new(msg);
+            catch_up.on_frame(&msg);
+            send_conn_ids = sp.send_video_frame_shared(msg);
         }
         Err(e) => {
+            super::metrics::on_encode_error();
             *encode_fail_counter += 1;
             // Encoding errors are not frequent except on Android
             if !cfg!(target_os = "android") {
@@ -1307,6 +1562,115 @@ pub fn make_display_changed_msg(
     Some(msg_out)
 }
 
+#[inline]
+fn is_lossless(encoder_cfg: &EncoderCfg) -> bool {
+    matches!(
+        encoder_cfg,
+        EncoderCfg::VPX(VpxEncoderConfig { lossless: true, .. })
+    )
+}
+
+// The profile of the encoders supporting it.
+fn encoder_profile(encoder_cfg: &EncoderCfg) -> Option<EncoderProfile> {
+    match encoder_cfg {
+        EncoderCfg::VPX(vpx) => Some(vpx.profile),
+        EncoderCfg::AOM(aom) => Some(aom.profile),
+        #[cfg(feature = "hwcodec")]
+        EncoderCfg::HWRAM(hw) => Some(hw.profile),
+        #[cfg(feature = "vram")]
+        EncoderCfg::VRAM(_) => None,
+    }
+}
+
+fn is_hw_av1(_encoder_cfg: &EncoderCfg) -> bool {
+    #[cfg(feature = "hwcodec")]
+    if let EncoderCfg::HWRAM(hw) = _encoder_cfg {
+        return hw.name.to_lowercase().contains("av1");
+    }
+    false
+}
+
+// Encode a keyframe when the scene changes, e.g. switching windows, the last frame is useless
+// as the reference.
+#[derive(Default)]
+struct SceneChangeDetector {
+    last_ratio: f32,
+    last_change: Option<Instant>,
+}
+
+impl SceneChangeDetector {
+    fn detect(&mut self, frame: &scrap::Frame) -> bool {
+        let (scrap::Frame::PixelBuffer(pixelbuffer), Some(damage)) = (frame, frame.damage()) else {
+            self.last_ratio = 1.0;
+            return false;
+        };
+        let area = damage.iter().map(|r| r.w * r.h).sum::<usize>();
+        let frame_area = (pixelbuffer.width() * pixelbuffer.height()).max(1);
+        let ratio = (area as f32 / frame_area as f32).min(1.0);
+        let changed = ratio >= SCENE_CHANGE_RATIO
+            && self.last_ratio < SCENE_STABLE_RATIO
+            && self
+                .last_change
+                .map_or(true, |t| t.elapsed() >= MIN_SCENE_CHANGE_INTERVAL);
+        self.last_ratio = ratio;
+        if changed {
+            self.last_change = Some(Instant::now());
+        }
+        changed
+    }
+}
+
+// The lossless stream is not limited by the bitrate, warn the peers when it is too high.
+#[derive(Default)]
+struct LosslessMonitor {
+    bytes: u64,
+    instant: Option<Instant>,
+    last_warning: Option<Instant>,
+}
+
+impl LosslessMonitor {
+    const WARNING_KBPS: u64 = 50_000;
+    const WARNING_INTERVAL: Duration = Duration::from_secs(60);
+
+    fn on_frame(&mut self, sp: &GenericService, size: u64) {
+        let instant = *self.instant.get_or_insert_with(Instant::now);
+        self.bytes += size;
+        let elapsed = instant.elapsed();
+        if elapsed < Duration::from_secs(1) {
+            return;
+        }
+        let kbps = self.bytes * 8 / elapsed.as_millis() as u64;
+        self.bytes = 0;
+        self.instant = Some(Instant::now());
+        if kbps <= Self::WARNING_KBPS
+            || self
+                .last_warning
+                .map_or(false, |t| t.elapsed() < Self::WARNING_INTERVAL)
+        {
+            return;
+        }
+        self.last_warning = Some(Instant::now());
+        log::warn!("lossless stream bitrate is high: {} kbps", kbps);
+        let mut msg_out = Message::new();
+        msg_out.set_message_box(MessageBox {
+            msgtype: "custom-nook-nocancel-hasclose".to_owned(),
+            title: "Lossless".to_owned(),
+            text: "lossless-high-bitrate-tip".to_owned(),
+            link: "".to_owned(),
+            ..Default::default()
+        });
+        sp.send(msg_out);
+    }
+}
+
+// The codec of the display if all the peers can use it, otherwise the negotiated one.
+fn display_codec(name: &str) -> CodecFormat {
+    match VIDEO_QOS.lock().unwrap().display_codec(name) {
+        Some(codec) if Encoder::usable(codec) => codec,
+        _ => Encoder::negotiated_codec(),
+    }
+}
+
 fn check_qos(
     encoder: &mut Encoder,
     ratio: &mut f32,
@@ -1317,9 +1681,9 @@ fn check_qos(
     name: &str,
 ) -> ResultType<()> {
     let mut video_qos = VIDEO_QOS.lock().unwrap();
-    *spf = video_qos.spf();
-    if *ratio != video_qos.ratio() {
-        *ratio = video_qos.ratio();
+    *spf = video_qos.display_spf(name);
+    if *ratio != video_qos.display_ratio(name) {
+        *ratio = video_qos.display_ratio(name);
         if encoder.support_changing_quality() {
             allow_err!(encoder.set_quality(*ratio));
             video_qos.store_bitrate(encoder.bitrate());
@@ -1336,6 +1700,10 @@ fn check_qos(
         bail!("SWITCH");
     }
     if second_instant.elapsed() > Duration::from_secs(1) {
+        super::metrics::set_fps(
+            name,
+            *send_counter as f64 / second_instant.elapsed().as_secs_f64(),
+        );
         *second_instant = Instant::now();
         video_qos.update_display_data(&name, *send_counter);
         *send_counter = 0;
@@ -0,0 +1,360 @@
+//! Install, uninstall and query the background service of Linux (systemd) and macOS (launchd)
+//! without the packaging scripts, e.g. for the host embedded in Unity to install its own service.
+//!
+//! The service runs `<exe> --service` like the one of the packages, `exe` is the current
+//! executable by default. On Linux the unit is written to `/etc/systemd/system`, overriding the
+//! packaged one. On macOS the daemon and the agent plists of the app are written with the paths
+//! of `exe`. The privileged commands are run directly as root, or with the password prompt of
+//! the platform.
+//!
+//! Used by `--manage-service` and `rustdesk_unity_service_*`.
+use hbb_common::{bail, config::Config, log, ResultType};
+use serde_derive::Serialize;
+use std::{path::Path, process::Command};
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct Status {
+    pub installed: bool,
+    pub running: bool,
+    /// The unit file or the daemon plist.
+    pub path: String,
+    /// The executable of the service, empty if not known.
+    pub exe: String,
+}
+
+fn current_exe() -> ResultType<String> {
+    Ok(std::env::current_exe()?.to_string_lossy().to_string())
+}
+
+/// Write `content` to a new file of the temporary directory, readable by the user only.
+///
+/// The name is random and the file is created exclusively, so an existing file or symlink
+/// of the name is never written through.
+fn write_temp(name: &str, content: &str) -> ResultType<String> {
+    use std::{io::Write, os::unix::fs::OpenOptionsExt};
+    let path = std::env::temp_dir().join(format!("{}_{}", uuid::Uuid::new_v4(), name));
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&path)?;
+    file.write_all(content.as_bytes())?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+#[inline]
+fn quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// Install and start the service running `exe`, the current executable if `None`.
+pub fn install(exe: Option<String>) -> ResultType<()> {
+    let exe = match exe.filter(|e| !e.is_empty()) {
+        Some(exe) => exe,
+        None => current_exe()?,
+    };
+    if !Path::new(&exe).is_file() {
+        bail!("Executable not found: {}", exe);
+    }
+    log::info!("Installing the service of {}", exe);
+    // Or the service stops itself once started.
+    Config::set_option("stop-service".into(), "".into());
+    imp::install(&exe)
+}
+
+pub fn uninstall() -> ResultType<()> {
+    log::info!("Uninstalling the service");
+    imp::uninstall()
+}
+
+pub fn status() -> Status {
+    imp::status()
+}
+
+#[cfg(target_os = "linux")]
+mod imp {
+    use super::*;
+
+    const UNIT_DIR: &str = "/etc/systemd/system";
+    const PACKAGED_UNIT_DIRS: [&str; 2] = ["/usr/lib/systemd/system", "/lib/systemd/system"];
+
+    fn name() -> String {
+        crate::get_app_name().to_lowercase()
+    }
+
+    fn unit_path() -> String {
+        format!("{}/{}.service", UNIT_DIR, name())
+    }
+
+    /// The pattern of `pkill -f` matching `exe`, as a double quoted argument of systemd.
+    fn pkill_pattern(exe: &str) -> String {
+        let mut pattern = String::new();
+        for c in format!("{} --", exe).chars() {
+            match c {
+                // Escaped for the regex, then for systemd.
+                '.' | '^' | '$' | '*' | '+' | '?' | '(' | ')' | '[' | ']' | '{' | '}' | '|' => {
+                    pattern.push_str(r"\\");
+                    pattern.push(c);
+                }
+                '\\' => pattern.push_str(r"\\\\"),
+                // Escaped for systemd only.
+                '"' => pattern.push_str(r#"\""#),
+                '%' => pattern.push_str("%%"),
+                _ => pattern.push(c),
+            }
+        }
+        format!("\"{}\"", pattern)
+    }
+
+    /// The unit of the package with the paths of `exe`.
+    pub(super) fn render_unit(exe: &str) -> String {
+        let name = name();
+        include_str!("../res/rustdesk.service")
+            .replace(
+                "Description=RustDesk",
+                &format!("Description={}", crate::get_app_name()),
+            )
+            .replace(
+                "/usr/bin/rustdesk --service",
+                &format!("{} --service", quote(exe)),
+            )
+            .replace(
+                "pkill -f \"rustdesk --\"",
+                &format!("pkill -f {}", pkill_pattern(exe)),
+            )
+            .replace("/run/rustdesk.pid", &format!("/run/{}.pid", name))
+    }
+
+    fn run_privileged(script: &str) -> ResultType<()> {
+        if crate::platform::is_root() {
+            let output = Command::new("sh").arg("-c").arg(script).output()?;
+            if !output.status.success() {
+                bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+            }
+        } else if !crate::platform::run_cmds_privileged(script) {
+            bail!("Failed to run the commands with the root privileges");
+        }
+        Ok(())
+    }
+
+    pub fn install(exe: &str) -> ResultType<()> {
+        if Command::new("systemctl").arg("--version").output().is_err() {
+            bail!("systemd is not available");
+        }
+        let tmp = write_temp("service", &render_unit(exe))?;
+        let name = name();
+        let res = run_privileged(&format!(
+            "install -m 644 {tmp} {unit} && systemctl daemon-reload && systemctl enable {name} && systemctl restart {name}",
+            tmp = quote(&tmp),
+            unit = unit_path(),
+        ));
+        std::fs::remove_file(&tmp).ok();
+        res
+    }
+
+    pub fn uninstall() -> ResultType<()> {
+        let name = name();
+        // The packaged unit is disabled only.
+        run_privileged(&format!(
+            "systemctl disable --now {name}; rm -f {unit}; systemctl daemon-reload",
+            unit = unit_path(),
+        ))
+    }
+
+    pub fn status() -> Status {
+        let name = name();
+        let path = std::iter::once(UNIT_DIR)
+            .chain(PACKAGED_UNIT_DIRS)
+            .map(|dir| format!("{}/{}.service", dir, name))
+            .find(|p| Path::new(p).exists());
+        let systemctl = |cmd: &str| {
+            Command::new("systemctl")
+                .args([cmd, "--quiet", &name])
+                .status()
+                .map(|s| s.success())
+                .unwrap_or(false)
+        };
+        let exe = path
+            .as_ref()
+            .and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|unit| {
+                unit.lines()
+                    .find_map(|l| l.strip_prefix("ExecStart="))
+                    .map(|l| {
+                        l.trim_end_matches("--service")
+                            .trim()
+                            .trim_matches('\'')
+                            .to_owned()
+                    })
+            })
+            .unwrap_or_default();
+        Status {
+            installed: path.is_some() && systemctl("is-enabled"),
+            running: systemctl("is-active"),
+            path: path.unwrap_or_default(),
+            exe,
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod imp {
+    use super::*;
+
+    fn label(kind: &str) -> String {
+        format!("{}_{}", crate::get_full_name(), kind)
+    }
+
+    fn daemon_path() -> String {
+        format!("/Library/LaunchDaemons/{}.plist", label("service"))
+    }
+
+    fn agent_path() -> String {
+        format!("/Library/LaunchAgents/{}.plist", label("server"))
+    }
+
+    fn escape_xml(s: &str) -> String {
+        s.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+    }
+
+    /// The plists of the app with the paths of `exe` instead of the bundle in `/Applications`.
+    pub(super) fn render_plists(daemon: &str, agent: &str, exe: &str) -> (String, String) {
+        let app_name = crate::get_app_name();
+        let bundle_dir = format!("/Applications/{}.app/Contents/MacOS/", app_name);
+        let dir = Path::new(exe)
+            .parent()
+            .map(|p| format!("{}/", p.to_string_lossy()))
+            .unwrap_or_default();
+        let daemon = daemon.replace(
+            &format!("{}service", bundle_dir),
+            &escape_xml(&format!("{} --service", quote(exe))),
+        );
+        let agent = agent.replace(&format!("{}{}", bundle_dir, app_name), &escape_xml(exe));
+        (
+            daemon.replace(&bundle_dir, &escape_xml(&dir)),
+            agent.replace(&bundle_dir, &escape_xml(&dir)),
+        )
+    }
+
+    fn run_privileged(script: &str) -> ResultType<()> {
+        let status = if crate::platform::is_root() {
+            Command::new("sh").arg("-c").arg(script).status()?
+        } else {
+            Command::new("osascript")
+                .arg("-e")
+                .arg(format!(
+                    "on run {{sh}}\ndo shell script sh with prompt \"{} wants to manage its service\" with administrator privileges\nend run",
+                    crate::get_app_name()
+                ))
+                .arg(script)
+                .status()?
+        };
+        if !status.success() {
+            bail!("Failed to run the commands with the administrator privileges");
+        }
+        Ok(())
+    }
+
+    pub fn install(exe: &str) -> ResultType<()> {
+        let Some((daemon, agent)) = crate::platform::macos::service_plists() else {
+            bail!("The service plists are not found");
+        };
+        let (daemon, agent) = render_plists(&daemon, &agent, exe);
+        let (daemon_tmp, agent_tmp) = (
+            write_temp("service.plist", &daemon)?,
+            write_temp("server.plist", &agent)?,
+        );
+        let res = run_privileged(&format!(
+            "launchctl unload -w {daemon} 2>/dev/null; install -m 644 -o root -g wheel {daemon_tmp} {daemon} && install -m 644 -o root -g wheel {agent_tmp} {agent} && launchctl load -w {daemon}",
+            daemon = daemon_path(),
+            agent = agent_path(),
+            daemon_tmp = quote(&daemon_tmp),
+            agent_tmp = quote(&agent_tmp),
+        ));
+        std::fs::remove_file(&daemon_tmp).ok();
+        std::fs::remove_file(&agent_tmp).ok();
+        res?;
+        // The agent runs in the session of the user.
+        if !crate::platform::is_root() {
+            Command::new("launchctl")
+                .args(["load", "-w", &agent_path()])
+                .status()
+                .ok();
+        }
+        Ok(())
+    }
+
+    pub fn uninstall() -> ResultType<()> {
+        run_privileged(&format!(
+            "launchctl unload -w {daemon}; rm -f {daemon} {agent}",
+            daemon = daemon_path(),
+            agent = agent_path(),
+        ))?;
+        Command::new("launchctl")
+            .args(["remove", &label("server")])
+            .status()
+            .ok();
+        Ok(())
+    }
+
+    pub fn status() -> Status {
+        let path = daemon_path();
+        let installed = Path::new(&path).exists() && Path::new(&agent_path()).exists();
+        let running = Command::new("launchctl")
+            .args(["print", &format!("system/{}", label("service"))])
+            .output()
+            .map(|o| o.status.success())
+            .unwrap_or(false);
+        let exe = std::fs::read_to_string(agent_path())
+            .ok()
+            .and_then(|agent| {
+                // The first string of ProgramArguments.
+                let args = agent.split("<key>ProgramArguments</key>").nth(1)?;
+                let start = args.find("<string>")? + "<string>".len();
+                let end = args[start..].find("</string>")?;
+                Some(args[start..start + end].to_owned())
+            })
+            .unwrap_or_default();
+        Status {
+            installed,
+            running,
+            path,
+            exe,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_render() {
+        let unit = imp::render_unit("/opt/my app/rustdesk");
+        assert!(unit.contains("ExecStart='/opt/my app/rustdesk' --service\n"));
+        assert!(unit.contains("ExecStop=pkill -f \"/opt/my app/rustdesk --\"\n"));
+        let unit = imp::render_unit(r#"/opt/a.b"c%/rustdesk"#);
+        assert!(unit.contains(r#"ExecStop=pkill -f "/opt/a\\.b\"c%%/rustdesk --""#));
+        assert!(!unit.contains("/usr/bin/rustdesk"));
+        assert!(unit.contains("WantedBy=multi-user.target"));
+    }
+
+    #[test]
+    #[cfg(target_os = "macos")]
+    fn test_render() {
+        let app_name = crate::get_app_name();
+        let (daemon, agent) = imp::render_plists(
+            &format!("<string>/Applications/{app_name}.app/Contents/MacOS/service</string><string>/Applications/{app_name}.app/Contents/MacOS/</string>"),
+            &format!("<string>/Applications/{app_name}.app/Contents/MacOS/{app_name}</string>"),
+            "/opt/a&b/host",
+        );
+        assert_eq!(
+            daemon,
+            "<string>'/opt/a&amp;b/host' --service</string><string>/opt/a&amp;b/</string>"
+        );
+        assert_eq!(agent, "<string>/opt/a&amp;b/host</string>");
+    }
+}
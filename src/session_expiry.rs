@@ -0,0 +1,147 @@
+//! Time-boxed approval of the incoming sessions, e.g. for the access of the contractors.
+//!
+//! With the option [`OPTION_TIME_LIMIT`] in minutes, an accepted session is granted only for
+//! that long. On the expiry, the host disconnects it, or with the option [`OPTION_EXPIRY_ACTION`]
+//! set to [`ACTION_VIEW_ONLY`], revokes all the permissions and keeps it for viewing only.
+//!
+//! The countdown is shown on both sides. The host sends the remaining time with a plugin request
+//! of the reserved id [`REQUEST_ID`] after the login, a few times before the expiry and on the
+//! expiry, and passes the expiry time to the connection manager.
+use hbb_common::{
+    config::Config,
+    message_proto::Message,
+    tokio::time::{Duration, Instant},
+};
+use serde_derive::{Deserialize, Serialize};
+
+pub const REQUEST_ID: &str = crate::common::plugin_request_id::SESSION_EXPIRY;
+pub const OPTION_TIME_LIMIT: &str = "session-time-limit";
+pub const OPTION_EXPIRY_ACTION: &str = "session-expiry-action";
+pub const ACTION_VIEW_ONLY: &str = "view-only";
+pub const CLOSE_REASON: &str = "Session expired";
+// The remaining seconds to notify at before the expiry.
+const NOTIFY_AT: [u64; 3] = [300, 60, 10];
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SessionExpiry {
+    /// The remaining seconds, 0 on the expiry.
+    pub remaining: u64,
+    /// [`ACTION_VIEW_ONLY`], or empty if disconnected on the expiry.
+    pub action: String,
+}
+
+impl SessionExpiry {
+    pub fn to_message(&self) -> Message {
+        crate::common::make_plugin_request(REQUEST_ID, serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.remaining == 0
+    }
+
+    pub fn is_view_only(&self) -> bool {
+        self.action == ACTION_VIEW_ONLY
+    }
+}
+
+/// The expiry of an accepted session on the host.
+pub struct ExpiryTimer {
+    start: Instant,
+    limit: Duration,
+    action: String,
+    // The next one of `NOTIFY_AT` to notify at.
+    next: usize,
+    expired: bool,
+}
+
+impl ExpiryTimer {
+    /// Start the timer on the acceptance of the session, `None` if there's no time limit.
+    pub fn start() -> Option<Self> {
+        let minutes: u64 = Config::get_option(OPTION_TIME_LIMIT).parse().unwrap_or(0);
+        if minutes == 0 {
+            return None;
+        }
+        let action = Config::get_option(OPTION_EXPIRY_ACTION);
+        let action = if action == ACTION_VIEW_ONLY {
+            action
+        } else {
+            "".to_owned()
+        };
+        Some(Self::new(Duration::from_secs(minutes * 60), action))
+    }
+
+    fn new(limit: Duration, action: String) -> Self {
+        Self {
+            start: Instant::now(),
+            next: NOTIFY_AT
+                .iter()
+                .position(|s| *s < limit.as_secs())
+                .unwrap_or(NOTIFY_AT.len()),
+            limit,
+            action,
+            expired: false,
+        }
+    }
+
+    /// The first notification, sent after the login.
+    pub fn current(&self) -> SessionExpiry {
+        self.notification(self.remaining(self.start.elapsed()))
+    }
+
+    /// The unix time of the expiry in seconds, for the connection manager.
+    pub fn expire_time(&self) -> i64 {
+        hbb_common::get_time() / 1000 + self.remaining(self.start.elapsed()) as i64
+    }
+
+    /// Called every second, returns the notification to send if any.
+    pub fn on_timer(&mut self) -> Option<SessionExpiry> {
+        self.check(self.start.elapsed())
+    }
+
+    fn check(&mut self, elapsed: Duration) -> Option<SessionExpiry> {
+        if self.expired {
+            return None;
+        }
+        let remaining = self.remaining(elapsed);
+        if remaining == 0 {
+            self.expired = true;
+            return Some(self.notification(0));
+        }
+        let mut notify = false;
+        while self.next < NOTIFY_AT.len() && remaining <= NOTIFY_AT[self.next] {
+            self.next += 1;
+            notify = true;
+        }
+        notify.then(|| self.notification(remaining))
+    }
+
+    fn remaining(&self, elapsed: Duration) -> u64 {
+        self.limit.saturating_sub(elapsed).as_secs()
+    }
+
+    fn notification(&self, remaining: u64) -> SessionExpiry {
+        SessionExpiry {
+            remaining,
+            action: self.action.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        let mut timer = ExpiryTimer::new(Duration::from_secs(120), "".to_owned());
+        assert_eq!(timer.check(Duration::from_secs(30)), None);
+        // 300 is skipped as the limit is shorter.
+        assert_eq!(timer.check(Duration::from_secs(61)).unwrap().remaining, 59);
+        assert_eq!(timer.check(Duration::from_secs(62)), None);
+        // Notify once if several are passed at the same time.
+        assert_eq!(timer.check(Duration::from_secs(115)).unwrap().remaining, 5);
+        assert_eq!(timer.check(Duration::from_secs(116)), None);
+        assert!(timer.check(Duration::from_secs(120)).unwrap().is_expired());
+        assert_eq!(timer.check(Duration::from_secs(121)), None);
+    }
+}
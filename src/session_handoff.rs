@@ -0,0 +1,160 @@
+//! Handoff of a live remote session to another device of the user without the re-authentication,
+//! e.g. from the desktop to the tablet.
+//!
+//! With the option [`OPTION_ENABLE`] of the host, the client in the session asks for a token with
+//! [`Handoff::Request`], and the host issues a single-use one valid for [`TOKEN_TTL_SECS`].
+//! The other device connects with the link `rustdesk://connection/new/<id>?handoff=<token>`,
+//! which saves the token in the peer option [`OPTION_TOKEN`], and sends it with
+//! [`Handoff::Redeem`] before the login request. The host logs it in without the password and
+//! the 2FA, and closes the original session, the audit log links both by their connection ids.
+//!
+//! The token is redeemed only while the original session is still authorized, and the new
+//! session gets at most its permissions, including the ones revoked or restricted by its
+//! permission profile after the token was issued.
+//!
+//! The wrong tokens are counted as the login failures of the password.
+use hbb_common::{
+    message_proto::Message,
+    tokio::time::{Duration, Instant},
+};
+use serde_derive::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::Mutex};
+
+pub const REQUEST_ID: &str = crate::common::plugin_request_id::SESSION_HANDOFF;
+pub const OPTION_ENABLE: &str = "enable-session-handoff";
+/// The peer option of the client to redeem the token on the next login.
+pub const OPTION_TOKEN: &str = "handoff-token";
+pub const TOKEN_TTL_SECS: u64 = 60;
+pub const CLOSE_REASON: &str = "Session handed off";
+const TOKEN_LEN: usize = 10;
+// Without the similar characters, for the manual input of the link.
+const TOKEN_CHARS: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "t", content = "c")]
+pub enum Handoff {
+    /// From the client in the session, for a token.
+    Request,
+    /// From the host, the token issued, or the error if empty.
+    Token {
+        token: String,
+        expires_in: u64,
+        error: String,
+    },
+    /// From the other device before the login request.
+    Redeem { token: String },
+}
+
+impl Handoff {
+    pub fn to_message(&self) -> Message {
+        crate::common::make_plugin_request(REQUEST_ID, serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    pub fn from_message(msg: &Message) -> Option<Self> {
+        serde_json::from_slice(crate::common::get_plugin_request(msg, REQUEST_ID)?).ok()
+    }
+}
+
+/// The session a token is issued for.
+#[derive(Debug, Clone)]
+pub struct Ticket {
+    pub conn_id: i32,
+    pub peer_id: String,
+    /// The names of the permissions enabled in the session, see
+    /// [`crate::permission_revocation::PERMISSIONS`], the new one is restricted to them.
+    pub permissions: Vec<String>,
+    issued: Instant,
+}
+
+lazy_static::lazy_static! {
+    static ref TICKETS: Mutex<HashMap<String, Ticket>> = Default::default();
+}
+
+#[inline]
+pub fn is_enabled() -> bool {
+    hbb_common::config::Config::get_option(OPTION_ENABLE) == "Y"
+}
+
+/// Issue a token for the session `conn_id` with its `permissions`, replacing its previous one.
+pub fn issue(conn_id: i32, peer_id: String, permissions: Vec<String>) -> String {
+    let token = crate::common::random_token(TOKEN_LEN, TOKEN_CHARS);
+    let mut tickets = TICKETS.lock().unwrap();
+    tickets.retain(|_, t| t.conn_id != conn_id && !is_expired(t));
+    tickets.insert(
+        token.clone(),
+        Ticket {
+            conn_id,
+            peer_id,
+            permissions,
+            issued: Instant::now(),
+        },
+    );
+    token
+}
+
+/// Update the permissions of the token of the session `conn_id` when they're switched.
+pub fn update_permissions(conn_id: i32, permissions: Vec<String>) {
+    if let Some(ticket) = TICKETS
+        .lock()
+        .unwrap()
+        .values_mut()
+        .find(|t| t.conn_id == conn_id)
+    {
+        ticket.permissions = permissions;
+    }
+}
+
+/// Take the ticket of the token, `None` if it's invalid or expired.
+pub fn redeem(token: &str) -> Option<Ticket> {
+    let token = token.trim().to_uppercase();
+    let ticket = TICKETS.lock().unwrap().remove(&token)?;
+    if is_expired(&ticket) {
+        None
+    } else {
+        Some(ticket)
+    }
+}
+
+/// The link to connect to the host `id` with the token on the other device.
+pub fn link(id: &str, token: &str) -> String {
+    format!(
+        "{}connection/new/{}?handoff={}",
+        crate::get_uri_prefix(),
+        id,
+        token
+    )
+}
+
+fn is_expired(ticket: &Ticket) -> bool {
+    ticket.issued.elapsed() >= Duration::from_secs(TOKEN_TTL_SECS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_issue_and_redeem() {
+        let token = issue(1, "123".to_owned(), vec![]);
+        assert_eq!(token.len(), TOKEN_LEN);
+        let token2 = issue(
+            1,
+            "123".to_owned(),
+            vec!["keyboard".to_owned(), "file".to_owned()],
+        );
+        // The previous token is replaced.
+        assert!(redeem(&token).is_none());
+        // Switched off after the token is issued.
+        update_permissions(1, vec!["keyboard".to_owned()]);
+        let ticket = redeem(&token2.to_lowercase()).unwrap();
+        assert_eq!(ticket.conn_id, 1);
+        assert_eq!(ticket.permissions, vec!["keyboard".to_owned()]);
+        // Single use.
+        assert!(redeem(&token2).is_none());
+        let msg = Handoff::Redeem { token: token2 }.to_message();
+        assert!(matches!(
+            Handoff::from_message(&msg),
+            Some(Handoff::Redeem { .. })
+        ));
+    }
+}
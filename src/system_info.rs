@@ -0,0 +1,178 @@
+//! The system information of the host, e.g. for the support staff to see the machine they're
+//! helping without asking the user.
+//!
+//! After the login of a remote session, the host sends [`SystemInfo`] with a plugin request of
+//! the reserved id [`REQUEST_ID`] if its permission [`OPTION_ENABLE`] is on, the default. The
+//! client saves it to the option [`OPTION_PEER`] of the peer config, so it's also in the peer
+//! info, and passes it to the ui with the event `update_system_info`.
+use hbb_common::message_proto::{DisplayInfo, Message};
+use serde_derive::{Deserialize, Serialize};
+
+pub const REQUEST_ID: &str = crate::common::plugin_request_id::SYSTEM_INFO;
+pub const OPTION_ENABLE: &str = "enable-system-info";
+/// The last [`SystemInfo`] of the peer in JSON.
+pub const OPTION_PEER: &str = "system-info";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Display {
+    pub name: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+    pub scale: f64,
+    pub primary: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SystemInfo {
+    /// e.g. `windows / Windows 11 Pro - 10.0.22631`.
+    pub os: String,
+    pub cpu: String,
+    pub cpu_cores: usize,
+    pub cpu_physical_cores: usize,
+    /// The models of the graphics adapters, empty if unknown.
+    pub gpus: Vec<String>,
+    /// Bytes.
+    pub memory: u64,
+    /// Seconds.
+    pub uptime: u64,
+    pub displays: Vec<Display>,
+}
+
+impl SystemInfo {
+    /// The info of this machine with the displays sent in the login response, it takes a while
+    /// to look up the graphics adapters the first time.
+    pub fn local(displays: &[DisplayInfo], primary: usize) -> Self {
+        use hbb_common::sysinfo::System;
+        let mut system = System::new();
+        system.refresh_memory();
+        system.refresh_cpu();
+        let mut os = format!(
+            "{} / {}",
+            system.distribution_id(),
+            system.long_os_version().unwrap_or_default()
+        );
+        #[cfg(windows)]
+        {
+            os = format!("{os} - {}", system.os_version().unwrap_or_default());
+        }
+        Self {
+            os,
+            cpu: system
+                .cpus()
+                .first()
+                .map(|c| c.brand().trim().to_owned())
+                .unwrap_or_default(),
+            cpu_cores: num_cpus::get(),
+            cpu_physical_cores: num_cpus::get_physical(),
+            gpus: GPUS.clone(),
+            memory: system.total_memory(),
+            uptime: system.uptime(),
+            displays: displays
+                .iter()
+                .enumerate()
+                .map(|(i, d)| Display {
+                    name: d.name.clone(),
+                    x: d.x,
+                    y: d.y,
+                    width: d.width,
+                    height: d.height,
+                    scale: d.scale,
+                    primary: i == primary,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn to_message(&self) -> Message {
+        crate::common::make_plugin_request(REQUEST_ID, serde_json::to_vec(self).unwrap_or_default())
+    }
+}
+
+lazy_static::lazy_static! {
+    // They don't change while running.
+    static ref GPUS: Vec<String> = gpus();
+}
+
+#[cfg(any(windows, target_os = "linux", target_os = "macos"))]
+fn command_output(program: &str, args: &[&str]) -> String {
+    let mut command = std::process::Command::new(program);
+    command.args(args);
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        command.creation_flags(winapi::um::winbase::CREATE_NO_WINDOW);
+    }
+    match command.output() {
+        Ok(output) => String::from_utf8_lossy(&output.stdout).into_owned(),
+        Err(e) => {
+            hbb_common::log::debug!("Failed to run {}: {}", program, e);
+            String::new()
+        }
+    }
+}
+
+fn gpus() -> Vec<String> {
+    #[cfg(windows)]
+    let gpus = parse_lines(
+        &command_output(
+            "powershell.exe",
+            &[
+                "-NoProfile",
+                "-Command",
+                "Get-CimInstance Win32_VideoController | ForEach-Object { $_.Name }",
+            ],
+        ),
+        "",
+    );
+    #[cfg(target_os = "linux")]
+    let gpus = command_output("lspci", &[])
+        .lines()
+        .filter(|l| l.contains("VGA compatible controller") || l.contains("3D controller"))
+        .filter_map(|l| l.splitn(2, ": ").nth(1).map(|s| s.trim().to_owned()))
+        .collect();
+    #[cfg(target_os = "macos")]
+    let gpus = parse_lines(
+        &command_output("system_profiler", &["SPDisplaysDataType"]),
+        "Chipset Model:",
+    );
+    #[cfg(not(any(windows, target_os = "linux", target_os = "macos")))]
+    let gpus = vec![];
+    gpus
+}
+
+// The trimmed lines starting with `prefix`, without it.
+#[cfg(any(windows, target_os = "macos", test))]
+fn parse_lines(output: &str, prefix: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|l| l.trim().strip_prefix(prefix))
+        .map(|l| l.trim().to_owned())
+        .filter(|l| !l.is_empty())
+        .collect()
+}
+
+/// Save the info of the host to the config of the peer, returns it in JSON for the ui.
+pub fn save(lc: &mut crate::client::LoginConfigHandler, info: &SystemInfo) -> String {
+    let json = serde_json::to_string(info).unwrap_or_default();
+    lc.set_option(OPTION_PEER.to_owned(), json.clone());
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_lines() {
+        let output = "Graphics/Displays:\n\n    Apple M1:\n\n      Chipset Model: Apple M1\n      Type: GPU\n";
+        assert_eq!(parse_lines(output, "Chipset Model:"), vec!["Apple M1"]);
+        assert_eq!(
+            parse_lines("NVIDIA GeForce RTX 3060\r\n\r\n", ""),
+            vec!["NVIDIA GeForce RTX 3060"]
+        );
+    }
+}
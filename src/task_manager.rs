@@ -0,0 +1,112 @@
+//! The processes of the host for the client to list and terminate, e.g. when a hung full-screen
+//! app covers the task manager of the host.
+//!
+//! The client sends [`TaskManager::List`] or [`TaskManager::Kill`] with a plugin request of the
+//! reserved id [`REQUEST_ID`], the host replies with [`TaskManager::Processes`] or
+//! [`TaskManager::Killed`]. It's allowed if the permission [`OPTION_ENABLE`] is on, the default,
+//! and the keyboard of the session is enabled, else the reply has the error. The processes
+//! killed are recorded to the audit log.
+use hbb_common::{
+    log,
+    message_proto::Message,
+    sysinfo::{Pid, System},
+};
+use serde_derive::{Deserialize, Serialize};
+use std::time::Duration;
+
+pub const REQUEST_ID: &str = crate::common::plugin_request_id::TASK_MANAGER;
+pub const OPTION_ENABLE: &str = "enable-task-manager";
+pub const ERR_DENIED: &str = "task-manager-denied-tip";
+// The CPU usage is measured between two refreshes.
+const CPU_SAMPLE_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Process {
+    pub pid: u32,
+    pub name: String,
+    /// The percent of one core, may be larger than 100 on multiple cores.
+    pub cpu: f32,
+    /// Bytes.
+    pub memory: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "t", content = "c")]
+pub enum TaskManager {
+    /// From the client.
+    List,
+    /// From the client.
+    Kill { pid: u32 },
+    /// From the host, the processes or the error.
+    Processes {
+        processes: Vec<Process>,
+        error: String,
+    },
+    /// From the host, the error is empty on success.
+    Killed { pid: u32, error: String },
+}
+
+impl TaskManager {
+    pub fn to_message(&self) -> Message {
+        crate::common::make_plugin_request(REQUEST_ID, serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    pub fn from_message(msg: &Message) -> Option<Self> {
+        serde_json::from_slice(crate::common::get_plugin_request(msg, REQUEST_ID)?).ok()
+    }
+}
+
+/// The processes of the host by the memory, it blocks to measure the CPU usage.
+pub fn list() -> Vec<Process> {
+    let mut system = System::new();
+    system.refresh_processes();
+    std::thread::sleep(CPU_SAMPLE_INTERVAL);
+    system.refresh_processes();
+    let mut processes: Vec<Process> = system
+        .processes()
+        .iter()
+        .map(|(pid, p)| Process {
+            pid: pid.as_u32(),
+            name: p.name().to_owned(),
+            cpu: p.cpu_usage(),
+            memory: p.memory(),
+        })
+        .collect();
+    processes.sort_by(|a, b| b.memory.cmp(&a.memory));
+    processes
+}
+
+/// Terminate the process, returns its name.
+pub fn kill(pid: u32) -> Result<String, String> {
+    if pid == std::process::id() {
+        return Err("It's the process of the remote desktop".to_owned());
+    }
+    let mut system = System::new();
+    system.refresh_processes();
+    let Some(process) = system.process(Pid::from_u32(pid)) else {
+        return Err(format!("No process {}", pid));
+    };
+    let name = process.name().to_owned();
+    if process.kill() {
+        log::info!("Killed process {} {}", pid, name);
+        Ok(name)
+    } else {
+        Err(format!("Failed to kill process {} {}", pid, name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message() {
+        let msg = TaskManager::Killed {
+            pid: 42,
+            error: "".to_owned(),
+        };
+        assert_eq!(TaskManager::from_message(&msg.to_message()), Some(msg));
+        assert!(kill(std::process::id()).is_err());
+    }
+}
@@ -0,0 +1,167 @@
+//! Rate limit of the file transfers, so a large copy doesn't starve the video stream
+//! of the sessions sharing a slow link.
+//!
+//! The limits are applied by the side reading the files: the client for the uploads,
+//! the host for the downloads, which receives the limits with plugin requests
+//! of the reserved id [`REQUEST_ID`].
+//! The global limit is the local option [`OPTION_TRANSFER_RATE_LIMIT`] of the client,
+//! it applies to all the jobs of the session together, the job limits to each job.
+use hbb_common::{config::LocalConfig, fs, message_proto::Message, ResultType, Stream};
+use serde_derive::{Deserialize, Serialize};
+use std::{collections::HashMap, time::Instant};
+
+pub const REQUEST_ID: &str = crate::common::plugin_request_id::TRANSFER_LIMIT;
+/// The global limit in KB/s, 0 or empty for no limit.
+pub const OPTION_TRANSFER_RATE_LIMIT: &str = "file-transfer-rate-limit";
+// The bytes saved while idle are limited to the ones of this part of a second.
+const BURST_DIV: f64 = 10.;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LimitRequest {
+    /// The job limited, `None` for the global limit.
+    pub id: Option<i32>,
+    /// The limit in KB/s, 0 for no limit.
+    pub kbps: u32,
+}
+
+impl LimitRequest {
+    pub fn to_message(&self) -> Message {
+        crate::common::make_plugin_request(REQUEST_ID, serde_json::to_vec(self).unwrap_or_default())
+    }
+}
+
+/// The global limit of the local option.
+pub fn get_global_limit() -> u32 {
+    LocalConfig::get_option(OPTION_TRANSFER_RATE_LIMIT)
+        .parse()
+        .unwrap_or(0)
+}
+
+struct Bucket {
+    // bytes per second
+    rate: f64,
+    // Negative after sending a block larger than the bytes allowed, the job waits for the refill.
+    tokens: f64,
+    last: Instant,
+}
+
+impl Bucket {
+    fn new(kbps: u32) -> Self {
+        Self {
+            rate: kbps as f64 * 1024.,
+            tokens: 0.,
+            last: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last).as_secs_f64();
+        self.tokens = (self.tokens + self.rate * elapsed).min(self.rate / BURST_DIV);
+        self.last = now;
+    }
+
+    fn is_open(&self) -> bool {
+        self.tokens >= 0.
+    }
+
+    fn take(&mut self, bytes: u64) {
+        self.tokens -= bytes as f64;
+    }
+}
+
+#[derive(Default)]
+pub struct TransferLimiter {
+    global: Option<Bucket>,
+    jobs: HashMap<i32, Bucket>,
+    // The bytes transferred by each job when last counted.
+    transferred: HashMap<i32, u64>,
+}
+
+impl TransferLimiter {
+    pub fn new(global_kbps: u32) -> Self {
+        let mut limiter = Self::default();
+        limiter.set_limit(None, global_kbps);
+        limiter
+    }
+
+    pub fn set_limit(&mut self, id: Option<i32>, kbps: u32) {
+        let bucket = (kbps > 0).then(|| Bucket::new(kbps));
+        match (id, bucket) {
+            (None, bucket) => self.global = bucket,
+            (Some(id), Some(bucket)) => {
+                self.jobs.insert(id, bucket);
+            }
+            (Some(id), None) => {
+                self.jobs.remove(&id);
+            }
+        }
+    }
+
+    pub fn remove_job(&mut self, id: i32) {
+        self.jobs.remove(&id);
+        self.transferred.remove(&id);
+    }
+
+    fn is_open(&mut self, id: i32) -> bool {
+        self.jobs.get_mut(&id).map_or(true, |bucket| {
+            bucket.refill();
+            bucket.is_open()
+        })
+    }
+
+    /// [`fs::handle_read_jobs`] for the jobs within their limits, the others wait for the next tick.
+    pub async fn handle_read_jobs(
+        &mut self,
+        jobs: &mut Vec<fs::TransferJob>,
+        stream: &mut Stream,
+    ) -> ResultType<String> {
+        if self.global.is_none() && self.jobs.is_empty() {
+            return fs::handle_read_jobs(jobs, stream).await;
+        }
+        let global_open = self.global.as_mut().map_or(true, |bucket| {
+            bucket.refill();
+            bucket.is_open()
+        });
+        let (mut active, paused): (Vec<_>, Vec<_>) = std::mem::take(jobs)
+            .into_iter()
+            .partition(|job| global_open && self.is_open(job.id()));
+        let res = if active.is_empty() {
+            Ok(String::new())
+        } else {
+            fs::handle_read_jobs(&mut active, stream).await
+        };
+        for job in active.iter() {
+            let transferred = job.transferred();
+            let last = self.transferred.insert(job.id(), transferred).unwrap_or(0);
+            let bytes = transferred.saturating_sub(last);
+            if let Some(bucket) = self.global.as_mut() {
+                bucket.take(bytes);
+            }
+            if let Some(bucket) = self.jobs.get_mut(&job.id()) {
+                bucket.take(bytes);
+            }
+        }
+        active.extend(paused);
+        *jobs = active;
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket() {
+        let mut bucket = Bucket::new(100);
+        assert!(bucket.is_open());
+        bucket.take(100 * 1024 / 2);
+        assert!(!bucket.is_open());
+        bucket.last -= std::time::Duration::from_secs(1);
+        bucket.refill();
+        assert!(bucket.is_open());
+        // The bytes saved are limited to the burst.
+        assert_eq!(bucket.tokens, 100. * 1024. / BURST_DIV);
+    }
+}
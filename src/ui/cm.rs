@@ -71,6 +71,18 @@ impl InvokeUiCM for SciterHandler {
     }
 
     fn file_transfer_log(&self, _action: &str, _log: &str) {}
+
+    fn update_session_expiry(&self, _client: &crate::ui_cm_interface::Client) {
+        // Ignore for sciter version.
+    }
+
+    fn update_permissions(&self, _client: &crate::ui_cm_interface::Client) {
+        // Ignore for sciter version.
+    }
+
+    fn update_command_requests(&self, _client: &crate::ui_cm_interface::Client) {
+        // Ignore for sciter version.
+    }
 }
 
 impl SciterHandler {
@@ -187,6 +187,9 @@ impl InvokeUiSession for SciterHandler {
 
     fn set_fingerprint(&self, _fingerprint: String) {}
 
+    // Ignore for sciter version.
+    fn set_session_fingerprint(&self, _fingerprint: String) {}
+
     fn job_error(&self, id: i32, err: String, file_num: i32) {
         self.call("jobError", &make_args!(id, err, file_num));
     }
@@ -306,10 +309,62 @@ impl InvokeUiSession for SciterHandler {
         // Ignore for sciter version.
     }
 
+    fn update_audio_sources(&self, _sources: &str) {
+        // Ignore for sciter version.
+    }
+
+    fn update_relative_mouse_mode(&self, _enabled: bool) {
+        // Ignore for sciter version.
+    }
+
+    fn keyboard_grab_released(&self) {
+        // Ignore for sciter version.
+    }
+
+    fn update_folder_sync(&self, _status: &str) {
+        // Ignore for sciter version.
+    }
+
+    fn update_session_expiry(&self, _remaining: u64, _view_only: bool) {
+        // Ignore for sciter version.
+    }
+
+    fn permissions_revoked(&self, _permissions: &[String], _reason: &str) {
+        // Ignore for sciter version.
+    }
+
+    fn update_system_info(&self, _info: &str) {
+        // Ignore for sciter version.
+    }
+
+    fn update_task_manager(&self, _reply: &str) {
+        // Ignore for sciter version.
+    }
+
+    fn update_remote_command(&self, _reply: &str) {
+        // Ignore for sciter version.
+    }
+
+    fn update_annotation(&self, _reply: &str) {
+        // Ignore for sciter version.
+    }
+
+    fn update_input_control(&self, _state: &str) {
+        // Ignore for sciter version.
+    }
+
+    fn on_handoff_token(&self, _link: &str, _token: &str, _expires_in: u64, _error: &str) {
+        // Ignore for sciter version.
+    }
+
     fn set_current_display(&self, _disp_idx: i32) {
         self.call("setCurrentDisplay", &make_args!(_disp_idx));
     }
 
+    fn update_windows_sessions(&self, _sessions: Vec<WindowsSession>) {
+        // Ignore for sciter version.
+    }
+
     fn set_multiple_windows_session(&self, sessions: Vec<WindowsSession>) {
         let mut v = Value::array(0);
         let mut sessions = sessions;
@@ -63,6 +63,15 @@ pub struct Client {
     pub from_switch: bool,
     pub in_voice_call: bool,
     pub incoming_voice_call: bool,
+    /// The unix time in seconds when the session expires, 0 if no time limit.
+    pub expire_time: i64,
+    pub expired: bool,
+    /// The fingerprint of the end-to-end key of the session, empty if not secured.
+    pub session_fingerprint: String,
+    /// Whether it controls the input with the policy of [`crate::input_arbitration`].
+    pub in_control: bool,
+    /// The commands waiting for the consent, see [`crate::remote_command`].
+    pub command_requests: Vec<crate::remote_command::Request>,
     #[serde(skip)]
     #[cfg(not(any(target_os = "ios")))]
     tx: UnboundedSender<Data>,
@@ -110,6 +119,12 @@ pub trait InvokeUiCM: Send + Clone + 'static + Sized {
     fn update_voice_call_state(&self, client: &Client);
 
     fn file_transfer_log(&self, action: &str, log: &str);
+
+    fn update_session_expiry(&self, client: &Client);
+
+    fn update_permissions(&self, client: &Client);
+
+    fn update_command_requests(&self, client: &Client);
 }
 
 impl<T: InvokeUiCM> Deref for ConnectionManager<T> {
@@ -145,6 +160,7 @@ impl<T: InvokeUiCM> ConnectionManager<T> {
         recording: bool,
         block_input: bool,
         from_switch: bool,
+        session_fingerprint: String,
         #[cfg(not(any(target_os = "ios")))] tx: mpsc::UnboundedSender<Data>,
     ) {
         let client = Client {
@@ -169,6 +185,11 @@ impl<T: InvokeUiCM> ConnectionManager<T> {
             tx,
             in_voice_call: false,
             incoming_voice_call: false,
+            expire_time: 0,
+            expired: false,
+            session_fingerprint,
+            in_control: false,
+            command_requests: vec![],
         };
         CLIENTS
             .write()
@@ -255,6 +276,75 @@ impl<T: InvokeUiCM> ConnectionManager<T> {
             self.ui_handler.update_voice_call_state(client);
         }
     }
+
+    #[cfg(not(target_os = "ios"))]
+    fn session_expiry_changed(&self, id: i32, expire_time: i64, expired: bool) {
+        if let Some(client) = CLIENTS.write().unwrap().get_mut(&id) {
+            if expired {
+                // Revoked by the connection if not disconnected.
+                client.expired = true;
+                client.keyboard = false;
+                client.clipboard = false;
+                client.audio = false;
+                client.file = false;
+                client.restart = false;
+                client.recording = false;
+                client.block_input = false;
+            } else {
+                client.expire_time = expire_time;
+            }
+            self.ui_handler.update_session_expiry(client);
+        }
+    }
+
+    #[cfg(not(target_os = "ios"))]
+    fn permissions_revoked(&self, id: i32, names: &[String]) {
+        if let Some(client) = CLIENTS.write().unwrap().get_mut(&id) {
+            for name in names {
+                match name.as_str() {
+                    "keyboard" => client.keyboard = false,
+                    "clipboard" => client.clipboard = false,
+                    "audio" => client.audio = false,
+                    "file" => client.file = false,
+                    "restart" => client.restart = false,
+                    "recording" => client.recording = false,
+                    "block_input" => client.block_input = false,
+                    _ => {}
+                }
+            }
+            self.ui_handler.update_permissions(client);
+        }
+    }
+
+    #[cfg(not(target_os = "ios"))]
+    fn input_control_changed(&self, id: i32, state: crate::input_arbitration::InputControl) {
+        if let crate::input_arbitration::InputControl::State {
+            controller, you, ..
+        } = state
+        {
+            if let Some(client) = CLIENTS.write().unwrap().get_mut(&id) {
+                client.in_control = controller != 0 && controller == you;
+                self.ui_handler.update_permissions(client);
+            }
+        }
+    }
+
+    #[cfg(not(target_os = "ios"))]
+    fn command_requested(&self, id: i32, request: crate::remote_command::Request) {
+        if let Some(client) = CLIENTS.write().unwrap().get_mut(&id) {
+            client.command_requests.retain(|r| r.id != request.id);
+            client.command_requests.push(request);
+            self.ui_handler.update_command_requests(client);
+        }
+    }
+
+    #[cfg(not(target_os = "ios"))]
+    fn command_cancelled(&self, id: i32, command_id: u32) {
+        if let Some(client) = CLIENTS.write().unwrap().get_mut(&id) {
+            client.command_requests.retain(|r| r.id != command_id);
+            self.ui_handler.update_command_requests(client);
+        }
+    }
 }
 
 #[inline]
@@ -310,6 +400,14 @@ pub fn switch_permission(id: i32, name: String, enabled: bool) {
     };
 }
 
+#[inline]
+#[cfg(not(any(target_os = "ios")))]
+pub fn assign_input_control(id: i32) {
+    if let Some(client) = CLIENTS.read().unwrap().get(&id) {
+        allow_err!(client.tx.send(Data::AssignInputControl));
+    };
+}
+
 #[inline]
 #[cfg(target_os = "android")]
 pub fn switch_permission_all(name: String, enabled: bool) {
@@ -409,9 +507,9 @@ impl<T: InvokeUiCM> IpcTaskRunner<T> {
                         }
                         Ok(Some(data)) => {
                             match data {
-                                Data::Login{id, is_file_transfer, is_view_camera, is_terminal, port_forward, peer_id, name, authorized, keyboard, clipboard, audio, file, file_transfer_enabled: _file_transfer_enabled, restart, recording, block_input, from_switch} => {
+                                Data::Login{id, is_file_transfer, is_view_camera, is_terminal, port_forward, peer_id, name, authorized, keyboard, clipboard, audio, file, file_transfer_enabled: _file_transfer_enabled, restart, recording, block_input, from_switch, session_fingerprint} => {
                                     log::debug!("conn_id: {}", id);
-                                    self.cm.add_connection(id, is_file_transfer, is_view_camera, is_terminal, port_forward, peer_id, name, authorized, keyboard, clipboard, audio, file, restart, recording, block_input, from_switch, self.tx.clone());
+                                    self.cm.add_connection(id, is_file_transfer, is_view_camera, is_terminal, port_forward, peer_id, name, authorized, keyboard, clipboard, audio, file, restart, recording, block_input, from_switch, session_fingerprint, self.tx.clone());
                                     self.conn_id = id;
                                     #[cfg(target_os = "windows")]
                                     {
@@ -502,6 +600,21 @@ impl<T: InvokeUiCM> IpcTaskRunner<T> {
                                 Data::CloseVoiceCall(reason) => {
                                     self.cm.voice_call_closed(self.conn_id, reason.as_str());
                                 }
+                                Data::SessionExpiry { expire_time, expired } => {
+                                    self.cm.session_expiry_changed(self.conn_id, expire_time, expired);
+                                }
+                                Data::RevokePermissions { names, .. } => {
+                                    self.cm.permissions_revoked(self.conn_id, &names);
+                                }
+                                Data::InputControl(state) => {
+                                    self.cm.input_control_changed(self.conn_id, state);
+                                }
+                                Data::RemoteCommand(request) => {
+                                    self.cm.command_requested(self.conn_id, request);
+                                }
+                                Data::RemoteCommandCancelled(command_id) => {
+                                    self.cm.command_cancelled(self.conn_id, command_id);
+                                }
                                 #[cfg(target_os = "windows")]
                                 Data::ClipboardNonFile(_) => {
                                     match crate::clipboard::check_clipboard_cm() {
@@ -693,6 +806,7 @@ pub async fn start_listen<T: InvokeUiCM>(
                 recording,
                 block_input,
                 from_switch,
+                session_fingerprint,
                 ..
             }) => {
                 current_id = id;
@@ -713,6 +827,7 @@ pub async fn start_listen<T: InvokeUiCM>(
                     recording,
                     block_input,
                     from_switch,
+                    session_fingerprint,
                     tx.clone(),
                 );
             }
@@ -734,6 +849,24 @@ pub async fn start_listen<T: InvokeUiCM>(
             Some(Data::CloseVoiceCall(reason)) => {
                 cm.voice_call_closed(current_id, reason.as_str());
             }
+            Some(Data::SessionExpiry {
+                expire_time,
+                expired,
+            }) => {
+                cm.session_expiry_changed(current_id, expire_time, expired);
+            }
+            Some(Data::RevokePermissions { names, .. }) => {
+                cm.permissions_revoked(current_id, &names);
+            }
+            Some(Data::InputControl(state)) => {
+                cm.input_control_changed(current_id, state);
+            }
+            Some(Data::RemoteCommand(request)) => {
+                cm.command_requested(current_id, request);
+            }
+            Some(Data::RemoteCommandCancelled(command_id)) => {
+                cm.command_cancelled(current_id, command_id);
+            }
             None => {
                 break;
             }
@@ -825,6 +958,8 @@ async fn handle_fs(
             if let Some(job) = fs::remove_job(id, write_jobs) {
                 job.modify_time();
                 send_raw(fs::new_done(id, file_num), tx);
+                // Tell the connection the files are written, for the audit log.
+                allow_err!(tx.send(Data::FS(ipc::FS::WriteDone { id, file_num })));
                 tx_log.map(|tx| tx.send(serialize_transfer_job(&job, true, false, "")));
             }
         }
@@ -919,6 +1054,39 @@ async fn handle_fs(
                 }
             }
         }
+        ipc::FS::Checksum { id, file_num, size } => {
+            use crate::file_checksum::{partial_path, FileChecksum};
+
+            let path = fs::get_job(id, write_jobs).and_then(|job| {
+                match (&job.data_source, job.files().get(file_num as usize)) {
+                    (fs::DataSource::FilePath(p), Some(file)) => Some(partial_path(&get_string(
+                        &fs::TransferJob::join(p, &file.name),
+                    ))),
+                    _ => None,
+                }
+            });
+            let response = match path {
+                Some(path) => {
+                    spawn_blocking(move || FileChecksum::respond(id, file_num, &path, size))
+                        .await
+                        .unwrap_or_else(|_| FileChecksum::empty(id, file_num))
+                }
+                None => FileChecksum::empty(id, file_num),
+            };
+            send_raw(response.to_message(), &tx);
+        }
+        ipc::FS::FolderSync(content) => {
+            use crate::folder_sync::{handle_write, SyncMessage};
+
+            let res = spawn_blocking(move || {
+                let (msg, data) = SyncMessage::decode(&content)?;
+                handle_write(msg, data)
+            })
+            .await;
+            if let Ok(Some(res)) = res {
+                send_raw(res.to_message(&[]), &tx);
+            }
+        }
         ipc::FS::Rename { id, path, new_name } => {
             rename_file(path, new_name, id, tx).await;
         }
@@ -1089,6 +1257,19 @@ pub fn handle_incoming_voice_call(id: i32, accept: bool) {
     };
 }
 
+#[cfg(any(target_os = "android", target_os = "ios", feature = "flutter"))]
+pub fn handle_remote_command(id: i32, command_id: u32, approved: bool) {
+    if let Some(client) = CLIENTS.write().unwrap().get_mut(&id) {
+        client.command_requests.retain(|r| r.id != command_id);
+        // Not handled in iOS yet.
+        #[cfg(not(any(target_os = "ios")))]
+        allow_err!(client.tx.send(Data::RemoteCommandResponse {
+            id: command_id,
+            approved
+        }));
+    };
+}
+
 #[cfg(any(target_os = "android", target_os = "ios", feature = "flutter"))]
 #[inline]
 pub fn close_voice_call(id: i32) {
@@ -338,6 +338,38 @@ pub fn test_if_valid_server(host: String, test_with_proxy: bool) -> String {
     hbb_common::socket_client::test_if_valid_server(&host, test_with_proxy)
 }
 
+/// Connect to the rendezvous server through the proxy with the credentials before saving them,
+/// returns the error, e.g. the authentication failure, or empty if the proxy works.
+#[tokio::main(flavor = "current_thread")]
+pub async fn test_proxy(proxy: String, username: String, password: String) -> String {
+    let conf = config::Socks5Server {
+        proxy,
+        username,
+        password,
+    };
+    let proxy = match hbb_common::proxy::Proxy::from_conf(&conf, Some(CONNECT_TIMEOUT)) {
+        Ok(proxy) => proxy,
+        Err(e) => return e.to_string(),
+    };
+    #[cfg(not(any(target_os = "android", target_os = "ios")))]
+    let rendezvous_servers = crate::ipc::get_rendezvous_servers(1_000).await;
+    #[cfg(any(target_os = "android", target_os = "ios"))]
+    let rendezvous_servers = Config::get_rendezvous_servers();
+    let Some(server) = rendezvous_servers.into_iter().next() else {
+        return "".to_owned();
+    };
+    match proxy
+        .connect(crate::check_port(server, RENDEZVOUS_PORT), None)
+        .await
+    {
+        Ok(_) => "".to_owned(),
+        Err(e) => {
+            log::info!("Failed to connect through the proxy: {}", e);
+            e.to_string()
+        }
+    }
+}
+
 #[inline]
 #[cfg(feature = "flutter")]
 #[cfg(not(any(target_os = "android", target_os = "ios")))]
@@ -729,14 +761,29 @@ pub fn discover() {
 #[cfg(feature = "flutter")]
 pub fn peer_to_map(id: String, p: PeerConfig) -> HashMap<&'static str, String> {
     use hbb_common::sodiumoxide::base64;
+    let metadata = crate::peer_metadata::PeerMetadata::from_options(&p.options);
+    let usage = crate::peer_usage::get(&id);
     HashMap::<&str, String>::from_iter([
         ("id", id),
         ("username", p.info.username.clone()),
         ("hostname", p.info.hostname.clone()),
         ("platform", p.info.platform.clone()),
+        ("alias", metadata.alias),
+        ("note", metadata.note),
+        // A JSON list.
+        (
+            "tags",
+            serde_json::to_string(&metadata.tags).unwrap_or_default(),
+        ),
+        // A JSON object of `peer_usage::PeerUsage`.
+        ("usage", serde_json::to_string(&usage).unwrap_or_default()),
+        // The JSON of `system_info::SystemInfo`, empty if it's never received.
         (
-            "alias",
-            p.options.get("alias").unwrap_or(&"".to_owned()).to_owned(),
+            "system_info",
+            p.options
+                .get(crate::system_info::OPTION_PEER)
+                .cloned()
+                .unwrap_or_default(),
         ),
         (
             "hash",
@@ -1475,6 +1522,131 @@ pub fn verify_bot(token: String) -> String {
     }
 }
 
+/// The built-in and custom permission profiles, as a JSON map of the names to the permissions.
+pub fn get_permission_profiles() -> String {
+    use crate::server::permission_profile::{get_profiles, OPTION_PROFILES};
+    serde_json::to_string(&get_profiles(&get_option(OPTION_PROFILES))).unwrap_or_default()
+}
+
+/// Validate the access windows and their time zone, the error or empty if they're valid.
+pub fn check_access_windows(windows: String, timezone: String) -> String {
+    match crate::server::access_schedule::check(&windows, &timezone) {
+        Ok(()) => "".to_owned(),
+        Err(e) => e.to_string(),
+    }
+}
+
+/// Assign the permission `profile` to the peer id or the address book tag `key`,
+/// `kind` is "peer" or "tag", an empty `profile` removes the assignment.
+pub fn set_permission_profile_assignment(kind: String, key: String, profile: String) {
+    use crate::server::permission_profile::{Assignments, OPTION_ASSIGNMENTS};
+    let mut assignments = Assignments::load(&get_option(OPTION_ASSIGNMENTS));
+    let map = match kind.as_str() {
+        "peer" => &mut assignments.peers,
+        "tag" => &mut assignments.tags,
+        _ => return,
+    };
+    if profile.is_empty() {
+        map.remove(&key);
+    } else {
+        map.insert(key, profile);
+    }
+    set_option(
+        OPTION_ASSIGNMENTS.to_owned(),
+        serde_json::to_string(&assignments).unwrap_or_default(),
+    );
+}
+
+/// The entries of the audit log matching the JSON of `audit_log::Query`, as a JSON array.
+pub fn get_audit_log(query: String) -> String {
+    #[cfg(not(target_os = "ios"))]
+    {
+        let query = serde_json::from_str(&query).unwrap_or_default();
+        // The log is written by the server, which is in this process on Android.
+        #[cfg(target_os = "android")]
+        let res = crate::server::audit_log::query(&query);
+        #[cfg(not(target_os = "android"))]
+        let res = ipc::get_audit_log(query);
+        match res {
+            Ok(entries) => return serde_json::to_string(&entries).unwrap_or_default(),
+            Err(e) => log::error!("Failed to query the audit log: {}", e),
+        }
+    }
+    "[]".to_owned()
+}
+
+/// The connection history matching the JSON of `connection_history::Query`, as a JSON array.
+pub fn get_connection_history(query: String) -> String {
+    let query = serde_json::from_str(&query).unwrap_or_default();
+    serde_json::to_string(&crate::connection_history::query_all(&query)).unwrap_or_default()
+}
+
+/// The names of the security keys registered, as a JSON array.
+pub fn get_fido2_keys() -> String {
+    let credentials =
+        crate::auth_fido2::parse_credentials(&get_option(crate::auth_fido2::OPTION_CREDENTIALS));
+    let names: Vec<_> = credentials.into_iter().map(|c| c.name).collect();
+    serde_json::to_string(&names).unwrap_or_default()
+}
+
+/// Register the security key plugged in, it blocks until the user touches the key.
+/// Returns the error, empty on success.
+pub fn register_fido2_key(name: String, pin: String) -> String {
+    #[cfg(all(feature = "fido2", not(any(target_os = "android", target_os = "ios"))))]
+    {
+        let key = crate::auth_fido2::OPTION_CREDENTIALS;
+        let mut credentials = crate::auth_fido2::parse_credentials(&get_option(key));
+        if name.is_empty() || credentials.iter().any(|c| c.name == name) {
+            return "Invalid name".to_owned();
+        }
+        match crate::auth_fido2::make_credential(name, &pin) {
+            Ok(credential) => {
+                credentials.push(credential);
+                set_option(
+                    key.to_owned(),
+                    serde_json::to_string(&credentials).unwrap_or_default(),
+                );
+                "".to_owned()
+            }
+            Err(e) => e.to_string(),
+        }
+    }
+    #[cfg(not(all(feature = "fido2", not(any(target_os = "android", target_os = "ios")))))]
+    {
+        let _ = (name, pin);
+        "fido2-not-supported-tip".to_owned()
+    }
+}
+
+pub fn remove_fido2_key(name: String) {
+    let key = crate::auth_fido2::OPTION_CREDENTIALS;
+    let mut credentials = crate::auth_fido2::parse_credentials(&get_option(key));
+    credentials.retain(|c| c.name != name);
+    let value = if credentials.is_empty() {
+        "".to_owned()
+    } else {
+        serde_json::to_string(&credentials).unwrap_or_default()
+    };
+    set_option(key.to_owned(), value);
+}
+
+/// Rotate the key pair of this host, see [`crate::server::key_rotation`].
+/// Returns the error, empty on success.
+pub fn rotate_key_pair(force: bool) -> String {
+    #[cfg(not(any(target_os = "android", target_os = "ios")))]
+    {
+        match ipc::rotate_key_pair(force) {
+            Ok(_) => "".to_owned(),
+            Err(e) => e.to_string(),
+        }
+    }
+    #[cfg(any(target_os = "android", target_os = "ios"))]
+    {
+        let _ = force;
+        "Not supported".to_owned()
+    }
+}
+
 pub fn check_hwcodec() {
     #[cfg(feature = "hwcodec")]
     #[cfg(not(any(target_os = "android", target_os = "ios")))]
@@ -1,6 +1,9 @@
 use crate::{
     common::{get_supported_keyboard_modes, is_keyboard_mode_supported},
-    input::{MOUSE_BUTTON_LEFT, MOUSE_TYPE_DOWN, MOUSE_TYPE_UP, MOUSE_TYPE_WHEEL},
+    input::{
+        MOUSE_BUTTON_LEFT, MOUSE_TYPE_DOWN, MOUSE_TYPE_MOVE_RELATIVE, MOUSE_TYPE_UP,
+        MOUSE_TYPE_WHEEL,
+    },
     ui_interface::use_texture_render,
 };
 use async_trait::async_trait;
@@ -67,6 +70,7 @@ pub struct Session<T: InvokeUiSession> {
     // Indicate whether the session is reconnected.
     // Used to auto start file transfer after reconnection.
     pub reconnect_count: Arc<AtomicUsize>,
+    pub latency_probe: Arc<crate::latency_probe::ClientProbe>,
 }
 
 #[derive(Clone)]
@@ -94,6 +98,7 @@ enum ConnectionState {
 pub struct ConnectionRoundState {
     round: u32,
     state: ConnectionState,
+    closed: bool,
 }
 
 impl ConnectionRoundState {
@@ -115,6 +120,14 @@ impl ConnectionRoundState {
         }
     }
 
+    pub fn set_closed(&mut self) {
+        self.closed = true;
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
     pub fn set_disconnected(&mut self, round: u32) -> bool {
         if self.is_round_gt(round) {
             false
@@ -130,6 +143,7 @@ impl Default for ConnectionRoundState {
         Self {
             round: 0,
             state: ConnectionState::Connecting,
+            closed: false,
         }
     }
 }
@@ -423,6 +437,17 @@ impl<T: InvokeUiSession> Session<T> {
         }
     }
 
+    /// Request a keyframe of the display, the older peers refresh the video instead.
+    pub fn request_keyframe(&self, display: i32) {
+        if crate::common::is_support_keyframe_request_num(self.lc.read().unwrap().version) {
+            self.send(Data::Message(LoginConfigHandler::refresh_display(
+                (display | crate::common::REFRESH_KEYFRAME_FLAG) as _,
+            )));
+        } else {
+            self.refresh_video(display);
+        }
+    }
+
     pub fn toggle_virtual_display(&self, index: i32, on: bool) {
         let mut misc = Misc::new();
         misc.set_toggle_virtual_display(ToggleVirtualDisplay {
@@ -465,6 +490,119 @@ impl<T: InvokeUiSession> Session<T> {
         self.send(Data::Message(msg));
     }
 
+    pub fn save_encoder_profile(&self, value: String) {
+        let msg = self.lc.write().unwrap().save_encoder_profile(value);
+        self.send(Data::Message(msg));
+    }
+
+    /// Save the jitter buffer of the audio playback, 0 means the default.
+    pub fn save_audio_jitter_buffer(&self, target_ms: usize, max_ms: usize) {
+        let config = {
+            let mut lc = self.lc.write().unwrap();
+            lc.set_option("audio-buffer-target-ms".to_owned(), target_ms.to_string());
+            lc.set_option("audio-buffer-max-ms".to_owned(), max_ms.to_string());
+            lc.get_jitter_buffer_config()
+        };
+        crate::unity::audio::get_session_audio_output(&self.get_id()).set_jitter_buffer(config);
+    }
+
+    /// Ask the peer for the applications whose audio can be captured, or select one,
+    /// `Some(0)` selects the whole system mix again.
+    ///
+    /// The peer replies with [`crate::common::AudioSources`], passed to `update_audio_sources`.
+    pub fn request_audio_source(&self, pid: Option<u32>) {
+        use crate::common::{AudioSourceRequest, AUDIO_SOURCE_REQUEST_ID};
+        if !crate::common::is_support_audio_app_capture_num(self.lc.read().unwrap().version) {
+            return;
+        }
+        let request = match pid {
+            Some(pid) => AudioSourceRequest::Select { pid },
+            None => AudioSourceRequest::List,
+        };
+        self.send(Data::Message(crate::common::make_plugin_request(
+            AUDIO_SOURCE_REQUEST_ID,
+            serde_json::to_vec(&request).unwrap_or_default(),
+        )));
+    }
+
+    pub fn send_gamepad_event(&self, event: crate::gamepad::GamepadEvent) {
+        if !crate::common::is_support_gamepad_num(self.lc.read().unwrap().version) {
+            return;
+        }
+        self.send(Data::Message(crate::common::make_plugin_request(
+            crate::gamepad::REQUEST_ID,
+            event.encode(),
+        )));
+    }
+
+    pub fn send_pen_event(&self, event: crate::pen::PenEvent) {
+        if !crate::common::is_support_pen_num(self.lc.read().unwrap().version) {
+            return;
+        }
+        self.send(Data::Message(crate::common::make_plugin_request(
+            crate::pen::REQUEST_ID,
+            event.encode(),
+        )));
+    }
+
+    pub fn is_support_relative_mouse(&self) -> bool {
+        crate::common::is_support_relative_mouse_num(self.lc.read().unwrap().version)
+            && self.peer_platform() != crate::PLATFORM_ANDROID
+    }
+
+    /// Send the movements of the local mouse as the distance moved, with the cursor pinned.
+    ///
+    /// Returns false if it's not supported by the peer, or the cursor can not be moved,
+    /// such as on Wayland.
+    #[cfg(not(any(target_os = "android", target_os = "ios")))]
+    pub fn set_relative_mouse_mode(&self, enabled: bool) -> bool {
+        use crate::client::relative_mouse;
+
+        let key = self.lc.read().unwrap().session_id;
+        if !enabled {
+            relative_mouse::stop(Some(key));
+            return true;
+        }
+        if !self.is_support_relative_mouse() {
+            return false;
+        }
+        let session = self.clone();
+        let sender: relative_mouse::Sender = Box::new(move |x, y| {
+            session.send_mouse(MOUSE_TYPE_MOVE_RELATIVE, x, y, false, false, false, false)
+        });
+        let session = self.clone();
+        let on_released = Box::new(move || session.update_relative_mouse_mode(false));
+        match relative_mouse::start(key, sender, on_released) {
+            Ok(()) => true,
+            Err(e) => {
+                log::error!("Failed to start relative mouse mode: {}", e);
+                false
+            }
+        }
+    }
+
+    /// Forward the local game controllers to the peer.
+    #[cfg(feature = "gamepad")]
+    pub fn set_gamepad_forwarding(&self, enabled: bool) {
+        let key = self.lc.read().unwrap().session_id;
+        let forwarder: Option<crate::gamepad::local::Forwarder> = if enabled {
+            let session = self.clone();
+            Some(Box::new(move |event: &crate::gamepad::GamepadEvent| {
+                session.send_gamepad_event(*event)
+            }))
+        } else {
+            None
+        };
+        crate::gamepad::local::set_forwarder(key, forwarder);
+    }
+
+    pub fn save_display_settings(&self, settings: scrap::codec::DisplaySettings) {
+        let msg = self.lc.write().unwrap().save_display_settings(settings);
+        if let Some(msg) = msg {
+            self.send(Data::Message(msg));
+        }
+    }
+
     pub fn save_image_quality(&self, value: String) {
         let msg = self.lc.write().unwrap().save_image_quality(value.clone());
         if let Some(msg) = msg {
@@ -551,6 +689,13 @@ impl<T: InvokeUiSession> Session<T> {
         self.send(Data::Message(msg));
     }
 
+    /// Send the data without passing the input hooks of the plugins.
+    pub(crate) fn send_unhooked(&self, data: Data) {
+        if let Some(sender) = self.sender.read().unwrap().as_ref() {
+            sender.send(data).ok();
+        }
+    }
+
     #[cfg(all(feature = "flutter", feature = "plugin_framework"))]
     #[cfg(not(any(target_os = "android", target_os = "ios")))]
     pub fn send_plugin_request(&self, request: PluginRequest) {
@@ -763,6 +908,138 @@ impl<T: InvokeUiSession> Session<T> {
         self.send(Data::Message(msg_out));
     }
 
+    /// Ask the host for a token to hand off the session to another device,
+    /// see [`crate::session_handoff`].
+    pub fn request_handoff(&self) {
+        let msg = crate::session_handoff::Handoff::Request.to_message();
+        self.send(Data::Message(msg));
+    }
+
+    /// Pass the input control to the session `to` of the host, see [`crate::input_arbitration`].
+    pub fn pass_input_control(&self, to: i32) {
+        let msg = crate::input_arbitration::InputControl::Pass { to }.to_message();
+        self.send(Data::Message(msg));
+    }
+
+    /// Ask the Windows host for its sessions, see [`crate::windows_session`].
+    pub fn request_windows_sessions(&self) {
+        let msg = crate::windows_session::WindowsSessions::List.to_message();
+        self.send(Data::Message(msg));
+    }
+
+    /// Ask the host for its processes, see [`crate::task_manager`].
+    pub fn request_processes(&self) {
+        let msg = crate::task_manager::TaskManager::List.to_message();
+        self.send(Data::Message(msg));
+    }
+
+    /// Terminate the process of the host.
+    pub fn kill_process(&self, pid: u32) {
+        let msg = crate::task_manager::TaskManager::Kill { pid }.to_message();
+        self.send(Data::Message(msg));
+    }
+
+    /// Run the command on the host, returns its id in the replies, see [`crate::remote_command`].
+    pub fn run_remote_command(&self, command: String, args: Vec<String>, cwd: String) -> u32 {
+        let id = crate::remote_command::next_id();
+        let request = crate::remote_command::Request {
+            id,
+            command,
+            args,
+            cwd,
+        };
+        let msg = crate::remote_command::RemoteCommand::Run(request).to_message();
+        self.send(Data::Message(msg));
+        id
+    }
+
+    pub fn cancel_remote_command(&self, id: u32) {
+        let msg = crate::remote_command::RemoteCommand::Cancel { id }.to_message();
+        self.send(Data::Message(msg));
+    }
+
+    /// Show or hide the annotations on the screen of the host, see [`crate::annotation`].
+    pub fn set_annotating(&self, on: bool) {
+        let msg = if on {
+            crate::annotation::Annotations::Start
+        } else {
+            crate::annotation::Annotations::Stop
+        };
+        self.send(Data::Message(msg.to_message()));
+    }
+
+    /// Draw the annotation in the JSON of [`crate::annotation::Annotation`].
+    pub fn draw_annotation(&self, annotation: String) {
+        match serde_json::from_str(&annotation) {
+            Ok(annotation) => {
+                let msg = crate::annotation::Annotations::Draw(annotation).to_message();
+                self.send(Data::Message(msg));
+            }
+            Err(e) => log::error!("Invalid annotation {}: {}", annotation, e),
+        }
+    }
+
+    pub fn undo_annotation(&self) {
+        let msg = crate::annotation::Annotations::Undo.to_message();
+        self.send(Data::Message(msg));
+    }
+
+    pub fn clear_annotations(&self) {
+        let msg = crate::annotation::Annotations::Clear.to_message();
+        self.send(Data::Message(msg));
+    }
+
+    /// Add a virtual monitor of the mode to the host, see [`crate::virtual_monitor`].
+    pub fn add_virtual_monitor(&self, width: u32, height: u32, refresh_rate: u32) {
+        let msg = crate::virtual_monitor::AddMonitor {
+            width,
+            height,
+            refresh_rate,
+        }
+        .to_message();
+        self.send(Data::Message(msg));
+    }
+
+    /// Ask the host to encode the rect of the display in a higher quality, in the pixels of the
+    /// display, an empty rect to clear it, see [`crate::priority_region`].
+    pub fn set_priority_region(&self, display: usize, x: u32, y: u32, w: u32, h: u32) {
+        let rect = if w > 0 && h > 0 {
+            Some(crate::priority_region::Rect { x, y, w, h })
+        } else {
+            None
+        };
+        let msg = crate::priority_region::PriorityRegion { display, rect }.to_message();
+        self.send(Data::Message(msg));
+    }
+
+    /// Start measuring the latency of the video frames, see [`crate::latency_probe`].
+    pub fn start_latency_probe(&self) {
+        self.latency_probe.set_enabled(true);
+        let msg = crate::latency_probe::LatencyProbe::Start.to_message();
+        self.send(Data::Message(msg));
+    }
+
+    pub fn stop_latency_probe(&self) {
+        self.save_latency_report();
+        self.latency_probe.set_enabled(false);
+        let msg = crate::latency_probe::LatencyProbe::Stop.to_message();
+        self.send(Data::Message(msg));
+    }
+
+    /// The percentiles of the stages in JSON, empty if not probing.
+    pub fn get_latency_report(&self) -> String {
+        if !self.latency_probe.is_enabled() {
+            return "".to_owned();
+        }
+        serde_json::to_string(&self.latency_probe.report(&self.get_id())).unwrap_or_default()
+    }
+
+    pub fn save_latency_report(&self) {
+        if self.latency_probe.is_enabled() {
+            crate::latency_probe::save_report(&self.latency_probe.report(&self.get_id()));
+        }
+    }
+
     pub fn send_chat(&self, text: String) {
         let mut misc = Misc::new();
         misc.set_chat_message(ChatMessage {
@@ -869,6 +1146,14 @@ impl<T: InvokeUiSession> Session<T> {
         keyboard::client::change_grab_status(GrabState::Wait, &keyboard_mode);
     }
 
+    /// Release the full keyboard grab by the escape hotkey, until the session is entered again.
+    #[cfg(not(any(target_os = "android", target_os = "ios")))]
+    pub fn release_keyboard_grab(&self) {
+        log::info!("Keyboard grab released by the escape hotkey");
+        self.leave(self.get_keyboard_mode());
+        self.keyboard_grab_released();
+    }
+
     // flutter only TODO new input
     pub fn input_key(
         &self,
@@ -1216,6 +1501,9 @@ impl<T: InvokeUiSession> Session<T> {
             }
         }
 
+        if mask == MOUSE_TYPE_MOVE_RELATIVE && !self.is_support_relative_mouse() {
+            return;
+        }
         let (x, y) = if mask == MOUSE_TYPE_WHEEL || mask == MOUSE_TYPE_TRACKPAD {
             self.get_scroll_xy((x, y))
         } else {
@@ -1384,9 +1672,33 @@ impl<T: InvokeUiSession> Session<T> {
     }
 
     pub fn close(&self) {
+        self.connection_round_state.lock().unwrap().set_closed();
+        crate::unity::reconnect::cancel(&self.get_id());
+        #[cfg(feature = "gamepad")]
+        crate::gamepad::local::set_forwarder(self.lc.read().unwrap().session_id, None);
+        #[cfg(not(any(target_os = "android", target_os = "ios")))]
+        crate::client::relative_mouse::stop(Some(self.lc.read().unwrap().session_id));
         self.send(Data::Close);
     }
 
+    /// Schedule a reconnect if the reconnect policy of this peer allows.
+    fn try_auto_reconnect(&self, round: u32, reason: &str) {
+        let Some(delay) = crate::unity::reconnect::next_attempt(&self.get_id(), reason) else {
+            return;
+        };
+        let session = self.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(delay);
+            let state = session.connection_round_state.lock().unwrap();
+            // Closed by the user, or a newer round is already started.
+            if state.is_closed() || state.is_round_gt(round) {
+                return;
+            }
+            drop(state);
+            session.reconnect(false);
+        });
+    }
+
     fn try_auto_start_job_str(is_reconnected: bool, job_str: &str) -> Option<String> {
         if is_reconnected {
             let job_str = job_str.trim();
@@ -1413,7 +1725,8 @@ impl<T: InvokeUiSession> Session<T> {
             return;
         }
         let reconnect_count_thr = if cfg!(feature = "flutter") { 0 } else { 1 };
-        let is_reconnected = self.reconnect_count.load(Ordering::SeqCst) > reconnect_count_thr;
+        let is_reconnected = self.reconnect_count.load(Ordering::SeqCst) > reconnect_count_thr
+            && crate::unity::reconnect::resume_session(&self.get_id());
         // TODO: can add a confirm dialog
         let mut cnt = 1;
         for job_str in pc.transfer.read_jobs.iter() {
@@ -1658,6 +1971,22 @@ pub trait InvokeUiSession: Send + Sync + Clone + 'static + Sized + Default {
     fn set_peer_info(&self, peer_info: &PeerInfo); // flutter
     fn set_displays(&self, displays: &Vec<DisplayInfo>);
     fn set_platform_additions(&self, data: &str);
+    fn update_audio_sources(&self, sources: &str);
+    fn update_relative_mouse_mode(&self, enabled: bool);
+    fn keyboard_grab_released(&self);
+    fn update_folder_sync(&self, status: &str);
+    fn update_session_expiry(&self, remaining: u64, view_only: bool);
+    fn permissions_revoked(&self, permissions: &[String], reason: &str);
+    fn update_input_control(&self, state: &str);
+    /// The JSON of [`crate::system_info::SystemInfo`] sent by the host after the login.
+    fn update_system_info(&self, info: &str);
+    /// The JSON of the reply of [`crate::task_manager::TaskManager`].
+    fn update_task_manager(&self, reply: &str);
+    /// The JSON of the reply of [`crate::remote_command::RemoteCommand`].
+    fn update_remote_command(&self, reply: &str);
+    /// The JSON of the reply of [`crate::annotation::Annotations`].
+    fn update_annotation(&self, reply: &str);
+    fn on_handoff_token(&self, link: &str, token: &str, expires_in: u64, error: &str);
     fn on_connected(&self, conn_type: ConnType);
     fn update_privacy_mode(&self);
     fn set_permission(&self, name: &str, value: bool);
@@ -1665,6 +1994,7 @@ pub trait InvokeUiSession: Send + Sync + Clone + 'static + Sized + Default {
     fn update_quality_status(&self, qs: QualityStatus);
     fn set_connection_type(&self, is_secured: bool, direct: bool, stream_type: &str);
     fn set_fingerprint(&self, fingerprint: String);
+    fn set_session_fingerprint(&self, fingerprint: String);
     fn job_error(&self, id: i32, err: String, file_num: i32);
     fn job_done(&self, id: i32, file_num: i32);
     fn clear_all_jobs(&self);
@@ -1707,6 +2037,8 @@ pub trait InvokeUiSession: Send + Sync + Clone + 'static + Sized + Default {
     #[cfg(all(feature = "vram", feature = "flutter"))]
     fn on_texture(&self, display: usize, texture: *mut c_void);
     fn set_multiple_windows_session(&self, sessions: Vec<WindowsSession>);
+    /// The sessions asked by the user in the session, for the selection dialog.
+    fn update_windows_sessions(&self, sessions: Vec<WindowsSession>);
     fn set_current_display(&self, disp_idx: i32);
     #[cfg(feature = "flutter")]
     fn is_multi_ui_session(&self) -> bool;
@@ -1740,9 +2072,17 @@ impl<T: InvokeUiSession> Interface for Session<T> {
     }
 
     fn send(&self, data: Data) {
-        if let Some(sender) = self.sender.read().unwrap().as_ref() {
-            sender.send(data).ok();
-        }
+        #[cfg(all(feature = "flutter", feature = "plugin_framework"))]
+        #[cfg(not(any(target_os = "android", target_os = "ios")))]
+        let data = if crate::plugin::native_handlers::hook::has_input_hooks() {
+            match crate::plugin::native_handlers::hook::on_input(&self.get_id(), data) {
+                Some(data) => data,
+                None => return,
+            }
+        } else {
+            data
+        };
+        self.send_unhooked(data);
     }
 
     fn msgbox(&self, msgtype: &str, title: &str, text: &str, link: &str) {
@@ -1816,6 +2156,12 @@ impl<T: InvokeUiSession> Interface for Session<T> {
             );
         }
         self.on_connected(self.lc.read().unwrap().conn_type);
+        if !self.is_file_transfer() && !self.is_port_forward() && !self.is_terminal() {
+            let msgs = self.lc.read().unwrap().display_settings_messages();
+            for msg in msgs {
+                self.send(Data::Message(msg));
+            }
+        }
         #[cfg(windows)]
         {
             let mut path = std::env::temp_dir();
@@ -2001,9 +2347,12 @@ pub async fn io_loop<T: InvokeUiSession>(handler: Session<T>, round: u32) {
         }
         return;
     }
-    let mut remote = Remote::new(handler, receiver, sender);
+    let mut remote = Remote::new(handler.clone(), receiver, sender);
     remote.io_loop(&key, &token, round).await;
     remote.sync_jobs_status_to_local().await;
+    if let Some(reason) = remote.connection_lost_reason() {
+        handler.try_auto_reconnect(round, reason);
+    }
 }
 
 #[cfg(not(any(target_os = "android", target_os = "ios")))]
@@ -1,9 +1,41 @@
-use std::ffi::{c_char, CString};
+use std::ffi::{c_char, c_void, CStr, CString};
 use std::sync::RwLock;
 
-use hbb_common::log;
+use hbb_common::{libc, log};
 use scrap::ImageFormat;
 
+pub mod audio;
+pub mod auth_2fa;
+pub mod bandwidth;
+pub mod batch;
+pub mod diagnostics;
+pub mod display;
+pub mod event_bridge;
+pub mod fingerprint;
+pub mod gamepad;
+pub mod host_state;
+pub mod latest_frame;
+pub mod mouse;
+pub mod peers;
+pub mod pen;
+pub mod prewarm;
+pub mod reconnect;
+pub mod service;
+pub mod session_expiry;
+pub mod subscription;
+pub mod terminal;
+pub mod wake_on_lan;
+
+/// Version of the `rustdesk_unity_*` ABI.
+///
+/// Increase it when a symbol is added or removed, or a signature or struct layout is changed.
+pub const UNITY_API_VERSION: u32 = 27;
+
+pub const UNITY_OK: i32 = 0;
+pub const UNITY_ERR_INVALID_ARGS: i32 = -1;
+pub const UNITY_ERR_NOT_FOUND: i32 = -2;
+pub const UNITY_ERR_FAILED: i32 = -3;
+
 pub type UnityVideoFrameCallback = Option<
     extern "C" fn(
         peer_id: *const c_char,
@@ -17,8 +49,13 @@ pub type UnityVideoFrameCallback = Option<
     ),
 >;
 
+pub type UnitySessionEventCallback = Option<
+    extern "C" fn(peer_id: *const c_char, event_type: *const c_char, payload: *const c_char),
+>;
+
 lazy_static::lazy_static! {
     static ref VIDEO_FRAME_CALLBACK: RwLock<UnityVideoFrameCallback> = RwLock::new(None);
+    static ref SESSION_EVENT_CALLBACK: RwLock<UnitySessionEventCallback> = RwLock::new(None);
 }
 
 #[no_mangle]
@@ -29,6 +66,47 @@ pub extern "C" fn rustdesk_unity_register_video_frame_callback(
     *guard = callback;
 }
 
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_register_session_event_callback(
+    callback: UnitySessionEventCallback,
+) {
+    let mut guard = SESSION_EVENT_CALLBACK.write().unwrap();
+    *guard = callback;
+}
+
+/// Push a session event to Unity, `payload` is a JSON string.
+///
+/// The event is also passed to the Unreal bridge, see [`crate::unreal`].
+pub fn notify_session_event(peer_id: &str, event_type: &str, payload: &str) {
+    if !subscription::is_session_event_subscribed(event_type, peer_id) {
+        return;
+    }
+    crate::unreal::notify_session_event(peer_id, event_type, payload);
+    event_bridge::notify(event_bridge::SOURCE_SESSION, peer_id, event_type, payload);
+    if batch::push(event_bridge::SOURCE_SESSION, peer_id, event_type, payload) {
+        return;
+    }
+    let Some(callback) = *SESSION_EVENT_CALLBACK.read().unwrap() else {
+        return;
+    };
+    match (
+        CString::new(peer_id),
+        CString::new(event_type),
+        CString::new(payload),
+    ) {
+        (Ok(peer_id), Ok(event_type), Ok(payload)) => {
+            callback(peer_id.as_ptr(), event_type.as_ptr(), payload.as_ptr());
+        }
+        _ => {
+            log::warn!(
+                "Failed to convert session event '{}' of {} into CString",
+                event_type,
+                peer_id
+            );
+        }
+    }
+}
+
 pub fn notify_video_frame(
     peer_id: &str,
     display: usize,
@@ -38,14 +116,37 @@ pub fn notify_video_frame(
     format: ImageFormat,
     buffer: &[u8],
 ) {
-    let callback_opt = {
+    let mut stride = if height > 0 {
+        buffer.len() / height
+    } else {
+        0
+    };
+    if stride == 0 {
+        stride = width.saturating_mul(4);
+    }
+    if stride_hint > stride {
+        stride = stride_hint;
+    }
+    let format = image_format_to_u32(format);
+
+    let callback_opt = if latest_frame::store(
+        peer_id,
+        display,
+        width as u32,
+        height as u32,
+        stride as u32,
+        format,
+        buffer,
+    ) {
+        None
+    } else {
         let guard = VIDEO_FRAME_CALLBACK.read().unwrap();
         *guard
     };
 
-    let Some(callback) = callback_opt else {
+    if callback_opt.is_none() && !crate::unreal::has_video_frame_callback() {
         return;
-    };
+    }
 
     let c_peer_id = match CString::new(peer_id) {
         Ok(value) => value,
@@ -55,20 +156,7 @@ pub fn notify_video_frame(
         }
     };
 
-    let mut stride = if height > 0 {
-        buffer.len() / height
-    } else {
-        0
-    };
-    if stride == 0 {
-        stride = width.saturating_mul(4);
-    }
-    if stride_hint > stride {
-        stride = stride_hint;
-    }
-    let format = image_format_to_u32(format);
-
-    unsafe {
+    if let Some(callback) = callback_opt {
         callback(
             c_peer_id.as_ptr(),
             display as u32,
@@ -80,12 +168,192 @@ pub fn notify_video_frame(
             buffer.len(),
         );
     }
+    crate::unreal::notify_video_frame(crate::unreal::UnrealFrameDesc {
+        peer_id: c_peer_id.as_ptr(),
+        display: display as u32,
+        width: width as u32,
+        height: height as u32,
+        stride: stride as u32,
+        format,
+        data: buffer.as_ptr(),
+        len: buffer.len(),
+    });
+}
+
+/// Tear down the bridge before the embedding runtime unloads, e.g. on a Unity domain reload.
+///
+/// All callbacks are unregistered first, so no native thread calls into the unloaded managed code.
+/// Then the sessions are closed and the plugins are unloaded.
+/// The bridge can be used again after this, all `rustdesk_unity_init_*` calls are idempotent.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_shutdown() {
+    log::info!("Unity bridge shutdown");
+    *VIDEO_FRAME_CALLBACK.write().unwrap() = None;
+    *SESSION_EVENT_CALLBACK.write().unwrap() = None;
+    audio::reset();
+    reconnect::reset();
+    subscription::reset();
+    event_bridge::stop();
+    batch::stop();
+    latest_frame::reset();
+    #[cfg(any(target_os = "android", target_os = "ios", feature = "flutter"))]
+    close_sessions();
+    #[cfg(all(feature = "flutter", feature = "plugin_framework"))]
+    #[cfg(not(any(target_os = "android", target_os = "ios")))]
+    crate::plugin::uninit();
+}
+
+#[cfg(any(target_os = "android", target_os = "ios", feature = "flutter"))]
+fn close_sessions() {
+    use crate::client::Interface;
+
+    const JOIN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(3);
+
+    let sessions = crate::flutter::sessions::get_sessions();
+    for session in sessions.iter() {
+        session.close();
+    }
+    // Wait for the io loops to sync the transfer jobs and exit.
+    let start = std::time::Instant::now();
+    for session in sessions.iter() {
+        let Some(thread) = session.thread.lock().unwrap().take() else {
+            continue;
+        };
+        while !thread.is_finished() && start.elapsed() < JOIN_TIMEOUT {
+            std::thread::sleep(std::time::Duration::from_millis(30));
+        }
+        if thread.is_finished() {
+            thread.join().ok();
+        } else {
+            log::warn!("Session {} doesn't exit in time", session.get_id());
+        }
+    }
+    for session in sessions.iter() {
+        for id in crate::flutter::sessions::get_session_ids(session) {
+            crate::flutter::sessions::remove_session_by_session_id(&id);
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_get_api_version() -> u32 {
+    UNITY_API_VERSION
+}
+
+/// Returns a JSON object describing what this build supports,
+/// so a wrapper built against another version can adapt instead of calling missing symbols.
+///
+/// The returned string must be freed with `rustdesk_unity_free`.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_get_capabilities() -> *mut c_char {
+    string_to_c_ret(&get_capabilities().to_string())
+}
+
+fn get_capabilities() -> serde_json::Value {
+    let mut callbacks = vec!["video_frame", "session_event", "audio_frame"];
+    if cfg!(all(
+        feature = "flutter",
+        feature = "plugin_framework",
+        not(any(target_os = "android", target_os = "ios"))
+    )) {
+        callbacks.push("plugin_event");
+    }
+    let pixel_formats = [ImageFormat::Raw, ImageFormat::ABGR, ImageFormat::ARGB]
+        .into_iter()
+        .map(|f| {
+            serde_json::json!({
+                "name": format!("{:?}", f).to_lowercase(),
+                "value": image_format_to_u32(f),
+            })
+        })
+        .collect::<Vec<_>>();
+    let mut features = vec![
+        "audio_output",
+        "reconnect",
+        "bandwidth_cap",
+        "shutdown",
+        "event_subscription",
+        "display_settings",
+        "gamepad",
+        "pen",
+        "relative_mouse",
+        "diagnostics",
+        "wake_on_lan",
+        "prewarm",
+        "2fa",
+        "session_expiry",
+        "fingerprint",
+        "host_state",
+        "event_bridge",
+        "event_batching",
+        "latest_frame",
+        "priority_region",
+        "peer_metadata",
+        "terminal",
+    ];
+    if cfg!(any(target_os = "linux", target_os = "macos")) {
+        features.push("service_manager");
+    }
+    let mut gpu_paths: Vec<&str> = vec![];
+    if cfg!(all(feature = "vram", feature = "flutter", target_os = "windows")) {
+        gpu_paths.push("d3d11_texture");
+    }
+    serde_json::json!({
+        "api_version": UNITY_API_VERSION,
+        "version": crate::VERSION,
+        "platform": std::env::consts::OS,
+        "arch": std::env::consts::ARCH,
+        "link": if cfg!(feature = "unity-static") { "static" } else { "dynamic" },
+        "callbacks": callbacks,
+        "features": features,
+        "local_gamepads": cfg!(feature = "gamepad"),
+        "pixel_formats": pixel_formats,
+        "gpu_paths": gpu_paths,
+    })
 }
 
-fn image_format_to_u32(format: ImageFormat) -> u32 {
+pub(crate) fn image_format_to_u32(format: ImageFormat) -> u32 {
     match format {
         ImageFormat::Raw => 0,
         ImageFormat::ABGR => 1,
         ImageFormat::ARGB => 2,
     }
 }
+
+/// Free a string returned by one of the `rustdesk_unity_*` getters.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_free(ptr: *mut c_void) {
+    if !ptr.is_null() {
+        unsafe {
+            libc::free(ptr);
+        }
+    }
+}
+
+pub(crate) fn cstr_to_string(ptr: *const c_char) -> Option<String> {
+    if ptr.is_null() {
+        return None;
+    }
+    unsafe { CStr::from_ptr(ptr) }
+        .to_str()
+        .ok()
+        .map(|s| s.to_owned())
+}
+
+/// The returned pointer must be released with `rustdesk_unity_free`.
+///
+/// It is allocated with `malloc`, like the strings returned by the plugin getters.
+pub(crate) fn string_to_c_ret(s: &str) -> *mut c_char {
+    if s.as_bytes().contains(&0) {
+        log::warn!("Failed to convert Unity return value into C string: interior nul byte");
+        return std::ptr::null_mut();
+    }
+    unsafe {
+        let r = libc::malloc(s.len() + 1) as *mut c_char;
+        if !r.is_null() {
+            libc::memcpy(r as *mut c_void, s.as_ptr() as *const c_void, s.len());
+            *r.add(s.len()) = 0;
+        }
+        r
+    }
+}
@@ -0,0 +1,307 @@
+use std::{
+    collections::HashMap,
+    ffi::{c_char, CString},
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc, RwLock,
+    },
+};
+
+use hbb_common::log;
+use serde_json::json;
+
+use super::{cstr_to_string, string_to_c_ret, UNITY_ERR_INVALID_ARGS, UNITY_OK};
+use crate::client::JitterBufferConfig;
+
+/// Audio output settings of one remote session, shared with the audio thread of that session.
+#[derive(Debug)]
+pub struct SessionAudioOutput {
+    device: RwLock<Option<String>>,
+    device_changed: AtomicBool,
+    // f32 bits
+    volume: AtomicU32,
+    muted: AtomicBool,
+    jitter_buffer: RwLock<JitterBufferConfig>,
+    jitter_buffer_changed: AtomicBool,
+    // u32::MAX before the playback starts
+    buffer_ms: AtomicU32,
+}
+
+impl Default for SessionAudioOutput {
+    fn default() -> Self {
+        Self {
+            device: Default::default(),
+            device_changed: Default::default(),
+            volume: AtomicU32::new(1.0f32.to_bits()),
+            muted: Default::default(),
+            jitter_buffer: Default::default(),
+            jitter_buffer_changed: Default::default(),
+            buffer_ms: AtomicU32::new(u32::MAX),
+        }
+    }
+}
+
+impl SessionAudioOutput {
+    /// The output device name, `None` means the default device.
+    pub fn device(&self) -> Option<String> {
+        self.device.read().unwrap().clone()
+    }
+
+    /// Returns true once after the device is changed.
+    pub fn take_device_changed(&self) -> bool {
+        self.device_changed.swap(false, Ordering::SeqCst)
+    }
+
+    pub fn volume(&self) -> f32 {
+        f32::from_bits(self.volume.load(Ordering::Relaxed))
+    }
+
+    pub fn muted(&self) -> bool {
+        self.muted.load(Ordering::Relaxed)
+    }
+
+    /// The gain to apply to the decoded pcm.
+    #[inline]
+    pub fn gain(&self) -> f32 {
+        if self.muted() {
+            0.
+        } else {
+            self.volume()
+        }
+    }
+
+    pub fn jitter_buffer(&self) -> JitterBufferConfig {
+        *self.jitter_buffer.read().unwrap()
+    }
+
+    /// Returns true once after the jitter buffer is changed.
+    pub fn take_jitter_buffer_changed(&self) -> bool {
+        self.jitter_buffer_changed.swap(false, Ordering::SeqCst)
+    }
+
+    pub fn set_jitter_buffer(&self, config: JitterBufferConfig) {
+        let mut lock = self.jitter_buffer.write().unwrap();
+        if *lock != config {
+            *lock = config;
+            self.jitter_buffer_changed.store(true, Ordering::SeqCst);
+        }
+    }
+
+    /// The buffered audio in ms, `None` before the playback starts.
+    pub fn buffer_ms(&self) -> Option<u32> {
+        Some(self.buffer_ms.load(Ordering::Relaxed)).filter(|v| *v != u32::MAX)
+    }
+
+    pub fn set_buffer_ms(&self, ms: u32) {
+        self.buffer_ms.store(ms, Ordering::Relaxed);
+    }
+
+    fn set_device(&self, device: Option<String>) {
+        let mut lock = self.device.write().unwrap();
+        if *lock != device {
+            *lock = device;
+            self.device_changed.store(true, Ordering::SeqCst);
+        }
+    }
+}
+
+/// The channel layout of the audio frames, in the channel order of the interleaved samples.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelLayout {
+    Unknown = 0,
+    Mono = 1,
+    /// Left, right.
+    Stereo = 2,
+    /// Front left, front right, center, LFE, rear left, rear right.
+    Surround51 = 6,
+}
+
+impl ChannelLayout {
+    pub fn from_channels(channels: u16) -> Self {
+        match channels {
+            1 => Self::Mono,
+            2 => Self::Stereo,
+            6 => Self::Surround51,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+/// The decoded remote audio, interleaved f32 samples, `frames` samples per channel.
+pub type UnityAudioFrameCallback = Option<
+    extern "C" fn(
+        peer_id: *const c_char,
+        sample_rate: u32,
+        channels: u32,
+        layout: u32,
+        buffer: *const f32,
+        frames: usize,
+    ),
+>;
+
+lazy_static::lazy_static! {
+    static ref AUDIO_FRAME_CALLBACK: RwLock<UnityAudioFrameCallback> = RwLock::new(None);
+}
+
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_register_audio_frame_callback(callback: UnityAudioFrameCallback) {
+    *AUDIO_FRAME_CALLBACK.write().unwrap() = callback;
+}
+
+pub(super) fn reset() {
+    *AUDIO_FRAME_CALLBACK.write().unwrap() = None;
+}
+
+/// Pass the decoded audio of the session to Unity, before the volume of the session is applied,
+/// so that Unity can mute the playback of the session and play the audio itself.
+pub fn notify_audio_frame(peer_id: &str, sample_rate: u32, channels: u16, data: &[f32]) {
+    let Some(callback) = *AUDIO_FRAME_CALLBACK.read().unwrap() else {
+        return;
+    };
+    if channels == 0 {
+        return;
+    }
+    let Ok(c_peer_id) = CString::new(peer_id) else {
+        log::warn!("Failed to convert peer id to CString for Unity audio callback");
+        return;
+    };
+    callback(
+        c_peer_id.as_ptr(),
+        sample_rate,
+        channels as _,
+        ChannelLayout::from_channels(channels) as _,
+        data.as_ptr(),
+        data.len() / channels as usize,
+    );
+}
+
+lazy_static::lazy_static! {
+    static ref SESSION_AUDIO_OUTPUTS: RwLock<HashMap<String, Arc<SessionAudioOutput>>> = Default::default();
+}
+
+/// Get or create the audio output settings of the session to `peer_id`.
+///
+/// Settings can be made before the session is started, the audio thread picks them up on start.
+pub fn get_session_audio_output(peer_id: &str) -> Arc<SessionAudioOutput> {
+    if let Some(output) = SESSION_AUDIO_OUTPUTS.read().unwrap().get(peer_id) {
+        return output.clone();
+    }
+    SESSION_AUDIO_OUTPUTS
+        .write()
+        .unwrap()
+        .entry(peer_id.to_owned())
+        .or_default()
+        .clone()
+}
+
+pub fn remove_session_audio_output(peer_id: &str) {
+    SESSION_AUDIO_OUTPUTS.write().unwrap().remove(peer_id);
+}
+
+#[cfg(not(target_os = "linux"))]
+fn get_audio_output_devices() -> Vec<String> {
+    use cpal::traits::{DeviceTrait, HostTrait};
+
+    let mut out = Vec::new();
+    let host = cpal::default_host();
+    if let Ok(devices) = host.output_devices() {
+        for device in devices {
+            if device.default_output_config().is_err() {
+                continue;
+            }
+            if let Ok(name) = device.name() {
+                out.push(name);
+            }
+        }
+    }
+    out
+}
+
+#[cfg(target_os = "linux")]
+fn get_audio_output_devices() -> Vec<String> {
+    crate::platform::linux::get_pa_sinks()
+        .drain(..)
+        .map(|x| x.0)
+        .collect()
+}
+
+/// Returns a JSON array of the local audio output device names.
+///
+/// The returned string must be freed with `rustdesk_unity_free`.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_get_audio_output_devices() -> *mut c_char {
+    // Enumerating devices may initialize COM on Windows, keep it off the caller thread.
+    let devices = std::thread::spawn(get_audio_output_devices)
+        .join()
+        .unwrap_or_default();
+    string_to_c_ret(&json!(devices).to_string())
+}
+
+/// Route the remote audio of the session to `device`.
+///
+/// A null or empty `device` selects the default output device.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_set_session_audio_output(
+    peer_id: *const c_char,
+    device: *const c_char,
+) -> i32 {
+    let Some(peer_id) = cstr_to_string(peer_id) else {
+        return UNITY_ERR_INVALID_ARGS;
+    };
+    let device = cstr_to_string(device).filter(|d| !d.is_empty());
+    log::info!(
+        "Set audio output of session {} to {:?}",
+        peer_id,
+        device.as_deref().unwrap_or("default")
+    );
+    get_session_audio_output(&peer_id).set_device(device);
+    UNITY_OK
+}
+
+/// Set the volume of the session, `volume` is in range `[0.0, 1.0]`.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_set_session_volume(peer_id: *const c_char, volume: f32) -> i32 {
+    let Some(peer_id) = cstr_to_string(peer_id) else {
+        return UNITY_ERR_INVALID_ARGS;
+    };
+    if !volume.is_finite() {
+        return UNITY_ERR_INVALID_ARGS;
+    }
+    get_session_audio_output(&peer_id)
+        .volume
+        .store(volume.clamp(0., 1.).to_bits(), Ordering::Relaxed);
+    UNITY_OK
+}
+
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_set_session_mute(peer_id: *const c_char, muted: bool) -> i32 {
+    let Some(peer_id) = cstr_to_string(peer_id) else {
+        return UNITY_ERR_INVALID_ARGS;
+    };
+    get_session_audio_output(&peer_id)
+        .muted
+        .store(muted, Ordering::Relaxed);
+    UNITY_OK
+}
+
+/// Returns `{"device": .., "volume": .., "muted": .., "buffer_ms": .., "buffer_target_ms": ..,
+/// "buffer_max_ms": ..}` of the session, the buffer values are null if not available or not set.
+///
+/// The returned string must be freed with `rustdesk_unity_free`.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_get_session_audio(peer_id: *const c_char) -> *mut c_char {
+    let Some(peer_id) = cstr_to_string(peer_id) else {
+        return std::ptr::null_mut();
+    };
+    let output = get_session_audio_output(&peer_id);
+    let jitter_buffer = output.jitter_buffer();
+    let payload = json!({
+        "device": output.device(),
+        "volume": output.volume(),
+        "muted": output.muted(),
+        "buffer_ms": output.buffer_ms(),
+        "buffer_target_ms": jitter_buffer.target_ms,
+        "buffer_max_ms": jitter_buffer.max_ms,
+    });
+    string_to_c_ret(&payload.to_string())
+}
@@ -0,0 +1,89 @@
+use std::ffi::c_char;
+
+use serde_json::json;
+
+use super::{
+    cstr_to_string, notify_session_event, string_to_c_ret, UNITY_ERR_INVALID_ARGS,
+    UNITY_ERR_NOT_FOUND, UNITY_OK,
+};
+
+/// The host requires the TOTP code of the two-factor authentication,
+/// the payload is `{"wrong": bool}`, true if the last code was wrong.
+pub const EVENT_2FA_REQUIRED: &str = "2fa_required";
+
+/// Called when the login of the session with `peer_id` requires the 2FA code.
+pub fn on_required(peer_id: &str, wrong: bool) {
+    notify_session_event(
+        peer_id,
+        EVENT_2FA_REQUIRED,
+        &json!({ "wrong": wrong }).to_string(),
+    );
+}
+
+/// Send the TOTP `code` to the sessions with `peer_id` after the `2fa_required` event.
+///
+/// If `trust_this_device` is true, the host skips the 2FA for this device next time,
+/// if the host enables the trusted devices.
+/// Returns `UNITY_OK`, `UNITY_ERR_INVALID_ARGS` or `UNITY_ERR_NOT_FOUND` if there's no session.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_send_2fa(
+    peer_id: *const c_char,
+    code: *const c_char,
+    trust_this_device: bool,
+) -> i32 {
+    let (Some(peer_id), Some(code)) = (cstr_to_string(peer_id), cstr_to_string(code)) else {
+        return UNITY_ERR_INVALID_ARGS;
+    };
+    let code = code.trim().to_owned();
+    if code.is_empty() {
+        return UNITY_ERR_INVALID_ARGS;
+    }
+    send_to_sessions(&peer_id, code, trust_this_device)
+}
+
+#[cfg(any(target_os = "android", target_os = "ios", feature = "flutter"))]
+fn send_to_sessions(peer_id: &str, code: String, trust_this_device: bool) -> i32 {
+    use crate::client::Interface;
+
+    let sessions: Vec<_> = crate::flutter::sessions::get_sessions()
+        .into_iter()
+        .filter(|s| s.get_id() == peer_id)
+        .collect();
+    if sessions.is_empty() {
+        return UNITY_ERR_NOT_FOUND;
+    }
+    for session in sessions {
+        session.send2fa(code.clone(), trust_this_device);
+    }
+    UNITY_OK
+}
+
+#[cfg(not(any(target_os = "android", target_os = "ios", feature = "flutter")))]
+fn send_to_sessions(_peer_id: &str, _code: String, _trust_this_device: bool) -> i32 {
+    UNITY_ERR_NOT_FOUND
+}
+
+/// Start the 2FA enrollment of this host, returns the `otpauth://` URL of a new secret
+/// to show as a QR code, empty on failure. The 2FA is enabled only after
+/// `rustdesk_unity_verify_2fa` succeeds with a code of the authenticator app.
+///
+/// The returned string must be freed with `rustdesk_unity_free`.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_generate_2fa() -> *mut c_char {
+    string_to_c_ret(&crate::ui_interface::generate2fa())
+}
+
+/// Verify the `code` of the secret from `rustdesk_unity_generate_2fa` and enable the 2FA.
+///
+/// Returns `UNITY_OK`, or `UNITY_ERR_INVALID_ARGS` if the code is wrong.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_verify_2fa(code: *const c_char) -> i32 {
+    let Some(code) = cstr_to_string(code) else {
+        return UNITY_ERR_INVALID_ARGS;
+    };
+    if crate::ui_interface::verify2fa(code.trim().to_owned()) {
+        UNITY_OK
+    } else {
+        UNITY_ERR_INVALID_ARGS
+    }
+}
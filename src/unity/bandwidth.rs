@@ -0,0 +1,142 @@
+use std::{collections::HashMap, ffi::c_char, sync::RwLock};
+
+use hbb_common::log;
+use serde_derive::Serialize;
+use serde_json::json;
+
+use super::{cstr_to_string, string_to_c_ret, UNITY_ERR_INVALID_ARGS, UNITY_OK};
+
+/// Hard bandwidth caps of a session in kbps, 0 means no limit.
+///
+/// Directions are from the local side: the viewer receives video on the downlink,
+/// the host sends video on the uplink.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize)]
+pub struct BandwidthCap {
+    pub downlink_kbps: u32,
+    pub uplink_kbps: u32,
+}
+
+lazy_static::lazy_static! {
+    static ref CAPS: RwLock<HashMap<String, BandwidthCap>> = Default::default();
+}
+
+pub fn get_cap(peer_id: &str) -> BandwidthCap {
+    CAPS.read()
+        .unwrap()
+        .get(peer_id)
+        .cloned()
+        .unwrap_or_default()
+}
+
+#[inline]
+pub fn get_uplink_cap(peer_id: &str) -> Option<u32> {
+    Some(get_cap(peer_id).uplink_kbps).filter(|v| *v > 0)
+}
+
+#[inline]
+pub fn get_downlink_cap(peer_id: &str) -> Option<u32> {
+    Some(get_cap(peer_id).downlink_kbps).filter(|v| *v > 0)
+}
+
+const MIN_QUALITY: i32 = 10;
+const MIN_FPS: i32 = 5;
+const QUALITY_STEP: i32 = 10;
+const FPS_STEP: i32 = 5;
+// Increase again only if the rate is below 70% of the cap.
+const RESTORE_THRESHOLD: f32 = 0.7;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DownlinkAdjust {
+    /// Request the custom image quality from the host and limit the fps.
+    Limit { quality: i32, fps: i32 },
+    /// The cap is removed, restore the image quality of the session.
+    Restore,
+}
+
+/// Keeps the incoming stream of a session under the downlink cap.
+///
+/// The host has no notion of a hard cap, so the limiter steers it with the custom image quality,
+/// lowering quality first and fps (applied by the fps control of the session) when quality is
+/// already at its minimum.
+#[derive(Debug, Default)]
+pub struct DownlinkLimiter {
+    // (quality, fps) currently requested from the host
+    limited: Option<(i32, i32)>,
+}
+
+impl DownlinkLimiter {
+    /// The fps limit while the stream is limited.
+    #[inline]
+    pub fn limited_fps(&self) -> Option<i32> {
+        self.limited.map(|(_, fps)| fps)
+    }
+
+    /// `kbps` is the measured rate of the last second, `base` is the (quality, fps) of the session settings.
+    pub fn update(
+        &mut self,
+        kbps: u32,
+        cap_kbps: Option<u32>,
+        base: (i32, i32),
+    ) -> Option<DownlinkAdjust> {
+        let Some(cap) = cap_kbps else {
+            return self.limited.take().map(|_| DownlinkAdjust::Restore);
+        };
+        let (mut quality, mut fps) = self.limited.unwrap_or(base);
+        if kbps > cap {
+            if quality > MIN_QUALITY {
+                quality = (quality - QUALITY_STEP).max(MIN_QUALITY);
+            } else if fps > MIN_FPS {
+                fps = (fps - FPS_STEP).max(MIN_FPS);
+            }
+        } else if (kbps as f32) < cap as f32 * RESTORE_THRESHOLD && self.limited.is_some() {
+            if fps < base.1 {
+                fps = (fps + FPS_STEP).min(base.1);
+            } else if quality < base.0 {
+                quality = (quality + QUALITY_STEP).min(base.0);
+            }
+        }
+        if self.limited == Some((quality, fps)) || (self.limited.is_none() && (quality, fps) == base)
+        {
+            return None;
+        }
+        self.limited = Some((quality, fps));
+        Some(DownlinkAdjust::Limit { quality, fps })
+    }
+}
+
+/// Set the bandwidth caps of the session with `peer_id` in kbps, 0 means no limit.
+///
+/// The downlink cap limits the stream received from the host `peer_id`.
+/// The uplink cap limits the stream sent to the controlling client `peer_id`.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_set_session_bandwidth_cap(
+    peer_id: *const c_char,
+    downlink_kbps: u32,
+    uplink_kbps: u32,
+) -> i32 {
+    let Some(peer_id) = cstr_to_string(peer_id) else {
+        return UNITY_ERR_INVALID_ARGS;
+    };
+    let cap = BandwidthCap {
+        downlink_kbps,
+        uplink_kbps,
+    };
+    log::info!("Set bandwidth cap of {}: {:?}", peer_id, cap);
+    if cap == BandwidthCap::default() {
+        CAPS.write().unwrap().remove(&peer_id);
+    } else {
+        CAPS.write().unwrap().insert(peer_id.clone(), cap);
+    }
+    #[cfg(not(target_os = "ios"))]
+    crate::server::set_uplink_bandwidth_cap(&peer_id, get_uplink_cap(&peer_id));
+    UNITY_OK
+}
+
+/// The returned string must be freed with `rustdesk_unity_free`.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_get_session_bandwidth_cap(peer_id: *const c_char) -> *mut c_char {
+    let Some(peer_id) = cstr_to_string(peer_id) else {
+        return std::ptr::null_mut();
+    };
+    string_to_c_ret(&json!(get_cap(&peer_id)).to_string())
+}
@@ -0,0 +1,166 @@
+//! Batched delivery of the session and plugin events, so the high-frequency events cross the
+//! C ABI once per tick instead of once per event.
+//!
+//! While batching is started with [`rustdesk_unity_start_event_batching`], the subscribed events
+//! are not passed to the session and plugin event callbacks, they're collected and passed to
+//! the batch callback every interval as a JSON array, in the order they occurred, each in the
+//! format of the [`event_bridge`](super::event_bridge):
+//!
+//! ```json
+//! [{"source": "session", "peer_id": "123456789", "type": "session_expiry", "payload": {...}}]
+//! ```
+//!
+//! A batch is passed earlier if it reaches [`MAX_BATCH_EVENTS`].
+use std::{
+    ffi::{c_char, CString},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use hbb_common::log;
+
+use super::{UNITY_ERR_INVALID_ARGS, UNITY_OK};
+
+pub const MAX_BATCH_EVENTS: usize = 1024;
+const MIN_INTERVAL_MS: u32 = 1;
+const MAX_INTERVAL_MS: u32 = 10_000;
+
+/// `events` is the JSON array of the `count` events.
+pub type UnityEventBatchCallback = Option<extern "C" fn(events: *const c_char, count: u32)>;
+
+struct Batch {
+    callback: extern "C" fn(*const c_char, u32),
+    events: Vec<String>,
+    // Tells the flushing thread of a replaced batch to exit.
+    generation: u64,
+}
+
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+lazy_static::lazy_static! {
+    static ref BATCH: Mutex<Option<Batch>> = Default::default();
+    // Keeps the batches in order, locked before `BATCH`.
+    static ref DELIVERY: Mutex<()> = Default::default();
+}
+
+/// Collect the event if batching, returns false to pass it to the event callbacks.
+pub fn push(source: &str, peer_id: &str, event_type: &str, payload: &str) -> bool {
+    let mut batch = BATCH.lock().unwrap();
+    let Some(b) = batch.as_mut() else {
+        return false;
+    };
+    b.events.push(super::event_bridge::to_json(
+        source, peer_id, event_type, payload,
+    ));
+    let full = b.events.len() >= MAX_BATCH_EVENTS;
+    drop(batch);
+    if full {
+        flush();
+    }
+    true
+}
+
+fn to_json_array(events: &[String]) -> String {
+    format!("[{}]", events.join(","))
+}
+
+fn deliver(callback: extern "C" fn(*const c_char, u32), events: Vec<String>) {
+    if events.is_empty() {
+        return;
+    }
+    match CString::new(to_json_array(&events)) {
+        Ok(json) => callback(json.as_ptr(), events.len() as _),
+        Err(err) => log::warn!("Failed to convert event batch into CString: {}", err),
+    }
+}
+
+/// Pass the collected events of the batching of `generation`, or the current one if `None`.
+/// Returns false if it's not running.
+fn flush_generation(generation: Option<u64>) -> bool {
+    let _delivery = DELIVERY.lock().unwrap();
+    let mut batch = BATCH.lock().unwrap();
+    let Some(b) = batch
+        .as_mut()
+        .filter(|b| generation.map_or(true, |g| g == b.generation))
+    else {
+        return false;
+    };
+    let events = std::mem::take(&mut b.events);
+    let callback = b.callback;
+    drop(batch);
+    deliver(callback, events);
+    true
+}
+
+/// Pass the collected events now.
+pub fn flush() {
+    flush_generation(None);
+}
+
+pub fn stop() {
+    let _delivery = DELIVERY.lock().unwrap();
+    let Some(b) = BATCH.lock().unwrap().take() else {
+        return;
+    };
+    deliver(b.callback, b.events);
+}
+
+/// Start batching the subscribed session and plugin events, passed to `callback` every
+/// `interval_ms` (1 to 10000) if any, see [`crate::unity::batch`]. The events are passed on a
+/// thread of the library. A running batching is replaced, the pending events are passed first.
+///
+/// Returns `UNITY_OK` or `UNITY_ERR_INVALID_ARGS`.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_start_event_batching(
+    callback: UnityEventBatchCallback,
+    interval_ms: u32,
+) -> i32 {
+    let Some(callback) = callback else {
+        return UNITY_ERR_INVALID_ARGS;
+    };
+    if !(MIN_INTERVAL_MS..=MAX_INTERVAL_MS).contains(&interval_ms) {
+        return UNITY_ERR_INVALID_ARGS;
+    }
+    stop();
+    let generation = GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    *BATCH.lock().unwrap() = Some(Batch {
+        callback,
+        events: Vec::new(),
+        generation,
+    });
+    std::thread::spawn(move || loop {
+        std::thread::sleep(Duration::from_millis(interval_ms as _));
+        if !flush_generation(Some(generation)) {
+            break;
+        }
+    });
+    log::info!("Unity event batching started, interval: {}ms", interval_ms);
+    UNITY_OK
+}
+
+/// Stop batching, the pending events are passed to the batch callback first, and the next
+/// events to the event callbacks again.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_stop_event_batching() {
+    stop();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_json_array() {
+        let events = vec![
+            super::super::event_bridge::to_json("session", "1", "a", "{}"),
+            super::super::event_bridge::to_json("plugin", "", "b", "x"),
+        ];
+        let v: serde_json::Value = serde_json::from_str(&to_json_array(&events)).unwrap();
+        assert_eq!(v.as_array().unwrap().len(), 2);
+        assert_eq!(v[1]["payload"], "x");
+        assert_eq!(to_json_array(&[]), "[]");
+    }
+}
@@ -0,0 +1,10 @@
+use std::ffi::c_char;
+
+/// Run the connection diagnostics, see [`crate::diagnostics`], and return the JSON report.
+///
+/// It blocks for a few seconds, so call it off the main thread.
+/// The returned string must be freed with `rustdesk_unity_free`.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_run_diagnostics() -> *mut c_char {
+    super::string_to_c_ret(&crate::diagnostics::run_json())
+}
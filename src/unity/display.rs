@@ -0,0 +1,173 @@
+use std::ffi::c_char;
+
+use hbb_common::{config::PeerConfig, log};
+use scrap::codec::{DisplaySettings, IMAGE_QUALITY_LOSSLESS};
+use serde_json::json;
+
+use super::{
+    cstr_to_string, string_to_c_ret, UNITY_ERR_INVALID_ARGS, UNITY_ERR_NOT_FOUND, UNITY_OK,
+};
+
+const OPTION_DISPLAY_SETTINGS: &str = "display-settings";
+// The max fps of the host.
+const MAX_FPS: u32 = 120;
+
+/// Set the codec, quality and fps of `display` in the sessions with `peer_id`,
+/// overriding the ones of the session.
+///
+/// `quality` is the custom image quality, 0 for `quality` and `fps` and an empty or null `codec`
+/// ("vp8", "vp9", "av1", "h264", "h265") follow the session, all of them remove the override.
+/// The settings are saved in the peer config, so they apply to the next sessions as well.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_set_display_settings(
+    peer_id: *const c_char,
+    display: u32,
+    quality: i32,
+    fps: u32,
+    codec: *const c_char,
+) -> i32 {
+    let Some(peer_id) = cstr_to_string(peer_id) else {
+        return UNITY_ERR_INVALID_ARGS;
+    };
+    let codec_name = if codec.is_null() {
+        String::new()
+    } else {
+        let Some(codec) = cstr_to_string(codec) else {
+            return UNITY_ERR_INVALID_ARGS;
+        };
+        codec
+    };
+    let codec = DisplaySettings::codec_from_name(&codec_name);
+    if (!codec_name.is_empty() && codec.is_none())
+        || quality < 0
+        || quality >= IMAGE_QUALITY_LOSSLESS
+        || fps > MAX_FPS
+    {
+        return UNITY_ERR_INVALID_ARGS;
+    }
+    let settings = DisplaySettings {
+        display: display as _,
+        quality,
+        fps,
+        codec,
+    };
+    log::info!("Set display settings of {}: {:?}", peer_id, settings);
+    if !apply_to_sessions(&peer_id, settings) {
+        let mut config = PeerConfig::load(&peer_id);
+        let value = crate::client::update_display_settings(
+            &config
+                .options
+                .get(OPTION_DISPLAY_SETTINGS)
+                .cloned()
+                .unwrap_or_default(),
+            settings,
+        );
+        config
+            .options
+            .insert(OPTION_DISPLAY_SETTINGS.to_owned(), value);
+        config.store(&peer_id);
+    }
+    UNITY_OK
+}
+
+// Returns false if there's no session with the peer.
+#[cfg(any(target_os = "android", target_os = "ios", feature = "flutter"))]
+fn apply_to_sessions(peer_id: &str, settings: DisplaySettings) -> bool {
+    use crate::client::Interface;
+
+    let sessions: Vec<_> = crate::flutter::sessions::get_sessions()
+        .into_iter()
+        .filter(|s| s.get_id() == peer_id && (s.is_default() || s.is_view_camera()))
+        .collect();
+    for session in sessions.iter() {
+        session.save_display_settings(settings);
+    }
+    !sessions.is_empty()
+}
+
+#[cfg(not(any(target_os = "android", target_os = "ios", feature = "flutter")))]
+fn apply_to_sessions(_peer_id: &str, _settings: DisplaySettings) -> bool {
+    false
+}
+
+/// The display settings of `peer_id` as a JSON list of
+/// `{"display": 0, "quality": 50, "fps": 10, "codec": "vp9"}`.
+///
+/// The returned string must be freed with `rustdesk_unity_free`.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_get_display_settings(peer_id: *const c_char) -> *mut c_char {
+    let Some(peer_id) = cstr_to_string(peer_id) else {
+        return std::ptr::null_mut();
+    };
+    let config = PeerConfig::load(&peer_id);
+    let settings = crate::client::parse_display_settings(
+        &config
+            .options
+            .get(OPTION_DISPLAY_SETTINGS)
+            .cloned()
+            .unwrap_or_default(),
+    )
+    .into_iter()
+    .map(|s| {
+        json!({
+            "display": s.display,
+            "quality": s.quality,
+            "fps": s.fps,
+            "codec": s.codec_name(),
+        })
+    })
+    .collect::<Vec<_>>();
+    string_to_c_ret(&json!(settings).to_string())
+}
+
+/// Ask the host to encode the rect of `display` in the sessions with `peer_id` in a higher
+/// quality than the rest, e.g. around the gaze point of a VR headset, in the pixels of the
+/// display. A zero `width` or `height` clears it. See [`crate::priority_region`].
+///
+/// Returns `UNITY_ERR_NOT_FOUND` if there's no session with the peer.
+/// It's ignored if the encoder of the host doesn't support it.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_set_priority_region(
+    peer_id: *const c_char,
+    display: u32,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+) -> i32 {
+    let Some(peer_id) = cstr_to_string(peer_id) else {
+        return UNITY_ERR_INVALID_ARGS;
+    };
+    if set_priority_region(&peer_id, display as _, x, y, width, height) {
+        UNITY_OK
+    } else {
+        UNITY_ERR_NOT_FOUND
+    }
+}
+
+// Returns false if there's no session with the peer.
+#[cfg(any(target_os = "android", target_os = "ios", feature = "flutter"))]
+fn set_priority_region(peer_id: &str, display: usize, x: u32, y: u32, w: u32, h: u32) -> bool {
+    use crate::client::Interface;
+
+    let sessions: Vec<_> = crate::flutter::sessions::get_sessions()
+        .into_iter()
+        .filter(|s| s.get_id() == peer_id && s.is_default())
+        .collect();
+    for session in sessions.iter() {
+        session.set_priority_region(display, x, y, w, h);
+    }
+    !sessions.is_empty()
+}
+
+#[cfg(not(any(target_os = "android", target_os = "ios", feature = "flutter")))]
+fn set_priority_region(
+    _peer_id: &str,
+    _display: usize,
+    _x: u32,
+    _y: u32,
+    _w: u32,
+    _h: u32,
+) -> bool {
+    false
+}
@@ -0,0 +1,254 @@
+//! A local WebSocket server mirroring the session and plugin events of the Unity callbacks as
+//! JSON, for the web dashboards and the Electron tools to observe the client without the native
+//! bindings.
+//!
+//! It listens on `127.0.0.1` only, the clients connect to `ws://127.0.0.1:<port>/?token=<token>`
+//! and receive one text message per event:
+//!
+//! ```json
+//! {"source": "session", "peer_id": "123456789", "type": "session_expiry", "payload": {...}}
+//! ```
+//!
+//! `source` is `session` or `plugin`, the `payload` is the JSON of the callback, or a string if
+//! it's not JSON. The events are filtered by the subscriptions of the callbacks, the slow clients
+//! miss the events instead of blocking the sessions.
+//!
+//! Started by Unity with [`rustdesk_unity_start_event_bridge`], or by the app at the start with
+//! the local options [`OPTION_PORT`] and [`OPTION_TOKEN`].
+use std::{
+    ffi::c_char,
+    sync::{Arc, Mutex},
+};
+
+use hbb_common::{
+    bail,
+    config::LocalConfig,
+    futures::{SinkExt, StreamExt},
+    log,
+    tokio::{
+        self,
+        net::{TcpListener, TcpStream},
+        sync::{broadcast, oneshot},
+    },
+    ResultType,
+};
+use tokio_tungstenite::tungstenite::{
+    handshake::server::{ErrorResponse, Request, Response},
+    http::StatusCode,
+    Message,
+};
+
+use super::{cstr_to_string, UNITY_ERR_FAILED, UNITY_ERR_INVALID_ARGS};
+
+pub const SOURCE_SESSION: &str = "session";
+pub const SOURCE_PLUGIN: &str = "plugin";
+/// The local option of the port to start the bridge at the start of the app.
+pub const OPTION_PORT: &str = "event-bridge-port";
+/// The local option of the token of the clients.
+pub const OPTION_TOKEN: &str = "event-bridge-token";
+const CHANNEL_CAPACITY: usize = 256;
+
+struct Bridge {
+    port: u16,
+    events: broadcast::Sender<Arc<String>>,
+    stop: oneshot::Sender<()>,
+}
+
+lazy_static::lazy_static! {
+    static ref BRIDGE: Mutex<Option<Bridge>> = Default::default();
+}
+
+/// Start the bridge on `127.0.0.1:<port>`, 0 for a free port, replacing the running one.
+/// Returns the port listened on.
+pub fn start(port: u16, token: String) -> ResultType<u16> {
+    if token.is_empty() {
+        bail!("The token is required");
+    }
+    stop();
+    let listener = std::net::TcpListener::bind(("127.0.0.1", port))?;
+    listener.set_nonblocking(true)?;
+    let port = listener.local_addr()?.port();
+    let (events, _) = broadcast::channel(CHANNEL_CAPACITY);
+    let (stop, stopped) = oneshot::channel();
+    let tx = events.clone();
+    std::thread::spawn(move || run(listener, token, tx, stopped));
+    *BRIDGE.lock().unwrap() = Some(Bridge { port, events, stop });
+    log::info!("Event bridge listening on 127.0.0.1:{}", port);
+    Ok(port)
+}
+
+pub fn stop() {
+    if let Some(bridge) = BRIDGE.lock().unwrap().take() {
+        bridge.stop.send(()).ok();
+        log::info!("Event bridge on port {} stopped", bridge.port);
+    }
+}
+
+/// Start the bridge if the local options are set.
+pub fn start_with_options() {
+    let port = LocalConfig::get_option(OPTION_PORT);
+    let token = LocalConfig::get_option(OPTION_TOKEN);
+    if port.is_empty() || token.is_empty() {
+        return;
+    }
+    match port.parse() {
+        Ok(port) => {
+            if let Err(e) = start(port, token) {
+                log::error!("Failed to start the event bridge: {}", e);
+            }
+        }
+        Err(_) => log::error!("Invalid event bridge port: {}", port),
+    }
+}
+
+/// Pass an event to the clients, `payload` is the JSON of the callback.
+pub fn notify(source: &str, peer_id: &str, event_type: &str, payload: &str) {
+    let bridge = BRIDGE.lock().unwrap();
+    let Some(bridge) = bridge.as_ref() else {
+        return;
+    };
+    if bridge.events.receiver_count() == 0 {
+        return;
+    }
+    let event = to_json(source, peer_id, event_type, payload);
+    bridge.events.send(Arc::new(event)).ok();
+}
+
+pub(super) fn to_json(source: &str, peer_id: &str, event_type: &str, payload: &str) -> String {
+    let payload = serde_json::from_str(payload)
+        .unwrap_or_else(|_| serde_json::Value::String(payload.to_owned()));
+    serde_json::json!({
+        "source": source,
+        "peer_id": peer_id,
+        "type": event_type,
+        "payload": payload,
+    })
+    .to_string()
+}
+
+fn is_authorized(query: Option<&str>, token: &str) -> bool {
+    query
+        .unwrap_or_default()
+        .split('&')
+        .filter_map(|kv| kv.strip_prefix("token="))
+        .any(|t| crate::common::constant_time_eq(t, token))
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn run(
+    listener: std::net::TcpListener,
+    token: String,
+    events: broadcast::Sender<Arc<String>>,
+    mut stopped: oneshot::Receiver<()>,
+) {
+    let listener = match TcpListener::from_std(listener) {
+        Ok(listener) => listener,
+        Err(e) => {
+            log::error!("Failed to listen for the event bridge: {}", e);
+            return;
+        }
+    };
+    let token = Arc::new(token);
+    loop {
+        tokio::select! {
+            _ = &mut stopped => break,
+            res = listener.accept() => {
+                if let Ok((stream, addr)) = res {
+                    log::debug!("Event bridge client from {}", addr);
+                    tokio::spawn(serve(stream, token.clone(), events.subscribe()));
+                }
+            }
+        }
+    }
+}
+
+async fn serve(
+    stream: TcpStream,
+    token: Arc<String>,
+    mut events: broadcast::Receiver<Arc<String>>,
+) {
+    let auth = |req: &Request, res: Response| -> Result<Response, ErrorResponse> {
+        if is_authorized(req.uri().query(), &token) {
+            Ok(res)
+        } else {
+            let mut err = ErrorResponse::new(Some("Unauthorized".to_owned()));
+            *err.status_mut() = StatusCode::UNAUTHORIZED;
+            Err(err)
+        }
+    };
+    let mut ws = match tokio_tungstenite::accept_hdr_async(stream, auth).await {
+        Ok(ws) => ws,
+        Err(e) => {
+            log::warn!("Event bridge handshake failed: {}", e);
+            return;
+        }
+    };
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) => {
+                    if ws.send(Message::text(event.to_string())).await.is_err() {
+                        break;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    log::debug!("Event bridge client missed {} events", n);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            msg = ws.next() => match msg {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                _ => {}
+            },
+        }
+    }
+    ws.close(None).await.ok();
+}
+
+/// Start the WebSocket server mirroring the session and plugin events on `127.0.0.1:<port>`,
+/// 0 for a free port, see [`crate::unity::event_bridge`]. The clients must pass `token` in the
+/// query, it must not be empty. A running bridge is replaced.
+///
+/// Returns the port listened on, `UNITY_ERR_INVALID_ARGS` or `UNITY_ERR_FAILED`.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_start_event_bridge(port: u16, token: *const c_char) -> i32 {
+    let Some(token) = cstr_to_string(token).filter(|t| !t.is_empty()) else {
+        return UNITY_ERR_INVALID_ARGS;
+    };
+    match start(port, token) {
+        Ok(port) => port as i32,
+        Err(e) => {
+            log::error!("Failed to start the event bridge: {}", e);
+            UNITY_ERR_FAILED
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_stop_event_bridge() {
+    stop();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_json() {
+        let event: serde_json::Value = serde_json::from_str(&to_json(
+            "session",
+            "123",
+            "session_expiry",
+            "{\"remaining\":5}",
+        ))
+        .unwrap();
+        assert_eq!(event["type"], "session_expiry");
+        assert_eq!(event["payload"]["remaining"], 5);
+        let event: serde_json::Value =
+            serde_json::from_str(&to_json("plugin", "", "plugin_reload", "not json")).unwrap();
+        assert_eq!(event["payload"], "not json");
+        assert!(is_authorized(Some("a=1&token=abc"), "abc"));
+        assert!(!is_authorized(Some("token=abd"), "abc"));
+        assert!(!is_authorized(None, "abc"));
+    }
+}
@@ -0,0 +1,55 @@
+use std::ffi::c_char;
+
+use serde_json::json;
+
+use super::{cstr_to_string, notify_session_event, string_to_c_ret};
+
+/// The fingerprint of the end-to-end key of the session, see [`crate::common::session_fingerprint`].
+/// The payload is `{"fingerprint": string}`, sent on the connection, empty if not secured.
+/// The host shows the same one in its connection manager, compare them out-of-band.
+pub const EVENT_SESSION_FINGERPRINT: &str = "session_fingerprint";
+
+/// Called when the session with `peer_id` is connected.
+pub fn on_session_fingerprint(peer_id: &str, fingerprint: &str) {
+    notify_session_event(
+        peer_id,
+        EVENT_SESSION_FINGERPRINT,
+        &json!({ "fingerprint": fingerprint }).to_string(),
+    );
+}
+
+/// The fingerprint of the end-to-end key of the first session with `peer_id`,
+/// empty if there's no secured session.
+///
+/// The returned string must be freed with `rustdesk_unity_free`.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_get_session_fingerprint(peer_id: *const c_char) -> *mut c_char {
+    let fingerprint = cstr_to_string(peer_id)
+        .map(|peer_id| get_session_fingerprint(&peer_id))
+        .unwrap_or_default();
+    string_to_c_ret(&fingerprint)
+}
+
+/// The fingerprint of the public key of this host, empty if not registered yet.
+///
+/// The returned string must be freed with `rustdesk_unity_free`.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_get_fingerprint() -> *mut c_char {
+    string_to_c_ret(&crate::ui_interface::get_fingerprint())
+}
+
+#[cfg(any(target_os = "android", target_os = "ios", feature = "flutter"))]
+fn get_session_fingerprint(peer_id: &str) -> String {
+    use crate::client::Interface;
+
+    crate::flutter::sessions::get_sessions()
+        .into_iter()
+        .find(|s| s.get_id() == peer_id)
+        .map(|s| s.lc.read().unwrap().session_fingerprint.clone())
+        .unwrap_or_default()
+}
+
+#[cfg(not(any(target_os = "android", target_os = "ios", feature = "flutter")))]
+fn get_session_fingerprint(_peer_id: &str) -> String {
+    "".to_owned()
+}
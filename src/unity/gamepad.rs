@@ -0,0 +1,142 @@
+use std::ffi::c_char;
+
+use super::{cstr_to_string, UNITY_ERR_INVALID_ARGS, UNITY_ERR_NOT_FOUND, UNITY_OK};
+use crate::gamepad::{GamepadEvent, GamepadState, MAX_GAMEPADS};
+
+/// Send the state of the controller `index` (0..4) read by Unity to the sessions with `peer_id`.
+///
+/// `buttons` are the bits of `XINPUT_GAMEPAD`, the triggers are 0..255,
+/// the thumbs are -32768..32767 with up positive.
+/// The host plugs in a virtual controller on the first state and unplugs it when the session ends.
+///
+/// Returns `UNITY_ERR_NOT_FOUND` if there's no session with the peer.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_send_gamepad_state(
+    peer_id: *const c_char,
+    index: u32,
+    buttons: u16,
+    left_trigger: u8,
+    right_trigger: u8,
+    thumb_lx: i16,
+    thumb_ly: i16,
+    thumb_rx: i16,
+    thumb_ry: i16,
+) -> i32 {
+    send(
+        peer_id,
+        index,
+        Some(GamepadState {
+            buttons,
+            left_trigger,
+            right_trigger,
+            thumb_lx,
+            thumb_ly,
+            thumb_rx,
+            thumb_ry,
+        }),
+    )
+}
+
+/// Unplug the virtual controller `index` on the host.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_send_gamepad_disconnected(
+    peer_id: *const c_char,
+    index: u32,
+) -> i32 {
+    send(peer_id, index, None)
+}
+
+fn send(peer_id: *const c_char, index: u32, state: Option<GamepadState>) -> i32 {
+    let Some(peer_id) = cstr_to_string(peer_id) else {
+        return UNITY_ERR_INVALID_ARGS;
+    };
+    if index >= MAX_GAMEPADS as u32 {
+        return UNITY_ERR_INVALID_ARGS;
+    }
+    let event = GamepadEvent {
+        index: index as _,
+        state,
+    };
+    if send_to_sessions(&peer_id, event) {
+        UNITY_OK
+    } else {
+        UNITY_ERR_NOT_FOUND
+    }
+}
+
+// Returns false if there's no session with the peer.
+#[cfg(any(target_os = "android", target_os = "ios", feature = "flutter"))]
+fn send_to_sessions(peer_id: &str, event: GamepadEvent) -> bool {
+    use crate::client::Interface;
+
+    let sessions: Vec<_> = crate::flutter::sessions::get_sessions()
+        .into_iter()
+        .filter(|s| s.get_id() == peer_id && s.is_default())
+        .collect();
+    for session in sessions.iter() {
+        session.send_gamepad_event(event);
+    }
+    !sessions.is_empty()
+}
+
+#[cfg(not(any(target_os = "android", target_os = "ios", feature = "flutter")))]
+fn send_to_sessions(_peer_id: &str, _event: GamepadEvent) -> bool {
+    false
+}
+
+/// Forward the controllers connected to this device to the sessions with `peer_id`,
+/// instead of sending their state from Unity.
+#[cfg(feature = "gamepad")]
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_set_gamepad_forwarding(
+    peer_id: *const c_char,
+    enabled: bool,
+) -> i32 {
+    let Some(peer_id) = cstr_to_string(peer_id) else {
+        return UNITY_ERR_INVALID_ARGS;
+    };
+    hbb_common::log::info!("Set gamepad forwarding of {}: {}", peer_id, enabled);
+    if set_forwarding(&peer_id, enabled) {
+        UNITY_OK
+    } else {
+        UNITY_ERR_NOT_FOUND
+    }
+}
+
+#[cfg(all(
+    feature = "gamepad",
+    any(target_os = "android", target_os = "ios", feature = "flutter")
+))]
+fn set_forwarding(peer_id: &str, enabled: bool) -> bool {
+    use crate::client::Interface;
+
+    let sessions: Vec<_> = crate::flutter::sessions::get_sessions()
+        .into_iter()
+        .filter(|s| s.get_id() == peer_id && s.is_default())
+        .collect();
+    for session in sessions.iter() {
+        session.set_gamepad_forwarding(enabled);
+    }
+    !sessions.is_empty()
+}
+
+#[cfg(all(
+    feature = "gamepad",
+    not(any(target_os = "android", target_os = "ios", feature = "flutter"))
+))]
+fn set_forwarding(_peer_id: &str, _enabled: bool) -> bool {
+    false
+}
+
+/// The controllers connected to this device as a JSON list of `{"index": 0, "name": "..."}`.
+///
+/// The returned string must be freed with `rustdesk_unity_free`.
+#[cfg(feature = "gamepad")]
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_list_gamepads() -> *mut c_char {
+    let gamepads = crate::gamepad::local::get_gamepads()
+        .into_iter()
+        .map(|(index, name)| serde_json::json!({ "index": index, "name": name }))
+        .collect::<Vec<_>>();
+    super::string_to_c_ret(&serde_json::json!(gamepads).to_string())
+}
@@ -0,0 +1,71 @@
+use std::{ffi::c_char, sync::Mutex};
+
+use serde_derive::{Deserialize, Serialize};
+
+use super::{notify_session_event, string_to_c_ret};
+
+/// The state of the Android host of the bridge changed, the payload is [`HostState`],
+/// sent with an empty peer id.
+pub const EVENT_HOST_STATE: &str = "host_state";
+
+/// Reported by `HostService` of the Android bridge.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HostState {
+    /// The host service is running and available to the incoming connections.
+    pub available: bool,
+    /// The screen capture permission is granted.
+    pub capture: bool,
+    /// The screen is being captured for a session.
+    pub capturing: bool,
+    /// The accessibility service of the input is enabled.
+    pub input: bool,
+    /// Not killed by Doze, if the battery optimizations are ignored.
+    pub ignoring_battery_optimizations: bool,
+}
+
+lazy_static::lazy_static! {
+    static ref HOST_STATE: Mutex<HostState> = Default::default();
+}
+
+/// Update the state, and notify Unity if it's changed.
+pub fn on_host_state(state: HostState) {
+    {
+        let mut last = HOST_STATE.lock().unwrap();
+        if *last == state {
+            return;
+        }
+        *last = state.clone();
+    }
+    notify_session_event(
+        "",
+        EVENT_HOST_STATE,
+        &serde_json::to_string(&state).unwrap_or_default(),
+    );
+}
+
+/// The last [`HostState`] as JSON, for the state before the callback is registered.
+///
+/// The returned string must be freed with `rustdesk_unity_free`.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_get_host_state() -> *mut c_char {
+    let state = HOST_STATE.lock().unwrap().clone();
+    string_to_c_ret(&serde_json::to_string(&state).unwrap_or_default())
+}
+
+#[cfg(target_os = "android")]
+#[no_mangle]
+pub extern "system" fn Java_ffi_FFI_onHostStateChanged(
+    mut env: jni::JNIEnv,
+    _class: jni::objects::JClass,
+    state: jni::objects::JString,
+) {
+    let Ok(state) = env.get_string(&state) else {
+        return;
+    };
+    let state: String = state.into();
+    match serde_json::from_str::<HostState>(&state) {
+        Ok(state) => on_host_state(state),
+        Err(e) => hbb_common::log::error!("Invalid host state {}: {}", state, e),
+    }
+}
@@ -0,0 +1,213 @@
+//! The newest-frame-wins delivery of the video frames of a session.
+//!
+//! By default every decoded frame is passed to the video frame callback, so the frames queue up
+//! in Unity and the latency grows when its render loop falls behind. In [`MODE_LATEST`] the
+//! frames of the session are not passed to the callback, only the latest frame of each display
+//! is kept, replacing the one not taken yet, and the render loop copies it with
+//! [`rustdesk_unity_copy_latest_frame`] when it's ready to draw, so the latency is bounded by
+//! one frame. The mode is kept for the peer id until it's set back or the bridge is shut down.
+use std::{collections::HashMap, ffi::c_char, sync::Mutex};
+
+use hbb_common::log;
+
+use super::{cstr_to_string, UNITY_ERR_INVALID_ARGS, UNITY_ERR_NOT_FOUND, UNITY_OK};
+
+pub const MODE_CALLBACK: u32 = 0;
+pub const MODE_LATEST: u32 = 1;
+
+struct Frame {
+    width: u32,
+    height: u32,
+    stride: u32,
+    format: u32,
+    data: Vec<u8>,
+}
+
+#[derive(Default)]
+struct Slot {
+    frame: Option<Frame>,
+    // The buffer of the taken frame, to copy the next frame into.
+    spare: Vec<u8>,
+}
+
+#[derive(Default)]
+struct Session {
+    slots: HashMap<usize, Slot>,
+    // The frames replaced before they're taken.
+    dropped: u64,
+}
+
+lazy_static::lazy_static! {
+    // Only the sessions in `MODE_LATEST`.
+    static ref SESSIONS: Mutex<HashMap<String, Session>> = Default::default();
+}
+
+pub(super) fn reset() {
+    SESSIONS.lock().unwrap().clear();
+}
+
+/// Keep the frame if the session is in [`MODE_LATEST`], returns false to pass it to the callback.
+pub(super) fn store(
+    peer_id: &str,
+    display: usize,
+    width: u32,
+    height: u32,
+    stride: u32,
+    format: u32,
+    buffer: &[u8],
+) -> bool {
+    let mut sessions = SESSIONS.lock().unwrap();
+    let Some(session) = sessions.get_mut(peer_id) else {
+        return false;
+    };
+    let slot = session.slots.entry(display).or_default();
+    let mut data = match slot.frame.take() {
+        Some(frame) => {
+            session.dropped += 1;
+            frame.data
+        }
+        None => std::mem::take(&mut slot.spare),
+    };
+    data.clear();
+    data.extend_from_slice(buffer);
+    slot.frame = Some(Frame {
+        width,
+        height,
+        stride,
+        format,
+        data,
+    });
+    true
+}
+
+/// Set how the video frames of the session with `peer_id` are delivered,
+/// [`MODE_CALLBACK`] (default) or [`MODE_LATEST`], see [`crate::unity::latest_frame`].
+///
+/// Returns `UNITY_OK` or `UNITY_ERR_INVALID_ARGS`.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_set_frame_delivery_mode(peer_id: *const c_char, mode: u32) -> i32 {
+    let Some(peer_id) = cstr_to_string(peer_id) else {
+        return UNITY_ERR_INVALID_ARGS;
+    };
+    let mut sessions = SESSIONS.lock().unwrap();
+    match mode {
+        MODE_CALLBACK => {
+            sessions.remove(&peer_id);
+        }
+        MODE_LATEST => {
+            sessions.entry(peer_id.clone()).or_default();
+        }
+        _ => return UNITY_ERR_INVALID_ARGS,
+    }
+    log::info!("Set frame delivery mode of session {} to {}", peer_id, mode);
+    UNITY_OK
+}
+
+/// Copy the latest frame of `display` of the session with `peer_id` into `buffer` of
+/// `capacity` bytes, the frame is taken. The out parameters are in the same format as the
+/// arguments of the video frame callback, `len` is the bytes of the frame.
+///
+/// Returns `UNITY_OK`, `UNITY_ERR_NOT_FOUND` if there's no new frame since the last one
+/// taken, or `UNITY_ERR_INVALID_ARGS`. If `capacity` is too small, the frame is kept and only
+/// `len` is set, to copy it again with a larger buffer.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_copy_latest_frame(
+    peer_id: *const c_char,
+    display: u32,
+    buffer: *mut u8,
+    capacity: usize,
+    width: *mut u32,
+    height: *mut u32,
+    stride: *mut u32,
+    format: *mut u32,
+    len: *mut usize,
+) -> i32 {
+    let Some(peer_id) = cstr_to_string(peer_id) else {
+        return UNITY_ERR_INVALID_ARGS;
+    };
+    if buffer.is_null()
+        || width.is_null()
+        || height.is_null()
+        || stride.is_null()
+        || format.is_null()
+        || len.is_null()
+    {
+        return UNITY_ERR_INVALID_ARGS;
+    }
+    let mut sessions = SESSIONS.lock().unwrap();
+    let Some(slot) = sessions
+        .get_mut(&peer_id)
+        .and_then(|s| s.slots.get_mut(&(display as usize)))
+    else {
+        return UNITY_ERR_NOT_FOUND;
+    };
+    let Some(frame) = slot.frame.as_ref() else {
+        return UNITY_ERR_NOT_FOUND;
+    };
+    unsafe {
+        *len = frame.data.len();
+        if capacity < frame.data.len() {
+            return UNITY_ERR_INVALID_ARGS;
+        }
+        std::ptr::copy_nonoverlapping(frame.data.as_ptr(), buffer, frame.data.len());
+        *width = frame.width;
+        *height = frame.height;
+        *stride = frame.stride;
+        *format = frame.format;
+    }
+    if let Some(frame) = slot.frame.take() {
+        slot.spare = frame.data;
+    }
+    UNITY_OK
+}
+
+/// The frames of the session with `peer_id` replaced in [`MODE_LATEST`] before they're taken.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_get_dropped_frames(peer_id: *const c_char) -> u64 {
+    cstr_to_string(peer_id)
+        .and_then(|peer_id| SESSIONS.lock().unwrap().get(&peer_id).map(|s| s.dropped))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+
+    #[test]
+    fn test_latest_frame() {
+        let peer_id = CString::new("latest-frame-test").unwrap();
+        let copy = |buffer: &mut Vec<u8>| {
+            let (mut w, mut h, mut s, mut f, mut len) = (0, 0, 0, 0, 0);
+            let res = rustdesk_unity_copy_latest_frame(
+                peer_id.as_ptr(),
+                0,
+                buffer.as_mut_ptr(),
+                buffer.len(),
+                &mut w,
+                &mut h,
+                &mut s,
+                &mut f,
+                &mut len,
+            );
+            (res, w, len)
+        };
+        let mut buffer = vec![0u8; 4];
+        assert!(!store("latest-frame-test", 0, 1, 1, 4, 0, &[1; 4]));
+        assert_eq!(
+            rustdesk_unity_set_frame_delivery_mode(peer_id.as_ptr(), MODE_LATEST),
+            UNITY_OK
+        );
+        assert_eq!(copy(&mut buffer).0, UNITY_ERR_NOT_FOUND);
+        assert!(store("latest-frame-test", 0, 1, 1, 4, 0, &[1; 4]));
+        assert!(store("latest-frame-test", 0, 2, 1, 8, 0, &[2; 8]));
+        assert_eq!(rustdesk_unity_get_dropped_frames(peer_id.as_ptr()), 1);
+        assert_eq!(copy(&mut buffer), (UNITY_ERR_INVALID_ARGS, 0, 8));
+        buffer.resize(8, 0);
+        assert_eq!(copy(&mut buffer), (UNITY_OK, 2, 8));
+        assert_eq!(buffer, vec![2; 8]);
+        assert_eq!(copy(&mut buffer).0, UNITY_ERR_NOT_FOUND);
+        rustdesk_unity_set_frame_delivery_mode(peer_id.as_ptr(), MODE_CALLBACK);
+        assert!(!store("latest-frame-test", 0, 1, 1, 4, 0, &[1; 4]));
+    }
+}
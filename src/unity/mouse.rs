@@ -0,0 +1,45 @@
+use std::ffi::c_char;
+
+use super::{cstr_to_string, UNITY_ERR_INVALID_ARGS, UNITY_ERR_NOT_FOUND, UNITY_OK};
+
+/// Move the mouse of the peer by `dx`, `dy` pixels, for the first-person cameras and
+/// the 3D viewports on the peer. Unity locks its own cursor and reads the mouse delta.
+///
+/// Returns `UNITY_ERR_NOT_FOUND` if there's no session with the peer.
+/// The move is dropped if the peer does not support it.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_send_mouse_relative(
+    peer_id: *const c_char,
+    dx: i32,
+    dy: i32,
+) -> i32 {
+    let Some(peer_id) = cstr_to_string(peer_id) else {
+        return UNITY_ERR_INVALID_ARGS;
+    };
+    if send_to_sessions(&peer_id, dx, dy) {
+        UNITY_OK
+    } else {
+        UNITY_ERR_NOT_FOUND
+    }
+}
+
+// Returns false if there's no session with the peer.
+#[cfg(any(target_os = "android", target_os = "ios", feature = "flutter"))]
+fn send_to_sessions(peer_id: &str, dx: i32, dy: i32) -> bool {
+    use crate::client::Interface;
+    use crate::input::MOUSE_TYPE_MOVE_RELATIVE;
+
+    let sessions: Vec<_> = crate::flutter::sessions::get_sessions()
+        .into_iter()
+        .filter(|s| s.get_id() == peer_id && s.is_default())
+        .collect();
+    for session in sessions.iter() {
+        session.send_mouse(MOUSE_TYPE_MOVE_RELATIVE, dx, dy, false, false, false, false);
+    }
+    !sessions.is_empty()
+}
+
+#[cfg(not(any(target_os = "android", target_os = "ios", feature = "flutter")))]
+fn send_to_sessions(_peer_id: &str, _dx: i32, _dy: i32) -> bool {
+    false
+}
@@ -0,0 +1,75 @@
+use std::ffi::c_char;
+
+use hbb_common::config::PeerConfig;
+use serde_json::json;
+
+use super::{cstr_to_string, string_to_c_ret, UNITY_ERR_INVALID_ARGS, UNITY_OK};
+use crate::peer_metadata::{self, PeerMetadata};
+
+/// The stored peers, the most recent first, as a JSON list of
+/// `{"id": "123456789", "alias": "Lab PC", "note": "", "tags": ["lab"], "username": "",
+/// "hostname": "", "platform": "Windows", "usage": {"month": "2026-10", "this_month": {"sent": 0,
+/// "received": 0}, "last_month": {...}}}`, see [`crate::peer_metadata`] and
+/// [`crate::peer_usage`].
+///
+/// The returned string must be freed with `rustdesk_unity_free`.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_get_peers() -> *mut c_char {
+    let peers = PeerConfig::peers(None)
+        .into_iter()
+        .map(|(id, _, p)| {
+            let metadata = PeerMetadata::from_options(&p.options);
+            let usage = crate::peer_usage::get(&id);
+            json!({
+                "id": id,
+                "alias": metadata.alias,
+                "note": metadata.note,
+                "tags": metadata.tags,
+                "username": p.info.username,
+                "hostname": p.info.hostname,
+                "platform": p.info.platform,
+                "usage": usage,
+            })
+        })
+        .collect::<Vec<_>>();
+    string_to_c_ret(&json!(peers).to_string())
+}
+
+/// Replace the alias, note and tags of the peer with the JSON object
+/// `{"alias": "Lab PC", "note": "", "tags": ["lab"]}`, a missing field is cleared.
+///
+/// Returns `UNITY_OK` or `UNITY_ERR_INVALID_ARGS`.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_set_peer_metadata(
+    peer_id: *const c_char,
+    metadata: *const c_char,
+) -> i32 {
+    let Some(peer_id) = cstr_to_string(peer_id).filter(|id| !id.is_empty()) else {
+        return UNITY_ERR_INVALID_ARGS;
+    };
+    let Some(metadata) =
+        cstr_to_string(metadata).and_then(|m| serde_json::from_str::<PeerMetadata>(&m).ok())
+    else {
+        return UNITY_ERR_INVALID_ARGS;
+    };
+    metadata.store(&peer_id);
+    UNITY_OK
+}
+
+/// The colors of the tags as a JSON map of the tag to the ARGB color.
+///
+/// The returned string must be freed with `rustdesk_unity_free`.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_get_peer_tag_colors() -> *mut c_char {
+    string_to_c_ret(&json!(peer_metadata::get_tag_colors()).to_string())
+}
+
+/// Set the ARGB color of the tag, 0 to use the default one.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_set_peer_tag_color(tag: *const c_char, color: u32) -> i32 {
+    let Some(tag) = cstr_to_string(tag).filter(|t| !t.trim().is_empty()) else {
+        return UNITY_ERR_INVALID_ARGS;
+    };
+    peer_metadata::set_tag_color(&tag, Some(color).filter(|c| *c != 0));
+    UNITY_OK
+}
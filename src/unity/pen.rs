@@ -0,0 +1,64 @@
+use std::ffi::c_char;
+
+use super::{cstr_to_string, UNITY_ERR_INVALID_ARGS, UNITY_ERR_NOT_FOUND, UNITY_OK};
+use crate::pen::{PenEvent, MAX_PRESSURE, MAX_TILT};
+
+/// Send the pen read by Unity to the sessions with `peer_id`.
+///
+/// `x` and `y` are in the coordinates of the peer's displays, the same as the mouse.
+/// `pressure` is 0..1024, the tilts are -90..90 degrees with right and toward the user positive.
+/// `flags` are the bits of in range (1), contact (2), barrel (4), eraser (8) and inverted (16).
+/// Send an event without the in range bit when the pen leaves the tablet.
+///
+/// Returns `UNITY_ERR_NOT_FOUND` if there's no session with the peer.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_send_pen(
+    peer_id: *const c_char,
+    x: i32,
+    y: i32,
+    pressure: u16,
+    tilt_x: i8,
+    tilt_y: i8,
+    flags: u8,
+) -> i32 {
+    let Some(peer_id) = cstr_to_string(peer_id) else {
+        return UNITY_ERR_INVALID_ARGS;
+    };
+    let tilt_range = -MAX_TILT..=MAX_TILT;
+    if pressure > MAX_PRESSURE || !tilt_range.contains(&tilt_x) || !tilt_range.contains(&tilt_y) {
+        return UNITY_ERR_INVALID_ARGS;
+    }
+    let event = PenEvent {
+        x,
+        y,
+        pressure,
+        tilt_x,
+        tilt_y,
+        flags,
+    };
+    if send_to_sessions(&peer_id, event) {
+        UNITY_OK
+    } else {
+        UNITY_ERR_NOT_FOUND
+    }
+}
+
+// Returns false if there's no session with the peer.
+#[cfg(any(target_os = "android", target_os = "ios", feature = "flutter"))]
+fn send_to_sessions(peer_id: &str, event: PenEvent) -> bool {
+    use crate::client::Interface;
+
+    let sessions: Vec<_> = crate::flutter::sessions::get_sessions()
+        .into_iter()
+        .filter(|s| s.get_id() == peer_id && s.is_default())
+        .collect();
+    for session in sessions.iter() {
+        session.send_pen_event(event);
+    }
+    !sessions.is_empty()
+}
+
+#[cfg(not(any(target_os = "android", target_os = "ios", feature = "flutter")))]
+fn send_to_sessions(_peer_id: &str, _event: PenEvent) -> bool {
+    false
+}
@@ -0,0 +1,23 @@
+use std::ffi::c_char;
+
+use super::{cstr_to_string, UNITY_ERR_INVALID_ARGS, UNITY_OK};
+
+/// Keep the connections to the peers `peer_ids`, separated by commas, warm in the background,
+/// so the sessions to them get the first frame sooner, see [`crate::client::prewarm`].
+///
+/// An empty string stops all. The peers are saved to the local config and pre-warmed again
+/// at the next start. Returns `UNITY_OK` or `UNITY_ERR_INVALID_ARGS`.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_set_prewarm_peers(peer_ids: *const c_char) -> i32 {
+    let Some(peer_ids) = cstr_to_string(peer_ids) else {
+        return UNITY_ERR_INVALID_ARGS;
+    };
+    let peer_ids = peer_ids
+        .split(',')
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_owned())
+        .collect();
+    crate::client::prewarm::set_peers(peer_ids);
+    UNITY_OK
+}
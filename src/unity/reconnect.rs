@@ -0,0 +1,208 @@
+use std::{
+    collections::HashMap,
+    ffi::c_char,
+    sync::RwLock,
+    time::Duration,
+};
+
+use hbb_common::log;
+use serde_derive::{Deserialize, Serialize};
+use serde_json::json;
+
+use super::{
+    cstr_to_string, notify_session_event, string_to_c_ret, UNITY_ERR_INVALID_ARGS, UNITY_OK,
+};
+
+pub const EVENT_RECONNECT_ATTEMPT: &str = "reconnect_attempt";
+pub const EVENT_RECONNECT_SUCCEEDED: &str = "reconnect_succeeded";
+pub const EVENT_RECONNECT_FAILED: &str = "reconnect_failed";
+
+/// Auto-reconnect policy of a session.
+///
+/// The policy is disabled by default, the UI decides whether to reconnect.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ReconnectPolicy {
+    pub enabled: bool,
+    /// 0 means no limit.
+    pub max_attempts: u32,
+    pub initial_delay_ms: u64,
+    pub max_delay_ms: u64,
+    pub backoff_multiplier: f64,
+    /// Restore the unfinished file transfer jobs after reconnected.
+    pub resume_session: bool,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_attempts: 5,
+            initial_delay_ms: 1_000,
+            max_delay_ms: 30_000,
+            backoff_multiplier: 2.0,
+            resume_session: true,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// The delay before the `attempt`th (1-based) reconnect.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exp = attempt.saturating_sub(1).min(32) as i32;
+        let multiplier = if self.backoff_multiplier.is_finite() && self.backoff_multiplier >= 1. {
+            self.backoff_multiplier
+        } else {
+            1.
+        };
+        let ms = (self.initial_delay_ms as f64 * multiplier.powi(exp)).min(self.max_delay_ms as f64);
+        Duration::from_millis(ms.max(0.) as u64)
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref DEFAULT_POLICY: RwLock<ReconnectPolicy> = Default::default();
+    static ref PEER_POLICIES: RwLock<HashMap<String, ReconnectPolicy>> = Default::default();
+    // peer id -> attempts since the last established connection
+    static ref ATTEMPTS: RwLock<HashMap<String, u32>> = Default::default();
+}
+
+pub fn get_policy(peer_id: &str) -> ReconnectPolicy {
+    if let Some(policy) = PEER_POLICIES.read().unwrap().get(peer_id) {
+        return policy.clone();
+    }
+    DEFAULT_POLICY.read().unwrap().clone()
+}
+
+#[inline]
+pub fn resume_session(peer_id: &str) -> bool {
+    get_policy(peer_id).resume_session
+}
+
+#[inline]
+pub fn is_reconnecting(peer_id: &str) -> bool {
+    ATTEMPTS.read().unwrap().get(peer_id).is_some()
+}
+
+/// Called when the connection to `peer_id` is lost unexpectedly.
+///
+/// Returns the delay before the next attempt, or `None` if no more attempts should be made.
+pub fn next_attempt(peer_id: &str, reason: &str) -> Option<Duration> {
+    let policy = get_policy(peer_id);
+    if !policy.enabled {
+        return None;
+    }
+    let mut attempts = ATTEMPTS.write().unwrap();
+    let attempt = attempts.get(peer_id).cloned().unwrap_or_default() + 1;
+    if policy.max_attempts > 0 && attempt > policy.max_attempts {
+        attempts.remove(peer_id);
+        drop(attempts);
+        log::info!(
+            "Give up reconnecting to {} after {} attempts: {}",
+            peer_id,
+            attempt - 1,
+            reason
+        );
+        notify_session_event(
+            peer_id,
+            EVENT_RECONNECT_FAILED,
+            &json!({
+                "attempts": attempt - 1,
+                "reason": reason,
+            })
+            .to_string(),
+        );
+        return None;
+    }
+    attempts.insert(peer_id.to_owned(), attempt);
+    drop(attempts);
+    let delay = policy.delay(attempt);
+    log::info!(
+        "Reconnect to {} in {:?}, attempt {}/{}: {}",
+        peer_id,
+        delay,
+        attempt,
+        policy.max_attempts,
+        reason
+    );
+    notify_session_event(
+        peer_id,
+        EVENT_RECONNECT_ATTEMPT,
+        &json!({
+            "attempt": attempt,
+            "max_attempts": policy.max_attempts,
+            "delay_ms": delay.as_millis() as u64,
+            "reason": reason,
+        })
+        .to_string(),
+    );
+    Some(delay)
+}
+
+/// Called when the connection to `peer_id` is established.
+pub fn on_connected(peer_id: &str) {
+    if let Some(attempts) = ATTEMPTS.write().unwrap().remove(peer_id) {
+        notify_session_event(
+            peer_id,
+            EVENT_RECONNECT_SUCCEEDED,
+            &json!({ "attempts": attempts }).to_string(),
+        );
+    }
+}
+
+/// Stop counting attempts, e.g. the session is closed by the user.
+pub fn cancel(peer_id: &str) {
+    ATTEMPTS.write().unwrap().remove(peer_id);
+}
+
+/// Forget all pending attempts, the policies are kept.
+pub(crate) fn reset() {
+    ATTEMPTS.write().unwrap().clear();
+}
+
+/// Set the reconnect policy from a JSON object, see [`ReconnectPolicy`] for the fields.
+///
+/// A null `peer_id` sets the default policy of all sessions.
+/// A null `policy` removes the policy of `peer_id`.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_set_reconnect_policy(
+    peer_id: *const c_char,
+    policy: *const c_char,
+) -> i32 {
+    let peer_id = cstr_to_string(peer_id);
+    let policy = match cstr_to_string(policy) {
+        Some(policy) => match serde_json::from_str::<ReconnectPolicy>(&policy) {
+            Ok(policy) => Some(policy),
+            Err(err) => {
+                log::warn!("Invalid reconnect policy '{}': {}", policy, err);
+                return UNITY_ERR_INVALID_ARGS;
+            }
+        },
+        None => None,
+    };
+    match (peer_id, policy) {
+        (Some(peer_id), Some(policy)) => {
+            PEER_POLICIES.write().unwrap().insert(peer_id, policy);
+        }
+        (Some(peer_id), None) => {
+            PEER_POLICIES.write().unwrap().remove(&peer_id);
+        }
+        (None, Some(policy)) => {
+            *DEFAULT_POLICY.write().unwrap() = policy;
+        }
+        (None, None) => return UNITY_ERR_INVALID_ARGS,
+    }
+    UNITY_OK
+}
+
+/// Get the effective reconnect policy of `peer_id` as JSON, or the default one if `peer_id` is null.
+///
+/// The returned string must be freed with `rustdesk_unity_free`.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_get_reconnect_policy(peer_id: *const c_char) -> *mut c_char {
+    let policy = match cstr_to_string(peer_id) {
+        Some(peer_id) => get_policy(&peer_id),
+        None => DEFAULT_POLICY.read().unwrap().clone(),
+    };
+    string_to_c_ret(&serde_json::to_string(&policy).unwrap_or_default())
+}
@@ -0,0 +1,58 @@
+use std::ffi::c_char;
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+use super::{cstr_to_string, string_to_c_ret, UNITY_ERR_FAILED, UNITY_OK};
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+use super::{string_to_c_ret, UNITY_ERR_NOT_FOUND};
+
+/// Install and start the background service running `exe`, see [`crate::service_manager`].
+///
+/// `exe` is the executable of the service, null or empty for the current one.
+/// It may show the password prompt of the system and block until it's answered.
+/// Returns `UNITY_OK`, `UNITY_ERR_FAILED`, or `UNITY_ERR_NOT_FOUND` if not supported.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_service_install(exe: *const c_char) -> i32 {
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    return match crate::service_manager::install(cstr_to_string(exe)) {
+        Ok(()) => UNITY_OK,
+        Err(e) => {
+            hbb_common::log::error!("Failed to install the service: {}", e);
+            UNITY_ERR_FAILED
+        }
+    };
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    {
+        let _ = exe;
+        return UNITY_ERR_NOT_FOUND;
+    }
+}
+
+/// Stop and uninstall the background service.
+///
+/// Returns `UNITY_OK`, `UNITY_ERR_FAILED`, or `UNITY_ERR_NOT_FOUND` if not supported.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_service_uninstall() -> i32 {
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    return match crate::service_manager::uninstall() {
+        Ok(()) => UNITY_OK,
+        Err(e) => {
+            hbb_common::log::error!("Failed to uninstall the service: {}", e);
+            UNITY_ERR_FAILED
+        }
+    };
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    return UNITY_ERR_NOT_FOUND;
+}
+
+/// The status of the background service, the JSON
+/// `{"installed": bool, "running": bool, "path": string, "exe": string}`, `{}` if not supported.
+///
+/// The returned string must be freed with `rustdesk_unity_free`.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_service_status() -> *mut c_char {
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    let status = serde_json::to_string(&crate::service_manager::status()).unwrap_or_default();
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    let status = "{}".to_owned();
+    string_to_c_ret(&status)
+}
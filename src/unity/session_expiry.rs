@@ -0,0 +1,18 @@
+use serde_json::json;
+
+use super::notify_session_event;
+
+/// The host limits the time of the session, see [`crate::session_expiry`].
+/// The payload is `{"remaining": seconds, "view_only": bool}`, sent after the login,
+/// a few times before the expiry and on the expiry with `remaining` 0.
+/// If `view_only` is true, the permissions are revoked on the expiry, otherwise it's disconnected.
+pub const EVENT_SESSION_EXPIRY: &str = "session_expiry";
+
+/// Called when the host of the session with `peer_id` sends the remaining time.
+pub fn on_expiry(peer_id: &str, remaining: u64, view_only: bool) {
+    notify_session_event(
+        peer_id,
+        EVENT_SESSION_EXPIRY,
+        &json!({ "remaining": remaining, "view_only": view_only }).to_string(),
+    );
+}
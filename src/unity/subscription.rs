@@ -0,0 +1,68 @@
+//! Event subscriptions of the embedder.
+//!
+//! By default all events are passed to the registered callbacks. A subscription limits them
+//! to some event types and peers, so the high-frequency events don't reach the callbacks
+//! which only care about the lifecycle events.
+use std::{collections::HashSet, ffi::c_char, sync::RwLock};
+
+use super::{cstr_to_string, UNITY_OK};
+
+/// `None` matches all.
+#[derive(Debug, Default, Clone)]
+pub struct Subscription {
+    event_types: Option<HashSet<String>>,
+    peers: Option<HashSet<String>>,
+}
+
+fn parse_list(ptr: *const c_char) -> Option<HashSet<String>> {
+    let set = cstr_to_string(ptr)?
+        .split(',')
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_owned())
+        .collect::<HashSet<_>>();
+    Some(set).filter(|s| !s.is_empty())
+}
+
+impl Subscription {
+    /// `event_types` and `peers` are comma separated lists, null or empty for all.
+    pub fn from_c(event_types: *const c_char, peers: *const c_char) -> Self {
+        Self {
+            event_types: parse_list(event_types),
+            peers: parse_list(peers),
+        }
+    }
+
+    /// An empty `peer_id` is for the events not bound to a peer, they are only filtered by the type.
+    pub fn matches(&self, event_type: &str, peer_id: &str) -> bool {
+        self.event_types
+            .as_ref()
+            .map_or(true, |t| t.contains(event_type))
+            && (peer_id.is_empty() || self.peers.as_ref().map_or(true, |p| p.contains(peer_id)))
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref SESSION_EVENTS: RwLock<Subscription> = Default::default();
+}
+
+#[inline]
+pub fn is_session_event_subscribed(event_type: &str, peer_id: &str) -> bool {
+    SESSION_EVENTS.read().unwrap().matches(event_type, peer_id)
+}
+
+/// Only pass the session events of `event_types` and `peers` to the session event callbacks
+/// of Unity and Unreal. Both are comma separated lists, null or empty for all.
+/// The last call replaces the previous subscription.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_subscribe_session_events(
+    event_types: *const c_char,
+    peers: *const c_char,
+) -> i32 {
+    *SESSION_EVENTS.write().unwrap() = Subscription::from_c(event_types, peers);
+    UNITY_OK
+}
+
+pub(super) fn reset() {
+    *SESSION_EVENTS.write().unwrap() = Default::default();
+}
@@ -0,0 +1,185 @@
+use std::ffi::c_char;
+
+use hbb_common::message_proto::{terminal_response::Union, TerminalResponse};
+use serde_json::json;
+
+use super::{
+    cstr_to_string, notify_session_event, UNITY_ERR_INVALID_ARGS, UNITY_ERR_NOT_FOUND, UNITY_OK,
+};
+
+/// The output and the state of the terminals of a terminal session, the same as the
+/// `terminal_response` event of Flutter. The payload is one of
+/// - `{"type": "opened", "terminal_id", "success", "message", "pid", "service_id"}`
+/// - `{"type": "data", "terminal_id", "data"}`, `data` is the output in base64, the scrollback
+///   of the terminal is sent first when it's opened again after reconnecting
+/// - `{"type": "closed", "terminal_id", "exit_code"}`
+/// - `{"type": "error", "terminal_id", "message"}`
+pub const EVENT_TERMINAL: &str = "terminal";
+
+#[cfg_attr(
+    not(any(target_os = "android", target_os = "ios", feature = "flutter")),
+    allow(dead_code)
+)]
+enum Action {
+    Open { rows: u32, cols: u32 },
+    Input(String),
+    Resize { rows: u32, cols: u32 },
+    Close,
+}
+
+/// Called when the terminal session with `peer_id` receives a response of the host.
+pub fn on_response(peer_id: &str, response: &TerminalResponse) {
+    if let Some(payload) = to_payload(response) {
+        notify_session_event(peer_id, EVENT_TERMINAL, &payload.to_string());
+    }
+}
+
+fn to_payload(response: &TerminalResponse) -> Option<serde_json::Value> {
+    let payload = match &response.union {
+        Some(Union::Opened(opened)) => json!({
+            "type": "opened",
+            "terminal_id": opened.terminal_id,
+            "success": opened.success,
+            "message": opened.message,
+            "pid": opened.pid,
+            "service_id": opened.service_id,
+        }),
+        Some(Union::Data(data)) => {
+            let output = if data.compressed {
+                hbb_common::compress::decompress(&data.data)
+            } else {
+                data.data.to_vec()
+            };
+            json!({
+                "type": "data",
+                "terminal_id": data.terminal_id,
+                "data": crate::encode64(&output),
+            })
+        }
+        Some(Union::Closed(closed)) => json!({
+            "type": "closed",
+            "terminal_id": closed.terminal_id,
+            "exit_code": closed.exit_code,
+        }),
+        Some(Union::Error(error)) => json!({
+            "type": "error",
+            "terminal_id": error.terminal_id,
+            "message": error.message,
+        }),
+        _ => return None,
+    };
+    Some(payload)
+}
+
+/// Open the terminal `terminal_id` with the size of `rows` x `cols` in the terminal session
+/// with `peer_id`, or open it again after reconnecting. The result is the "opened" event.
+///
+/// Returns `UNITY_ERR_NOT_FOUND` if there's no terminal session with the peer.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_open_terminal(
+    peer_id: *const c_char,
+    terminal_id: i32,
+    rows: u32,
+    cols: u32,
+) -> i32 {
+    let Some(peer_id) = cstr_to_string(peer_id) else {
+        return UNITY_ERR_INVALID_ARGS;
+    };
+    if rows == 0 || cols == 0 {
+        return UNITY_ERR_INVALID_ARGS;
+    }
+    send_to_session(&peer_id, terminal_id, Action::Open { rows, cols })
+}
+
+/// Send the UTF-8 `data`, e.g. the typed keys, to the terminal.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_send_terminal_input(
+    peer_id: *const c_char,
+    terminal_id: i32,
+    data: *const c_char,
+) -> i32 {
+    let (Some(peer_id), Some(data)) = (cstr_to_string(peer_id), cstr_to_string(data)) else {
+        return UNITY_ERR_INVALID_ARGS;
+    };
+    send_to_session(&peer_id, terminal_id, Action::Input(data))
+}
+
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_resize_terminal(
+    peer_id: *const c_char,
+    terminal_id: i32,
+    rows: u32,
+    cols: u32,
+) -> i32 {
+    let Some(peer_id) = cstr_to_string(peer_id) else {
+        return UNITY_ERR_INVALID_ARGS;
+    };
+    if rows == 0 || cols == 0 {
+        return UNITY_ERR_INVALID_ARGS;
+    }
+    send_to_session(&peer_id, terminal_id, Action::Resize { rows, cols })
+}
+
+/// Close the terminal, the shell of the host exits. The result is the "closed" event.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_close_terminal(peer_id: *const c_char, terminal_id: i32) -> i32 {
+    let Some(peer_id) = cstr_to_string(peer_id) else {
+        return UNITY_ERR_INVALID_ARGS;
+    };
+    send_to_session(&peer_id, terminal_id, Action::Close)
+}
+
+// Returns `UNITY_ERR_NOT_FOUND` if there's no terminal session with the peer.
+#[cfg(any(target_os = "android", target_os = "ios", feature = "flutter"))]
+fn send_to_session(peer_id: &str, terminal_id: i32, action: Action) -> i32 {
+    use crate::client::Interface;
+
+    let Some(session) = crate::flutter::sessions::get_sessions()
+        .into_iter()
+        .find(|s| s.get_id() == peer_id && s.is_terminal())
+    else {
+        return UNITY_ERR_NOT_FOUND;
+    };
+    match action {
+        Action::Open { rows, cols } => session.open_terminal(terminal_id, rows, cols),
+        Action::Input(data) => session.send_terminal_input(terminal_id, data),
+        Action::Resize { rows, cols } => session.resize_terminal(terminal_id, rows, cols),
+        Action::Close => session.close_terminal(terminal_id),
+    }
+    UNITY_OK
+}
+
+#[cfg(not(any(target_os = "android", target_os = "ios", feature = "flutter")))]
+fn send_to_session(_peer_id: &str, _terminal_id: i32, _action: Action) -> i32 {
+    UNITY_ERR_NOT_FOUND
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hbb_common::message_proto::{TerminalClosed, TerminalData};
+
+    #[test]
+    fn test_to_payload() {
+        let mut response = TerminalResponse::new();
+        let mut data = TerminalData::new();
+        data.terminal_id = 1;
+        data.data = hbb_common::compress::compress(b"ls\r\n").into();
+        data.compressed = true;
+        response.set_data(data);
+        let v = to_payload(&response).unwrap();
+        assert_eq!(v["type"], "data");
+        assert_eq!(v["terminal_id"], 1);
+        assert_eq!(v["data"], crate::encode64(b"ls\r\n"));
+
+        let mut closed = TerminalClosed::new();
+        closed.terminal_id = 2;
+        closed.exit_code = 127;
+        response.set_closed(closed);
+        let v = to_payload(&response).unwrap();
+        assert_eq!(v["type"], "closed");
+        assert_eq!(v["exit_code"], 127);
+
+        assert!(to_payload(&TerminalResponse::new()).is_none());
+    }
+}
@@ -0,0 +1,18 @@
+use std::ffi::c_char;
+
+use super::{cstr_to_string, UNITY_ERR_INVALID_ARGS};
+
+/// Wake the sleeping peer `peer_id`, see [`crate::wake_on_lan`].
+///
+/// The magic packet is sent on the local LAN if the peer was discovered on it,
+/// and via the sessions to the other peers last seen on the LAN of the peer.
+/// Returns the number of these peers, 0 if none is online, or `UNITY_ERR_INVALID_ARGS`.
+#[no_mangle]
+pub extern "C" fn rustdesk_unity_wake_peer(peer_id: *const c_char) -> i32 {
+    let Some(peer_id) = cstr_to_string(peer_id) else {
+        return UNITY_ERR_INVALID_ARGS;
+    };
+    #[cfg(not(target_os = "ios"))]
+    crate::lan::send_wol(peer_id.clone());
+    crate::wake_on_lan::wake_via_peers(&peer_id) as _
+}
@@ -0,0 +1,157 @@
+//! Native bridge for Unreal Engine plugins.
+//!
+//! It shares the sessions and the controls of the Unity bridge in [`crate::unity`], all `rustdesk_unity_*`
+//! setters take plain arguments and can be called from Unreal as well.
+//! The callbacks differ: frames and session events are described by `#[repr(C)]` structs instead of
+//! positional arguments and JSON payloads, and every callback gets back an opaque `user_data`,
+//! so they can be bound to a `UObject` without globals.
+use std::{
+    ffi::{c_char, c_void, CString},
+    sync::RwLock,
+};
+
+use hbb_common::log;
+
+use crate::unity::reconnect::{
+    EVENT_RECONNECT_ATTEMPT, EVENT_RECONNECT_FAILED, EVENT_RECONNECT_SUCCEEDED,
+};
+
+/// Version of the `rustdesk_unreal_*` ABI.
+pub const UNREAL_API_VERSION: u32 = 1;
+
+pub const UNREAL_EVENT_OTHER: u32 = 0;
+pub const UNREAL_EVENT_RECONNECT_ATTEMPT: u32 = 1;
+pub const UNREAL_EVENT_RECONNECT_SUCCEEDED: u32 = 2;
+pub const UNREAL_EVENT_RECONNECT_FAILED: u32 = 3;
+
+/// A decoded frame, valid only during the callback.
+#[repr(C)]
+pub struct UnrealFrameDesc {
+    pub peer_id: *const c_char,
+    pub display: u32,
+    pub width: u32,
+    pub height: u32,
+    pub stride: u32,
+    /// Same values as the pixel formats of `rustdesk_unity_get_capabilities`.
+    pub format: u32,
+    pub data: *const u8,
+    pub len: usize,
+}
+
+/// A session event, valid only during the callback.
+#[repr(C)]
+pub struct UnrealSessionEvent {
+    pub peer_id: *const c_char,
+    /// One of `UNREAL_EVENT_*`.
+    pub kind: u32,
+    /// The reconnect attempt, or the number of attempts when reconnecting ends.
+    pub attempt: u32,
+    /// The delay before the next reconnect attempt.
+    pub delay_ms: u64,
+    /// The reason of the event or the event name if `kind` is `UNREAL_EVENT_OTHER`, may be empty.
+    pub message: *const c_char,
+}
+
+pub type UnrealVideoFrameCallback =
+    Option<extern "C" fn(frame: *const UnrealFrameDesc, user_data: *mut c_void)>;
+
+pub type UnrealSessionEventCallback =
+    Option<extern "C" fn(event: *const UnrealSessionEvent, user_data: *mut c_void)>;
+
+// The user data is kept as usize, raw pointers are not `Send`.
+lazy_static::lazy_static! {
+    static ref VIDEO_FRAME_CALLBACK: RwLock<(UnrealVideoFrameCallback, usize)> = RwLock::new((None, 0));
+    static ref SESSION_EVENT_CALLBACK: RwLock<(UnrealSessionEventCallback, usize)> = RwLock::new((None, 0));
+}
+
+#[no_mangle]
+pub extern "C" fn rustdesk_unreal_get_api_version() -> u32 {
+    UNREAL_API_VERSION
+}
+
+#[no_mangle]
+pub extern "C" fn rustdesk_unreal_register_video_frame_callback(
+    callback: UnrealVideoFrameCallback,
+    user_data: *mut c_void,
+) {
+    *VIDEO_FRAME_CALLBACK.write().unwrap() = (callback, user_data as usize);
+}
+
+#[no_mangle]
+pub extern "C" fn rustdesk_unreal_register_session_event_callback(
+    callback: UnrealSessionEventCallback,
+    user_data: *mut c_void,
+) {
+    *SESSION_EVENT_CALLBACK.write().unwrap() = (callback, user_data as usize);
+}
+
+/// Unregister the Unreal callbacks and shut down the shared bridge, see `rustdesk_unity_shutdown`.
+#[no_mangle]
+pub extern "C" fn rustdesk_unreal_shutdown() {
+    *VIDEO_FRAME_CALLBACK.write().unwrap() = (None, 0);
+    *SESSION_EVENT_CALLBACK.write().unwrap() = (None, 0);
+    crate::unity::rustdesk_unity_shutdown();
+}
+
+#[inline]
+pub(crate) fn has_video_frame_callback() -> bool {
+    VIDEO_FRAME_CALLBACK.read().unwrap().0.is_some()
+}
+
+pub(crate) fn notify_video_frame(frame: UnrealFrameDesc) {
+    let (callback, user_data) = *VIDEO_FRAME_CALLBACK.read().unwrap();
+    if let Some(callback) = callback {
+        callback(&frame, user_data as *mut c_void);
+    }
+}
+
+fn event_kind(event_type: &str) -> u32 {
+    match event_type {
+        EVENT_RECONNECT_ATTEMPT => UNREAL_EVENT_RECONNECT_ATTEMPT,
+        EVENT_RECONNECT_SUCCEEDED => UNREAL_EVENT_RECONNECT_SUCCEEDED,
+        EVENT_RECONNECT_FAILED => UNREAL_EVENT_RECONNECT_FAILED,
+        _ => UNREAL_EVENT_OTHER,
+    }
+}
+
+/// Flatten a session event of the Unity bridge into [`UnrealSessionEvent`].
+pub(crate) fn notify_session_event(peer_id: &str, event_type: &str, payload: &str) {
+    let (Some(callback), user_data) = *SESSION_EVENT_CALLBACK.read().unwrap() else {
+        return;
+    };
+    let kind = event_kind(event_type);
+    let payload: serde_json::Value = serde_json::from_str(payload).unwrap_or_default();
+    let attempt = payload
+        .get("attempt")
+        .or_else(|| payload.get("attempts"))
+        .and_then(|v| v.as_u64())
+        .unwrap_or_default() as u32;
+    let delay_ms = payload
+        .get("delay_ms")
+        .and_then(|v| v.as_u64())
+        .unwrap_or_default();
+    let message = if kind == UNREAL_EVENT_OTHER {
+        event_type
+    } else {
+        payload
+            .get("reason")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+    };
+    let (Ok(c_peer_id), Ok(c_message)) = (CString::new(peer_id), CString::new(message)) else {
+        log::warn!(
+            "Failed to convert session event '{}' of {} into CString",
+            event_type,
+            peer_id
+        );
+        return;
+    };
+    let event = UnrealSessionEvent {
+        peer_id: c_peer_id.as_ptr(),
+        kind,
+        attempt,
+        delay_ms,
+        message: c_message.as_ptr(),
+    };
+    callback(&event, user_data as *mut c_void);
+}
@@ -0,0 +1,61 @@
+//! Virtual monitors of the given resolution added from the session, for the client to extend the
+//! desktop of the Windows host with the virtual display driver instead of mirroring it.
+//!
+//! [`ToggleVirtualDisplay`](hbb_common::message_proto::ToggleVirtualDisplay) plugs in the monitors
+//! of the default mode only, so the client sends [`AddMonitor`] with a plugin request of the
+//! reserved id [`REQUEST_ID`], and the host plugs in a monitor of the first free index with the
+//! mode. It's removed the same as the other virtual displays.
+use hbb_common::message_proto::Message;
+use serde_derive::{Deserialize, Serialize};
+
+pub const REQUEST_ID: &str = crate::common::plugin_request_id::VIRTUAL_MONITOR;
+pub const DEFAULT_REFRESH_RATE: u32 = 60;
+// The limits of the modes of the virtual display driver.
+const MIN_WIDTH: u32 = 640;
+const MIN_HEIGHT: u32 = 480;
+const MAX_WIDTH: u32 = 7680;
+const MAX_HEIGHT: u32 = 4320;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AddMonitor {
+    pub width: u32,
+    pub height: u32,
+    pub refresh_rate: u32,
+}
+
+impl AddMonitor {
+    pub fn to_message(&self) -> Message {
+        crate::common::make_plugin_request(REQUEST_ID, serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    pub fn is_valid(&self) -> bool {
+        (MIN_WIDTH..=MAX_WIDTH).contains(&self.width)
+            && (MIN_HEIGHT..=MAX_HEIGHT).contains(&self.height)
+            && (24..=240).contains(&self.refresh_rate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_valid() {
+        let monitor = AddMonitor {
+            width: 2560,
+            height: 1440,
+            refresh_rate: DEFAULT_REFRESH_RATE,
+        };
+        assert!(monitor.is_valid());
+        assert!(!AddMonitor {
+            width: 10000,
+            ..monitor
+        }
+        .is_valid());
+        assert!(!AddMonitor {
+            refresh_rate: 0,
+            ..monitor
+        }
+        .is_valid());
+    }
+}
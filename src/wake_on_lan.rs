@@ -0,0 +1,115 @@
+//! Wake-on-LAN of a sleeping peer through another online peer on the same LAN.
+//!
+//! The magic packet is a broadcast, so it's sent only on the LAN of the sender.
+//! After the login, the host sends its MAC address and subnet with a plugin request
+//! of the reserved id [`REQUEST_ID`], the client saves them to the config of the peer.
+//! To wake a peer, the client asks the peers of its sessions, connected directly or via the relay,
+//! which were last seen in the same subnet, to send the magic packet on their LAN.
+use hbb_common::{config::PeerConfig, log, message_proto::Message};
+use serde_derive::{Deserialize, Serialize};
+use std::net::Ipv4Addr;
+
+pub const REQUEST_ID: &str = crate::common::plugin_request_id::WAKE_ON_LAN;
+const OPTION_MAC: &str = "wol-mac";
+const OPTION_SUBNET: &str = "wol-subnet";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "t", content = "c")]
+pub enum WakeOnLan {
+    /// The MAC address and the subnet of the host, e.g. "192.168.1.0/24".
+    Info { mac: String, subnet: String },
+    /// Ask the host to send the magic packet of `mac` on its LAN.
+    Wake { mac: String },
+}
+
+impl WakeOnLan {
+    pub fn to_message(&self) -> Message {
+        crate::common::make_plugin_request(REQUEST_ID, serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    /// The info of the interface of the default route.
+    pub fn local_info() -> Option<Self> {
+        let interface = default_net::get_default_interface().ok()?;
+        let mac = interface.mac_addr?.address();
+        let ipv4 = interface.ipv4.first()?;
+        Some(Self::Info {
+            mac,
+            subnet: subnet(ipv4.addr, ipv4.netmask),
+        })
+    }
+}
+
+fn subnet(addr: Ipv4Addr, netmask: Ipv4Addr) -> String {
+    let network = Ipv4Addr::from(u32::from(addr) & u32::from(netmask));
+    format!("{}/{}", network, u32::from(netmask).count_ones())
+}
+
+/// Save the info of the host to the config of the peer.
+pub fn save_info(lc: &mut crate::client::LoginConfigHandler, mac: String, subnet: String) {
+    lc.set_option(OPTION_MAC.to_owned(), mac);
+    lc.set_option(OPTION_SUBNET.to_owned(), subnet);
+}
+
+/// Send the magic packet of the peer `id` via the sessions to the other peers last seen
+/// in its subnet, returns the number of the peers asked.
+pub fn wake_via_peers(id: &str) -> usize {
+    let config = PeerConfig::load(id);
+    let (Some(mac), Some(subnet)) = (
+        config.options.get(OPTION_MAC),
+        config.options.get(OPTION_SUBNET),
+    ) else {
+        log::info!("The LAN of {} is unknown", id);
+        return 0;
+    };
+    let msg = WakeOnLan::Wake { mac: mac.clone() }.to_message();
+    send_to_peers_in_subnet(id, subnet, msg)
+}
+
+#[cfg(any(target_os = "android", target_os = "ios", feature = "flutter"))]
+fn send_to_peers_in_subnet(id: &str, subnet: &str, msg: Message) -> usize {
+    use crate::client::{Data, Interface};
+
+    let mut peers = std::collections::HashSet::new();
+    for session in crate::flutter::sessions::get_sessions() {
+        let peer_id = session.get_id();
+        if peer_id == id || peers.contains(&peer_id) {
+            continue;
+        }
+        let lc = session.lc.read().unwrap();
+        if lc.get_option(OPTION_SUBNET) != subnet
+            || !crate::common::is_support_wake_on_lan_num(lc.version)
+        {
+            continue;
+        }
+        drop(lc);
+        log::info!("Wake {} via {}", id, peer_id);
+        session.send(Data::Message(msg.clone()));
+        peers.insert(peer_id);
+    }
+    peers.len()
+}
+
+#[cfg(not(any(target_os = "android", target_os = "ios", feature = "flutter")))]
+fn send_to_peers_in_subnet(_id: &str, _subnet: &str, _msg: Message) -> usize {
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subnet() {
+        assert_eq!(
+            subnet(
+                Ipv4Addr::new(192, 168, 1, 23),
+                Ipv4Addr::new(255, 255, 255, 0)
+            ),
+            "192.168.1.0/24"
+        );
+        assert_eq!(
+            subnet(Ipv4Addr::new(10, 1, 2, 3), Ipv4Addr::new(255, 255, 0, 0)),
+            "10.1.0.0/16"
+        );
+    }
+}
@@ -39,6 +39,11 @@ pub fn get_key_cursor(conn_id: i32) -> String {
     format!("{}-cursor", conn_id)
 }
 
+#[inline]
+pub fn get_key_annotation(conn_id: i32) -> String {
+    format!("{}-annotation", conn_id)
+}
+
 pub fn register_whiteboard(k: String) {
     std::thread::spawn(|| {
         allow_err!(start_whiteboard_());
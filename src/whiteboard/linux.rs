@@ -1,7 +1,7 @@
 use super::{
     server::{Ripple, EVENT_PROXY},
-    win_linux::{create_font_face, draw_text},
-    Cursor, CustomEvent,
+    win_linux::{create_font_face, draw_annotations, draw_text},
+    AnnotationLayer, Cursor, CustomEvent,
 };
 use hbb_common::{bail, log, tokio::sync::mpsc::unbounded_channel, ResultType};
 use softbuffer::{Context, Surface};
@@ -137,6 +137,7 @@ struct WindowState {
     surface: Surface<DisplayHandle<'static>, Arc<Window>>,
     ripples: Vec<Ripple>,
     last_cursors: HashMap<String, Cursor>,
+    annotations: AnnotationLayer,
 }
 
 struct WhiteboardApplication {
@@ -198,7 +199,13 @@ impl ApplicationHandler<(String, CustomEvent)> for WhiteboardApplication {
             CustomEvent::Exit => {
                 self.close_requested = true;
             }
-            _ => {}
+            evt => {
+                if let Some(state) = self.windows.first_mut() {
+                    if state.annotations.handle(&k, &evt) {
+                        state.window.request_redraw();
+                    }
+                }
+            }
         }
     }
 
@@ -305,6 +312,7 @@ impl ApplicationHandler<(String, CustomEvent)> for WhiteboardApplication {
             surface,
             ripples: Vec::new(),
             last_cursors: HashMap::new(),
+            annotations: AnnotationLayer::default(),
         };
 
         self.windows.push(state);
@@ -379,6 +387,7 @@ impl WindowState {
             bail!("Failed to create pixmap from buffer");
         };
         pixmap.fill(Color::TRANSPARENT);
+        draw_annotations(&mut pixmap, &self.annotations);
 
         Ripple::retain_active(&mut self.ripples);
         for ripple in &self.ripples {
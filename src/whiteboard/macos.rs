@@ -1,4 +1,4 @@
-use super::{server::EVENT_PROXY, Cursor, CustomEvent, Ripple};
+use super::{server::EVENT_PROXY, AnnotationLayer, Cursor, CustomEvent, Ripple};
 use core_graphics::context::CGContextRef;
 use foreign_types::ForeignTypeRef;
 use hbb_common::{bail, log, ResultType};
@@ -115,6 +115,7 @@ fn draw_cursors(
     window_ripples: &mut HashMap<WindowId, Vec<Ripple>>,
     last_cursors: &HashMap<String, CursorInfo>,
     map_cursor_text: &mut HashMap<(String, u32), CoreGraphicsTextLayout>,
+    annotations: &AnnotationLayer,
 ) {
     for window in windows.iter() {
         if window.window.id() != window_id {
@@ -140,6 +141,27 @@ fn draw_cursors(
                             );
                             context.clear(None, piet::Color::TRANSPARENT);
 
+                            for annotation in annotations.iter() {
+                                let annotation = annotation.translate(
+                                    -window.display_origin.0 as f32,
+                                    -window.display_origin.1 as f32,
+                                );
+                                let rgba = super::argb_to_rgba(annotation.argb);
+                                let color = piet::Color::rgba8(rgba.0, rgba.1, rgba.2, rgba.3);
+                                for line in annotation.polylines() {
+                                    let mut pb = BezPath::new();
+                                    for (i, p) in line.iter().enumerate() {
+                                        let p = (p.0 as f64, p.1 as f64);
+                                        if i == 0 {
+                                            pb.move_to(p);
+                                        } else {
+                                            pb.line_to(p);
+                                        }
+                                    }
+                                    context.stroke(pb, &color, annotation.width.max(1.0) as f64);
+                                }
+                            }
+
                             if let Some(ripples) = window_ripples.get_mut(&window_id) {
                                 Ripple::retain_active(ripples);
                                 for ripple in ripples.iter() {
@@ -234,6 +256,7 @@ pub(super) fn create_event_loop() -> ResultType<()> {
     let mut window_ripples: HashMap<WindowId, Vec<Ripple>> = HashMap::new();
     let mut last_cursors: HashMap<String, CursorInfo> = HashMap::new();
     let mut map_cursor_text: HashMap<(String, u32), CoreGraphicsTextLayout> = HashMap::new();
+    let mut annotations = AnnotationLayer::default();
 
     event_loop.run(move |event, _, control_flow| {
         *control_flow = ControlFlow::Poll;
@@ -259,6 +282,7 @@ pub(super) fn create_event_loop() -> ResultType<()> {
                     &mut window_ripples,
                     &last_cursors,
                     &mut map_cursor_text,
+                    &annotations,
                 );
             }
             Event::MainEventsCleared => {
@@ -315,7 +339,13 @@ pub(super) fn create_event_loop() -> ResultType<()> {
                 CustomEvent::Exit => {
                     *control_flow = ControlFlow::Exit;
                 }
-                _ => {}
+                evt => {
+                    if annotations.handle(&k, &evt) {
+                        for window in windows.iter() {
+                            window.window.request_redraw();
+                        }
+                    }
+                }
             },
             _ => (),
         }
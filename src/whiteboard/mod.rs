@@ -1,4 +1,6 @@
+use crate::annotation::{Annotation, MAX_ANNOTATIONS};
 use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 mod client;
 mod server;
@@ -26,6 +28,10 @@ pub use server::*;
 #[serde(tag = "t", content = "c")]
 pub enum CustomEvent {
     Cursor(Cursor),
+    /// Add the annotation of the key, see [`crate::annotation`].
+    Annotate(Annotation),
+    /// Remove the last annotation of the key.
+    UndoAnnotation,
     Clear,
     Exit,
 }
@@ -39,3 +45,49 @@ pub struct Cursor {
     pub btns: i32,
     pub text: String,
 }
+
+/// The annotations of the keys, in the coordinates of the events.
+#[derive(Default)]
+pub(super) struct AnnotationLayer(HashMap<String, Vec<Annotation>>);
+
+impl AnnotationLayer {
+    /// Apply the event to the annotations of the key, returns false if it's not about them.
+    pub fn handle(&mut self, k: &str, evt: &CustomEvent) -> bool {
+        match evt {
+            CustomEvent::Annotate(annotation) => {
+                let annotations = self.0.entry(k.to_owned()).or_default();
+                annotations.push(annotation.clone());
+                if annotations.len() > MAX_ANNOTATIONS {
+                    annotations.remove(0);
+                }
+            }
+            CustomEvent::UndoAnnotation => {
+                if let Some(annotations) = self.0.get_mut(k) {
+                    annotations.pop();
+                }
+            }
+            CustomEvent::Clear => {
+                self.0.remove(k);
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Annotation> {
+        self.0.values().flatten()
+    }
+}
+
+/// Why the whiteboard can't be shown on this machine, empty if it can.
+pub fn unsupported_reason() -> &'static str {
+    #[cfg(target_os = "windows")]
+    if !crate::platform::windows::is_win_10_or_greater() {
+        return "Windows 10 or greater is required.";
+    }
+    #[cfg(target_os = "linux")]
+    if !is_supported() {
+        return "This feature is not supported on native Wayland, please install XWayland or switch to X11.";
+    }
+    ""
+}
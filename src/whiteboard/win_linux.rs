@@ -1,5 +1,8 @@
+use super::AnnotationLayer;
 use hbb_common::{bail, ResultType};
-use tiny_skia::{FillRule, Paint, PathBuilder, PixmapMut, Point, Rect, Transform};
+use tiny_skia::{
+    FillRule, LineCap, LineJoin, Paint, PathBuilder, PixmapMut, Point, Rect, Stroke, Transform,
+};
 use ttf_parser::Face;
 // A helper struct to bridge `ttf-parser` and `tiny-skia`.
 struct PathBuilderWrapper<'a> {
@@ -154,6 +157,37 @@ pub(super) fn draw_text(
     }
 }
 
+// Strokes the annotations onto the pixmap.
+pub(super) fn draw_annotations(pixmap: &mut PixmapMut, annotations: &AnnotationLayer) {
+    for annotation in annotations.iter() {
+        let rgba = super::argb_to_rgba(annotation.argb);
+        let mut paint = Paint::default();
+        // Note: The real color is bgra here.
+        paint.set_color_rgba8(rgba.2, rgba.1, rgba.0, rgba.3);
+        paint.anti_alias = true;
+        let stroke = Stroke {
+            width: annotation.width.max(1.0),
+            line_cap: LineCap::Round,
+            line_join: LineJoin::Round,
+            ..Default::default()
+        };
+        for line in annotation.polylines() {
+            let mut points = line.iter();
+            let Some(first) = points.next() else {
+                continue;
+            };
+            let mut pb = PathBuilder::new();
+            pb.move_to(first.0, first.1);
+            for p in points {
+                pb.line_to(p.0, p.1);
+            }
+            if let Some(path) = pb.finish() {
+                pixmap.stroke_path(&path, &paint, &stroke, Transform::identity(), None);
+            }
+        }
+    }
+}
+
 pub(super) fn create_font_face() -> ResultType<Face<'static>> {
     let mut font_db = fontdb::Database::new();
     font_db.load_system_fonts();
@@ -1,7 +1,7 @@
 use super::{
     server::{Ripple, EVENT_PROXY},
-    win_linux::{create_font_face, draw_text},
-    Cursor, CustomEvent,
+    win_linux::{create_font_face, draw_annotations, draw_text},
+    AnnotationLayer, Cursor, CustomEvent,
 };
 use hbb_common::{anyhow::anyhow, log, ResultType};
 use softbuffer::{Context, Surface};
@@ -71,6 +71,7 @@ pub(super) fn create_event_loop() -> ResultType<()> {
 
     let mut ripples: Vec<Ripple> = Vec::new();
     let mut last_cursors: HashMap<String, Cursor> = HashMap::new();
+    let mut annotations = AnnotationLayer::default();
     let mut resized = final_size.is_none();
 
     event_loop.run(move |event, _, control_flow| {
@@ -122,6 +123,7 @@ pub(super) fn create_event_loop() -> ResultType<()> {
                     return;
                 };
                 pixmap.fill(Color::TRANSPARENT);
+                draw_annotations(&mut pixmap, &annotations);
 
                 Ripple::retain_active(&mut ripples);
                 for ripple in &ripples {
@@ -222,7 +224,9 @@ pub(super) fn create_event_loop() -> ResultType<()> {
                 CustomEvent::Exit => {
                     *control_flow = ControlFlow::Exit;
                 }
-                _ => {}
+                evt => {
+                    annotations.handle(&k, &evt);
+                }
             },
             _ => (),
         }
@@ -0,0 +1,139 @@
+//! Explicit selection of the Windows session to capture and control, the physical console,
+//! an RDP session, or the login screen of a session without the user logged on.
+//!
+//! The sessions are only sent in the [`PeerInfo`](hbb_common::message_proto::PeerInfo) of the
+//! login when the host has more than one, so the client asks for them at any time with
+//! [`WindowsSessions::List`] of the reserved id [`REQUEST_ID`], and the host replies with
+//! [`WindowsSessions::Sessions`]. The selection is sent with `SelectedSid` as in the login, the
+//! service restarts the server in the selected session with the system privileges, which also
+//! captures the secure desktop, and the client reconnects to it.
+use hbb_common::message_proto::{Message, WindowsSession};
+use serde_derive::{Deserialize, Serialize};
+
+pub const REQUEST_ID: &str = crate::common::plugin_request_id::WINDOWS_SESSIONS;
+const STATION_CONSOLE: &str = "Console";
+const STATION_RDP_PREFIX: &str = "RDP-Tcp";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+    Console,
+    Rdp,
+    Other,
+}
+
+impl Kind {
+    pub fn of_station(station: &str) -> Self {
+        if station.eq_ignore_ascii_case(STATION_CONSOLE) {
+            Kind::Console
+        } else if station.starts_with(STATION_RDP_PREFIX) {
+            Kind::Rdp
+        } else {
+            Kind::Other
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    pub sid: u32,
+    /// The window station, e.g. `Console` or `RDP-Tcp#0`.
+    pub station: String,
+    pub kind: Kind,
+    pub user: String,
+}
+
+impl Session {
+    /// No user is logged on, the login screen is captured.
+    #[inline]
+    pub fn is_login_screen(&self) -> bool {
+        self.user.is_empty()
+    }
+
+    pub fn display_name(&self) -> String {
+        if self.is_login_screen() {
+            format!("{}: Login screen", self.station)
+        } else {
+            format!("{}: {}", self.station, self.user)
+        }
+    }
+
+    /// For the session selection dialog of the client.
+    pub fn to_proto(&self, current_sid: u32) -> WindowsSession {
+        let mut name = format!("{} (sid = {})", self.display_name(), self.sid);
+        if self.sid == current_sid {
+            name += " (running)";
+        }
+        WindowsSession {
+            sid: self.sid,
+            name,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "t", content = "c")]
+pub enum WindowsSessions {
+    /// From the client.
+    List,
+    /// From the host, `switchable` is false if the host doesn't allow to switch the session now.
+    Sessions {
+        sessions: Vec<Session>,
+        current_sid: u32,
+        switchable: bool,
+    },
+}
+
+impl WindowsSessions {
+    pub fn to_message(&self) -> Message {
+        crate::common::make_plugin_request(REQUEST_ID, serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    pub fn from_message(msg: &Message) -> Option<Self> {
+        serde_json::from_slice(crate::common::get_plugin_request(msg, REQUEST_ID)?).ok()
+    }
+}
+
+/// The sessions of the host, the physical console first.
+#[cfg(windows)]
+pub fn list() -> Vec<Session> {
+    crate::platform::windows::get_session_stations()
+        .into_iter()
+        .map(|(sid, station)| Session {
+            sid,
+            kind: Kind::of_station(&station),
+            user: crate::platform::windows::get_session_username(sid),
+            station,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session() {
+        assert_eq!(Kind::of_station("Console"), Kind::Console);
+        assert_eq!(Kind::of_station("RDP-Tcp#3"), Kind::Rdp);
+        assert_eq!(Kind::of_station("Services"), Kind::Other);
+        let session = Session {
+            sid: 2,
+            station: "RDP-Tcp#0".to_owned(),
+            kind: Kind::Rdp,
+            user: "".to_owned(),
+        };
+        assert!(session.is_login_screen());
+        assert_eq!(
+            session.to_proto(2).name,
+            "RDP-Tcp#0: Login screen (sid = 2) (running)"
+        );
+        let msg = WindowsSessions::Sessions {
+            sessions: vec![session],
+            current_sid: 1,
+            switchable: true,
+        };
+        assert_eq!(WindowsSessions::from_message(&msg.to_message()), Some(msg));
+    }
+}